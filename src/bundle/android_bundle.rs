@@ -0,0 +1,519 @@
+// An Android APK is a zip archive built with the Android SDK's `aapt` tool from
+// a generated manifest, icons and assets, to which the app's native library is
+// added:
+//
+// bundle/android/foobar/
+//     AndroidManifest.xml     # Declares a NativeActivity that loads libfoobar.so
+//     res/mipmap-*/ic_launcher.png    # Launcher icons generated from the bundle icon
+//     assets/...              # Resource files, at their relative paths
+//     lib/arm64-v8a/libfoobar.so      # The cdylib built for an Android target
+//
+// The package is written to `bundle/android/foobar-1.2.3.apk`, after being
+// aligned with `zipalign` and signed with `apksigner`.  Apps built this way
+// have no Java code: they are expected to use `android.app.NativeActivity`,
+// e.g. through the `android-activity` crate.
+
+use super::common;
+use crate::Settings;
+use anyhow::Context;
+use image::GenericImageView;
+use image::imageops::FilterType::Lanczos3;
+use quick_xml::escape::escape;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The launcher icon densities, with their sizes in pixels.
+const ICON_DENSITIES: &[(&str, u32)] = &[
+    ("mdpi", 48),
+    ("hdpi", 72),
+    ("xhdpi", 96),
+    ("xxhdpi", 144),
+    ("xxxhdpi", 192),
+];
+
+/// The environment variable holding the password of the `android_keystore`.
+const KEYSTORE_PASSWORD_VAR: &str = "CARGO_BUNDLE_ANDROID_KEYSTORE_PASSWORD";
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    if !settings
+        .target_triple()
+        .is_some_and(|triple| triple.contains("android"))
+    {
+        anyhow::bail!(
+            "Android packages must be built for an Android target, e.g. \
+             `--target aarch64-linux-android`"
+        );
+    }
+    let abi = android_abi(settings.binary_arch())?;
+    let package = package_name(settings)?;
+    let file_name = settings.bundle_file_name();
+    let package_name = format!("{file_name}-{}.apk", settings.version_string());
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/android");
+    let package_dir = base_dir.join(&file_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {file_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+    let sdk = AndroidSdk::find(settings.android_target_sdk())?;
+
+    // Stage the package contents.
+    let lib_path = Path::new("lib").join(abi).join(settings.binary_name());
+    common::copy_file(settings.binary_path(), &package_dir.join(&lib_path))
+        .with_context(|| format!("Failed to copy library from {:?}", settings.binary_path()))?;
+    let assets_dir = package_dir.join("assets");
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let dest = assets_dir.join(&resource.dest);
+        common::copy_resource(&resource, &dest)?;
+    }
+    if let Some(build_info) = settings.build_info() {
+        build_info
+            .write_json(&assets_dir)
+            .with_context(|| "Failed to write build info")?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        sbom.write_json(&assets_dir)
+            .with_context(|| "Failed to write SBOM")?;
+    }
+    let res_dir = package_dir.join("res");
+    let has_icon =
+        create_icons(settings, &res_dir).with_context(|| "Failed to create launcher icons")?;
+    let manifest = generate_manifest(settings, &package, sdk.api_level, has_icon)?;
+    let manifest_path = package_dir.join("AndroidManifest.xml");
+    fs::write(&manifest_path, manifest)
+        .with_context(|| format!("Failed to write {manifest_path:?}"))?;
+
+    // Package the resources, then add the library, which `aapt` must be given
+    // as a path relative to the APK's root.
+    let unaligned_path = package_dir.join("unaligned.apk");
+    let mut aapt = Command::new(sdk.build_tool("aapt"));
+    aapt.arg("package")
+        .arg("-f")
+        .arg("-F")
+        .arg(&unaligned_path)
+        .arg("-M")
+        .arg(&manifest_path)
+        .arg("-I")
+        .arg(&sdk.android_jar);
+    if has_icon {
+        aapt.arg("-S").arg(&res_dir);
+    }
+    if assets_dir.exists() {
+        aapt.arg("-A").arg(&assets_dir);
+    }
+    common::run_tool(&mut aapt, "aapt")?;
+    common::run_tool(
+        Command::new(sdk.build_tool("aapt"))
+            .current_dir(&package_dir)
+            .arg("add")
+            .arg(&unaligned_path)
+            .arg(lib_path.to_string_lossy().replace('\\', "/")),
+        "aapt",
+    )?;
+    common::run_tool(
+        Command::new(sdk.build_tool("zipalign"))
+            .args(["-f", "4"])
+            .arg(&unaligned_path)
+            .arg(&package_path),
+        "zipalign",
+    )?;
+    sign_apk(settings, &sdk, &package_path)?;
+    common::record_signed(&package_path);
+    Ok(vec![package_path])
+}
+
+/// The parts of the Android SDK needed to build an APK.
+struct AndroidSdk {
+    build_tools_dir: PathBuf,
+    android_jar: PathBuf,
+    api_level: u32,
+}
+
+impl AndroidSdk {
+    /// Locates the SDK through `ANDROID_HOME` (or `ANDROID_SDK_ROOT`), using
+    /// its newest build tools and the platform for `api_level` (by default
+    /// the newest one installed).
+    fn find(api_level: Option<u32>) -> crate::Result<AndroidSdk> {
+        let Some(sdk_dir) = std::env::var_os("ANDROID_HOME")
+            .or_else(|| std::env::var_os("ANDROID_SDK_ROOT"))
+            .map(PathBuf::from)
+        else {
+            anyhow::bail!("Android packages require `ANDROID_HOME` to be set to the Android SDK");
+        };
+        let (_, build_tools_dir) =
+            newest_version_dir(&sdk_dir.join("build-tools"), version_components)?.ok_or_else(
+                || anyhow::anyhow!("No build tools are installed in the Android SDK"),
+            )?;
+        let platforms_dir = sdk_dir.join("platforms");
+        let api_level = match api_level {
+            Some(api_level) => api_level,
+            None => {
+                let (version, _) = newest_version_dir(&platforms_dir, platform_api_level)?
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("No platforms are installed in the Android SDK")
+                    })?;
+                version[0]
+            }
+        };
+        let android_jar = platforms_dir
+            .join(format!("android-{api_level}"))
+            .join("android.jar");
+        if !android_jar.exists() {
+            anyhow::bail!(
+                "The Android SDK platform for API level {api_level} is not installed (no {android_jar:?})"
+            );
+        }
+        Ok(AndroidSdk {
+            build_tools_dir,
+            android_jar,
+            api_level,
+        })
+    }
+
+    fn build_tool(&self, name: &str) -> PathBuf {
+        if cfg!(windows) {
+            let extension = if name == "apksigner" { "bat" } else { "exe" };
+            self.build_tools_dir.join(name).with_extension(extension)
+        } else {
+            self.build_tools_dir.join(name)
+        }
+    }
+}
+
+/// Returns the path of the Android SDK build tool `name` (e.g. `"aapt"`), or
+/// `None` if the SDK cannot be found.
+pub fn sdk_build_tool(name: &str) -> Option<PathBuf> {
+    AndroidSdk::find(None).ok().map(|sdk| sdk.build_tool(name))
+}
+
+/// Returns the subdirectory of `dir` whose name has the greatest version, as
+/// parsed by `version`, along with that version.
+fn newest_version_dir(
+    dir: &Path,
+    version: impl Fn(&str) -> Option<Vec<u32>>,
+) -> crate::Result<Option<(Vec<u32>, PathBuf)>> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let mut newest: Option<(Vec<u32>, PathBuf)> = None;
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {dir:?}"))? {
+        let path = entry?.path();
+        let Some(components) = version(&path.file_name().unwrap().to_string_lossy()) else {
+            continue;
+        };
+        if newest
+            .as_ref()
+            .is_none_or(|(newest, _)| components > *newest)
+        {
+            newest = Some((components, path));
+        }
+    }
+    Ok(newest)
+}
+
+/// Parses a build tools version (e.g. `"34.0.0"`).
+fn version_components(name: &str) -> Option<Vec<u32>> {
+    name.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Parses the API level of a platform directory name (e.g. `"android-34"`).
+fn platform_api_level(name: &str) -> Option<Vec<u32>> {
+    Some(vec![name.strip_prefix("android-")?.parse().ok()?])
+}
+
+/// Returns the Android ABI name for a Rust target arch.
+fn android_abi(binary_arch: &str) -> crate::Result<&'static str> {
+    match binary_arch {
+        "aarch64" => Ok("arm64-v8a"),
+        "arm" => Ok("armeabi-v7a"),
+        "x86" => Ok("x86"),
+        "x86_64" => Ok("x86_64"),
+        arch => anyhow::bail!("Android does not support the {arch} architecture"),
+    }
+}
+
+/// Returns the Android package name, which is the bundle identifier with any
+/// characters Java does not allow in package names replaced by underscores.
+fn package_name(settings: &Settings) -> crate::Result<String> {
+    let identifier = settings.bundle_identifier();
+    let segments: Vec<String> = identifier
+        .split('.')
+        .map(|segment| {
+            let mut segment: String = segment
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if segment.starts_with(|c: char| c.is_ascii_digit()) {
+                segment.insert(0, '_');
+            }
+            segment
+        })
+        .collect();
+    if segments.len() < 2 || segments.iter().any(String::is_empty) {
+        anyhow::bail!(
+            "Android packages require a bundle `identifier` of the form `com.example.app`"
+        );
+    }
+    Ok(segments.join("."))
+}
+
+/// Converts a version string to the integer `versionCode` Android compares
+/// versions with, e.g. `1.2.3` becomes `1002003`.  Pre-release and build
+/// metadata are dropped.
+fn version_code(version: &str) -> crate::Result<u32> {
+    let release = version.split(['-', '+']).next().unwrap_or_default();
+    let mut parts = release
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let (major, minor, patch) = (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    );
+    if major > 2099 || minor > 999 || patch > 999 {
+        anyhow::bail!("Version {version} cannot be converted to an Android versionCode");
+    }
+    Ok(major * 1_000_000 + minor * 1_000 + patch)
+}
+
+/// Generates the launcher icons from the largest PNG icon, and returns whether
+/// there was one.
+fn create_icons(settings: &Settings, res_dir: &Path) -> crate::Result<bool> {
+    let mut largest: Option<image::DynamicImage> = None;
+    for icon_path in settings.icon_files() {
+        let icon_path = icon_path?;
+        if icon_path.extension() != Some(OsStr::new("png")) {
+            continue;
+        }
+        let icon = image::open(&icon_path)?;
+        if largest
+            .as_ref()
+            .is_none_or(|largest| icon.width() > largest.width())
+        {
+            largest = Some(icon);
+        }
+    }
+    let Some(icon) = largest else {
+        return Ok(false);
+    };
+    for &(density, size) in ICON_DENSITIES {
+        let scaled = if icon.dimensions() == (size, size) {
+            icon.clone()
+        } else {
+            icon.resize_exact(size, size, Lanczos3)
+        };
+        let path = res_dir
+            .join(format!("mipmap-{density}"))
+            .join("ic_launcher.png");
+        let mut file = common::create_file(&path)?;
+        scaled.write_to(&mut file, image::ImageFormat::Png)?;
+    }
+    Ok(true)
+}
+
+/// Generates the contents of `AndroidManifest.xml`.
+fn generate_manifest(
+    settings: &Settings,
+    package: &str,
+    target_sdk: u32,
+    has_icon: bool,
+) -> crate::Result<String> {
+    let version = settings.version_string().to_string();
+    let label = escape(settings.bundle_name());
+    let lib_name = settings.binary_name();
+    let lib_name = lib_name.strip_prefix("lib").unwrap_or(lib_name);
+    let lib_name = lib_name.strip_suffix(".so").unwrap_or(lib_name);
+
+    let mut xml = String::new();
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"utf-8\"?>").unwrap();
+    writeln!(
+        xml,
+        "<manifest xmlns:android=\"http://schemas.android.com/apk/res/android\"\n    \
+         package=\"{package}\"\n    \
+         android:versionCode=\"{}\"\n    \
+         android:versionName=\"{}\">",
+        version_code(&version)?,
+        escape(&version)
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "    <uses-sdk android:minSdkVersion=\"{}\" android:targetSdkVersion=\"{target_sdk}\"/>",
+        settings.android_min_sdk()
+    )
+    .unwrap();
+    for permission in settings.android_permissions() {
+        writeln!(
+            xml,
+            "    <uses-permission android:name=\"{}\"/>",
+            escape(permission)
+        )
+        .unwrap();
+    }
+    write!(xml, "    <application android:label=\"{label}\"").unwrap();
+    if has_icon {
+        write!(xml, " android:icon=\"@mipmap/ic_launcher\"").unwrap();
+    }
+    writeln!(xml, " android:hasCode=\"false\">").unwrap();
+    writeln!(
+        xml,
+        "        <activity android:name=\"android.app.NativeActivity\"\n            \
+         android:label=\"{label}\"\n            \
+         android:exported=\"true\"\n            \
+         android:configChanges=\"orientation|keyboardHidden|screenSize|screenLayout|uiMode\">"
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "            <meta-data android:name=\"android.app.lib_name\" android:value=\"{}\"/>",
+        escape(lib_name)
+    )
+    .unwrap();
+    writeln!(xml, "            <intent-filter>").unwrap();
+    writeln!(
+        xml,
+        "                <action android:name=\"android.intent.action.MAIN\"/>"
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "                <category android:name=\"android.intent.category.LAUNCHER\"/>"
+    )
+    .unwrap();
+    writeln!(xml, "            </intent-filter>").unwrap();
+    writeln!(xml, "        </activity>").unwrap();
+    writeln!(xml, "    </application>").unwrap();
+    writeln!(xml, "</manifest>").unwrap();
+    Ok(xml)
+}
+
+/// Signs the APK with the `android_keystore`, or else with the debug keystore
+/// that Android Studio uses, creating it if need be.
+fn sign_apk(settings: &Settings, sdk: &AndroidSdk, apk_path: &Path) -> crate::Result<()> {
+    let mut apksigner = Command::new(sdk.build_tool("apksigner"));
+    apksigner.arg("sign").arg("--ks");
+    match settings.android_keystore() {
+        Some(keystore) => {
+            if std::env::var_os(KEYSTORE_PASSWORD_VAR).is_none() {
+                anyhow::bail!(
+                    "`{KEYSTORE_PASSWORD_VAR}` must be set to the password of the `android_keystore`"
+                );
+            }
+            apksigner
+                .arg(keystore)
+                .arg("--ks-pass")
+                .arg(format!("env:{KEYSTORE_PASSWORD_VAR}"));
+        }
+        None => {
+            common::print_warning(
+                "No `android_keystore` is set, so the APK is signed with the debug keystore and \
+                 cannot be published.",
+            )?;
+            let keystore = debug_keystore()?;
+            apksigner.arg(keystore).args(["--ks-pass", "pass:android"]);
+        }
+    }
+    apksigner.arg(apk_path);
+    common::run_tool(&mut apksigner, "apksigner")?;
+    Ok(())
+}
+
+/// Returns the path of the debug keystore, generating it with `keytool` if it
+/// does not exist yet.
+fn debug_keystore() -> crate::Result<PathBuf> {
+    let Some(home_dir) = dirs::home_dir() else {
+        anyhow::bail!("Failed to find the home directory for the debug keystore");
+    };
+    let keystore = home_dir.join(".android/debug.keystore");
+    if !keystore.exists() {
+        fs::create_dir_all(keystore.parent().unwrap())?;
+        common::run_tool(
+            Command::new("keytool")
+                .args(["-genkeypair", "-keystore"])
+                .arg(&keystore)
+                .args([
+                    "-storepass",
+                    "android",
+                    "-alias",
+                    "androiddebugkey",
+                    "-keypass",
+                    "android",
+                    "-dname",
+                    "CN=Android Debug,O=Android,C=US",
+                    "-keyalg",
+                    "RSA",
+                    "-keysize",
+                    "2048",
+                    "-validity",
+                    "10000",
+                ]),
+            "keytool",
+        )?;
+    }
+    Ok(keystore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abis() {
+        assert_eq!(android_abi("aarch64").unwrap(), "arm64-v8a");
+        assert_eq!(android_abi("arm").unwrap(), "armeabi-v7a");
+        assert!(android_abi("powerpc").is_err());
+    }
+
+    #[test]
+    fn package_names() {
+        let settings = Settings::for_test("identifier = \"com.example.my-app\"\n");
+        assert_eq!(package_name(&settings).unwrap(), "com.example.my_app");
+        let settings = Settings::for_test("identifier = \"com.3d.viewer\"\n");
+        assert_eq!(package_name(&settings).unwrap(), "com._3d.viewer");
+        let settings = Settings::for_test("identifier = \"example\"\n");
+        assert!(package_name(&settings).is_err());
+    }
+
+    #[test]
+    fn version_codes() {
+        assert_eq!(version_code("1.0.0").unwrap(), 1_000_000);
+        assert_eq!(version_code("1.2.3-beta.1").unwrap(), 1_002_003);
+        assert!(version_code("1.1000.0").is_err());
+    }
+
+    #[test]
+    fn sdk_versions() {
+        assert_eq!(version_components("34.0.0"), Some(vec![34, 0, 0]));
+        assert_eq!(version_components("34.0.0-rc1"), None);
+        assert_eq!(platform_api_level("android-34"), Some(vec![34]));
+        assert_eq!(platform_api_level("android-TiramisuPrivacySandbox"), None);
+    }
+
+    #[test]
+    fn manifest() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             android_permissions = [\"android.permission.INTERNET\"]\n",
+        );
+        let xml = generate_manifest(&settings, "com.example.app", 34, false).unwrap();
+        assert!(xml.contains("package=\"com.example.app\""));
+        assert!(xml.contains("android:versionCode=\"1000000\""));
+        assert!(xml.contains("android:versionName=\"1.0.0\""));
+        assert!(
+            xml.contains(
+                "<uses-sdk android:minSdkVersion=\"23\" android:targetSdkVersion=\"34\"/>"
+            )
+        );
+        assert!(xml.contains("<uses-permission android:name=\"android.permission.INTERNET\"/>"));
+        assert!(xml.contains("<application android:label=\"My App\" android:hasCode=\"false\">"));
+        assert!(xml.contains(
+            "<meta-data android:name=\"android.app.lib_name\" android:value=\"example\"/>"
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+// A Mac App Store package is an installer package, built by `productbuild`
+// from an app bundle signed for the store, that Transporter uploads:
+//
+// bundle/appstore/
+//     foobar.app                  # Signed with `appstore_signing_identity`
+//         Contents
+//             embedded.provisionprofile
+//             ...                 # As for the `osx` bundler
+//     foobar.entitlements         # The entitlements the app is signed with
+//     foobar_1.2.3_x86_64.pkg     # Signed with `appstore_installer_identity`
+//
+// Unlike apps distributed outside the store, the app must be sandboxed, embeds
+// its provisioning profile, and is neither given the hardened runtime nor
+// notarized (Apple signs it again once it is approved).
+
+use super::common;
+use super::ios_bundle::decode_provisioning_profile;
+use super::osx_bundle::{self, Entitlements};
+use crate::Settings;
+use anyhow::Context;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The entitlement that sandboxes the app, which the Mac App Store requires.
+const SANDBOX_ENTITLEMENT: &str = "com.apple.security.app-sandbox";
+
+/// The entitlements identifying the app and its team, which must match those
+/// granted by the provisioning profile.
+const PROFILE_ENTITLEMENTS: [&str; 2] = [
+    "com.apple.application-identifier",
+    "com.apple.developer.team-identifier",
+];
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    if settings.bundle_identifier().is_empty() {
+        anyhow::bail!("Mac App Store packages require a bundle `identifier`");
+    }
+    let (Some(identity), Some(installer_identity)) = (
+        settings.appstore_signing_identity(),
+        settings.appstore_installer_identity(),
+    ) else {
+        anyhow::bail!(
+            "Mac App Store packages require `appstore_signing_identity` and \
+             `appstore_installer_identity` to be set"
+        );
+    };
+    let out_dir = settings.project_out_directory().join("bundle/appstore");
+    let app_bundle_path = osx_bundle::build_unsigned_app_bundle(settings, &out_dir)?;
+
+    let profile = match settings.appstore_provisioning_profile() {
+        Some(profile) => {
+            common::copy_file(
+                &profile,
+                &app_bundle_path.join("Contents/embedded.provisionprofile"),
+            )
+            .with_context(|| format!("Failed to copy provisioning profile {profile:?}"))?;
+            Some(decode_provisioning_profile(&profile)?)
+        }
+        None => None,
+    };
+    let entitlements = match osx_bundle::entitlements(settings)? {
+        Some(Entitlements::File(path)) => path,
+        keys => {
+            let keys = match keys {
+                Some(Entitlements::Keys(keys)) => keys,
+                _ => toml::Table::new(),
+            };
+            let path = app_bundle_path.with_extension("entitlements");
+            let entitlements = appstore_entitlements(keys, profile.as_deref());
+            fs::write(&path, osx_bundle::entitlements_plist(&entitlements)?)
+                .with_context(|| format!("Failed to write {path:?}"))?;
+            path
+        }
+    };
+
+    let app_bundle_name = app_bundle_path.file_name().unwrap().to_string_lossy();
+    common::print_signing(&app_bundle_name)?;
+    osx_bundle::sign_app_bundle(
+        &app_bundle_path,
+        identity,
+        Some(&entitlements),
+        false,
+        settings,
+    )
+    .with_context(|| format!("Failed to sign {app_bundle_name}"))?;
+    common::record_signed(&app_bundle_path);
+
+    let package_name = format!(
+        "{}_{}_{}.pkg",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    common::print_bundling(&package_name)?;
+    let package_path = out_dir.join(&package_name);
+    common::run_signing_tool(
+        settings,
+        &mut productbuild_command(&app_bundle_path, installer_identity, &package_path),
+        "productbuild",
+    )?;
+    common::record_signed(&package_path);
+    Ok(vec![package_path])
+}
+
+/// Returns the entitlements to sign the app with: those in `keys`, with the
+/// app sandbox enabled and the identifiers granted by the decoded provisioning
+/// `profile` added.
+fn appstore_entitlements(mut keys: toml::Table, profile: Option<&str>) -> toml::Table {
+    keys.insert(SANDBOX_ENTITLEMENT.to_string(), toml::Value::Boolean(true));
+    for key in PROFILE_ENTITLEMENTS {
+        if let Some(value) = profile.and_then(|profile| profile_string(profile, key)) {
+            keys.entry(key).or_insert(toml::Value::String(value));
+        }
+    }
+    keys
+}
+
+/// Returns the string value of `key` in a decoded provisioning profile.
+fn profile_string(profile: &str, key: &str) -> Option<String> {
+    let key = format!("<key>{key}</key>");
+    let rest = &profile[profile.find(&key)? + key.len()..];
+    let rest = rest.trim_start().strip_prefix("<string>")?;
+    Some(rest[..rest.find("</string>")?].to_string())
+}
+
+/// Builds the `productbuild` command that packages the signed app for
+/// installation into `/Applications`, signed with the installer identity.
+fn productbuild_command(app_bundle_path: &Path, identity: &str, package_path: &Path) -> Command {
+    let mut command = Command::new("productbuild");
+    command
+        .arg("--component")
+        .arg(app_bundle_path)
+        .arg("/Applications")
+        .arg("--sign")
+        .arg(identity)
+        .arg(package_path);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn entitlements_from_profile() {
+        let profile = "<dict>\n\
+             <key>Entitlements</key>\n\
+             <dict>\n\
+               <key>com.apple.application-identifier</key>\n\
+               <string>TEAMID.com.example.App</string>\n\
+               <key>com.apple.developer.team-identifier</key>\n\
+               <string>TEAMID</string>\n\
+             </dict>\n\
+             </dict>\n";
+        let mut keys = toml::Table::new();
+        keys.insert(
+            "com.apple.security.network.client".to_string(),
+            toml::Value::Boolean(true),
+        );
+        let entitlements = appstore_entitlements(keys, Some(profile));
+        assert_eq!(entitlements[SANDBOX_ENTITLEMENT].as_bool(), Some(true));
+        assert_eq!(
+            entitlements["com.apple.security.network.client"].as_bool(),
+            Some(true)
+        );
+        assert_eq!(
+            entitlements["com.apple.application-identifier"].as_str(),
+            Some("TEAMID.com.example.App")
+        );
+        assert_eq!(
+            entitlements["com.apple.developer.team-identifier"].as_str(),
+            Some("TEAMID")
+        );
+
+        let mut keys = toml::Table::new();
+        keys.insert(SANDBOX_ENTITLEMENT.to_string(), toml::Value::Boolean(false));
+        let entitlements = appstore_entitlements(keys, None);
+        assert_eq!(entitlements.len(), 1);
+        assert_eq!(entitlements[SANDBOX_ENTITLEMENT].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn productbuild_command_args() {
+        let command = productbuild_command(
+            Path::new("example.app"),
+            "3rd Party Mac Developer Installer: Example Inc. (TEAMID)",
+            Path::new("example.pkg"),
+        );
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "--component",
+                "example.app",
+                "/Applications",
+                "--sign",
+                "3rd Party Mac Developer Installer: Example Inc. (TEAMID)",
+                "example.pkg",
+            ]
+        );
+    }
+}
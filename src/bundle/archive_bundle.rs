@@ -0,0 +1,254 @@
+// A plain archive holds a portable build of the app, which runs from wherever
+// it is unpacked.  Archives for Windows targets are zip files, and archives for
+// other targets are gzipped tarballs:
+//
+// foobar-1.2.3-x86_64-unknown-linux-gnu.tar.gz
+//     foobar-1.2.3-x86_64-unknown-linux-gnu/
+//         foobar              # Binary executable file
+//         ...                 # External binaries, if any
+//         LICENSE             # The package's license file, if it has one
+//         build-info.json     # Build information, if enabled
+//         completions/        # The `shell_completions`, if any:
+//             foobar.bash         # for bash,
+//             _foobar             # zsh,
+//             foobar.fish         # and fish
+//         ...                 # Resource files, at their relative paths
+//
+// Entries are owned by root and share the build time as their modification
+// time, so that archives are reproducible.
+
+use super::build_info::build_time;
+use super::common;
+use super::linux::common::file_mode;
+use super::settings::Shell;
+use super::shared_libs;
+use super::zip::ZipWriter;
+use crate::Settings;
+use anyhow::Context;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The name the package's license file is given in the archive.
+const LICENSE_FILE_NAME: &str = "LICENSE";
+
+/// A file to put in the archive, under the top-level directory.
+struct ArchiveFile {
+    name: String,
+    source: FileSource,
+    /// The file's Unix permissions.
+    mode: u32,
+}
+
+enum FileSource {
+    Path(PathBuf),
+    Contents(String),
+}
+
+impl FileSource {
+    fn read(&self) -> crate::Result<Vec<u8>> {
+        match self {
+            FileSource::Path(path) => {
+                fs::read(path).with_context(|| format!("Failed to read {path:?}"))
+            }
+            FileSource::Contents(contents) => Ok(contents.clone().into_bytes()),
+        }
+    }
+}
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let target = target_triple(settings)?;
+    let dir_name = archive_dir_name(settings, &target);
+    let extension = if is_windows(&target) { "zip" } else { "tar.gz" };
+    let package_name = format!("{dir_name}.{extension}");
+    common::print_bundling(&package_name)?;
+    let package_path = settings
+        .project_out_directory()
+        .join("bundle/archive")
+        .join(&package_name);
+    let mtime = build_time()?;
+
+    let files = collect_files(settings)?;
+    let writer = common::create_file(&package_path)?;
+    if is_windows(&target) {
+        let mut zip = ZipWriter::new(writer, mtime);
+        zip.add_directory(&dir_name)?;
+        for file in &files {
+            let name = format!("{dir_name}/{}", file.name);
+            zip.add_file(&name, &file.source.read()?, file.mode & 0o111 != 0)?;
+        }
+        zip.finish()?;
+    } else {
+        let mut tar_builder = tar::Builder::new(common::gzip_encoder(writer)?);
+        let mut header = tar_header(0o755, mtime.timestamp());
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        tar_builder.append_data(&mut header, &dir_name, io::empty())?;
+        for file in &files {
+            let mut header = tar_header(file.mode, mtime.timestamp());
+            let path = Path::new(&dir_name).join(&file.name);
+            match &file.source {
+                FileSource::Path(src) => {
+                    header.set_size(fs::metadata(src)?.len());
+                    let src_file =
+                        File::open(src).with_context(|| format!("Failed to open {src:?}"))?;
+                    tar_builder.append_data(&mut header, path, src_file)?;
+                }
+                FileSource::Contents(contents) => {
+                    header.set_size(contents.len() as u64);
+                    tar_builder.append_data(&mut header, path, contents.as_bytes())?;
+                }
+            }
+        }
+        tar_builder.into_inner()?.finish().into_result()?.flush()?;
+    }
+    Ok(vec![package_path])
+}
+
+/// Returns the triple of the target being bundled for.
+fn target_triple(settings: &Settings) -> crate::Result<String> {
+    match settings.target_triple() {
+        Some(triple) => Ok(triple.to_string()),
+        None => super::host_triple(),
+    }
+}
+
+fn is_windows(target: &str) -> bool {
+    target.contains("windows")
+}
+
+/// Returns the name of the archive's top-level directory, which is also the
+/// default name of the archive itself.
+fn archive_dir_name(settings: &Settings, target: &str) -> String {
+    format!(
+        "{}-{}-{target}",
+        settings.binary_name(),
+        settings.version_string()
+    )
+}
+
+/// Returns the name of the top-level directory of the archive bundled for
+/// `settings`.
+pub fn top_level_dir(settings: &Settings) -> crate::Result<String> {
+    Ok(archive_dir_name(settings, &target_triple(settings)?))
+}
+
+fn tar_header(mode: u32, mtime: i64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(mode);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(mtime as u64);
+    header
+}
+
+/// Lists the files to archive: the binary, the external binaries, the license
+/// file, the build information, the shell completions and the resource files.
+fn collect_files(settings: &Settings) -> crate::Result<Vec<ArchiveFile>> {
+    let binary_path = settings.binary_path();
+    let mut files = vec![ArchiveFile {
+        name: binary_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned(),
+        source: FileSource::Path(binary_path.to_path_buf()),
+        mode: 0o755,
+    }];
+    for binary in shared_libs::bundled_executables(settings)? {
+        files.push(ArchiveFile {
+            name: binary.name,
+            source: FileSource::Path(binary.src),
+            mode: 0o755,
+        });
+    }
+    if let Some(license) = settings.license_content() {
+        files.push(ArchiveFile {
+            name: LICENSE_FILE_NAME.to_string(),
+            source: FileSource::Contents(license),
+            mode: 0o644,
+        });
+    }
+    if let Some(build_info) = settings.build_info() {
+        files.push(ArchiveFile {
+            name: "build-info.json".to_string(),
+            source: FileSource::Contents(build_info.to_json()),
+            mode: 0o644,
+        });
+    }
+    if let Some(sbom) = settings.sbom() {
+        files.push(ArchiveFile {
+            name: sbom.file_name().to_string(),
+            source: FileSource::Contents(sbom.to_json().to_string()),
+            mode: 0o644,
+        });
+    }
+    let binary_name = settings.binary_name();
+    for (shell, script) in settings.shell_completions() {
+        let name = match shell {
+            Shell::Bash => format!("completions/{binary_name}.bash"),
+            Shell::Zsh => format!("completions/_{binary_name}"),
+            Shell::Fish => format!("completions/{binary_name}.fish"),
+        };
+        files.push(ArchiveFile {
+            name,
+            source: FileSource::Path(script),
+            mode: 0o644,
+        });
+    }
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let name = resource.dest.to_string_lossy().replace('\\', "/");
+        files.push(ArchiveFile {
+            name,
+            mode: match resource.mode {
+                Some(mode) => mode,
+                None => file_mode(&resource.src)?,
+            },
+            source: FileSource::Path(resource.src),
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_dir_names() {
+        let settings = Settings::for_test("");
+        assert_eq!(
+            archive_dir_name(&settings, "x86_64-pc-windows-msvc"),
+            "example-1.0.0-x86_64-pc-windows-msvc"
+        );
+        assert!(is_windows("x86_64-pc-windows-msvc"));
+        assert!(!is_windows("aarch64-apple-darwin"));
+    }
+
+    #[test]
+    fn files() {
+        let settings = Settings::for_test("");
+        let files = collect_files(&settings).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "example");
+        assert_eq!(files[0].mode, 0o755);
+
+        let settings = Settings::for_test(
+            "shell_completions = { fish = \"example.fish\", zsh = \"_example\" }",
+        );
+        let names: Vec<_> = collect_files(&settings)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "example",
+                "completions/_example",
+                "completions/example.fish"
+            ]
+        );
+    }
+}
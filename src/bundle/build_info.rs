@@ -0,0 +1,140 @@
+use super::common;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+/// The name of the file that build metadata is written to, alongside the
+/// bundle's resource files.
+pub const BUILD_INFO_FILE_NAME: &str = "build-info.json";
+
+/// Metadata about the build being bundled, captured when the `build_info`
+/// setting is enabled.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildInfo {
+    /// The short hash of the git commit being built, or `None` if the package
+    /// is not in a git repository.
+    pub git_hash: Option<String>,
+    /// The time of the build, in RFC 3339 format.
+    pub build_date: String,
+}
+
+impl BuildInfo {
+    /// Captures the build metadata for the package in `package_dir`.  The
+    /// build time is taken from `build_time`.
+    pub fn capture(package_dir: &Path) -> crate::Result<BuildInfo> {
+        Ok(BuildInfo {
+            git_hash: git_short_hash(package_dir),
+            build_date: build_time()?.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        })
+    }
+
+    /// Returns the contents of the `build-info.json` file for this build.
+    pub fn to_json(&self) -> String {
+        let json = serde_json::json!({
+            "git_hash": self.git_hash,
+            "build_date": self.build_date,
+        });
+        serde_json::to_string_pretty(&json).unwrap() + "\n"
+    }
+
+    /// Writes `build-info.json` into the given directory, creating it if
+    /// needed.
+    pub fn write_json(&self, dir: &Path) -> crate::Result<()> {
+        let path = dir.join(BUILD_INFO_FILE_NAME);
+        let mut file = common::create_file(&path)?;
+        file.write_all(self.to_json().as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Returns the time of the build: the time given by `SOURCE_DATE_EPOCH` if it
+/// is set, so that reproducible builds embed the same timestamp, or else the
+/// current time.
+pub fn build_time() -> crate::Result<DateTime<Utc>> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => {
+            let seconds = epoch
+                .trim()
+                .parse::<i64>()
+                .with_context(|| format!("Invalid SOURCE_DATE_EPOCH {epoch:?}"))?;
+            DateTime::<Utc>::from_timestamp(seconds, 0)
+                .ok_or_else(|| anyhow::anyhow!("SOURCE_DATE_EPOCH {epoch:?} is out of range"))
+        }
+        Err(_) => Ok(Utc::now()),
+    }
+}
+
+/// Makes sure that `SOURCE_DATE_EPOCH` is set, as `--reproducible` requires: if
+/// it is unset, it is set to the time of the `HEAD` commit of the git
+/// repository containing `dir`, so that the tools run while bundling pick it up
+/// too.
+pub fn pin_source_date_epoch(dir: &Path) -> crate::Result<()> {
+    if std::env::var_os("SOURCE_DATE_EPOCH").is_some() {
+        build_time()?;
+        return Ok(());
+    }
+    let epoch = git_output(dir, &["log", "-1", "--format=%ct"]).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Reproducible builds need SOURCE_DATE_EPOCH to be set, or the package to be in \
+             a git repository"
+        )
+    })?;
+    // SAFETY: this is called while parsing the command line, before any other
+    // threads are started.
+    unsafe { std::env::set_var("SOURCE_DATE_EPOCH", epoch) };
+    Ok(())
+}
+
+/// Returns the short hash of `HEAD` for the git repository containing `dir`, or
+/// `None` if git is unavailable or `dir` is not in a git repository.
+fn git_short_hash(dir: &Path) -> Option<String> {
+    git_output(dir, &["rev-parse", "--short", "HEAD"])
+}
+
+/// Runs git in `dir` and returns its trimmed output, or `None` if git fails or
+/// prints nothing.
+pub fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if output.is_empty() {
+        None
+    } else {
+        Some(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BUILD_INFO_FILE_NAME, BuildInfo};
+
+    #[test]
+    fn build_info_json() {
+        let info = BuildInfo {
+            git_hash: Some("abc1234".to_string()),
+            build_date: "2024-01-02T03:04:05Z".to_string(),
+        };
+        let tmp = tempfile::tempdir().unwrap();
+        info.write_json(tmp.path()).unwrap();
+        let contents = std::fs::read_to_string(tmp.path().join(BUILD_INFO_FILE_NAME)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(json["git_hash"], "abc1234");
+        assert_eq!(json["build_date"], "2024-01-02T03:04:05Z");
+
+        let info = BuildInfo {
+            git_hash: None,
+            ..info
+        };
+        let json: serde_json::Value = serde_json::from_str(&info.to_json()).unwrap();
+        assert!(json["git_hash"].is_null());
+    }
+}
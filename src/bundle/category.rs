@@ -173,6 +173,52 @@ impl AppCategory {
         }
     }
 
+    /// Map an AppCategory to the closest section of the Debian archive, which
+    /// deb packages are filed under.
+    pub fn debian_section(&self) -> &'static str {
+        match &self {
+            AppCategory::DeveloperTool => "devel",
+            AppCategory::Education => "education",
+            AppCategory::Game
+            | AppCategory::ActionGame
+            | AppCategory::AdventureGame
+            | AppCategory::ArcadeGame
+            | AppCategory::BoardGame
+            | AppCategory::CardGame
+            | AppCategory::CasinoGame
+            | AppCategory::DiceGame
+            | AppCategory::EducationalGame
+            | AppCategory::FamilyGame
+            | AppCategory::KidsGame
+            | AppCategory::MusicGame
+            | AppCategory::PuzzleGame
+            | AppCategory::RacingGame
+            | AppCategory::RolePlayingGame
+            | AppCategory::SimulationGame
+            | AppCategory::SportsGame
+            | AppCategory::StrategyGame
+            | AppCategory::TriviaGame
+            | AppCategory::WordGame => "games",
+            AppCategory::GraphicsAndDesign | AppCategory::Photography => "graphics",
+            AppCategory::HealthcareAndFitness | AppCategory::Medical | AppCategory::Weather => {
+                "science"
+            }
+            AppCategory::Music => "sound",
+            AppCategory::News => "news",
+            AppCategory::Reference => "doc",
+            AppCategory::SocialNetworking => "net",
+            AppCategory::Utility => "utils",
+            AppCategory::Video => "video",
+            AppCategory::Business
+            | AppCategory::Entertainment
+            | AppCategory::Finance
+            | AppCategory::Lifestyle
+            | AppCategory::Productivity
+            | AppCategory::Sports
+            | AppCategory::Travel => "misc",
+        }
+    }
+
     /// Map an AppCategory to the closest LSApplicationCategoryType value that
     /// matches that category.
     pub fn osx_application_category_type(&self) -> &'static str {
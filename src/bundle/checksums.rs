@@ -0,0 +1,126 @@
+// With `--checksums`, checksum files are written next to the bundles, in the
+// format that `sha256sum --check` and friends read:
+//
+// bundle/deb/
+//     foobar_1.2.3_amd64.deb
+//     SHA256SUMS              # "<hex digest>  foobar_1.2.3_amd64.deb" lines
+//
+// With `--checksums-per-artifact`, each bundle gets its own file instead
+// (e.g. `foobar_1.2.3_amd64.deb.sha256`).  Directory bundles, such as OSX app
+// bundles, are not checksummed.
+
+use super::common;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A digest algorithm for checksum files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    /// Returns the name of this algorithm, as used for the extension of
+    /// per-artifact checksum files.
+    pub const fn name(&self) -> &'static str {
+        match *self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Returns the name of the checksum file listing several artifacts.
+    const fn sums_file_name(&self) -> &'static str {
+        match *self {
+            ChecksumAlgorithm::Sha256 => "SHA256SUMS",
+            ChecksumAlgorithm::Sha512 => "SHA512SUMS",
+        }
+    }
+}
+
+/// Writes checksum files for the artifacts at `paths` with each of the
+/// `algorithms`, and returns the paths of the files written.
+pub fn write_checksums(
+    paths: &[PathBuf],
+    algorithms: &[ChecksumAlgorithm],
+    per_artifact: bool,
+) -> crate::Result<Vec<PathBuf>> {
+    let mut checksum_paths = Vec::new();
+    for &algorithm in algorithms {
+        // The artifacts of each format are in their own directory.
+        let mut lines_by_dir: BTreeMap<&Path, String> = BTreeMap::new();
+        for path in paths.iter().filter(|path| path.is_file()) {
+            let line = checksum_line(path, algorithm)?;
+            if per_artifact {
+                let mut checksum_path = path.as_os_str().to_owned();
+                checksum_path.push(format!(".{}", algorithm.name()));
+                let checksum_path = PathBuf::from(checksum_path);
+                fs::write(&checksum_path, line)?;
+                checksum_paths.push(checksum_path);
+            } else {
+                let dir = path.parent().unwrap_or(Path::new("."));
+                lines_by_dir.entry(dir).or_default().push_str(&line);
+            }
+        }
+        for (dir, lines) in lines_by_dir {
+            let checksum_path = dir.join(algorithm.sums_file_name());
+            fs::write(&checksum_path, lines)?;
+            checksum_paths.push(checksum_path);
+        }
+    }
+    Ok(checksum_paths)
+}
+
+/// Returns the line listing the artifact at `path` in a checksum file.
+fn checksum_line(path: &Path, algorithm: ChecksumAlgorithm) -> crate::Result<String> {
+    let digest = common::digest_hex(path, algorithm)?;
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    Ok(format!("{digest}  {file_name}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let deb = tmp.path().join("deb/foo.deb");
+        let rpm = tmp.path().join("rpm/foo.rpm");
+        for path in [&deb, &rpm] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "hello\n").unwrap();
+        }
+        let paths = vec![deb.clone(), rpm.clone(), tmp.path().join("deb")];
+        let sha256 = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+        let written = write_checksums(&paths, &[ChecksumAlgorithm::Sha256], false).unwrap();
+        assert_eq!(
+            written,
+            [
+                tmp.path().join("deb/SHA256SUMS"),
+                tmp.path().join("rpm/SHA256SUMS")
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(&written[0]).unwrap(),
+            format!("{sha256}  foo.deb\n")
+        );
+
+        let written = write_checksums(&paths[..1], &[ChecksumAlgorithm::Sha256], true).unwrap();
+        assert_eq!(written, [tmp.path().join("deb/foo.deb.sha256")]);
+        assert_eq!(
+            fs::read_to_string(&written[0]).unwrap(),
+            format!("{sha256}  foo.deb\n")
+        );
+
+        let written = write_checksums(&paths[..1], &[ChecksumAlgorithm::Sha512], true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&written[0]).unwrap(),
+            "e7c22b994c59d9cf2b48e549b1e24666636045930d3da7c1acb299d1c3b7f931\
+             f94aae41edda2c2b207a36e10f8bcb8d45223e54878f5b316e7ce3b6bc019629  foo.deb\n"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+// A Chocolatey package is a NuGet package (a zip file) containing a nuspec
+// and PowerShell scripts that Chocolatey runs on install and uninstall:
+//
+// foobar.1.2.3.nupkg
+//     [Content_Types].xml     # Open Packaging Conventions metadata
+//     _rels/.rels
+//     foobar.nuspec           # Package metadata
+//     tools/
+//         chocolateyinstall.ps1    # Unzips the app and creates a shortcut
+//         chocolateyuninstall.ps1  # Removes the shortcut
+//         foobar.zip               # The binary and resource files
+//
+// Chocolatey creates shims on the PATH for the executables the install script
+// unzips, and removes the package directory itself on uninstall.
+
+use super::build_info::build_time;
+use super::common;
+use super::shared_libs;
+use super::zip::ZipWriter;
+use crate::Settings;
+use quick_xml::escape::escape;
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let id = package_id(settings);
+    let version = nuget_version(&settings.version_string().to_string());
+    let package_name = format!("{id}.{version}.nupkg");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/choco");
+    let package_path = base_dir.join(&package_name);
+    let mtime = build_time()?;
+
+    // Zip up the files to install.
+    let mut app_zip = ZipWriter::new(Cursor::new(Vec::new()), mtime);
+    let exe_name = settings
+        .binary_path()
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    app_zip.add_file(&exe_name, &std::fs::read(settings.binary_path())?, true)?;
+    for binary in shared_libs::bundled_executables(settings)? {
+        app_zip.add_file(&binary.name, &std::fs::read(&binary.src)?, true)?;
+    }
+    let resource_files = settings
+        .resource_files()
+        .collect::<crate::Result<Vec<_>>>()?;
+    if !resource_files.is_empty() || settings.build_info().is_some() || settings.sbom().is_some() {
+        app_zip.add_directory("Resources")?;
+    }
+    for resource in resource_files {
+        let name = PathBuf::from("Resources").join(&resource.dest);
+        let name = name.to_string_lossy().replace('\\', "/");
+        app_zip.add_file(&name, &std::fs::read(&resource.src)?, false)?;
+    }
+    if let Some(build_info) = settings.build_info() {
+        app_zip.add_file(
+            "Resources/build-info.json",
+            build_info.to_json().as_bytes(),
+            false,
+        )?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        app_zip.add_file(
+            &format!("Resources/{}", sbom.file_name()),
+            sbom.to_json().as_bytes(),
+            false,
+        )?;
+    }
+    let app_zip = app_zip.finish()?.into_inner();
+
+    let app_zip_name = format!("{}.zip", settings.binary_name());
+    let mut nupkg = ZipWriter::new(common::create_file(&package_path)?, mtime);
+    nupkg.add_file("[Content_Types].xml", CONTENT_TYPES.as_bytes(), false)?;
+    nupkg.add_file("_rels/.rels", generate_relationships(&id).as_bytes(), false)?;
+    nupkg.add_file(
+        &format!("{id}.nuspec"),
+        generate_nuspec(settings, &id, &version).as_bytes(),
+        false,
+    )?;
+    nupkg.add_file(
+        "tools/chocolateyinstall.ps1",
+        generate_install_script(settings, &app_zip_name, &exe_name).as_bytes(),
+        false,
+    )?;
+    nupkg.add_file(
+        "tools/chocolateyuninstall.ps1",
+        generate_uninstall_script(settings).as_bytes(),
+        false,
+    )?;
+    nupkg.add_file(&format!("tools/{app_zip_name}"), &app_zip, false)?;
+    nupkg.finish()?;
+    Ok(vec![package_path])
+}
+
+const CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\n  \
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\n  \
+<Default Extension=\"nuspec\" ContentType=\"application/octet\"/>\n  \
+<Default Extension=\"ps1\" ContentType=\"application/octet\"/>\n  \
+<Default Extension=\"zip\" ContentType=\"application/octet\"/>\n\
+</Types>\n";
+
+/// Derives a valid Chocolatey package id from the (human-readable) bundle
+/// name.  By convention, ids are lowercase and dash-separated.
+fn package_id(settings: &Settings) -> String {
+    common::package_name_slug(settings, &['.'])
+}
+
+/// Converts a version string to a SemVer 1 version, which is all Chocolatey
+/// supports: build metadata is dropped, and the pre-release label may only
+/// contain letters, digits and dashes (e.g. `1.2.3-beta.1` becomes
+/// `1.2.3-beta1`).
+fn nuget_version(version: &str) -> String {
+    let version = version.split('+').next().unwrap();
+    match version.split_once('-') {
+        Some((release, pre_release)) => {
+            let pre_release: String = pre_release
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+                .collect();
+            format!("{release}-{pre_release}")
+        }
+        None => version.to_string(),
+    }
+}
+
+/// Quotes a string as a single-quoted PowerShell string.
+fn powershell_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn generate_relationships(id: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\n  \
+         <Relationship Type=\"http://schemas.microsoft.com/packaging/2010/07/manifest\" \
+         Target=\"/{}.nuspec\" Id=\"R1\"/>\n\
+         </Relationships>\n",
+        escape(id)
+    )
+}
+
+/// Generates the contents of the nuspec file.
+fn generate_nuspec(settings: &Settings, id: &str, version: &str) -> String {
+    let mut description = settings.long_description().unwrap_or("").trim();
+    if description.is_empty() {
+        description = settings.short_description().trim();
+    }
+    if description.is_empty() {
+        description = settings.bundle_name();
+    }
+    let authors = settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| settings.bundle_name().to_string());
+
+    let mut xml = String::new();
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"utf-8\"?>").unwrap();
+    writeln!(
+        xml,
+        "<package xmlns=\"http://schemas.microsoft.com/packaging/2015/06/nuspec.xsd\">"
+    )
+    .unwrap();
+    writeln!(xml, "  <metadata>").unwrap();
+    writeln!(xml, "    <id>{}</id>", escape(id)).unwrap();
+    writeln!(xml, "    <version>{}</version>", escape(version)).unwrap();
+    writeln!(xml, "    <title>{}</title>", escape(settings.bundle_name())).unwrap();
+    writeln!(xml, "    <authors>{}</authors>", escape(&authors)).unwrap();
+    if !settings.homepage_url().is_empty() {
+        writeln!(
+            xml,
+            "    <projectUrl>{}</projectUrl>",
+            escape(settings.homepage_url())
+        )
+        .unwrap();
+    }
+    if let Some(license) = settings.license() {
+        // Link to the SPDX page of the first license in the expression.
+        let license = license
+            .split(" OR ")
+            .flat_map(|l| l.split('/'))
+            .next()
+            .unwrap()
+            .trim();
+        writeln!(
+            xml,
+            "    <licenseUrl>https://spdx.org/licenses/{}.html</licenseUrl>",
+            escape(license)
+        )
+        .unwrap();
+    }
+    writeln!(
+        xml,
+        "    <requireLicenseAcceptance>false</requireLicenseAcceptance>"
+    )
+    .unwrap();
+    if let Some(copyright) = settings.copyright_string() {
+        writeln!(xml, "    <copyright>{}</copyright>", escape(copyright)).unwrap();
+    }
+    let summary = settings.short_description().trim();
+    if !summary.is_empty() {
+        writeln!(xml, "    <summary>{}</summary>", escape(summary)).unwrap();
+    }
+    writeln!(
+        xml,
+        "    <description>{}</description>",
+        escape(description)
+    )
+    .unwrap();
+    writeln!(xml, "  </metadata>").unwrap();
+    writeln!(xml, "</package>").unwrap();
+    xml
+}
+
+/// Generates the script Chocolatey runs to install the package.
+fn generate_install_script(settings: &Settings, app_zip_name: &str, exe_name: &str) -> String {
+    let mut ps1 = String::new();
+    writeln!(ps1, "$ErrorActionPreference = 'Stop'").unwrap();
+    writeln!(
+        ps1,
+        "$toolsDir = Split-Path -Parent $MyInvocation.MyCommand.Definition"
+    )
+    .unwrap();
+    writeln!(ps1, "$appDir = Join-Path $toolsDir 'app'").unwrap();
+    writeln!(
+        ps1,
+        "Get-ChocolateyUnzip -FileFullPath (Join-Path $toolsDir {}) -Destination $appDir",
+        powershell_string(app_zip_name)
+    )
+    .unwrap();
+    writeln!(
+        ps1,
+        "$exe = Join-Path $appDir {}",
+        powershell_string(exe_name)
+    )
+    .unwrap();
+    writeln!(
+        ps1,
+        "# Mark the shim as a GUI app, so it doesn't wait for it."
+    )
+    .unwrap();
+    writeln!(
+        ps1,
+        "New-Item \"$exe.gui\" -ItemType File -Force | Out-Null"
+    )
+    .unwrap();
+    writeln!(
+        ps1,
+        "$shortcut = Join-Path ([Environment]::GetFolderPath('CommonPrograms')) {}",
+        powershell_string(&format!("{}.lnk", settings.bundle_file_name()))
+    )
+    .unwrap();
+    writeln!(
+        ps1,
+        "Install-ChocolateyShortcut -ShortcutFilePath $shortcut -TargetPath $exe"
+    )
+    .unwrap();
+    ps1
+}
+
+/// Generates the script Chocolatey runs to uninstall the package.
+fn generate_uninstall_script(settings: &Settings) -> String {
+    let mut ps1 = String::new();
+    writeln!(ps1, "$ErrorActionPreference = 'Stop'").unwrap();
+    writeln!(
+        ps1,
+        "$shortcut = Join-Path ([Environment]::GetFolderPath('CommonPrograms')) {}",
+        powershell_string(&format!("{}.lnk", settings.bundle_file_name()))
+    )
+    .unwrap();
+    writeln!(
+        ps1,
+        "if (Test-Path $shortcut) {{ Remove-Item $shortcut -Force }}"
+    )
+    .unwrap();
+    ps1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_ids() {
+        let package_id = |name: &str| package_id(&Settings::for_test(&format!("name = {name:?}")));
+        assert_eq!(package_id("My Cool App"), "my-cool-app");
+        assert_eq!(package_id("foo.bar_baz"), "foo.bar-baz");
+        assert_eq!(package_id(".NET Tool"), "net-tool");
+        assert_eq!(package_id("日本語"), "example");
+    }
+
+    #[test]
+    fn nuget_versions() {
+        assert_eq!(nuget_version("1.2.3"), "1.2.3");
+        assert_eq!(nuget_version("1.2.3-beta.1+build.5"), "1.2.3-beta1");
+    }
+
+    #[test]
+    fn nuspec() {
+        let settings = Settings::for_test(
+            "name = \"Tom & Jerry\"\n\
+             short_description = \"A cartoon\"\n",
+        );
+        let nuspec = generate_nuspec(&settings, "tom-jerry", "1.0.0");
+        assert!(nuspec.contains("    <id>tom-jerry</id>\n    <version>1.0.0</version>\n"));
+        assert!(nuspec.contains("    <title>Tom &amp; Jerry</title>\n"));
+        assert!(nuspec.contains("    <description>A cartoon</description>\n"));
+    }
+
+    #[test]
+    fn install_script() {
+        let settings = Settings::for_test("name = \"Bob's App\"\n");
+        let ps1 = generate_install_script(&settings, "example.zip", "example.exe");
+        assert!(ps1.contains("(Join-Path $toolsDir 'example.zip')"));
+        assert!(ps1.contains("'Bob''s App.lnk'"));
+    }
+}
@@ -1,8 +1,107 @@
+use super::build_info::build_time;
+use super::checksums::ChecksumAlgorithm;
+use super::settings::{ExternalBinary, ResourceFile, Settings};
+use super::shared_libs;
 use anyhow::Context;
+use libflate::gzip;
 use std::ffi::OsStr;
 use std::fs::{self, File};
 use std::io::{self, BufWriter, Write};
 use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+/// When warnings should be turned into errors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum DenyWarnings {
+    /// Fail after bundling has finished, listing every warning emitted.
+    End,
+    /// Fail as soon as the first warning is emitted.
+    Immediate,
+}
+
+/// Records the warnings emitted during a run, so that they can be turned into
+/// errors when warnings are denied.
+struct WarningLog {
+    deny: Mutex<Option<DenyWarnings>>,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl WarningLog {
+    const fn new() -> WarningLog {
+        WarningLog {
+            deny: Mutex::new(None),
+            warnings: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn deny(&self, when: DenyWarnings) {
+        self.deny.lock().unwrap().get_or_insert(when);
+    }
+
+    fn record(&self, message: &str) -> crate::Result<()> {
+        self.warnings.lock().unwrap().push(message.to_string());
+        if *self.deny.lock().unwrap() == Some(DenyWarnings::Immediate) {
+            anyhow::bail!("warning treated as an error: {message}");
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> crate::Result<()> {
+        if self.deny.lock().unwrap().is_none() {
+            return Ok(());
+        }
+        let warnings = self.warnings.lock().unwrap();
+        if warnings.is_empty() {
+            return Ok(());
+        }
+        let pluralised = if warnings.len() == 1 {
+            "warning was"
+        } else {
+            "warnings were"
+        };
+        let list = warnings
+            .iter()
+            .map(|warning| format!("\n  - {warning}"))
+            .collect::<String>();
+        anyhow::bail!(
+            "{} {pluralised} emitted while warnings are denied:{list}",
+            warnings.len()
+        )
+    }
+}
+
+static WARNING_LOG: WarningLog = WarningLog::new();
+
+/// Makes warnings printed by `print_warning` count as errors.  If warnings are
+/// already denied, the earlier choice of `when` is kept.
+pub fn deny_warnings(when: DenyWarnings) {
+    WARNING_LOG.deny(when);
+}
+
+/// Fails with a summary of the warnings emitted so far, if warnings are denied
+/// and any were emitted.
+pub fn check_warnings() -> crate::Result<()> {
+    WARNING_LOG.check()
+}
+
+// The files and bundles signed during the run, so that the bundles can be
+// reported as signed or not.
+static SIGNED_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Records that the file or bundle at `path` has been signed.
+pub fn record_signed(path: &Path) {
+    SIGNED_PATHS.lock().unwrap().push(path.to_path_buf());
+}
+
+/// Returns true if the file or bundle at `path` has been signed.
+pub fn was_signed(path: &Path) -> bool {
+    SIGNED_PATHS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|signed| signed == path)
+}
 
 /// Returns true if the path has a filename indicating that it is a high-desity
 /// "retina" icon.  Specifically, returns true the the file stem ends with
@@ -27,13 +126,24 @@ pub fn create_file(path: &Path) -> crate::Result<BufWriter<File>> {
     Ok(BufWriter::new(file))
 }
 
+/// Creates a gzip encoder writing to `writer`, with the build time rather than
+/// the current time in its header, so that compressed output is reproducible.
+pub fn gzip_encoder<W: Write>(writer: W) -> crate::Result<gzip::Encoder<W>> {
+    let mtime = build_time()?.timestamp().clamp(0, u32::MAX as i64) as u32;
+    let header = gzip::HeaderBuilder::new().modification_time(mtime).finish();
+    Ok(gzip::Encoder::with_options(
+        writer,
+        gzip::EncodeOptions::new().header(header),
+    )?)
+}
+
 #[cfg(unix)]
-fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
+pub fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
     std::os::unix::fs::symlink(src, dst)
 }
 
 #[cfg(windows)]
-fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
+pub fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
     std::os::windows::fs::symlink_dir(src, dst)
 }
 
@@ -63,6 +173,78 @@ pub fn copy_file(from: &Path, to: &Path) -> crate::Result<()> {
     Ok(())
 }
 
+/// Copies a resource file into a bundle at `to`, giving the copy the
+/// resource's permissions if they were set explicitly.
+pub fn copy_resource(resource: &ResourceFile, to: &Path) -> crate::Result<()> {
+    copy_file(&resource.src, to)
+        .with_context(|| format!("Failed to copy resource file {:?}", resource.src))?;
+    if let Some(mode) = resource.mode {
+        set_mode(to, mode)?;
+    }
+    Ok(())
+}
+
+/// Copies the bundle's external binaries (and the DLLs it needs, when bundling
+/// shared libraries for Windows) into `dir`, which is usually where the binary
+/// itself goes, and makes them executable.
+pub fn copy_external_binaries(settings: &Settings, dir: &Path) -> crate::Result<()> {
+    for binary in shared_libs::bundled_executables(settings)? {
+        copy_external_binary(&binary, &dir.join(&binary.name))?;
+    }
+    Ok(())
+}
+
+/// Copies an external binary to `to`, and makes the copy executable.
+pub fn copy_external_binary(binary: &ExternalBinary, to: &Path) -> crate::Result<()> {
+    copy_file(&binary.src, to)
+        .with_context(|| format!("Failed to copy external binary {:?}", binary.src))?;
+    set_mode(to, 0o755)
+}
+
+/// Copies the `man_pages` into `dir`, each in the directory of its section,
+/// e.g. `man1/foobar.1` for `docs/foobar.1`, and returns their paths.
+pub fn copy_man_pages(settings: &Settings, dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for page in settings.man_pages() {
+        let dest = dir.join(man_page_relpath(&page)?);
+        copy_file(&page, &dest).with_context(|| format!("Failed to copy man page {page:?}"))?;
+        paths.push(dest);
+    }
+    Ok(paths)
+}
+
+/// Returns where the man page at `page` goes in a man directory: in the
+/// directory of the section its extension starts with, e.g. `man1/foo.1` or
+/// `man3/foo.3pm.gz`.
+fn man_page_relpath(page: &Path) -> crate::Result<PathBuf> {
+    let name = page.file_name().unwrap_or_default().to_string_lossy();
+    let extension = Path::new(name.strip_suffix(".gz").unwrap_or(&name))
+        .extension()
+        .map(|extension| extension.to_string_lossy());
+    let section = extension
+        .as_deref()
+        .and_then(|extension| extension.chars().next())
+        .filter(char::is_ascii_digit);
+    let Some(section) = section else {
+        anyhow::bail!("The man page {page:?} has no section extension, such as `.1`");
+    };
+    Ok(Path::new(&format!("man{section}")).join(&*name))
+}
+
+/// Sets the Unix permissions of a file.  This does nothing on other hosts,
+/// where files have no such permissions.
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> crate::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set the permissions of {path:?}"))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_path: &Path, _mode: u32) -> crate::Result<()> {
+    Ok(())
+}
+
 /// Reads a regular file into memory
 pub fn read_file(file: &Path) -> crate::Result<String> {
     if !file.exists() {
@@ -75,6 +257,18 @@ pub fn read_file(file: &Path) -> crate::Result<String> {
     Ok(contents)
 }
 
+/// Checks that an image shown by an installer is a BMP file, the only format
+/// that the Windows installer dialogs show.
+pub fn check_bmp(image: &Path) -> crate::Result<()> {
+    let is_bmp = image
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("bmp"));
+    if !is_bmp {
+        anyhow::bail!("Installer image {image:?} is not a BMP file");
+    }
+    Ok(())
+}
+
 /// Recursively copies a directory file from one path to another, creating any
 /// parent directories of the destination path as necessary.  Fails if the
 /// source path is not a directory or doesn't exist, or if the destination path
@@ -112,6 +306,54 @@ pub fn copy_dir(from: &Path, to: &Path) -> crate::Result<()> {
     Ok(())
 }
 
+/// Derives a package name from the (human-readable) bundle name, for package
+/// formats whose names may only contain lowercase ASCII letters, digits and
+/// the given `punctuation`.  Other characters are replaced by dashes, and the
+/// name always starts with a letter or a digit.  If the bundle name has no
+/// ASCII letters or digits, the name of the crate is used instead.
+pub fn package_name_slug(settings: &Settings, punctuation: &[char]) -> String {
+    let slug = |name: &str| {
+        let mut slug = String::new();
+        for c in name.chars().flat_map(char::to_lowercase) {
+            if c.is_ascii_lowercase()
+                || c.is_ascii_digit()
+                || (!slug.is_empty() && punctuation.contains(&c))
+            {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.trim_end_matches('-').to_string()
+    };
+    match slug(settings.bundle_name()) {
+        name if name.is_empty() => slug(settings.package_name()),
+        name => name,
+    }
+}
+
+/// Returns a version of `name` that is safe to use as a file or directory name
+/// on all supported platforms.  Spaces and non-ASCII characters are kept, but
+/// path separators, characters reserved on Windows and control characters are
+/// replaced with `-`, and trailing dots and spaces (which Windows strips) are
+/// removed.
+pub fn file_name_slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+    let slug = slug.trim_end_matches(['.', ' ']).trim_start();
+    if slug.is_empty() {
+        "_".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
 /// Given a path (absolute or relative) to a resource file, returns the
 /// relative path from the bundle resources directory where that resource
 /// should be stored.
@@ -129,12 +371,265 @@ pub fn resource_relpath(path: &Path) -> PathBuf {
     dest
 }
 
+/// Expands the `{token}` placeholders in `template` using the given
+/// `(token, value)` pairs.  Fails if the template refers to an unknown token.
+pub fn expand_template(template: &str, tokens: &[(&str, &str)]) -> crate::Result<String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            anyhow::bail!("Unterminated token in template {template:?}");
+        };
+        let token = &rest[start + 1..start + end];
+        match tokens.iter().find(|(key, _)| *key == token) {
+            Some((_, value)) => expanded.push_str(value),
+            None => anyhow::bail!("Unknown token {{{token}}} in template {template:?}"),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expands the `{token}` placeholders in an artifact name template using the
+/// given `(token, value)` pairs.  Fails if the template refers to an unknown
+/// token, or if the resulting name is empty or contains a path separator.
+pub fn expand_artifact_name_template(
+    template: &str,
+    tokens: &[(&str, &str)],
+) -> crate::Result<String> {
+    let name = expand_template(template, tokens)?;
+    if name.trim().is_empty() {
+        anyhow::bail!("Artifact name template {template:?} produces an empty name");
+    }
+    if name.contains(['/', '\\']) {
+        anyhow::bail!("Artifact name {name:?} must not contain a path separator");
+    }
+    Ok(name)
+}
+
+// Extensions made of several parts, which are kept whole when renaming.
+const COMPOUND_EXTENSIONS: &[&str] = &[
+    "pkg.tar.zst",
+    "pkg.tar.xz",
+    "src.rpm",
+    "tar.gz",
+    "tar.xz",
+    "tar.zst",
+    "tar.bz2",
+];
+
+// Extensions of files that sign or check another file, which keep the
+// extension of that file too (e.g. `.deb.asc`).
+const SIDECAR_EXTENSIONS: &[&str] = &["asc", "sig", "sha256", "sha512"];
+
+// Splits an artifact's file name into its stem and its full extension,
+// including the leading dot.
+fn split_artifact_name(file_name: &str) -> (&str, &str) {
+    let mut stem = file_name;
+    while let Some((rest, extension)) = stem.rsplit_once('.') {
+        if rest.is_empty() || !SIDECAR_EXTENSIONS.contains(&extension) {
+            break;
+        }
+        stem = rest;
+    }
+    let stem = COMPOUND_EXTENSIONS
+        .iter()
+        .find_map(|extension| stem.strip_suffix(extension)?.strip_suffix('.'))
+        .or_else(|| stem.rsplit_once('.').map(|(rest, _)| rest))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(stem);
+    (stem, &file_name[stem.len()..])
+}
+
+fn artifact_file_name(path: &Path) -> crate::Result<&str> {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .with_context(|| format!("Cannot rename {path:?}, its name is not valid UTF-8"))
+}
+
+// Returns the part of `stem` that sets it apart from `primary`, the stem of the
+// main artifact of the same format, such as `dbgsym` for `foo-dbgsym_1.0_amd64`
+// next to `foo_1.0_amd64`.  Only whole `-`, `_` or `.` separated words are
+// shared.
+fn distinguishing_part<'a>(primary: &str, stem: &'a str) -> &'a str {
+    let is_separator = |c: char| matches!(c, '-' | '_' | '.');
+    let is_boundary = |string: &str, index: usize| {
+        string[..index].ends_with(is_separator)
+            || string[index..].starts_with(is_separator)
+            || index == 0
+            || index == string.len()
+    };
+    let mut prefix: usize = primary
+        .chars()
+        .zip(stem.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    while !(is_boundary(primary, prefix) && is_boundary(stem, prefix)) {
+        prefix = stem[..prefix].char_indices().last().map_or(0, |(i, _)| i);
+    }
+    let mut suffix: usize = primary[prefix..]
+        .chars()
+        .rev()
+        .zip(stem[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    while !(is_boundary(primary, primary.len() - suffix) && is_boundary(stem, stem.len() - suffix))
+    {
+        suffix = stem[stem.len() - suffix..]
+            .chars()
+            .next()
+            .map_or(0, |c| suffix - c.len_utf8());
+    }
+    stem[prefix..stem.len() - suffix].trim_matches(is_separator)
+}
+
+/// Renames the artifacts at `paths` (files or directories), all produced for
+/// one format, to the file stem produced by `template`, and returns their new
+/// paths.  The first path is the main artifact; the others keep the part of
+/// their name that sets them apart from it as a suffix (e.g.
+/// `myapp_ubuntu-dbgsym.deb`).  Each artifact keeps its parent directory and
+/// its full extension, such as `.tar.gz`, `.src.rpm` or `.deb.asc`.  Fails
+/// without renaming anything if two artifacts would get the same name.
+pub fn rename_artifacts(
+    paths: &[PathBuf],
+    template: &str,
+    tokens: &[(&str, &str)],
+) -> crate::Result<Vec<PathBuf>> {
+    let name = expand_artifact_name_template(template, tokens)?;
+    let Some(primary) = paths.first() else {
+        return Ok(Vec::new());
+    };
+    let (primary_stem, _) = split_artifact_name(artifact_file_name(primary)?);
+    let mut dests: Vec<PathBuf> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (stem, extension) = split_artifact_name(artifact_file_name(path)?);
+        let dest = match distinguishing_part(primary_stem, stem) {
+            "" => path.with_file_name(format!("{name}{extension}")),
+            part => path.with_file_name(format!("{name}-{part}{extension}")),
+        };
+        if dests.contains(&dest) || (&dest != path && paths.contains(&dest)) {
+            anyhow::bail!(
+                "Artifact name template {template:?} gives {path:?} the name of another bundle, {dest:?}"
+            );
+        }
+        dests.push(dest);
+    }
+    for (path, dest) in paths.iter().zip(&dests) {
+        if dest == path {
+            continue;
+        }
+        if dest.is_dir() {
+            fs::remove_dir_all(dest).with_context(|| format!("Failed to remove old {dest:?}"))?;
+        } else if dest.exists() {
+            fs::remove_file(dest).with_context(|| format!("Failed to remove old {dest:?}"))?;
+        }
+        fs::rename(path, dest).with_context(|| format!("Failed to rename {path:?} to {dest:?}"))?;
+    }
+    Ok(dests)
+}
+
+/// Runs an external tool to completion and returns its standard output,
+/// failing with the tool's output if it cannot be started or exits
+/// unsuccessfully.
+pub fn run_tool(command: &mut Command, name: &str) -> crate::Result<Vec<u8>> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {name}, is it installed?"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{name} failed with status {}\nStdout: {}\nStderr: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Runs a signing tool like `run_tool`, retrying it as configured by the
+/// `timestamp_retries` and `timestamp_retry_delay` settings if it fails, as
+/// timestamp servers are prone to transient failures.
+pub fn run_signing_tool(
+    settings: &Settings,
+    command: &mut Command,
+    name: &str,
+) -> crate::Result<Vec<u8>> {
+    let mut retries = settings.timestamp_retries();
+    loop {
+        match run_tool(command, name) {
+            Err(error) if retries > 0 => {
+                let delay = settings.timestamp_retry_delay();
+                print_progress(
+                    "Retrying",
+                    &format!("{name} in {} seconds: {error:#}", delay.as_secs()),
+                )?;
+                std::thread::sleep(delay);
+                retries -= 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Computes the SHA-256 hash of the given file, as a hex string.
+pub fn sha256_hex(path: &Path) -> crate::Result<String> {
+    digest_hex(path, ChecksumAlgorithm::Sha256)
+}
+
+/// Computes the hash of the given file with `algorithm`, as a hex string.
+pub fn digest_hex(path: &Path, algorithm: ChecksumAlgorithm) -> crate::Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+    match algorithm {
+        ChecksumAlgorithm::Sha256 => hash_hex::<sha2::Sha256>(&mut file),
+        ChecksumAlgorithm::Sha512 => hash_hex::<sha2::Sha512>(&mut file),
+    }
+    .with_context(|| format!("Failed to hash {path:?}"))
+}
+
+fn hash_hex<D: sha2::Digest + Write>(reader: &mut impl io::Read) -> io::Result<String> {
+    let mut hasher = D::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
 /// Prints a message to stderr, in the same format that `cargo` uses,
 /// indicating that we are creating a bundle with the given filename.
 pub fn print_bundling(filename: &str) -> crate::Result<()> {
     print_progress("Bundling", filename)
 }
 
+/// Prints a message to stderr, in the same format that `cargo` uses,
+/// indicating that we are stripping the given file.
+pub fn print_stripping(filename: &str) -> crate::Result<()> {
+    print_progress("Stripping", filename)
+}
+
+/// Prints a message to stderr, in the same format that `cargo` uses,
+/// indicating that we are signing the given file.
+pub fn print_signing(filename: &str) -> crate::Result<()> {
+    print_progress("Signing", filename)
+}
+
+/// Prints a message to stderr, in the same format that `cargo` uses,
+/// indicating that we are notarizing the given file.
+pub fn print_notarizing(filename: &str) -> crate::Result<()> {
+    print_progress("Notarizing", filename)
+}
+
+/// Prints a message to stderr, in the same format that `cargo` uses,
+/// indicating that we are launching the given app.
+pub fn print_launching(message: &str) -> crate::Result<()> {
+    print_progress("Launching", message)
+}
+
 /// Prints a message to stderr, in the same format that `cargo` uses,
 /// indicating that we have finished the the given bundles.
 pub fn print_finished(output_paths: &Vec<PathBuf>) -> crate::Result<()> {
@@ -146,7 +641,7 @@ pub fn print_finished(output_paths: &Vec<PathBuf>) -> crate::Result<()> {
     let msg = format!("{} {} at:", output_paths.len(), pluralised);
     print_progress("Finished", &msg)?;
     for path in output_paths {
-        println!("        {}", path.display());
+        eprintln!("        {}", path.display());
     }
     Ok(())
 }
@@ -184,20 +679,36 @@ fn print_progress(step: &str, msg: &str) -> crate::Result<()> {
 }
 
 /// Prints a warning message to stderr, in the same format that `cargo` uses.
+/// Fails if warnings are denied immediately (see `deny_warnings`).
 pub fn print_warning(message: &str) -> crate::Result<()> {
+    write_warning(message)?;
+    WARNING_LOG.record(message)
+}
+
+fn write_warning(message: &str) -> crate::Result<()> {
+    write_diagnostic("warning:", term::color::YELLOW, message)
+}
+
+/// Prints a note to stderr, in the same format that `cargo` uses.  Unlike
+/// warnings, notes are informational, and never fail the build.
+pub fn print_note(message: &str) -> crate::Result<()> {
+    write_diagnostic("note:", term::color::CYAN, message)
+}
+
+fn write_diagnostic(label: &str, color: term::color::Color, message: &str) -> crate::Result<()> {
     if let Some(mut output) = term::stderr() {
         safe_term_attr(&mut output, term::Attr::Bold)?;
         if output.supports_color() {
-            output.fg(term::color::YELLOW)?;
+            output.fg(color)?;
         }
-        write!(output, "warning:")?;
+        write!(output, "{label}")?;
         output.reset()?;
         writeln!(output, " {message}")?;
         output.flush()?;
         Ok(())
     } else {
         let mut output = io::stderr();
-        write!(output, "warning:")?;
+        write!(output, "{label}")?;
         writeln!(output, " {message}")?;
         output.flush()?;
         Ok(())
@@ -243,7 +754,12 @@ pub fn print_error(error: &anyhow::Error) -> crate::Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::{copy_dir, create_file, is_retina, read_file, resource_relpath, symlink_file};
+    use super::{
+        DenyWarnings, WarningLog, copy_dir, create_file, expand_artifact_name_template,
+        file_name_slug, is_retina, man_page_relpath, read_file, rename_artifacts, resource_relpath,
+        run_signing_tool, symlink_file,
+    };
+    use crate::Settings;
 
     use std::io::Write;
     use std::path::{Path, PathBuf};
@@ -350,4 +866,154 @@ mod tests {
         // Find dir instead of file
         assert!(read_file(&tmp.path().join(Path::new(FILE).parent().unwrap())).is_err());
     }
+
+    #[test]
+    fn artifact_name_templates() {
+        let tokens = [
+            ("name", "myapp"),
+            ("version", "1.2.3"),
+            ("arch", "amd64"),
+            ("format", "deb"),
+            ("target", "x86_64-unknown-linux-gnu"),
+        ];
+        let template = "{name}_{version}_{arch}_ubuntu";
+        let tmp = tempfile::tempdir().unwrap();
+        let create = |name: &str| {
+            let path = tmp.path().join(name);
+            std::fs::write(&path, b"").unwrap();
+            path
+        };
+        let deb = create("myapp_1.2.3_amd64.deb");
+        let asc = create("myapp_1.2.3_amd64.deb.asc");
+        let dbgsym = create("myapp-dbgsym_1.2.3_amd64.deb");
+        let renamed = rename_artifacts(&[deb.clone(), asc, dbgsym], template, &tokens).unwrap();
+        assert_eq!(
+            renamed,
+            [
+                tmp.path().join("myapp_1.2.3_amd64_ubuntu.deb"),
+                tmp.path().join("myapp_1.2.3_amd64_ubuntu.deb.asc"),
+                tmp.path().join("myapp_1.2.3_amd64_ubuntu-dbgsym.deb"),
+            ]
+        );
+        assert!(renamed.iter().all(|path| path.is_file()));
+        assert!(!deb.exists());
+
+        let rpm = create("myapp-1.2.3-1.x86_64.rpm");
+        let debuginfo = create("myapp-debuginfo-1.2.3-1.x86_64.rpm");
+        assert_eq!(
+            rename_artifacts(&[rpm, debuginfo], "{name}", &tokens).unwrap(),
+            [
+                tmp.path().join("myapp.rpm"),
+                tmp.path().join("myapp-debuginfo.rpm")
+            ]
+        );
+        let english = create("myapp-1.2.3-en-US.msi");
+        let spanish = create("myapp-1.2.3-es-ES.msi");
+        assert_eq!(
+            rename_artifacts(&[english, spanish], "{name}", &tokens).unwrap(),
+            [
+                tmp.path().join("myapp.msi"),
+                tmp.path().join("myapp-es-ES.msi")
+            ]
+        );
+        for (name, renamed) in [
+            ("myapp-1.2.3.tar.gz", "myapp.tar.gz"),
+            ("myapp-1.2.3-1.pkg.tar.zst", "myapp.pkg.tar.zst"),
+            ("myapp-1.2.3-1.src.rpm", "myapp.src.rpm"),
+            ("myapp-1.2.3.zip.sha256", "myapp.zip.sha256"),
+        ] {
+            assert_eq!(
+                rename_artifacts(&[create(name)], "{name}", &tokens).unwrap(),
+                [tmp.path().join(renamed)]
+            );
+        }
+
+        // Artifacts that would get the same name are left alone.
+        let first = tmp.path().join("a").join("myapp.deb");
+        let second = tmp.path().join("b").join("myapp.deb");
+        assert!(rename_artifacts(&[first.clone(), first], "{name}", &tokens).is_err());
+        let other = create("other.deb");
+        let existing = create("myapp-x.deb");
+        assert!(rename_artifacts(&[other.clone(), existing.clone()], "{name}-x", &tokens).is_err());
+        assert!(other.exists() && existing.exists());
+        std::fs::create_dir_all(second.parent().unwrap()).unwrap();
+        std::fs::write(&second, b"").unwrap();
+        assert_eq!(
+            rename_artifacts(std::slice::from_ref(&second), "{name}", &tokens).unwrap(),
+            [second]
+        );
+
+        assert_eq!(
+            expand_artifact_name_template("{name}-{target}", &tokens).unwrap(),
+            "myapp-x86_64-unknown-linux-gnu"
+        );
+        assert!(expand_artifact_name_template("", &tokens).is_err());
+        assert!(expand_artifact_name_template("{name}/{arch}", &tokens).is_err());
+        assert!(expand_artifact_name_template("{nmae}", &tokens).is_err());
+        assert!(expand_artifact_name_template("{name", &tokens).is_err());
+    }
+
+    #[test]
+    fn file_name_slugs() {
+        assert_eq!(file_name_slug("My Cool App"), "My Cool App");
+        assert_eq!(file_name_slug("Café Über"), "Café Über");
+        assert_eq!(file_name_slug("AC/DC: Live?"), "AC-DC- Live-");
+        assert_eq!(file_name_slug("Trailing dots..."), "Trailing dots");
+        assert_eq!(file_name_slug("///"), "---");
+        assert_eq!(file_name_slug(" . "), "_");
+    }
+
+    #[test]
+    fn denied_warnings() {
+        let log = WarningLog::new();
+        log.record("no icon").unwrap();
+        assert!(log.check().is_ok());
+        log.deny(DenyWarnings::End);
+        log.record("zero-match glob").unwrap();
+        let error = log.check().unwrap_err().to_string();
+        assert!(error.starts_with("2 warnings were emitted"));
+        assert!(error.contains("no icon") && error.contains("zero-match glob"));
+
+        let log = WarningLog::new();
+        log.deny(DenyWarnings::Immediate);
+        log.deny(DenyWarnings::End);
+        assert!(log.record("suspicious identifier").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signing_retries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let marker = tmp.path().join("attempted");
+        // Fails on the first attempt only.
+        let mut command = std::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg("test -e \"$1\" || { touch \"$1\"; exit 1; }")
+            .arg("sh")
+            .arg(&marker);
+        let settings = Settings::for_test("timestamp_retry_delay = 0");
+        assert!(run_signing_tool(&settings, &mut command, "sh").is_err());
+        std::fs::remove_file(&marker).unwrap();
+        let settings = Settings::for_test("timestamp_retries = 1\ntimestamp_retry_delay = 0");
+        assert!(run_signing_tool(&settings, &mut command, "sh").is_ok());
+    }
+
+    #[test]
+    fn man_page_relpaths() {
+        assert_eq!(
+            man_page_relpath(Path::new("docs/foo.1")).unwrap(),
+            Path::new("man1/foo.1")
+        );
+        assert_eq!(
+            man_page_relpath(Path::new("docs/Foo::Bar.3pm.gz")).unwrap(),
+            Path::new("man3/Foo::Bar.3pm.gz")
+        );
+        assert_eq!(
+            man_page_relpath(Path::new("foo.conf.5")).unwrap(),
+            Path::new("man5/foo.conf.5")
+        );
+        assert!(man_page_relpath(Path::new("docs/foo.md")).is_err());
+        assert!(man_page_relpath(Path::new("docs/foo")).is_err());
+    }
 }
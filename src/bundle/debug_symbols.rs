@@ -0,0 +1,360 @@
+// With `debug_symbols = "strip"` or `"split"`, the bundles contain a copy of
+// the binary stripped of its debug information.  With `"split"`, the debug
+// information is also kept in a file of its own, which is shipped next to the
+// bundles:
+//
+// bundle/symbols/
+//     stripped/foobar     # The stripped binary, which the bundles contain
+//     foobar.debug        # ELF targets; packaged as `foobar-dbgsym_*.deb` and
+//                         # `foobar-debuginfo-*.rpm`, which install it in
+//                         # `/usr/lib/debug`
+//     foobar.dSYM         # Apple targets; copied next to the OSX app bundle,
+//                         # and archived as `foobar-1.2.3-dSYM.zip` for crash
+//                         # reporters
+//     foobar.pdb          # Windows targets; archived as `foobar-1.2.3-pdb.zip`
+//
+// MSVC keeps the debug information of Windows binaries in the PDB already, so
+// there is nothing to strip from them.
+
+use super::build_info::build_time;
+use super::common;
+use super::settings::{DebugSymbols, Settings};
+use super::zip::ZipWriter;
+use anyhow::Context;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The format of the binary, which decides how it is stripped.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BinaryFormat {
+    Elf,
+    MachO,
+    Pe,
+}
+
+impl BinaryFormat {
+    fn of(settings: &Settings) -> BinaryFormat {
+        let (windows, apple) = match settings.target_triple() {
+            Some(triple) => (triple.contains("windows"), triple.contains("apple")),
+            None => (cfg!(windows), cfg!(target_vendor = "apple")),
+        };
+        if windows {
+            BinaryFormat::Pe
+        } else if apple {
+            BinaryFormat::MachO
+        } else {
+            BinaryFormat::Elf
+        }
+    }
+}
+
+fn symbols_dir(settings: &Settings) -> PathBuf {
+    settings.project_out_directory().join("bundle/symbols")
+}
+
+/// Returns the path of the debug information split from the binary.
+fn split_path(settings: &Settings, format: BinaryFormat) -> PathBuf {
+    let name = settings.binary_name();
+    symbols_dir(settings).join(match format {
+        BinaryFormat::Elf => format!("{name}.debug"),
+        BinaryFormat::MachO => format!("{name}.dSYM"),
+        BinaryFormat::Pe => format!("{}.pdb", pdb_stem(settings)),
+    })
+}
+
+/// Returns the name cargo gives the PDB of the binary, without its extension:
+/// the crate name, whose dashes become underscores.
+fn pdb_stem(settings: &Settings) -> String {
+    let stem = settings.binary_path().file_stem().unwrap_or_default();
+    stem.to_string_lossy().replace('-', "_")
+}
+
+/// Strips the binary as the `debug_symbols` setting asks, splitting its debug
+/// information off first if needed, and makes `settings` refer to the
+/// stripped copy.
+pub fn strip_binary(settings: &mut Settings) -> crate::Result<()> {
+    let mode = settings.debug_symbols();
+    if mode == DebugSymbols::Keep {
+        return Ok(());
+    }
+    let format = BinaryFormat::of(settings);
+    let binary = settings.binary_path().to_path_buf();
+    let split = split_path(settings, format);
+    if format == BinaryFormat::Pe {
+        if mode == DebugSymbols::Split {
+            let pdb = binary.with_file_name(split.file_name().unwrap());
+            if pdb.is_file() {
+                common::copy_file(&pdb, &split)?;
+            } else {
+                common::print_warning(&format!("No PDB found at {pdb:?}"))?;
+            }
+        }
+        return Ok(());
+    }
+    let file_name = binary.file_name().unwrap();
+    common::print_stripping(&file_name.to_string_lossy())?;
+    let stripped = symbols_dir(settings).join("stripped").join(file_name);
+    if stripped.exists() {
+        fs::remove_file(&stripped)?;
+    }
+    if split.is_dir() {
+        fs::remove_dir_all(&split)?;
+    }
+    fs::create_dir_all(stripped.parent().unwrap())?;
+    for mut command in strip_commands(format, mode, &binary, &stripped, &split) {
+        let name = command.get_program().to_string_lossy().into_owned();
+        common::run_tool(&mut command, &name)
+            .with_context(|| format!("Failed to strip {binary:?}"))?;
+    }
+    settings.set_binary_path(stripped);
+    Ok(())
+}
+
+/// Returns the commands that strip the ELF or Mach-O `binary` into
+/// `stripped`, splitting the debug information into `split` first if `mode`
+/// asks for it.
+fn strip_commands(
+    format: BinaryFormat,
+    mode: DebugSymbols,
+    binary: &Path,
+    stripped: &Path,
+    split: &Path,
+) -> Vec<Command> {
+    let mut commands = Vec::new();
+    match format {
+        BinaryFormat::Elf => {
+            // `OBJCOPY` may name the objcopy of a cross toolchain.
+            let objcopy = std::env::var_os("OBJCOPY").unwrap_or_else(|| OsString::from("objcopy"));
+            if mode == DebugSymbols::Split {
+                let mut command = Command::new(&objcopy);
+                command.arg("--only-keep-debug").arg(binary).arg(split);
+                commands.push(command);
+            }
+            let mut command = Command::new(&objcopy);
+            command.arg("--strip-debug");
+            if mode == DebugSymbols::Split {
+                let mut debuglink = OsString::from("--add-gnu-debuglink=");
+                debuglink.push(split);
+                command.arg(debuglink);
+            }
+            command.arg(binary).arg(stripped);
+            commands.push(command);
+        }
+        BinaryFormat::MachO => {
+            if mode == DebugSymbols::Split {
+                let mut command = Command::new("dsymutil");
+                command.arg(binary).arg("-o").arg(split);
+                commands.push(command);
+            }
+            let mut command = Command::new("strip");
+            command.arg("-S").arg("-o").arg(stripped).arg(binary);
+            commands.push(command);
+        }
+        BinaryFormat::Pe => {}
+    }
+    commands
+}
+
+/// Returns the debug information split from the binary, if it was.
+pub fn split_debug_info(settings: &Settings) -> Option<PathBuf> {
+    if settings.debug_symbols() != DebugSymbols::Split {
+        return None;
+    }
+    let split = split_path(settings, BinaryFormat::of(settings));
+    split.exists().then_some(split)
+}
+
+/// Copies the `.dSYM` split from the binary next to the app bundle at
+/// `app_bundle_path`, as `Foo.app.dSYM`.  Returns the path of the copy, if
+/// there is one.
+pub fn copy_dsym(settings: &Settings, app_bundle_path: &Path) -> crate::Result<Option<PathBuf>> {
+    let Some(dsym) = split_debug_info(settings) else {
+        return Ok(None);
+    };
+    let mut dest = app_bundle_path.as_os_str().to_owned();
+    dest.push(".dSYM");
+    let dest = PathBuf::from(dest);
+    if dest.exists() {
+        fs::remove_dir_all(&dest)?;
+    }
+    common::copy_dir(&dsym, &dest)?;
+    Ok(Some(dest))
+}
+
+/// Archives the PDB of a Windows binary as `foobar-1.2.3-pdb.zip`, next to
+/// the split debug information.  Returns the path of the archive, if there is
+/// a PDB.
+pub fn archive_pdb(settings: &Settings) -> crate::Result<Option<PathBuf>> {
+    if BinaryFormat::of(settings) != BinaryFormat::Pe {
+        return Ok(None);
+    }
+    let Some(pdb) = split_debug_info(settings) else {
+        return Ok(None);
+    };
+    let zip_name = format!(
+        "{}-{}-pdb.zip",
+        settings.binary_name(),
+        settings.version_string()
+    );
+    common::print_bundling(&zip_name)?;
+    let zip_path = symbols_dir(settings).join(&zip_name);
+    let mut zip = ZipWriter::new(common::create_file(&zip_path)?, build_time()?);
+    let data = fs::read(&pdb).with_context(|| format!("Failed to read {pdb:?}"))?;
+    zip.add_file(&pdb.file_name().unwrap().to_string_lossy(), &data, false)?;
+    zip.finish()?;
+    Ok(Some(zip_path))
+}
+
+/// Archives the `.dSYM` split from an Apple binary as
+/// `foobar-1.2.3-dSYM.zip`, next to it, as the upload tools of crash reporters
+/// expect it: holding the `.dSYM` of the app, `Foo.app.dSYM`.  Returns the
+/// path of the archive, if there is a `.dSYM`.
+pub fn archive_dsym(settings: &Settings) -> crate::Result<Option<PathBuf>> {
+    if BinaryFormat::of(settings) != BinaryFormat::MachO {
+        return Ok(None);
+    }
+    let Some(dsym) = split_debug_info(settings) else {
+        return Ok(None);
+    };
+    let zip_name = format!(
+        "{}-{}-dSYM.zip",
+        settings.binary_name(),
+        settings.version_string()
+    );
+    common::print_bundling(&zip_name)?;
+    let zip_path = symbols_dir(settings).join(&zip_name);
+    let dsym_name = format!("{}.app.dSYM", settings.bundle_file_name());
+    zip_dsym(&dsym, &dsym_name, &zip_path)
+        .with_context(|| format!("Failed to archive {dsym:?}"))?;
+    Ok(Some(zip_path))
+}
+
+/// Zips the `.dSYM` directory at `dsym` into `zip_path`, as `dsym_name`.
+fn zip_dsym(dsym: &Path, dsym_name: &str, zip_path: &Path) -> crate::Result<()> {
+    let mut zip = ZipWriter::new(common::create_file(zip_path)?, build_time()?);
+    zip.add_directory(dsym_name)?;
+    for entry in walkdir::WalkDir::new(dsym).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path().strip_prefix(dsym).unwrap();
+        let name = format!("{dsym_name}/{}", path.to_string_lossy().replace('\\', "/"));
+        if entry.file_type().is_dir() {
+            zip.add_directory(&name)?;
+        } else {
+            let data = fs::read(entry.path())
+                .with_context(|| format!("Failed to read {:?}", entry.path()))?;
+            zip.add_file(&name, &data, false)?;
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn elf_strip_commands() {
+        let commands = strip_commands(
+            BinaryFormat::Elf,
+            DebugSymbols::Split,
+            Path::new("foo"),
+            Path::new("stripped/foo"),
+            Path::new("foo.debug"),
+        );
+        let objcopy = std::env::var("OBJCOPY").unwrap_or_else(|_| "objcopy".to_string());
+        assert_eq!(
+            commands.iter().map(args).collect::<Vec<_>>(),
+            [
+                vec![&objcopy, "--only-keep-debug", "foo", "foo.debug"],
+                vec![
+                    &objcopy,
+                    "--strip-debug",
+                    "--add-gnu-debuglink=foo.debug",
+                    "foo",
+                    "stripped/foo"
+                ],
+            ]
+        );
+        let commands = strip_commands(
+            BinaryFormat::Elf,
+            DebugSymbols::Strip,
+            Path::new("foo"),
+            Path::new("stripped/foo"),
+            Path::new("foo.debug"),
+        );
+        assert_eq!(commands.len(), 1);
+        assert_eq!(
+            args(&commands[0])[1..],
+            ["--strip-debug", "foo", "stripped/foo"]
+        );
+    }
+
+    #[test]
+    fn macho_strip_commands() {
+        let commands = strip_commands(
+            BinaryFormat::MachO,
+            DebugSymbols::Split,
+            Path::new("foo"),
+            Path::new("stripped/foo"),
+            Path::new("foo.dSYM"),
+        );
+        assert_eq!(
+            commands.iter().map(args).collect::<Vec<_>>(),
+            [
+                vec!["dsymutil", "foo", "-o", "foo.dSYM"],
+                vec!["strip", "-S", "-o", "stripped/foo", "foo"],
+            ]
+        );
+    }
+
+    #[test]
+    fn dsym_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dsym = tmp.path().join("foo.dSYM");
+        fs::create_dir_all(dsym.join("Contents/Resources/DWARF")).unwrap();
+        fs::write(dsym.join("Contents/Info.plist"), "<plist/>").unwrap();
+        fs::write(dsym.join("Contents/Resources/DWARF/foo"), "dwarf").unwrap();
+        let zip_path = tmp.path().join("foo-1.0.0-dSYM.zip");
+        zip_dsym(&dsym, "Foo.app.dSYM", &zip_path).unwrap();
+        let zip = String::from_utf8_lossy(&fs::read(&zip_path).unwrap()).into_owned();
+        for name in [
+            "Foo.app.dSYM/",
+            "Foo.app.dSYM/Contents/",
+            "Foo.app.dSYM/Contents/Info.plist",
+            "Foo.app.dSYM/Contents/Resources/DWARF/foo",
+        ] {
+            assert!(zip.contains(name), "{name}");
+        }
+        assert!(!zip.contains("foo.dSYM"));
+    }
+
+    #[test]
+    fn split_paths() {
+        let settings = Settings::for_test("debug_symbols = \"split\"");
+        let symbols = Path::new("/nonexistent/example/target/debug/bundle/symbols");
+        assert_eq!(
+            split_path(&settings, BinaryFormat::Elf),
+            symbols.join("example.debug")
+        );
+        assert_eq!(
+            split_path(&settings, BinaryFormat::MachO),
+            symbols.join("example.dSYM")
+        );
+        assert_eq!(
+            split_path(&settings, BinaryFormat::Pe),
+            symbols.join("example.pdb")
+        );
+        assert_eq!(split_debug_info(&settings), None);
+    }
+}
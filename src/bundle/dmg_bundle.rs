@@ -0,0 +1,367 @@
+// A DMG is a compressed disk image wrapping the OSX app bundle.  We stage the
+// volume contents in a directory and let `hdiutil` turn it into an image:
+//
+// bundle/dmg/foobar_1.2.3_x86_64/
+//     foobar.app        # A copy of the OSX app bundle
+//     Applications      # A symlink to /Applications, for drag-and-drop installs
+//
+// The image is written to `bundle/dmg/foobar_1.2.3_x86_64.dmg`, and its volume
+// is named after the bundle.  When a signing identity is set, the image is
+// signed too, and it is the image rather than the app that gets notarized.
+//
+// With the `dmg_*` appearance settings, the staging directory also holds the
+// background image and the volume icon:
+//
+// bundle/dmg/foobar_1.2.3_x86_64/
+//     .background/background.png
+//     .VolumeIcon.icns
+//
+// The image is then first created writable (`foobar_1.2.3_x86_64.rw.dmg`) and
+// mounted, so that the Finder can lay out its window, which it records in the
+// `.DS_Store` of the volume, before it is compressed into the final image.
+
+use super::common;
+use super::notarize::notarize;
+use super::osx_bundle::{self, SigningKey, rcodesign_command};
+use super::settings::OsxSigningTool;
+use crate::Settings;
+use anyhow::Context;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let app_bundle_path = &osx_bundle::build_app_bundle(settings)?;
+
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    let package_name = format!("{package_base_name}.dmg");
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/dmg");
+    let staging_dir = base_dir.join(&package_base_name);
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    let app_bundle_name = app_bundle_path.file_name().unwrap();
+    common::copy_dir(app_bundle_path, &staging_dir.join(app_bundle_name))
+        .with_context(|| format!("Failed to copy {app_bundle_path:?}"))?;
+    common::symlink_dir(
+        Path::new("/Applications"),
+        &staging_dir.join("Applications"),
+    )
+    .with_context(|| "Failed to create Applications symlink")?;
+
+    if settings.dmg_customized() {
+        let background = stage_appearance(settings, &staging_dir)?;
+        let rw_path = base_dir.join(format!("{package_base_name}.rw.dmg"));
+        common::run_tool(
+            &mut hdiutil_command(settings.bundle_name(), &staging_dir, &rw_path, "UDRW"),
+            "hdiutil",
+        )?;
+        let app_bundle_name = app_bundle_name.to_string_lossy();
+        customize_image(settings, &rw_path, &app_bundle_name, background.as_deref())
+            .with_context(|| format!("Failed to lay out {package_name}"))?;
+        common::run_tool(
+            Command::new("hdiutil")
+                .arg("convert")
+                .arg(&rw_path)
+                .args(["-format", "UDZO", "-ov", "-o"])
+                .arg(&package_path),
+            "hdiutil",
+        )?;
+        fs::remove_file(&rw_path).with_context(|| format!("Failed to remove {rw_path:?}"))?;
+    } else {
+        common::run_tool(
+            &mut hdiutil_command(settings.bundle_name(), &staging_dir, &package_path, "UDZO"),
+            "hdiutil",
+        )?;
+    }
+    fs::remove_dir_all(&staging_dir)
+        .with_context(|| format!("Failed to remove {staging_dir:?}"))?;
+    if let Some(identity) = settings.osx_signing_identity() {
+        common::print_signing(&package_name)?;
+        match settings.osx_signing_tool() {
+            OsxSigningTool::Codesign => {
+                common::run_signing_tool(
+                    settings,
+                    &mut codesign_command(identity, &package_path, settings.osx_timestamp_url()),
+                    "codesign",
+                )?;
+            }
+            OsxSigningTool::Rcodesign => {
+                let key = SigningKey::from_settings(settings, identity)?;
+                common::run_signing_tool(
+                    settings,
+                    &mut rcodesign_command(
+                        key.as_ref(),
+                        &package_path,
+                        None,
+                        false,
+                        settings.osx_timestamp_url(),
+                    ),
+                    "rcodesign",
+                )?;
+            }
+        }
+        common::record_signed(&package_path);
+    }
+    if settings.notarize() {
+        notarize(settings, &package_path)?;
+    }
+    Ok(vec![package_path])
+}
+
+/// Builds the `codesign` command that signs the disk image itself.  The app
+/// inside it has already been signed by the OSX bundler.
+fn codesign_command(identity: &str, dmg_path: &Path, timestamp_url: Option<&str>) -> Command {
+    let mut command = Command::new("codesign");
+    command.arg("--force").arg("--sign").arg(identity);
+    match timestamp_url {
+        _ if identity == "-" => {}
+        Some(url) => {
+            command.arg(format!("--timestamp={url}"));
+        }
+        None => {
+            command.arg("--timestamp");
+        }
+    }
+    command.arg(dmg_path);
+    command
+}
+
+/// Copies the background image and the volume icon into the staging
+/// directory, returning the file name of the background image, if any.
+fn stage_appearance(settings: &Settings, staging_dir: &Path) -> crate::Result<Option<String>> {
+    if let Some(icon) = settings.dmg_volume_icon() {
+        common::copy_file(&icon, &staging_dir.join(".VolumeIcon.icns"))
+            .with_context(|| format!("Failed to copy volume icon {icon:?}"))?;
+    }
+    let Some(background) = settings.dmg_background() else {
+        return Ok(None);
+    };
+    let extension = background
+        .extension()
+        .map_or(String::new(), |ext| format!(".{}", ext.to_string_lossy()));
+    let file_name = format!("background{extension}");
+    common::copy_file(
+        &background,
+        &staging_dir.join(".background").join(&file_name),
+    )
+    .with_context(|| format!("Failed to copy background image {background:?}"))?;
+    Ok(Some(file_name))
+}
+
+/// Mounts the writable image at `rw_path`, has the Finder lay out its window,
+/// and unmounts it.
+fn customize_image(
+    settings: &Settings,
+    rw_path: &Path,
+    app_bundle_name: &str,
+    background: Option<&str>,
+) -> crate::Result<()> {
+    let output = common::run_tool(
+        Command::new("hdiutil")
+            .args(["attach", "-readwrite", "-noverify", "-noautoopen"])
+            .arg(rw_path),
+        "hdiutil",
+    )?;
+    let Some(mount_point) = mount_point(&String::from_utf8_lossy(&output)) else {
+        anyhow::bail!("Could not find where hdiutil mounted {rw_path:?}");
+    };
+    let result = lay_out_volume(settings, &mount_point, app_bundle_name, background);
+    common::run_tool(
+        Command::new("hdiutil")
+            .args(["detach", "-force"])
+            .arg(&mount_point),
+        "hdiutil",
+    )?;
+    result
+}
+
+/// Returns the mount point of the volume listed in the output of
+/// `hdiutil attach`, whose lines hold the device, the partition type and, for
+/// a mounted volume, the mount point, separated by tabs.
+fn mount_point(attach_output: &str) -> Option<PathBuf> {
+    attach_output
+        .lines()
+        .filter_map(|line| line.split('\t').nth(2))
+        .map(str::trim)
+        .find(|mount_point| !mount_point.is_empty())
+        .map(PathBuf::from)
+}
+
+fn lay_out_volume(
+    settings: &Settings,
+    mount_point: &Path,
+    app_bundle_name: &str,
+    background: Option<&str>,
+) -> crate::Result<()> {
+    if settings.dmg_volume_icon().is_some() {
+        common::run_tool(
+            Command::new("SetFile").args(["-a", "C"]).arg(mount_point),
+            "SetFile",
+        )?;
+    }
+    let disk_name = mount_point.file_name().unwrap().to_string_lossy();
+    let script = finder_script(settings, &disk_name, app_bundle_name, background);
+    common::run_tool(Command::new("osascript").arg("-e").arg(script), "osascript")?;
+    common::run_tool(&mut Command::new("sync"), "sync")?;
+    Ok(())
+}
+
+/// Returns the AppleScript that has the Finder lay out the window of the disk
+/// named `disk_name`.
+fn finder_script(
+    settings: &Settings,
+    disk_name: &str,
+    app_bundle_name: &str,
+    background: Option<&str>,
+) -> String {
+    let [width, height] = settings.dmg_window_size();
+    let [app_x, app_y] = settings.dmg_app_position();
+    let [applications_x, applications_y] = settings.dmg_applications_position();
+    let mut script = format!(
+        "tell application \"Finder\"\n  \
+           tell disk \"{}\"\n    \
+             open\n    \
+             set current view of container window to icon view\n    \
+             set toolbar visible of container window to false\n    \
+             set statusbar visible of container window to false\n    \
+             set the bounds of container window to {{100, 100, {}, {}}}\n    \
+             set viewOptions to the icon view options of container window\n    \
+             set arrangement of viewOptions to not arranged\n    \
+             set icon size of viewOptions to {}\n",
+        applescript_escape(disk_name),
+        100 + width,
+        100 + height,
+        settings.dmg_icon_size(),
+    );
+    if let Some(file_name) = background {
+        script.push_str(&format!(
+            "    set background picture of viewOptions to file \".background:{}\"\n",
+            applescript_escape(file_name)
+        ));
+    }
+    script.push_str(&format!(
+        "    set position of item \"{}\" of container window to {{{app_x}, {app_y}}}\n    \
+             set position of item \"Applications\" of container window to \
+             {{{applications_x}, {applications_y}}}\n    \
+             close\n    \
+             open\n    \
+             update without registering applications\n    \
+             delay 2\n    \
+             close\n  \
+           end tell\n\
+         end tell\n",
+        applescript_escape(app_bundle_name),
+    ));
+    script
+}
+
+fn applescript_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Builds the `hdiutil` command that creates an image of the given `format`
+/// (e.g. `UDZO` for a compressed one) at `dmg_path` from the contents of
+/// `src_folder`.
+fn hdiutil_command(volume_name: &str, src_folder: &Path, dmg_path: &Path, format: &str) -> Command {
+    let mut command = Command::new("hdiutil");
+    command
+        .arg("create")
+        .arg("-volname")
+        .arg(volume_name)
+        .arg("-srcfolder")
+        .arg(src_folder)
+        .arg("-ov")
+        .arg("-format")
+        .arg(format)
+        .arg(dmg_path);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn hdiutil_command_args() {
+        let command = hdiutil_command(
+            "My App",
+            Path::new("bundle/dmg/my_app"),
+            Path::new("bundle/dmg/my_app.dmg"),
+            "UDZO",
+        );
+        assert_eq!(command.get_program(), "hdiutil");
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "create",
+                "-volname",
+                "My App",
+                "-srcfolder",
+                "bundle/dmg/my_app",
+                "-ov",
+                "-format",
+                "UDZO",
+                "bundle/dmg/my_app.dmg",
+            ]
+        );
+    }
+
+    #[test]
+    fn attached_mount_point() {
+        let output = "/dev/disk4          \tGUID_partition_scheme          \t\n\
+                      /dev/disk4s1        \tApple_HFS                      \t/Volumes/My App 1\n";
+        assert_eq!(mount_point(output).unwrap(), Path::new("/Volumes/My App 1"));
+        assert_eq!(mount_point("/dev/disk4\tGUID_partition_scheme\t\n"), None);
+    }
+
+    #[test]
+    fn finder_layout_script() {
+        let settings = Settings::for_test(
+            r#"
+            dmg_background = "dmg/background.png"
+            dmg_window_size = [600, 300]
+            dmg_app_position = [150, 140]
+            "#,
+        );
+        assert!(settings.dmg_customized());
+        assert!(!Settings::for_test("").dmg_customized());
+        assert_eq!(
+            settings.dmg_background().unwrap(),
+            Path::new("/nonexistent/example/dmg/background.png")
+        );
+        let script = finder_script(
+            &settings,
+            "My \"App\"",
+            "My App.app",
+            Some("background.png"),
+        );
+        assert!(
+            script.starts_with("tell application \"Finder\"\n  tell disk \"My \\\"App\\\"\"\n")
+        );
+        assert!(
+            script.contains("    set the bounds of container window to {100, 100, 700, 400}\n")
+        );
+        assert!(script.contains("    set icon size of viewOptions to 128\n"));
+        assert!(script.contains(
+            "    set background picture of viewOptions to file \".background:background.png\"\n"
+        ));
+        assert!(script.contains(
+            "    set position of item \"My App.app\" of container window to {150, 140}\n    \
+             set position of item \"Applications\" of container window to {480, 170}\n"
+        ));
+        assert!(script.ends_with("    close\n  end tell\nend tell\n"));
+    }
+}
@@ -0,0 +1,141 @@
+// `cargo bundle list` prints the bundle formats that `--format` accepts, and
+// whether each can be built on this host, judging by whether the external
+// tools it requires are on the `PATH` (or, for `android`, in the SDK):
+//
+// FORMAT    STATUS       REQUIRED TOOLS
+// android   available    aapt (found), zipalign (found), apksigner (found)
+// apk       available
+// appimage  unavailable  mksquashfs (missing)
+// ...
+
+use super::{PackageType, android_bundle};
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Prints the bundle formats to stdout, with the status of the tools each
+/// requires.
+pub fn list_formats() -> crate::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(format_list(tool_found).as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// Lists the formats sorted by name, checking for each tool with `found`.
+fn format_list(found: impl Fn(PackageType, &str) -> bool) -> String {
+    let mut names = PackageType::all().to_vec();
+    names.sort_unstable();
+    let mut list = String::from("FORMAT    STATUS       REQUIRED TOOLS\n");
+    for name in names {
+        let package_type = PackageType::from_short_name(name).unwrap();
+        let tools: Vec<_> = package_type
+            .required_tools()
+            .iter()
+            .map(|&tool| (tool, found(package_type, tool)))
+            .collect();
+        let status = if tools.iter().all(|&(_, found)| found) {
+            "available"
+        } else {
+            "unavailable"
+        };
+        let tools = tools
+            .iter()
+            .map(|&(tool, found)| format!("{tool} ({})", if found { "found" } else { "missing" }))
+            .collect::<Vec<_>>()
+            .join(", ");
+        list.push_str(format!("{name:<9} {status:<12} {tools}").trim_end());
+        list.push('\n');
+    }
+    list
+}
+
+// Returns true if `tool`, which the bundler for `package_type` requires, can
+// be found.
+fn tool_found(package_type: PackageType, tool: &str) -> bool {
+    match package_type {
+        PackageType::Android => {
+            android_bundle::sdk_build_tool(tool).is_some_and(|path| is_executable(&path))
+        }
+        _ => tool_on_path(tool),
+    }
+}
+
+/// Returns true if an executable named `tool` is in one of the directories on
+/// the `PATH`.
+fn tool_on_path(tool: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| {
+        executable_names(tool)
+            .iter()
+            .any(|name| is_executable(&dir.join(name)))
+    })
+}
+
+// Windows finds `tool.exe` (and so on) for `tool`.
+fn executable_names(tool: &str) -> Vec<OsString> {
+    let mut names = vec![OsString::from(tool)];
+    if cfg!(windows) {
+        let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+        names.extend(
+            extensions
+                .split(';')
+                .filter(|extension| !extension.is_empty())
+                .map(|extension| OsString::from(format!("{tool}{extension}"))),
+        );
+    }
+    names
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_formats() {
+        let list = format_list(|_, tool| tool != "mksquashfs");
+        let lines: Vec<_> = list.lines().collect();
+        assert_eq!(lines.len(), PackageType::all().len() + 1);
+        assert_eq!(lines[0], "FORMAT    STATUS       REQUIRED TOOLS");
+        assert_eq!(
+            lines[1],
+            "android   available    aapt (found), zipalign (found), apksigner (found)"
+        );
+        assert_eq!(lines[2], "apk       available");
+        assert_eq!(lines[3], "appimage  unavailable  mksquashfs (missing)");
+        assert!(lines.contains(&"portable  unavailable  mksquashfs (missing)"));
+        assert!(lines.contains(&"archive   available"));
+        assert!(lines.contains(&"flatpak   available    flatpak (found), flatpak-builder (found)"));
+    }
+
+    #[test]
+    fn executable_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tool = tmp.path().join("sometool");
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert!(!is_executable(&tool));
+            std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        assert!(is_executable(&tool));
+        assert!(!is_executable(tmp.path()));
+        assert!(!is_executable(&tmp.path().join("othertool")));
+    }
+}
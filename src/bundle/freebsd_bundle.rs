@@ -0,0 +1,256 @@
+// The structure of a FreeBSD pkg(8) package looks something like this:
+//
+// foobar-1.2.3.pkg        # Actually a gzipped tar archive
+//     +COMPACT_MANIFEST       # Package metadata, as JSON
+//     +MANIFEST               # The same metadata, plus a checksum of each file
+//     /usr/local/bin/foobar                           # Binary executable file
+//     /usr/local/share/applications/foobar.desktop    # Desktop file
+//     /usr/local/share/icons/hicolor/...              # Icon files
+//     /usr/local/lib/foobar/...                       # Other resource files
+//
+// The files are staged exactly as in the deb bundler, and then moved under the
+// `/usr/local` prefix that FreeBSD installs packages into.  Paths in the
+// archive are absolute, as pkg(8) expects.
+
+use super::build_info::build_time;
+use super::common;
+use super::linux::common::{collect_entries, file_mode, generate_data_files, total_dir_size};
+use crate::Settings;
+use anyhow::Context;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PREFIX: &str = "/usr/local";
+/// The category packages are put in, as part of their origin.
+const CATEGORY: &str = "misc";
+
+#[derive(Serialize)]
+struct Manifest {
+    name: String,
+    origin: String,
+    version: String,
+    comment: String,
+    desc: String,
+    maintainer: String,
+    www: String,
+    abi: String,
+    prefix: &'static str,
+    flatsize: u64,
+    licenselogic: &'static str,
+    licenses: Vec<String>,
+    categories: Vec<&'static str>,
+    /// Maps each installed file to its checksum.  Omitted from the compact
+    /// manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<BTreeMap<String, String>>,
+}
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let name = super::linux::deb_bundle::package_name(settings);
+    let version = pkg_version(&settings.version_string().to_string());
+    let package_base_name = format!("{name}-{version}");
+    let package_name = format!("{package_base_name}.pkg");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/freebsd");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+    let mtime = build_time()?.timestamp();
+
+    let data_dir = package_dir.join("data");
+    generate_data_files(settings, &data_dir)?;
+    let files: Vec<(PathBuf, String)> = collect_entries(&data_dir)?
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| {
+            let installed_path = installed_path(&entry.path);
+            (entry.path, installed_path)
+        })
+        .collect();
+    let mut checksums = BTreeMap::new();
+    for (path, installed_path) in &files {
+        let checksum = common::sha256_hex(&data_dir.join(path))?;
+        checksums.insert(installed_path.clone(), format!("1${checksum}"));
+    }
+
+    let abi = abi(settings)?;
+    let mut manifest = generate_manifest(settings, &name, &version, &abi);
+    manifest.flatsize = total_dir_size(&data_dir)?;
+    let compact_manifest = serde_json::to_string(&manifest)?;
+    manifest.files = Some(checksums);
+    let full_manifest = serde_json::to_string(&manifest)?;
+
+    let mut tar_builder =
+        tar::Builder::new(common::gzip_encoder(common::create_file(&package_path)?)?);
+    tar_builder.preserve_absolute(true);
+    for (file_name, contents) in [
+        ("+COMPACT_MANIFEST", compact_manifest),
+        ("+MANIFEST", full_manifest),
+    ] {
+        let mut header = tar_header(0o644, mtime);
+        header.set_size(contents.len() as u64);
+        tar_builder.append_data(&mut header, file_name, contents.as_bytes())?;
+    }
+    for (path, installed_path) in &files {
+        let src_path = data_dir.join(path);
+        let mut header = tar_header(file_mode(&src_path)?, mtime);
+        header.set_size(fs::metadata(&src_path)?.len());
+        let src_file =
+            File::open(&src_path).with_context(|| format!("Failed to open {src_path:?}"))?;
+        tar_builder.append_data(&mut header, installed_path, src_file)?;
+    }
+    tar_builder.into_inner()?.finish().into_result()?.flush()?;
+    Ok(vec![package_path])
+}
+
+/// Returns the absolute path that a file staged at `path` (e.g.
+/// `usr/bin/foobar`) is installed to (e.g. `/usr/local/bin/foobar`).
+fn installed_path(path: &Path) -> String {
+    let relative = path.strip_prefix("usr").unwrap_or(path);
+    format!("{PREFIX}/{}", relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Converts a version string to a valid pkg(8) version, which may not contain
+/// dashes (e.g. `1.2.3-beta.1` becomes `1.2.3.beta.1`).  Build metadata is
+/// dropped.
+fn pkg_version(version: &str) -> String {
+    version.split('+').next().unwrap().replace(['-', '_'], ".")
+}
+
+/// Maps a Rust target architecture (e.g. `"x86_64"`) to the corresponding
+/// FreeBSD architecture name (e.g. `"amd64"`).
+fn arch_name(binary_arch: &str) -> &str {
+    match binary_arch {
+        "x86_64" => "amd64",
+        "x86" => "i386",
+        "arm" => "armv7",
+        other => other,
+    }
+}
+
+/// Returns the ABI string of the package (e.g. `"FreeBSD:14:amd64"`).  The
+/// FreeBSD major version comes from the `freebsd_version` setting or, when
+/// bundling on FreeBSD, from the running system.
+fn abi(settings: &Settings) -> crate::Result<String> {
+    let version = match settings.freebsd_version() {
+        Some(version) => version.to_string(),
+        None if cfg!(target_os = "freebsd") => {
+            let release = common::run_tool(Command::new("uname").arg("-r"), "uname")?;
+            let release = String::from_utf8_lossy(&release);
+            release
+                .split('.')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        }
+        None => anyhow::bail!(
+            "The `freebsd_version` setting is required to bundle FreeBSD packages on other systems"
+        ),
+    };
+    Ok(format!(
+        "FreeBSD:{version}:{}",
+        arch_name(settings.binary_arch())
+    ))
+}
+
+fn tar_header(mode: u32, mtime: i64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(mode);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_username("root").unwrap();
+    header.set_groupname("wheel").unwrap();
+    header.set_mtime(mtime as u64);
+    header
+}
+
+/// Generates the package's manifest, without its size or file checksums.
+fn generate_manifest(settings: &Settings, name: &str, version: &str, abi: &str) -> Manifest {
+    let mut comment = settings.short_description().trim();
+    if comment.is_empty() {
+        comment = settings.bundle_name();
+    }
+    let desc = settings
+        .long_description()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or(comment);
+    let licenses: Vec<String> = settings
+        .license()
+        .map(|license| {
+            license
+                .split(" OR ")
+                .flat_map(|l| l.split('/'))
+                .map(|l| l.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    Manifest {
+        name: name.to_string(),
+        origin: format!("{CATEGORY}/{name}"),
+        version: version.to_string(),
+        comment: comment.to_string(),
+        desc: desc.to_string(),
+        maintainer: settings
+            .authors_comma_separated()
+            .filter(|a| !a.trim().is_empty())
+            .unwrap_or_else(|| "ports@FreeBSD.org".to_string()),
+        www: settings.homepage_url().to_string(),
+        abi: abi.to_string(),
+        prefix: PREFIX,
+        flatsize: 0,
+        licenselogic: if licenses.len() > 1 { "or" } else { "single" },
+        licenses,
+        categories: vec![CATEGORY],
+        files: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkg_versions() {
+        assert_eq!(pkg_version("1.2.3"), "1.2.3");
+        assert_eq!(pkg_version("1.2.3-beta.1+build.5"), "1.2.3.beta.1");
+    }
+
+    #[test]
+    fn installed_paths() {
+        assert_eq!(
+            installed_path(Path::new("usr/bin/example")),
+            "/usr/local/bin/example"
+        );
+        assert_eq!(
+            installed_path(Path::new("usr/share/applications/example.desktop")),
+            "/usr/local/share/applications/example.desktop"
+        );
+    }
+
+    #[test]
+    fn manifest() {
+        let settings = Settings::for_test(
+            "short_description = \"An example\"\n\
+             freebsd_version = \"14\"\n",
+        );
+        let abi = abi(&settings).unwrap();
+        assert!(abi.starts_with("FreeBSD:14:"));
+        let manifest = generate_manifest(&settings, "example", "1.0.0", &abi);
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["name"], "example");
+        assert_eq!(json["origin"], "misc/example");
+        assert_eq!(json["comment"], "An example");
+        assert_eq!(json["desc"], "An example");
+        assert_eq!(json["prefix"], "/usr/local");
+        assert!(json.get("files").is_none());
+    }
+}
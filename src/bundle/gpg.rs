@@ -0,0 +1,118 @@
+// Packages are signed with GnuPG, using the key set with the `gpg_key` setting
+// or the `--gpg-key` flag (or else the default key of `gpg`):
+//
+// gpg --batch --yes --local-user KEY --detach-sign --output foobar.sig foobar
+//
+// The passphrase of the key, if any, is read from the environment, so that
+// signing works without a pinentry prompt in CI.
+
+use super::common;
+use crate::Settings;
+use std::path::Path;
+use std::process::Command;
+
+/// The environment variable holding the passphrase of the signing key.
+const PASSPHRASE_VAR: &str = "CARGO_BUNDLE_GPG_PASSPHRASE";
+
+/// Writes a detached signature of the file at `input` to `output`, armored if
+/// `armor` is set.
+pub fn detach_sign(
+    settings: &Settings,
+    input: &Path,
+    output: &Path,
+    armor: bool,
+) -> crate::Result<()> {
+    let passphrase = std::env::var(PASSPHRASE_VAR).ok();
+    common::run_tool(
+        &mut detach_sign_command(
+            settings.gpg_key(),
+            passphrase.as_deref(),
+            input,
+            output,
+            armor,
+        ),
+        "gpg",
+    )?;
+    Ok(())
+}
+
+fn detach_sign_command(
+    key: Option<&str>,
+    passphrase: Option<&str>,
+    input: &Path,
+    output: &Path,
+    armor: bool,
+) -> Command {
+    let mut command = Command::new("gpg");
+    command.args(["--batch", "--yes"]);
+    if let Some(key) = key {
+        command.arg("--local-user").arg(key);
+    }
+    if let Some(passphrase) = passphrase {
+        command
+            .args(["--pinentry-mode", "loopback", "--passphrase"])
+            .arg(passphrase);
+    }
+    command.arg("--detach-sign");
+    if armor {
+        command.arg("--armor");
+    }
+    command.arg("--output").arg(output).arg(input);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detach_sign_arguments() {
+        let args = |command: Command| {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            args(detach_sign_command(
+                Some("ABCD1234"),
+                Some("secret"),
+                Path::new("foo.deb"),
+                Path::new("foo.deb.asc"),
+                true
+            )),
+            [
+                "--batch",
+                "--yes",
+                "--local-user",
+                "ABCD1234",
+                "--pinentry-mode",
+                "loopback",
+                "--passphrase",
+                "secret",
+                "--detach-sign",
+                "--armor",
+                "--output",
+                "foo.deb.asc",
+                "foo.deb"
+            ]
+        );
+        assert_eq!(
+            args(detach_sign_command(
+                None,
+                None,
+                Path::new("origin"),
+                Path::new("_gpgorigin"),
+                false
+            )),
+            [
+                "--batch",
+                "--yes",
+                "--detach-sign",
+                "--output",
+                "_gpgorigin",
+                "origin"
+            ]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+// Homebrew installs macOS apps from casks and command-line tools from
+// formulae.  Both are Ruby files that point at a downloadable artifact and
+// record its SHA-256 hash.  When `homebrew_url` is set, we write a cask next to
+// each DMG and a formula next to each tarball:
+//
+// bundle/dmg/foobar_1.2.3_x86_64.dmg
+// bundle/dmg/foobar.rb        # cask "foobar", installing the app bundle
+// bundle/archive/foobar-1.2.3-x86_64-apple-darwin.tar.gz
+// bundle/archive/foobar.rb    # class Foobar < Formula, installing the binary
+//
+// The generated files can be committed to a tap as-is, once the artifacts have
+// been uploaded to the URLs they refer to.
+
+use super::common;
+use crate::Settings;
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes a cask or formula for each of the `paths` that Homebrew can
+/// install, and returns the paths of the written files.
+pub fn generate_manifests(
+    settings: &Settings,
+    url_template: &str,
+    paths: &[PathBuf],
+) -> crate::Result<Vec<PathBuf>> {
+    let token = homebrew_token(settings);
+    let mut manifests = Vec::new();
+    for path in paths {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let is_cask = if file_name.ends_with(".dmg") {
+            true
+        } else if file_name.ends_with(".tar.gz") {
+            false
+        } else {
+            continue;
+        };
+        let url = download_url(settings, url_template, &file_name)?;
+        let sha256 = common::sha256_hex(path)?;
+        let manifest_name = format!("{token}.rb");
+        common::print_bundling(&manifest_name)?;
+        let contents = if is_cask {
+            generate_cask(settings, &token, &url, &sha256)
+        } else {
+            generate_formula(settings, &token, &url, &sha256)
+        };
+        let manifest_path = path.with_file_name(manifest_name);
+        fs::write(&manifest_path, contents)
+            .with_context(|| format!("Failed to write {manifest_path:?}"))?;
+        manifests.push(manifest_path);
+    }
+    Ok(manifests)
+}
+
+/// Expands the `homebrew_url` template for the artifact named `file_name`.
+fn download_url(settings: &Settings, url_template: &str, file_name: &str) -> crate::Result<String> {
+    let version = settings.version_string().to_string();
+    let tokens = [
+        ("name", settings.binary_name()),
+        ("version", version.as_str()),
+        ("file", file_name),
+    ];
+    common::expand_template(url_template, &tokens).context("Failed to expand `homebrew_url`")
+}
+
+/// Derives a valid cask token or formula name from the (human-readable)
+/// bundle name.  These may only contain lowercase letters, digits and dashes.
+fn homebrew_token(settings: &Settings) -> String {
+    common::package_name_slug(settings, &[])
+}
+
+/// Converts a formula name to the name of its Ruby class (e.g. `"foo-bar"`
+/// becomes `"FooBar"`).
+fn formula_class_name(token: &str) -> String {
+    token
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Quotes a string as a double-quoted Ruby string literal.
+fn ruby_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("#{", "\\#{");
+    format!("\"{escaped}\"")
+}
+
+/// Generates a cask installing the app bundle from a DMG.
+fn generate_cask(settings: &Settings, token: &str, url: &str, sha256: &str) -> String {
+    let mut rb = String::new();
+    writeln!(rb, "cask {} do", ruby_string(token)).unwrap();
+    writeln!(
+        rb,
+        "  version {}",
+        ruby_string(&settings.version_string().to_string())
+    )
+    .unwrap();
+    writeln!(rb, "  sha256 {}", ruby_string(sha256)).unwrap();
+    writeln!(rb).unwrap();
+    writeln!(rb, "  url {}", ruby_string(url)).unwrap();
+    writeln!(rb, "  name {}", ruby_string(settings.bundle_name())).unwrap();
+    let description = settings.short_description().trim();
+    if !description.is_empty() {
+        writeln!(rb, "  desc {}", ruby_string(description)).unwrap();
+    }
+    if !settings.homepage_url().is_empty() {
+        writeln!(rb, "  homepage {}", ruby_string(settings.homepage_url())).unwrap();
+    }
+    writeln!(rb).unwrap();
+    let app_name = format!("{}.app", settings.bundle_file_name());
+    writeln!(rb, "  app {}", ruby_string(&app_name)).unwrap();
+    writeln!(rb, "end").unwrap();
+    rb
+}
+
+/// Generates a formula installing the binary (and resources) from a tarball.
+/// Homebrew strips the tarball's top-level directory, if it has one.
+fn generate_formula(settings: &Settings, token: &str, url: &str, sha256: &str) -> String {
+    let mut rb = String::new();
+    writeln!(rb, "class {} < Formula", formula_class_name(token)).unwrap();
+    let description = settings.short_description().trim();
+    if !description.is_empty() {
+        writeln!(rb, "  desc {}", ruby_string(description)).unwrap();
+    }
+    if !settings.homepage_url().is_empty() {
+        writeln!(rb, "  homepage {}", ruby_string(settings.homepage_url())).unwrap();
+    }
+    writeln!(rb, "  url {}", ruby_string(url)).unwrap();
+    writeln!(
+        rb,
+        "  version {}",
+        ruby_string(&settings.version_string().to_string())
+    )
+    .unwrap();
+    writeln!(rb, "  sha256 {}", ruby_string(sha256)).unwrap();
+    if let Some(license) = settings.license() {
+        let licenses: Vec<String> = license
+            .split(" OR ")
+            .flat_map(|l| l.split('/'))
+            .map(|l| ruby_string(l.trim()))
+            .collect();
+        if licenses.len() == 1 {
+            writeln!(rb, "  license {}", licenses[0]).unwrap();
+        } else {
+            writeln!(rb, "  license any_of: [{}]", licenses.join(", ")).unwrap();
+        }
+    }
+    let binary = ruby_string(settings.binary_name());
+    writeln!(rb).unwrap();
+    writeln!(rb, "  def install").unwrap();
+    writeln!(rb, "    libexec.install Dir[\"*\"]").unwrap();
+    writeln!(rb, "    bin.install_symlink libexec/{binary}").unwrap();
+    writeln!(rb, "  end").unwrap();
+    writeln!(rb).unwrap();
+    writeln!(rb, "  test do").unwrap();
+    writeln!(rb, "    assert_predicate bin/{binary}, :exist?").unwrap();
+    writeln!(rb, "  end").unwrap();
+    writeln!(rb, "end").unwrap();
+    rb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let token = |name: &str| homebrew_token(&Settings::for_test(&format!("name = {name:?}")));
+        assert_eq!(token("My Cool App"), "my-cool-app");
+        assert_eq!(token("日本語"), "example");
+        assert_eq!(formula_class_name("my-cool-app"), "MyCoolApp");
+        assert_eq!(ruby_string("a \"b\" #{c}"), "\"a \\\"b\\\" \\#{c}\"");
+    }
+
+    #[test]
+    fn download_urls() {
+        let settings = Settings::for_test("");
+        let url = download_url(
+            &settings,
+            "https://example.com/v{version}/{file}",
+            "example.dmg",
+        )
+        .unwrap();
+        assert_eq!(url, "https://example.com/v1.0.0/example.dmg");
+        assert!(download_url(&settings, "https://example.com/{tag}", "example.dmg").is_err());
+    }
+
+    #[test]
+    fn cask() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             short_description = \"Does things\"\n",
+        );
+        let rb = generate_cask(&settings, "my-app", "https://example.com/a.dmg", "abc123");
+        assert!(rb.starts_with("cask \"my-app\" do\n  version \"1.0.0\"\n  sha256 \"abc123\"\n"));
+        assert!(rb.contains("  url \"https://example.com/a.dmg\"\n  name \"My App\"\n"));
+        assert!(rb.contains("  desc \"Does things\"\n"));
+        assert!(rb.contains("  app \"My App.app\"\n"));
+    }
+
+    #[test]
+    fn formula() {
+        let settings = Settings::for_test("");
+        let rb = generate_formula(
+            &settings,
+            "example",
+            "https://example.com/a.tar.gz",
+            "abc123",
+        );
+        assert!(rb.starts_with("class Example < Formula\n"));
+        assert!(rb.contains("  url \"https://example.com/a.tar.gz\"\n"));
+        assert!(rb.contains("  sha256 \"abc123\"\n"));
+        assert!(rb.contains("    libexec.install Dir[\"*\"]\n"));
+    }
+}
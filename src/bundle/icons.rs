@@ -0,0 +1,250 @@
+// When the `icon` setting names a single PNG or SVG image, the icons that the
+// bundle formats need are generated from it before bundling:
+//
+// bundle/icons/
+//     foobar.ico                  # Windows icon (16 to 256 pixels)
+//     foobar_16x16.png ...        # The hicolor sizes, for Linux (and the
+//     foobar_512x512.png          # formats that resize the largest PNG)
+//     foobar_16x16@2x.png ...     # High density sizes, for Mac OS X and iOS
+//     foobar_512x512@2x.png
+//     foobar.icns                 # Mac OS X icon (16 to 1024 pixels)
+//
+// These then replace the `icon` setting for every bundler, along with the SVG
+// itself, which Linux packages install as the scalable icon.  A PNG is only
+// scaled down, so the sizes larger than it are left out.
+
+use super::common;
+use super::settings::Settings;
+use super::svg;
+use anyhow::Context;
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::FilterType::Lanczos3;
+use image::{ExtendedColorType, RgbaImage};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The sizes of the PNG icons, as in the hicolor icon theme.
+const PNG_SIZES: &[u32] = &[16, 24, 32, 48, 64, 128, 256, 512];
+
+/// The sizes of the images in the ICO icon; Windows scales the others.
+const ICO_SIZES: &[u32] = &[16, 24, 32, 48, 64, 256];
+
+/// The sizes (in points) and pixel densities of the images in the ICNS icon.
+const ICNS_SIZES: &[(u32, u32)] = &[
+    (16, 1),
+    (16, 2),
+    (32, 1),
+    (32, 2),
+    (128, 1),
+    (128, 2),
+    (256, 1),
+    (256, 2),
+    (512, 1),
+    (512, 2),
+];
+
+/// A single source image to generate the icons from.
+enum Source {
+    Png(RgbaImage),
+    Svg(svg::Document),
+}
+
+impl Source {
+    /// Renders the source at `size` pixels, or returns `None` if that would
+    /// scale a PNG up.
+    fn render(&self, size: u32) -> crate::Result<Option<RgbaImage>> {
+        match self {
+            Source::Png(image) if image.width() == size => Ok(Some(image.clone())),
+            Source::Png(image) if image.width() < size => Ok(None),
+            Source::Png(image) => Ok(Some(image::imageops::resize(image, size, size, Lanczos3))),
+            Source::Svg(document) => document.render(size).map(Some),
+        }
+    }
+}
+
+/// Generates the icons from the `icon` setting if it names a single PNG or
+/// SVG image, and makes `settings` use them instead.
+pub fn generate_icons(settings: &mut Settings) -> crate::Result<()> {
+    let icons: Vec<PathBuf> = settings.icon_files().collect::<crate::Result<_>>()?;
+    let [source_path] = icons.as_slice() else {
+        return Ok(());
+    };
+    let extension = source_path
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_ascii_lowercase);
+    let source = match extension.as_deref() {
+        Some("svg") => {
+            let text = common::read_file(source_path)?;
+            let document = svg::Document::parse(&text)
+                .with_context(|| format!("Failed to parse icon {source_path:?}"))?;
+            for name in document.skipped_elements() {
+                common::print_warning(&format!(
+                    "Icon {source_path:?} has `{name}` elements, which are not drawn"
+                ))?;
+            }
+            Source::Svg(document)
+        }
+        Some("png") => {
+            let image = image::open(source_path)
+                .with_context(|| format!("Failed to read icon {source_path:?}"))?
+                .into_rgba8();
+            if image.width() != image.height() {
+                common::print_warning(&format!(
+                    "Icon {source_path:?} is not square, so no other sizes are generated from it"
+                ))?;
+                return Ok(());
+            }
+            Source::Png(image)
+        }
+        _ => return Ok(()),
+    };
+    let out_dir = settings.project_out_directory().join("bundle/icons");
+    if out_dir.exists() {
+        fs::remove_dir_all(&out_dir)
+            .with_context(|| format!("Failed to remove old {out_dir:?}"))?;
+    }
+    let mut icons = write_icons(&source, &out_dir, settings.binary_name())
+        .with_context(|| format!("Failed to generate icons from {source_path:?}"))?;
+    if let Source::Svg(_) = source {
+        icons.push(source_path.clone());
+    }
+    settings.set_icon_files(&icons);
+    Ok(())
+}
+
+/// Writes the icons generated from `source` into `out_dir`, and returns their
+/// paths, with the ICO first since some bundlers only look at the first icon.
+fn write_icons(source: &Source, out_dir: &Path, name: &str) -> crate::Result<Vec<PathBuf>> {
+    // Each size is only rendered once.
+    let mut images = BTreeMap::new();
+    let mut render = |size: u32| -> crate::Result<Option<RgbaImage>> {
+        if let Entry::Vacant(entry) = images.entry(size) {
+            entry.insert(source.render(size)?);
+        }
+        Ok(images[&size].clone())
+    };
+
+    let mut frames = Vec::new();
+    for &size in ICO_SIZES {
+        if let Some(image) = render(size)? {
+            frames.push(IcoFrame::as_png(
+                image.as_raw(),
+                size,
+                size,
+                ExtendedColorType::Rgba8,
+            )?);
+        }
+    }
+    let mut icons = Vec::new();
+    if !frames.is_empty() {
+        let ico_path = out_dir.join(format!("{name}.ico"));
+        IcoEncoder::new(common::create_file(&ico_path)?).encode_images(&frames)?;
+        icons.push(ico_path);
+    }
+
+    // The high density PNGs match the ICNS images, so that the bundlers that
+    // read both get the same set of sizes from either.
+    let png_sizes = PNG_SIZES.iter().map(|&size| (size, 1)).chain(
+        ICNS_SIZES
+            .iter()
+            .copied()
+            .filter(|&(_, density)| density == 2),
+    );
+    for (size, density) in png_sizes {
+        if let Some(image) = render(size * density)? {
+            let suffix = if density == 2 { "@2x" } else { "" };
+            let path = out_dir.join(format!("{name}_{size}x{size}{suffix}.png"));
+            let mut file = common::create_file(&path)?;
+            image.write_to(&mut file, image::ImageFormat::Png)?;
+            icons.push(path);
+        }
+    }
+
+    let mut family = icns::IconFamily::new();
+    for &(size, density) in ICNS_SIZES {
+        let pixels = size * density;
+        let Some(image) = render(pixels)? else {
+            continue;
+        };
+        let icon_type = icns::IconType::from_pixel_size_and_density(pixels, pixels, density)
+            .context("No ICNS icon type for the size")?;
+        let image =
+            icns::Image::from_data(icns::PixelFormat::RGBA, pixels, pixels, image.into_raw())?;
+        family.add_icon_with_type(&image, icon_type)?;
+    }
+    if !family.is_empty() {
+        let icns_path = out_dir.join(format!("{name}.icns"));
+        family.write(common::create_file(&icns_path)?)?;
+        icons.push(icns_path);
+    }
+    Ok(icons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icons_from_svg() {
+        let document = svg::Document::parse(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 2 2\">\
+             <circle cx=\"1\" cy=\"1\" r=\"1\" fill=\"#08f\"/></svg>",
+        )
+        .unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let icons = write_icons(&Source::Svg(document), tmp.path(), "foo").unwrap();
+        let names: Vec<_> = icons
+            .iter()
+            .map(|icon| icon.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "foo.ico",
+                "foo_16x16.png",
+                "foo_24x24.png",
+                "foo_32x32.png",
+                "foo_48x48.png",
+                "foo_64x64.png",
+                "foo_128x128.png",
+                "foo_256x256.png",
+                "foo_512x512.png",
+                "foo_16x16@2x.png",
+                "foo_32x32@2x.png",
+                "foo_128x128@2x.png",
+                "foo_256x256@2x.png",
+                "foo_512x512@2x.png",
+                "foo.icns"
+            ]
+        );
+        let png = image::open(tmp.path().join("foo_48x48.png"))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(png.dimensions(), (48, 48));
+        assert_eq!(png.get_pixel(24, 24).0, [0, 0x88, 0xff, 255]);
+        assert_eq!(png.get_pixel(0, 0).0[3], 0);
+        let ico = image::open(tmp.path().join("foo.ico")).unwrap();
+        assert_eq!(ico.width(), 256);
+        let icns =
+            icns::IconFamily::read(fs::File::open(tmp.path().join("foo.icns")).unwrap()).unwrap();
+        assert_eq!(icns.available_icons().len(), ICNS_SIZES.len());
+    }
+
+    #[test]
+    fn icons_from_png() {
+        let source = Source::Png(RgbaImage::from_pixel(64, 64, image::Rgba([255, 0, 0, 255])));
+        let tmp = tempfile::tempdir().unwrap();
+        let icons = write_icons(&source, tmp.path(), "foo").unwrap();
+        // A PNG is never scaled up.
+        assert_eq!(icons.len(), 1 + 5 + 2 + 1);
+        assert!(icons.contains(&tmp.path().join("foo_64x64.png")));
+        assert!(!tmp.path().join("foo_128x128.png").exists());
+        let icns =
+            icns::IconFamily::read(fs::File::open(tmp.path().join("foo.icns")).unwrap()).unwrap();
+        assert_eq!(icns.available_icons().len(), 4);
+    }
+}
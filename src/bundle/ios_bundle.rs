@@ -3,25 +3,56 @@
 // Foobar.app         # Actually a directory
 //     Foobar             # The main binary executable of the app
 //     Info.plist         # An XML file containing the app's metadata
-//     ...                # Icons and other resource files
+//     Assets.car         # The compiled asset catalog, holding the app icons
+//     Launch.storyboardc # The compiled launch storyboard, if any
+//     ...                # Other resource files
 //
 // See https://developer.apple.com/go/?id=bundle-structure for a full
 // explanation.
+//
+// App Store Connect only accepts apps whose icons are in an asset catalog.
+// Unless `ios_asset_catalog` names one, an `AppIcon` set is generated from the
+// largest PNG icon, and compiled with `actool`, which needs Xcode (so off
+// macOS, the icons are copied as loose PNG files instead).  Without an
+// `ios_launch_storyboard`, the app shows the default `UILaunchScreen`, a blank
+// screen in the system background color.
+//
+// The app is then zipped into an IPA, which App Store Connect (and TestFlight)
+// accept uploads of:
+//
+// Foobar.ipa
+//     Payload/Foobar.app/...
+//
+// To install on devices, the app must embed a provisioning profile (as
+// `embedded.mobileprovision`) and be signed with an identity the profile
+// lists.  Unless `ios_entitlements` is set, the app is signed with the
+// entitlements granted by the profile.
+//
+// Apps built for the iOS simulator (e.g. for `aarch64-apple-ios-sim`) need
+// neither, and are bundled without an IPA.  `cargo bundle run --ios-simulator
+// <device>` installs such an app on a simulator with `simctl`, and launches it.
 
+use super::build_info::build_time;
 use super::common;
+use super::linux::common::{collect_entries, file_mode};
+use super::osx_bundle::{SigningKey, rcodesign_command, short_version_string};
+use super::settings::OsxSigningTool;
+use super::zip::ZipWriter;
 use crate::Settings;
 use anyhow::Context;
+use image::imageops::FilterType::Lanczos3;
 use image::{self, GenericImageView};
 use std::collections::BTreeSet;
 use std::ffi::OsStr;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     common::print_warning("iOS bundle support is still experimental.")?;
 
-    let app_bundle_name = format!("{}.app", settings.bundle_name());
+    let app_bundle_name = format!("{}.app", settings.bundle_file_name());
     common::print_bundling(&app_bundle_name)?;
     let bundle_dir = settings
         .project_out_directory()
@@ -34,21 +65,428 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     fs::create_dir_all(&bundle_dir)
         .with_context(|| format!("Failed to create bundle directory at {bundle_dir:?}"))?;
 
-    for src in settings.resource_files() {
-        let src = src?;
-        let dest = bundle_dir.join(common::resource_relpath(&src));
-        common::copy_file(&src, &dest)
-            .with_context(|| format!("Failed to copy resource file {src:?}"))?;
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let dest = bundle_dir.join(&resource.dest);
+        common::copy_resource(&resource, &dest)?;
+    }
+
+    if let Some(build_info) = settings.build_info() {
+        build_info
+            .write_json(&bundle_dir)
+            .with_context(|| "Failed to write build info")?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        sbom.write_json(&bundle_dir)
+            .with_context(|| "Failed to write SBOM")?;
     }
 
-    let icon_filenames =
-        generate_icon_files(&bundle_dir, settings).with_context(|| "Failed to create app icons")?;
-    generate_info_plist(&bundle_dir, settings, &icon_filenames)
-        .with_context(|| "Failed to create Info.plist")?;
+    let icon_keys =
+        create_app_icons(&bundle_dir, settings).with_context(|| "Failed to create app icons")?;
+    let launch_storyboard = add_launch_storyboard(&bundle_dir, settings)
+        .with_context(|| "Failed to add the launch storyboard")?;
+    generate_info_plist(
+        &bundle_dir,
+        settings,
+        &icon_keys,
+        launch_storyboard.as_deref(),
+    )
+    .with_context(|| "Failed to create Info.plist")?;
     let bin_path = bundle_dir.join(settings.binary_name());
     common::copy_file(settings.binary_path(), &bin_path)
         .with_context(|| format!("Failed to copy binary from {:?}", settings.binary_path()))?;
-    Ok(vec![bundle_dir])
+    common::copy_external_binaries(settings, &bundle_dir)?;
+    if settings.is_ios_simulator() {
+        return Ok(vec![bundle_dir]);
+    }
+    if let Some(profile) = settings.ios_provisioning_profile() {
+        common::copy_file(&profile, &bundle_dir.join("embedded.mobileprovision"))
+            .with_context(|| format!("Failed to copy provisioning profile {profile:?}"))?;
+    }
+
+    if let Some(identity) = settings.ios_signing_identity() {
+        common::print_signing(&app_bundle_name)?;
+        sign_app(&bundle_dir, identity, settings)
+            .with_context(|| format!("Failed to sign {app_bundle_name}"))?;
+        common::record_signed(&bundle_dir);
+    }
+
+    let ipa_path = bundle_dir.with_extension("ipa");
+    common::print_bundling(&ipa_path.file_name().unwrap().to_string_lossy())?;
+    create_ipa(&bundle_dir, &ipa_path, settings.binary_name())
+        .with_context(|| "Failed to create IPA")?;
+    Ok(vec![bundle_dir, ipa_path])
+}
+
+/// Installs the app bundle on the simulator `device` (a name or UDID), booting
+/// it first if need be, and launches the app, identified by
+/// `bundle_identifier`, with its output on the console.
+pub fn run_in_simulator(
+    app_bundle_path: &Path,
+    bundle_identifier: &str,
+    device: &str,
+) -> crate::Result<()> {
+    let app_bundle_name = app_bundle_path.file_name().unwrap().to_string_lossy();
+    common::print_launching(&format!("{app_bundle_name} on {device}"))?;
+    common::run_tool(&mut simctl_command(&["bootstatus", device, "-b"]), "simctl")?;
+    common::run_tool(Command::new("open").args(["-a", "Simulator"]), "open")?;
+    common::run_tool(
+        simctl_command(&["install", device]).arg(app_bundle_path),
+        "simctl",
+    )?;
+    let status = simctl_command(&["launch", "--console-pty", device, bundle_identifier])
+        .status()
+        .context("Failed to run simctl")?;
+    if !status.success() {
+        anyhow::bail!("simctl failed to launch the app: {status}");
+    }
+    Ok(())
+}
+
+fn simctl_command(args: &[&str]) -> Command {
+    let mut command = Command::new("xcrun");
+    command.arg("simctl").args(args);
+    command
+}
+
+/// Signs the app, with the entitlements from `ios_entitlements` or else from
+/// the embedded provisioning profile.
+fn sign_app(bundle_dir: &Path, identity: &str, settings: &Settings) -> crate::Result<()> {
+    let entitlements = match settings.ios_entitlements() {
+        Some(path) => Some(path),
+        None => match settings.ios_provisioning_profile() {
+            Some(profile) => {
+                let path = bundle_dir.with_extension("entitlements");
+                let entitlements = profile_entitlements(&decode_provisioning_profile(&profile)?)
+                    .with_context(|| format!("No entitlements found in {profile:?}"))?;
+                fs::write(&path, entitlements)?;
+                Some(path)
+            }
+            None => None,
+        },
+    };
+    match settings.osx_signing_tool() {
+        OsxSigningTool::Codesign => {
+            common::run_signing_tool(
+                settings,
+                &mut codesign_command(identity, bundle_dir, entitlements.as_deref()),
+                "codesign",
+            )?;
+        }
+        OsxSigningTool::Rcodesign => {
+            let key = SigningKey::from_settings(settings, identity)?;
+            common::run_signing_tool(
+                settings,
+                &mut rcodesign_command(
+                    key.as_ref(),
+                    bundle_dir,
+                    entitlements.as_deref(),
+                    false,
+                    settings.osx_timestamp_url(),
+                ),
+                "rcodesign",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the property list inside a provisioning profile, which is signed
+/// as a CMS message.
+pub(crate) fn decode_provisioning_profile(profile: &Path) -> crate::Result<String> {
+    let decoded = common::run_tool(
+        Command::new("openssl")
+            .args(["smime", "-inform", "der", "-verify", "-noverify", "-in"])
+            .arg(profile),
+        "openssl",
+    )?;
+    Ok(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Builds the `codesign` invocation that signs the app at `path`.  iOS apps
+/// don't use the hardened runtime or secure timestamps.
+fn codesign_command(identity: &str, path: &Path, entitlements: Option<&Path>) -> Command {
+    let mut command = Command::new("codesign");
+    command.args(["--force", "--sign"]).arg(identity);
+    if let Some(entitlements) = entitlements {
+        command.arg("--entitlements").arg(entitlements);
+    }
+    command.arg(path);
+    command
+}
+
+/// Returns an entitlements plist holding the `Entitlements` dictionary of a
+/// decoded provisioning profile, or `None` if it has none.
+fn profile_entitlements(profile: &str) -> Option<String> {
+    let key = profile.find("<key>Entitlements</key>")?;
+    let start = key + profile[key..].find("<dict>")?;
+    // The dictionary may contain nested dictionaries.
+    let mut depth = 0;
+    let mut end = start;
+    loop {
+        let next_open = profile[end..].find("<dict>").map(|i| end + i);
+        let next_close = profile[end..].find("</dict>").map(|i| end + i)?;
+        match next_open {
+            Some(open) if open < next_close => {
+                depth += 1;
+                end = open + "<dict>".len();
+            }
+            _ => {
+                depth -= 1;
+                end = next_close + "</dict>".len();
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    Some(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+         \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n{}\n</plist>\n",
+        &profile[start..end]
+    ))
+}
+
+/// Zips the app into an IPA, under its `Payload` directory.  The app's binary
+/// is marked executable even where file permissions don't say so.
+fn create_ipa(bundle_dir: &Path, ipa_path: &Path, binary_name: &str) -> crate::Result<()> {
+    let app_name = bundle_dir.file_name().unwrap().to_string_lossy();
+    let mut zip = ZipWriter::new(common::create_file(ipa_path)?, build_time()?);
+    zip.add_directory("Payload")?;
+    zip.add_directory(&format!("Payload/{app_name}"))?;
+    for entry in collect_entries(bundle_dir)? {
+        let name = format!(
+            "Payload/{app_name}/{}",
+            entry.path.to_string_lossy().replace('\\', "/")
+        );
+        let path = bundle_dir.join(&entry.path);
+        if entry.is_dir {
+            zip.add_directory(&name)?;
+        } else {
+            let executable = entry.path == Path::new(binary_name) || file_mode(&path)? & 0o111 != 0;
+            zip.add_file(&name, &fs::read(&path)?, executable)?;
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// The name of the icon set holding the app icons in the asset catalog.
+const APP_ICON_NAME: &str = "AppIcon";
+
+/// The icons of the generated `AppIcon` set: their idiom, size in points, and
+/// scales.
+const APP_ICON_SIZES: &[(&str, f32, &[u32])] = &[
+    ("iphone", 20.0, &[2, 3]),
+    ("iphone", 29.0, &[2, 3]),
+    ("iphone", 40.0, &[2, 3]),
+    ("iphone", 60.0, &[2, 3]),
+    ("ipad", 20.0, &[1, 2]),
+    ("ipad", 29.0, &[1, 2]),
+    ("ipad", 40.0, &[1, 2]),
+    ("ipad", 76.0, &[1, 2]),
+    ("ipad", 83.5, &[2]),
+    ("ios-marketing", 1024.0, &[1]),
+];
+
+/// The oldest iOS version that the asset catalog and launch storyboard are
+/// compiled for, and the first to support the default `UILaunchScreen`.
+const MINIMUM_DEPLOYMENT_TARGET: &str = "14.0";
+
+/// Adds the app icons to the bundle, and returns the `Info.plist` keys naming
+/// them.
+fn create_app_icons(bundle_dir: &Path, settings: &Settings) -> crate::Result<String> {
+    match settings.ios_asset_catalog() {
+        Some(catalog) if catalog.extension() == Some(OsStr::new("car")) => {
+            common::copy_file(&catalog, &bundle_dir.join("Assets.car"))
+                .with_context(|| format!("Failed to copy asset catalog {catalog:?}"))?;
+            return Ok(format!(
+                "  <key>CFBundleIcons</key>\n  <dict>\n    \
+                   <key>CFBundlePrimaryIcon</key>\n    <dict>\n      \
+                     <key>CFBundleIconName</key>\n      <string>{APP_ICON_NAME}</string>\n    \
+                   </dict>\n  \
+                 </dict>\n"
+            ));
+        }
+        Some(catalog) => return compile_asset_catalog(&catalog, bundle_dir, settings),
+        None => {}
+    }
+    if cfg!(target_os = "macos") {
+        let catalog = bundle_dir.with_extension("xcassets");
+        if create_asset_catalog(settings, &catalog)? {
+            let icon_keys = compile_asset_catalog(&catalog, bundle_dir, settings);
+            fs::remove_dir_all(&catalog)
+                .with_context(|| format!("Failed to remove {catalog:?}"))?;
+            return icon_keys;
+        }
+    }
+    let icon_filenames = generate_icon_files(bundle_dir, settings)?;
+    if icon_filenames.is_empty() {
+        return Ok(String::new());
+    }
+    if !cfg!(target_os = "macos") {
+        common::print_warning(
+            "The app icons can only be compiled into an asset catalog on macOS, \
+             without which App Store Connect rejects the app.",
+        )?;
+    }
+    let mut icon_keys = String::from("  <key>CFBundleIconFiles</key>\n  <array>\n");
+    for filename in icon_filenames {
+        icon_keys.push_str(&format!("    <string>{filename}</string>\n"));
+    }
+    icon_keys.push_str("  </array>\n");
+    Ok(icon_keys)
+}
+
+/// Writes an asset catalog holding an `AppIcon` set scaled from the largest PNG
+/// icon, and returns whether there was one.
+fn create_asset_catalog(settings: &Settings, catalog: &Path) -> crate::Result<bool> {
+    let mut largest: Option<image::DynamicImage> = None;
+    for icon_path in settings.icon_files() {
+        let icon_path = icon_path?;
+        if icon_path.extension() != Some(OsStr::new("png")) {
+            continue;
+        }
+        let icon = image::open(&icon_path)?;
+        if largest
+            .as_ref()
+            .is_none_or(|largest| icon.width() > largest.width())
+        {
+            largest = Some(icon);
+        }
+    }
+    let Some(icon) = largest else {
+        return Ok(false);
+    };
+    if icon.width() < 1024 {
+        common::print_warning(&format!(
+            "The largest PNG icon is {}x{}, but App Store Connect expects one of 1024x1024.",
+            icon.width(),
+            icon.height()
+        ))?;
+    }
+    let icon_set = catalog.join(format!("{APP_ICON_NAME}.appiconset"));
+    let mut images = Vec::new();
+    for &(idiom, points, scales) in APP_ICON_SIZES {
+        for &scale in scales {
+            let filename = format!("{idiom}-{points}@{scale}x.png");
+            let size = (points * scale as f32) as u32;
+            let mut scaled = if icon.dimensions() == (size, size) {
+                icon.clone()
+            } else {
+                icon.resize_exact(size, size, Lanczos3)
+            };
+            // The App Store icon must not be transparent.
+            if idiom == "ios-marketing" {
+                scaled = image::DynamicImage::ImageRgb8(scaled.to_rgb8());
+            }
+            let mut file = common::create_file(&icon_set.join(&filename))?;
+            scaled.write_to(&mut file, image::ImageFormat::Png)?;
+            file.flush()?;
+            images.push(serde_json::json!({
+                "filename": filename,
+                "idiom": idiom,
+                "scale": format!("{scale}x"),
+                "size": format!("{points}x{points}"),
+            }));
+        }
+    }
+    let info = serde_json::json!({ "author": "cargo-bundle", "version": 1 });
+    write_json(
+        &icon_set.join("Contents.json"),
+        &serde_json::json!({ "images": images, "info": info }),
+    )?;
+    write_json(
+        &catalog.join("Contents.json"),
+        &serde_json::json!({ "info": info }),
+    )?;
+    Ok(true)
+}
+
+fn write_json(path: &Path, value: &serde_json::Value) -> crate::Result<()> {
+    let mut file = common::create_file(path)?;
+    serde_json::to_writer_pretty(&mut file, value)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Compiles the asset catalog into the bundle with `actool`, and returns the
+/// `Info.plist` keys it names the app icons with.
+fn compile_asset_catalog(
+    catalog: &Path,
+    bundle_dir: &Path,
+    settings: &Settings,
+) -> crate::Result<String> {
+    let partial_plist = bundle_dir.with_extension("partial.plist");
+    common::run_tool(
+        Command::new("xcrun")
+            .arg("actool")
+            .arg(catalog)
+            .arg("--compile")
+            .arg(bundle_dir)
+            .args(["--platform", sdk_platform(settings)])
+            .args(["--minimum-deployment-target", MINIMUM_DEPLOYMENT_TARGET])
+            .args(["--app-icon", APP_ICON_NAME])
+            .args(["--target-device", "iphone", "--target-device", "ipad"])
+            .arg("--output-partial-info-plist")
+            .arg(&partial_plist),
+        "actool",
+    )
+    .with_context(|| format!("Failed to compile asset catalog {catalog:?}"))?;
+    let plist = common::read_file(&partial_plist)?;
+    fs::remove_file(&partial_plist)
+        .with_context(|| format!("Failed to remove {partial_plist:?}"))?;
+    plist_dict_body(&plist)
+        .ok_or_else(|| anyhow::anyhow!("Malformed Info.plist keys from actool: {plist:?}"))
+}
+
+/// Returns the lines between the `<dict>` and `</dict>` of a plist.
+fn plist_dict_body(plist: &str) -> Option<String> {
+    let start = plist.find("<dict>")? + "<dict>".len();
+    let end = plist.rfind("</dict>")?;
+    let body = plist.get(start..end)?.trim_matches('\n');
+    Some(body.lines().map(|line| format!("{line}\n")).collect())
+}
+
+/// Adds the `ios_launch_storyboard` to the bundle, compiling it with `ibtool`
+/// unless it is compiled already, and returns its name.
+fn add_launch_storyboard(bundle_dir: &Path, settings: &Settings) -> crate::Result<Option<String>> {
+    let Some(storyboard) = settings.ios_launch_storyboard() else {
+        return Ok(None);
+    };
+    let name = match storyboard.file_stem() {
+        Some(stem) => stem.to_string_lossy().into_owned(),
+        None => anyhow::bail!("Invalid launch storyboard path {storyboard:?}"),
+    };
+    let dest = bundle_dir.join(format!("{name}.storyboardc"));
+    if storyboard.extension() == Some(OsStr::new("storyboardc")) {
+        common::copy_dir(&storyboard, &dest)
+            .with_context(|| format!("Failed to copy launch storyboard {storyboard:?}"))?;
+    } else {
+        common::run_tool(
+            Command::new("xcrun")
+                .arg("ibtool")
+                .args(["--target-device", "iphone", "--target-device", "ipad"])
+                .args(["--minimum-deployment-target", MINIMUM_DEPLOYMENT_TARGET])
+                .arg("--compile")
+                .arg(&dest)
+                .arg(&storyboard),
+            "ibtool",
+        )
+        .with_context(|| format!("Failed to compile launch storyboard {storyboard:?}"))?;
+    }
+    Ok(Some(name))
+}
+
+/// Returns the platform of the SDK that the app is built with.
+fn sdk_platform(settings: &Settings) -> &'static str {
+    if settings.is_ios_simulator() {
+        "iphonesimulator"
+    } else {
+        "iphoneos"
+    }
 }
 
 /// Generate the icon files and store them under the `bundle_dir`.
@@ -123,7 +561,8 @@ fn generate_icon_files(bundle_dir: &Path, settings: &Settings) -> crate::Result<
 fn generate_info_plist(
     bundle_dir: &Path,
     settings: &Settings,
-    icon_filenames: &Vec<String>,
+    icon_keys: &str,
+    launch_storyboard: Option<&str>,
 ) -> crate::Result<()> {
     let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
     write!(
@@ -158,31 +597,151 @@ fn generate_info_plist(
     write!(
         file,
         "  <key>CFBundleVersion</key>\n  <string>{}</string>\n",
-        settings.version_string()
+        short_version_string(&settings.version_string().to_string())
     )?;
     write!(
         file,
         "  <key>CFBundleShortVersionString</key>\n  <string>{}</string>\n",
-        settings.version_string()
+        short_version_string(&settings.version_string().to_string())
     )?;
     write!(
         file,
         "  <key>CFBundleDevelopmentRegion</key>\n  <string>en_US</string>\n"
     )?;
+    match launch_storyboard {
+        Some(name) => write!(
+            file,
+            "  <key>UILaunchStoryboardName</key>\n  <string>{name}</string>\n"
+        )?,
+        None => write!(file, "  <key>UILaunchScreen</key>\n  <dict/>\n")?,
+    }
+    file.write_all(icon_keys.as_bytes())?;
     write!(
         file,
-        "  <key>UILaunchStoryboardName</key>\n  <string></string>\n"
-    )?;
-
-    if !icon_filenames.is_empty() {
-        write!(file, "  <key>CFBundleIconFiles</key>\n  <array>\n")?;
-        for filename in icon_filenames {
-            writeln!(file, "    <string>{filename}</string>")?;
+        "  <key>CFBundleSupportedPlatforms</key>\n  <array>\n    <string>{}</string>\n  </array>\n",
+        if settings.is_ios_simulator() {
+            "iPhoneSimulator"
+        } else {
+            "iPhoneOS"
         }
-        writeln!(file, "  </array>")?;
-    }
+    )?;
     write!(file, "  <key>LSRequiresIPhoneOS</key>\n  <true/>\n")?;
     write!(file, "</dict>\n</plist>\n")?;
     file.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entitlements_from_profile() {
+        let profile = "<plist version=\"1.0\">\n<dict>\n\
+            <key>AppIDName</key>\n<string>Foo</string>\n\
+            <key>Entitlements</key>\n<dict>\n\
+            <key>application-identifier</key>\n<string>TEAMID.com.example.foo</string>\n\
+            <key>nested</key>\n<dict><key>a</key><true/></dict>\n\
+            <key>get-task-allow</key>\n<false/>\n\
+            </dict>\n\
+            <key>TeamName</key>\n<string>Example</string>\n\
+            </dict>\n</plist>";
+        let entitlements = profile_entitlements(profile).unwrap();
+        assert!(entitlements.ends_with(
+            "<plist version=\"1.0\">\n<dict>\n\
+             <key>application-identifier</key>\n<string>TEAMID.com.example.foo</string>\n\
+             <key>nested</key>\n<dict><key>a</key><true/></dict>\n\
+             <key>get-task-allow</key>\n<false/>\n\
+             </dict>\n</plist>\n"
+        ));
+        assert_eq!(profile_entitlements("<plist><dict></dict></plist>"), None);
+    }
+
+    #[test]
+    fn info_plist_supported_platforms() {
+        let tmp = tempfile::tempdir().unwrap();
+        let settings = Settings::for_test("");
+        generate_info_plist(tmp.path(), &settings, "", None).unwrap();
+        let plist = fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains(
+            "  <key>CFBundleSupportedPlatforms</key>\n  <array>\n    \
+             <string>iPhoneOS</string>\n  </array>\n"
+        ));
+        assert!(plist.contains("  <key>UILaunchScreen</key>\n  <dict/>\n"));
+        assert!(!plist.contains("UILaunchStoryboardName"));
+
+        generate_info_plist(tmp.path(), &settings, "", Some("Launch")).unwrap();
+        let plist = fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains("  <key>UILaunchStoryboardName</key>\n  <string>Launch</string>\n"));
+        assert!(!plist.contains("UILaunchScreen"));
+    }
+
+    #[test]
+    fn asset_catalog_icon_set() {
+        let tmp = tempfile::tempdir().unwrap();
+        let icon_path = tmp.path().join("icon.png");
+        image::RgbaImage::from_pixel(64, 64, image::Rgba([0, 0, 255, 128]))
+            .save(&icon_path)
+            .unwrap();
+        let mut settings = Settings::for_test("");
+        settings.set_icon_files(&[icon_path]);
+        let catalog = tmp.path().join("Foo.xcassets");
+        assert!(create_asset_catalog(&settings, &catalog).unwrap());
+
+        let icon_set = catalog.join("AppIcon.appiconset");
+        let contents: serde_json::Value =
+            serde_json::from_slice(&fs::read(icon_set.join("Contents.json")).unwrap()).unwrap();
+        let images = contents["images"].as_array().unwrap();
+        assert_eq!(images.len(), 18);
+        assert!(images.contains(&serde_json::json!({
+            "filename": "ipad-83.5@2x.png",
+            "idiom": "ipad",
+            "scale": "2x",
+            "size": "83.5x83.5",
+        })));
+        let ipad = image::open(icon_set.join("ipad-83.5@2x.png")).unwrap();
+        assert_eq!(ipad.dimensions(), (167, 167));
+        let iphone = image::open(icon_set.join("iphone-60@3x.png")).unwrap();
+        assert_eq!(iphone.dimensions(), (180, 180));
+        let marketing = image::open(icon_set.join("ios-marketing-1024@1x.png")).unwrap();
+        assert!(!marketing.color().has_alpha());
+        assert!(catalog.join("Contents.json").is_file());
+
+        let empty = tmp.path().join("Empty.xcassets");
+        assert!(!create_asset_catalog(&Settings::for_test(""), &empty).unwrap());
+        assert!(!empty.exists());
+    }
+
+    #[test]
+    fn actool_info_plist_keys() {
+        let plist = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <plist version=\"1.0\">\n<dict>\n\
+            \t<key>CFBundleIcons</key>\n\t<dict>\n\
+            \t\t<key>CFBundlePrimaryIcon</key>\n\t\t<dict>\n\
+            \t\t\t<key>CFBundleIconName</key>\n\t\t\t<string>AppIcon</string>\n\
+            \t\t</dict>\n\t</dict>\n\
+            </dict>\n</plist>\n";
+        assert_eq!(
+            plist_dict_body(plist).unwrap(),
+            "\t<key>CFBundleIcons</key>\n\t<dict>\n\
+             \t\t<key>CFBundlePrimaryIcon</key>\n\t\t<dict>\n\
+             \t\t\t<key>CFBundleIconName</key>\n\t\t\t<string>AppIcon</string>\n\
+             \t\t</dict>\n\t</dict>\n"
+        );
+        assert_eq!(plist_dict_body("<plist><dict/></plist>"), None);
+    }
+
+    #[test]
+    fn ipa_layout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let app = tmp.path().join("Foo.app");
+        common::create_file(&app.join("Info.plist")).unwrap();
+        common::create_file(&app.join("_CodeSignature/CodeResources")).unwrap();
+        let ipa = tmp.path().join("Foo.ipa");
+        create_ipa(&app, &ipa, "Foo").unwrap();
+        let data = fs::read(&ipa).unwrap();
+        let contains = |name: &str| data.windows(name.len()).any(|w| w == name.as_bytes());
+        assert!(contains("Payload/Foo.app/Info.plist"));
+        assert!(contains("Payload/Foo.app/_CodeSignature/CodeResources"));
+    }
+}
@@ -0,0 +1,261 @@
+// The structure of an Alpine (apk v2) package looks something like this:
+//
+// foobar-1.2.3-r0.apk     # Actually three concatenated gzip streams:
+//     signature               # A tar (without end-of-archive blocks) holding:
+//         .SIGN.RSA.key.rsa.pub    # RSA signature of the control stream
+//     control                 # A tar (without end-of-archive blocks) holding:
+//         .PKGINFO                 # Basic package metadata
+//     data                    # A tar containing files to be installed:
+//         usr/bin/foobar                            # Binary executable file
+//         usr/share/applications/foobar.desktop     # Desktop file (for apps)
+//         usr/share/icons/hicolor/...               # Icon files (for apps)
+//         usr/lib/foobar/...                        # Other resource files
+//
+// The files are laid out exactly as in the deb bundler.  `.PKGINFO` records
+// the SHA-256 hash of the data stream, so signing the control stream covers
+// the whole package.  Signing uses the `openssl` tool, as
+// `abuild-sign` does.
+
+use crate::bundle::build_info::build_time;
+use crate::bundle::{
+    Settings, common,
+    linux::common::{append_tar_entry, collect_entries, generate_data_files, total_dir_size},
+};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The size of the end-of-archive marker that terminates a tar archive.
+const TAR_END_OF_ARCHIVE_LEN: usize = 1024;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let pkgname = super::deb_bundle::package_name(settings);
+    let pkgver = pkgver(&settings.version_string().to_string());
+    let package_base_name = format!("{pkgname}-{pkgver}");
+    let package_name = format!("{package_base_name}.apk");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/apk");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+    let build_date = build_time()?.timestamp();
+
+    // Generate the data stream.
+    let data_dir = package_dir.join("data");
+    generate_data_files(settings, &data_dir)?;
+    let mut data_tar = tar::Builder::new(Vec::new());
+    for entry in collect_entries(&data_dir)? {
+        append_tar_entry(&mut data_tar, &data_dir, &entry.path, build_date)?;
+    }
+    let data_gz_path = package_dir.join("data.tar.gz");
+    fs::write(&data_gz_path, gzip_bytes(&data_tar.into_inner()?)?)?;
+    let datahash = common::sha256_hex(&data_gz_path)?;
+
+    // Generate the control stream.
+    let control_dir = package_dir.join("control");
+    let pkginfo = generate_pkginfo(
+        settings, &pkgname, &pkgver, build_date, &data_dir, &datahash,
+    )?;
+    fs::create_dir_all(&control_dir)?;
+    fs::write(control_dir.join(".PKGINFO"), pkginfo)
+        .with_context(|| "Failed to create .PKGINFO")?;
+    let control_gz_path = package_dir.join("control.tar.gz");
+    fs::write(
+        &control_gz_path,
+        gzip_bytes(&cut_tar(
+            &control_dir,
+            &[Path::new(".PKGINFO")],
+            build_date,
+        )?)?,
+    )?;
+
+    // Sign the control stream, if there is a key to sign it with.
+    let mut streams = Vec::new();
+    match settings.apk_signing_key() {
+        Some(key_path) => {
+            let sign_name = format!(
+                ".SIGN.RSA.{}.pub",
+                key_path.file_name().unwrap().to_string_lossy()
+            );
+            let signature_dir = package_dir.join("signature");
+            fs::create_dir_all(&signature_dir)?;
+            common::run_tool(
+                Command::new("openssl")
+                    .args(["dgst", "-sha1", "-sign"])
+                    .arg(&key_path)
+                    .arg("-out")
+                    .arg(signature_dir.join(&sign_name))
+                    .arg(&control_gz_path),
+                "openssl",
+            )
+            .with_context(|| format!("Failed to sign package with {key_path:?}"))?;
+            common::record_signed(&package_path);
+            streams.push(gzip_bytes(&cut_tar(
+                &signature_dir,
+                &[Path::new(&sign_name)],
+                build_date,
+            )?)?);
+        }
+        None => common::print_warning(
+            "No `apk_signing_key` is set, so the apk package is unsigned and must be installed \
+             with `apk add --allow-untrusted`.",
+        )?,
+    }
+    streams.push(fs::read(&control_gz_path)?);
+    streams.push(fs::read(&data_gz_path)?);
+
+    let mut package_file = common::create_file(&package_path)?;
+    for stream in streams {
+        package_file.write_all(&stream)?;
+    }
+    package_file.flush()?;
+    Ok(vec![package_path])
+}
+
+/// Maps a Rust target architecture (e.g. `"arm"`) to the corresponding Alpine
+/// architecture name (e.g. `"armv7"`).
+fn arch_name(binary_arch: &str) -> &str {
+    match binary_arch {
+        "arm" => "armv7",
+        other => other,
+    }
+}
+
+/// Converts a version string to a valid Alpine package version with release
+/// `r0`.  Alpine only allows pre-release suffixes such as `_beta1`, so
+/// `1.2.3-beta.1` becomes `1.2.3_beta1-r0`, and other pre-release or build
+/// metadata is dropped.
+fn pkgver(version: &str) -> String {
+    let version = version.split('+').next().unwrap();
+    let (release, pre_release) = match version.split_once('-') {
+        Some((release, pre_release)) => (release, Some(pre_release)),
+        None => (version, None),
+    };
+    let mut pkgver = release.to_string();
+    if let Some(pre_release) = pre_release {
+        let suffix: String = pre_release
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        let word_len = suffix
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(suffix.len());
+        if matches!(&suffix[..word_len], "alpha" | "beta" | "pre" | "rc") {
+            pkgver.push('_');
+            pkgver.push_str(&suffix);
+        }
+    }
+    pkgver + "-r0"
+}
+
+/// Builds a tar archive of the given files in `dir`, without the
+/// end-of-archive marker, so that it can be concatenated with the other
+/// streams of the package.
+fn cut_tar(dir: &Path, files: &[&Path], mtime: i64) -> crate::Result<Vec<u8>> {
+    let mut tar_builder = tar::Builder::new(Vec::new());
+    for file in files {
+        append_tar_entry(&mut tar_builder, dir, file, mtime)?;
+    }
+    let mut tar = tar_builder.into_inner()?;
+    tar.truncate(tar.len() - TAR_END_OF_ARCHIVE_LEN);
+    Ok(tar)
+}
+
+fn gzip_bytes(data: &[u8]) -> crate::Result<Vec<u8>> {
+    let mut encoder = common::gzip_encoder(Vec::new())?;
+    encoder.write_all(data)?;
+    Ok(encoder.finish().into_result()?)
+}
+
+/// Generates the contents of `.PKGINFO`.
+fn generate_pkginfo(
+    settings: &Settings,
+    pkgname: &str,
+    pkgver: &str,
+    build_date: i64,
+    data_dir: &Path,
+    datahash: &str,
+) -> crate::Result<String> {
+    let mut pkginfo = String::new();
+    writeln!(pkginfo, "# Generated by cargo-bundle")?;
+    writeln!(pkginfo, "pkgname = {pkgname}")?;
+    writeln!(pkginfo, "pkgver = {pkgver}")?;
+    let mut description = settings.short_description().trim();
+    if description.is_empty() {
+        description = settings.bundle_name();
+    }
+    writeln!(pkginfo, "pkgdesc = {description}")?;
+    if !settings.homepage_url().is_empty() {
+        writeln!(pkginfo, "url = {}", settings.homepage_url())?;
+    }
+    writeln!(pkginfo, "builddate = {build_date}")?;
+    if let Some(packager) = settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+    {
+        writeln!(pkginfo, "packager = {packager}")?;
+    }
+    writeln!(pkginfo, "size = {}", total_dir_size(data_dir)?)?;
+    writeln!(pkginfo, "arch = {}", arch_name(settings.binary_arch()))?;
+    writeln!(pkginfo, "origin = {pkgname}")?;
+    if let Some(license) = settings.license() {
+        writeln!(pkginfo, "license = {license}")?;
+    }
+    for dependency in settings.apk_dependencies() {
+        writeln!(pkginfo, "depend = {dependency}")?;
+    }
+    writeln!(pkginfo, "datahash = {datahash}")?;
+    Ok(pkginfo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkgvers() {
+        assert_eq!(pkgver("1.2.3"), "1.2.3-r0");
+        assert_eq!(pkgver("1.2.3-beta.1"), "1.2.3_beta1-r0");
+        assert_eq!(pkgver("1.2.3-rc2+build.5"), "1.2.3_rc2-r0");
+        assert_eq!(pkgver("1.2.3-nightly"), "1.2.3-r0");
+        assert_eq!(pkgver("1.2.3+build.5"), "1.2.3-r0");
+    }
+
+    #[test]
+    fn control_stream_is_cut() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".PKGINFO"), "pkgname = example\n").unwrap();
+        let tar = cut_tar(dir.path(), &[Path::new(".PKGINFO")], 1700000000).unwrap();
+        // One header block and one (padded) data block.
+        assert_eq!(tar.len(), 1024);
+        let mut archive = tar::Archive::new(tar.as_slice());
+        let entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap(), Path::new(".PKGINFO"));
+        assert_eq!(entry.header().uid().unwrap(), 0);
+    }
+
+    #[test]
+    fn pkginfo() {
+        let settings = Settings::for_test("apk_depends = [\"gtk+3.0\"]\n");
+        let data_dir = tempfile::tempdir().unwrap();
+        let pkginfo = generate_pkginfo(
+            &settings,
+            "example",
+            "1.0.0-r0",
+            1700000000,
+            data_dir.path(),
+            "abc123",
+        )
+        .unwrap();
+        assert!(pkginfo.contains("pkgname = example\npkgver = 1.0.0-r0\n"));
+        assert!(pkginfo.contains("origin = example\n"));
+        assert!(pkginfo.contains("depend = gtk+3.0\n"));
+        assert!(pkginfo.ends_with("datahash = abc123\n"));
+    }
+}
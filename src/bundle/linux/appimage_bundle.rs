@@ -4,13 +4,15 @@ use std::os::unix::fs::PermissionsExt;
 use std::{
     fs::File,
     io::{BufReader, BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Command,
 };
 
-use crate::bundle::{Settings, common};
+use crate::bundle::{Settings, common, settings::AppImageCompression};
 
-use super::common::{generate_desktop_file, generate_icon_files};
+use super::common::{
+    find_largest_icon, generate_desktop_file, generate_icon_files, install_binary,
+};
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     let package_base_name = format!(
@@ -32,34 +34,52 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
 
     let app_dir = package_dir.join("AppDir");
     let binary_dest_rel = PathBuf::from("usr/bin").join(settings.binary_name());
-    let binary_dest_abs = app_dir.join(binary_dest_rel.clone());
-    common::copy_file(settings.binary_path(), &binary_dest_abs)?;
+    install_binary(settings, &app_dir)?;
+    common::copy_external_binaries(
+        settings,
+        &app_dir.join("usr/lib").join(settings.binary_name()),
+    )?;
     generate_icon_files(settings, &app_dir)?;
     generate_desktop_file(settings, &app_dir)?;
+    super::appstream::generate_metainfo(settings, &app_dir)?;
 
-    // TODO Symlinks (AppRun, .DirIcon, .desktop)
+    // The AppImage spec expects the entry point, desktop file and icon to be at
+    // the root of the AppDir.
     common::symlink_file(&binary_dest_rel, &app_dir.join("AppRun"))?;
+    let desktop_file_name = format!("{}.desktop", settings.binary_name());
+    common::symlink_file(
+        &Path::new("usr/share/applications").join(&desktop_file_name),
+        &app_dir.join(&desktop_file_name),
+    )?;
+    if let Some(icon_rel) = find_largest_icon(&app_dir, settings.binary_name()) {
+        let icon_name = icon_rel.file_name().unwrap();
+        common::symlink_file(&icon_rel, &app_dir.join(icon_name))?;
+        common::symlink_file(Path::new(icon_name), &app_dir.join(".DirIcon"))?;
+    }
 
     // Download the AppImage runtime
     let runtime = fetch_runtime(settings.binary_arch())?;
 
     // Make the squashfs
     let squashfs = base_dir.join(format!("{package_name}.squashfs"));
-    let _status = Command::new("mksquashfs")
-        .arg(&app_dir)
-        .arg(&squashfs)
-        .arg("-root-owned")
-        .arg("-noappend")
-        .arg("-quiet")
+    let status = mksquashfs_command(&app_dir, &squashfs, settings.appimage_compression())
         .status()
         .with_context(|| "Failed to make sqaushfs, does the mksquashfs binary exist?")?;
+    if !status.success() {
+        anyhow::bail!("mksquashfs failed with status: {status}");
+    }
 
     // Write the runtime and the fs to the .AppImage file
-    let mut squashfs = BufReader::new(File::open(squashfs)?);
-    let mut f = File::create(&package_path)?;
-    let mut out = BufWriter::new(&mut f);
-    out.write_all(&runtime)?;
-    std::io::copy(&mut squashfs, &mut out)?;
+    {
+        let mut squashfs = BufReader::new(File::open(&squashfs)?);
+        let mut f = File::create(&package_path)?;
+        let mut out = BufWriter::new(&mut f);
+        out.write_all(&runtime)?;
+        std::io::copy(&mut squashfs, &mut out)?;
+        out.flush()?;
+    }
+    std::fs::remove_file(&squashfs)
+        .with_context(|| format!("Failed to remove temporary {squashfs:?}"))?;
 
     #[allow(unused_mut)]
     let mut perms = std::fs::metadata(&package_path)?.permissions();
@@ -70,6 +90,26 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     Ok(vec![package_path])
 }
 
+/// Builds the `mksquashfs` invocation that packs `app_dir` into `squashfs`,
+/// using the given compression algorithm (or the tool's default if `None`).
+pub(crate) fn mksquashfs_command(
+    app_dir: &Path,
+    squashfs: &Path,
+    compression: Option<AppImageCompression>,
+) -> Command {
+    let mut command = Command::new("mksquashfs");
+    command
+        .arg(app_dir)
+        .arg(squashfs)
+        .arg("-root-owned")
+        .arg("-noappend")
+        .arg("-quiet");
+    if let Some(compression) = compression {
+        command.arg("-comp").arg(compression.name());
+    }
+    command
+}
+
 fn fetch_runtime(arch: &str) -> crate::Result<Vec<u8>> {
     let url = format!(
         "https://github.com/AppImage/type2-runtime/releases/download/continuous/runtime-{arch}"
@@ -82,3 +122,25 @@ fn fetch_runtime(arch: &str) -> crate::Result<Vec<u8>> {
 
     Ok(response.to_vec())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn mksquashfs_command_compression() {
+        let app_dir = Path::new("AppDir");
+        let squashfs = Path::new("foo.AppImage.squashfs");
+
+        let command = mksquashfs_command(app_dir, squashfs, Some(AppImageCompression::Zstd));
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert!(
+            args.windows(2)
+                .any(|w| w == [OsStr::new("-comp"), OsStr::new("zstd")])
+        );
+
+        let command = mksquashfs_command(app_dir, squashfs, None);
+        assert!(!command.get_args().any(|arg| arg == "-comp"));
+    }
+}
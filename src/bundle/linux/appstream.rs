@@ -0,0 +1,262 @@
+// Software centers such as GNOME Software and KDE Discover only list the apps
+// that describe themselves in AppStream metainfo, installed as
+// `usr/share/metainfo/<id>.metainfo.xml`:
+//
+// <component type="desktop-application">
+//   <id>com.example.foobar</id>                  # The bundle `identifier`
+//   <name>Foobar</name>
+//   <summary>Frobnicates the bars</summary>
+//   <description><p>...</p></description>         # The `long_description`
+//   <launchable type="desktop-id">foobar.desktop</launchable>
+//   <screenshots>...</screenshots>                 # The `linux_screenshots`
+//   <releases>                                     # From the `CHANGELOG.md`
+//     <release version="1.2.3" date="2024-01-02">...</release>
+//   </releases>
+// </component>
+//
+// The releases are read from the same changelog as the `deb` changelog, with
+// a release for the version being bundled if the changelog has none.  The
+// `linux_metainfo` setting installs a hand-written file instead.  See
+// https://www.freedesktop.org/software/appstream/docs/ for the format.
+
+use super::changelog::{self, Entry};
+use crate::bundle::{Settings, common};
+use quick_xml::escape::escape;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Installs the metainfo of the app into `usr/share/metainfo` under the
+/// `data_dir`, unless it has no bundle `identifier` to name it after.
+pub fn generate_metainfo(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let id = settings.bundle_identifier();
+    if id.is_empty() {
+        return Ok(());
+    }
+    let path = data_dir.join(format!("usr/share/metainfo/{id}.metainfo.xml"));
+    if let Some(metainfo) = settings.linux_metainfo() {
+        return common::copy_file(&metainfo, &path);
+    }
+    let releases = changelog::releases(settings, &settings.version_string().to_string())?;
+    super::common::create_file_with_data(&path, &metainfo(settings, &id, &releases))
+}
+
+/// Returns the contents of the metainfo of the app whose identifier is `id`.
+fn metainfo(settings: &Settings, id: &str, releases: &[Entry]) -> String {
+    let summary = settings.short_description().trim();
+    let summary = if summary.is_empty() {
+        settings.bundle_name()
+    } else {
+        summary
+    };
+    let description = settings.long_description().unwrap_or(summary);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(xml, "<component type=\"desktop-application\">").unwrap();
+    writeln!(xml, "  <id>{}</id>", escape(id)).unwrap();
+    writeln!(xml, "  <metadata_license>CC0-1.0</metadata_license>").unwrap();
+    if let Some(license) = settings.license() {
+        // AppStream expects the operators of SPDX expressions in uppercase,
+        // rather than the legacy `MIT/Apache-2.0`.
+        let license = license.replace('/', " OR ");
+        writeln!(
+            xml,
+            "  <project_license>{}</project_license>",
+            escape(&license)
+        )
+        .unwrap();
+    }
+    writeln!(xml, "  <name>{}</name>", escape(settings.bundle_name())).unwrap();
+    // Summaries are not sentences, so they do not end with a period.
+    let summary = summary.strip_suffix('.').unwrap_or(summary);
+    writeln!(xml, "  <summary>{}</summary>", escape(summary)).unwrap();
+    writeln!(xml, "  <description>").unwrap();
+    for paragraph in paragraphs(description) {
+        writeln!(xml, "    <p>{}</p>", escape(&paragraph)).unwrap();
+    }
+    writeln!(xml, "  </description>").unwrap();
+    writeln!(
+        xml,
+        "  <launchable type=\"desktop-id\">{}.desktop</launchable>",
+        escape(settings.binary_name())
+    )
+    .unwrap();
+    if !settings.homepage_url().is_empty() {
+        writeln!(
+            xml,
+            "  <url type=\"homepage\">{}</url>",
+            escape(settings.homepage_url())
+        )
+        .unwrap();
+    }
+    if let Some(repository) = settings.repository_url() {
+        writeln!(
+            xml,
+            "  <url type=\"vcs-browser\">{}</url>",
+            escape(repository)
+        )
+        .unwrap();
+    }
+    if let Some(author) = settings.author_names().first() {
+        // Authors are written as `Name <email>`.
+        let name = author.split('<').next().unwrap_or(author).trim();
+        writeln!(
+            xml,
+            "  <developer>\n    <name>{}</name>\n  </developer>",
+            escape(name)
+        )
+        .unwrap();
+    }
+    if !settings.linux_screenshots().is_empty() {
+        writeln!(xml, "  <screenshots>").unwrap();
+        for (i, screenshot) in settings.linux_screenshots().iter().enumerate() {
+            if i == 0 {
+                writeln!(xml, "    <screenshot type=\"default\">").unwrap();
+            } else {
+                writeln!(xml, "    <screenshot>").unwrap();
+            }
+            if let Some(caption) = &screenshot.caption {
+                writeln!(xml, "      <caption>{}</caption>", escape(caption)).unwrap();
+            }
+            writeln!(xml, "      <image>{}</image>", escape(&screenshot.url)).unwrap();
+            writeln!(xml, "    </screenshot>").unwrap();
+        }
+        writeln!(xml, "  </screenshots>").unwrap();
+    }
+    writeln!(xml, "  <releases>").unwrap();
+    for release in releases {
+        let version = escape(&release.version);
+        let date = match release.date {
+            Some(date) => format!(" date=\"{}\"", date.format("%Y-%m-%d")),
+            None => String::new(),
+        };
+        // Nested changes are left out, as release notes may not nest lists.
+        let changes: Vec<&str> = release
+            .changes
+            .iter()
+            .filter(|(top_level, _)| *top_level)
+            .map(|(_, change)| change.as_str())
+            .collect();
+        if changes.is_empty() {
+            writeln!(xml, "    <release version=\"{version}\"{date}/>").unwrap();
+            continue;
+        }
+        writeln!(xml, "    <release version=\"{version}\"{date}>").unwrap();
+        writeln!(xml, "      <description>\n        <ul>").unwrap();
+        for change in changes {
+            writeln!(xml, "          <li>{}</li>", escape(change)).unwrap();
+        }
+        writeln!(xml, "        </ul>\n      </description>").unwrap();
+        writeln!(xml, "    </release>").unwrap();
+    }
+    writeln!(xml, "  </releases>").unwrap();
+    writeln!(xml, "  <content_rating type=\"oars-1.1\"/>").unwrap();
+    writeln!(xml, "</component>").unwrap();
+    xml
+}
+
+/// Splits `text` into its paragraphs, separated by blank lines, joining the
+/// lines of each.
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().chain([""]) {
+        let line = line.trim();
+        if !line.is_empty() {
+            lines.push(line);
+        } else if !lines.is_empty() {
+            paragraphs.push(lines.join(" "));
+            lines.clear();
+        }
+    }
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn metainfo_file() {
+        let settings = Settings::for_test(
+            r#"
+            name = "Tom & Jerry"
+            identifier = "com.example.tom-and-jerry"
+            short_description = "Chases mice."
+            long_description = "A cat\nand a mouse.\n\nThe end."
+            linux_screenshots = [
+                { url = "https://example.com/main.png", caption = "Main window" },
+                { url = "https://example.com/other.png" },
+            ]
+            "#,
+        );
+        let releases = [
+            Entry {
+                version: "1.1.0".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 2),
+                changes: vec![
+                    (true, "Faster <chases>".to_string()),
+                    (false, "Nested".to_string()),
+                ],
+            },
+            Entry {
+                version: "1.0.0".to_string(),
+                date: None,
+                changes: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            metainfo(&settings, "com.example.tom-and-jerry", &releases),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <component type=\"desktop-application\">\n  \
+             <id>com.example.tom-and-jerry</id>\n  \
+             <metadata_license>CC0-1.0</metadata_license>\n  \
+             <name>Tom &amp; Jerry</name>\n  \
+             <summary>Chases mice</summary>\n  \
+             <description>\n    \
+             <p>A cat and a mouse.</p>\n    \
+             <p>The end.</p>\n  \
+             </description>\n  \
+             <launchable type=\"desktop-id\">example.desktop</launchable>\n  \
+             <developer>\n    <name>Jane Doe</name>\n  </developer>\n  \
+             <screenshots>\n    \
+             <screenshot type=\"default\">\n      \
+             <caption>Main window</caption>\n      \
+             <image>https://example.com/main.png</image>\n    \
+             </screenshot>\n    \
+             <screenshot>\n      \
+             <image>https://example.com/other.png</image>\n    \
+             </screenshot>\n  \
+             </screenshots>\n  \
+             <releases>\n    \
+             <release version=\"1.1.0\" date=\"2024-01-02\">\n      \
+             <description>\n        <ul>\n          \
+             <li>Faster &lt;chases&gt;</li>\n        \
+             </ul>\n      </description>\n    \
+             </release>\n    \
+             <release version=\"1.0.0\"/>\n  \
+             </releases>\n  \
+             <content_rating type=\"oars-1.1\"/>\n\
+             </component>\n"
+        );
+    }
+
+    #[test]
+    fn metainfo_release() {
+        let settings = Settings::for_test("identifier = \"com.example.app\"");
+        let tmp = tempfile::tempdir().unwrap();
+        generate_metainfo(&settings, tmp.path()).unwrap();
+        let xml = std::fs::read_to_string(
+            tmp.path()
+                .join("usr/share/metainfo/com.example.app.metainfo.xml"),
+        )
+        .unwrap();
+        assert!(xml.contains("    <release version=\"1.0.0\" date=\""));
+
+        // Apps without an identifier have no metainfo.
+        let settings = Settings::for_test("");
+        let tmp = tempfile::tempdir().unwrap();
+        generate_metainfo(&settings, tmp.path()).unwrap();
+        assert!(!tmp.path().join("usr/share/metainfo").exists());
+    }
+}
@@ -0,0 +1,282 @@
+// An AUR package is published as a git repository holding a PKGBUILD (which
+// `makepkg` builds the package from) and its metadata in `.SRCINFO`.  We
+// generate a `-bin` package, which repackages the `archive` bundle rather than
+// building from source:
+//
+// bundle/aur/foobar-bin/
+//     PKGBUILD        # Downloads and installs the archive
+//     .SRCINFO        # The PKGBUILD's metadata, as the AUR expects it
+//
+// The archive is installed with the same layout as the pacman bundler uses.
+// If `aur_source_url` is not set, the archive is copied next to the PKGBUILD,
+// which is enough to build the package locally but not to publish it.
+
+use super::{depends, pacman_bundle};
+use crate::bundle::{Settings, archive_bundle, common};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// The release number of the package, which is always 1 since we package the
+/// upstream version directly.
+const PKGREL: &str = "1";
+
+/// The metadata shared by `PKGBUILD` and `.SRCINFO`.
+struct PackageInfo {
+    pkgname: String,
+    pkgver: String,
+    arch: String,
+    /// The source entry for the archive: either `name::url`, or the name of
+    /// the local copy.
+    source: String,
+    sha256: String,
+    depends: Vec<String>,
+    /// The top-level directory of the archive.
+    src_dir: String,
+}
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let archive_paths = archive_bundle::bundle_project(settings)?;
+    let archive_path = &archive_paths[0];
+    let archive_name = archive_path.file_name().unwrap().to_string_lossy();
+
+    let name = super::deb_bundle::package_name(settings);
+    let pkgname = format!("{name}-bin");
+    common::print_bundling(&format!("{pkgname}/PKGBUILD"))?;
+    let package_dir = settings
+        .project_out_directory()
+        .join("bundle/aur")
+        .join(&pkgname);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {pkgname}"))?;
+    }
+    fs::create_dir_all(&package_dir)?;
+
+    let source = match settings.aur_source_url() {
+        Some(template) => {
+            let version = settings.version_string().to_string();
+            let tokens = [
+                ("name", settings.binary_name()),
+                ("version", version.as_str()),
+                ("file", archive_name.as_ref()),
+            ];
+            let url = common::expand_template(template, &tokens)
+                .context("Failed to expand `aur_source_url`")?;
+            format!("{archive_name}::{url}")
+        }
+        None => {
+            common::print_warning(
+                "No `aur_source_url` is set, so the PKGBUILD refers to a local copy of the \
+                 archive and cannot be published to the AUR.",
+            )?;
+            common::copy_file(archive_path, &package_dir.join(archive_name.as_ref()))?;
+            archive_name.to_string()
+        }
+    };
+    let info = PackageInfo {
+        pkgname,
+        pkgver: pacman_bundle::pkgver(&settings.version_string().to_string()),
+        arch: pacman_bundle::arch_name(settings.binary_arch()).to_string(),
+        source,
+        sha256: common::sha256_hex(archive_path)?,
+        depends: dependencies(settings)?,
+        src_dir: archive_bundle::top_level_dir(settings)?,
+    };
+    fs::write(
+        package_dir.join("PKGBUILD"),
+        generate_pkgbuild(settings, &name, &info),
+    )
+    .context("Failed to write PKGBUILD")?;
+    fs::write(
+        package_dir.join(".SRCINFO"),
+        generate_srcinfo(settings, &name, &info),
+    )
+    .context("Failed to write .SRCINFO")?;
+    Ok(vec![package_dir])
+}
+
+/// Returns the package's dependencies: the `pacman_depends` setting, plus
+/// the packages providing the shared libraries the binary links against.
+fn dependencies(settings: &Settings) -> crate::Result<Vec<String>> {
+    let mut depends: Vec<String> = settings.pacman_dependencies().to_vec();
+    for package in depends::pacman_depends(settings)? {
+        if !depends.iter().any(|d| dependency_name(d) == package) {
+            depends.push(package);
+        }
+    }
+    Ok(depends)
+}
+
+/// Strips any version constraint from a dependency (e.g. `"openssl>=3"`).
+fn dependency_name(dependency: &str) -> &str {
+    dependency
+        .split(['<', '>', '='])
+        .next()
+        .unwrap_or(dependency)
+}
+
+/// Quotes a string as a single-quoted shell word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn shell_array(values: &[String]) -> String {
+    let quoted: Vec<String> = values.iter().map(|v| shell_quote(v)).collect();
+    format!("({})", quoted.join(" "))
+}
+
+fn licenses(settings: &Settings) -> Vec<String> {
+    settings
+        .license()
+        .map(|license| {
+            license
+                .split(" OR ")
+                .flat_map(|l| l.split('/'))
+                .map(|l| l.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn description(settings: &Settings) -> &str {
+    let description = settings.short_description().trim();
+    if description.is_empty() {
+        settings.bundle_name()
+    } else {
+        description
+    }
+}
+
+/// Generates the contents of `PKGBUILD`.
+fn generate_pkgbuild(settings: &Settings, name: &str, info: &PackageInfo) -> String {
+    let mut pkgbuild = String::new();
+    if let Some(authors) = settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+    {
+        writeln!(pkgbuild, "# Maintainer: {authors}").unwrap();
+    }
+    writeln!(pkgbuild, "pkgname={}", info.pkgname).unwrap();
+    writeln!(pkgbuild, "pkgver={}", info.pkgver).unwrap();
+    writeln!(pkgbuild, "pkgrel={PKGREL}").unwrap();
+    writeln!(pkgbuild, "pkgdesc={}", shell_quote(description(settings))).unwrap();
+    writeln!(pkgbuild, "arch=({})", shell_quote(&info.arch)).unwrap();
+    if !settings.homepage_url().is_empty() {
+        writeln!(pkgbuild, "url={}", shell_quote(settings.homepage_url())).unwrap();
+    }
+    writeln!(pkgbuild, "license={}", shell_array(&licenses(settings))).unwrap();
+    writeln!(pkgbuild, "depends={}", shell_array(&info.depends)).unwrap();
+    writeln!(pkgbuild, "provides=({})", shell_quote(name)).unwrap();
+    writeln!(pkgbuild, "conflicts=({})", shell_quote(name)).unwrap();
+    writeln!(pkgbuild, "source=({})", shell_quote(&info.source)).unwrap();
+    writeln!(pkgbuild, "sha256sums=({})", shell_quote(&info.sha256)).unwrap();
+    writeln!(pkgbuild).unwrap();
+    writeln!(pkgbuild, "package() {{").unwrap();
+    writeln!(pkgbuild, "  cd {}", shell_quote(&info.src_dir)).unwrap();
+    let binary_name = shell_quote(settings.binary_name());
+    writeln!(
+        pkgbuild,
+        "  install -Dm755 {binary_name} \"$pkgdir/usr/bin/\"{binary_name}"
+    )
+    .unwrap();
+    if settings.license_content().is_some() {
+        writeln!(
+            pkgbuild,
+            "  install -Dm644 LICENSE \"$pkgdir/usr/share/licenses/$pkgname/LICENSE\""
+        )
+        .unwrap();
+    }
+    let lib_dir = format!("\"$pkgdir/usr/lib/\"{binary_name}");
+    for binary in settings.external_binaries().into_iter().flatten() {
+        let name = shell_quote(&binary.name);
+        writeln!(pkgbuild, "  install -Dm755 {name} {lib_dir}/{name}").unwrap();
+    }
+    for resource in settings.resource_files().flatten() {
+        // The archive holds the resources at their destination paths.
+        let relpath = shell_quote(&resource.dest.to_string_lossy());
+        let mode = resource.mode.unwrap_or(0o644);
+        writeln!(
+            pkgbuild,
+            "  install -Dm{mode:o} {relpath} {lib_dir}/{relpath}"
+        )
+        .unwrap();
+    }
+    writeln!(pkgbuild, "}}").unwrap();
+    pkgbuild
+}
+
+/// Generates the contents of `.SRCINFO`, as `makepkg --printsrcinfo` would.
+fn generate_srcinfo(settings: &Settings, name: &str, info: &PackageInfo) -> String {
+    let mut srcinfo = String::new();
+    writeln!(srcinfo, "pkgbase = {}", info.pkgname).unwrap();
+    writeln!(srcinfo, "\tpkgdesc = {}", description(settings)).unwrap();
+    writeln!(srcinfo, "\tpkgver = {}", info.pkgver).unwrap();
+    writeln!(srcinfo, "\tpkgrel = {PKGREL}").unwrap();
+    if !settings.homepage_url().is_empty() {
+        writeln!(srcinfo, "\turl = {}", settings.homepage_url()).unwrap();
+    }
+    writeln!(srcinfo, "\tarch = {}", info.arch).unwrap();
+    for license in licenses(settings) {
+        writeln!(srcinfo, "\tlicense = {license}").unwrap();
+    }
+    for dependency in &info.depends {
+        writeln!(srcinfo, "\tdepends = {dependency}").unwrap();
+    }
+    writeln!(srcinfo, "\tprovides = {name}").unwrap();
+    writeln!(srcinfo, "\tconflicts = {name}").unwrap();
+    writeln!(srcinfo, "\tsource = {}", info.source).unwrap();
+    writeln!(srcinfo, "\tsha256sums = {}", info.sha256).unwrap();
+    writeln!(srcinfo).unwrap();
+    writeln!(srcinfo, "pkgname = {}", info.pkgname).unwrap();
+    srcinfo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package_info() -> PackageInfo {
+        PackageInfo {
+            pkgname: "example-bin".to_string(),
+            pkgver: "1.0.0".to_string(),
+            arch: "x86_64".to_string(),
+            source: "example.tar.gz::https://example.com/example.tar.gz".to_string(),
+            sha256: "abc123".to_string(),
+            depends: vec!["gtk3".to_string(), "glibc".to_string()],
+            src_dir: "example-1.0.0-x86_64-unknown-linux-gnu".to_string(),
+        }
+    }
+
+    #[test]
+    fn dependency_names() {
+        assert_eq!(dependency_name("openssl>=3"), "openssl");
+    }
+
+    #[test]
+    fn pkgbuild() {
+        let settings = Settings::for_test("short_description = \"Bob's app\"\n");
+        let pkgbuild = generate_pkgbuild(&settings, "example", &package_info());
+        assert!(pkgbuild.contains("pkgname=example-bin\npkgver=1.0.0\npkgrel=1\n"));
+        assert!(pkgbuild.contains("pkgdesc='Bob'\\''s app'\n"));
+        assert!(pkgbuild.contains("depends=('gtk3' 'glibc')\n"));
+        assert!(
+            pkgbuild.contains("source=('example.tar.gz::https://example.com/example.tar.gz')\n")
+        );
+        assert!(pkgbuild.contains(
+            "  cd 'example-1.0.0-x86_64-unknown-linux-gnu'\n  \
+             install -Dm755 'example' \"$pkgdir/usr/bin/\"'example'\n"
+        ));
+    }
+
+    #[test]
+    fn srcinfo() {
+        let settings = Settings::for_test("short_description = \"An example\"\n");
+        let srcinfo = generate_srcinfo(&settings, "example", &package_info());
+        assert!(srcinfo.starts_with("pkgbase = example-bin\n\tpkgdesc = An example\n"));
+        assert!(srcinfo.contains("\tdepends = gtk3\n\tdepends = glibc\n"));
+        assert!(srcinfo.contains("\tsha256sums = abc123\n"));
+        assert!(srcinfo.ends_with("\npkgname = example-bin\n"));
+    }
+}
@@ -0,0 +1,388 @@
+// Debian packages ship their changelog as `usr/share/doc/<package>/changelog.gz`,
+// in the format of `debian/changelog`:
+//
+// foobar (1.2.3) unstable; urgency=medium
+//
+//   * Fix the frobnicator.
+//
+//  -- Jane Doe <jane@example.com>  Tue, 02 Jan 2024 00:00:00 +0000
+//
+// The changelog is taken from the `deb_changelog` setting, or else the
+// `CHANGELOG.md` next to `Cargo.toml`.  A Markdown changelog, as in the "Keep a
+// Changelog" style, is converted: each `## ` heading with a version (and
+// optionally a `YYYY-MM-DD` date) starts an entry, and its list items become
+// the entry's changes.  If the changelog has no entry for the version being
+// bundled, a minimal one is added at the top.
+//
+// The `%changelog` of rpm spec files, and the releases of AppStream metainfo,
+// are converted from the same entries:
+//
+// * Tue Jan 02 2024 Jane Doe <jane@example.com> - 1.2.3-1
+// - Fix the frobnicator.
+
+use crate::bundle::build_info::build_time;
+use crate::bundle::{Settings, common};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// The longest lines lintian accepts in changelogs.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// A version's entry of the changelog.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub version: String,
+    pub date: Option<NaiveDate>,
+    /// The changes, as lines without their indentation, and whether each line
+    /// starts a change or is nested in the previous one.
+    pub changes: Vec<(bool, String)>,
+}
+
+/// Returns the Debian changelog for the package named `package`, whose
+/// version is `version`, written by `maintainer`.
+pub fn debian_changelog(
+    settings: &Settings,
+    package: &str,
+    version: &str,
+    maintainer: &str,
+) -> crate::Result<String> {
+    let mut entries = Vec::new();
+    if let Some(path) = changelog_path(settings) {
+        let text = common::read_file(&path)?;
+        if !is_markdown(&path) {
+            return Ok(text);
+        }
+        entries = parse_markdown(&text);
+    }
+    add_entry(&mut entries, version);
+    let now = build_time()?;
+    Ok(format_changelog(&entries, package, maintainer, now))
+}
+
+/// Returns the `%changelog` of rpm spec files for the package, whose version
+/// is `version`, packaged by `packager`.  Only a Markdown changelog is
+/// converted; a Debian changelog is left out.
+pub fn rpm_changelog(settings: &Settings, version: &str, packager: &str) -> crate::Result<String> {
+    let entries = markdown_entries(settings, version)?;
+    let now = build_time()?;
+    Ok(format_rpm_changelog(&entries, packager, now))
+}
+
+/// Returns the entries of the Markdown changelog, starting with one for
+/// `version`, which is dated at the time of the build if it has no date.
+pub fn releases(settings: &Settings, version: &str) -> crate::Result<Vec<Entry>> {
+    let mut entries = markdown_entries(settings, version)?;
+    if entries[0].date.is_none() {
+        entries[0].date = Some(build_time()?.date_naive());
+    }
+    Ok(entries)
+}
+
+/// Returns the entries of the changelog if it is in Markdown, starting with
+/// one for `version`.
+fn markdown_entries(settings: &Settings, version: &str) -> crate::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    if let Some(path) = changelog_path(settings).filter(|path| is_markdown(path)) {
+        entries = parse_markdown(&common::read_file(&path)?);
+    }
+    add_entry(&mut entries, version);
+    Ok(entries)
+}
+
+/// Returns the path of the changelog: the `deb_changelog` setting, or else
+/// the `CHANGELOG.md` next to `Cargo.toml`, if there is one.
+fn changelog_path(settings: &Settings) -> Option<PathBuf> {
+    match settings.deb_changelog() {
+        Some(path) => Some(path),
+        None => {
+            let dir = settings.manifest_path().parent().map(PathBuf::from);
+            dir.map(|dir| dir.join("CHANGELOG.md"))
+                .filter(|path| path.is_file())
+        }
+    }
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "md")
+}
+
+/// Adds a minimal entry for `version` at the top of the `entries`, unless
+/// they start with one.
+fn add_entry(entries: &mut Vec<Entry>, version: &str) {
+    if entries.first().is_none_or(|entry| entry.version != version) {
+        entries.insert(
+            0,
+            Entry {
+                version: version.to_string(),
+                date: None,
+                changes: Vec::new(),
+            },
+        );
+    }
+}
+
+/// Parses the entries of a Markdown changelog, skipping the sections without
+/// a version, such as `## [Unreleased]`.
+fn parse_markdown(text: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut in_entry = false;
+    for line in text.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            in_entry = match parse_heading(heading) {
+                Some((version, date)) => {
+                    entries.push(Entry {
+                        version,
+                        date,
+                        changes: Vec::new(),
+                    });
+                    true
+                }
+                None => false,
+            };
+            continue;
+        }
+        if line.starts_with('#') || !in_entry || line.trim().is_empty() {
+            continue;
+        }
+        let changes = &mut entries.last_mut().unwrap().changes;
+        let indented = line.starts_with(char::is_whitespace);
+        let trimmed = line.trim();
+        let item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "));
+        match item {
+            Some(item) => changes.push((!indented, item.trim().to_string())),
+            // A line continuing the previous item.
+            None => match changes.last_mut() {
+                Some((_, change)) => {
+                    change.push(' ');
+                    change.push_str(trimmed);
+                }
+                None => changes.push((true, trimmed.to_string())),
+            },
+        }
+    }
+    entries
+}
+
+/// Returns the version and date in a heading such as `[1.2.3] - 2024-01-02`
+/// or `v1.2.3 (2024-01-02)`, or `None` if it has no version.
+fn parse_heading(heading: &str) -> Option<(String, Option<NaiveDate>)> {
+    let mut words = heading
+        .split(|c: char| c.is_whitespace() || "[]()".contains(c))
+        .filter(|word| !word.is_empty() && *word != "-");
+    let version = words.next()?;
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let date = words.find_map(|word| NaiveDate::parse_from_str(word, "%Y-%m-%d").ok());
+    Some((version.to_string(), date))
+}
+
+/// Formats the `entries` in the Debian format, giving the entries without a
+/// date the time `now`.
+fn format_changelog(
+    entries: &[Entry],
+    package: &str,
+    maintainer: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let mut changelog = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            changelog.push('\n');
+        }
+        let version = super::deb_bundle::deb_version(&entry.version);
+        writeln!(
+            changelog,
+            "{package} ({version}) unstable; urgency=medium\n"
+        )
+        .unwrap();
+        if entry.changes.is_empty() {
+            writeln!(changelog, "  * Release {}.", entry.version).unwrap();
+        }
+        for (top_level, change) in &entry.changes {
+            if *top_level {
+                wrap(&mut changelog, change, "  * ", "    ");
+            } else {
+                wrap(&mut changelog, change, "    - ", "      ");
+            }
+        }
+        let date = match entry.date {
+            Some(date) => date.and_time(chrono::NaiveTime::MIN).and_utc(),
+            None => now,
+        };
+        let date = date.format("%a, %d %b %Y %H:%M:%S %z");
+        writeln!(changelog, "\n -- {maintainer}  {date}").unwrap();
+    }
+    changelog
+}
+
+/// Formats the `entries` as the `%changelog` of a spec file, giving the
+/// entries without a date the time `now`.
+fn format_rpm_changelog(entries: &[Entry], packager: &str, now: DateTime<Utc>) -> String {
+    let mut changelog = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            changelog.push('\n');
+        }
+        let version = super::rpm_bundle::rpm_version(&entry.version);
+        let date = match entry.date {
+            Some(date) => date,
+            None => now.date_naive(),
+        };
+        let date = date.format("%a %b %d %Y");
+        let release = super::rpm_bundle::RELEASE;
+        writeln!(changelog, "* {date} {packager} - {version}-{release}").unwrap();
+        if entry.changes.is_empty() {
+            writeln!(changelog, "- Release {}.", entry.version).unwrap();
+        }
+        for (top_level, change) in &entry.changes {
+            // A `%` would start a macro.
+            let change = change.replace('%', "%%");
+            if *top_level {
+                wrap(&mut changelog, &change, "- ", "  ");
+            } else {
+                wrap(&mut changelog, &change, "  - ", "    ");
+            }
+        }
+    }
+    changelog
+}
+
+/// Appends `text` to `changelog`, wrapped to `MAX_LINE_LENGTH`, with `first`
+/// before the first line and `rest` before the others.
+fn wrap(changelog: &mut String, text: &str, first: &str, rest: &str) {
+    let mut line = first.to_string();
+    let mut empty = true;
+    for word in text.split_whitespace() {
+        if !empty && line.len() + 1 + word.len() > MAX_LINE_LENGTH {
+            changelog.push_str(&line);
+            changelog.push('\n');
+            line = rest.to_string();
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        empty = false;
+    }
+    changelog.push_str(&line);
+    changelog.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_changelog() {
+        let entries = parse_markdown(
+            "# Changelog\n\
+             \n\
+             ## [Unreleased]\n\
+             - Not yet\n\
+             \n\
+             ## [1.2.0] - 2024-01-02\n\
+             ### Added\n\
+             - A frobnicator, which is very long and needs to be wrapped since lintian does not\n\
+             \x20 like long lines.\n\
+             \x20 - Nested\n\
+             \n\
+             ## v1.1.0\n",
+        );
+        assert_eq!(
+            entries,
+            [
+                Entry {
+                    version: "1.2.0".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 1, 2),
+                    changes: vec![
+                        (
+                            true,
+                            "A frobnicator, which is very long and needs to be wrapped since \
+                             lintian does not like long lines."
+                                .to_string()
+                        ),
+                        (false, "Nested".to_string()),
+                    ],
+                },
+                Entry {
+                    version: "1.1.0".to_string(),
+                    date: None,
+                    changes: Vec::new(),
+                },
+            ]
+        );
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            format_changelog(&entries, "foo", "Jane Doe <jane@example.com>", now),
+            "foo (1.2.0) unstable; urgency=medium\n\
+             \n\
+             \x20 * A frobnicator, which is very long and needs to be wrapped since lintian does\n\
+             \x20   not like long lines.\n\
+             \x20   - Nested\n\
+             \n\
+             \x20-- Jane Doe <jane@example.com>  Tue, 02 Jan 2024 00:00:00 +0000\n\
+             \n\
+             foo (1.1.0) unstable; urgency=medium\n\
+             \n\
+             \x20 * Release 1.1.0.\n\
+             \n\
+             \x20-- Jane Doe <jane@example.com>  Tue, 14 Nov 2023 22:13:20 +0000\n"
+        );
+    }
+
+    #[test]
+    fn rpm_changelogs() {
+        let entries = [
+            Entry {
+                version: "1.2.0-beta.1".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 1, 2),
+                changes: vec![
+                    (true, "Speed up by 50%".to_string()),
+                    (false, "Nested".to_string()),
+                ],
+            },
+            Entry {
+                version: "1.1.0".to_string(),
+                date: None,
+                changes: Vec::new(),
+            },
+        ];
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            format_rpm_changelog(&entries, "Jane Doe <jane@example.com>", now),
+            "* Tue Jan 02 2024 Jane Doe <jane@example.com> - 1.2.0~beta.1-1\n\
+             - Speed up by 50%%\n\
+             \x20 - Nested\n\
+             \n\
+             * Tue Nov 14 2023 Jane Doe <jane@example.com> - 1.1.0-1\n\
+             - Release 1.1.0.\n"
+        );
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(
+            parse_heading("1.2.3-beta.1 (2024-01-02)"),
+            Some((
+                "1.2.3-beta.1".to_string(),
+                NaiveDate::from_ymd_opt(2024, 1, 2)
+            ))
+        );
+        assert_eq!(parse_heading("[Unreleased]"), None);
+    }
+
+    #[test]
+    fn minimal_changelog() {
+        let settings = Settings::for_test("");
+        let changelog = debian_changelog(&settings, "example", "1.0.0", "Jane Doe").unwrap();
+        assert!(changelog.starts_with(
+            "example (1.0.0) unstable; urgency=medium\n\n  * Release 1.0.0.\n\n -- Jane Doe  "
+        ));
+    }
+}
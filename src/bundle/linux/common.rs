@@ -1,10 +1,13 @@
-use crate::bundle::{Settings, common};
+use crate::bundle::build_info::build_time;
+use crate::bundle::settings::{Localization, Shell};
+use crate::bundle::{Settings, common, shared_libs};
+use anyhow::Context;
 use image::GenericImageView;
-use libflate::gzip;
 use md5::Digest;
+use quick_xml::escape::escape;
 use std::collections::BTreeSet;
 use std::ffi::OsStr;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -12,42 +15,309 @@ use walkdir::WalkDir;
 
 /// Generate the application desktop file and store it under the `data_dir`.
 pub fn generate_desktop_file(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
-    let bin_name = settings.binary_name();
-    let desktop_file_name = format!("{bin_name}.desktop");
+    let desktop_file_name = format!("{}.desktop", settings.binary_name());
     let desktop_file_path = data_dir
         .join("usr/share/applications")
         .join(desktop_file_name);
-    let file = &mut common::create_file(&desktop_file_path)?;
-    let mime_types = settings
-        .linux_mime_types()
-        .iter()
-        .fold("".to_owned(), |acc, s| format!("{acc}{s};"));
-    // For more information about the format of this file, see
-    // https://developer.gnome.org/integration-guide/stable/desktop-files.html.en
-    writeln!(file, "[Desktop Entry]")?;
-    writeln!(file, "Encoding=UTF-8")?;
+    let mut file = common::create_file(&desktop_file_path)?;
+    file.write_all(desktop_entry(settings).as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Returns the contents of the desktop file.  For more information about the
+/// format, see
+/// https://specifications.freedesktop.org/desktop-entry-spec/latest/
+fn desktop_entry(settings: &Settings) -> String {
+    let bin_name = settings.binary_name();
+    let list = |items: &[String]| {
+        items
+            .iter()
+            .map(|item| format!("{};", item.replace(';', "\\;")))
+            .collect::<String>()
+    };
+    let exec = |args: Option<&str>| match args {
+        Some(args) => format!("{bin_name} {args}"),
+        None => bin_name.to_owned(),
+    };
+    // Localized keys are suffixed with the locale, as in `Name[pt_BR]`.
+    let localized = |key: &str, text: fn(&Localization) -> Option<&String>| {
+        settings
+            .localizations()
+            .iter()
+            .filter_map(|(locale, localization)| {
+                let locale = locale.replace('-', "_");
+                Some((format!("{key}[{locale}]"), text(localization)?.clone()))
+            })
+            .collect::<Vec<_>>()
+    };
+    let localized_comments = localized("Comment", |localization| {
+        localization
+            .comment
+            .as_ref()
+            .or(localization.short_description.as_ref())
+    });
+    let localized_names = localized("Name", |localization| localization.name.as_ref());
+    let mut entries = vec![("Encoding", "UTF-8".to_string())];
     if let Some(category) = settings.app_category() {
-        writeln!(file, "Categories={}", category.gnome_desktop_categories())?;
+        entries.push((
+            "Categories",
+            category.gnome_desktop_categories().to_string(),
+        ));
     }
     if !settings.short_description().is_empty() {
-        writeln!(file, "Comment={}", settings.short_description())?;
+        entries.push(("Comment", settings.short_description().to_string()));
     }
-    let exec = match settings.linux_exec_args() {
-        Some(args) => format!("{bin_name} {args}"),
-        None => bin_name.to_owned(),
+    for (key, comment) in &localized_comments {
+        entries.push((key, comment.clone()));
+    }
+    // An app opening files (or URLs) is passed the file to open by default.
+    let exec_args = match settings.linux_exec_args() {
+        None if !settings.url_schemes().is_empty() => Some("%u"),
+        None if !settings.file_associations().is_empty() => Some("%f"),
+        exec_args => exec_args,
     };
-    writeln!(file, "Exec={exec}")?;
-    writeln!(file, "Icon={bin_name}")?;
-    writeln!(file, "Name={}", settings.bundle_name())?;
-    writeln!(
-        file,
-        "Terminal={}",
-        settings.linux_use_terminal().unwrap_or(false)
-    )?;
-    writeln!(file, "Type=Application")?;
-    writeln!(file, "MimeType={mime_types}")?;
+    entries.push(("Exec", exec(exec_args)));
+    entries.push(("Icon", bin_name.to_string()));
+    entries.push(("Name", settings.bundle_name().to_string()));
+    for (key, name) in &localized_names {
+        entries.push((key, name.clone()));
+    }
+    let terminal = settings.linux_use_terminal().unwrap_or(false);
+    entries.push(("Terminal", terminal.to_string()));
+    entries.push(("Type", "Application".to_string()));
+    entries.push(("MimeType", list(&mime_types(settings))));
     // The `Version` field is omitted on pupose. See `generate_control_file` for specifying
     // the application version.
+    if !settings.linux_keywords().is_empty() {
+        entries.push(("Keywords", list(settings.linux_keywords())));
+    }
+    if let Some(wm_class) = settings.linux_startup_wm_class() {
+        entries.push(("StartupWMClass", wm_class.to_string()));
+    }
+    if settings.linux_no_display() {
+        entries.push(("NoDisplay", "true".to_string()));
+    }
+    let actions = settings.linux_desktop_actions();
+    if !actions.is_empty() {
+        let ids: Vec<String> = actions.iter().map(|action| action.id.clone()).collect();
+        entries.push(("Actions", list(&ids)));
+    }
+    for (key, value) in settings.linux_desktop_entries() {
+        match entries.iter_mut().find(|(existing, _)| existing == key) {
+            Some(entry) => entry.1 = value.clone(),
+            None => entries.push((key, value.clone())),
+        }
+    }
+
+    let mut desktop_entry = String::from("[Desktop Entry]\n");
+    for (key, value) in entries {
+        desktop_entry.push_str(&format!("{key}={value}\n"));
+    }
+    for action in actions {
+        desktop_entry.push_str(&format!("\n[Desktop Action {}]\n", action.id));
+        desktop_entry.push_str(&format!("Name={}\n", action.name));
+        desktop_entry.push_str(&format!("Exec={}\n", exec(action.args.as_deref())));
+        if let Some(icon) = &action.icon {
+            desktop_entry.push_str(&format!("Icon={icon}\n"));
+        }
+    }
+    desktop_entry
+}
+
+/// Returns the MIME types the app opens: the `linux_mime_types`, followed by
+/// those of the `file_associations`, and the `x-scheme-handler/<scheme>` types
+/// of the `url_schemes`.
+fn mime_types(settings: &Settings) -> Vec<String> {
+    let mut mime_types = settings.linux_mime_types().to_vec();
+    let associations = settings
+        .file_associations()
+        .iter()
+        .map(|association| association.mime_type().into_owned());
+    let schemes = settings
+        .url_schemes()
+        .iter()
+        .map(|scheme| format!("x-scheme-handler/{scheme}"));
+    for mime_type in associations.chain(schemes) {
+        if !mime_types.contains(&mime_type) {
+            mime_types.push(mime_type);
+        }
+    }
+    mime_types
+}
+
+/// Generates the shared-mime-info package declaring the MIME types of the
+/// `file_associations`, so that the system knows the files by their
+/// extensions, and installs their icons, if they have PNG or SVG ones.
+pub fn generate_mime_info(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    if settings.file_associations().is_empty() {
+        return Ok(());
+    }
+    let path = data_dir
+        .join("usr/share/mime/packages")
+        .join(format!("{}.xml", settings.binary_name()));
+    create_file_with_data(&path, &mime_info(settings))?;
+    let icons_dir = data_dir.join("usr/share/icons/hicolor");
+    for association in settings.file_associations() {
+        let Some(icon) = settings.file_association_icon(association, &["png", "svg"]) else {
+            continue;
+        };
+        // The icon of a MIME type is named after it, as `text-markdown`.
+        let icon_name = association.mime_type().replace('/', "-");
+        let dest = if icon.extension() == Some(OsStr::new("svg")) {
+            icons_dir.join(format!("scalable/mimetypes/{icon_name}.svg"))
+        } else {
+            let (width, height) = image::image_dimensions(&icon)
+                .with_context(|| format!("Failed to read icon {icon:?}"))?;
+            icons_dir.join(format!("{width}x{height}/mimetypes/{icon_name}.png"))
+        };
+        common::copy_file(&icon, &dest)?;
+    }
+    Ok(())
+}
+
+/// Returns the contents of the shared-mime-info package.  See
+/// https://specifications.freedesktop.org/shared-mime-info-spec/latest/
+fn mime_info(settings: &Settings) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n",
+    );
+    for association in settings.file_associations() {
+        xml.push_str(&format!(
+            "  <mime-type type=\"{}\">\n    <comment>{}</comment>\n",
+            escape(association.mime_type()),
+            escape(association.description())
+        ));
+        for extension in &association.extensions {
+            xml.push_str(&format!(
+                "    <glob pattern=\"*.{}\"/>\n",
+                escape(extension)
+            ));
+        }
+        xml.push_str("  </mime-type>\n");
+    }
+    xml.push_str("</mime-info>\n");
+    xml
+}
+
+/// Stages the files to install under the `data_dir`, in the standard layout
+/// shared by the Linux package formats: the binary in `usr/bin`, resources in
+/// `usr/lib/<binary>`, and the man pages (compressed), icons, desktop file and
+/// AppStream metainfo under `usr/share`.  Returns the path of the binary, relative to the
+/// `data_dir`.
+pub fn generate_data_files(settings: &Settings, data_dir: &Path) -> crate::Result<PathBuf> {
+    let binary_path = install_binary(settings, data_dir)?;
+    transfer_resource_files(settings, data_dir).with_context(|| "Failed to copy resource files")?;
+    common::copy_man_pages(settings, &data_dir.join("usr/share/man"))?;
+    compress_man_pages(data_dir).with_context(|| "Failed to compress man pages")?;
+    generate_icon_files(settings, data_dir).with_context(|| "Failed to create icon files")?;
+    generate_desktop_file(settings, data_dir).with_context(|| "Failed to create desktop file")?;
+    generate_mime_info(settings, data_dir).with_context(|| "Failed to create MIME type info")?;
+    super::appstream::generate_metainfo(settings, data_dir)
+        .with_context(|| "Failed to create AppStream metainfo")?;
+    Ok(binary_path)
+}
+
+/// Copies the binary into `usr/bin` under the `data_dir`.  When bundling
+/// shared libraries, the binary goes in `usr/lib/<binary>` instead, with its
+/// libraries in `usr/lib/<binary>/lib`, and `usr/bin/<binary>` is a script
+/// running it with those libraries on the `LD_LIBRARY_PATH`.  Returns the
+/// path of the binary, relative to the `data_dir`.
+pub fn install_binary(settings: &Settings, data_dir: &Path) -> crate::Result<PathBuf> {
+    let binary_name = settings.binary_name();
+    let binary_relpath = Path::new("usr/bin").join(binary_name);
+    let binary_dest = data_dir.join(&binary_relpath);
+    let libraries = if settings.bundle_shared_libraries() {
+        shared_libs::linux_libraries(settings.binary_path())?
+    } else {
+        Vec::new()
+    };
+    if libraries.is_empty() {
+        common::copy_file(settings.binary_path(), &binary_dest)
+            .with_context(|| "Failed to copy binary file")?;
+        return Ok(binary_relpath);
+    }
+    let app_relpath = Path::new("usr/lib").join(binary_name).join(binary_name);
+    let app_dir = data_dir.join("usr/lib").join(binary_name);
+    common::copy_file(settings.binary_path(), &data_dir.join(&app_relpath))
+        .with_context(|| "Failed to copy binary file")?;
+    for library in &libraries {
+        let dest = app_dir.join("lib").join(library.file_name().unwrap());
+        common::copy_file(library, &dest)
+            .with_context(|| format!("Failed to copy shared library {library:?}"))?;
+    }
+    create_file_with_data(&binary_dest, &library_wrapper_script(binary_name))?;
+    common::set_mode(&binary_dest, 0o755)?;
+    Ok(app_relpath)
+}
+
+/// Returns where the `debug_file` split from the binary installed at
+/// `binary_relpath` is installed, so that gdb finds it through the binary's
+/// debug link: under `usr/lib/debug`, in the binary's directory.
+pub fn debug_file_relpath(binary_relpath: &Path, debug_file: &Path) -> PathBuf {
+    Path::new("usr/lib/debug")
+        .join(binary_relpath.parent().unwrap_or(Path::new("")))
+        .join(debug_file.file_name().unwrap())
+}
+
+/// Returns a script running the binary installed in `usr/lib/<binary>` with
+/// its bundled libraries.  The paths are relative to the script, so that it
+/// also works from an AppImage or a snap.
+fn library_wrapper_script(binary_name: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         dir=\"$(dirname \"$(readlink -f \"$0\")\")/../lib/{binary_name}\"\n\
+         export LD_LIBRARY_PATH=\"$dir/lib${{LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}}\"\n\
+         exec \"$dir/{binary_name}\" \"$@\"\n"
+    )
+}
+
+/// Installs the `shell_completions` under the `data_dir`, where the shells
+/// look for the completions of installed commands.  The zsh completion goes
+/// in `zsh_dir`, which differs between distributions, e.g.
+/// `usr/share/zsh/vendor-completions` on Debian.
+pub fn install_shell_completions(
+    settings: &Settings,
+    data_dir: &Path,
+    zsh_dir: &str,
+) -> crate::Result<()> {
+    let binary_name = settings.binary_name();
+    for (shell, script) in settings.shell_completions() {
+        let relpath = match shell {
+            Shell::Bash => Path::new("usr/share/bash-completion/completions").join(binary_name),
+            Shell::Zsh => Path::new(zsh_dir).join(format!("_{binary_name}")),
+            Shell::Fish => {
+                Path::new("usr/share/fish/vendor_completions.d").join(format!("{binary_name}.fish"))
+            }
+        };
+        common::copy_file(&script, &data_dir.join(relpath))
+            .with_context(|| format!("Failed to copy shell completion {script:?}"))?;
+    }
+    Ok(())
+}
+
+/// Copy the bundle's resource files and external binaries into an appropriate
+/// directory under the `data_dir`, along with the build info, and the SBOM
+/// (which goes in `usr/share/doc/<binary>`).
+pub fn transfer_resource_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let resource_dir = data_dir.join("usr/lib").join(settings.binary_name());
+    common::copy_external_binaries(settings, &resource_dir)?;
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let dest = resource_dir.join(&resource.dest);
+        common::copy_resource(&resource, &dest)?;
+    }
+    if let Some(build_info) = settings.build_info() {
+        build_info
+            .write_json(&resource_dir)
+            .with_context(|| "Failed to write build info")?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        let doc_dir = data_dir.join("usr/share/doc").join(settings.binary_name());
+        sbom.write_json(&doc_dir)
+            .with_context(|| "Failed to write SBOM")?;
+    }
     Ok(())
 }
 
@@ -58,30 +328,22 @@ pub fn tar_and_gzip_dir<P: AsRef<Path>>(src_dir: P) -> crate::Result<PathBuf> {
     let src_dir = src_dir.as_ref();
     let dest_path = src_dir.with_extension("tar.gz");
     let dest_file = common::create_file(&dest_path)?;
-    let gzip_encoder = gzip::Encoder::new(dest_file)?;
+    let gzip_encoder = common::gzip_encoder(dest_file)?;
     let gzip_encoder = create_tar_from_dir(src_dir, gzip_encoder)?;
     let mut dest_file = gzip_encoder.finish().into_result()?;
     dest_file.flush()?;
     Ok(dest_path)
 }
 
-/// Writes a tar file to the given writer containing the given directory.
+/// Writes a tar file to the given writer containing the given directory.  The
+/// entries are sorted, owned by root and given the build time as their
+/// modification time, so that the archive is reproducible.
 pub fn create_tar_from_dir<P: AsRef<Path>, W: Write>(src_dir: P, dest_file: W) -> crate::Result<W> {
     let src_dir = src_dir.as_ref();
+    let mtime = build_time()?.timestamp();
     let mut tar_builder = tar::Builder::new(dest_file);
-    for entry in WalkDir::new(src_dir) {
-        let entry = entry?;
-        let src_path = entry.path();
-        if src_path == src_dir {
-            continue;
-        }
-        let dest_path = src_path.strip_prefix(src_dir).unwrap();
-        if entry.file_type().is_dir() {
-            tar_builder.append_dir(dest_path, src_path)?;
-        } else {
-            let mut src_file = File::open(src_path)?;
-            tar_builder.append_file(dest_path, &mut src_file)?;
-        }
+    for entry in collect_entries(src_dir)? {
+        append_tar_entry(&mut tar_builder, src_dir, &entry.path, mtime)?;
     }
     let dest_file = tar_builder.into_inner()?;
     Ok(dest_file)
@@ -106,6 +368,92 @@ pub fn total_dir_size(dir: &Path) -> crate::Result<u64> {
     Ok(total)
 }
 
+/// A file or directory to be installed, relative to the data directory.
+pub struct Entry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Lists the files and directories in `data_dir`, sorted by path.
+pub fn collect_entries(data_dir: &Path) -> crate::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(data_dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.path() == data_dir {
+            continue;
+        }
+        entries.push(Entry {
+            path: entry.path().strip_prefix(data_dir).unwrap().to_path_buf(),
+            is_dir: entry.file_type().is_dir(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Returns the permissions to package a file with: 755 for directories, and
+/// 644 for files, plus any execute, setuid, setgid or sticky bits the file has
+/// (such as those set by a resource's `mode`).
+pub fn file_mode(path: &Path) -> crate::Result<u32> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_dir() {
+        return Ok(0o755);
+    }
+    #[cfg(unix)]
+    let bits = {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        if mode & 0o111 != 0 {
+            mode & 0o7000 | 0o755
+        } else {
+            mode & 0o7000 | 0o644
+        }
+    };
+    #[cfg(not(unix))]
+    let bits = if path.parent().is_some_and(|dir| dir.ends_with("usr/bin"))
+        || is_maintainer_script(path)
+    {
+        0o755
+    } else {
+        0o644
+    };
+    Ok(bits)
+}
+
+/// Returns true if `path` is a maintainer script of the control archive of a
+/// deb package, which must be executable.
+#[cfg(not(unix))]
+fn is_maintainer_script(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str());
+    path.parent().is_some_and(|dir| dir.ends_with("control"))
+        && matches!(name, Some("preinst" | "postinst" | "prerm" | "postrm"))
+}
+
+/// Appends a file or directory from `data_dir` to the archive, owned by root
+/// and with the given modification time, so that packages are reproducible.
+pub fn append_tar_entry<W: Write>(
+    tar_builder: &mut tar::Builder<W>,
+    data_dir: &Path,
+    path: &Path,
+    mtime: i64,
+) -> crate::Result<()> {
+    let src_path = data_dir.join(path);
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(file_mode(&src_path)?);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(mtime as u64);
+    if src_path.is_dir() {
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        tar_builder.append_data(&mut header, path, io::empty())?;
+    } else {
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(fs::metadata(&src_path)?.len());
+        tar_builder.append_data(&mut header, path, File::open(&src_path)?)?;
+    }
+    Ok(())
+}
+
 fn get_dest_path<'a>(
     width: u32,
     height: u32,
@@ -220,6 +568,72 @@ pub fn generate_icon_files(settings: &Settings, data_dir: &Path) -> crate::Resul
     Ok(())
 }
 
+/// Returns the path (relative to `data_dir`) of the icon that best represents
+/// the app: the largest PNG icon generated by `generate_icon_files`, or the SVG
+/// icon if there are no PNG icons.
+pub fn find_largest_icon(data_dir: &Path, binary_name: &str) -> Option<PathBuf> {
+    let hicolor_dir = Path::new("usr/share/icons/hicolor");
+    let png_name = format!("{binary_name}.png");
+    let largest_png = std::fs::read_dir(data_dir.join(hicolor_dir))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let size_dir = entry.file_name().into_string().ok()?;
+            let (width, _) = size_dir.split_once('x')?;
+            let width = width.parse::<u32>().ok()?;
+            let icon = hicolor_dir.join(&size_dir).join("apps").join(&png_name);
+            data_dir.join(&icon).is_file().then_some((width, icon))
+        })
+        .max_by_key(|(width, _)| *width)
+        .map(|(_, icon)| icon);
+    largest_png.or_else(|| {
+        let svg = hicolor_dir
+            .join("scalable/apps")
+            .join(format!("{binary_name}.svg"));
+        data_dir.join(&svg).is_file().then_some(svg)
+    })
+}
+
+/// Compresses the man pages installed under `usr/share/man` in the `data_dir`
+/// with gzip, as Debian policy asks, replacing each page by its `.gz`.
+pub fn compress_man_pages(data_dir: &Path) -> crate::Result<()> {
+    let man_dir = data_dir.join("usr/share/man");
+    if !man_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(&man_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension() == Some(OsStr::new("gz")) {
+            continue;
+        }
+        let mut gz_path = path.as_os_str().to_owned();
+        gz_path.push(".gz");
+        let mut encoder = common::gzip_encoder(common::create_file(Path::new(&gz_path))?)?;
+        io::copy(&mut File::open(path)?, &mut encoder)?;
+        encoder.finish().into_result()?.flush()?;
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Returns the installed size of the files in `data_dir`, in KiB, as
+/// `dpkg-gencontrol` computes it: each file counts for its size rounded up to
+/// a KiB, and each directory for one KiB.
+pub fn installed_size(data_dir: &Path) -> crate::Result<u64> {
+    let mut size = 0;
+    for entry in collect_entries(data_dir)? {
+        size += if entry.is_dir {
+            1
+        } else {
+            fs::metadata(data_dir.join(&entry.path))?
+                .len()
+                .div_ceil(1024)
+        };
+    }
+    Ok(size)
+}
+
 /// Compute the md5 hash of the given file.
 pub fn generate_md5sum(file_path: &Path) -> crate::Result<Digest> {
     let mut file = File::open(file_path)?;
@@ -253,6 +667,156 @@ mod tests {
         assert!(tar_gz_file.metadata().unwrap().len() > 0);
     }
 
+    #[test]
+    fn tar_from_dir_is_sorted_and_root_owned() {
+        let temp_dir = tempdir().unwrap();
+        for name in ["b/file", "a", "c"] {
+            common::create_file(&temp_dir.path().join(name)).unwrap();
+        }
+        let tar = create_tar_from_dir(temp_dir.path(), Vec::new()).unwrap();
+        let mut archive = tar::Archive::new(tar.as_slice());
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let header = entry.unwrap().header().clone();
+                assert_eq!((header.uid().unwrap(), header.gid().unwrap()), (0, 0));
+                header.path().unwrap().into_owned()
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                PathBuf::from("a"),
+                PathBuf::from("b"),
+                PathBuf::from("b/file"),
+                PathBuf::from("c")
+            ]
+        );
+    }
+
+    #[test]
+    fn desktop_entries() {
+        let settings = Settings::for_test(
+            r#"
+            short_description = "Does things"
+            linux_mime_types = ["text/plain"]
+            linux_keywords = ["edit", "a;b"]
+            linux_startup_wm_class = "Example"
+            linux_no_display = true
+            linux_desktop_actions = [
+                { id = "new-window", name = "New Window", args = "--new-window" },
+                { id = "safe", name = "Safe Mode", icon = "example-safe" },
+            ]
+            linux_desktop_entries = { Terminal = "true", "X-GNOME-UsesNotifications" = "true" }
+            "#,
+        );
+        assert_eq!(
+            desktop_entry(&settings),
+            "[Desktop Entry]\n\
+             Encoding=UTF-8\n\
+             Comment=Does things\n\
+             Exec=example\n\
+             Icon=example\n\
+             Name=example\n\
+             Terminal=true\n\
+             Type=Application\n\
+             MimeType=text/plain;\n\
+             Keywords=edit;a\\;b;\n\
+             StartupWMClass=Example\n\
+             NoDisplay=true\n\
+             Actions=new-window;safe;\n\
+             X-GNOME-UsesNotifications=true\n\
+             \n\
+             [Desktop Action new-window]\n\
+             Name=New Window\n\
+             Exec=example --new-window\n\
+             \n\
+             [Desktop Action safe]\n\
+             Name=Safe Mode\n\
+             Exec=example\n\
+             Icon=example-safe\n"
+        );
+    }
+
+    #[test]
+    fn file_association_mime_info() {
+        let settings = Settings::for_test(
+            r#"
+            linux_mime_types = ["text/plain"]
+            file_associations = [
+                { extensions = ["md", "markdown"], mime_type = "text/markdown", description = "Markdown <text>" },
+                { extensions = ["foo"] },
+                { extensions = ["txt"], mime_type = "text/plain" },
+            ]
+            "#,
+        );
+        assert!(
+            desktop_entry(&settings)
+                .contains("MimeType=text/plain;text/markdown;application/x-foo;\n")
+        );
+        assert_eq!(
+            mime_info(&settings),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n  \
+             <mime-type type=\"text/markdown\">\n    \
+             <comment>Markdown &lt;text&gt;</comment>\n    \
+             <glob pattern=\"*.md\"/>\n    \
+             <glob pattern=\"*.markdown\"/>\n  \
+             </mime-type>\n  \
+             <mime-type type=\"application/x-foo\">\n    \
+             <comment>FOO document</comment>\n    \
+             <glob pattern=\"*.foo\"/>\n  \
+             </mime-type>\n  \
+             <mime-type type=\"text/plain\">\n    \
+             <comment>TXT document</comment>\n    \
+             <glob pattern=\"*.txt\"/>\n  \
+             </mime-type>\n\
+             </mime-info>\n"
+        );
+    }
+
+    #[test]
+    fn localized_desktop_entries() {
+        let settings = Settings::for_test(
+            r#"
+            name = "Example"
+            short_description = "Does things"
+            [localizations.fr]
+            name = "Exemple"
+            short_description = "Fait des choses"
+            [localizations.pt-BR]
+            short_description = "Faz coisas"
+            comment = "Abre arquivos"
+            "#,
+        );
+        assert!(desktop_entry(&settings).contains(
+            "Comment=Does things\n\
+             Comment[fr]=Fait des choses\n\
+             Comment[pt_BR]=Abre arquivos\n\
+             Exec=example\n\
+             Icon=example\n\
+             Name=Example\n\
+             Name[fr]=Exemple\n\
+             Terminal=false\n"
+        ));
+    }
+
+    #[test]
+    fn url_scheme_mime_types() {
+        let settings = Settings::for_test(
+            r#"
+            url_schemes = ["myapp", "myapp-auth"]
+            file_associations = [{ extensions = ["foo"] }]
+            "#,
+        );
+        let desktop_entry = desktop_entry(&settings);
+        assert!(desktop_entry.contains("Exec=example %u\n"));
+        assert!(desktop_entry.contains(
+            "MimeType=application/x-foo;x-scheme-handler/myapp;x-scheme-handler/myapp-auth;\n"
+        ));
+    }
+
     #[test]
     fn test_create_file_with_data() {
         let temp_dir = tempdir().unwrap();
@@ -280,4 +844,69 @@ mod tests {
 
         assert_eq!(md5_str, "098f6bcd4621d373cade4e832627b4f6".to_string());
     }
+
+    #[test]
+    fn largest_icon() {
+        let tmp = tempfile::tempdir().unwrap();
+        let hicolor = tmp.path().join("usr/share/icons/hicolor");
+        for size in ["32x32", "128x128", "64x64@2x"] {
+            common::create_file(&hicolor.join(size).join("apps/foo.png")).unwrap();
+        }
+        assert_eq!(
+            find_largest_icon(tmp.path(), "foo"),
+            Some(PathBuf::from(
+                "usr/share/icons/hicolor/128x128/apps/foo.png"
+            ))
+        );
+        assert_eq!(find_largest_icon(tmp.path(), "bar"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_modes() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("helper.sh");
+        File::create(&path).unwrap();
+        for (mode, expected) in [
+            (0o600, 0o644),
+            (0o664, 0o644),
+            (0o700, 0o755),
+            (0o4755, 0o4755),
+        ] {
+            common::set_mode(&path, mode).unwrap();
+            assert_eq!(file_mode(&path).unwrap(), expected);
+        }
+        assert_eq!(file_mode(tmp.path()).unwrap(), 0o755);
+    }
+
+    #[test]
+    fn library_wrapper() {
+        let script = library_wrapper_script("foo");
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("/../lib/foo\"\n"));
+        assert!(script.contains(
+            "export LD_LIBRARY_PATH=\"$dir/lib${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}\"\n"
+        ));
+        assert!(script.ends_with("exec \"$dir/foo\" \"$@\"\n"));
+    }
+
+    #[test]
+    fn man_pages_and_installed_size() {
+        let tmp = tempdir().unwrap();
+        let data_dir = tmp.path();
+        create_file_with_data(data_dir.join("usr/share/man/man1/foo.1"), ".TH FOO 1\n").unwrap();
+        create_file_with_data(data_dir.join("usr/share/man/man5/foo.toml.5.gz"), "").unwrap();
+        create_file_with_data(data_dir.join("usr/bin/foo"), &"x".repeat(1025)).unwrap();
+        compress_man_pages(data_dir).unwrap();
+        assert!(!data_dir.join("usr/share/man/man1/foo.1").exists());
+        let gz = File::open(data_dir.join("usr/share/man/man1/foo.1.gz")).unwrap();
+        let mut page = String::new();
+        io::Read::read_to_string(&mut libflate::gzip::Decoder::new(gz).unwrap(), &mut page)
+            .unwrap();
+        assert_eq!(page, ".TH FOO 1\n");
+        assert!(data_dir.join("usr/share/man/man5/foo.toml.5.gz").exists());
+        // 6 directories, a KiB for the compressed page (the empty one counts for
+        // nothing), and 2 KiB for the binary.
+        assert_eq!(installed_size(data_dir).unwrap(), 6 + 1 + 2);
+    }
 }
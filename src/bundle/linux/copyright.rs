@@ -0,0 +1,325 @@
+// Debian packages ship a copyright file as `usr/share/doc/<package>/copyright`,
+// in the machine-readable format of DEP-5:
+//
+// Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+// Upstream-Name: foobar
+// Upstream-Contact: Jane Doe <jane@example.com>
+// Source: https://example.com/foobar
+//
+// Files: *
+// Copyright: Jane Doe <jane@example.com>
+// License: MIT or Apache-2.0
+//
+// License: MIT                        # A paragraph for each license of the
+//  Permission is hereby granted, ...  # `license` expression, giving its text
+//
+// License: Apache-2.0
+//  On Debian systems, the full text of the Apache-2.0 license can be found in
+//  /usr/share/common-licenses/Apache-2.0.
+//
+// The licenses found in `/usr/share/common-licenses` are referred to there.
+// The text of the others is read from the `license-file` of the package if it
+// is the only license, or else from a `LICENSE-<NAME>` file next to
+// `Cargo.toml`, such as the `LICENSE-MIT` of many crates (or `LICENSE-APACHE`,
+// named after the family of `Apache-2.0`).  A license whose text is not found
+// gets a warning, and a placeholder line, as DEP-5 paragraphs can't be empty.
+
+use crate::bundle::Settings;
+use crate::bundle::common::print_warning;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+const FORMAT: &str = "https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/";
+
+/// The body of the paragraph of a license whose text was not found.
+const MISSING_TEXT: &str = "The full text of this license was not provided.";
+
+/// The licenses in `/usr/share/common-licenses`, by their SPDX identifiers.
+const COMMON_LICENSES: &[(&str, &str)] = &[
+    ("Apache-2.0", "Apache-2.0"),
+    ("CC0-1.0", "CC0-1.0"),
+    ("GPL-2.0", "GPL-2"),
+    ("GPL-2.0-only", "GPL-2"),
+    ("GPL-2.0-or-later", "GPL-2"),
+    ("GPL-3.0", "GPL-3"),
+    ("GPL-3.0-only", "GPL-3"),
+    ("GPL-3.0-or-later", "GPL-3"),
+    ("LGPL-2.1", "LGPL-2.1"),
+    ("LGPL-2.1-only", "LGPL-2.1"),
+    ("LGPL-2.1-or-later", "LGPL-2.1"),
+    ("LGPL-3.0", "LGPL-3"),
+    ("LGPL-3.0-only", "LGPL-3"),
+    ("LGPL-3.0-or-later", "LGPL-3"),
+    ("MPL-2.0", "MPL-2.0"),
+];
+
+/// Returns the copyright file of the package named `package`, maintained by
+/// `maintainer`.  Warns about licenses whose text is not found.
+pub fn debian_copyright(
+    settings: &Settings,
+    package: &str,
+    maintainer: &str,
+) -> crate::Result<String> {
+    let holders: Vec<String> = match settings.copyright_string() {
+        Some(copyright) => vec![copyright.to_string()],
+        None if !settings.author_names().is_empty() => settings.author_names().to_vec(),
+        None => vec![maintainer.to_string()],
+    };
+    let expression = settings.license().unwrap_or("other");
+    let names = license_names(expression);
+    let licenses: Vec<(&str, Option<String>)> = names
+        .iter()
+        .map(|&name| (name, license_text(settings, name, names.len() == 1)))
+        .collect();
+    for (name, license) in &licenses {
+        if license.is_some() {
+            continue;
+        }
+        if settings.license().is_none() {
+            print_warning(&format!(
+                "Package {package:?} has no `license` or `license-file`, so its Debian \
+                 copyright file has no license text"
+            ))?;
+        } else {
+            print_warning(&format!(
+                "No text found for the {name} license of package {package:?}; add a \
+                 LICENSE-{} file next to Cargo.toml to include it in the Debian copyright file",
+                name.to_ascii_uppercase()
+            ))?;
+        }
+    }
+    let source = settings
+        .repository_url()
+        .or(Some(settings.homepage_url()))
+        .filter(|url| !url.is_empty());
+    Ok(format_copyright(
+        package, maintainer, source, &holders, expression, &licenses,
+    ))
+}
+
+/// Formats the copyright file, whose `licenses` pair the names of the licenses
+/// of the `expression` with their texts, if found.
+fn format_copyright(
+    package: &str,
+    maintainer: &str,
+    source: Option<&str>,
+    holders: &[String],
+    expression: &str,
+    licenses: &[(&str, Option<String>)],
+) -> String {
+    let mut text = String::new();
+    writeln!(text, "Format: {FORMAT}").unwrap();
+    writeln!(text, "Upstream-Name: {package}").unwrap();
+    writeln!(text, "Upstream-Contact: {maintainer}").unwrap();
+    if let Some(source) = source {
+        writeln!(text, "Source: {source}").unwrap();
+    }
+    writeln!(text).unwrap();
+    writeln!(text, "Files: *").unwrap();
+    writeln!(text, "Copyright: {}", holders.join("\n ")).unwrap();
+    writeln!(text, "License: {}", dep5_expression(expression)).unwrap();
+    for (name, license) in licenses {
+        writeln!(text).unwrap();
+        writeln!(text, "License: {name}").unwrap();
+        for line in license
+            .as_deref()
+            .unwrap_or(MISSING_TEXT)
+            .trim_end()
+            .lines()
+        {
+            let line = line.trim_end();
+            if line.is_empty() {
+                writeln!(text, " .").unwrap();
+            } else {
+                writeln!(text, " {line}").unwrap();
+            }
+        }
+    }
+    text
+}
+
+/// Returns the names of the licenses of an SPDX `expression`, leaving out its
+/// operators and exceptions.  The legacy `MIT/Apache-2.0` form is accepted too.
+fn license_names(expression: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut exception = false;
+    for token in expression
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '/'))
+        .filter(|token| !token.is_empty())
+    {
+        match token {
+            "OR" | "AND" => {}
+            "WITH" => exception = true,
+            _ if exception => exception = false,
+            _ if !names.contains(&token) => names.push(token),
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Converts an SPDX `expression` to the syntax of DEP-5, whose operators are
+/// lowercase.
+fn dep5_expression(expression: &str) -> String {
+    let expression = expression.replace('/', " OR ");
+    let words: Vec<&str> = expression
+        .split_whitespace()
+        .map(|word| match word {
+            "OR" => "or",
+            "AND" => "and",
+            "WITH" => "with",
+            word => word,
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// Returns the text of the license called `name`: a reference to the copy in
+/// `/usr/share/common-licenses`, or the contents of its license file, if one
+/// is found.  The `license-file` of the package is only used if `only`, that
+/// is, if the package has no other license.
+fn license_text(settings: &Settings, name: &str, only: bool) -> Option<String> {
+    if let Some((_, file)) = COMMON_LICENSES.iter().find(|(spdx, _)| *spdx == name) {
+        return Some(format!(
+            "On Debian systems, the full text of the {name} license can be found in\n\
+             /usr/share/common-licenses/{file}."
+        ));
+    }
+    if only && let Some(path) = settings.license_file() {
+        return std::fs::read_to_string(path).ok();
+    }
+    let dir = settings.manifest_path().parent()?;
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| is_license_file(path, name))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+}
+
+/// Returns true if `path` is named as the file of the license called `name`
+/// usually is: `LICENSE-<name>` (or `LICENCE`, or with an underscore), in any
+/// case and optionally ending in `.md` or `.txt`, where the name may be
+/// shortened to its family, such as `LICENSE-APACHE` for `Apache-2.0`.
+fn is_license_file(path: &Path, name: &str) -> bool {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let file_name = file_name.to_ascii_lowercase();
+    let stem = [".md", ".txt"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .unwrap_or(&file_name);
+    let Some(suffix) = ["license-", "license_", "licence-", "licence_"]
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))
+    else {
+        return false;
+    };
+    let name = name.to_ascii_lowercase();
+    let family = name.split('-').next().unwrap_or(&name);
+    suffix == name || suffix == family
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn license_expressions() {
+        assert_eq!(license_names("MIT OR Apache-2.0"), ["MIT", "Apache-2.0"]);
+        assert_eq!(license_names("MIT/Apache-2.0"), ["MIT", "Apache-2.0"]);
+        assert_eq!(
+            license_names("(MIT OR Apache-2.0) AND GPL-2.0-only WITH Classpath-exception-2.0"),
+            ["MIT", "Apache-2.0", "GPL-2.0-only"]
+        );
+        assert_eq!(dep5_expression("MIT/Apache-2.0"), "MIT or Apache-2.0");
+        assert_eq!(
+            dep5_expression("(MIT OR Apache-2.0) AND Zlib"),
+            "(MIT or Apache-2.0) and Zlib"
+        );
+    }
+
+    #[test]
+    fn license_files() {
+        assert!(is_license_file(Path::new("LICENSE-MIT"), "MIT"));
+        assert!(is_license_file(Path::new("License_MIT.md"), "MIT"));
+        assert!(is_license_file(Path::new("LICENSE-APACHE"), "Apache-2.0"));
+        assert!(is_license_file(
+            Path::new("licence-apache-2.0.txt"),
+            "Apache-2.0"
+        ));
+        assert!(is_license_file(
+            Path::new("LICENSE-APACHE-2.0"),
+            "Apache-2.0"
+        ));
+        assert!(!is_license_file(Path::new("LICENSE"), "MIT"));
+        assert!(!is_license_file(Path::new("LICENSE-MIT"), "Apache-2.0"));
+    }
+
+    #[test]
+    fn copyright_file() {
+        let settings = Settings::for_test("");
+        let text = format_copyright(
+            "foo",
+            "Jane Doe <jane@example.com>",
+            Some("https://example.com/foo"),
+            &["Jane Doe".to_string(), "John Doe".to_string()],
+            "MIT OR Apache-2.0",
+            &[
+                (
+                    "MIT",
+                    Some("MIT License\n\nPermission is granted.\n".to_string()),
+                ),
+                ("Apache-2.0", license_text(&settings, "Apache-2.0", false)),
+            ],
+        );
+        assert_eq!(
+            text,
+            "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/\n\
+             Upstream-Name: foo\n\
+             Upstream-Contact: Jane Doe <jane@example.com>\n\
+             Source: https://example.com/foo\n\
+             \n\
+             Files: *\n\
+             Copyright: Jane Doe\n \
+             John Doe\n\
+             License: MIT or Apache-2.0\n\
+             \n\
+             License: MIT\n \
+             MIT License\n \
+             .\n \
+             Permission is granted.\n\
+             \n\
+             License: Apache-2.0\n \
+             On Debian systems, the full text of the Apache-2.0 license can be found in\n \
+             /usr/share/common-licenses/Apache-2.0.\n"
+        );
+    }
+
+    #[test]
+    fn copyright_without_license() {
+        let settings = Settings::for_test("copyright = \"Copyright (c) 2024 Jane Doe\"");
+        let text = debian_copyright(&settings, "example", "Jane Doe <jane@example.com>").unwrap();
+        assert!(text.contains("Copyright: Copyright (c) 2024 Jane Doe\nLicense: other\n"));
+        assert!(
+            text.ends_with("\nLicense: other\n The full text of this license was not provided.\n")
+        );
+        assert!(!text.contains("Source:"));
+    }
+
+    #[test]
+    fn copyright_without_license_text() {
+        // The test settings have no license files next to their `Cargo.toml`.
+        let settings = Settings::for_test("");
+        let licenses = [("MIT", license_text(&settings, "MIT", true))];
+        assert_eq!(licenses[0].1, None);
+        let text = format_copyright("foo", "Jane Doe", None, &[], "MIT", &licenses);
+        assert!(text.ends_with(
+            "License: MIT\n\nLicense: MIT\n The full text of this license was not provided.\n"
+        ));
+    }
+}
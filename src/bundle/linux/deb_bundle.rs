@@ -5,44 +5,78 @@
 //     control.tar.gz          # Contains files controlling the installation:
 //         control                  # Basic package metadata
 //         md5sums                  # Checksums for files in data.tar.gz below
+//         conffiles                # Configuration files (optional)
+//         preinst                  # Pre-installation script (optional)
 //         postinst                 # Post-installation script (optional)
 //         prerm                    # Pre-uninstallation script (optional)
+//         postrm                   # Post-uninstallation script (optional)
 //     data.tar.gz             # Contains files to be installed:
 //         usr/bin/foobar                            # Binary executable file
+//         lib/systemd/system/foobar.service         # Systemd units (optional)
+//         etc/foobar/...                            # Configuration files
+//         usr/share/doc/foobar/changelog.gz         # Debian changelog
+//         usr/share/doc/foobar/copyright            # DEP-5 copyright file
+//         usr/share/man/man1/foobar.1.gz            # Man pages (`man_pages`)
+//         usr/share/bash-completion/completions/foobar  # Shell completions
+//         usr/share/zsh/vendor-completions/_foobar      # (`shell_completions`)
+//         usr/share/fish/vendor_completions.d/foobar.fish
 //         usr/share/applications/foobar.desktop     # Desktop file (for apps)
 //         usr/share/icons/hicolor/...               # Icon files (for apps)
+//         usr/share/metainfo/com.example.foobar.metainfo.xml  # AppStream
 //         usr/lib/foobar/...                        # Other resource files
+//     _gpgorigin              # GPG signature of the members above (optional)
 //
 // For cargo-bundle, we put bundle resource files under /usr/lib/package_name/,
 // and then generate the desktop file and control file from the bundle
-// metadata, as well as generating the md5sums file.  Currently we do not
-// generate postinst or prerm files.
+// metadata, as well as generating the md5sums file.  The package is laid out
+// as lintian expects: the control file has a `Section` and `Priority`, and an
+// `Installed-Size` computed as dpkg does, files and directories have the
+// standard modes, and the man pages and changelog are compressed.  The maintainer scripts
+// (preinst, postinst, prerm and postrm) are those given by the `deb_<script>`
+// settings, into which the snippets managing the `linux_systemd_units` are
+// inserted: where the script has a `#DEBHELPER#` line, as with debhelper, or
+// else right after its `#!` line.
+//
+// The files installed in `/etc`, such as the `deb_conffiles`, are listed in
+// `conffiles`, as debhelper does, so that dpkg keeps the changes the admin
+// made to them when the package is upgraded.
+//
+// If a signature is requested, the `_gpgorigin` member is a detached signature
+// of the concatenated `debian-binary`, `control.tar.gz` and `data.tar.gz`, as
+// checked by `debsig-verify`, and a detached signature of the whole package is
+// written to `foobar_1.2.3_i386.deb.asc`.
+//
+// With `debug_symbols = "split"`, the debug information split from the binary
+// is packaged on its own as `foobar-dbgsym_1.2.3_i386.deb`, which installs it
+// as `usr/lib/debug/usr/bin/foobar.debug`.
 
 use crate::bundle::{
-    Settings, common,
+    Settings,
+    build_info::build_time,
+    common, debug_symbols, gpg,
     linux::common::{
-        create_file_with_data, generate_desktop_file, generate_icon_files, generate_md5sum,
-        tar_and_gzip_dir, total_dir_size,
+        collect_entries, create_file_with_data, create_tar_from_dir, debug_file_relpath,
+        generate_data_files, generate_md5sum, install_shell_completions, installed_size,
+        tar_and_gzip_dir,
     },
+    linux::{changelog, copyright, depends, systemd},
+    settings::DebCompression,
 };
 use anyhow::Context;
 
+use std::fmt::Write as _;
+use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use walkdir::WalkDir;
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    let arch = match settings.binary_arch() {
-        "x86" => "i386",
-        "x86_64" => "amd64",
-        "arm" => "armhf", // ARM64 is detected differently, armel isn't supported, so armhf is the only reasonable choice here.
-        "aarch64" => "arm64",
-        other => other,
-    };
+    let arch = arch_name(settings);
     let package_base_name = format!(
         "{}_{}_{}",
         settings.binary_name(),
-        settings.version_string(),
+        deb_version(&settings.version_string().to_string()),
         arch
     );
     let package_name = format!("{package_base_name}.deb");
@@ -53,23 +87,238 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         std::fs::remove_dir_all(&package_dir)
             .with_context(|| format!("Failed to remove old {package_base_name}"))?;
     }
-    let package_path = base_dir.join(package_name);
+    let package_path = base_dir.join(&package_name);
 
     // Generate data files.
     let data_dir = package_dir.join("data");
-    let binary_dest = data_dir.join("usr/bin").join(settings.binary_name());
-    common::copy_file(settings.binary_path(), &binary_dest)
-        .with_context(|| "Failed to copy binary file")?;
-    transfer_resource_files(settings, &data_dir)
-        .with_context(|| "Failed to copy resource files")?;
-    generate_icon_files(settings, &data_dir).with_context(|| "Failed to create icon files")?;
-    generate_desktop_file(settings, &data_dir).with_context(|| "Failed to create desktop file")?;
+    let binary_relpath = generate_data_files(settings, &data_dir)?;
+    install_shell_completions(settings, &data_dir, "usr/share/zsh/vendor-completions")?;
+    let units = systemd::install_units(settings, &data_dir, "lib/systemd/system")
+        .with_context(|| "Failed to copy systemd units")?;
+    let conffiles =
+        install_conffiles(settings, &data_dir).with_context(|| "Failed to copy conffiles")?;
+    generate_changelog(settings, &data_dir).with_context(|| "Failed to create changelog")?;
+    generate_copyright(settings, &data_dir).with_context(|| "Failed to create copyright file")?;
 
     // Generate control files.
     let control_dir = package_dir.join("control");
-    generate_control_file(settings, arch, &control_dir, &data_dir)
+    let dependencies = merge_dependencies(
+        settings.debian_dependencies(),
+        depends::deb_depends(settings)?,
+    );
+    generate_control_file(settings, arch, &dependencies, &control_dir, &data_dir)
         .with_context(|| "Failed to create control file")?;
     generate_md5sums(&control_dir, &data_dir).with_context(|| "Failed to create md5sums file")?;
+    generate_conffiles(&conffiles, &control_dir, &data_dir)
+        .with_context(|| "Failed to create conffiles file")?;
+    generate_maintainer_scripts(settings, &units, &control_dir)
+        .with_context(|| "Failed to create maintainer scripts")?;
+
+    let mut paths = write_package(settings, &package_dir, &package_path)?;
+    if let Some(debug_file) = debug_symbols::split_debug_info(settings) {
+        paths.extend(bundle_debug_package(
+            settings,
+            arch,
+            &base_dir,
+            &binary_relpath,
+            &debug_file,
+        )?);
+    }
+    Ok(paths)
+}
+
+/// Builds the `foobar-dbgsym_1.2.3_i386.deb` package, which installs the debug
+/// information split from the binary at `binary_relpath` where gdb looks for
+/// it.
+fn bundle_debug_package(
+    settings: &Settings,
+    arch: &str,
+    base_dir: &Path,
+    binary_relpath: &Path,
+    debug_file: &Path,
+) -> crate::Result<Vec<PathBuf>> {
+    let package_base_name = format!(
+        "{}-dbgsym_{}_{}",
+        settings.binary_name(),
+        deb_version(&settings.version_string().to_string()),
+        arch
+    );
+    let package_name = format!("{package_base_name}.deb");
+    common::print_bundling(&package_name)?;
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let data_dir = package_dir.join("data");
+    common::copy_file(
+        debug_file,
+        &data_dir.join(debug_file_relpath(binary_relpath, debug_file)),
+    )?;
+    let control_dir = package_dir.join("control");
+    generate_debug_control_file(settings, arch, &control_dir, &data_dir)
+        .with_context(|| "Failed to create control file")?;
+    generate_md5sums(&control_dir, &data_dir).with_context(|| "Failed to create md5sums file")?;
+    write_package(settings, &package_dir, &base_dir.join(&package_name))
+}
+
+/// Appends the `automatic` dependencies to the `configured` ones, leaving out
+/// those on packages that the `configured` ones already name.
+pub(crate) fn merge_dependencies(configured: &[String], automatic: Vec<String>) -> Vec<String> {
+    let package = |dependency: &str| {
+        let end = dependency.find([' ', '(', '|']).unwrap_or(dependency.len());
+        dependency[..end].to_string()
+    };
+    let named: Vec<String> = configured
+        .iter()
+        .map(|dependency| package(dependency))
+        .collect();
+    let mut dependencies = configured.to_vec();
+    for dependency in automatic {
+        if !named.contains(&package(&dependency)) && !dependencies.contains(&dependency) {
+            dependencies.push(dependency);
+        }
+    }
+    dependencies
+}
+
+/// Returns the `Maintainer` of the package: the authors from `Cargo.toml`.
+fn maintainer(settings: &Settings) -> String {
+    // Debian packages require a non-empty Maintainer field; if no author is provided
+    // in Cargo.toml, fall back to a generic placeholder.
+    settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| "Unknown <unknown@localhost>".to_string())
+}
+
+/// Writes the changelog, compressed, into `usr/share/doc/<package>` under the
+/// `data_dir`.  Its entries are signed by the first of the authors.
+fn generate_changelog(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let package = package_name(settings);
+    let author = settings
+        .author_names()
+        .first()
+        .filter(|author| !author.trim().is_empty())
+        .cloned()
+        .unwrap_or_else(|| maintainer(settings));
+    let version = settings.version_string().to_string();
+    let text = changelog::debian_changelog(settings, &package, &version, &author)?;
+    let path = data_dir
+        .join("usr/share/doc")
+        .join(&package)
+        .join("changelog.gz");
+    let mut encoder = common::gzip_encoder(common::create_file(&path)?)?;
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish().into_result()?.flush()?;
+    Ok(())
+}
+
+/// Writes the copyright file, in the machine-readable format of DEP-5, into
+/// `usr/share/doc/<package>` under the `data_dir`.
+fn generate_copyright(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let package = package_name(settings);
+    let text = copyright::debian_copyright(settings, &package, &maintainer(settings))?;
+    let path = data_dir
+        .join("usr/share/doc")
+        .join(&package)
+        .join("copyright");
+    create_file_with_data(path, &text)
+}
+
+/// Copies the `deb_conffiles` into `data_dir`, and returns where they are
+/// installed, relative to the `data_dir`.
+fn install_conffiles(settings: &Settings, data_dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    let mut relpaths = Vec::new();
+    for (dest, src) in settings.deb_conffiles() {
+        let Some(relpath) = dest.strip_prefix('/') else {
+            anyhow::bail!("The conffile {dest:?} is not an absolute path");
+        };
+        common::copy_file(&src, &data_dir.join(relpath))?;
+        relpaths.push(PathBuf::from(relpath));
+    }
+    Ok(relpaths)
+}
+
+/// Writes the `conffiles` file, which lists the given `conffiles` and the
+/// other files in `data_dir` installed in `/etc`, unless there are none.
+fn generate_conffiles(
+    conffiles: &[PathBuf],
+    control_dir: &Path,
+    data_dir: &Path,
+) -> crate::Result<()> {
+    let mut lines = String::new();
+    for entry in collect_entries(data_dir)? {
+        if !entry.is_dir && (entry.path.starts_with("etc") || conffiles.contains(&entry.path)) {
+            let path = entry.path.to_string_lossy().replace('\\', "/");
+            writeln!(lines, "/{path}")?;
+        }
+    }
+    if !lines.is_empty() {
+        create_file_with_data(control_dir.join("conffiles"), &lines)?;
+    }
+    Ok(())
+}
+
+/// The line of a maintainer script that the generated snippets replace.
+const DEBHELPER_TOKEN: &str = "#DEBHELPER#";
+
+/// Writes the maintainer scripts into `control_dir`: those given in the
+/// settings, combined with the snippets managing the systemd `units`.
+fn generate_maintainer_scripts(
+    settings: &Settings,
+    units: &[String],
+    control_dir: &Path,
+) -> crate::Result<()> {
+    let scripts = [
+        ("preinst", String::new()),
+        ("postinst", systemd::deb_postinst(settings, units)),
+        ("prerm", systemd::deb_prerm(settings, units)),
+        ("postrm", systemd::deb_postrm(units)),
+    ];
+    for (name, snippet) in scripts {
+        let script = settings.deb_maintainer_script(name)?;
+        let Some(script) = maintainer_script(script.as_deref(), &snippet) else {
+            continue;
+        };
+        let path = control_dir.join(name);
+        create_file_with_data(&path, &script)?;
+        common::set_mode(&path, 0o755)?;
+    }
+    Ok(())
+}
+
+/// Inserts the generated `snippet` into the maintainer `script` from the
+/// settings, in place of its `#DEBHELPER#` line, or else after its `#!` line.
+/// Scripts without a `#!` line are run by `/bin/sh -e`.  Returns `None` if
+/// there is neither a script nor a snippet.
+fn maintainer_script(script: Option<&str>, snippet: &str) -> Option<String> {
+    let script = match script {
+        Some(script) => script,
+        None if snippet.is_empty() => return None,
+        None => "",
+    };
+    let (shebang, body) = match script.split_once('\n') {
+        Some((shebang, body)) if shebang.starts_with("#!") => (shebang, body),
+        _ => ("#!/bin/sh\nset -e", script),
+    };
+    let body = if body.contains(DEBHELPER_TOKEN) {
+        body.replace(DEBHELPER_TOKEN, snippet.trim_end())
+    } else {
+        format!("{snippet}{body}")
+    };
+    Some(format!("{shebang}\n{body}"))
+}
+
+/// Assembles the `control` and `data` directories staged in `package_dir` into
+/// the package at `package_path`, signing it if requested.  Returns the paths
+/// of the package and of its detached signature, if any.
+fn write_package(
+    settings: &Settings,
+    package_dir: &Path,
+    package_path: &Path,
+) -> crate::Result<Vec<PathBuf>> {
+    let package_name = package_path.file_name().unwrap().to_string_lossy();
 
     // Generate `debian-binary` file; see
     // http://www.tldp.org/HOWTO/Debian-Binary-Package-Building-HOWTO/x60.html#AEN66
@@ -77,22 +326,154 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     create_file_with_data(&debian_binary_path, "2.0\n")
         .with_context(|| "Failed to create debian-binary file")?;
 
-    // Apply tar/gzip/ar to create the final package file.
-    let control_tar_gz_path =
-        tar_and_gzip_dir(control_dir).with_context(|| "Failed to tar/gzip control directory")?;
-    let data_tar_gz_path =
-        tar_and_gzip_dir(data_dir).with_context(|| "Failed to tar/gzip data directory")?;
-    create_archive(
-        vec![debian_binary_path, control_tar_gz_path, data_tar_gz_path],
-        &package_path,
-    )
-    .with_context(|| "Failed to create package archive")?;
-    Ok(vec![package_path])
+    // Apply tar/compression/ar to create the final package file.
+    let control_tar_path = tar_and_compress_dir(settings, &package_dir.join("control"))
+        .with_context(|| "Failed to tar/compress control directory")?;
+    let data_tar_path = tar_and_compress_dir(settings, &package_dir.join("data"))
+        .with_context(|| "Failed to tar/compress data directory")?;
+    let mut members = vec![debian_binary_path, control_tar_path, data_tar_path];
+    let signature = settings.deb_signature();
+    if signature.is_some_and(|signature| signature.origin()) {
+        common::print_signing(&package_name)?;
+        let signed_path = package_dir.join("origin");
+        let mut signed_file = common::create_file(&signed_path)?;
+        for path in &members {
+            io::copy(&mut File::open(path)?, &mut signed_file)?;
+        }
+        signed_file.flush()?;
+        drop(signed_file);
+        let origin_path = package_dir.join("_gpgorigin");
+        gpg::detach_sign(settings, &signed_path, &origin_path, false)
+            .with_context(|| format!("Failed to sign {package_name}"))?;
+        members.push(origin_path);
+        common::record_signed(package_path);
+    }
+    create_archive(members, package_path).with_context(|| "Failed to create package archive")?;
+    let mut paths = vec![package_path.to_path_buf()];
+    if signature.is_some_and(|signature| signature.detached()) {
+        let asc_name = format!("{package_name}.asc");
+        common::print_signing(&asc_name)?;
+        let asc_path = package_path.with_file_name(&asc_name);
+        gpg::detach_sign(settings, package_path, &asc_path, true)
+            .with_context(|| format!("Failed to sign {package_name}"))?;
+        common::record_signed(package_path);
+        paths.push(asc_path);
+    }
+    Ok(paths)
+}
+
+/// Archives `src_dir` into a tar file next to it, compressed as the
+/// `deb_compression` setting asks, and returns the path of the archive.
+/// Gzip at the default level is done in-process, and the other compressions
+/// with the `gzip`, `xz` and `zstd` tools.
+fn tar_and_compress_dir(settings: &Settings, src_dir: &Path) -> crate::Result<PathBuf> {
+    let compression = settings.deb_compression();
+    let level = settings.deb_compression_level();
+    if compression == DebCompression::Gzip && level.is_none() {
+        return tar_and_gzip_dir(src_dir);
+    }
+    let tar_path = src_dir.with_extension("tar");
+    create_tar_from_dir(src_dir, common::create_file(&tar_path)?)?;
+    let dest_path = src_dir.with_extension(compression.extension());
+    let Some(mut command) = compress_command(compression, level, &tar_path, &dest_path) else {
+        return Ok(tar_path);
+    };
+    let name = command.get_program().to_string_lossy().into_owned();
+    common::run_tool(&mut command, &name)?;
+    Ok(dest_path)
 }
 
-fn generate_control_file(
+/// Returns the command compressing `tar_path` into `dest_path` (and removing
+/// it), or `None` if there is no compression.
+fn compress_command(
+    compression: DebCompression,
+    level: Option<u32>,
+    tar_path: &Path,
+    dest_path: &Path,
+) -> Option<Command> {
+    let mut command = match compression {
+        DebCompression::None => return None,
+        // `-n` leaves the name and time of the file out of the header, so that
+        // the package is reproducible.
+        DebCompression::Gzip => {
+            let mut command = Command::new("gzip");
+            command.args(["-n", "-f"]);
+            command
+        }
+        DebCompression::Xz => {
+            let mut command = Command::new("xz");
+            command.arg("-f");
+            command
+        }
+        DebCompression::Zstd => {
+            let mut command = Command::new("zstd");
+            command.args(["-q", "-f", "--rm", "-T0"]);
+            if level.is_some_and(|level| level > 19) {
+                command.arg("--ultra");
+            }
+            command
+        }
+    };
+    if let Some(level) = level {
+        command.arg(format!("-{level}"));
+    }
+    command.arg(tar_path);
+    if compression == DebCompression::Zstd {
+        command.arg("-o").arg(dest_path);
+    }
+    Some(command)
+}
+
+/// Returns the Debian architecture name (e.g. `"amd64"`) of the binary being
+/// bundled, which is built for the `--target` if one is given.
+pub(crate) fn arch_name(settings: &Settings) -> &str {
+    debian_arch(settings.binary_arch(), settings.target_triple())
+}
+
+/// Maps a Rust target architecture (e.g. `"x86_64"`) to the corresponding
+/// Debian architecture name (e.g. `"amd64"`).  The target triple, if given,
+/// tells the ABIs and byte orders of an architecture apart, such as `armel`
+/// (`arm-unknown-linux-gnueabi`) and `armhf` (`armv7-unknown-linux-gnueabihf`).
+fn debian_arch<'a>(binary_arch: &'a str, target_triple: Option<&str>) -> &'a str {
+    let triple = target_triple.unwrap_or_default();
+    let little_endian = match target_triple {
+        Some(triple) => {
+            let cpu = triple.split('-').next().unwrap_or_default();
+            cpu.ends_with("el") || cpu.ends_with("le")
+        }
+        None => cfg!(target_endian = "little"),
+    };
+    match binary_arch {
+        "x86" => "i386",
+        "x86_64" if triple.ends_with("gnux32") => "x32",
+        "x86_64" => "amd64",
+        // Without a triple, armhf is the more common of the 32-bit ARM ABIs.
+        "arm" if triple.ends_with("eabi") => "armel",
+        "arm" => "armhf",
+        "aarch64" => "arm64",
+        "loongarch64" => "loong64",
+        "mips" if little_endian => "mipsel",
+        "mips64" if little_endian => "mips64el",
+        "powerpc64" if little_endian => "ppc64el",
+        "powerpc64" => "ppc64",
+        other => other,
+    }
+}
+
+/// Derives a valid Debian package name from the (human-readable) bundle name.
+/// Debian package names may only consist of lowercase ASCII letters, digits,
+/// `+`, `-` and `.`, and must start with a letter or a digit, so other
+/// characters are replaced by dashes.
+pub(crate) fn package_name(settings: &Settings) -> String {
+    common::package_name_slug(settings, &['+', '.'])
+}
+
+/// Writes the `control` file into `control_dir`.  This is shared with the ipk
+/// bundler, whose control files use the same format.
+pub(crate) fn generate_control_file(
     settings: &Settings,
     arch: &str,
+    dependencies: &[String],
     control_dir: &Path,
     data_dir: &Path,
 ) -> crate::Result<()> {
@@ -100,34 +481,30 @@ fn generate_control_file(
     // https://www.debian.org/doc/debian-policy/ch-controlfields.html
     let dest_path = control_dir.join("control");
     let mut file = common::create_file(&dest_path)?;
+    writeln!(&mut file, "Package: {}", package_name(settings))?;
     writeln!(
         &mut file,
-        "Package: {}",
-        str::replace(settings.bundle_name(), " ", "-").to_ascii_lowercase()
+        "Version: {}",
+        deb_version(&settings.version_string().to_string())
     )?;
-    writeln!(&mut file, "Version: {}", settings.version_string())?;
     writeln!(&mut file, "Architecture: {arch}")?;
-    // deb Installed-Size is size in bytes / 1024
     // https://www.debian.org/doc/debian-policy/ch-controlfields.html#s-f-installed-size
-    writeln!(
-        &mut file,
-        "Installed-Size: {}",
-        (total_dir_size(data_dir)?).div_ceil(1024)
-    )?;
-    // Debian packages require a non-empty Maintainer field; if no author is provided
-    // in Cargo.toml, fall back to a generic placeholder.
-    let maintainer = settings
-        .authors_comma_separated()
-        .filter(|a| !a.trim().is_empty())
-        .unwrap_or_else(|| "Unknown <unknown@localhost>".to_string());
-    writeln!(&mut file, "Maintainer: {maintainer}")?;
+    writeln!(&mut file, "Installed-Size: {}", installed_size(data_dir)?)?;
+    writeln!(&mut file, "Maintainer: {}", maintainer(settings))?;
+    writeln!(&mut file, "Section: {}", settings.deb_section())?;
+    writeln!(&mut file, "Priority: {}", settings.deb_priority())?;
     if !settings.homepage_url().is_empty() {
         writeln!(&mut file, "Homepage: {}", settings.homepage_url())?;
     }
-    let dependencies = settings.debian_dependencies();
     if !dependencies.is_empty() {
         writeln!(&mut file, "Depends: {}", dependencies.join(", "))?;
     }
+    if let Some(build_info) = settings.build_info() {
+        if let Some(git_hash) = &build_info.git_hash {
+            writeln!(&mut file, "X-Git-Hash: {git_hash}")?;
+        }
+        writeln!(&mut file, "X-Build-Date: {}", build_info.build_date)?;
+    }
     let mut short_description = settings.short_description().trim();
     if short_description.is_empty() {
         short_description = "(none)";
@@ -149,12 +526,36 @@ fn generate_control_file(
     Ok(())
 }
 
+/// Writes the `control` file of the debug symbols package into `control_dir`.
+fn generate_debug_control_file(
+    settings: &Settings,
+    arch: &str,
+    control_dir: &Path,
+    data_dir: &Path,
+) -> crate::Result<()> {
+    let package = package_name(settings);
+    let version = deb_version(&settings.version_string().to_string());
+    let mut file = common::create_file(&control_dir.join("control"))?;
+    writeln!(&mut file, "Package: {package}-dbgsym")?;
+    writeln!(&mut file, "Source: {package}")?;
+    writeln!(&mut file, "Version: {version}")?;
+    writeln!(&mut file, "Architecture: {arch}")?;
+    writeln!(&mut file, "Installed-Size: {}", installed_size(data_dir)?)?;
+    writeln!(&mut file, "Maintainer: {}", maintainer(settings))?;
+    writeln!(&mut file, "Depends: {package} (= {version})")?;
+    writeln!(&mut file, "Section: debug")?;
+    writeln!(&mut file, "Priority: optional")?;
+    writeln!(&mut file, "Description: debug symbols for {package}")?;
+    file.flush()?;
+    Ok(())
+}
+
 /// Create an `md5sums` file in the `control_dir` containing the MD5 checksums
 /// for each file within the `data_dir`.
 fn generate_md5sums(control_dir: &Path, data_dir: &Path) -> crate::Result<()> {
     let md5sums_path = control_dir.join("md5sums");
     let mut md5sums_file = common::create_file(&md5sums_path)?;
-    for entry in WalkDir::new(data_dir) {
+    for entry in WalkDir::new(data_dir).sort_by_file_name() {
         let entry = entry?;
         let path = entry.path();
         if path.is_dir() {
@@ -173,26 +574,242 @@ fn generate_md5sums(control_dir: &Path, data_dir: &Path) -> crate::Result<()> {
     Ok(())
 }
 
-/// Copy the bundle's resource files into an appropriate directory under the
-/// `data_dir`.
-fn transfer_resource_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
-    let resource_dir = data_dir.join("usr/lib").join(settings.binary_name());
-    for src in settings.resource_files() {
-        let src = src?;
-        let dest = resource_dir.join(common::resource_relpath(&src));
-        common::copy_file(&src, &dest)
-            .with_context(|| format!("Failed to copy resource file {src:?}"))?;
+/// Converts a version string to a valid Debian upstream version, which may
+/// only contain dashes with a Debian revision.  Pre-release versions use a
+/// tilde, which dpkg sorts before the release (e.g. `1.2.3-beta.1` becomes
+/// `1.2.3~beta.1`), and build metadata is kept after the `+`.
+pub(crate) fn deb_version(version: &str) -> String {
+    let (release, build) = match version.split_once('+') {
+        Some((release, build)) => (release, Some(build)),
+        None => (version, None),
+    };
+    let mut deb_version = release.replacen('-', "~", 1);
+    if let Some(build) = build {
+        deb_version.push('+');
+        deb_version.push_str(build);
     }
-    Ok(())
+    deb_version.replace(['-', '_'], ".")
 }
 
 /// Creates an `ar` archive from the given source files and writes it to the
-/// given destination path.
+/// given destination path.  Members are owned by root and given the build time
+/// as their modification time, so that the package is reproducible.
 fn create_archive(srcs: Vec<PathBuf>, dest: &Path) -> crate::Result<()> {
+    let mtime = build_time()?.timestamp() as u64;
     let mut builder = ar::Builder::new(common::create_file(dest)?);
     for path in &srcs {
-        builder.append_path(path)?;
+        let name = path.file_name().unwrap().to_string_lossy();
+        let mut header = ar::Header::new(name.as_bytes().to_vec(), fs::metadata(path)?.len());
+        header.set_mtime(mtime);
+        header.set_mode(0o100644);
+        builder.append(&header, File::open(path)?)?;
     }
     builder.into_inner()?.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn compress_commands() {
+        let tar = Path::new("data.tar");
+        let command = |compression: DebCompression, level| {
+            let dest = tar.with_extension(compression.extension());
+            compress_command(compression, level, tar, &dest).map(|command| args(&command))
+        };
+        assert_eq!(
+            command(DebCompression::Zstd, Some(22)).unwrap(),
+            [
+                "zstd",
+                "-q",
+                "-f",
+                "--rm",
+                "-T0",
+                "--ultra",
+                "-22",
+                "data.tar",
+                "-o",
+                "data.tar.zst"
+            ]
+        );
+        assert_eq!(
+            command(DebCompression::Xz, None).unwrap(),
+            ["xz", "-f", "data.tar"]
+        );
+        assert_eq!(
+            command(DebCompression::Gzip, Some(9)).unwrap(),
+            ["gzip", "-n", "-f", "-9", "data.tar"]
+        );
+        assert_eq!(command(DebCompression::None, Some(9)), None);
+    }
+
+    #[test]
+    fn merged_dependencies() {
+        let configured = ["libssl3 (>= 3.0.2)".to_string(), "curl".to_string()];
+        let automatic = ["libc6 (>= 2.34)", "libssl3 (>= 3.0.0)", "curl"].map(String::from);
+        assert_eq!(
+            merge_dependencies(&configured, automatic.to_vec()),
+            ["libssl3 (>= 3.0.2)", "curl", "libc6 (>= 2.34)"]
+        );
+    }
+
+    #[test]
+    fn conffiles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        for file in ["etc/foo/config.toml", "usr/bin/foo", "var/lib/foo/state"] {
+            create_file_with_data(data_dir.join(file), "").unwrap();
+        }
+        let control_dir = tmp.path().join("control");
+        generate_conffiles(
+            &[PathBuf::from("var/lib/foo/state")],
+            &control_dir,
+            &data_dir,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(control_dir.join("conffiles")).unwrap(),
+            "/etc/foo/config.toml\n/var/lib/foo/state\n"
+        );
+
+        fs::remove_dir_all(data_dir.join("etc")).unwrap();
+        let control_dir = tmp.path().join("control2");
+        generate_conffiles(&[], &control_dir, &data_dir).unwrap();
+        assert!(!control_dir.join("conffiles").exists());
+    }
+
+    #[test]
+    fn maintainer_scripts() {
+        let snippet = "deb-systemd-invoke stop 'foo.service'\n";
+        assert_eq!(maintainer_script(None, ""), None);
+        assert_eq!(
+            maintainer_script(None, snippet).unwrap(),
+            "#!/bin/sh\nset -e\ndeb-systemd-invoke stop 'foo.service'\n"
+        );
+        assert_eq!(
+            maintainer_script(Some("#!/bin/bash\nrm -rf /var/cache/foo\n"), snippet).unwrap(),
+            "#!/bin/bash\ndeb-systemd-invoke stop 'foo.service'\nrm -rf /var/cache/foo\n"
+        );
+        assert_eq!(
+            maintainer_script(
+                Some("#!/bin/sh\nset -e\nfoo\n#DEBHELPER#\nexit 0\n"),
+                snippet
+            )
+            .unwrap(),
+            "#!/bin/sh\nset -e\nfoo\ndeb-systemd-invoke stop 'foo.service'\nexit 0\n"
+        );
+        assert_eq!(
+            maintainer_script(Some("adduser --system foo\n#DEBHELPER#\n"), "").unwrap(),
+            "#!/bin/sh\nset -e\nadduser --system foo\n\n"
+        );
+        assert_eq!(
+            maintainer_script(Some("adduser --system foo\n"), "").unwrap(),
+            "#!/bin/sh\nset -e\nadduser --system foo\n"
+        );
+    }
+
+    #[test]
+    fn deb_versions() {
+        assert_eq!(deb_version("1.2.3"), "1.2.3");
+        assert_eq!(deb_version("1.2.3-beta.1"), "1.2.3~beta.1");
+        assert_eq!(deb_version("1.2.3+5.gabc1234"), "1.2.3+5.gabc1234");
+        assert_eq!(
+            deb_version("1.2.3-rc-2+linux-x86_64"),
+            "1.2.3~rc.2+linux.x86.64"
+        );
+    }
+
+    #[test]
+    fn package_names() {
+        let package_name =
+            |name: &str| package_name(&Settings::for_test(&format!("name = {name:?}")));
+        assert_eq!(package_name("My Cool App"), "my-cool-app");
+        assert_eq!(package_name("  Spaced   Out  "), "spaced-out");
+        assert_eq!(package_name("Café Über 2"), "caf-ber-2");
+        assert_eq!(package_name("gtk+ viewer_v1.0"), "gtk+-viewer-v1.0");
+        assert_eq!(package_name("++ .net tool"), "net-tool");
+        // Without ASCII letters or digits, the crate name is used.
+        assert_eq!(package_name("日本語"), "example");
+    }
+
+    #[test]
+    fn control_file_package_name() {
+        let settings = Settings::for_test("name = \"My Cool App\"");
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        std::fs::create_dir(&data_dir).unwrap();
+        generate_control_file(
+            &settings,
+            "amd64",
+            &[],
+            &tmp.path().join("control"),
+            &data_dir,
+        )
+        .unwrap();
+        let control = std::fs::read_to_string(tmp.path().join("control/control")).unwrap();
+        assert!(control.contains("Package: my-cool-app\n"));
+    }
+
+    #[test]
+    fn debug_control_file() {
+        let settings = Settings::for_test("name = \"My Cool App\"");
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        std::fs::create_dir(&data_dir).unwrap();
+        generate_debug_control_file(&settings, "amd64", &tmp.path().join("control"), &data_dir)
+            .unwrap();
+        let control = std::fs::read_to_string(tmp.path().join("control/control")).unwrap();
+        assert!(control.starts_with("Package: my-cool-app-dbgsym\nSource: my-cool-app\n"));
+        assert!(control.contains("Depends: my-cool-app (= 1.0.0)\n"));
+        assert!(control.contains("Section: debug\n"));
+    }
+
+    #[test]
+    fn debian_archs() {
+        assert_eq!(debian_arch("x86_64", None), "amd64");
+        assert_eq!(debian_arch("x86", Some("i686-unknown-linux-gnu")), "i386");
+        assert_eq!(
+            debian_arch("aarch64", Some("aarch64-unknown-linux-gnu")),
+            "arm64"
+        );
+        assert_eq!(
+            debian_arch("arm", Some("armv7-unknown-linux-gnueabihf")),
+            "armhf"
+        );
+        assert_eq!(
+            debian_arch("arm", Some("arm-unknown-linux-gnueabi")),
+            "armel"
+        );
+        assert_eq!(debian_arch("arm", None), "armhf");
+        assert_eq!(
+            debian_arch("riscv64", Some("riscv64gc-unknown-linux-gnu")),
+            "riscv64"
+        );
+        assert_eq!(
+            debian_arch("powerpc64", Some("powerpc64le-unknown-linux-gnu")),
+            "ppc64el"
+        );
+        assert_eq!(
+            debian_arch("powerpc64", Some("powerpc64-unknown-linux-gnu")),
+            "ppc64"
+        );
+        assert_eq!(
+            debian_arch("mips64", Some("mips64el-unknown-linux-gnuabi64")),
+            "mips64el"
+        );
+        assert_eq!(debian_arch("mips", Some("mips-unknown-linux-gnu")), "mips");
+        assert_eq!(
+            debian_arch("loongarch64", Some("loongarch64-unknown-linux-gnu")),
+            "loong64"
+        );
+    }
+}
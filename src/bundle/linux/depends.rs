@@ -0,0 +1,370 @@
+// With `linux_auto_depends = true`, the packages providing the shared
+// libraries the binary needs (as listed in its ELF dynamic section) are added
+// to the dependencies of deb and rpm packages:
+//
+// deb   `dpkg-shlibdeps` maps the libraries to packages, with the minimum
+//       versions the binary needs, e.g. `libc6 (>= 2.34)`
+// rpm   `rpm -q --whatprovides 'libssl.so.3()(64bit)'` maps each library to
+//       the package providing it, e.g. `openssl-libs`
+//
+// The dependencies of aur packages are always found this way, with the core
+// runtime libraries mapped directly and the others looked up with
+// `pacman -Qqo /usr/lib/libssl.so.3`.
+//
+// These only know the libraries installed on the build host, so where they are
+// unavailable or fail, or when cross-compiling for another architecture, the
+// libraries are looked up in the `KNOWN_LIBRARIES` below instead, and the
+// unknown ones are reported.  When the shared libraries
+// are bundled, only the core system libraries are dependencies.
+
+use crate::bundle::shared_libs::{elf_needed, is_linux_system_library};
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Common libraries, with the Debian and Fedora packages providing them.
+/// Alternatives cover the packages renamed for the 64-bit `time_t` transition.
+const KNOWN_LIBRARIES: &[(&str, &str, &str)] = &[
+    ("libc.so.6", "libc6", "glibc"),
+    ("libm.so.6", "libc6", "glibc"),
+    ("libdl.so.2", "libc6", "glibc"),
+    ("libpthread.so.0", "libc6", "glibc"),
+    ("librt.so.1", "libc6", "glibc"),
+    ("libutil.so.1", "libc6", "glibc"),
+    ("libresolv.so.2", "libc6", "glibc"),
+    ("libgcc_s.so.1", "libgcc-s1", "libgcc"),
+    ("libstdc++.so.6", "libstdc++6", "libstdc++"),
+    ("libz.so.1", "zlib1g", "zlib"),
+    ("libssl.so.3", "libssl3t64 | libssl3", "openssl-libs"),
+    ("libcrypto.so.3", "libssl3t64 | libssl3", "openssl-libs"),
+    ("libssl.so.1.1", "libssl1.1", "openssl1.1"),
+    ("libcrypto.so.1.1", "libssl1.1", "openssl1.1"),
+    ("libdbus-1.so.3", "libdbus-1-3", "dbus-libs"),
+    ("libudev.so.1", "libudev1", "systemd-libs"),
+    ("libsqlite3.so.0", "libsqlite3-0", "sqlite-libs"),
+    ("libcurl.so.4", "libcurl4t64 | libcurl4", "libcurl"),
+    ("libX11.so.6", "libx11-6", "libX11"),
+    ("libX11-xcb.so.1", "libx11-xcb1", "libX11-xcb"),
+    ("libXcursor.so.1", "libxcursor1", "libXcursor"),
+    ("libXi.so.6", "libxi6", "libXi"),
+    ("libXrandr.so.2", "libxrandr2", "libXrandr"),
+    ("libxcb.so.1", "libxcb1", "libxcb"),
+    ("libxkbcommon.so.0", "libxkbcommon0", "libxkbcommon"),
+    (
+        "libwayland-client.so.0",
+        "libwayland-client0",
+        "libwayland-client",
+    ),
+    ("libGL.so.1", "libgl1", "libglvnd-glx"),
+    ("libEGL.so.1", "libegl1", "libglvnd-egl"),
+    ("libvulkan.so.1", "libvulkan1", "vulkan-loader"),
+    ("libasound.so.2", "libasound2t64 | libasound2", "alsa-lib"),
+    ("libpulse.so.0", "libpulse0", "pulseaudio-libs"),
+    ("libfontconfig.so.1", "libfontconfig1", "fontconfig"),
+    ("libfreetype.so.6", "libfreetype6", "freetype"),
+    (
+        "libglib-2.0.so.0",
+        "libglib2.0-0t64 | libglib2.0-0",
+        "glib2",
+    ),
+    (
+        "libgobject-2.0.so.0",
+        "libglib2.0-0t64 | libglib2.0-0",
+        "glib2",
+    ),
+    ("libgio-2.0.so.0", "libglib2.0-0t64 | libglib2.0-0", "glib2"),
+    ("libcairo.so.2", "libcairo2", "cairo"),
+    ("libpango-1.0.so.0", "libpango-1.0-0", "pango"),
+    (
+        "libgdk_pixbuf-2.0.so.0",
+        "libgdk-pixbuf-2.0-0",
+        "gdk-pixbuf2",
+    ),
+    ("libgtk-3.so.0", "libgtk-3-0t64 | libgtk-3-0", "gtk3"),
+    ("libgdk-3.so.0", "libgtk-3-0t64 | libgtk-3-0", "gtk3"),
+    ("libgtk-4.so.1", "libgtk-4-1", "gtk4"),
+    (
+        "libwebkit2gtk-4.1.so.0",
+        "libwebkit2gtk-4.1-0",
+        "webkit2gtk4.1",
+    ),
+    (
+        "libjavascriptcoregtk-4.1.so.0",
+        "libjavascriptcoregtk-4.1-0",
+        "webkit2gtk4.1",
+    ),
+    ("libsoup-3.0.so.0", "libsoup-3.0-0", "libsoup3"),
+    (
+        "libayatana-appindicator3.so.1",
+        "libayatana-appindicator3-1",
+        "libayatana-appindicator-gtk3",
+    ),
+];
+
+/// The libraries the binary needs, and whether it is a 64-bit binary.
+struct Needed {
+    libraries: Vec<String>,
+    is_64: bool,
+}
+
+/// Returns the libraries the binary needs that are dependencies, or `None` if
+/// the `linux_auto_depends` setting is off.
+fn needed_libraries(settings: &Settings) -> crate::Result<Option<Needed>> {
+    if !settings.linux_auto_depends() {
+        return Ok(None);
+    }
+    read_needed_libraries(settings).map(Some)
+}
+
+/// Reads the libraries the binary needs that are dependencies from its ELF
+/// dynamic section.
+fn read_needed_libraries(settings: &Settings) -> crate::Result<Needed> {
+    let binary = settings.binary_path();
+    let data = fs::read(binary).with_context(|| format!("Failed to read {binary:?}"))?;
+    let mut libraries = elf_needed(&data)
+        .with_context(|| format!("Failed to list the libraries {binary:?} needs"))?;
+    if settings.bundle_shared_libraries() {
+        libraries.retain(|library| is_linux_system_library(library));
+    }
+    Ok(Needed {
+        libraries,
+        is_64: data[4] == 2,
+    })
+}
+
+/// Returns the dependencies of the deb package on the packages providing the
+/// libraries the binary needs.
+pub fn deb_depends(settings: &Settings) -> crate::Result<Vec<String>> {
+    let Some(needed) = needed_libraries(settings)? else {
+        return Ok(Vec::new());
+    };
+    if needed.libraries.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !settings.bundle_shared_libraries()
+        && !settings.is_cross_compiling()
+        && let Some(depends) = run_shlibdeps(settings)
+    {
+        return Ok(depends);
+    }
+    known_packages(&needed.libraries, |(_, deb, _)| deb)
+}
+
+/// Returns the packages that the rpm package requires, which provide the
+/// libraries the binary needs.
+pub fn rpm_requires(settings: &Settings) -> crate::Result<Vec<String>> {
+    let Some(needed) = needed_libraries(settings)? else {
+        return Ok(Vec::new());
+    };
+    let mut requires = Vec::new();
+    let mut unresolved = Vec::new();
+    for library in &needed.libraries {
+        let package = if settings.is_cross_compiling() {
+            None
+        } else {
+            rpm_whatprovides(library, needed.is_64)
+        };
+        match package {
+            Some(package) => {
+                if !requires.contains(&package) {
+                    requires.push(package);
+                }
+            }
+            None => unresolved.push(library.clone()),
+        }
+    }
+    for package in known_packages(&unresolved, |(_, _, rpm)| rpm)? {
+        if !requires.contains(&package) {
+            requires.push(package);
+        }
+    }
+    Ok(requires)
+}
+
+/// Returns the Arch Linux packages providing the libraries the binary needs,
+/// warning about those no package is found for.
+pub fn pacman_depends(settings: &Settings) -> crate::Result<Vec<String>> {
+    let needed = read_needed_libraries(settings)?;
+    let mut depends = Vec::new();
+    let mut unknown = Vec::new();
+    for library in &needed.libraries {
+        let package = pacman_core_package(library)
+            .map(str::to_string)
+            .or_else(|| {
+                if settings.is_cross_compiling() {
+                    None
+                } else {
+                    pacman_owner(library)
+                }
+            });
+        match package {
+            Some(package) => {
+                if !depends.contains(&package) {
+                    depends.push(package);
+                }
+            }
+            None => unknown.push(library.as_str()),
+        }
+    }
+    if !unknown.is_empty() {
+        common::print_warning(&format!(
+            "No package is known to provide {}; add it to `pacman_depends` by hand",
+            unknown.join(", ")
+        ))?;
+    }
+    Ok(depends)
+}
+
+/// Returns the Arch Linux package providing one of the core runtime
+/// libraries.
+fn pacman_core_package(library: &str) -> Option<&'static str> {
+    let stem = library.split(".so").next().unwrap();
+    match stem {
+        "libc" | "libm" | "libdl" | "libpthread" | "librt" | "libutil" => Some("glibc"),
+        "libgcc_s" | "libstdc++" => Some("gcc-libs"),
+        _ if stem.starts_with("ld-linux") => Some("glibc"),
+        _ => None,
+    }
+}
+
+/// Returns the package owning `library`, as installed on the build host, or
+/// `None` if pacman is not installed or knows no such package.
+fn pacman_owner(library: &str) -> Option<String> {
+    let output = Command::new("pacman")
+        .arg("-Qqo")
+        .arg(Path::new("/usr/lib").join(library))
+        .output()
+        .ok()?;
+    let package = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !package.is_empty()).then_some(package)
+}
+
+/// Looks the `libraries` up in the `KNOWN_LIBRARIES`, returning the packages
+/// picked by `package`, and warning about the libraries that are not known.
+fn known_packages(
+    libraries: &[String],
+    package: fn(&(&str, &'static str, &'static str)) -> &'static str,
+) -> crate::Result<Vec<String>> {
+    let mut packages = Vec::new();
+    let mut unknown = Vec::new();
+    for library in libraries {
+        match KNOWN_LIBRARIES.iter().find(|known| known.0 == library) {
+            Some(known) => {
+                let name = package(known).to_string();
+                if !packages.contains(&name) {
+                    packages.push(name);
+                }
+            }
+            None => unknown.push(library.as_str()),
+        }
+    }
+    if !unknown.is_empty() {
+        common::print_warning(&format!(
+            "No package is known to provide {}; add it to the dependencies by hand",
+            unknown.join(", ")
+        ))?;
+    }
+    Ok(packages)
+}
+
+/// Runs `dpkg-shlibdeps` on the binary, returning the dependencies it finds,
+/// or `None` if it is not installed or fails.
+fn run_shlibdeps(settings: &Settings) -> Option<Vec<String>> {
+    let binary = std::path::absolute(settings.binary_path()).ok()?;
+    // dpkg-shlibdeps insists on running in a source package.
+    let dir = settings
+        .project_out_directory()
+        .join("bundle/deb/shlibdeps");
+    let control = "Source: shlibdeps\n\nPackage: shlibdeps\nArchitecture: any\n";
+    fs::create_dir_all(dir.join("debian")).ok()?;
+    fs::write(dir.join("debian/control"), control).ok()?;
+    let mut exe = std::ffi::OsString::from("-e");
+    exe.push(&binary);
+    let output = Command::new("dpkg-shlibdeps")
+        .arg("-O")
+        .arg(exe)
+        .current_dir(&dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_shlibdeps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the `shlibs:Depends=...` line that `dpkg-shlibdeps -O` prints.
+fn parse_shlibdeps(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("shlibs:Depends="))
+        .flat_map(|depends| depends.split(','))
+        .map(|depend| depend.trim().to_string())
+        .filter(|depend| !depend.is_empty())
+        .collect()
+}
+
+/// Returns the package providing `library`, as installed on the build host,
+/// or `None` if rpm is not installed or knows no such package.
+fn rpm_whatprovides(library: &str, is_64: bool) -> Option<String> {
+    let suffix = if is_64 { "(64bit)" } else { "" };
+    let output = Command::new("rpm")
+        .args(["-q", "--queryformat", "%{NAME}\\n", "--whatprovides"])
+        .arg(format!("{library}(){suffix}"))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shlibdeps_output() {
+        assert_eq!(
+            parse_shlibdeps(
+                "shlibs:Depends=libc6 (>= 2.34), libgcc-s1 (>= 4.2), libssl3t64 (>= 3.0.0)\n"
+            ),
+            [
+                "libc6 (>= 2.34)",
+                "libgcc-s1 (>= 4.2)",
+                "libssl3t64 (>= 3.0.0)"
+            ]
+        );
+        assert!(parse_shlibdeps("").is_empty());
+    }
+
+    #[test]
+    fn known_libraries() {
+        let libraries = ["libc.so.6", "libm.so.6", "libssl.so.3"].map(String::from);
+        assert_eq!(
+            known_packages(&libraries, |(_, deb, _)| deb).unwrap(),
+            ["libc6", "libssl3t64 | libssl3"]
+        );
+        assert_eq!(
+            known_packages(&libraries, |(_, _, rpm)| rpm).unwrap(),
+            ["glibc", "openssl-libs"]
+        );
+    }
+
+    #[test]
+    fn pacman_core_packages() {
+        assert_eq!(pacman_core_package("libc.so.6"), Some("glibc"));
+        assert_eq!(pacman_core_package("ld-linux-x86-64.so.2"), Some("glibc"));
+        assert_eq!(pacman_core_package("libgcc_s.so.1"), Some("gcc-libs"));
+        assert_eq!(pacman_core_package("libssl.so.3"), None);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let settings = Settings::for_test("");
+        assert!(deb_depends(&settings).unwrap().is_empty());
+        assert!(rpm_requires(&settings).unwrap().is_empty());
+    }
+}
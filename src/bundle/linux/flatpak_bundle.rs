@@ -0,0 +1,136 @@
+// A Flatpak single-file bundle is built from a manifest by `flatpak-builder`.
+// We stage the application files in the same layout as the deb bundler:
+//
+// bundle/flatpak/foobar_1.2.3_x86_64/
+//     com.example.foobar.json     # The generated Flatpak manifest
+//     files/usr/bin/foobar        # Binary executable file
+//     files/usr/share/applications/foobar.desktop
+//     files/usr/share/icons/hicolor/...
+//     files/usr/share/metainfo/com.example.foobar.metainfo.xml
+//     files/usr/lib/foobar/...    # Other resource files
+//
+// The manifest's single module copies `files/usr` into the `/app` prefix, and
+// `rename-desktop-file`/`rename-icon` export the desktop file and icons under
+// the app id, as Flatpak requires.  The resulting repository is then exported
+// with `flatpak build-bundle` to `bundle/flatpak/foobar_1.2.3_x86_64.flatpak`.
+
+use super::common::generate_data_files;
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const DEFAULT_RUNTIME: &str = "org.freedesktop.Platform";
+const DEFAULT_RUNTIME_VERSION: &str = "24.08";
+const DEFAULT_SDK: &str = "org.freedesktop.Sdk";
+const DEFAULT_FINISH_ARGS: &[&str] = &[
+    "--share=ipc",
+    "--socket=fallback-x11",
+    "--socket=wayland",
+    "--device=dri",
+];
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let app_id = settings.bundle_identifier();
+    if app_id.is_empty() {
+        anyhow::bail!("Flatpak bundles require a bundle `identifier` to use as the app id");
+    }
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    let package_name = format!("{package_base_name}.flatpak");
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/flatpak");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    // Stage the files to install.
+    let files_dir = package_dir.join("files");
+    generate_data_files(settings, &files_dir)?;
+
+    // Generate the manifest.
+    let manifest_path = package_dir.join(format!("{app_id}.json"));
+    let manifest = serde_json::to_string_pretty(&generate_manifest(settings))?;
+    fs::write(&manifest_path, manifest)
+        .with_context(|| format!("Failed to write {manifest_path:?}"))?;
+
+    // Build the app into a local repository, then export it as a bundle.
+    let build_dir = package_dir.join("build");
+    let repo_dir = package_dir.join("repo");
+    common::run_tool(
+        Command::new("flatpak-builder")
+            .arg("--force-clean")
+            .arg(format!("--repo={}", repo_dir.display()))
+            .arg(&build_dir)
+            .arg(&manifest_path)
+            .current_dir(&package_dir),
+        "flatpak-builder",
+    )?;
+    common::run_tool(
+        Command::new("flatpak")
+            .arg("build-bundle")
+            .arg(&repo_dir)
+            .arg(&package_path)
+            .arg(app_id.as_ref()),
+        "flatpak",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Generates the `flatpak-builder` manifest for the bundle.  The manifest
+/// expects the application files to be staged under `files/usr`, next to it.
+fn generate_manifest(settings: &Settings) -> serde_json::Value {
+    let bin_name = settings.binary_name();
+    let finish_args = match settings.flatpak_finish_args() {
+        Some(args) => args.to_vec(),
+        None => DEFAULT_FINISH_ARGS.iter().map(|s| s.to_string()).collect(),
+    };
+    serde_json::json!({
+        "app-id": settings.bundle_identifier(),
+        "runtime": settings.flatpak_runtime().unwrap_or(DEFAULT_RUNTIME),
+        "runtime-version": settings.flatpak_runtime_version().unwrap_or(DEFAULT_RUNTIME_VERSION),
+        "sdk": settings.flatpak_sdk().unwrap_or(DEFAULT_SDK),
+        "command": bin_name,
+        "finish-args": finish_args,
+        "rename-desktop-file": format!("{bin_name}.desktop"),
+        "rename-icon": bin_name,
+        "modules": [{
+            "name": bin_name,
+            "buildsystem": "simple",
+            "build-commands": ["cp -a usr/. /app/"],
+            "sources": [{ "type": "dir", "path": "files" }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let settings = Settings::for_test(
+            "identifier = \"com.example.App\"\n\
+             flatpak_finish_args = [\"--share=network\"]\n",
+        );
+        let manifest = generate_manifest(&settings);
+        assert_eq!(manifest["app-id"], "com.example.App");
+        assert_eq!(manifest["runtime"], DEFAULT_RUNTIME);
+        assert_eq!(manifest["command"], "example");
+        assert_eq!(
+            manifest["finish-args"],
+            serde_json::json!(["--share=network"])
+        );
+        assert_eq!(manifest["rename-desktop-file"], "example.desktop");
+        assert_eq!(manifest["modules"][0]["sources"][0]["path"], "files");
+    }
+}
@@ -0,0 +1,111 @@
+// The structure of an ipk package, as installed by opkg on OpenWrt, looks
+// something like this:
+//
+// foobar_1.2.3_aarch64_generic.ipk   # Actually a gzipped tar archive
+//     ./debian-binary         # Specifies the package format version (2.0)
+//     ./data.tar.gz           # Contains files to be installed:
+//         usr/bin/foobar                  # Binary executable file
+//         usr/lib/foobar/...              # Other resource files
+//     ./control.tar.gz        # Contains files controlling the installation:
+//         control                 # Basic package metadata
+//
+// The data and control files are generated as in the deb bundler; only the
+// outer archive format and the architecture names differ.
+
+use super::common::{create_file_with_data, generate_data_files, tar_and_gzip_dir};
+use super::deb_bundle::{deb_version, generate_control_file};
+use crate::bundle::build_info::build_time;
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let arch = settings
+        .ipk_arch()
+        .unwrap_or_else(|| arch_name(settings.binary_arch()));
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        deb_version(&settings.version_string().to_string()),
+        arch
+    );
+    let package_name = format!("{package_base_name}.ipk");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/ipk");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        std::fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(package_name);
+
+    let data_dir = package_dir.join("data");
+    generate_data_files(settings, &data_dir)?;
+    let control_dir = package_dir.join("control");
+    generate_control_file(
+        settings,
+        arch,
+        settings.ipk_dependencies(),
+        &control_dir,
+        &data_dir,
+    )
+    .with_context(|| "Failed to create control file")?;
+    let debian_binary_path = package_dir.join("debian-binary");
+    create_file_with_data(&debian_binary_path, "2.0\n")
+        .with_context(|| "Failed to create debian-binary file")?;
+
+    let control_tar_gz_path =
+        tar_and_gzip_dir(control_dir).with_context(|| "Failed to tar/gzip control directory")?;
+    let data_tar_gz_path =
+        tar_and_gzip_dir(data_dir).with_context(|| "Failed to tar/gzip data directory")?;
+
+    // opkg expects the members in this order, with `./` prefixes, as written
+    // by OpenWrt's `ipkg-build`.
+    let mtime = build_time()?.timestamp() as u64;
+    let mut tar_builder =
+        tar::Builder::new(common::gzip_encoder(common::create_file(&package_path)?)?);
+    for path in [&debian_binary_path, &data_tar_gz_path, &control_tar_gz_path] {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(mtime);
+        header.set_size(path.metadata()?.len());
+        // `Header::set_path` would normalize away the `./` prefix, so the name
+        // is written directly.
+        let name = format!("./{}", path.file_name().unwrap().to_string_lossy());
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_cksum();
+        let file = File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+        tar_builder.append(&header, file)?;
+    }
+    tar_builder.into_inner()?.finish().into_result()?.flush()?;
+    Ok(vec![package_path])
+}
+
+/// Maps a Rust target architecture (e.g. `"aarch64"`) to the most generic
+/// OpenWrt package architecture for it (e.g. `"aarch64_generic"`).  OpenWrt
+/// architectures are specific to a CPU, so this can be overridden with the
+/// `ipk_arch` setting.
+pub(crate) fn arch_name(binary_arch: &str) -> &str {
+    match binary_arch {
+        "x86" => "i386_pentium4",
+        "aarch64" => "aarch64_generic",
+        "mips" => "mips_24kc",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arch_names() {
+        assert_eq!(arch_name("x86_64"), "x86_64");
+        assert_eq!(arch_name("aarch64"), "aarch64_generic");
+        assert_eq!(arch_name("mips"), "mips_24kc");
+    }
+}
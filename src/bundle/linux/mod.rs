@@ -1,4 +1,18 @@
+pub(crate) mod apk_bundle;
 pub(crate) mod appimage_bundle;
-mod common;
+pub(crate) mod appstream;
+pub(crate) mod aur_bundle;
+pub(crate) mod changelog;
+pub(crate) mod common;
+pub(crate) mod copyright;
 pub(crate) mod deb_bundle;
+pub(crate) mod depends;
+pub(crate) mod flatpak_bundle;
+pub(crate) mod ipk_bundle;
+pub(crate) mod nix_bundle;
+pub(crate) mod pacman_bundle;
+pub(crate) mod portable_bundle;
 pub(crate) mod rpm_bundle;
+pub(crate) mod snap_bundle;
+pub(crate) mod srpm_bundle;
+pub(crate) mod systemd;
@@ -0,0 +1,233 @@
+// A Nix package is emitted as a directory containing a derivation that wraps
+// the prebuilt binary, rather than as a single archive:
+//
+// bundle/nix/foobar-1.2.3/
+//     default.nix             # A derivation, buildable with `nix-build`
+//     flake.nix               # A flake exposing the derivation as its default
+//                             # package, for `nix build` and `nix profile`
+//     files/usr/bin/foobar    # Binary executable file
+//     files/usr/share/applications/foobar.desktop
+//     files/usr/share/icons/hicolor/...
+//     files/usr/lib/foobar/...    # Other resource files
+//
+// The files are staged exactly as in the deb bundler, and the derivation's
+// install phase copies `files/usr` into `$out`, where Nix expects binaries,
+// desktop entries and icons.  `autoPatchelfHook` points the binary at the
+// libraries in the Nix store.
+
+use super::common::generate_data_files;
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let pname = super::deb_bundle::package_name(settings);
+    let package_name = format!("{pname}-{}", settings.version_string());
+    common::print_bundling(&package_name)?;
+
+    let package_dir = settings
+        .project_out_directory()
+        .join("bundle/nix")
+        .join(&package_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_name}"))?;
+    }
+    generate_data_files(settings, &package_dir.join("files"))?;
+
+    let default_nix = package_dir.join("default.nix");
+    fs::write(&default_nix, generate_derivation(settings, &pname))
+        .with_context(|| format!("Failed to write {default_nix:?}"))?;
+    let flake_nix = package_dir.join("flake.nix");
+    fs::write(&flake_nix, generate_flake(settings))
+        .with_context(|| format!("Failed to write {flake_nix:?}"))?;
+    Ok(vec![package_dir])
+}
+
+/// Maps a Rust target architecture (e.g. `"aarch64"`) to the corresponding
+/// Nix system (e.g. `"aarch64-linux"`).
+fn nix_system(binary_arch: &str) -> String {
+    match binary_arch {
+        "x86" => "i686-linux".to_string(),
+        "arm" => "armv7l-linux".to_string(),
+        other => format!("{other}-linux"),
+    }
+}
+
+/// Maps an SPDX license identifier to the corresponding attribute of
+/// `lib.licenses`, for the licenses Rust crates most commonly use.
+fn nix_license(spdx: &str) -> Option<&'static str> {
+    Some(match spdx {
+        "MIT" => "mit",
+        "Apache-2.0" => "asl20",
+        "BSD-2-Clause" => "bsd2",
+        "BSD-3-Clause" => "bsd3",
+        "ISC" => "isc",
+        "MPL-2.0" => "mpl20",
+        "Zlib" => "zlib",
+        "Unlicense" => "unlicense",
+        "GPL-2.0-only" | "GPL-2.0" => "gpl2Only",
+        "GPL-2.0-or-later" => "gpl2Plus",
+        "GPL-3.0-only" | "GPL-3.0" => "gpl3Only",
+        "GPL-3.0-or-later" => "gpl3Plus",
+        "LGPL-2.1-only" | "LGPL-2.1" => "lgpl21Only",
+        "LGPL-2.1-or-later" => "lgpl21Plus",
+        "LGPL-3.0-only" | "LGPL-3.0" => "lgpl3Only",
+        "LGPL-3.0-or-later" => "lgpl3Plus",
+        "AGPL-3.0-only" | "AGPL-3.0" => "agpl3Only",
+        "AGPL-3.0-or-later" => "agpl3Plus",
+        _ => return None,
+    })
+}
+
+/// Quotes a string as a Nix string literal.
+fn nix_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{escaped}\"")
+}
+
+/// Generates the contents of `default.nix`.
+fn generate_derivation(settings: &Settings, pname: &str) -> String {
+    let build_inputs = settings.nix_build_inputs().join(" ");
+    let mut nix = String::new();
+    writeln!(nix, "{{ pkgs ? import <nixpkgs> {{ }} }}:").unwrap();
+    writeln!(nix).unwrap();
+    writeln!(nix, "pkgs.stdenv.mkDerivation {{").unwrap();
+    writeln!(nix, "  pname = {};", nix_string(pname)).unwrap();
+    writeln!(
+        nix,
+        "  version = {};",
+        nix_string(&settings.version_string().to_string())
+    )
+    .unwrap();
+    writeln!(nix, "  src = ./files;").unwrap();
+    writeln!(nix).unwrap();
+    writeln!(nix, "  nativeBuildInputs = [ pkgs.autoPatchelfHook ];").unwrap();
+    writeln!(
+        nix,
+        "  buildInputs = with pkgs; [ stdenv.cc.cc.lib {build_inputs} ];"
+    )
+    .unwrap();
+    writeln!(nix, "  dontConfigure = true;").unwrap();
+    writeln!(nix, "  dontBuild = true;").unwrap();
+    writeln!(nix).unwrap();
+    writeln!(nix, "  installPhase = ''").unwrap();
+    writeln!(nix, "    runHook preInstall").unwrap();
+    writeln!(nix, "    mkdir -p $out").unwrap();
+    writeln!(nix, "    cp -r usr/. $out/").unwrap();
+    writeln!(nix, "    runHook postInstall").unwrap();
+    writeln!(nix, "  '';").unwrap();
+    writeln!(nix).unwrap();
+    writeln!(nix, "  meta = {{").unwrap();
+    let description = settings.short_description().trim();
+    if !description.is_empty() {
+        writeln!(nix, "    description = {};", nix_string(description)).unwrap();
+    }
+    if !settings.homepage_url().is_empty() {
+        writeln!(
+            nix,
+            "    homepage = {};",
+            nix_string(settings.homepage_url())
+        )
+        .unwrap();
+    }
+    let licenses: Vec<&str> = settings
+        .license()
+        .into_iter()
+        .flat_map(|license| license.split(" OR ").flat_map(|l| l.split('/')))
+        .filter_map(|license| nix_license(license.trim()))
+        .collect();
+    if !licenses.is_empty() {
+        writeln!(
+            nix,
+            "    license = with pkgs.lib.licenses; [ {} ];",
+            licenses.join(" ")
+        )
+        .unwrap();
+    }
+    writeln!(
+        nix,
+        "    platforms = [ {} ];",
+        nix_string(&nix_system(settings.binary_arch()))
+    )
+    .unwrap();
+    writeln!(
+        nix,
+        "    mainProgram = {};",
+        nix_string(settings.binary_name())
+    )
+    .unwrap();
+    writeln!(nix, "  }};").unwrap();
+    writeln!(nix, "}}").unwrap();
+    nix
+}
+
+/// Generates the contents of `flake.nix`.
+fn generate_flake(settings: &Settings) -> String {
+    let system = nix_string(&nix_system(settings.binary_arch()));
+    let mut nix = String::new();
+    writeln!(nix, "{{").unwrap();
+    writeln!(
+        nix,
+        "  description = {};",
+        nix_string(settings.bundle_name())
+    )
+    .unwrap();
+    writeln!(nix).unwrap();
+    writeln!(
+        nix,
+        "  inputs.nixpkgs.url = \"github:NixOS/nixpkgs/nixos-unstable\";"
+    )
+    .unwrap();
+    writeln!(nix).unwrap();
+    writeln!(nix, "  outputs = {{ self, nixpkgs }}: {{").unwrap();
+    writeln!(
+        nix,
+        "    packages.${{{system}}}.default = import ./default.nix {{"
+    )
+    .unwrap();
+    writeln!(nix, "      pkgs = nixpkgs.legacyPackages.${{{system}}};").unwrap();
+    writeln!(nix, "    }};").unwrap();
+    writeln!(nix, "  }};").unwrap();
+    writeln!(nix, "}}").unwrap();
+    nix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nix_strings() {
+        assert_eq!(nix_string("a \"b\" ${c}"), "\"a \\\"b\\\" \\${c}\"");
+    }
+
+    #[test]
+    fn derivation() {
+        let settings = Settings::for_test(
+            "short_description = \"An example\"\n\
+             nix_build_inputs = [\"gtk3\", \"openssl\"]\n",
+        );
+        let nix = generate_derivation(&settings, "example");
+        assert!(nix.starts_with("{ pkgs ? import <nixpkgs> { } }:\n"));
+        assert!(nix.contains("  pname = \"example\";\n  version = \"1.0.0\";\n"));
+        assert!(nix.contains("buildInputs = with pkgs; [ stdenv.cc.cc.lib gtk3 openssl ];\n"));
+        assert!(nix.contains("    description = \"An example\";\n"));
+        assert!(nix.contains("    mainProgram = \"example\";\n"));
+    }
+
+    #[test]
+    fn flake() {
+        let settings = Settings::for_test("");
+        let nix = generate_flake(&settings);
+        let system = nix_string(&nix_system(settings.binary_arch()));
+        assert!(nix.contains(&format!(
+            "packages.${{{system}}}.default = import ./default.nix {{"
+        )));
+    }
+}
@@ -0,0 +1,244 @@
+// The structure of a pacman package looks something like this:
+//
+// foobar-1.2.3-1-x86_64.pkg.tar.zst   # Actually a zstd-compressed tar archive
+//     .PKGINFO                # Basic package metadata
+//     .MTREE                  # Gzipped mtree listing (with checksums) of the
+//                             # files below
+//     usr/bin/foobar                            # Binary executable file
+//     usr/share/applications/foobar.desktop     # Desktop file (for apps)
+//     usr/share/icons/hicolor/...               # Icon files (for apps)
+//     usr/lib/foobar/...                        # Other resource files
+//
+// The files are laid out as in the deb bundler, except that the zsh completion
+// goes in `usr/share/zsh/site-functions`.  The archive is written uncompressed,
+// then compressed with the `zstd` tool.
+
+use crate::bundle::build_info::build_time;
+use crate::bundle::{
+    Settings, common,
+    linux::common::{
+        Entry, append_tar_entry, collect_entries, file_mode, generate_data_files, generate_md5sum,
+        install_shell_completions, total_dir_size,
+    },
+};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The release number of the package, which is always 1 since we package the
+/// upstream version directly.
+const PKGREL: &str = "1";
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let pkgname = super::deb_bundle::package_name(settings);
+    let pkgver = pkgver(&settings.version_string().to_string());
+    let arch = arch_name(settings.binary_arch());
+    let package_base_name = format!("{pkgname}-{pkgver}-{PKGREL}-{arch}");
+    let package_name = format!("{package_base_name}.pkg.tar.zst");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/pacman");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    // Generate data files.
+    let data_dir = package_dir.join("data");
+    generate_data_files(settings, &data_dir)?;
+    install_shell_completions(settings, &data_dir, "usr/share/zsh/site-functions")?;
+
+    // Generate the metadata files.  `.MTREE` lists `.PKGINFO`, so it must be
+    // generated last.
+    let build_date = build_time()?.timestamp();
+    let pkginfo = generate_pkginfo(settings, &pkgname, &pkgver, arch, build_date, &data_dir)?;
+    fs::write(data_dir.join(".PKGINFO"), pkginfo).with_context(|| "Failed to create .PKGINFO")?;
+    let entries = collect_entries(&data_dir)?;
+    let mtree = generate_mtree(&data_dir, &entries, build_date)?;
+    let mut encoder = common::gzip_encoder(common::create_file(&data_dir.join(".MTREE"))?)?;
+    encoder.write_all(mtree.as_bytes())?;
+    encoder.finish().into_result()?.flush()?;
+
+    // Create the archive.  pacman expects the metadata files to come first.
+    let tar_path = base_dir.join(format!("{package_base_name}.pkg.tar"));
+    let mut tar_builder = tar::Builder::new(common::create_file(&tar_path)?);
+    let files = entries
+        .iter()
+        .map(|entry| entry.path.as_path())
+        .filter(|path| *path != Path::new(".PKGINFO"));
+    for path in [Path::new(".PKGINFO"), Path::new(".MTREE")]
+        .into_iter()
+        .chain(files)
+    {
+        append_tar_entry(&mut tar_builder, &data_dir, path, build_date)?;
+    }
+    tar_builder.into_inner()?.flush()?;
+    common::run_tool(
+        Command::new("zstd")
+            .args(["-q", "-f", "--rm", "-19", "-T0"])
+            .arg(&tar_path)
+            .arg("-o")
+            .arg(&package_path),
+        "zstd",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Maps a Rust target architecture (e.g. `"x86"`) to the corresponding Arch
+/// Linux architecture name (e.g. `"i686"`).
+pub(crate) fn arch_name(binary_arch: &str) -> &str {
+    match binary_arch {
+        "x86" => "i686",
+        "arm" => "armv7h",
+        other => other,
+    }
+}
+
+/// Converts a version string to a valid `pkgver`, which may not contain
+/// dashes (e.g. `1.2.3-beta.1` becomes `1.2.3_beta.1`).
+pub(crate) fn pkgver(version: &str) -> String {
+    version.replace(['-', ':', '/'], "_")
+}
+
+/// Generates the contents of `.PKGINFO`.  See `man 5 PKGINFO` (in newer
+/// versions of pacman) for the format.
+fn generate_pkginfo(
+    settings: &Settings,
+    pkgname: &str,
+    pkgver: &str,
+    arch: &str,
+    build_date: i64,
+    data_dir: &Path,
+) -> crate::Result<String> {
+    let mut pkginfo = String::new();
+    writeln!(pkginfo, "# Generated by cargo-bundle")?;
+    writeln!(pkginfo, "pkgname = {pkgname}")?;
+    writeln!(pkginfo, "pkgbase = {pkgname}")?;
+    writeln!(pkginfo, "pkgver = {pkgver}-{PKGREL}")?;
+    let description = settings.short_description().trim();
+    if !description.is_empty() {
+        writeln!(pkginfo, "pkgdesc = {description}")?;
+    }
+    if !settings.homepage_url().is_empty() {
+        writeln!(pkginfo, "url = {}", settings.homepage_url())?;
+    }
+    writeln!(pkginfo, "builddate = {build_date}")?;
+    let packager = settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| "Unknown Packager".to_string());
+    writeln!(pkginfo, "packager = {packager}")?;
+    writeln!(pkginfo, "size = {}", total_dir_size(data_dir)?)?;
+    writeln!(pkginfo, "arch = {arch}")?;
+    if let Some(license) = settings.license() {
+        for license in license.split(" OR ").flat_map(|l| l.split('/')) {
+            writeln!(pkginfo, "license = {}", license.trim())?;
+        }
+    }
+    for dependency in settings.pacman_dependencies() {
+        writeln!(pkginfo, "depend = {dependency}")?;
+    }
+    Ok(pkginfo)
+}
+
+/// Generates the contents of `.MTREE` (before compression), listing every
+/// entry with the attributes pacman uses to validate installed files.
+fn generate_mtree(data_dir: &Path, entries: &[Entry], mtime: i64) -> crate::Result<String> {
+    let mut mtree = String::new();
+    writeln!(mtree, "#mtree")?;
+    writeln!(mtree, "/set type=file uid=0 gid=0 mode=644")?;
+    for entry in entries {
+        let src_path = data_dir.join(&entry.path);
+        let path = mtree_escape(&entry.path.to_string_lossy());
+        let mode = file_mode(&src_path)?;
+        if entry.is_dir {
+            writeln!(mtree, "./{path} time={mtime}.0 mode={mode:o} type=dir")?;
+            continue;
+        }
+        let size = fs::metadata(&src_path)?.len();
+        write!(mtree, "./{path} time={mtime}.0")?;
+        if mode != 0o644 {
+            write!(mtree, " mode={mode:o}")?;
+        }
+        write!(mtree, " size={size} md5digest=")?;
+        for byte in generate_md5sum(&src_path)?.iter() {
+            write!(mtree, "{byte:02x}")?;
+        }
+        writeln!(mtree)?;
+    }
+    Ok(mtree)
+}
+
+/// Escapes a path for mtree, which encodes whitespace, non-ASCII and other
+/// special characters as backslash-prefixed octal bytes.
+fn mtree_escape(path: &str) -> String {
+    let mut escaped = String::new();
+    for byte in path.bytes() {
+        if byte.is_ascii_graphic() && byte != b'\\' && byte != b'#' {
+            escaped.push(byte as char);
+        } else {
+            write!(escaped, "\\{byte:03o}").unwrap();
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkgvers() {
+        assert_eq!(pkgver("1.2.3"), "1.2.3");
+        assert_eq!(pkgver("1.2.3-beta.1"), "1.2.3_beta.1");
+    }
+
+    #[test]
+    fn mtree() {
+        let data_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(data_dir.path().join("usr/share/my app")).unwrap();
+        fs::write(data_dir.path().join("usr/share/my app/a.txt"), "hello").unwrap();
+        let entries = collect_entries(data_dir.path()).unwrap();
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("usr"),
+                Path::new("usr/share"),
+                Path::new("usr/share/my app"),
+                Path::new("usr/share/my app/a.txt"),
+            ]
+        );
+        let mtree = generate_mtree(data_dir.path(), &entries, 1700000000).unwrap();
+        assert!(mtree.starts_with("#mtree\n/set type=file uid=0 gid=0 mode=644\n"));
+        assert!(mtree.contains("./usr time=1700000000.0 mode=755 type=dir\n"));
+        assert!(mtree.contains(
+            "./usr/share/my\\040app/a.txt time=1700000000.0 size=5 \
+             md5digest=5d41402abc4b2a76b9719d911017c592\n"
+        ));
+    }
+
+    #[test]
+    fn pkginfo() {
+        let settings = Settings::for_test("pacman_depends = [\"gtk3\"]\n");
+        let data_dir = tempfile::tempdir().unwrap();
+        let pkginfo = generate_pkginfo(
+            &settings,
+            "example",
+            "1.0.0",
+            "x86_64",
+            1700000000,
+            data_dir.path(),
+        )
+        .unwrap();
+        assert!(pkginfo.contains("pkgname = example\n"));
+        assert!(pkginfo.contains("pkgver = 1.0.0-1\n"));
+        assert!(pkginfo.contains("builddate = 1700000000\n"));
+        assert!(pkginfo.contains("arch = x86_64\n"));
+        assert!(pkginfo.contains("depend = gtk3\n"));
+    }
+}
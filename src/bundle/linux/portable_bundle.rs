@@ -0,0 +1,195 @@
+// A systemd portable service image is a squashfs file system containing a
+// minimal OS tree, which `portablectl attach` makes the units of available to
+// the host:
+//
+// foobar_1.2.3.raw        # Actually a squashfs image
+//     usr/bin/foobar                          # Binary executable file
+//     usr/lib/foobar/...                      # Other resource files
+//     usr/lib/os-release                      # Identifies the image
+//     usr/lib/systemd/system/foobar.service   # The service running the binary
+//     etc/machine-id, etc/resolv.conf         # Empty mount points for the host's
+//     proc/, sys/, dev/, run/, tmp/, var/tmp/ # files and file systems
+//
+// portablectl only attaches units whose names start with the image's name (the
+// part before the `_`), so the service is named after the package.  As the
+// image contains no libraries, the binary should be statically linked.
+
+use super::appimage_bundle::mksquashfs_command;
+use super::common::{create_file_with_data, install_binary, transfer_resource_files};
+use super::deb_bundle::package_name;
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// Directories the service manager mounts over, which must exist in the image.
+const MOUNT_POINT_DIRS: &[&str] = &["proc", "sys", "dev", "run", "tmp", "var/tmp"];
+/// Files the service manager bind mounts over, which must exist in the image.
+const MOUNT_POINT_FILES: &[&str] = &["etc/machine-id", "etc/resolv.conf"];
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let name = package_name(settings);
+    let package_base_name = format!("{name}_{}", settings.version_string());
+    let package_name = format!("{package_base_name}.raw");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/portable");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    let root_dir = package_dir.join("root");
+    install_binary(settings, &root_dir)?;
+    transfer_resource_files(settings, &root_dir)?;
+    create_file_with_data(
+        root_dir.join("usr/lib/os-release"),
+        &generate_os_release(settings, &name),
+    )
+    .with_context(|| "Failed to create os-release file")?;
+    let unit_path = root_dir
+        .join("usr/lib/systemd/system")
+        .join(format!("{name}.service"));
+    create_file_with_data(&unit_path, &generate_unit(settings)?)
+        .with_context(|| "Failed to create service unit")?;
+    for dir in MOUNT_POINT_DIRS {
+        fs::create_dir_all(root_dir.join(dir))?;
+    }
+    for file in MOUNT_POINT_FILES {
+        common::create_file(&root_dir.join(file))?;
+    }
+
+    if package_path.exists() {
+        fs::remove_file(&package_path)
+            .with_context(|| format!("Failed to remove old {package_name}"))?;
+    }
+    common::run_tool(
+        &mut mksquashfs_command(&root_dir, &package_path, None),
+        "mksquashfs",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Quotes a value for an os-release file.
+fn os_release_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Generates the `os-release` file identifying the image, which portablectl
+/// shows when listing images.
+fn generate_os_release(settings: &Settings, name: &str) -> String {
+    let version = settings.version_string().to_string();
+    let pretty_name = format!("{} {version}", settings.bundle_name());
+    let mut os_release = String::new();
+    writeln!(os_release, "ID={name}").unwrap();
+    writeln!(
+        os_release,
+        "NAME={}",
+        os_release_string(settings.bundle_name())
+    )
+    .unwrap();
+    writeln!(os_release, "VERSION_ID={}", os_release_string(&version)).unwrap();
+    writeln!(
+        os_release,
+        "PRETTY_NAME={}",
+        os_release_string(&pretty_name)
+    )
+    .unwrap();
+    writeln!(
+        os_release,
+        "PORTABLE_PRETTY_NAME={}",
+        os_release_string(&pretty_name)
+    )
+    .unwrap();
+    if !settings.homepage_url().is_empty() {
+        writeln!(
+            os_release,
+            "HOME_URL={}",
+            os_release_string(settings.homepage_url())
+        )
+        .unwrap();
+    }
+    os_release
+}
+
+/// Generates the service unit from the `portable_unit` template, or else a
+/// simple service running the binary.
+fn generate_unit(settings: &Settings) -> crate::Result<String> {
+    let exec = format!("/usr/bin/{}", settings.binary_name());
+    let Some(template_path) = settings.portable_unit() else {
+        let mut description = settings.short_description().trim();
+        if description.is_empty() {
+            description = settings.bundle_name();
+        }
+        return Ok(format!(
+            "[Unit]\n\
+             Description={description}\n\
+             \n\
+             [Service]\n\
+             ExecStart={exec}\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n"
+        ));
+    };
+    let template = common::read_file(&template_path)?;
+    let version = settings.version_string().to_string();
+    let tokens = [
+        ("name", settings.binary_name()),
+        ("version", version.as_str()),
+        ("exec", exec.as_str()),
+    ];
+    common::expand_template(&template, &tokens)
+        .with_context(|| format!("Failed to expand the unit template {template_path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_release() {
+        let settings = Settings::for_test("name = \"My \\\"App\\\"\"\n");
+        assert_eq!(
+            generate_os_release(&settings, "my-app"),
+            "ID=my-app\n\
+             NAME=\"My \\\"App\\\"\"\n\
+             VERSION_ID=\"1.0.0\"\n\
+             PRETTY_NAME=\"My \\\"App\\\" 1.0.0\"\n\
+             PORTABLE_PRETTY_NAME=\"My \\\"App\\\" 1.0.0\"\n"
+        );
+    }
+
+    #[test]
+    fn default_unit() {
+        let settings = Settings::for_test("short_description = \"An example\"\n");
+        let unit = generate_unit(&settings).unwrap();
+        assert!(unit.contains("Description=An example\n"));
+        assert!(unit.contains("ExecStart=/usr/bin/example\n"));
+    }
+
+    #[test]
+    fn unit_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let template_path = dir.path().join("example.service");
+        fs::write(&template_path, "[Service]\nExecStart={exec} --serve\n").unwrap();
+        let settings = Settings::for_test(&format!(
+            "portable_unit = {:?}\n",
+            template_path.to_str().unwrap()
+        ));
+        assert_eq!(
+            generate_unit(&settings).unwrap(),
+            "[Service]\nExecStart=/usr/bin/example --serve\n"
+        );
+    }
+}
@@ -1,6 +1,1007 @@
-use crate::Settings;
-use std::path::PathBuf;
+// The structure of an RPM package looks something like this:
+//
+// foobar-1.2.3-1.x86_64.rpm
+//     Lead                    # A fixed-size legacy header, mostly ignored
+//     Signature header        # Digests (and signatures) of the sections below
+//     Header                  # Basic package metadata, and the list of files
+//     Payload                 # A gzipped cpio archive of the files to install:
+//         ./usr/bin/foobar                            # Binary executable file
+//         ./usr/share/applications/foobar.desktop     # Desktop file (for apps)
+//         ./usr/share/icons/hicolor/...               # Icon files (for apps)
+//         ./usr/share/man/man1/foobar.1.gz            # Man pages (`man_pages`)
+//         ./usr/share/zsh/site-functions/_foobar      # Shell completions
+//         ./usr/lib/systemd/system/foobar.service     # Systemd units
+//         ./usr/lib/foobar/...                        # Other resource files
+//
+// The files are laid out as in the deb bundler, except that the zsh completion
+// goes in `usr/share/zsh/site-functions` and the systemd units in
+// `usr/lib/systemd/system`, as on Fedora.  The `%pre`, `%post`, `%preun` and
+// `%postun` scriptlets are stored in the header, with the interpreter of their
+// `#!` line (or `/bin/sh`), and the snippets managing the units.  The headers are
+// written directly rather than with `rpmbuild`, so that packages can be built
+// on any system.  See https://rpm-software-management.github.io/rpm/manual/format_v4.html
+// for the format.
+//
+// With `debug_symbols = "split"`, the debug information split from the binary
+// is packaged on its own as `foobar-debuginfo-1.2.3-1.x86_64.rpm`, which
+// installs it as `/usr/lib/debug/usr/bin/foobar.debug`.
+//
+// If a GPG key is set, the package is signed as `rpmsign --addsign` does:
+// detached signatures of the header, and of the header and payload together,
+// are stored in the signature header.
 
-pub fn bundle_project(_settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    unimplemented!();
+use crate::bundle::build_info::build_time;
+use crate::bundle::linux::common::{
+    collect_entries, debug_file_relpath, file_mode, generate_data_files, install_shell_completions,
+};
+use crate::bundle::linux::deb_bundle::merge_dependencies;
+use crate::bundle::linux::{depends, systemd};
+use crate::bundle::{Settings, common, debug_symbols, gpg};
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The release number of the package, which is always 1 since we package the
+/// upstream version directly.
+pub(crate) const RELEASE: &str = "1";
+
+// Header tags.  See `rpmtag.h` in the RPM sources.
+const RPMTAG_HEADERSIGNATURES: u32 = 62;
+const RPMTAG_HEADERIMMUTABLE: u32 = 63;
+const RPMTAG_HEADERI18NTABLE: u32 = 100;
+const RPMTAG_NAME: u32 = 1000;
+const RPMTAG_VERSION: u32 = 1001;
+const RPMTAG_RELEASE: u32 = 1002;
+const RPMTAG_EPOCH: u32 = 1003;
+const RPMTAG_SUMMARY: u32 = 1004;
+const RPMTAG_DESCRIPTION: u32 = 1005;
+const RPMTAG_BUILDTIME: u32 = 1006;
+const RPMTAG_BUILDHOST: u32 = 1007;
+const RPMTAG_SIZE: u32 = 1009;
+const RPMTAG_LICENSE: u32 = 1014;
+const RPMTAG_PACKAGER: u32 = 1015;
+const RPMTAG_GROUP: u32 = 1016;
+const RPMTAG_URL: u32 = 1020;
+const RPMTAG_OS: u32 = 1021;
+const RPMTAG_ARCH: u32 = 1022;
+const RPMTAG_PREIN: u32 = 1023;
+const RPMTAG_POSTIN: u32 = 1024;
+const RPMTAG_PREUN: u32 = 1025;
+const RPMTAG_POSTUN: u32 = 1026;
+const RPMTAG_FILESIZES: u32 = 1028;
+const RPMTAG_FILEMODES: u32 = 1030;
+const RPMTAG_FILERDEVS: u32 = 1033;
+const RPMTAG_FILEMTIMES: u32 = 1034;
+const RPMTAG_FILEDIGESTS: u32 = 1035;
+const RPMTAG_FILELINKTOS: u32 = 1036;
+const RPMTAG_FILEFLAGS: u32 = 1037;
+const RPMTAG_FILEUSERNAME: u32 = 1039;
+const RPMTAG_FILEGROUPNAME: u32 = 1040;
+const RPMTAG_SOURCERPM: u32 = 1044;
+const RPMTAG_PROVIDENAME: u32 = 1047;
+const RPMTAG_REQUIREFLAGS: u32 = 1048;
+const RPMTAG_REQUIRENAME: u32 = 1049;
+const RPMTAG_REQUIREVERSION: u32 = 1050;
+const RPMTAG_CONFLICTFLAGS: u32 = 1053;
+const RPMTAG_CONFLICTNAME: u32 = 1054;
+const RPMTAG_CONFLICTVERSION: u32 = 1055;
+const RPMTAG_PREINPROG: u32 = 1085;
+const RPMTAG_POSTINPROG: u32 = 1086;
+const RPMTAG_PREUNPROG: u32 = 1087;
+const RPMTAG_POSTUNPROG: u32 = 1088;
+const RPMTAG_OBSOLETENAME: u32 = 1090;
+const RPMTAG_FILEDEVICES: u32 = 1095;
+const RPMTAG_FILEINODES: u32 = 1096;
+const RPMTAG_FILELANGS: u32 = 1097;
+const RPMTAG_PROVIDEFLAGS: u32 = 1112;
+const RPMTAG_PROVIDEVERSION: u32 = 1113;
+const RPMTAG_OBSOLETEFLAGS: u32 = 1114;
+const RPMTAG_OBSOLETEVERSION: u32 = 1115;
+const RPMTAG_DIRINDEXES: u32 = 1116;
+const RPMTAG_BASENAMES: u32 = 1117;
+const RPMTAG_DIRNAMES: u32 = 1118;
+const RPMTAG_PAYLOADFORMAT: u32 = 1124;
+const RPMTAG_PAYLOADCOMPRESSOR: u32 = 1125;
+const RPMTAG_PAYLOADFLAGS: u32 = 1126;
+const RPMTAG_FILEDIGESTALGO: u32 = 5011;
+const RPMTAG_PAYLOADDIGEST: u32 = 5092;
+const RPMTAG_PAYLOADDIGESTALGO: u32 = 5093;
+
+// Signature header tags.
+const RPMSIGTAG_RSA: u32 = 268;
+const RPMSIGTAG_SHA256: u32 = 273;
+const RPMSIGTAG_SIZE: u32 = 1000;
+const RPMSIGTAG_PGP: u32 = 1002;
+const RPMSIGTAG_MD5: u32 = 1004;
+const RPMSIGTAG_PAYLOADSIZE: u32 = 1007;
+
+/// The `PGPHASHALGO_SHA256` digest algorithm.
+const SHA256_ALGO: u32 = 8;
+/// The scriptlets, as the name of their setting, the tags of their script
+/// and interpreter, and their `RPMSENSE_SCRIPT_*` flag.
+const SCRIPTLETS: [(&str, u32, u32, u32); 4] = [
+    ("pre", RPMTAG_PREIN, RPMTAG_PREINPROG, 1 << 9),
+    ("post", RPMTAG_POSTIN, RPMTAG_POSTINPROG, 1 << 10),
+    ("preun", RPMTAG_PREUN, RPMTAG_PREUNPROG, 1 << 11),
+    ("postun", RPMTAG_POSTUN, RPMTAG_POSTUNPROG, 1 << 12),
+];
+/// The `RPMSENSE_*` flags of the comparisons in versioned dependencies.
+const RPMSENSE_LESS: u32 = 1 << 1;
+const RPMSENSE_GREATER: u32 = 1 << 2;
+const RPMSENSE_EQUAL: u32 = 1 << 3;
+/// `RPMSENSE_ANY`, for dependencies on any version.
+const RPMSENSE_ANY: u32 = 0;
+/// `RPMSENSE_LESS | RPMSENSE_EQUAL | RPMSENSE_RPMLIB`, for the features of
+/// RPM itself that the package requires.
+const RPMSENSE_RPMLIB_LESS_EQUAL: u32 = (1 << 1) | (1 << 3) | (1 << 24);
+/// `RPMSENSE_INTERP`, for the interpreters of scriptlets, along with the
+/// `RPMSENSE_SCRIPT_*` flag of the scriptlet.
+const RPMSENSE_INTERP: u32 = 1 << 8;
+/// `RPMFILE_DOC`, for the documentation that `rpm --excludedocs` leaves out.
+const RPMFILE_DOC: u32 = 1 << 1;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let name = super::deb_bundle::package_name(settings);
+    let version = rpm_version(&settings.version_string().to_string());
+    let summary = settings.short_description().trim();
+    let summary = if summary.is_empty() {
+        settings.bundle_name()
+    } else {
+        summary
+    };
+    let requires = merge_dependencies(settings.rpm_requires(), depends::rpm_requires(settings)?);
+    let requires = parse_dependencies(&requires)?;
+    let provides = parse_dependencies(settings.rpm_provides())?;
+    let conflicts = parse_dependencies(settings.rpm_conflicts())?;
+    let obsoletes = parse_dependencies(settings.rpm_obsoletes())?;
+    let package = PackageInfo {
+        name: &name,
+        version: &version,
+        arch: arch_name(settings.binary_arch()),
+        summary,
+        description: settings.long_description().unwrap_or(summary).trim(),
+        requires: &requires,
+        provides: &provides,
+        conflicts: &conflicts,
+        obsoletes: &obsoletes,
+        scriptlets: &[],
+    };
+    let base_dir = settings.project_out_directory().join("bundle/rpm");
+    let package_dir = prepare_package_dir(&base_dir, &package)?;
+
+    // Generate data files.
+    let data_dir = package_dir.join("data");
+    let binary_relpath = generate_data_files(settings, &data_dir)?;
+    install_shell_completions(settings, &data_dir, "usr/share/zsh/site-functions")?;
+    let units = systemd::install_units(settings, &data_dir, "usr/lib/systemd/system")
+        .with_context(|| "Failed to copy systemd units")?;
+    let scriptlets = generate_scriptlets(settings, &units)?;
+    let package = PackageInfo {
+        scriptlets: &scriptlets,
+        ..package
+    };
+    let mut paths = vec![write_package(settings, &package, &base_dir)?];
+
+    if let Some(debug_file) = debug_symbols::split_debug_info(settings) {
+        let debug_name = format!("{name}-debuginfo");
+        let debug_summary = format!("Debug information for package {name}");
+        let debug_description =
+            format!("This package provides debug information for package {name}.");
+        let debug_package = PackageInfo {
+            name: &debug_name,
+            summary: &debug_summary,
+            description: &debug_description,
+            requires: &[],
+            provides: &[],
+            conflicts: &[],
+            obsoletes: &[],
+            scriptlets: &[],
+            ..package
+        };
+        let package_dir = prepare_package_dir(&base_dir, &debug_package)?;
+        let dest = package_dir
+            .join("data")
+            .join(debug_file_relpath(&binary_relpath, &debug_file));
+        common::copy_file(&debug_file, &dest)?;
+        paths.push(write_package(settings, &debug_package, &base_dir)?);
+    }
+    Ok(paths)
+}
+
+/// The metadata that differs between the package and its debuginfo package.
+#[derive(Clone, Copy)]
+struct PackageInfo<'a> {
+    name: &'a str,
+    version: &'a str,
+    arch: &'a str,
+    summary: &'a str,
+    description: &'a str,
+    /// The packages required besides the features of RPM itself.
+    requires: &'a [Dependency],
+    /// The capabilities provided besides the package itself.
+    provides: &'a [Dependency],
+    conflicts: &'a [Dependency],
+    obsoletes: &'a [Dependency],
+    scriptlets: &'a [Scriptlet],
+}
+
+impl PackageInfo<'_> {
+    /// Returns the file name of the package, without its extension.
+    fn base_name(&self) -> String {
+        format!("{}-{}-{RELEASE}.{}", self.name, self.version, self.arch)
+    }
+}
+
+/// Prints the name of the `package`, and returns an empty directory to stage
+/// its files in.
+fn prepare_package_dir(base_dir: &Path, package: &PackageInfo) -> crate::Result<PathBuf> {
+    let package_base_name = package.base_name();
+    common::print_bundling(&format!("{package_base_name}.rpm"))?;
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    Ok(package_dir)
+}
+
+/// Writes the `package`, whose files are staged in the `data` directory of its
+/// package directory, into `base_dir`, and returns its path.
+fn write_package(
+    settings: &Settings,
+    package: &PackageInfo,
+    base_dir: &Path,
+) -> crate::Result<PathBuf> {
+    let package_base_name = package.base_name();
+    let package_name = format!("{package_base_name}.rpm");
+    let package_dir = base_dir.join(&package_base_name);
+    let package_path = base_dir.join(&package_name);
+    let files = collect_files(&package_dir.join("data"))?;
+
+    // Write the payload, then the header describing it.
+    let build_date = build_time()?.timestamp() as u32;
+    let payload_path = package_dir.join("payload.cpio.gz");
+    let payload_size = write_payload(&files, build_date, &payload_path)
+        .with_context(|| "Failed to create payload")?;
+    let payload_digest = common::sha256_hex(&payload_path)?;
+    let header = generate_header(settings, package, build_date, &files)
+        .add(
+            RPMTAG_PAYLOADDIGEST,
+            Value::StringArray(vec![payload_digest]),
+        )
+        .add(RPMTAG_PAYLOADDIGESTALGO, Value::Int32(vec![SHA256_ALGO]))
+        .to_bytes(RPMTAG_HEADERIMMUTABLE);
+    let header_path = package_dir.join("header");
+    fs::write(&header_path, &header).with_context(|| "Failed to write header")?;
+    let signed_path = package_dir.join("header+payload");
+    let mut signed_file = common::create_file(&signed_path)?;
+    signed_file.write_all(&header)?;
+    io::copy(&mut File::open(&payload_path)?, &mut signed_file)?;
+    signed_file.flush()?;
+    drop(signed_file);
+
+    // Generate the signature header.
+    let mut signature = Header::default()
+        .add(
+            RPMSIGTAG_SIZE,
+            Value::Int32(vec![fs::metadata(&signed_path)?.len() as u32]),
+        )
+        .add(RPMSIGTAG_MD5, Value::Bin(md5_digest(&signed_path)?))
+        .add(RPMSIGTAG_PAYLOADSIZE, Value::Int32(vec![payload_size]))
+        .add(
+            RPMSIGTAG_SHA256,
+            Value::String(common::sha256_hex(&header_path)?),
+        );
+    if settings.gpg_key().is_some() {
+        common::print_signing(&package_name)?;
+        let header_sig_path = package_dir.join("header.sig");
+        gpg::detach_sign(settings, &header_path, &header_sig_path, false)
+            .with_context(|| format!("Failed to sign {package_name}"))?;
+        let signed_sig_path = package_dir.join("header+payload.sig");
+        gpg::detach_sign(settings, &signed_path, &signed_sig_path, false)
+            .with_context(|| format!("Failed to sign {package_name}"))?;
+        signature = signature
+            .add(RPMSIGTAG_RSA, Value::Bin(fs::read(&header_sig_path)?))
+            .add(RPMSIGTAG_PGP, Value::Bin(fs::read(&signed_sig_path)?));
+        common::record_signed(&package_path);
+    }
+    let mut signature = signature.to_bytes(RPMTAG_HEADERSIGNATURES);
+    // The header that follows is aligned to 8 bytes.
+    signature.resize(signature.len().next_multiple_of(8), 0);
+
+    let mut package_file = common::create_file(&package_path)?;
+    package_file.write_all(&generate_lead(&format!(
+        "{}-{}-{RELEASE}",
+        package.name, package.version
+    )))?;
+    package_file.write_all(&signature)?;
+    io::copy(&mut File::open(&signed_path)?, &mut package_file)?;
+    package_file.flush()?;
+    Ok(package_path)
+}
+
+/// Maps a Rust target architecture (e.g. `"x86"`) to the corresponding RPM
+/// architecture (e.g. `"i686"`).
+pub(crate) fn arch_name(binary_arch: &str) -> &str {
+    match binary_arch {
+        "x86" => "i686",
+        "arm" => "armv7hl",
+        other => other,
+    }
+}
+
+/// A dependency of the package, as in the `Requires` of a spec file.
+#[derive(Clone, Debug, PartialEq)]
+struct Dependency {
+    name: String,
+    /// The `RPMSENSE_*` flags, e.g. `RPMSENSE_GREATER | RPMSENSE_EQUAL` for
+    /// `>=`.
+    flags: u32,
+    /// The version compared to, or an empty string.
+    version: String,
+}
+
+impl Dependency {
+    fn new(name: &str, flags: u32, version: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            flags,
+            version: version.to_string(),
+        }
+    }
+
+    /// Parses a dependency such as `foo`, `foo >= 1.2` or `foo=1:1.2-3`.
+    fn parse(dependency: &str) -> crate::Result<Dependency> {
+        let invalid = || format!("Invalid rpm dependency {dependency:?}");
+        let is_operator = |c: char| matches!(c, '<' | '>' | '=');
+        let (name, rest) =
+            dependency.split_at(dependency.find(is_operator).unwrap_or(dependency.len()));
+        let (operator, version) =
+            rest.split_at(rest.find(|c| !is_operator(c)).unwrap_or(rest.len()));
+        let (name, version) = (name.trim(), version.trim());
+        let flags = match operator {
+            "" => RPMSENSE_ANY,
+            "<" => RPMSENSE_LESS,
+            "<=" => RPMSENSE_LESS | RPMSENSE_EQUAL,
+            "=" | "==" => RPMSENSE_EQUAL,
+            ">=" => RPMSENSE_GREATER | RPMSENSE_EQUAL,
+            ">" => RPMSENSE_GREATER,
+            _ => anyhow::bail!(invalid()),
+        };
+        let is_word = |word: &str| !word.is_empty() && !word.contains(char::is_whitespace);
+        if !is_word(name) || (!operator.is_empty() && !is_word(version)) {
+            anyhow::bail!(invalid());
+        }
+        Ok(Dependency::new(name, flags, version))
+    }
+}
+
+fn parse_dependencies(dependencies: &[String]) -> crate::Result<Vec<Dependency>> {
+    dependencies
+        .iter()
+        .map(|dependency| Dependency::parse(dependency))
+        .collect()
+}
+
+/// A scriptlet that RPM runs when installing or erasing the package.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Scriptlet {
+    /// The name of the scriptlet, e.g. `post` for `%post`.
+    pub name: &'static str,
+    /// The tags of the script and of its interpreter.
+    tag: u32,
+    prog_tag: u32,
+    /// The `RPMSENSE_SCRIPT_*` flag of the scriptlet.
+    sense: u32,
+    /// The interpreter, and its arguments if any.
+    pub interpreter: Vec<String>,
+    pub script: String,
+}
+
+/// Returns the scriptlets of the package: those given in the settings,
+/// combined with the snippets managing the systemd `units`.
+pub(crate) fn generate_scriptlets(
+    settings: &Settings,
+    units: &[String],
+) -> crate::Result<Vec<Scriptlet>> {
+    let mut scriptlets = Vec::new();
+    for (name, tag, prog_tag, sense) in SCRIPTLETS {
+        let snippet = match name {
+            "post" => systemd::rpm_post(settings, units),
+            "preun" => systemd::rpm_preun(settings, units),
+            "postun" => systemd::rpm_postun(settings, units),
+            _ => String::new(),
+        };
+        let script = settings.rpm_scriptlet(name)?;
+        if let Some((interpreter, script)) = scriptlet(script.as_deref(), &snippet) {
+            scriptlets.push(Scriptlet {
+                name,
+                tag,
+                prog_tag,
+                sense,
+                interpreter,
+                script,
+            });
+        }
+    }
+    Ok(scriptlets)
+}
+
+/// Splits the scriptlet `script` from the settings into the interpreter of
+/// its `#!` line (or `/bin/sh`) and the rest of the script, which the
+/// generated `snippet` is prepended to.  Returns `None` if there is neither
+/// a script nor a snippet.
+fn scriptlet(script: Option<&str>, snippet: &str) -> Option<(Vec<String>, String)> {
+    let script = match script {
+        Some(script) => script,
+        None if snippet.is_empty() => return None,
+        None => "",
+    };
+    let shebang = script
+        .split_once('\n')
+        .and_then(|(line, body)| Some((line.strip_prefix("#!")?, body)));
+    let (interpreter, body) = match shebang {
+        Some((line, body)) if !line.trim().is_empty() => {
+            (line.split_whitespace().map(String::from).collect(), body)
+        }
+        _ => (vec!["/bin/sh".to_string()], script),
+    };
+    Some((interpreter, format!("{snippet}{body}")))
+}
+
+/// Converts a version string to a valid RPM version, which may not contain
+/// dashes.  Pre-release versions use a tilde, which RPM sorts before the
+/// release (e.g. `1.2.3-beta.1` becomes `1.2.3~beta.1`).
+pub(crate) fn rpm_version(version: &str) -> String {
+    version.replacen('-', "~", 1).replace('-', "_")
+}
+
+/// A file to install, as listed in the header.
+struct RpmFile {
+    /// The installed path, e.g. `/usr/bin/foobar`.
+    path: String,
+    /// The path of the file in the data directory.
+    src: PathBuf,
+    /// The file type and permission bits.
+    mode: u32,
+    size: u32,
+    /// The SHA-256 digest of the contents, as a hex string.
+    digest: String,
+}
+
+/// Lists the files in `data_dir`, sorted by installed path as RPM expects.
+fn collect_files(data_dir: &Path) -> crate::Result<Vec<RpmFile>> {
+    let mut files = Vec::new();
+    for entry in collect_entries(data_dir)? {
+        if entry.is_dir {
+            continue;
+        }
+        let src = data_dir.join(&entry.path);
+        files.push(RpmFile {
+            path: format!("/{}", entry.path.to_string_lossy().replace('\\', "/")),
+            mode: 0o100000 | file_mode(&src)?,
+            size: fs::metadata(&src)?.len() as u32,
+            digest: common::sha256_hex(&src)?,
+            src,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Writes the payload, a gzipped cpio archive of `files`, to `path`, and
+/// returns its uncompressed size.
+fn write_payload(files: &[RpmFile], mtime: u32, path: &Path) -> crate::Result<u32> {
+    let mut cpio = CpioWriter {
+        writer: common::gzip_encoder(common::create_file(path)?)?,
+        written: 0,
+    };
+    for (index, file) in files.iter().enumerate() {
+        let name = format!(".{}", file.path);
+        cpio.write_header(index as u32 + 1, file.mode, mtime, file.size, &name)?;
+        io::copy(&mut File::open(&file.src)?, &mut cpio)?;
+        cpio.pad()?;
+    }
+    cpio.write_header(0, 0, 0, 0, "TRAILER!!!")?;
+    let written = cpio.written;
+    cpio.writer.finish().into_result()?.flush()?;
+    Ok(written as u32)
+}
+
+/// Writes a cpio archive in the "new ASCII" format that RPM payloads use.
+struct CpioWriter<W: Write> {
+    writer: W,
+    written: u64,
+}
+
+impl<W: Write> CpioWriter<W> {
+    fn write_header(
+        &mut self,
+        ino: u32,
+        mode: u32,
+        mtime: u32,
+        size: u32,
+        name: &str,
+    ) -> io::Result<()> {
+        let nlink = if mode == 0 { 0 } else { 1 };
+        let fields = [ino, mode, 0, 0, nlink, mtime, size, 0, 0, 0, 0];
+        let mut header = String::from("070701");
+        for field in fields {
+            header.push_str(&format!("{field:08x}"));
+        }
+        header.push_str(&format!("{:08x}{:08x}", name.len() + 1, 0));
+        header.push_str(name);
+        header.push('\0');
+        self.write_all(header.as_bytes())?;
+        self.pad()
+    }
+
+    /// Pads the archive to a multiple of 4 bytes.
+    fn pad(&mut self) -> io::Result<()> {
+        let padding = self.written.next_multiple_of(4) - self.written;
+        self.write_all(&[0; 3][..padding as usize])
+    }
+}
+
+impl<W: Write> Write for CpioWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// The value of a header entry.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Int16(Vec<u16>),
+    Int32(Vec<u32>),
+    String(String),
+    Bin(Vec<u8>),
+    StringArray(Vec<String>),
+    I18nString(String),
+}
+
+impl Value {
+    /// Returns the type number, the alignment and the count of the value.
+    fn info(&self) -> (u32, usize, usize) {
+        match self {
+            Value::Int16(values) => (3, 2, values.len()),
+            Value::Int32(values) => (4, 4, values.len()),
+            Value::String(_) => (6, 1, 1),
+            Value::Bin(bytes) => (7, 1, bytes.len()),
+            Value::StringArray(values) => (8, 1, values.len()),
+            Value::I18nString(_) => (9, 1, 1),
+        }
+    }
+
+    fn write(&self, data: &mut Vec<u8>) {
+        match self {
+            Value::Int16(values) => values
+                .iter()
+                .for_each(|value| data.extend(value.to_be_bytes())),
+            Value::Int32(values) => values
+                .iter()
+                .for_each(|value| data.extend(value.to_be_bytes())),
+            Value::String(value) | Value::I18nString(value) => {
+                data.extend(value.as_bytes());
+                data.push(0);
+            }
+            Value::Bin(bytes) => data.extend(bytes),
+            Value::StringArray(values) => {
+                for value in values {
+                    data.extend(value.as_bytes());
+                    data.push(0);
+                }
+            }
+        }
+    }
+}
+
+/// A header (or signature header), whose entries are written in tag order.
+#[derive(Debug, Default)]
+struct Header {
+    entries: BTreeMap<u32, Value>,
+}
+
+impl Header {
+    fn add(mut self, tag: u32, value: Value) -> Header {
+        self.entries.insert(tag, value);
+        self
+    }
+
+    /// Adds the entries listing the names, flags and versions of the
+    /// `dependencies` under the given `tags`, unless there are none.
+    fn add_dependencies(self, tags: [u32; 3], dependencies: &[Dependency]) -> Header {
+        if dependencies.is_empty() {
+            return self;
+        }
+        let [name_tag, flags_tag, version_tag] = tags;
+        self.add(
+            name_tag,
+            Value::StringArray(dependencies.iter().map(|d| d.name.clone()).collect()),
+        )
+        .add(
+            flags_tag,
+            Value::Int32(dependencies.iter().map(|d| d.flags).collect()),
+        )
+        .add(
+            version_tag,
+            Value::StringArray(dependencies.iter().map(|d| d.version.clone()).collect()),
+        )
+    }
+
+    /// Serializes the header, with all of its entries in the region marked by
+    /// `region_tag`, as RPM requires of headers that can be signed.
+    fn to_bytes(&self, region_tag: u32) -> Vec<u8> {
+        let entry_count = self.entries.len() as u32 + 1;
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        for (tag, value) in &self.entries {
+            let (value_type, alignment, count) = value.info();
+            data.resize(data.len().next_multiple_of(alignment), 0);
+            index.push([*tag, value_type, data.len() as u32, count as u32]);
+            value.write(&mut data);
+        }
+        // The region entry comes first, and points to a trailer at the end of
+        // the data, whose offset is minus the size of the region's index.
+        let trailer_offset = data.len() as u32;
+        let region_size = -((entry_count * 16) as i32);
+        for field in [region_tag, 7, region_size as u32, 16] {
+            data.extend(field.to_be_bytes());
+        }
+        index.insert(0, [region_tag, 7, trailer_offset, 16]);
+
+        let mut bytes = vec![0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0];
+        bytes.extend(entry_count.to_be_bytes());
+        bytes.extend((data.len() as u32).to_be_bytes());
+        for entry in index {
+            for field in entry {
+                bytes.extend(field.to_be_bytes());
+            }
+        }
+        bytes.extend(data);
+        bytes
+    }
+}
+
+/// Returns the `Packager` of the package: the authors from `Cargo.toml`.
+pub(crate) fn packager(settings: &Settings) -> String {
+    settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| "Unknown Packager".to_string())
+}
+
+/// Generates the header, containing the package metadata and file list.
+fn generate_header(
+    settings: &Settings,
+    package: &PackageInfo,
+    build_date: u32,
+    files: &[RpmFile],
+) -> Header {
+    let PackageInfo {
+        name,
+        version,
+        arch,
+        summary,
+        description,
+        requires,
+        provides,
+        conflicts,
+        obsoletes,
+        scriptlets,
+    } = *package;
+    let packager = packager(settings);
+
+    // File names are stored as indexes into a list of directories, plus base
+    // names.
+    let mut dir_names: Vec<String> = Vec::new();
+    let mut dir_indexes = Vec::new();
+    let mut base_names = Vec::new();
+    for file in files {
+        let (dir, base) = file.path.rsplit_once('/').unwrap();
+        let dir = format!("{dir}/");
+        let dir_index = match dir_names.iter().position(|name| *name == dir) {
+            Some(index) => index,
+            None => {
+                dir_names.push(dir);
+                dir_names.len() - 1
+            }
+        };
+        dir_indexes.push(dir_index as u32);
+        base_names.push(base.to_string());
+    }
+    let each = |value: &str| vec![value.to_string(); files.len()];
+
+    // The requirements, as their name, flags and version: the features of RPM
+    // itself, the packages, and the interpreters of the scriptlets.
+    let rpmlib = [
+        ("rpmlib(CompressedFileNames)", "3.0.4-1"),
+        ("rpmlib(FileDigests)", "4.6.0-1"),
+        ("rpmlib(PayloadFilesHavePrefix)", "4.0-1"),
+    ];
+    let requires: Vec<Dependency> = rpmlib
+        .into_iter()
+        .map(|(name, version)| Dependency::new(name, RPMSENSE_RPMLIB_LESS_EQUAL, version))
+        .chain(requires.iter().cloned())
+        .chain(scriptlets.iter().map(|scriptlet| {
+            Dependency::new(
+                &scriptlet.interpreter[0],
+                RPMSENSE_INTERP | scriptlet.sense,
+                "",
+            )
+        }))
+        .collect();
+    // The package provides itself, at its full version.
+    let full_version = match settings.rpm_epoch() {
+        Some(epoch) => format!("{epoch}:{version}-{RELEASE}"),
+        None => format!("{version}-{RELEASE}"),
+    };
+    let provides: Vec<Dependency> =
+        std::iter::once(Dependency::new(name, RPMSENSE_EQUAL, &full_version))
+            .chain(provides.iter().cloned())
+            .collect();
+
+    let mut header = Header::default()
+        .add(RPMTAG_HEADERI18NTABLE, Value::StringArray(vec!["C".into()]))
+        .add(RPMTAG_NAME, Value::String(name.into()))
+        .add(RPMTAG_VERSION, Value::String(version.into()))
+        .add(RPMTAG_RELEASE, Value::String(RELEASE.into()))
+        .add(RPMTAG_SUMMARY, Value::I18nString(summary.into()))
+        .add(RPMTAG_DESCRIPTION, Value::I18nString(description.into()))
+        .add(RPMTAG_BUILDTIME, Value::Int32(vec![build_date]))
+        .add(RPMTAG_BUILDHOST, Value::String("localhost".into()))
+        .add(
+            RPMTAG_SIZE,
+            Value::Int32(vec![files.iter().map(|file| file.size).sum()]),
+        )
+        .add(
+            RPMTAG_LICENSE,
+            Value::String(settings.license().unwrap_or("Unspecified").into()),
+        )
+        .add(RPMTAG_PACKAGER, Value::String(packager))
+        .add(RPMTAG_GROUP, Value::I18nString(settings.rpm_group().into()))
+        .add(RPMTAG_OS, Value::String("linux".into()))
+        .add(RPMTAG_ARCH, Value::String(arch.into()))
+        .add(
+            RPMTAG_SOURCERPM,
+            Value::String(format!("{name}-{version}-{RELEASE}.src.rpm")),
+        )
+        .add_dependencies(
+            [
+                RPMTAG_PROVIDENAME,
+                RPMTAG_PROVIDEFLAGS,
+                RPMTAG_PROVIDEVERSION,
+            ],
+            &provides,
+        )
+        .add_dependencies(
+            [
+                RPMTAG_REQUIRENAME,
+                RPMTAG_REQUIREFLAGS,
+                RPMTAG_REQUIREVERSION,
+            ],
+            &requires,
+        )
+        .add_dependencies(
+            [
+                RPMTAG_CONFLICTNAME,
+                RPMTAG_CONFLICTFLAGS,
+                RPMTAG_CONFLICTVERSION,
+            ],
+            conflicts,
+        )
+        .add_dependencies(
+            [
+                RPMTAG_OBSOLETENAME,
+                RPMTAG_OBSOLETEFLAGS,
+                RPMTAG_OBSOLETEVERSION,
+            ],
+            obsoletes,
+        )
+        .add(RPMTAG_PAYLOADFORMAT, Value::String("cpio".into()))
+        .add(RPMTAG_PAYLOADCOMPRESSOR, Value::String("gzip".into()))
+        .add(RPMTAG_PAYLOADFLAGS, Value::String("9".into()));
+    if let Some(epoch) = settings.rpm_epoch() {
+        header = header.add(RPMTAG_EPOCH, Value::Int32(vec![epoch]));
+    }
+    if let Some(url) = settings.rpm_url() {
+        header = header.add(RPMTAG_URL, Value::String(url.into()));
+    }
+    for scriptlet in scriptlets {
+        let interpreter = match scriptlet.interpreter.as_slice() {
+            [interpreter] => Value::String(interpreter.clone()),
+            interpreter => Value::StringArray(interpreter.to_vec()),
+        };
+        header = header
+            .add(scriptlet.tag, Value::String(scriptlet.script.clone()))
+            .add(scriptlet.prog_tag, interpreter);
+    }
+    if files.is_empty() {
+        return header;
+    }
+    header
+        .add(
+            RPMTAG_FILESIZES,
+            Value::Int32(files.iter().map(|file| file.size).collect()),
+        )
+        .add(
+            RPMTAG_FILEMODES,
+            Value::Int16(files.iter().map(|file| file.mode as u16).collect()),
+        )
+        .add(RPMTAG_FILERDEVS, Value::Int16(vec![0; files.len()]))
+        .add(
+            RPMTAG_FILEMTIMES,
+            Value::Int32(vec![build_date; files.len()]),
+        )
+        .add(
+            RPMTAG_FILEDIGESTS,
+            Value::StringArray(files.iter().map(|file| file.digest.clone()).collect()),
+        )
+        .add(RPMTAG_FILELINKTOS, Value::StringArray(each("")))
+        .add(
+            RPMTAG_FILEFLAGS,
+            Value::Int32(files.iter().map(|file| file_flags(&file.path)).collect()),
+        )
+        .add(RPMTAG_FILEUSERNAME, Value::StringArray(each("root")))
+        .add(RPMTAG_FILEGROUPNAME, Value::StringArray(each("root")))
+        .add(RPMTAG_FILEDEVICES, Value::Int32(vec![1; files.len()]))
+        .add(
+            RPMTAG_FILEINODES,
+            Value::Int32((1..=files.len() as u32).collect()),
+        )
+        .add(RPMTAG_FILELANGS, Value::StringArray(each("")))
+        .add(RPMTAG_DIRINDEXES, Value::Int32(dir_indexes))
+        .add(RPMTAG_BASENAMES, Value::StringArray(base_names))
+        .add(RPMTAG_DIRNAMES, Value::StringArray(dir_names))
+        .add(RPMTAG_FILEDIGESTALGO, Value::Int32(vec![SHA256_ALGO]))
+}
+
+/// Returns the flags of the file installed at `path`: the man pages and the
+/// files in `/usr/share/doc` are documentation, as `rpmbuild` marks them.
+fn file_flags(path: &str) -> u32 {
+    if path.starts_with("/usr/share/man/") || path.starts_with("/usr/share/doc/") {
+        RPMFILE_DOC
+    } else {
+        0
+    }
+}
+
+/// Generates the lead, which RPM only checks the magic number and signature
+/// type of.
+fn generate_lead(name: &str) -> Vec<u8> {
+    let mut lead = vec![0xed, 0xab, 0xee, 0xdb, 3, 0];
+    lead.extend(0u16.to_be_bytes()); // Binary package
+    lead.extend(1u16.to_be_bytes()); // Architecture number
+    let mut name_bytes = [0; 66];
+    let len = name.len().min(65);
+    name_bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+    lead.extend(name_bytes);
+    lead.extend(1u16.to_be_bytes()); // Linux
+    lead.extend(5u16.to_be_bytes()); // Signature in a header
+    lead.extend([0; 16]);
+    lead
+}
+
+fn md5_digest(path: &Path) -> crate::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut hash = md5::Context::new();
+    io::copy(&mut file, &mut hash)?;
+    Ok(hash.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpm_versions() {
+        assert_eq!(rpm_version("1.2.3"), "1.2.3");
+        assert_eq!(rpm_version("1.2.3-beta.1"), "1.2.3~beta.1");
+        assert_eq!(rpm_version("1.2.3-rc-2+build"), "1.2.3~rc_2+build");
+    }
+
+    #[test]
+    fn dependencies() {
+        assert_eq!(
+            Dependency::parse("foo").unwrap(),
+            Dependency::new("foo", RPMSENSE_ANY, "")
+        );
+        assert_eq!(
+            Dependency::parse("foo >= 1.2").unwrap(),
+            Dependency::new("foo", RPMSENSE_GREATER | RPMSENSE_EQUAL, "1.2")
+        );
+        assert_eq!(
+            Dependency::parse("libfoo.so.1()(64bit)<2:3.0-1").unwrap(),
+            Dependency::new("libfoo.so.1()(64bit)", RPMSENSE_LESS, "2:3.0-1")
+        );
+        assert!(Dependency::parse("foo bar").is_err());
+        assert!(Dependency::parse("foo >=").is_err());
+        assert!(Dependency::parse("foo => 1").is_err());
+    }
+
+    #[test]
+    fn scriptlets() {
+        assert_eq!(scriptlet(None, ""), None);
+        assert_eq!(
+            scriptlet(None, "systemctl daemon-reload\n"),
+            Some((
+                vec!["/bin/sh".to_string()],
+                "systemctl daemon-reload\n".to_string()
+            ))
+        );
+        assert_eq!(
+            scriptlet(Some("#!/usr/bin/bash -e\necho hi\n"), "snippet\n"),
+            Some((
+                vec!["/usr/bin/bash".to_string(), "-e".to_string()],
+                "snippet\necho hi\n".to_string()
+            ))
+        );
+
+        let settings = Settings::for_test(
+            "linux_systemd_units = [\"foo.service\"]\n\
+             rpm_pre = { script = \"useradd -r foo\\n\" }\n",
+        );
+        let scriptlets = generate_scriptlets(&settings, &["foo.service".to_string()]).unwrap();
+        let tags: Vec<u32> = scriptlets.iter().map(|scriptlet| scriptlet.tag).collect();
+        assert_eq!(
+            tags,
+            [RPMTAG_PREIN, RPMTAG_POSTIN, RPMTAG_PREUN, RPMTAG_POSTUN]
+        );
+        assert_eq!(scriptlets[0].script, "useradd -r foo\n");
+        assert!(scriptlets[2].script.contains("disable --now 'foo.service'"));
+    }
+
+    #[test]
+    fn doc_file_flags() {
+        assert_eq!(file_flags("/usr/share/man/man1/foo.1.gz"), RPMFILE_DOC);
+        assert_eq!(file_flags("/usr/share/doc/foo/sbom.json"), RPMFILE_DOC);
+        assert_eq!(file_flags("/usr/bin/foo"), 0);
+    }
+
+    #[test]
+    fn header_bytes() {
+        let header = Header::default()
+            .add(1000, Value::String("foo".into()))
+            .add(1001, Value::Int32(vec![7]))
+            .to_bytes(RPMTAG_HEADERIMMUTABLE);
+        let mut expected = vec![0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 24];
+        for entry in [[63, 7, 8, 16], [1000, 6, 0, 1], [1001, 4, 4, 1]] {
+            for field in entry {
+                expected.extend(u32::to_be_bytes(field));
+            }
+        }
+        expected.extend(b"foo\0");
+        expected.extend([0, 0, 0, 7]);
+        expected.extend([0, 0, 0, 63, 0, 0, 0, 7, 0xff, 0xff, 0xff, 0xd0, 0, 0, 0, 16]);
+        assert_eq!(header, expected);
+    }
+
+    #[test]
+    fn cpio_entries() {
+        let mut cpio = CpioWriter {
+            writer: Vec::new(),
+            written: 0,
+        };
+        cpio.write_header(1, 0o100644, 0, 2, "./a").unwrap();
+        cpio.write_all(b"hi").unwrap();
+        cpio.pad().unwrap();
+        assert_eq!(cpio.written, 120);
+        assert_eq!(&cpio.writer[..22], b"07070100000001000081a4");
+        assert_eq!(&cpio.writer[110..], b"./a\0\0\0hi\0\0");
+    }
+
+    #[test]
+    fn lead() {
+        let lead = generate_lead("foo-1.0-1");
+        assert_eq!(lead.len(), 96);
+        assert_eq!(&lead[10..19], b"foo-1.0-1");
+    }
 }
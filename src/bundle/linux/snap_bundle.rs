@@ -0,0 +1,194 @@
+// A snap is built by `snapcraft` from a `snapcraft.yaml` file.  We stage the
+// application files in the same layout as the deb bundler and let a single
+// `dump` part copy them into the snap:
+//
+// bundle/snap/foobar_1.2.3_x86_64/
+//     snap/snapcraft.yaml         # The generated snapcraft project file
+//     files/usr/bin/foobar        # Binary executable file
+//     files/usr/share/applications/foobar.desktop
+//     files/usr/share/icons/hicolor/...
+//     files/usr/lib/foobar/...    # Other resource files
+//
+// `snapcraft pack` then writes `bundle/snap/foobar_1.2.3_x86_64.snap`.
+
+use super::common::{find_largest_icon, generate_data_files};
+use crate::bundle::settings::SnapConfinement;
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DEFAULT_BASE: &str = "core22";
+const DEFAULT_PLUGS: &[&str] = &[
+    "desktop",
+    "desktop-legacy",
+    "wayland",
+    "x11",
+    "opengl",
+    "home",
+];
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    let package_name = format!("{package_base_name}.snap");
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/snap");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    // Stage the files to install.
+    let files_dir = package_dir.join("files");
+    generate_data_files(settings, &files_dir)?;
+
+    let icon = find_largest_icon(&files_dir, settings.binary_name());
+    let snapcraft_yaml = generate_snapcraft_yaml(settings, icon.as_deref());
+    let snapcraft_path = package_dir.join("snap/snapcraft.yaml");
+    fs::create_dir_all(package_dir.join("snap"))?;
+    fs::write(&snapcraft_path, snapcraft_yaml)
+        .with_context(|| format!("Failed to write {snapcraft_path:?}"))?;
+
+    common::run_tool(
+        Command::new("snapcraft")
+            .arg("pack")
+            .arg("--output")
+            .arg(&package_path)
+            .current_dir(&package_dir),
+        "snapcraft",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Derives a valid snap name from the (human-readable) bundle name.  Snap
+/// names may only consist of lowercase ASCII letters, digits and dashes.
+fn snap_name(settings: &Settings) -> String {
+    common::package_name_slug(settings, &[])
+}
+
+/// Quotes a string as a YAML double-quoted scalar.
+fn yaml_string(value: &str) -> String {
+    // JSON strings are valid YAML double-quoted scalars.
+    serde_json::to_string(value).unwrap()
+}
+
+/// Generates the contents of `snapcraft.yaml`.  `icon` is the path of the app
+/// icon relative to the staged `files` directory, if there is one.
+fn generate_snapcraft_yaml(settings: &Settings, icon: Option<&Path>) -> String {
+    let name = snap_name(settings);
+    let bin_name = settings.binary_name();
+    let mut summary = settings.short_description().trim();
+    if summary.is_empty() {
+        summary = settings.bundle_name();
+    }
+    let description = settings
+        .long_description()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or(summary);
+    let grade = settings.snap_grade().map_or("stable", |grade| grade.name());
+    let confinement = settings
+        .snap_confinement()
+        .unwrap_or(SnapConfinement::Strict);
+
+    let mut yaml = String::new();
+    writeln!(yaml, "name: {name}").unwrap();
+    writeln!(
+        yaml,
+        "base: {}",
+        settings.snap_base().unwrap_or(DEFAULT_BASE)
+    )
+    .unwrap();
+    writeln!(
+        yaml,
+        "version: {}",
+        yaml_string(&settings.version_string().to_string())
+    )
+    .unwrap();
+    writeln!(yaml, "summary: {}", yaml_string(summary)).unwrap();
+    writeln!(yaml, "description: {}", yaml_string(description)).unwrap();
+    if let Some(icon) = icon {
+        let icon = Path::new("files").join(icon);
+        writeln!(yaml, "icon: {}", yaml_string(&icon.to_string_lossy())).unwrap();
+    }
+    writeln!(yaml, "grade: {grade}").unwrap();
+    writeln!(yaml, "confinement: {}", confinement.name()).unwrap();
+    writeln!(yaml).unwrap();
+    writeln!(yaml, "apps:").unwrap();
+    writeln!(yaml, "  {name}:").unwrap();
+    writeln!(yaml, "    command: usr/bin/{bin_name}").unwrap();
+    writeln!(
+        yaml,
+        "    desktop: usr/share/applications/{bin_name}.desktop"
+    )
+    .unwrap();
+    // Classic snaps are not sandboxed, so they have no interfaces to plug.
+    if confinement != SnapConfinement::Classic {
+        writeln!(yaml, "    plugs:").unwrap();
+        match settings.snap_plugs() {
+            Some(plugs) => {
+                for plug in plugs {
+                    writeln!(yaml, "      - {}", yaml_string(plug)).unwrap();
+                }
+            }
+            None => {
+                for plug in DEFAULT_PLUGS {
+                    writeln!(yaml, "      - {plug}").unwrap();
+                }
+            }
+        }
+    }
+    writeln!(yaml).unwrap();
+    writeln!(yaml, "parts:").unwrap();
+    writeln!(yaml, "  {name}:").unwrap();
+    writeln!(yaml, "    plugin: dump").unwrap();
+    writeln!(yaml, "    source: files").unwrap();
+    yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_names() {
+        let snap_name = |name: &str| snap_name(&Settings::for_test(&format!("name = {name:?}")));
+        assert_eq!(snap_name("My Cool App"), "my-cool-app");
+        assert_eq!(snap_name("gtk+ viewer_v1.0"), "gtk-viewer-v1-0");
+        assert_eq!(snap_name("日本語"), "example");
+    }
+
+    #[test]
+    fn snapcraft_yaml() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             short_description = \"Does \\\"things\\\"\"\n\
+             snap_confinement = \"devmode\"\n\
+             snap_plugs = [\"network\"]\n",
+        );
+        let yaml = generate_snapcraft_yaml(
+            &settings,
+            Some(Path::new(
+                "usr/share/icons/hicolor/128x128/apps/example.png",
+            )),
+        );
+        assert!(yaml.starts_with("name: my-app\nbase: core22\nversion: \"1.0.0\"\n"));
+        assert!(yaml.contains("summary: \"Does \\\"things\\\"\"\n"));
+        assert!(
+            yaml.contains("icon: \"files/usr/share/icons/hicolor/128x128/apps/example.png\"\n")
+        );
+        assert!(yaml.contains("confinement: devmode\n"));
+        assert!(yaml.contains("    command: usr/bin/example\n"));
+        assert!(yaml.contains("    plugs:\n      - \"network\"\n"));
+    }
+}
@@ -0,0 +1,423 @@
+// A source RPM holds a spec file and the sources that `rpmbuild` builds the
+// binary packages from, as distributions (with koji), COPR and OBS do.  Rather
+// than repackaging the binary, the spec file builds the crate from source,
+// offline:
+//
+// bundle/srpm/foobar-1.2.3/
+//     foobar.spec                     # Builds the crate and installs the files
+//     foobar-1.2.3.crate              # Source0: the crate, from `cargo package`
+//     foobar-1.2.3-vendor.tar.gz      # Source1: its dependencies, from `cargo vendor`
+//     foobar-1.2.3-files.tar.gz       # Source2: the files besides the binary
+// bundle/srpm/foobar-1.2.3-1.src.rpm  # Built with `rpmbuild -bs` (`rpm_srpm`)
+//
+// The files besides the binary (the desktop file, icons, resources, man pages,
+// shell completions and systemd units) are staged as in the rpm bundler, and
+// the spec file gives the same metadata, dependencies and scriptlets, so that
+// the package built from it matches the `rpm` bundle.  The dependencies on
+// shared libraries are left for `rpmbuild` to find.
+
+use super::common::{
+    collect_entries, create_tar_from_dir, generate_data_files, install_shell_completions,
+};
+use super::rpm_bundle::{self, RELEASE, Scriptlet};
+use super::{changelog, systemd};
+use crate::bundle::settings::BuildArtifact;
+use crate::bundle::{Settings, common};
+use anyhow::Context;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What the spec file refers to, besides the settings.
+struct SpecInfo {
+    name: String,
+    version: String,
+    /// The directory that the crate unpacks into, e.g. `foobar-1.2.3`.
+    crate_dir: String,
+    /// The file names of the sources: the crate, its vendored dependencies,
+    /// and the other files to install, if there are any.
+    sources: Vec<String>,
+    /// The source replacement that `cargo vendor` prints, which points cargo
+    /// at the vendored dependencies.
+    cargo_config: String,
+    /// The installed path of the binary, e.g. `/usr/bin/foobar`.
+    binary_path: String,
+    /// The installed paths of the other files.
+    files: Vec<String>,
+    scriptlets: Vec<Scriptlet>,
+    changelog: String,
+}
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let name = super::deb_bundle::package_name(settings);
+    let version = rpm_bundle::rpm_version(&settings.version_string().to_string());
+    let package_name = format!("{name}-{version}");
+    common::print_bundling(&format!("{name}.spec"))?;
+    let base_dir = settings.project_out_directory().join("bundle/srpm");
+    let package_dir = base_dir.join(&package_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_name}"))?;
+    }
+    fs::create_dir_all(&package_dir)?;
+
+    let crate_dir = format!("{}-{}", settings.package_name(), settings.package_version());
+    let crate_file = package_crate(settings, &package_dir, &crate_dir)
+        .with_context(|| "Failed to package the crate")?;
+    let vendor_file = format!("{package_name}-vendor.tar.gz");
+    let cargo_config = vendor_dependencies(settings, &package_dir, &vendor_file)
+        .with_context(|| "Failed to vendor the dependencies")?;
+    let mut sources = vec![crate_file, vendor_file];
+
+    // Stage the files besides the binary.
+    let data_dir = package_dir.join("files");
+    let binary_relpath = generate_data_files(settings, &data_dir)?;
+    install_shell_completions(settings, &data_dir, "usr/share/zsh/site-functions")?;
+    let units = systemd::install_units(settings, &data_dir, "usr/lib/systemd/system")
+        .with_context(|| "Failed to copy systemd units")?;
+    fs::remove_file(data_dir.join(&binary_relpath))?;
+    let files: Vec<String> = collect_entries(&data_dir)?
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| installed_path(&entry.path))
+        .collect();
+    if !files.is_empty() {
+        let files_file = format!("{package_name}-files.tar.gz");
+        let writer = common::gzip_encoder(common::create_file(&package_dir.join(&files_file))?)?;
+        create_tar_from_dir(&data_dir, writer)?
+            .finish()
+            .into_result()?
+            .flush()?;
+        sources.push(files_file);
+    }
+    fs::remove_dir_all(&data_dir)?;
+
+    let info = SpecInfo {
+        name: name.clone(),
+        version: version.clone(),
+        crate_dir,
+        sources,
+        cargo_config,
+        binary_path: installed_path(&binary_relpath),
+        files,
+        scriptlets: rpm_bundle::generate_scriptlets(settings, &units)?,
+        changelog: changelog::rpm_changelog(
+            settings,
+            &settings.version_string().to_string(),
+            &rpm_bundle::packager(settings),
+        )?,
+    };
+    let spec_path = package_dir.join(format!("{name}.spec"));
+    fs::write(&spec_path, generate_spec(settings, &info))
+        .with_context(|| format!("Failed to write {spec_path:?}"))?;
+    let mut paths = vec![package_dir.clone()];
+
+    if settings.rpm_srpm() {
+        common::print_bundling(&format!("{package_name}-{RELEASE}.src.rpm"))?;
+        let output = common::run_tool(
+            Command::new("rpmbuild")
+                .arg("-bs")
+                .arg("--define")
+                .arg(format!("_sourcedir {}", package_dir.display()))
+                .arg("--define")
+                .arg(format!("_srcrpmdir {}", base_dir.display()))
+                .arg(&spec_path),
+            "rpmbuild",
+        )?;
+        let output = String::from_utf8_lossy(&output);
+        let srpm_path = output
+            .lines()
+            .find_map(|line| line.strip_prefix("Wrote: "))
+            .context("rpmbuild did not report the source RPM it wrote")?;
+        paths.push(PathBuf::from(srpm_path.trim()));
+    }
+    Ok(paths)
+}
+
+/// Returns the cargo command, as cargo runs us with.
+fn cargo() -> Command {
+    Command::new(std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")))
+}
+
+/// Packages the crate with `cargo package` into `package_dir`, and returns the
+/// file name of the `.crate`, which unpacks into `crate_dir`.
+fn package_crate(
+    settings: &Settings,
+    package_dir: &Path,
+    crate_dir: &str,
+) -> crate::Result<String> {
+    let target_dir = package_dir.join("target");
+    common::run_tool(
+        cargo()
+            .arg("package")
+            .arg("--manifest-path")
+            .arg(settings.manifest_path())
+            .args(["--no-verify", "--allow-dirty", "--quiet"])
+            .arg("--target-dir")
+            .arg(&target_dir),
+        "cargo package",
+    )?;
+    let crate_file = format!("{crate_dir}.crate");
+    common::copy_file(
+        &target_dir.join("package").join(&crate_file),
+        &package_dir.join(&crate_file),
+    )?;
+    fs::remove_dir_all(&target_dir)?;
+    Ok(crate_file)
+}
+
+/// Vendors the dependencies of the crate with `cargo vendor`, archives them in
+/// `vendor_file` in `package_dir`, and returns the configuration that makes
+/// cargo use them.
+fn vendor_dependencies(
+    settings: &Settings,
+    package_dir: &Path,
+    vendor_file: &str,
+) -> crate::Result<String> {
+    // Vendoring into a relative path makes cargo print a relative path in
+    // the configuration, which then works wherever the crate is unpacked.
+    let vendor_dir = package_dir.join("vendor");
+    fs::create_dir_all(&vendor_dir)?;
+    let config = common::run_tool(
+        cargo()
+            .arg("vendor")
+            .arg("--manifest-path")
+            .arg(settings.manifest_path())
+            .arg("vendor")
+            .current_dir(&vendor_dir),
+        "cargo vendor",
+    )?;
+    let writer = common::gzip_encoder(common::create_file(&package_dir.join(vendor_file))?)?;
+    create_tar_from_dir(&vendor_dir, writer)?
+        .finish()
+        .into_result()?
+        .flush()?;
+    fs::remove_dir_all(&vendor_dir)?;
+    Ok(String::from_utf8_lossy(&config).trim().to_string())
+}
+
+/// Returns the installed path of the file at `relpath` in the data directory.
+fn installed_path(relpath: &Path) -> String {
+    format!("/{}", relpath.to_string_lossy().replace('\\', "/"))
+}
+
+/// Returns the arguments of `cargo build` that build the binary as it is
+/// bundled, but in release mode (unless a custom profile is used), and the
+/// path that the binary is built at.
+fn cargo_build(settings: &Settings) -> (String, String) {
+    let mut args = String::from("build --locked --offline");
+    let profile_dir = match settings.build_profile() {
+        "dev" | "release" => {
+            args.push_str(" --release");
+            "release"
+        }
+        custom => {
+            write!(args, " --profile {custom}").unwrap();
+            custom
+        }
+    };
+    let binary_name = settings.binary_path().file_name().unwrap_or_default();
+    let binary = match settings.build_artifact() {
+        BuildArtifact::Main => format!("target/{profile_dir}/{}", binary_name.display()),
+        BuildArtifact::Bin(name) => {
+            write!(args, " --bin {name}").unwrap();
+            format!("target/{profile_dir}/{}", binary_name.display())
+        }
+        BuildArtifact::Example(name) => {
+            write!(args, " --example {name}").unwrap();
+            format!("target/{profile_dir}/examples/{}", binary_name.display())
+        }
+    };
+    if let Some(features) = settings.features() {
+        write!(args, " --features '{features}'").unwrap();
+    }
+    if settings.all_features() {
+        args.push_str(" --all-features");
+    }
+    if settings.no_default_features() {
+        args.push_str(" --no-default-features");
+    }
+    (args, binary)
+}
+
+/// Escapes the `%` of text in a spec file, which would start a macro.
+fn escape(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+/// Returns a path as it is listed in `%files`, quoted if it has spaces.
+fn files_entry(path: &str) -> String {
+    let path = escape(path);
+    if path.contains(char::is_whitespace) {
+        format!("\"{path}\"")
+    } else {
+        path
+    }
+}
+
+/// Generates the spec file.
+fn generate_spec(settings: &Settings, info: &SpecInfo) -> String {
+    let summary = settings.short_description().trim();
+    let summary = if summary.is_empty() {
+        settings.bundle_name()
+    } else {
+        summary
+    };
+    let description = settings.long_description().unwrap_or(summary).trim();
+    let field = |spec: &mut String, name: &str, value: &str| {
+        writeln!(spec, "{:16}{}", format!("{name}:"), escape(value)).unwrap();
+    };
+
+    let mut spec = String::new();
+    field(&mut spec, "Name", &info.name);
+    field(&mut spec, "Version", &info.version);
+    // `%{?dist}` is kept unescaped, so that it names the distribution.
+    writeln!(spec, "{:16}{RELEASE}%{{?dist}}", "Release:").unwrap();
+    if let Some(epoch) = settings.rpm_epoch() {
+        field(&mut spec, "Epoch", &epoch.to_string());
+    }
+    field(&mut spec, "Summary", summary);
+    field(
+        &mut spec,
+        "License",
+        settings.license().unwrap_or("Unspecified"),
+    );
+    if let Some(url) = settings.rpm_url() {
+        field(&mut spec, "URL", url);
+    }
+    if settings.rpm_group() != "Unspecified" {
+        field(&mut spec, "Group", settings.rpm_group());
+    }
+    for (i, source) in info.sources.iter().enumerate() {
+        field(&mut spec, &format!("Source{i}"), source);
+    }
+    writeln!(spec).unwrap();
+    field(&mut spec, "BuildRequires", "cargo");
+    field(&mut spec, "BuildRequires", "rust");
+    let dependencies = [
+        ("Requires", settings.rpm_requires()),
+        ("Provides", settings.rpm_provides()),
+        ("Conflicts", settings.rpm_conflicts()),
+        ("Obsoletes", settings.rpm_obsoletes()),
+    ];
+    for (name, values) in dependencies {
+        for value in values {
+            field(&mut spec, name, value);
+        }
+    }
+
+    writeln!(spec, "\n%description\n{}", escape(description)).unwrap();
+
+    writeln!(spec, "\n%prep\n%setup -q -n {} -a 1", info.crate_dir).unwrap();
+    writeln!(
+        spec,
+        "mkdir -p .cargo\ncat > .cargo/config.toml << 'EOF'\n{}\nEOF",
+        escape(&info.cargo_config)
+    )
+    .unwrap();
+
+    let (build_args, binary) = cargo_build(settings);
+    writeln!(spec, "\n%build").unwrap();
+    writeln!(spec, "export RUSTFLAGS=\"%{{?build_rustflags}}\"").unwrap();
+    writeln!(spec, "cargo {}", escape(&build_args)).unwrap();
+
+    writeln!(spec, "\n%install").unwrap();
+    if !info.files.is_empty() {
+        writeln!(spec, "mkdir -p %{{buildroot}}").unwrap();
+        writeln!(spec, "tar -xzf %{{SOURCE2}} -C %{{buildroot}}").unwrap();
+    }
+    writeln!(
+        spec,
+        "install -Dm755 {} %{{buildroot}}{}",
+        escape(&binary),
+        escape(&info.binary_path)
+    )
+    .unwrap();
+
+    for scriptlet in &info.scriptlets {
+        match scriptlet.interpreter.first().map(String::as_str) {
+            Some("/bin/sh") | None => writeln!(spec, "\n%{}", scriptlet.name).unwrap(),
+            Some(interpreter) => writeln!(spec, "\n%{} -p {interpreter}", scriptlet.name).unwrap(),
+        }
+        writeln!(spec, "{}", escape(scriptlet.script.trim_end())).unwrap();
+    }
+
+    writeln!(spec, "\n%files").unwrap();
+    writeln!(spec, "{}", files_entry(&info.binary_path)).unwrap();
+    for file in &info.files {
+        writeln!(spec, "{}", files_entry(file)).unwrap();
+    }
+
+    write!(spec, "\n%changelog\n{}", info.changelog).unwrap();
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_file() {
+        let settings = Settings::for_test(
+            r#"
+            short_description = "An example with 100% coverage"
+            linux_systemd_units = ["foo.service"]
+            rpm_requires = ["bash >= 4"]
+            rpm_epoch = 1
+            "#,
+        );
+        let info = SpecInfo {
+            name: "example".to_string(),
+            version: "1.0.0".to_string(),
+            crate_dir: "example-1.0.0".to_string(),
+            sources: vec![
+                "example-1.0.0.crate".to_string(),
+                "example-1.0.0-vendor.tar.gz".to_string(),
+                "example-1.0.0-files.tar.gz".to_string(),
+            ],
+            cargo_config: "[source.crates-io]\nreplace-with = \"vendored-sources\"".to_string(),
+            binary_path: "/usr/bin/example".to_string(),
+            files: vec!["/usr/share/doc/example/read me.txt".to_string()],
+            scriptlets: rpm_bundle::generate_scriptlets(&settings, &["foo.service".to_string()])
+                .unwrap(),
+            changelog: "* Tue Jan 02 2024 Jane Doe <jane@example.com> - 1.0.0-1\n- Release.\n"
+                .to_string(),
+        };
+        let spec = generate_spec(&settings, &info);
+        assert!(spec.starts_with(
+            "Name:           example\n\
+             Version:        1.0.0\n\
+             Release:        1%{?dist}\n\
+             Epoch:          1\n\
+             Summary:        An example with 100%% coverage\n\
+             License:        Unspecified\n\
+             Source0:        example-1.0.0.crate\n\
+             Source1:        example-1.0.0-vendor.tar.gz\n\
+             Source2:        example-1.0.0-files.tar.gz\n\
+             \n\
+             BuildRequires:  cargo\n\
+             BuildRequires:  rust\n\
+             Requires:       bash >= 4\n"
+        ));
+        assert!(spec.contains(
+            "%prep\n%setup -q -n example-1.0.0 -a 1\nmkdir -p .cargo\n\
+             cat > .cargo/config.toml << 'EOF'\n[source.crates-io]\n"
+        ));
+        assert!(spec.contains("\ncargo build --locked --offline --release\n"));
+        assert!(spec.contains("\ntar -xzf %{SOURCE2} -C %{buildroot}\n"));
+        assert!(
+            spec.contains("\ninstall -Dm755 target/release/example %{buildroot}/usr/bin/example\n")
+        );
+        assert!(spec.contains("\n%post\nif [ $1 -eq 1 ]"));
+        assert!(spec.contains("\n%preun\n"));
+        assert!(
+            spec.contains("\n%files\n/usr/bin/example\n\"/usr/share/doc/example/read me.txt\"\n")
+        );
+        assert!(spec.ends_with(
+            "\n%changelog\n* Tue Jan 02 2024 Jane Doe <jane@example.com> - 1.0.0-1\n- Release.\n"
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+// The `linux_systemd_units` are installed with the package, and its maintainer
+// scripts enable and start them as `dh_installsystemd` would, through the
+// `deb-systemd-helper` and `deb-systemd-invoke` tools of Debian's
+// init-system-helpers:
+//
+// postinst   # Enables the units (unless the admin disabled them before), and
+//            # starts them, or restarts them on upgrades
+// prerm      # Stops the units when the package is removed
+// postrm     # Disables the units when the package is removed, and forgets
+//            # about them when it is purged
+//
+// rpm packages install the units in `usr/lib/systemd/system`, and their
+// scriptlets are expanded from the `%systemd_post`, `%systemd_preun` and
+// `%systemd_postun_with_restart` macros of Fedora's systemd-rpm-macros:
+//
+// %post      # Enables the units (and starts them) on the first install
+// %preun     # Disables and stops the units when the package is erased
+// %postun    # Reloads systemd, and restarts the units on upgrades
+//
+// Unlike `%systemd_post`, which applies the presets of the distribution (that
+// leave most services disabled), `%post` enables the units as the deb
+// postinst does, unless `linux_systemd_enable` is false.
+//
+// The snippets only run the tools if systemd is running (or the tools are
+// installed), so that installing the package in a chroot or container works.
+
+use crate::bundle::{Settings, common};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Installs the `linux_systemd_units` into `unit_dir` under the `data_dir`,
+/// e.g. `lib/systemd/system`, and returns their names.
+pub fn install_units(
+    settings: &Settings,
+    data_dir: &Path,
+    unit_dir: &str,
+) -> crate::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for unit in settings.linux_systemd_units() {
+        let Some(name) = unit.file_name() else {
+            anyhow::bail!("Systemd unit {unit:?} is not a file");
+        };
+        let dest: PathBuf = data_dir.join(unit_dir).join(name);
+        common::copy_file(&unit, &dest)?;
+        names.push(name.to_string_lossy().into_owned());
+    }
+    Ok(names)
+}
+
+/// Returns the units that are enabled or started: those that are not
+/// templates, which are only instantiated by the admin, e.g. `foo@.service`.
+fn managed_units(units: &[String]) -> Vec<&str> {
+    units
+        .iter()
+        .map(String::as_str)
+        .filter(|unit| !unit.contains("@."))
+        .collect()
+}
+
+/// Quotes `units` as arguments of the shell.
+fn quoted(units: &[&str]) -> String {
+    let quoted: Vec<String> = units.iter().map(|unit| format!("'{unit}'")).collect();
+    quoted.join(" ")
+}
+
+/// Returns the snippet of the deb `postinst` script for `units`.
+pub fn deb_postinst(settings: &Settings, units: &[String]) -> String {
+    let units = managed_units(units);
+    let mut script = String::new();
+    if units.is_empty() {
+        return script;
+    }
+    let units = quoted(&units);
+    let configure = "if [ \"$1\" = \"configure\" ] || [ \"$1\" = \"abort-upgrade\" ] || \
+                     [ \"$1\" = \"abort-deconfigure\" ] || [ \"$1\" = \"abort-remove\" ]; then";
+    writeln!(script, "{configure}").unwrap();
+    if settings.linux_systemd_enable() {
+        // This only removes the masks the postrm made when the package was
+        // removed.
+        writeln!(
+            script,
+            "\tdeb-systemd-helper unmask {units} >/dev/null || true"
+        )
+        .unwrap();
+        // was-enabled is true for units that were never seen before, so that
+        // the units are enabled on new installs.
+        writeln!(
+            script,
+            "\tif deb-systemd-helper --quiet was-enabled {units}; then\n\
+             \t\tdeb-systemd-helper enable {units} >/dev/null || true\n\
+             \telse\n\
+             \t\tdeb-systemd-helper update-state {units} >/dev/null || true\n\
+             \tfi"
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            script,
+            "\tdeb-systemd-helper update-state {units} >/dev/null || true"
+        )
+        .unwrap();
+    }
+    writeln!(script, "fi").unwrap();
+    writeln!(script, "{configure}").unwrap();
+    writeln!(script, "\tif [ -d /run/systemd/system ]; then").unwrap();
+    writeln!(
+        script,
+        "\t\tsystemctl --system daemon-reload >/dev/null || true"
+    )
+    .unwrap();
+    if settings.linux_systemd_start() {
+        writeln!(
+            script,
+            "\t\tif [ -n \"$2\" ]; then\n\
+             \t\t\tdeb-systemd-invoke restart {units} >/dev/null || true\n\
+             \t\telse\n\
+             \t\t\tdeb-systemd-invoke start {units} >/dev/null || true\n\
+             \t\tfi"
+        )
+        .unwrap();
+    }
+    writeln!(script, "\tfi").unwrap();
+    writeln!(script, "fi").unwrap();
+    script
+}
+
+/// Returns the snippet of the deb `prerm` script for `units`.
+pub fn deb_prerm(settings: &Settings, units: &[String]) -> String {
+    let units = managed_units(units);
+    if units.is_empty() || !settings.linux_systemd_start() {
+        return String::new();
+    }
+    format!(
+        "if [ -d /run/systemd/system ] && [ \"$1\" = remove ]; then\n\
+         \tdeb-systemd-invoke stop {} >/dev/null || true\n\
+         fi\n",
+        quoted(&units)
+    )
+}
+
+/// Returns the snippet of the deb `postrm` script for `units`.
+pub fn deb_postrm(units: &[String]) -> String {
+    let mut script = String::new();
+    if units.is_empty() {
+        return script;
+    }
+    writeln!(script, "if [ -d /run/systemd/system ]; then").unwrap();
+    writeln!(
+        script,
+        "\tsystemctl --system daemon-reload >/dev/null || true"
+    )
+    .unwrap();
+    writeln!(script, "fi").unwrap();
+    let units = managed_units(units);
+    if units.is_empty() {
+        return script;
+    }
+    let units = quoted(&units);
+    writeln!(
+        script,
+        "if [ \"$1\" = \"remove\" ]; then\n\
+         \tif [ -x \"/usr/bin/deb-systemd-helper\" ]; then\n\
+         \t\tdeb-systemd-helper mask {units} >/dev/null || true\n\
+         \tfi\n\
+         fi\n\
+         if [ \"$1\" = \"purge\" ]; then\n\
+         \tif [ -x \"/usr/bin/deb-systemd-helper\" ]; then\n\
+         \t\tdeb-systemd-helper purge {units} >/dev/null || true\n\
+         \t\tdeb-systemd-helper unmask {units} >/dev/null || true\n\
+         \tfi\n\
+         fi"
+    )
+    .unwrap();
+    script
+}
+
+/// Returns the snippet of the rpm `%post` scriptlet for `units`.
+pub fn rpm_post(settings: &Settings, units: &[String]) -> String {
+    let units = managed_units(units);
+    let enable = settings.linux_systemd_enable();
+    let start = settings.linux_systemd_start();
+    let mut script = String::new();
+    if units.is_empty() || !(enable || start) {
+        return script;
+    }
+    let units = quoted(&units);
+    writeln!(script, "if [ $1 -eq 1 ] && [ -x /usr/bin/systemctl ]; then").unwrap();
+    if enable {
+        writeln!(
+            script,
+            "\tsystemctl --no-reload enable {units} >/dev/null 2>&1 || :"
+        )
+        .unwrap();
+    }
+    if start {
+        writeln!(
+            script,
+            "\tif [ -d /run/systemd/system ]; then\n\
+             \t\tsystemctl daemon-reload >/dev/null 2>&1 || :\n\
+             \t\tsystemctl start {units} >/dev/null 2>&1 || :\n\
+             \tfi"
+        )
+        .unwrap();
+    }
+    writeln!(script, "fi").unwrap();
+    script
+}
+
+/// Returns the snippet of the rpm `%preun` scriptlet for `units`.
+pub fn rpm_preun(settings: &Settings, units: &[String]) -> String {
+    let units = managed_units(units);
+    if units.is_empty() {
+        return String::new();
+    }
+    let now = if settings.linux_systemd_start() {
+        " --now"
+    } else {
+        ""
+    };
+    format!(
+        "if [ $1 -eq 0 ] && [ -x /usr/bin/systemctl ]; then\n\
+         \tsystemctl --no-reload disable{now} {} >/dev/null 2>&1 || :\n\
+         fi\n",
+        quoted(&units)
+    )
+}
+
+/// Returns the snippet of the rpm `%postun` scriptlet for `units`.
+pub fn rpm_postun(settings: &Settings, units: &[String]) -> String {
+    let mut script = String::new();
+    if units.is_empty() {
+        return script;
+    }
+    writeln!(
+        script,
+        "if [ -x /usr/bin/systemctl ] && [ -d /run/systemd/system ]; then\n\
+         \tsystemctl daemon-reload >/dev/null 2>&1 || :\n\
+         fi"
+    )
+    .unwrap();
+    let units = managed_units(units);
+    if units.is_empty() || !settings.linux_systemd_start() {
+        return script;
+    }
+    writeln!(
+        script,
+        "if [ $1 -ge 1 ] && [ -x /usr/bin/systemctl ]; then\n\
+         \tsystemctl try-restart {} >/dev/null 2>&1 || :\n\
+         fi",
+        quoted(&units)
+    )
+    .unwrap();
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deb_snippets() {
+        let settings = Settings::for_test("linux_systemd_units = [\"foo.service\"]");
+        let units = ["foo.service".to_string(), "foo@.service".to_string()];
+        let postinst = deb_postinst(&settings, &units);
+        assert!(postinst.contains("\tdeb-systemd-helper unmask 'foo.service' >/dev/null"));
+        assert!(postinst.contains("\t\tdeb-systemd-helper enable 'foo.service' >/dev/null"));
+        assert!(postinst.contains("\t\t\tdeb-systemd-invoke restart 'foo.service' >/dev/null"));
+        assert!(!postinst.contains("foo@"));
+        assert_eq!(
+            deb_prerm(&settings, &units),
+            "if [ -d /run/systemd/system ] && [ \"$1\" = remove ]; then\n\
+             \tdeb-systemd-invoke stop 'foo.service' >/dev/null || true\n\
+             fi\n"
+        );
+        let postrm = deb_postrm(&units);
+        assert!(postrm.starts_with("if [ -d /run/systemd/system ]; then\n"));
+        assert!(postrm.contains("\t\tdeb-systemd-helper purge 'foo.service' >/dev/null"));
+
+        let settings = Settings::for_test(
+            "linux_systemd_units = [\"foo.service\"]\n\
+             linux_systemd_enable = false\n\
+             linux_systemd_start = false\n",
+        );
+        let postinst = deb_postinst(&settings, &units);
+        assert!(!postinst.contains("enable"));
+        assert!(!postinst.contains("deb-systemd-invoke"));
+        assert!(postinst.contains("daemon-reload"));
+        assert_eq!(deb_prerm(&settings, &units), "");
+
+        // Template units are installed, but left to the admin.
+        let units = ["foo@.service".to_string()];
+        assert_eq!(deb_postinst(&settings, &units), "");
+        assert!(!deb_postrm(&units).contains("deb-systemd-helper"));
+    }
+
+    #[test]
+    fn rpm_snippets() {
+        let settings = Settings::for_test("linux_systemd_units = [\"foo.service\"]");
+        let units = ["foo.service".to_string(), "foo@.service".to_string()];
+        let post = rpm_post(&settings, &units);
+        assert!(post.starts_with("if [ $1 -eq 1 ] && [ -x /usr/bin/systemctl ]; then\n"));
+        assert!(post.contains("\tsystemctl --no-reload enable 'foo.service' >/dev/null 2>&1"));
+        assert!(post.contains("\t\tsystemctl start 'foo.service' >/dev/null 2>&1"));
+        assert!(!post.contains("foo@"));
+        assert_eq!(
+            rpm_preun(&settings, &units),
+            "if [ $1 -eq 0 ] && [ -x /usr/bin/systemctl ]; then\n\
+             \tsystemctl --no-reload disable --now 'foo.service' >/dev/null 2>&1 || :\n\
+             fi\n"
+        );
+        let postun = rpm_postun(&settings, &units);
+        assert!(postun.contains("\tsystemctl daemon-reload >/dev/null 2>&1"));
+        assert!(postun.contains("\tsystemctl try-restart 'foo.service' >/dev/null 2>&1"));
+
+        let settings = Settings::for_test(
+            "linux_systemd_units = [\"foo.service\"]\n\
+             linux_systemd_enable = false\n\
+             linux_systemd_start = false\n",
+        );
+        assert_eq!(rpm_post(&settings, &units), "");
+        assert!(!rpm_preun(&settings, &units).contains("--now"));
+        assert!(!rpm_postun(&settings, &units).contains("try-restart"));
+
+        // Template units are installed, but left to the admin.
+        let units = ["foo@.service".to_string()];
+        assert_eq!(rpm_preun(&settings, &units), "");
+        assert!(rpm_postun(&settings, &units).contains("daemon-reload"));
+    }
+}
@@ -0,0 +1,131 @@
+// With `--message-format json`, a JSON object describing each file produced is
+// printed to stdout, one per line, for CI pipelines to read:
+//
+// {"checksums":{"sha256":"<hex digest>"},"format":"deb",
+//  "path":"/.../foobar_1.2.3_amd64.deb","signed":true,"size":1234}
+//
+// The checksums are those of the `--checksums` algorithms, or else SHA-256.
+// Directory bundles, such as OSX app bundles, have the total size of their
+// files, and no checksums.  The `format` of files describing the bundles, such
+// as checksum files, package manifests and debug symbols, is `null`.  Progress
+// and warnings are still printed to stderr.
+
+use super::checksums::ChecksumAlgorithm;
+use super::common;
+use super::{Artifact, PackageType};
+use std::fs;
+use std::io::{self, Write};
+use walkdir::WalkDir;
+
+/// How the produced bundles are reported.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Only list the bundles on stderr.
+    #[default]
+    Human,
+    /// Also print a JSON object for each bundle to stdout.
+    Json,
+}
+
+/// Prints a JSON object for each of the `artifacts` to stdout, with checksums
+/// made with each of the `algorithms`.
+pub fn print_artifacts(
+    artifacts: &[Artifact],
+    algorithms: &[ChecksumAlgorithm],
+) -> crate::Result<()> {
+    let algorithms = match algorithms {
+        [] => &[ChecksumAlgorithm::Sha256],
+        algorithms => algorithms,
+    };
+    let mut stdout = io::stdout().lock();
+    for artifact in artifacts {
+        serde_json::to_writer(&mut stdout, &artifact_message(artifact, algorithms)?)?;
+        writeln!(stdout)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn artifact_message(
+    artifact: &Artifact,
+    algorithms: &[ChecksumAlgorithm],
+) -> crate::Result<serde_json::Value> {
+    let path = &artifact.path;
+    let (size, checksums) = if path.is_dir() {
+        let mut size = 0;
+        for entry in WalkDir::new(path) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                size += entry.metadata()?.len();
+            }
+        }
+        (size, serde_json::Value::Null)
+    } else {
+        let mut checksums = serde_json::Map::new();
+        for algorithm in algorithms {
+            checksums.insert(
+                algorithm.name().to_string(),
+                common::digest_hex(path, *algorithm)?.into(),
+            );
+        }
+        (fs::metadata(path)?.len(), checksums.into())
+    };
+    Ok(serde_json::json!({
+        "format": artifact.package_type.as_ref().map(PackageType::short_name),
+        "path": path,
+        "size": size,
+        "checksums": checksums,
+        "signed": artifact.signed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifact_messages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let deb = tmp.path().join("foo.deb");
+        fs::write(&deb, "hello\n").unwrap();
+        let app = tmp.path().join("Foo.app");
+        fs::create_dir_all(app.join("Contents/MacOS")).unwrap();
+        fs::write(app.join("Contents/Info.plist"), "<plist/>\n").unwrap();
+        fs::write(app.join("Contents/MacOS/foo"), "binary\n").unwrap();
+
+        let message = artifact_message(
+            &Artifact {
+                path: deb.clone(),
+                package_type: Some(PackageType::Deb),
+                signed: true,
+            },
+            &[ChecksumAlgorithm::Sha256],
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({
+                "format": "deb",
+                "path": deb,
+                "size": 6,
+                "checksums": {
+                    "sha256": "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03",
+                },
+                "signed": true,
+            })
+        );
+
+        let message =
+            artifact_message(&Artifact::from(app.clone()), &[ChecksumAlgorithm::Sha256]).unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({
+                "format": null,
+                "path": app,
+                "size": 16,
+                "checksums": null,
+                "signed": false,
+            })
+        );
+    }
+}
@@ -1,30 +1,251 @@
+mod android_bundle;
+mod appstore_bundle;
+mod archive_bundle;
+mod build_info;
 mod category;
+mod checksums;
+mod choco_bundle;
 mod common;
+mod debug_symbols;
+mod dmg_bundle;
+mod formats;
+mod freebsd_bundle;
+mod gpg;
+mod homebrew;
+mod icons;
 mod ios_bundle;
 mod linux;
+mod messages;
 mod msi_bundle;
+mod msix_bundle;
+mod notarize;
+mod nsis_bundle;
+mod oci_bundle;
 mod osx_bundle;
+mod pkg_bundle;
+mod sbom;
+mod scoop;
 mod settings;
+mod shared_libs;
+mod sparkle;
+mod svg;
+mod version;
+mod windows_registry;
+mod windows_sign;
+mod winget;
 mod wxsmsi_bundle;
+mod zip;
 
-pub use self::common::{print_error, print_finished};
+pub use self::build_info::pin_source_date_epoch;
+pub use self::checksums::{ChecksumAlgorithm, write_checksums};
+pub use self::common::{DenyWarnings, check_warnings, deny_warnings, print_error, print_finished};
+pub use self::formats::list_formats;
+pub use self::ios_bundle::run_in_simulator;
 use self::linux::appimage_bundle;
-pub use self::settings::{BuildArtifact, PackageType, Settings};
-use crate::bundle::linux::{deb_bundle, rpm_bundle};
+pub use self::messages::{MessageFormat, print_artifacts};
+pub use self::osx_bundle::create_universal_binary;
+pub use self::settings::{
+    BuildArtifact, PackageType, Settings, UNIVERSAL_MACOS_TARGETS, check_identifier_collisions,
+};
+use crate::bundle::linux::{
+    apk_bundle, aur_bundle, deb_bundle, flatpak_bundle, ipk_bundle, nix_bundle, pacman_bundle,
+    portable_bundle, rpm_bundle, snap_bundle, srpm_bundle,
+};
+use anyhow::Context;
 use std::path::PathBuf;
+use std::process::Command;
 
-pub fn bundle_project(settings: Settings) -> crate::Result<Vec<PathBuf>> {
-    let mut paths = Vec::new();
-    for package_type in settings.package_types()? {
-        paths.append(&mut match package_type {
+/// A file or directory produced by bundling.
+#[derive(Clone, Debug)]
+pub struct Artifact {
+    pub path: PathBuf,
+    /// The format of the bundle, or `None` for files describing bundles, such
+    /// as package manifests and debug symbols.
+    pub package_type: Option<PackageType>,
+    /// True if the bundle itself was signed (or, for deb packages, has a
+    /// detached signature).
+    pub signed: bool,
+}
+
+impl From<PathBuf> for Artifact {
+    fn from(path: PathBuf) -> Artifact {
+        Artifact {
+            path,
+            package_type: None,
+            signed: false,
+        }
+    }
+}
+
+pub fn bundle_project(mut settings: Settings) -> crate::Result<Vec<Artifact>> {
+    let mut outputs = Vec::new();
+    let mut artifacts = Vec::new();
+    version::resolve_version(&mut settings)?;
+    let package_types = settings.package_types()?;
+    if settings.reproducible() {
+        let unreproducible: Vec<_> = package_types
+            .iter()
+            .filter(|package_type| !package_type.is_reproducible())
+            .map(PackageType::short_name)
+            .collect();
+        if !unreproducible.is_empty() {
+            anyhow::bail!(
+                "These bundle formats are not reproducible: {}",
+                unreproducible.join(", ")
+            );
+        }
+    }
+    debug_symbols::strip_binary(&mut settings)?;
+    icons::generate_icons(&mut settings)?;
+    if settings.windows_sign().is_some()
+        && package_types.iter().any(|package_type| {
+            matches!(
+                package_type,
+                PackageType::WindowsMsi
+                    | PackageType::WxsMsi
+                    | PackageType::Nsis
+                    | PackageType::Msix
+                    | PackageType::Choco
+            )
+        })
+    {
+        // The binary and external binaries are signed (in place) before they
+        // are packaged into any installer.
+        windows_sign::sign_file(&settings, settings.binary_path())?;
+        for binary in shared_libs::bundled_executables(&settings)? {
+            windows_sign::sign_file(&settings, &binary.src)?;
+        }
+    }
+    for package_type in package_types {
+        let mut package_paths = match package_type {
             PackageType::OsxBundle => osx_bundle::bundle_project(&settings)?,
             PackageType::IosBundle => ios_bundle::bundle_project(&settings)?,
             PackageType::WindowsMsi => msi_bundle::bundle_project(&settings)?,
             PackageType::WxsMsi => wxsmsi_bundle::bundle_project(&settings)?,
             PackageType::Deb => deb_bundle::bundle_project(&settings)?,
             PackageType::Rpm => rpm_bundle::bundle_project(&settings)?,
+            PackageType::Srpm => srpm_bundle::bundle_project(&settings)?,
             PackageType::AppImage => appimage_bundle::bundle_project(&settings)?,
-        });
+            PackageType::Flatpak => flatpak_bundle::bundle_project(&settings)?,
+            PackageType::Snap => snap_bundle::bundle_project(&settings)?,
+            PackageType::Dmg => dmg_bundle::bundle_project(&settings)?,
+            PackageType::Pkg => pkg_bundle::bundle_project(&settings)?,
+            PackageType::Nsis => nsis_bundle::bundle_project(&settings)?,
+            PackageType::Msix => msix_bundle::bundle_project(&settings)?,
+            PackageType::Pacman => pacman_bundle::bundle_project(&settings)?,
+            PackageType::Apk => apk_bundle::bundle_project(&settings)?,
+            PackageType::Nix => nix_bundle::bundle_project(&settings)?,
+            PackageType::Choco => choco_bundle::bundle_project(&settings)?,
+            PackageType::Archive => archive_bundle::bundle_project(&settings)?,
+            PackageType::Aur => aur_bundle::bundle_project(&settings)?,
+            PackageType::FreeBsd => freebsd_bundle::bundle_project(&settings)?,
+            PackageType::Android => android_bundle::bundle_project(&settings)?,
+            PackageType::Ipk => ipk_bundle::bundle_project(&settings)?,
+            PackageType::Oci => oci_bundle::bundle_project(&settings)?,
+            PackageType::Portable => portable_bundle::bundle_project(&settings)?,
+            PackageType::AppStore => appstore_bundle::bundle_project(&settings)?,
+        };
+        if matches!(
+            package_type,
+            PackageType::WindowsMsi | PackageType::WxsMsi | PackageType::Nsis | PackageType::Msix
+        ) {
+            for path in &package_paths {
+                windows_sign::sign_file(&settings, path)?;
+            }
+        }
+        // Renaming keeps the order of the paths.
+        let signed: Vec<bool> = package_paths
+            .iter()
+            .map(|path| common::was_signed(path))
+            .collect();
+        if let Some(template) = settings.artifact_name_template() {
+            package_paths = rename_artifacts(&settings, package_type, template, package_paths)?;
+        }
+        artifacts.extend(
+            package_paths
+                .iter()
+                .map(|path| (package_type, path.clone())),
+        );
+        outputs.extend(
+            package_paths
+                .iter()
+                .zip(signed)
+                .map(|(path, signed)| Artifact {
+                    path: path.clone(),
+                    package_type: Some(package_type),
+                    signed,
+                }),
+        );
+        if let Some(url_template) = settings.homebrew_url() {
+            let manifests = homebrew::generate_manifests(&settings, url_template, &package_paths)?;
+            outputs.extend(manifests.into_iter().map(Artifact::from));
+        }
+        if let Some(url_template) = settings.scoop_url() {
+            let manifests = scoop::generate_manifests(&settings, url_template, &package_paths)?;
+            outputs.extend(manifests.into_iter().map(Artifact::from));
+        }
     }
-    Ok(paths)
+    if let Some(url_template) = settings.winget_url() {
+        // One set of manifests lists all of the installers.
+        let manifests = winget::generate_manifests(&settings, url_template, &artifacts)?;
+        outputs.extend(manifests.into_iter().map(Artifact::from));
+    }
+    if !outputs.is_empty() {
+        outputs.extend(
+            debug_symbols::archive_pdb(&settings)?
+                .into_iter()
+                .map(Artifact::from),
+        );
+        outputs.extend(
+            debug_symbols::archive_dsym(&settings)?
+                .into_iter()
+                .map(Artifact::from),
+        );
+    }
+    Ok(outputs)
+}
+
+/// Renames the artifacts produced for `package_type` according to the
+/// `artifact_name_template` setting.
+fn rename_artifacts(
+    settings: &Settings,
+    package_type: PackageType,
+    template: &str,
+    paths: Vec<PathBuf>,
+) -> crate::Result<Vec<PathBuf>> {
+    let version = settings.version_string().to_string();
+    let arch = match package_type {
+        PackageType::Deb => deb_bundle::arch_name(settings),
+        PackageType::Rpm => rpm_bundle::arch_name(settings.binary_arch()),
+        PackageType::Srpm => "src",
+        PackageType::Ipk => settings
+            .ipk_arch()
+            .unwrap_or_else(|| ipk_bundle::arch_name(settings.binary_arch())),
+        _ => settings.binary_arch(),
+    };
+    let target = match settings.target_triple() {
+        Some(triple) => triple.to_string(),
+        None => host_triple()?,
+    };
+    let tokens = [
+        ("name", settings.binary_name()),
+        ("version", version.as_str()),
+        ("arch", arch),
+        ("format", package_type.short_name()),
+        ("target", target.as_str()),
+    ];
+    common::rename_artifacts(&paths, template, &tokens)
+}
+
+/// Returns the target triple of the host, as reported by `rustc -vV`.
+fn host_triple() -> crate::Result<String> {
+    let output = Command::new("rustc")
+        .arg("-vV")
+        .output()
+        .context("Failed to run `rustc -vV`")?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(|host| host.trim().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Failed to determine the host target triple"))
 }
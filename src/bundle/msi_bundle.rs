@@ -1,11 +1,22 @@
+use super::build_info::{BUILD_INFO_FILE_NAME, build_time};
 use super::common;
-use super::settings::Settings;
+use super::settings::{
+    InstallScope, RegistryData, RegistryEntry, RegistryRoot, ServiceStartType, Settings,
+    WindowsService,
+};
+use super::shared_libs;
+use super::windows_registry;
+use super::wxsmsi_bundle::{
+    arp_properties, installer_images, language_suffix, publisher, sanitize_version_for_wix,
+    upgrade_code, windows_services,
+};
 use anyhow::Context;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use uuid::Uuid;
 
 type Package = msi::Package<fs::File>;
@@ -23,6 +34,47 @@ const FILE_ATTR_VITAL: u16 = 0x200;
 // The name of the installer package's sole Feature:
 const MAIN_FEATURE_NAME: &str = "MainFeature";
 
+// Upgrade table attributes: migrate the feature states of the older product,
+// only detect the product rather than remove it, and match the maximum
+// version too:
+const UPGRADE_ATTR_MIGRATE_FEATURES: i32 = 0x1;
+const UPGRADE_ATTR_ONLY_DETECT: i32 = 0x2;
+const UPGRADE_ATTR_VERSION_MAX_INCLUSIVE: i32 = 0x200;
+
+// The database key for the `%LOCALAPPDATA%\Programs` folder, that per-user
+// installs install to:
+const LOCAL_PROGRAMS_DIR_KEY: &str = "LocalProgramsFolder";
+
+// Summary information word count bits: the files are compressed, and the
+// package installs without elevation:
+const WORD_COUNT_COMPRESSED: i32 = 0x2;
+const WORD_COUNT_NO_ELEVATION: i32 = 0x8;
+
+// The database key for the Start Menu folder of the app:
+const START_MENU_DIR_KEY: &str = "ApplicationProgramsFolder";
+
+// ServiceInstall table values: the service runs in a process of its own, and
+// failing to install it fails the installation:
+const SERVICE_TYPE_OWN_PROCESS: i32 = 0x10;
+const SERVICE_ERROR_CONTROL_NORMAL_VITAL: i32 = 0x8001;
+
+// ServiceControl table events: start the service on install, stop it on
+// install (of an upgrade) and uninstall, and delete it on uninstall:
+const SERVICE_EVENT_INSTALL_START: i32 = 0x1;
+const SERVICE_EVENT_INSTALL_STOP: i32 = 0x2;
+const SERVICE_EVENT_UNINSTALL_STOP: i32 = 0x20;
+const SERVICE_EVENT_UNINSTALL_DELETE: i32 = 0x80;
+
+// Component attributes indicating that its key path is a registry value, and
+// that it is 64-bit (so that it writes to the 64-bit view of the registry):
+const COMPONENT_ATTR_REGISTRY_KEY_PATH: i32 = 0x4;
+const COMPONENT_ATTR_64BIT: i32 = 0x100;
+
+// The properties which FindRelatedProducts sets to the products it finds, that
+// are either upgraded or newer than the package:
+const UPGRADE_FOUND_PROPERTY: &str = "UPGRADEFOUND";
+const NEWER_VERSION_FOUND_PROPERTY: &str = "NEWERVERSIONFOUND";
+
 // A v4 UUID that was generated specifically for cargo-bundle, to be used as a
 // namespace for generating v5 UUIDs from bundle identifier strings.
 const UUID_NAMESPACE: [u8; 16] = [
@@ -55,6 +107,52 @@ struct DirectoryInfo {
     files: Vec<String>,
 }
 
+// Info about a shortcut to the binary executable.  Shortcuts are not files that
+// a component can be keyed on, so each has a component of its own, whose key
+// path is a registry value under HKCU.
+struct ShortcutInfo {
+    // The database key for this shortcut, which is also the key of its
+    // component and of that registry value.
+    key: &'static str,
+    // The database key for the directory that the shortcut is created in.
+    directory_key: &'static str,
+}
+
+// The platform that a package is built for.
+struct Platform {
+    // The platform of the summary information's Template property.
+    template: &'static str,
+    // The schema of the package (its summary information's Page Count), which
+    // is the minimum Windows Installer version to install it, e.g. 500 for 5.0.
+    schema: i32,
+    // True if the package installs 64-bit components.
+    is_64bit: bool,
+}
+
+impl Platform {
+    // Returns the platform of packages for the target `arch`, e.g. `aarch64`.
+    fn for_arch(arch: &str) -> Platform {
+        match arch {
+            "x86_64" => Platform {
+                template: "x64",
+                schema: 200,
+                is_64bit: true,
+            },
+            // Arm64 packages require Windows Installer 5.0.
+            "aarch64" => Platform {
+                template: "Arm64",
+                schema: 500,
+                is_64bit: true,
+            },
+            _ => Platform {
+                template: "Intel",
+                schema: 200,
+                is_64bit: false,
+            },
+        }
+    }
+}
+
 // Info about a CAB archive within the installer package.
 struct CabinetInfo {
     // The stream name for this cabinet.
@@ -65,51 +163,115 @@ struct CabinetInfo {
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     common::print_warning("MSI bundle support is still experimental.")?;
+    if !settings.windows_prerequisites().is_empty() {
+        common::print_warning(
+            "`windows.prerequisites` are installed by the setup.exe of `wxsmsi` installers; \
+             the `msi` installer does not install them.",
+        )?;
+    }
+    // The installers for every language share the upgrade code, so it is
+    // worked out (and any note about it printed) once.
+    let upgrade_code = upgrade_code(settings)?;
+    let mut msi_paths = Vec::new();
+    for language in settings.msi_languages() {
+        msi_paths.push(bundle_language(
+            &settings.for_msi_language(&language),
+            upgrade_code,
+        )?);
+    }
+    Ok(msi_paths)
+}
 
-    let msi_name = format!("{}.msi", settings.bundle_name());
+// Builds the installer for the `msi_language` of the settings, in a directory
+// of its own if there are several languages, with the given upgrade code.
+fn bundle_language(settings: &Settings, upgrade_code: Uuid) -> crate::Result<PathBuf> {
+    let msi_name = format!(
+        "{}{}.msi",
+        settings.bundle_file_name(),
+        language_suffix(settings)
+    );
     common::print_bundling(&msi_name)?;
-    let base_dir = settings.project_out_directory().join("bundle/msi");
+    let mut base_dir = settings.project_out_directory().join("bundle/msi");
+    // The installers are kept apart, in case they are renamed alike.
+    if settings.msi_languages().len() > 1 {
+        base_dir.push(settings.msi_language());
+    }
     let msi_path = base_dir.join(&msi_name);
     let mut package =
         new_empty_package(&msi_path).with_context(|| "Failed to initialize MSI package")?;
 
+    // Collect the resource files, whose size Add/Remove Programs shows:
+    let mut resources = collect_resource_info(settings)
+        .with_context(|| "Failed to collect resource file information")?;
+    let installed_size = resources.iter().map(|resource| resource.size).sum();
+
     // Generate package metadata:
     let guid = generate_package_guid(settings);
-    set_summary_info(&mut package, guid, settings);
-    create_property_table(&mut package, guid, settings)
+    let creation_time = SystemTime::from(build_time()?);
+    set_summary_info(&mut package, guid, creation_time, settings);
+    create_property_table(&mut package, guid, upgrade_code, installed_size, settings)
         .with_context(|| "Failed to generate Property table")?;
+    create_upgrade_table(&mut package, upgrade_code, settings)
+        .with_context(|| "Failed to generate Upgrade table")?;
+    create_launch_condition_table(&mut package, settings)
+        .with_context(|| "Failed to generate LaunchCondition table")?;
 
     // Copy resource files into package:
-    let mut resources = collect_resource_info(settings)
-        .with_context(|| "Failed to collect resource file information")?;
     let directories = collect_directory_info(settings, &mut resources)
         .with_context(|| "Failed to collect resource directory information")?;
+    let shortcuts = collect_shortcut_info(settings);
     let cabinets = divide_resources_into_cabinets(resources);
-    generate_resource_cabinets(&mut package, &cabinets)
+    generate_resource_cabinets(&mut package, &cabinets, creation_time)
         .with_context(|| "Failed to generate resource cabinets")?;
 
     // Set up installer database tables:
-    create_directory_table(&mut package, &directories)
+    create_directory_table(&mut package, settings, &directories, &shortcuts)
         .with_context(|| "Failed to generate Directory table")?;
     create_feature_table(&mut package, settings)
         .with_context(|| "Failed to generate Feature table")?;
-    create_component_table(&mut package, guid, &directories)
-        .with_context(|| "Failed to generate Component table")?;
-    create_feature_components_table(&mut package, &directories)
+    let registry = settings.windows_registry();
+    create_component_table(
+        &mut package,
+        settings,
+        upgrade_code,
+        &directories,
+        &shortcuts,
+    )
+    .with_context(|| "Failed to generate Component table")?;
+    create_feature_components_table(&mut package, &directories, &shortcuts, registry)
         .with_context(|| "Failed to generate FeatureComponents table")?;
     create_media_table(&mut package, &cabinets)
         .with_context(|| "Failed to generate Media table")?;
     create_file_table(&mut package, &cabinets).with_context(|| "Failed to generate File table")?;
+    create_registry_table(&mut package, settings, &shortcuts, registry)
+        .with_context(|| "Failed to generate Registry table")?;
+    create_shortcut_table(&mut package, settings, &shortcuts)
+        .with_context(|| "Failed to generate Shortcut table")?;
+    create_remove_file_table(&mut package, &shortcuts)
+        .with_context(|| "Failed to generate RemoveFile table")?;
+    let services = windows_services(settings)?;
+    create_service_install_table(&mut package, services)
+        .with_context(|| "Failed to generate ServiceInstall table")?;
+    create_service_control_table(&mut package, services)
+        .with_context(|| "Failed to generate ServiceControl table")?;
     create_install_execute_sequence_table(&mut package, &cabinets)
         .with_context(|| "Failed to generate InstallExecuteSequence table")?;
     create_install_ui_sequence_table(&mut package, &cabinets)
         .with_context(|| "Failed to generate InstallUISequence table")?;
-    create_dialog_table(&mut package, &cabinets)
+    let license = license_text(settings)?;
+    let license = license.as_deref();
+    let bitmaps = create_binary_table(&mut package, settings)
+        .with_context(|| "Failed to generate Binary table")?;
+    create_dialog_table(&mut package, license)
         .with_context(|| "Failed to generate Dialog table")?;
-    create_control_table(&mut package, &cabinets)
+    create_control_table(&mut package, license, &bitmaps)
         .with_context(|| "Failed to generate Control table")?;
-    create_control_event_table(&mut package, &cabinets)
+    create_control_event_table(&mut package, license)
         .with_context(|| "Failed to generate ControlEvent table")?;
+    create_control_condition_table(&mut package, license)
+        .with_context(|| "Failed to generate ControlCondition table")?;
+    create_check_box_table(&mut package, license)
+        .with_context(|| "Failed to generate CheckBox table")?;
     create_event_mapping_table(&mut package, &cabinets)
         .with_context(|| "Failed to generate EventMapping table")?;
     create_text_style_table(&mut package, &cabinets)
@@ -131,11 +293,14 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         create_app_icon(&mut stream, settings)?;
     }
     package.insert_rows(
-        msi::Insert::into("Icon").row(vec![msi::Value::Str(icon_name), msi::Value::from("Name")]),
+        msi::Insert::into("Icon").row(vec![msi::Value::Str(icon_name), msi::Value::Binary]),
     )?;
 
     package.flush()?;
-    Ok(vec![msi_path])
+    drop(package);
+    set_root_entry_time(&msi_path, creation_time)
+        .with_context(|| "Failed to set MSI modification time")?;
+    Ok(msi_path)
 }
 
 fn new_empty_package(msi_path: &Path) -> crate::Result<Package> {
@@ -154,34 +319,81 @@ fn new_empty_package(msi_path: &Path) -> crate::Result<Package> {
     Ok(package)
 }
 
-// Generates a GUID for the package, based on `settings.bundle_identifier()`.
+// Returns the product code of the package, which is also its GUID.
+pub fn product_code(settings: &Settings) -> Uuid {
+    generate_package_guid(settings)
+}
+
+// Generates a GUID for the package, based on `settings.bundle_identifier()`
+// and the version, which must change with each version for the package to
+// upgrade the older ones.
 fn generate_package_guid(settings: &Settings) -> Uuid {
     let namespace = Uuid::from_bytes(UUID_NAMESPACE);
-    Uuid::new_v5(&namespace, settings.bundle_identifier().as_bytes())
+    let name = format!(
+        "{}/{}",
+        settings.bundle_identifier(),
+        settings.version_string()
+    );
+    Uuid::new_v5(&namespace, name.as_bytes())
+}
+
+// Sets the creation and modification times of the package's root storage,
+// which the compound file writer otherwise sets to the current time.
+fn set_root_entry_time(msi_path: &Path, time: SystemTime) -> crate::Result<()> {
+    let mut compound_file = cfb::open_rw(msi_path)?;
+    compound_file.set_created_time("/", time)?;
+    compound_file.set_modified_time("/", time)?;
+    compound_file.flush()?;
+    Ok(())
 }
 
 // Populates the summary metadata for the package from the bundle settings.
-fn set_summary_info(package: &mut Package, package_guid: Uuid, settings: &Settings) {
+fn set_summary_info(
+    package: &mut Package,
+    package_guid: Uuid,
+    creation_time: SystemTime,
+    settings: &Settings,
+) {
+    let language = settings.msi_language();
     let summary_info = package.summary_info_mut();
-    summary_info.set_creation_time_to_now();
-    summary_info.set_subject(settings.bundle_name().to_string());
+    summary_info.set_creation_time(creation_time);
+    summary_info.set_subject(settings.localized_bundle_name(language).to_string());
     summary_info.set_uuid(package_guid);
-    summary_info.set_comments(settings.short_description().to_string());
+    let platform = Platform::for_arch(settings.binary_arch());
+    summary_info.set_arch(platform.template);
+    summary_info.set_page_count(platform.schema);
+    summary_info.set_languages(&[msi::Language::from_tag(language)]);
+    summary_info.set_comments(settings.localized_short_description(language).to_string());
     if let Some(authors) = settings.authors_comma_separated() {
         summary_info.set_author(authors);
     }
     let creating_app = crate::version_info!();
     summary_info.set_creating_application(creating_app);
-    summary_info.set_word_count(2);
+    summary_info.set_word_count(match settings.windows_install_scope() {
+        InstallScope::PerMachine => WORD_COUNT_COMPRESSED,
+        InstallScope::PerUser | InstallScope::Dual => {
+            WORD_COUNT_COMPRESSED | WORD_COUNT_NO_ELEVATION
+        }
+    });
 }
 
-// Creates and populates the `Property` database table for the package.
+// Creates and populates the `Property` database table for the package, with
+// the Add/Remove Programs properties, giving the `installed_size` in bytes.
 fn create_property_table(
     package: &mut Package,
     package_guid: Uuid,
+    upgrade_code: Uuid,
+    installed_size: u64,
     settings: &Settings,
 ) -> crate::Result<()> {
-    let authors = settings.authors_comma_separated().unwrap_or_default();
+    let language = settings.msi_language();
+    // Per-user installs leave ALLUSERS unset, and dual-purpose ones install
+    // per-user unless ALLUSERS=1 or MSIINSTALLPERUSER="" is passed.
+    let scope_properties: &[(&str, &str)] = match settings.windows_install_scope() {
+        InstallScope::PerMachine => &[("ALLUSERS", "1")],
+        InstallScope::PerUser => &[],
+        InstallScope::Dual => &[("ALLUSERS", "2"), ("MSIINSTALLPERUSER", "1")],
+    };
     package.create_table(
         "Property",
         vec![
@@ -193,7 +405,7 @@ fn create_property_table(
         msi::Insert::into("Property")
             .row(vec![
                 msi::Value::from("Manufacturer"),
-                msi::Value::Str(authors),
+                msi::Value::Str(publisher(settings)),
             ])
             .row(vec![
                 msi::Value::from("ProductCode"),
@@ -201,15 +413,29 @@ fn create_property_table(
             ])
             .row(vec![
                 msi::Value::from("ProductLanguage"),
-                msi::Value::from(msi::Language::from_tag("en-US")),
+                msi::Value::from(msi::Language::from_tag(language)),
             ])
             .row(vec![
                 msi::Value::from("ProductName"),
-                msi::Value::from(settings.bundle_name()),
+                msi::Value::from(settings.localized_bundle_name(language)),
             ])
             .row(vec![
                 msi::Value::from("ProductVersion"),
-                msi::Value::from(settings.version_string().to_string()),
+                msi::Value::from(sanitize_version_for_wix(
+                    &settings.version_string().to_string(),
+                )),
+            ])
+            .row(vec![
+                msi::Value::from("UpgradeCode"),
+                msi::Value::from(upgrade_code),
+            ])
+            .row(vec![
+                // The properties set by FindRelatedProducts must be passed on
+                // to the elevated install, which removes the older product.
+                msi::Value::from("SecureCustomProperties"),
+                msi::Value::Str(format!(
+                    "{NEWER_VERSION_FOUND_PROPERTY};{UPGRADE_FOUND_PROPERTY}"
+                )),
             ])
             .row(vec![
                 msi::Value::from("DefaultUIFont"),
@@ -231,28 +457,168 @@ fn create_property_table(
             .row(vec![
                 msi::Value::from("Text_done"),
                 msi::Value::from("installed"),
+            ])
+            .row(vec![
+                // In kilobytes.
+                msi::Value::from("ARPSIZE"),
+                msi::Value::Str(installed_size.div_ceil(1024).to_string()),
+            ])
+            .rows(
+                arp_properties(settings, &format!("{}.ico", settings.binary_name()))
+                    .into_iter()
+                    .map(|(property, value)| {
+                        vec![msi::Value::from(property), msi::Value::Str(value)]
+                    })
+                    .collect(),
+            )
+            .rows(
+                scope_properties
+                    .iter()
+                    .map(|&(property, value)| {
+                        vec![msi::Value::from(property), msi::Value::from(value)]
+                    })
+                    .collect(),
+            ),
+    )?;
+    Ok(())
+}
+
+// Creates and populates the `Upgrade` database table for the package, with
+// which FindRelatedProducts finds the other versions of the product: the
+// older ones (or the same version, as versions differing only in their
+// pre-release tag have the same four-part version), which are removed, and
+// the newer ones, which the `LaunchCondition` table refuses to downgrade.
+fn create_upgrade_table(
+    package: &mut Package,
+    upgrade_code: Uuid,
+    settings: &Settings,
+) -> crate::Result<()> {
+    package.create_table(
+        "Upgrade",
+        vec![
+            msi::Column::build("UpgradeCode")
+                .primary_key()
+                .category(msi::Category::Guid)
+                .string(38),
+            msi::Column::build("VersionMin")
+                .primary_key()
+                .nullable()
+                .category(msi::Category::Text)
+                .string(20),
+            msi::Column::build("VersionMax")
+                .primary_key()
+                .nullable()
+                .category(msi::Category::Text)
+                .string(20),
+            msi::Column::build("Language")
+                .primary_key()
+                .nullable()
+                .category(msi::Category::Language)
+                .string(255),
+            msi::Column::build("Attributes").primary_key().int32(),
+            msi::Column::build("Remove")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("ActionProperty")
+                .category(msi::Category::UpperCase)
+                .string(72),
+        ],
+    )?;
+    let version = sanitize_version_for_wix(&settings.version_string().to_string());
+    package.insert_rows(
+        msi::Insert::into("Upgrade")
+            .row(vec![
+                msi::Value::from(upgrade_code),
+                msi::Value::Null,
+                msi::Value::Str(version.clone()),
+                msi::Value::Null,
+                msi::Value::Int(UPGRADE_ATTR_MIGRATE_FEATURES | UPGRADE_ATTR_VERSION_MAX_INCLUSIVE),
+                msi::Value::Null,
+                msi::Value::from(UPGRADE_FOUND_PROPERTY),
+            ])
+            .row(vec![
+                msi::Value::from(upgrade_code),
+                msi::Value::Str(version),
+                msi::Value::Null,
+                msi::Value::Null,
+                msi::Value::Int(UPGRADE_ATTR_ONLY_DETECT),
+                msi::Value::Null,
+                msi::Value::from(NEWER_VERSION_FOUND_PROPERTY),
             ]),
     )?;
     Ok(())
 }
 
-// Returns a list of `ResourceInfo` structs for the binary executable and all
-// the resource files that should be included in the package.
+// Creates and populates the `LaunchCondition` database table for the package,
+// which refuses to install over a newer version of the product.
+fn create_launch_condition_table(package: &mut Package, settings: &Settings) -> crate::Result<()> {
+    package.create_table(
+        "LaunchCondition",
+        vec![
+            msi::Column::build("Condition")
+                .primary_key()
+                .category(msi::Category::Condition)
+                .string(255),
+            msi::Column::build("Description").formatted_string(255),
+        ],
+    )?;
+    let name = settings.localized_bundle_name(settings.msi_language());
+    package.insert_rows(msi::Insert::into("LaunchCondition").row(vec![
+        msi::Value::Str(format!("NOT {NEWER_VERSION_FOUND_PROPERTY}")),
+        msi::Value::Str(format!(
+            "A newer version of {} is already installed.",
+            windows_registry::escape_formatted(name)
+        )),
+    ]))?;
+    Ok(())
+}
+
+// Returns the file name of the binary executable, which is also the key of its
+// row in the File table.
+fn exe_name(settings: &Settings) -> String {
+    let file_name = settings.binary_path().file_name().unwrap_or_default();
+    file_name.to_string_lossy().into_owned()
+}
+
+// Returns a list of `ResourceInfo` structs for the binary executable, the
+// external binaries and all the resource files that should be included in the
+// package.
 fn collect_resource_info(settings: &Settings) -> crate::Result<Vec<ResourceInfo>> {
     let mut resources = Vec::<ResourceInfo>::new();
+    let exe_name = exe_name(settings);
     resources.push(ResourceInfo {
         source_path: settings.binary_path().to_path_buf(),
-        dest_path: PathBuf::from(settings.binary_name()),
-        filename: settings.binary_name().to_string(),
+        dest_path: PathBuf::from(&exe_name),
+        filename: exe_name,
         size: settings.binary_path().metadata()?.len(),
         component_key: String::new(),
     });
+    for binary in shared_libs::bundled_executables(settings)? {
+        resources.push(ResourceInfo {
+            size: binary.src.metadata()?.len(),
+            source_path: binary.src,
+            dest_path: PathBuf::from(&binary.name),
+            filename: binary.name,
+            component_key: String::new(),
+        });
+    }
+    for icon in windows_registry::icon_files(settings) {
+        let filename = icon.file_name().unwrap().to_string_lossy().into_owned();
+        resources.push(ResourceInfo {
+            size: icon.metadata()?.len(),
+            source_path: icon,
+            dest_path: PathBuf::from(&filename),
+            filename,
+            component_key: String::new(),
+        });
+    }
     let root_rsrc_dir = PathBuf::from("Resources");
-    for source_path in settings.resource_files() {
-        let source_path = source_path?;
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let source_path = resource.src;
         let metadata = source_path.metadata()?;
         let size = metadata.len();
-        let dest_path = root_rsrc_dir.join(common::resource_relpath(&source_path));
+        let dest_path = root_rsrc_dir.join(&resource.dest);
         let filename = dest_path.file_name().unwrap().to_string_lossy().to_string();
         let info = ResourceInfo {
             source_path,
@@ -263,6 +629,30 @@ fn collect_resource_info(settings: &Settings) -> crate::Result<Vec<ResourceInfo>
         };
         resources.push(info);
     }
+    if let Some(build_info) = settings.build_info() {
+        let build_info_dir = settings.project_out_directory().join("bundle/msi");
+        build_info.write_json(&build_info_dir)?;
+        let source_path = build_info_dir.join(BUILD_INFO_FILE_NAME);
+        resources.push(ResourceInfo {
+            size: source_path.metadata()?.len(),
+            source_path,
+            dest_path: root_rsrc_dir.join(BUILD_INFO_FILE_NAME),
+            filename: BUILD_INFO_FILE_NAME.to_string(),
+            component_key: String::new(),
+        });
+    }
+    if let Some(sbom) = settings.sbom() {
+        let sbom_dir = settings.project_out_directory().join("bundle/msi");
+        sbom.write_json(&sbom_dir)?;
+        let source_path = sbom_dir.join(sbom.file_name());
+        resources.push(ResourceInfo {
+            size: source_path.metadata()?.len(),
+            source_path,
+            dest_path: root_rsrc_dir.join(sbom.file_name()),
+            filename: sbom.file_name().to_string(),
+            component_key: String::new(),
+        });
+    }
     Ok(resources)
 }
 
@@ -281,8 +671,8 @@ fn collect_directory_info(
         PathBuf::new(),
         DirectoryInfo {
             key: "INSTALLDIR".to_string(),
-            parent_key: "ProgramFilesFolder".to_string(),
-            name: settings.bundle_name().to_string(),
+            parent_key: install_root_key(settings).to_string(),
+            name: settings.bundle_file_name(),
             files: Vec::new(),
         },
     );
@@ -318,6 +708,40 @@ fn collect_directory_info(
     Ok(dir_map.into_values().collect())
 }
 
+// Returns the database key of the directory that INSTALLDIR is created in.
+// Dual-purpose packages install to the Program Files folder, which Windows
+// redirects to `%LOCALAPPDATA%\Programs` when they install per-user.
+fn install_root_key(settings: &Settings) -> &'static str {
+    match settings.windows_install_scope() {
+        InstallScope::PerMachine | InstallScope::Dual => {
+            if Platform::for_arch(settings.binary_arch()).is_64bit {
+                "ProgramFiles64Folder"
+            } else {
+                "ProgramFilesFolder"
+            }
+        }
+        InstallScope::PerUser => LOCAL_PROGRAMS_DIR_KEY,
+    }
+}
+
+// Returns the shortcuts to the binary executable that the package creates.
+fn collect_shortcut_info(settings: &Settings) -> Vec<ShortcutInfo> {
+    let mut shortcuts = Vec::new();
+    if settings.msi_start_menu_shortcut() {
+        shortcuts.push(ShortcutInfo {
+            key: "StartMenuShortcut",
+            directory_key: START_MENU_DIR_KEY,
+        });
+    }
+    if settings.msi_desktop_shortcut() {
+        shortcuts.push(ShortcutInfo {
+            key: "DesktopShortcut",
+            directory_key: "DesktopFolder",
+        });
+    }
+    shortcuts
+}
+
 // Divides up the list of resource into some number of cabinets, subject to a
 // few constraints: 1) no one cabinet will have two resources with the same
 // filename, 2) no one cabinet will have more than `CABINET_MAX_FILES` files
@@ -357,7 +781,10 @@ fn divide_resources_into_cabinets(mut resources: Vec<ResourceInfo>) -> Vec<Cabin
 fn generate_resource_cabinets(
     package: &mut Package,
     cabinets: &[CabinetInfo],
+    mtime: SystemTime,
 ) -> crate::Result<()> {
+    let mtime = time::OffsetDateTime::from(mtime);
+    let mtime = time::PrimitiveDateTime::new(mtime.date(), mtime.time());
     for cabinet_info in cabinets.iter() {
         let mut builder = cab::CabinetBuilder::new();
         let mut file_map = HashMap::<String, &Path>::new();
@@ -370,7 +797,9 @@ fn generate_resource_cabinets(
             {
                 let resource = &cabinet_info.resources[resource_index];
                 folder_size += resource.size;
-                folder.add_file(resource.filename.as_str());
+                folder
+                    .add_file(resource.filename.as_str())
+                    .set_datetime(mtime);
                 debug_assert!(!file_map.contains_key(&resource.filename));
                 file_map.insert(resource.filename.clone(), &resource.source_path);
                 resource_index += 1;
@@ -389,10 +818,13 @@ fn generate_resource_cabinets(
     Ok(())
 }
 
-// Creates and populates the `Directory` database table for the package.
+// Creates and populates the `Directory` database table for the package,
+// including the directories that the shortcuts are created in.
 fn create_directory_table(
     package: &mut Package,
+    settings: &Settings,
     directories: &[DirectoryInfo],
+    shortcuts: &[ShortcutInfo],
 ) -> crate::Result<()> {
     package.create_table(
         "Directory",
@@ -415,6 +847,44 @@ fn create_directory_table(
             msi::Value::Str(directory.name.clone()),
         ]);
     }
+    for shortcut in shortcuts {
+        if shortcut.directory_key == START_MENU_DIR_KEY {
+            rows.push(vec![
+                msi::Value::from("ProgramMenuFolder"),
+                msi::Value::from("TARGETDIR"),
+                msi::Value::from("."),
+            ]);
+            rows.push(vec![
+                msi::Value::from(START_MENU_DIR_KEY),
+                msi::Value::from("ProgramMenuFolder"),
+                msi::Value::Str(settings.bundle_file_name()),
+            ]);
+        } else {
+            rows.push(vec![
+                msi::Value::from(shortcut.directory_key),
+                msi::Value::from("TARGETDIR"),
+                msi::Value::from("."),
+            ]);
+        }
+    }
+    if install_root_key(settings) == LOCAL_PROGRAMS_DIR_KEY {
+        rows.push(vec![
+            msi::Value::from("LocalAppDataFolder"),
+            msi::Value::from("TARGETDIR"),
+            msi::Value::from("."),
+        ]);
+        rows.push(vec![
+            msi::Value::from(LOCAL_PROGRAMS_DIR_KEY),
+            msi::Value::from("LocalAppDataFolder"),
+            msi::Value::from("Programs"),
+        ]);
+    } else {
+        rows.push(vec![
+            msi::Value::from(install_root_key(settings)),
+            msi::Value::from("TARGETDIR"),
+            msi::Value::from("."),
+        ]);
+    }
     package.insert_rows(
         msi::Insert::into("Directory")
             .row(vec![
@@ -422,11 +892,6 @@ fn create_directory_table(
                 msi::Value::Null,
                 msi::Value::from("SourceDir"),
             ])
-            .row(vec![
-                msi::Value::from("ProgramFilesFolder"),
-                msi::Value::from("TARGETDIR"),
-                msi::Value::from("."),
-            ])
             .rows(rows),
     )?;
     Ok(())
@@ -462,7 +927,7 @@ fn create_feature_table(package: &mut Package, settings: &Settings) -> crate::Re
     package.insert_rows(msi::Insert::into("Feature").row(vec![
         msi::Value::from(MAIN_FEATURE_NAME),
         msi::Value::Null,
-        msi::Value::from(settings.bundle_name()),
+        msi::Value::from(settings.localized_bundle_name(settings.msi_language())),
         msi::Value::Null,
         msi::Value::Int(1),
         msi::Value::Int(1),
@@ -473,12 +938,20 @@ fn create_feature_table(package: &mut Package, settings: &Settings) -> crate::Re
 }
 
 // Creates and populates the `Component` database table for the package.  One
-// component is created for each subdirectory under in the install dir.
+// component is created for each subdirectory under in the install dir, and one
+// for each shortcut.
 fn create_component_table(
     package: &mut Package,
-    package_guid: Uuid,
+    settings: &Settings,
+    upgrade_code: Uuid,
     directories: &[DirectoryInfo],
+    shortcuts: &[ShortcutInfo],
 ) -> crate::Result<()> {
+    let bitness = if Platform::for_arch(settings.binary_arch()).is_64bit {
+        COMPONENT_ATTR_64BIT
+    } else {
+        0
+    };
     package.create_table(
         "Component",
         vec![
@@ -503,17 +976,47 @@ fn create_component_table(
     for directory in directories.iter() {
         if !directory.files.is_empty() {
             let hash_input = directory.files.join("/");
-            let uuid = Uuid::new_v5(&package_guid, hash_input.as_bytes());
+            // The component codes stay the same across the versions.
+            let uuid = Uuid::new_v5(&upgrade_code, hash_input.as_bytes());
             rows.push(vec![
                 msi::Value::Str(directory.key.clone()),
                 msi::Value::from(uuid),
                 msi::Value::Str(directory.key.clone()),
-                msi::Value::Int(0),
+                msi::Value::Int(bitness),
                 msi::Value::Null,
                 msi::Value::Str(directory.files[0].clone()),
             ]);
         }
     }
+    for shortcut in shortcuts {
+        rows.push(vec![
+            msi::Value::from(shortcut.key),
+            msi::Value::from(Uuid::new_v5(&upgrade_code, shortcut.key.as_bytes())),
+            msi::Value::from(shortcut.directory_key),
+            msi::Value::Int(COMPONENT_ATTR_REGISTRY_KEY_PATH | bitness),
+            msi::Value::Null,
+            msi::Value::from(shortcut.key),
+        ]);
+    }
+    // Each of the authored registry values is the key path of a component of
+    // its own, whose code depends on where the value is.
+    for (index, entry) in settings.windows_registry().iter().enumerate() {
+        let key = registry_entry_key(index);
+        let location = format!(
+            "{:?}\\{}\\{}",
+            entry.root,
+            entry.key,
+            entry.name.as_deref().unwrap_or_default()
+        );
+        rows.push(vec![
+            msi::Value::Str(key.clone()),
+            msi::Value::from(Uuid::new_v5(&upgrade_code, location.as_bytes())),
+            msi::Value::from("INSTALLDIR"),
+            msi::Value::Int(COMPONENT_ATTR_REGISTRY_KEY_PATH | bitness),
+            msi::Value::Null,
+            msi::Value::Str(key),
+        ]);
+    }
     package.insert_rows(msi::Insert::into("Component").rows(rows))?;
     Ok(())
 }
@@ -523,6 +1026,8 @@ fn create_component_table(
 fn create_feature_components_table(
     package: &mut Package,
     directories: &[DirectoryInfo],
+    shortcuts: &[ShortcutInfo],
+    registry: &[RegistryEntry],
 ) -> crate::Result<()> {
     package.create_table(
         "FeatureComponents",
@@ -546,6 +1051,18 @@ fn create_feature_components_table(
             ]);
         }
     }
+    for shortcut in shortcuts {
+        rows.push(vec![
+            msi::Value::from(MAIN_FEATURE_NAME),
+            msi::Value::from(shortcut.key),
+        ]);
+    }
+    for index in 0..registry.len() {
+        rows.push(vec![
+            msi::Value::from(MAIN_FEATURE_NAME),
+            msi::Value::Str(registry_entry_key(index)),
+        ]);
+    }
     package.insert_rows(msi::Insert::into("FeatureComponents").rows(rows))?;
     Ok(())
 }
@@ -643,6 +1160,341 @@ fn create_file_table(package: &mut Package, cabinets: &[CabinetInfo]) -> crate::
     Ok(())
 }
 
+// Creates and populates the `Registry` database table for the package, with
+// the values registering the file associations and URL schemes.  These belong to the
+// component of the install dir, which the binary executable is part of.  The
+// components of the shortcuts each have a value of their own too.
+fn create_registry_table(
+    package: &mut Package,
+    settings: &Settings,
+    shortcuts: &[ShortcutInfo],
+    registry: &[RegistryEntry],
+) -> crate::Result<()> {
+    package.create_table(
+        "Registry",
+        vec![
+            msi::Column::build("Registry").primary_key().id_string(72),
+            msi::Column::build("Root").range(-1, 3).int16(),
+            msi::Column::build("Key")
+                .category(msi::Category::RegPath)
+                .string(255),
+            msi::Column::build("Name")
+                .nullable()
+                .category(msi::Category::Formatted)
+                .string(255),
+            msi::Column::build("Value")
+                .nullable()
+                .category(msi::Category::Formatted)
+                .string(0),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+        ],
+    )?;
+    let values = windows_registry::registry_values(
+        settings,
+        "[INSTALLDIR]",
+        windows_registry::escape_formatted,
+    );
+    let mut rows = Vec::new();
+    for (index, value) in values.into_iter().enumerate() {
+        rows.push(vec![
+            msi::Value::Str(format!("REG{index:04}")),
+            // HKLM for machine-wide installs, and HKCU for per-user ones.
+            msi::Value::Int(-1),
+            msi::Value::Str(format!("{}\\{}", windows_registry::CLASSES_KEY, value.key)),
+            value.name.map_or(msi::Value::Null, msi::Value::Str),
+            msi::Value::Str(value.value),
+            msi::Value::from("INSTALLDIR"),
+        ]);
+    }
+    let key = format!(
+        "Software\\{}",
+        windows_registry::escape_formatted(&settings.bundle_file_name())
+    );
+    for shortcut in shortcuts {
+        rows.push(vec![
+            msi::Value::from(shortcut.key),
+            // HKCU, as shortcuts may be per-user.
+            msi::Value::Int(1),
+            msi::Value::Str(key.clone()),
+            msi::Value::from(shortcut.key),
+            msi::Value::from("#1"),
+            msi::Value::from(shortcut.key),
+        ]);
+    }
+    let formatted = |value: &str| {
+        windows_registry::expand_install_dir(
+            value,
+            "[INSTALLDIR]",
+            windows_registry::escape_formatted,
+        )
+    };
+    for (index, entry) in registry.iter().enumerate() {
+        let root = match entry.root {
+            RegistryRoot::Hkmu => -1,
+            RegistryRoot::Hkcr => 0,
+            RegistryRoot::Hkcu => 1,
+            RegistryRoot::Hklm => 2,
+            RegistryRoot::Hku => 3,
+        };
+        let value = match &entry.value {
+            RegistryData::Dword(value) => format!("#{value}"),
+            // Values starting with `#` are numbers, unless it is doubled.
+            RegistryData::String(value) if value.starts_with('#') => {
+                format!("#{}", formatted(value))
+            }
+            RegistryData::String(value) => formatted(value),
+        };
+        rows.push(vec![
+            msi::Value::Str(registry_entry_key(index)),
+            msi::Value::Int(root),
+            msi::Value::Str(formatted(&entry.key)),
+            entry
+                .name
+                .as_deref()
+                .map_or(msi::Value::Null, |name| msi::Value::Str(formatted(name))),
+            msi::Value::Str(value),
+            msi::Value::Str(registry_entry_key(index)),
+        ]);
+    }
+    package.insert_rows(msi::Insert::into("Registry").rows(rows))?;
+    Ok(())
+}
+
+// Returns the database key of the `index`th authored registry value, which is
+// also the key of its component.
+fn registry_entry_key(index: usize) -> String {
+    format!("RegistryEntry{index}")
+}
+
+// Creates and populates the `Shortcut` database table for the package, with
+// the shortcuts to the binary executable, which start it in the install dir.
+fn create_shortcut_table(
+    package: &mut Package,
+    settings: &Settings,
+    shortcuts: &[ShortcutInfo],
+) -> crate::Result<()> {
+    package.create_table(
+        "Shortcut",
+        vec![
+            msi::Column::build("Shortcut").primary_key().id_string(72),
+            msi::Column::build("Directory_")
+                .foreign_key("Directory", 1)
+                .id_string(72),
+            msi::Column::build("Name")
+                .category(msi::Category::Filename)
+                .string(128),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+            msi::Column::build("Target")
+                .category(msi::Category::Shortcut)
+                .string(72),
+            msi::Column::build("Arguments")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("Description")
+                .nullable()
+                .text_string(255),
+            msi::Column::build("Hotkey").nullable().int16(),
+            msi::Column::build("Icon_")
+                .nullable()
+                .foreign_key("Icon", 1)
+                .id_string(72),
+            msi::Column::build("IconIndex").nullable().int16(),
+            msi::Column::build("ShowCmd").nullable().int16(),
+            msi::Column::build("WkDir").nullable().id_string(72),
+        ],
+    )?;
+    let language = settings.msi_language();
+    let name = settings.localized_bundle_name(language);
+    let description = settings.localized_short_description(language);
+    let arguments = settings
+        .msi_shortcut_args()
+        .map_or(msi::Value::Null, msi::Value::from);
+    let mut rows = Vec::new();
+    for shortcut in shortcuts {
+        rows.push(vec![
+            msi::Value::from(shortcut.key),
+            msi::Value::from(shortcut.directory_key),
+            msi::Value::from(name),
+            msi::Value::from(shortcut.key),
+            // The File key of the binary executable is its filename.
+            msi::Value::Str(format!("[#{}]", exe_name(settings))),
+            arguments.clone(),
+            msi::Value::from(description),
+            msi::Value::Null,
+            msi::Value::Str(format!("{}.ico", settings.binary_name())),
+            msi::Value::Null,
+            msi::Value::Null,
+            msi::Value::from("INSTALLDIR"),
+        ]);
+    }
+    package.insert_rows(msi::Insert::into("Shortcut").rows(rows))?;
+    Ok(())
+}
+
+// Creates and populates the `RemoveFile` database table for the package, which
+// removes the Start Menu folder of the app on uninstall.
+fn create_remove_file_table(
+    package: &mut Package,
+    shortcuts: &[ShortcutInfo],
+) -> crate::Result<()> {
+    package.create_table(
+        "RemoveFile",
+        vec![
+            msi::Column::build("FileKey").primary_key().id_string(72),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+            msi::Column::build("FileName")
+                .nullable()
+                .category(msi::Category::WildCardFilename)
+                .string(255),
+            msi::Column::build("DirProperty").id_string(72),
+            msi::Column::build("InstallMode").range(1, 3).int16(),
+        ],
+    )?;
+    let mut rows = Vec::new();
+    for shortcut in shortcuts {
+        if shortcut.directory_key == START_MENU_DIR_KEY {
+            rows.push(vec![
+                msi::Value::from("RemoveStartMenuFolder"),
+                msi::Value::from(shortcut.key),
+                msi::Value::Null,
+                msi::Value::from(START_MENU_DIR_KEY),
+                // On uninstall only.
+                msi::Value::Int(2),
+            ]);
+        }
+    }
+    package.insert_rows(msi::Insert::into("RemoveFile").rows(rows))?;
+    Ok(())
+}
+
+// Creates and populates the `ServiceInstall` database table for the package,
+// which registers the binary executable as the `services`.
+fn create_service_install_table(
+    package: &mut Package,
+    services: &[WindowsService],
+) -> crate::Result<()> {
+    package.create_table(
+        "ServiceInstall",
+        vec![
+            msi::Column::build("ServiceInstall")
+                .primary_key()
+                .id_string(72),
+            msi::Column::build("Name").formatted_string(255),
+            msi::Column::build("DisplayName")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("ServiceType").int32(),
+            msi::Column::build("StartType").range(0, 4).int32(),
+            msi::Column::build("ErrorControl").int32(),
+            msi::Column::build("LoadOrderGroup")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("Dependencies")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("StartName")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("Password")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("Arguments")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+            msi::Column::build("Description")
+                .nullable()
+                .formatted_string(255),
+        ],
+    )?;
+    let formatted = |value: &Option<String>| {
+        value.as_deref().map_or(msi::Value::Null, |value| {
+            msi::Value::Str(windows_registry::escape_formatted(value))
+        })
+    };
+    let mut rows = Vec::new();
+    for (index, service) in services.iter().enumerate() {
+        let start_type = match service.start_type {
+            ServiceStartType::Auto => 2,
+            ServiceStartType::Demand => 3,
+            ServiceStartType::Disabled => 4,
+        };
+        rows.push(vec![
+            msi::Value::Str(format!("Service{index}")),
+            msi::Value::Str(windows_registry::escape_formatted(&service.name)),
+            formatted(&service.display_name),
+            msi::Value::Int(SERVICE_TYPE_OWN_PROCESS),
+            msi::Value::Int(start_type),
+            msi::Value::Int(SERVICE_ERROR_CONTROL_NORMAL_VITAL),
+            msi::Value::Null,
+            msi::Value::Null,
+            // Services without an account run as LocalSystem.
+            formatted(&service.account),
+            msi::Value::Null,
+            formatted(&service.arguments),
+            // The binary executable is in the component of INSTALLDIR.
+            msi::Value::from("INSTALLDIR"),
+            formatted(&service.description),
+        ]);
+    }
+    package.insert_rows(msi::Insert::into("ServiceInstall").rows(rows))?;
+    Ok(())
+}
+
+// Creates and populates the `ServiceControl` database table for the package,
+// which starts the `services` once they are installed (unless they are
+// disabled), and stops them before they are upgraded or removed.
+fn create_service_control_table(
+    package: &mut Package,
+    services: &[WindowsService],
+) -> crate::Result<()> {
+    package.create_table(
+        "ServiceControl",
+        vec![
+            msi::Column::build("ServiceControl")
+                .primary_key()
+                .id_string(72),
+            msi::Column::build("Name").formatted_string(255),
+            msi::Column::build("Event").int16(),
+            msi::Column::build("Arguments")
+                .nullable()
+                .formatted_string(255),
+            msi::Column::build("Wait").nullable().int16(),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+        ],
+    )?;
+    let mut rows = Vec::new();
+    for (index, service) in services.iter().enumerate() {
+        let mut event = SERVICE_EVENT_INSTALL_STOP
+            | SERVICE_EVENT_UNINSTALL_STOP
+            | SERVICE_EVENT_UNINSTALL_DELETE;
+        if service.start_type != ServiceStartType::Disabled {
+            event |= SERVICE_EVENT_INSTALL_START;
+        }
+        rows.push(vec![
+            msi::Value::Str(format!("Service{index}")),
+            msi::Value::Str(windows_registry::escape_formatted(&service.name)),
+            msi::Value::Int(event),
+            msi::Value::Null,
+            // Wait for the service to start or stop.
+            msi::Value::Int(1),
+            msi::Value::from("INSTALLDIR"),
+        ]);
+    }
+    package.insert_rows(msi::Insert::into("ServiceControl").rows(rows))?;
+    Ok(())
+}
+
 fn create_install_execute_sequence_table(
     package: &mut Package,
     _cabinets: &[CabinetInfo],
@@ -662,9 +1514,9 @@ fn create_install_execute_sequence_table(
         ],
     )?;
     let mut rows = Vec::new();
-    let actions: [(&str, &str, i32); 24] = [
-        //("LaunchConditions", "", 100), // Requires a LaunchCondition table
-        //("FindRelatedProducts", "", 200), // Requires an Upgrade table
+    let actions: [(&str, &str, i32); 36] = [
+        ("LaunchConditions", "", 100),
+        ("FindRelatedProducts", "", 200),
         //("AppSearch", "", 400), // Requires a Signature table
         //("CCPSearch", "NOT Installed", 500), // Requires a Signature or *Locator table
         //("RMCCPSearch", "NOT Installed", 600), // Requires the CCP_DRIVE property and a DrLocator table
@@ -673,27 +1525,30 @@ fn create_install_execute_sequence_table(
         ("FileCost", "", 900),
         ("CostFinalize", "", 1000),
         ("SetODBCFolders", "", 1100),
-        //("MigrateFeatureStates", "", 1200),
+        ("MigrateFeatureStates", "", 1200),
         ("InstallValidate", "", 1400),
+        // The older product is removed before installing this one, so that
+        // their files and registry values don't need matching.
+        ("RemoveExistingProducts", "", 1401),
         ("InstallInitialize", "", 1500),
         ("AllocateRegistrySpace", "NOT Installed", 1550),
         ("ProcessComponents", "", 1600),
         ("UnpublishComponents", "", 1700),
         ("UnpublishFeatures", "", 1800),
-        //("StopServices", "VersionNT", 1900), // Requires a ServiceControl table
-        //("DeleteServices", "VersionNT", 2000), // Requires a ServiceControl table
+        ("StopServices", "VersionNT", 1900),
+        ("DeleteServices", "VersionNT", 2000),
         ("UnregisterComPlus", "", 2100),
         //("SelfUnregModules", "", 2200), // Requires a SelfReg table
         //("UnregisterTypeLibraries", "", 2300), // Requires a TypeLib table
         //("RemoveODBC", "", 2400), // Requires an ODBC* table
         //("UnregisterFonts", "", 2500), // Requires a Font table
-        //("RemoveRegistryValues", "", 2600), // Requires a Registry table
+        ("RemoveRegistryValues", "", 2600),
         //("UnregisterClassInfo", "", 2700), // Requires a Class table
         //("UnregisterExtensionInfo", "", 2800), // Requires an Extension table
         //("UnregisterProgIdInfo", "", 2900), // Requires ProgId, Extension or Class table
         //("UnregisterMIMEInfo", "", 3000), // Requires a MIME table
         //("RemoveIniValues", "", 3100), // Requires an IniFile table
-        //("RemoveShortcuts", "", 3200), // Requires a Shortcut table
+        ("RemoveShortcuts", "", 3200),
         //("RemoveEnvironmentStrings", "", 3300), // Requires an Environment table
         //("RemoveDuplicateFiles", "", 3400), // Requires a DuplicateFile table
         ("RemoveFiles", "", 3500),
@@ -704,12 +1559,12 @@ fn create_install_execute_sequence_table(
         //("PatchFiles", "", 4090), // Requires a Patch table
         //("DuplicateFiles", "", 4210), // Requires a DuplicateFile table
         //("BindImage", "", 4300), // Requires a BindImage table
-        //("CreateShortcuts", "", 4500), // Requires a Shortcut table
+        ("CreateShortcuts", "", 4500),
         //("RegisterClassInfo", "", 4600), // Requires a Class table
         //("RegisterExtensionInfo", "", 4700), // Requires an Extension table
         //("RegisterProgIdInfo", "", 4800), // Requires a ProgId table
         //("RegisterMIMEInfo", "", 4900), // Requires a MIME table
-        //("WriteRegistryValues", "", 5000), // Requires a Registry table
+        ("WriteRegistryValues", "", 5000),
         //("WriteIniValues", "", 5100), // Requires an IniFile table
         //("WriteEnvironmentStrings", "", 5200), // Requires an Environment table
         //("RegisterFonts", "", 5300), // Requires a Font table
@@ -717,15 +1572,14 @@ fn create_install_execute_sequence_table(
         //("RegisterTypeLibraries", "", 5500), // Requires a TypeLib table
         //("SelfRegModules", "", 5600), // Requires a SelfReg table
         ("RegisterComPlus", "", 5700),
-        //("InstallServices", "VersionNT", 5800), // Requires a ServiceInstall table
-        //("StartServices", "VersionNT", 5900), // Requires a SelfReg ServiceControl
+        ("InstallServices", "VersionNT", 5800),
+        ("StartServices", "VersionNT", 5900),
         ("RegisterUser", "", 6000),
         ("RegisterProduct", "", 6100),
         ("PublishComponents", "", 6200),
         ("PublishFeatures", "", 6300),
         ("PublishProduct", "", 6400),
         ("InstallFinalize", "", 6600),
-        //("RemoveExistingProducts", "", 6700), // Requires an Upgrade table
     ];
     for action in actions {
         rows.push(vec![
@@ -761,18 +1615,18 @@ fn create_install_ui_sequence_table(
         ],
     )?;
     let mut rows = Vec::new();
-    let actions: [(&str, &str, i32); 9] = [
+    let actions: [(&str, &str, i32); 12] = [
         ("FatalErrorDialog", "", -3),
         ("ExitDialog", "", -1),
-        //("LaunchConditions", "", 100), // Requires a LaunchCondition table
-        //("FindRelatedProducts", "", 200), // Requires an Upgrade table
+        ("LaunchConditions", "", 100),
+        ("FindRelatedProducts", "", 200),
         //("AppSearch", "", 400), // Requires a Signature table
         //("CCPSearch", "NOT Installed", 500), // Requires a Signature or *Locator table
         //("RMCCPSearch", "NOT Installed", 600), // Requires the CCP_DRIVE property and a DrLocator table
         ("CostInitialize", "", 800),
         ("FileCost", "", 900),
         ("CostFinalize", "", 1000),
-        //("MigrateFeatureStates", "", 1200),
+        ("MigrateFeatureStates", "", 1200),
         ("WelcomeDialog", "NOT Installed", 1230),
         ("RemoveDialog", "Installed", 1240),
         ("ProgressDialog", "", 1280),
@@ -793,7 +1647,64 @@ fn create_install_ui_sequence_table(
     Ok(())
 }
 
-fn create_dialog_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> crate::Result<()> {
+// Reads the RTF license agreement that the installer shows before installing,
+// if the bundle has a `windows.license` and its dialog isn't turned off.
+fn license_text(settings: &Settings) -> crate::Result<Option<String>> {
+    if !settings.windows_license_dialog() {
+        return Ok(None);
+    }
+    let Some(path) = settings.windows_license() else {
+        return Ok(None);
+    };
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read license agreement {path:?}"))?;
+    Ok(Some(text))
+}
+
+// The names of the Binary table entries holding the installer artwork, which
+// are the texts of the Bitmap controls showing it.
+const BANNER_BITMAP: &str = "BannerBitmap";
+const DIALOG_BITMAP: &str = "DialogBitmap";
+
+// Stores the installer artwork in the Binary table, returning the names of the
+// bitmaps it holds.
+fn create_binary_table(
+    package: &mut Package,
+    settings: &Settings,
+) -> crate::Result<Vec<&'static str>> {
+    package.create_table(
+        "Binary",
+        vec![
+            msi::Column::build("Name").primary_key().id_string(72),
+            msi::Column::build("Data").binary(),
+        ],
+    )?;
+    let images = installer_images(settings)?;
+    let mut bitmaps = Vec::new();
+    for (name, image) in [
+        (BANNER_BITMAP, images.banner),
+        (DIALOG_BITMAP, images.dialog),
+    ] {
+        let Some(image) = image else {
+            continue;
+        };
+        {
+            let mut stream = package.write_stream(&format!("Binary.{name}"))?;
+            io::copy(
+                &mut fs::File::open(&image)
+                    .with_context(|| format!("Failed to open installer image {image:?}"))?,
+                &mut stream,
+            )?;
+        }
+        package.insert_rows(
+            msi::Insert::into("Binary").row(vec![msi::Value::from(name), msi::Value::Binary]),
+        )?;
+        bitmaps.push(name);
+    }
+    Ok(bitmaps)
+}
+
+fn create_dialog_table(package: &mut Package, license: Option<&str>) -> crate::Result<()> {
     package.create_table(
         "Dialog",
         vec![
@@ -845,7 +1756,14 @@ fn create_dialog_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> crat
         ("ExitDialog", 50, 50, 370, 270, 3, "[ProductName] Setup", "ExitFinish", "ExitFinish", "ExitFinish"),
         ("FatalErrorDialog", 50, 50, 370, 270, 3, "[ProductName] Setup", "FatalFinish", "FatalFinish", "FatalFinish"),
     ];
-    for action in actions {
+    #[rustfmt::skip]
+    let license_actions: Vec<DialogTableEntry> = match license {
+        Some(_) => vec![
+            ("LicenseDialog", 50, 50, 370, 270, 3, "[ProductName] Setup", "LicenseText", "LicenseInstall", "LicenseCancel"),
+        ],
+        None => Vec::new(),
+    };
+    for action in actions.into_iter().chain(license_actions) {
         rows.push(vec![
             msi::Value::Str(action.0.to_string()),
             msi::Value::Int(action.1),
@@ -871,7 +1789,11 @@ fn create_dialog_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> crat
     Ok(())
 }
 
-fn create_control_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> crate::Result<()> {
+fn create_control_table(
+    package: &mut Package,
+    license: Option<&str>,
+    bitmaps: &[&str],
+) -> crate::Result<()> {
     package.create_table(
         "Control",
         vec![
@@ -924,19 +1846,31 @@ fn create_control_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> cra
         &'a str,
         &'a str,
     );
+    // With a license agreement, the welcome dialog leads on to it, and the
+    // license dialog is the one that starts the installation.
+    let (welcome_description, welcome_install) = match license {
+        Some(_) => (
+            "{\\DefaultFont}This will install [ProductName] on your computer. Click Next to continue or Cancel to exit the installer.",
+            "Next",
+        ),
+        None => (
+            "{\\DefaultFont}This will install [ProductName] on your computer. Click Install to continue or Cancel to exit the installer.",
+            "Install",
+        ),
+    };
     #[rustfmt::skip]
-    let actions: [ControlTableEntry; 38] = [
-        ("WelcomeDialog", "WelcomeDescription", "Text", 135, 70, 220, 50, 196611, "", "{\\DefaultFont}This will install [ProductName] on your computer. Click Install to continue or Cancel to exit the installer.", "", ""),
+    let actions: [ControlTableEntry; 43] = [
+        ("WelcomeDialog", "WelcomeDescription", "Text", 135, 70, 220, 50, 196611, "", welcome_description, "", ""),
         ("WelcomeDialog", "WelcomeTitle", "Text", 135, 20, 220, 60, 196611, "", "{\\TitleFont}Welcome to the [ProductName] setup wizard", "", ""),
         ("WelcomeDialog", "WelcomeCancel", "PushButton", 304, 243, 56, 17, 3, "", "Cancel", "", ""),
-        //("WelcomeDialog", "WelcomeBitmap", "Bitmap", 0, 0, 370, 234, 1, "", "[DialogBitmap]", "WelcomeBack", ""),
+        ("WelcomeDialog", "WelcomeBitmap", "Bitmap", 0, 0, 370, 234, 1, "", DIALOG_BITMAP, "WelcomeBack", ""),
         ("WelcomeDialog", "WelcomeBack", "PushButton", 180, 243, 56, 17, 1, "", "Back", "WelcomeInstall", ""),
         ("WelcomeDialog", "WelcomeBottomLine", "Line", 0, 234, 374, 0, 1, "", "", "", ""),
-        ("WelcomeDialog", "WelcomeInstall", "PushButton", 236, 243, 56, 17, 3, "", "Install", "WelcomeCancel", ""),
+        ("WelcomeDialog", "WelcomeInstall", "PushButton", 236, 243, 56, 17, 3, "", welcome_install, "WelcomeCancel", ""),
         ("RemoveDialog", "RemoveDescription", "Text", 135, 70, 220, 50, 196611, "", "This will remove [ProductName] from your computer. Click Remove to continue or Cancel to exit the uninstaller.", "", ""),
         ("RemoveDialog", "RemoveTitle", "Text", 135, 20, 220, 60, 196611, "", "{\\TitleFont}Uninstall [ProductName]", "", ""),
         ("RemoveDialog", "RemoveCancel", "PushButton", 304, 243, 56, 17, 3, "", "Cancel", "", ""),
-        //("RemoveDialog", "RemoveBitmap", "Bitmap", 0, 0, 370, 234, 1, "", "[DialogBitmap]", "RemoveBack", ""),
+        ("RemoveDialog", "RemoveBitmap", "Bitmap", 0, 0, 370, 234, 1, "", DIALOG_BITMAP, "RemoveBack", ""),
         ("RemoveDialog", "RemoveBack", "PushButton", 180, 243, 56, 17, 1, "", "Back", "RemoveRemove", ""),
         ("RemoveDialog", "RemoveBottomLine", "Line", 0, 234, 374, 0, 1, "", "", "", ""),
         ("RemoveDialog", "RemoveRemove", "PushButton", 236, 243, 56, 17, 3, "", "Remove", "RemoveCancel", ""),
@@ -945,7 +1879,7 @@ fn create_control_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> cra
         ("CancelDialog", "CancelText", "Text", 48, 15, 194, 30, 3, "", "Do you want to abort [ProductName] [Text_action]?", "", ""),
         ("CancelDialog", "CancelYes", "PushButton", 72, 57, 56, 17, 3, "", "Abort", "CancelNo", ""),
         ("ProgressDialog", "ProgressTitle", "Text", 20, 15, 200, 15, 196611, "", "{\\BoldFont}[Text_Doing] [ProductName]", "", ""),
-        //("ProgressDialog", "ProgressBannerBitmap", "Bitmap", 0, 0, 374, 44, 1, "", "[BannerBitmap]", "ProgressBack", ""),
+        ("ProgressDialog", "ProgressBannerBitmap", "Bitmap", 0, 0, 374, 44, 1, "", BANNER_BITMAP, "ProgressBack", ""),
         ("ProgressDialog", "ProgressCancel", "PushButton", 304, 243, 56, 17, 3, "", "Cancel", "", ""),
         ("ProgressDialog", "ProgressText", "Text", 35, 65, 300, 25, 3, "", "Please wait while [ProductName] is [Text_done]. This may take several minutes.", "", ""),
         ("ProgressDialog", "ProgressActionText", "Text", 70, 105, 265, 15, 3, "", "", "", ""),
@@ -958,19 +1892,40 @@ fn create_control_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> cra
         ("ExitDialog", "ExitDescription", "Text", 135, 70, 220, 20, 196611, "", "Click the Finish button to exit the [Text_agent].", "", ""),
         ("ExitDialog", "ExitTitle", "Text", 135, 20, 220, 60, 196611, "", "{\\TitleFont}[ProductName] [Text_action] complete", "", ""),
         ("ExitDialog", "ExitCancel", "PushButton", 304, 243, 56, 17, 1, "", "Cancel", "", ""),
-        //("ExitDialog", "ExitBitmap", "Bitmap", 0, 0, 370, 234, 1, "", "[DialogBitmap]", "ExitBack", ""),
+        ("ExitDialog", "ExitBitmap", "Bitmap", 0, 0, 370, 234, 1, "", DIALOG_BITMAP, "ExitBack", ""),
         ("ExitDialog", "ExitBack", "PushButton", 180, 243, 56, 17, 1, "", "Back", "ExitFinish", ""),
         ("ExitDialog", "ExitBottomLine", "Line", 0, 234, 374, 0, 1, "", "", "", ""),
         ("ExitDialog", "ExitFinish", "PushButton", 236, 243, 56, 17, 3, "", "Finish", "ExitCancel", ""),
         ("FatalErrorDialog", "FatalTitle", "Text", 135, 20, 220, 60, 196611, "", "{\\TitleFont}[ProductName] [Text_agent] ended prematurely", "", ""),
         ("FatalErrorDialog", "FatalCancel", "PushButton", 304, 243, 56, 17, 1, "", "Cancel", "", ""),
-        //("FatalErrorDialog", "FatalBitmap", "Bitmap", 0, 0, 370, 234, 1, "", "[DialogBitmap]", "FatalBack", ""),
+        ("FatalErrorDialog", "FatalBitmap", "Bitmap", 0, 0, 370, 234, 1, "", DIALOG_BITMAP, "FatalBack", ""),
         ("FatalErrorDialog", "FatalBack", "PushButton", 180, 243, 56, 17, 1, "", "Back", "FatalFinish", ""),
         ("FatalErrorDialog", "FatalBottomLine", "Line", 0, 234, 374, 0, 1, "", "", "", ""),
         ("FatalErrorDialog", "FatalFinish", "PushButton", 236, 243, 56, 17, 3, "", "Finish", "FatalCancel", ""),
         ("FatalErrorDialog", "FatalDescription1", "Text", 135, 70, 220, 40, 196611, "", "[ProductName] [Text_action] ended because of an error. The program has not been installed. This installer can be run again at a later time.", "", ""),
         ("FatalErrorDialog", "FatalDescription2", "Text", 135, 115, 220, 20, 196611, "", "Click the Finish button to exit the [Text_agent].", "", ""),
     ];
+    #[rustfmt::skip]
+    let license_actions: Vec<ControlTableEntry> = match license {
+        Some(license) => vec![
+            ("LicenseDialog", "LicenseBannerBitmap", "Bitmap", 0, 0, 374, 44, 1, "", BANNER_BITMAP, "LicenseText", ""),
+            ("LicenseDialog", "LicenseTitle", "Text", 20, 15, 330, 15, 196611, "", "{\\BoldFont}End-User License Agreement", "", ""),
+            ("LicenseDialog", "LicenseDescription", "Text", 25, 28, 320, 15, 196611, "", "Please read the following license agreement carefully.", "", ""),
+            ("LicenseDialog", "LicenseBannerLine", "Line", 0, 44, 374, 0, 1, "", "", "", ""),
+            ("LicenseDialog", "LicenseText", "ScrollableText", 20, 55, 330, 140, 7, "", license, "LicenseAccept", ""),
+            ("LicenseDialog", "LicenseAccept", "CheckBox", 20, 205, 330, 18, 3, "LicenseAccepted", "I accept the terms in the License Agreement", "LicenseBack", ""),
+            ("LicenseDialog", "LicenseBottomLine", "Line", 0, 234, 374, 0, 1, "", "", "", ""),
+            ("LicenseDialog", "LicenseBack", "PushButton", 180, 243, 56, 17, 3, "", "Back", "LicenseInstall", ""),
+            ("LicenseDialog", "LicenseInstall", "PushButton", 236, 243, 56, 17, 3, "", "Install", "LicenseCancel", ""),
+            ("LicenseDialog", "LicenseCancel", "PushButton", 304, 243, 56, 17, 3, "", "Cancel", "LicenseText", ""),
+        ],
+        None => Vec::new(),
+    };
+    // The Bitmap controls only show the artwork that the bundle has.
+    let actions = actions
+        .into_iter()
+        .chain(license_actions)
+        .filter(|action| action.2 != "Bitmap" || bitmaps.contains(&action.9));
     for action in actions {
         rows.push(vec![
             msi::Value::Str(action.0.to_string()),
@@ -1007,10 +1962,7 @@ fn create_control_table(package: &mut Package, _cabinets: &[CabinetInfo]) -> cra
     Ok(())
 }
 
-fn create_control_event_table(
-    package: &mut Package,
-    _cabinets: &[CabinetInfo],
-) -> crate::Result<()> {
+fn create_control_event_table(package: &mut Package, license: Option<&str>) -> crate::Result<()> {
     package.create_table(
         "ControlEvent",
         vec![
@@ -1036,6 +1988,10 @@ fn create_control_event_table(
         ],
     )?;
     let mut rows = Vec::new();
+    let welcome_install = match license {
+        Some(_) => ("NewDialog", "LicenseDialog"),
+        None => ("EndDialog", "Return"),
+    };
     #[rustfmt::skip]
     let actions: [(&str, &str, &str, &str, &str, i32); 20] = [
         ("WelcomeDialog", "WelcomeCancel", "SpawnDialog", "CancelDialog", "1", 0),
@@ -1044,7 +2000,7 @@ fn create_control_event_table(
         ("WelcomeDialog", "WelcomeInstall", "[Text_agent]", "installer", "1", 3),
         ("WelcomeDialog", "WelcomeInstall", "[Text_Doing]", "Installing", "1", 4),
         ("WelcomeDialog", "WelcomeInstall", "[Text_done]", "installed", "1", 5),
-        ("WelcomeDialog", "WelcomeInstall", "EndDialog", "Return", "1", 6),
+        ("WelcomeDialog", "WelcomeInstall", welcome_install.0, welcome_install.1, "1", 6),
         ("RemoveDialog", "RemoveCancel", "[Text_action]", "removal", "1", 7),
         ("RemoveDialog", "RemoveCancel", "SpawnDialog", "CancelDialog", "1", 8),
         ("RemoveDialog", "RemoveRemove", "[Mode]", "Remove", "1", 9),
@@ -1059,7 +2015,16 @@ fn create_control_event_table(
         ("ExitDialog", "ExitFinish", "EndDialog", "Return", "1", 18),
         ("FatalErrorDialog", "FatalFinish", "EndDialog", "Exit", "1", 19),
     ];
-    for action in actions {
+    #[rustfmt::skip]
+    let license_actions: Vec<(&str, &str, &str, &str, &str, i32)> = match license {
+        Some(_) => vec![
+            ("LicenseDialog", "LicenseBack", "NewDialog", "WelcomeDialog", "1", 20),
+            ("LicenseDialog", "LicenseCancel", "SpawnDialog", "CancelDialog", "1", 21),
+            ("LicenseDialog", "LicenseInstall", "EndDialog", "Return", "LicenseAccepted = \"1\"", 22),
+        ],
+        None => Vec::new(),
+    };
+    for action in actions.into_iter().chain(license_actions) {
         rows.push(vec![
             msi::Value::Str(action.0.to_string()),
             msi::Value::Str(action.1.to_string()),
@@ -1073,6 +2038,71 @@ fn create_control_event_table(
     Ok(())
 }
 
+fn create_control_condition_table(
+    package: &mut Package,
+    license: Option<&str>,
+) -> crate::Result<()> {
+    package.create_table(
+        "ControlCondition",
+        vec![
+            msi::Column::build("Dialog_").primary_key().id_string(72),
+            msi::Column::build("Control_")
+                .primary_key()
+                .category(msi::Category::Identifier)
+                .string(50),
+            msi::Column::build("Action")
+                .primary_key()
+                .enum_values(&["Default", "Disable", "Enable", "Hide", "Show"])
+                .string(50),
+            msi::Column::build("Condition")
+                .primary_key()
+                .category(msi::Category::Condition)
+                .string(255),
+        ],
+    )?;
+    if license.is_none() {
+        return Ok(());
+    }
+    let mut rows = Vec::new();
+    // The license agreement has to be accepted before installing.
+    #[rustfmt::skip]
+    let actions: [(&str, &str, &str, &str); 2] = [
+        ("LicenseDialog", "LicenseInstall", "Disable", "LicenseAccepted <> \"1\""),
+        ("LicenseDialog", "LicenseInstall", "Enable", "LicenseAccepted = \"1\""),
+    ];
+    for action in actions {
+        rows.push(vec![
+            msi::Value::Str(action.0.to_string()),
+            msi::Value::Str(action.1.to_string()),
+            msi::Value::Str(action.2.to_string()),
+            msi::Value::Str(action.3.to_string()),
+        ]);
+    }
+    package.insert_rows(msi::Insert::into("ControlCondition").rows(rows))?;
+    Ok(())
+}
+
+fn create_check_box_table(package: &mut Package, license: Option<&str>) -> crate::Result<()> {
+    package.create_table(
+        "CheckBox",
+        vec![
+            msi::Column::build("Property").primary_key().id_string(72),
+            msi::Column::build("Value")
+                .nullable()
+                .category(msi::Category::Formatted)
+                .string(64),
+        ],
+    )?;
+    if license.is_none() {
+        return Ok(());
+    }
+    package.insert_rows(msi::Insert::into("CheckBox").row(vec![
+        msi::Value::Str("LicenseAccepted".to_string()),
+        msi::Value::Str("1".to_string()),
+    ]))?;
+    Ok(())
+}
+
 fn create_event_mapping_table(
     package: &mut Package,
     _cabinets: &[CabinetInfo],
@@ -1162,3 +2192,500 @@ fn create_app_icon<W: Write>(writer: &mut W, settings: &Settings) -> crate::Resu
     // TODO: Convert from other formats.
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn upgrade_table() {
+        let settings = Settings::for_test(
+            "name = \"My App [1]\"\n\
+             msi_upgrade_code = \"{6F8F2C2B-3B0B-4D5C-9C55-3E2E1C5E0F31}\"\n",
+        );
+        let upgrade_code = upgrade_code(&settings).unwrap();
+        assert_eq!(
+            upgrade_code.to_string(),
+            "6f8f2c2b-3b0b-4d5c-9c55-3e2e1c5e0f31"
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_upgrade_table(&mut package, upgrade_code, &settings).unwrap();
+        create_launch_condition_table(&mut package, &settings).unwrap();
+
+        let rows: Vec<Vec<msi::Value>> = package
+            .select_rows(msi::Select::table("Upgrade"))
+            .unwrap()
+            .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+            .collect();
+        let code = msi::Value::from("{6F8F2C2B-3B0B-4D5C-9C55-3E2E1C5E0F31}");
+        let version = msi::Value::from("1.0.0.0");
+        let null = msi::Value::Null;
+        assert_eq!(
+            rows,
+            [
+                [
+                    code.clone(),
+                    null.clone(),
+                    version.clone(),
+                    null.clone(),
+                    msi::Value::Int(0x201),
+                    null.clone(),
+                    msi::Value::from("UPGRADEFOUND"),
+                ],
+                [
+                    code,
+                    version,
+                    null.clone(),
+                    null.clone(),
+                    msi::Value::Int(0x2),
+                    null,
+                    msi::Value::from("NEWERVERSIONFOUND"),
+                ],
+            ]
+        );
+        let rows: Vec<(msi::Value, msi::Value)> = package
+            .select_rows(msi::Select::table("LaunchCondition"))
+            .unwrap()
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            [(
+                msi::Value::from("NOT NEWERVERSIONFOUND"),
+                msi::Value::from("A newer version of My App [\\[]1[\\]] is already installed."),
+            )]
+        );
+    }
+
+    #[test]
+    fn shortcut_tables() {
+        let settings = Settings::for_test("");
+        let keys: Vec<&str> = collect_shortcut_info(&settings)
+            .iter()
+            .map(|shortcut| shortcut.key)
+            .collect();
+        assert_eq!(keys, ["StartMenuShortcut"]);
+
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             short_description = \"An app\"\n\
+             msi_start_menu_shortcut = false\n\
+             msi_desktop_shortcut = true\n\
+             msi_shortcut_args = \"--open [file]\"\n",
+        );
+        let shortcuts = collect_shortcut_info(&settings);
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_directory_table(&mut package, &settings, &[], &shortcuts).unwrap();
+        create_shortcut_table(&mut package, &settings, &shortcuts).unwrap();
+        create_remove_file_table(&mut package, &shortcuts).unwrap();
+
+        let directories: Vec<msi::Value> = package
+            .select_rows(msi::Select::table("Directory"))
+            .unwrap()
+            .map(|row| row[0].clone())
+            .collect();
+        assert!(directories.contains(&msi::Value::from("DesktopFolder")));
+        assert!(!directories.contains(&msi::Value::from("ProgramMenuFolder")));
+        let rows: Vec<Vec<msi::Value>> = package
+            .select_rows(msi::Select::table("Shortcut"))
+            .unwrap()
+            .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+            .collect();
+        let null = msi::Value::Null;
+        assert_eq!(
+            rows,
+            [[
+                msi::Value::from("DesktopShortcut"),
+                msi::Value::from("DesktopFolder"),
+                msi::Value::from("My App"),
+                msi::Value::from("DesktopShortcut"),
+                msi::Value::from("[#example]"),
+                msi::Value::from("--open [file]"),
+                msi::Value::from("An app"),
+                null.clone(),
+                msi::Value::from("example.ico"),
+                null.clone(),
+                null,
+                msi::Value::from("INSTALLDIR"),
+            ]]
+        );
+        // Only the Start Menu folder of the app is removed.
+        let mut rows = package
+            .select_rows(msi::Select::table("RemoveFile"))
+            .unwrap();
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn install_scope_tables() {
+        let settings = Settings::for_test("windows = { install_scope = \"perUser\" }");
+        let directories = collect_directory_info(&settings, &mut []).unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_directory_table(&mut package, &settings, &directories, &[]).unwrap();
+        create_property_table(&mut package, Uuid::nil(), Uuid::nil(), 0, &settings).unwrap();
+
+        let rows: Vec<Vec<msi::Value>> = package
+            .select_rows(msi::Select::table("Directory"))
+            .unwrap()
+            .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                [
+                    msi::Value::from("INSTALLDIR"),
+                    msi::Value::from("LocalProgramsFolder"),
+                    msi::Value::from("example"),
+                ],
+                [
+                    msi::Value::from("LocalAppDataFolder"),
+                    msi::Value::from("TARGETDIR"),
+                    msi::Value::from("."),
+                ],
+                [
+                    msi::Value::from("LocalProgramsFolder"),
+                    msi::Value::from("LocalAppDataFolder"),
+                    msi::Value::from("Programs"),
+                ],
+                [
+                    msi::Value::from("TARGETDIR"),
+                    msi::Value::Null,
+                    msi::Value::from("SourceDir"),
+                ],
+            ]
+        );
+        let properties = |package: &mut Package| -> Vec<msi::Value> {
+            package
+                .select_rows(msi::Select::table("Property"))
+                .unwrap()
+                .map(|row| row[0].clone())
+                .collect()
+        };
+        assert!(!properties(&mut package).contains(&msi::Value::from("ALLUSERS")));
+
+        let settings = Settings::for_test("windows = { install_scope = \"dual\" }");
+        let mut package = new_empty_package(&tmp.path().join("dual.msi")).unwrap();
+        create_property_table(&mut package, Uuid::nil(), Uuid::nil(), 0, &settings).unwrap();
+        assert!(properties(&mut package).contains(&msi::Value::from("MSIINSTALLPERUSER")));
+        assert_ne!(install_root_key(&settings), LOCAL_PROGRAMS_DIR_KEY);
+    }
+
+    #[test]
+    fn service_tables() {
+        let settings = Settings::for_test(
+            r#"
+            [[windows.services]]
+            name = "example-svc"
+            display_name = "Example [Service]"
+            account = "NT AUTHORITY\\LocalService"
+            arguments = "--service"
+
+            [[windows.services]]
+            name = "example-helper"
+            start_type = "disabled"
+            "#,
+        );
+        let services = windows_services(&settings).unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_service_install_table(&mut package, services).unwrap();
+        create_service_control_table(&mut package, services).unwrap();
+
+        let rows: Vec<Vec<msi::Value>> = package
+            .select_rows(msi::Select::table("ServiceInstall"))
+            .unwrap()
+            .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+            .collect();
+        let null = msi::Value::Null;
+        assert_eq!(
+            rows[0],
+            [
+                msi::Value::from("Service0"),
+                msi::Value::from("example-svc"),
+                msi::Value::from("Example [\\[]Service[\\]]"),
+                msi::Value::Int(0x10),
+                msi::Value::Int(2),
+                msi::Value::Int(0x8001),
+                null.clone(),
+                null.clone(),
+                msi::Value::from("NT AUTHORITY\\LocalService"),
+                null.clone(),
+                msi::Value::from("--service"),
+                msi::Value::from("INSTALLDIR"),
+                null,
+            ]
+        );
+        assert_eq!(rows[1][4], msi::Value::Int(4));
+        let events: Vec<msi::Value> = package
+            .select_rows(msi::Select::table("ServiceControl"))
+            .unwrap()
+            .map(|row| row[2].clone())
+            .collect();
+        // Disabled services are not started.
+        assert_eq!(events, [msi::Value::Int(0xa3), msi::Value::Int(0xa2)]);
+
+        let settings = Settings::for_test(
+            "windows = { install_scope = \"perUser\", services = [{ name = \"svc\" }] }",
+        );
+        assert!(windows_services(&settings).is_err());
+    }
+
+    #[test]
+    fn authored_registry_tables() {
+        let settings = Settings::for_test(
+            r##"
+            [[windows.registry]]
+            root = "HKLM"
+            key = "Software\\Example"
+            name = "InstallPath"
+            value = "[INSTALLDIR]data [1]"
+
+            [[windows.registry]]
+            root = "HKCU"
+            key = "Software\\Example"
+            value = 42
+
+            [[windows.registry]]
+            root = "HKMU"
+            key = "Software\\Example"
+            name = "Color"
+            value = "#fff"
+            "##,
+        );
+        let registry = settings.windows_registry();
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_component_table(&mut package, &settings, Uuid::nil(), &[], &[]).unwrap();
+        create_registry_table(&mut package, &settings, &[], registry).unwrap();
+
+        let rows: Vec<Vec<msi::Value>> = package
+            .select_rows(msi::Select::table("Registry"))
+            .unwrap()
+            .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+            .collect();
+        let key = msi::Value::from("Software\\Example");
+        assert_eq!(
+            rows,
+            [
+                [
+                    msi::Value::from("RegistryEntry0"),
+                    msi::Value::Int(2),
+                    key.clone(),
+                    msi::Value::from("InstallPath"),
+                    msi::Value::from("[INSTALLDIR]data [\\[]1[\\]]"),
+                    msi::Value::from("RegistryEntry0"),
+                ],
+                [
+                    msi::Value::from("RegistryEntry1"),
+                    msi::Value::Int(1),
+                    key.clone(),
+                    msi::Value::Null,
+                    msi::Value::from("#42"),
+                    msi::Value::from("RegistryEntry1"),
+                ],
+                [
+                    msi::Value::from("RegistryEntry2"),
+                    msi::Value::Int(-1),
+                    key,
+                    msi::Value::from("Color"),
+                    msi::Value::from("##fff"),
+                    msi::Value::from("RegistryEntry2"),
+                ],
+            ]
+        );
+        let key_paths: Vec<msi::Value> = package
+            .select_rows(msi::Select::table("Component"))
+            .unwrap()
+            .map(|row| row[5].clone())
+            .collect();
+        assert_eq!(
+            key_paths,
+            [
+                msi::Value::from("RegistryEntry0"),
+                msi::Value::from("RegistryEntry1"),
+                msi::Value::from("RegistryEntry2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn arp_property_rows() {
+        let settings = Settings::for_test(
+            "msi_publisher = \"Example Corp\"\n\
+             msi_help_url = \"https://example.com/help\"\n\
+             msi_no_modify = true\n",
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_property_table(&mut package, Uuid::nil(), Uuid::nil(), 2049, &settings).unwrap();
+
+        let properties: HashMap<String, msi::Value> = package
+            .select_rows(msi::Select::table("Property"))
+            .unwrap()
+            .map(|row| (row[0].as_str().unwrap().to_string(), row[1].clone()))
+            .collect();
+        let property = |name: &str| properties.get(name).cloned();
+        assert_eq!(
+            property("Manufacturer"),
+            Some(msi::Value::from("Example Corp"))
+        );
+        assert_eq!(
+            property("ARPPRODUCTICON"),
+            Some(msi::Value::from("example.ico"))
+        );
+        assert_eq!(
+            property("ARPHELPLINK"),
+            Some(msi::Value::from("https://example.com/help"))
+        );
+        assert_eq!(property("ARPSIZE"), Some(msi::Value::from("3")));
+        assert_eq!(property("ARPNOMODIFY"), Some(msi::Value::from("1")));
+        assert_eq!(property("ARPNOREPAIR"), None);
+        assert_eq!(property("ARPURLINFOABOUT"), None);
+    }
+
+    #[test]
+    fn license_dialog_tables() {
+        let rows = |package: &mut Package, table: &str| -> Vec<Vec<msi::Value>> {
+            package
+                .select_rows(msi::Select::table(table))
+                .unwrap()
+                .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+                .collect()
+        };
+        let welcome_install_events = |rows: &[Vec<msi::Value>]| -> Vec<msi::Value> {
+            rows.iter()
+                .filter(|row| row[1] == msi::Value::from("WelcomeInstall"))
+                .map(|row| row[2].clone())
+                .collect()
+        };
+        let license = r"{\rtf1\ansi Example [EULA]\par}";
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        create_dialog_table(&mut package, Some(license)).unwrap();
+        create_control_table(&mut package, Some(license), &[]).unwrap();
+        create_control_event_table(&mut package, Some(license)).unwrap();
+        create_control_condition_table(&mut package, Some(license)).unwrap();
+        create_check_box_table(&mut package, Some(license)).unwrap();
+
+        let dialogs = rows(&mut package, "Dialog");
+        assert!(
+            dialogs
+                .iter()
+                .any(|row| row[0] == msi::Value::from("LicenseDialog"))
+        );
+        let controls = rows(&mut package, "Control");
+        let text = controls
+            .iter()
+            .find(|row| row[1] == msi::Value::from("LicenseText"))
+            .unwrap();
+        assert_eq!(text[2], msi::Value::from("ScrollableText"));
+        assert_eq!(text[9], msi::Value::from(license));
+        let events = rows(&mut package, "ControlEvent");
+        assert!(welcome_install_events(&events).contains(&msi::Value::from("NewDialog")));
+        assert!(events.contains(&vec![
+            msi::Value::from("LicenseDialog"),
+            msi::Value::from("LicenseInstall"),
+            msi::Value::from("EndDialog"),
+            msi::Value::from("Return"),
+            msi::Value::from("LicenseAccepted = \"1\""),
+            msi::Value::Int(22),
+        ]));
+        assert_eq!(rows(&mut package, "ControlCondition").len(), 2);
+        assert_eq!(
+            rows(&mut package, "CheckBox"),
+            [vec![
+                msi::Value::from("LicenseAccepted"),
+                msi::Value::from("1")
+            ]]
+        );
+
+        let mut package = new_empty_package(&tmp.path().join("plain.msi")).unwrap();
+        create_dialog_table(&mut package, None).unwrap();
+        create_control_event_table(&mut package, None).unwrap();
+        create_control_condition_table(&mut package, None).unwrap();
+        assert_eq!(rows(&mut package, "Dialog").len(), 6);
+        let events = rows(&mut package, "ControlEvent");
+        assert!(welcome_install_events(&events).contains(&msi::Value::from("EndDialog")));
+        assert!(rows(&mut package, "ControlCondition").is_empty());
+
+        let settings = Settings::for_test(
+            r#"
+            [windows]
+            license = "EULA.rtf"
+            license_dialog = false
+            "#,
+        );
+        assert!(license_text(&settings).unwrap().is_none());
+    }
+
+    #[test]
+    fn installer_artwork() {
+        let tmp = tempfile::tempdir().unwrap();
+        let banner = tmp.path().join("banner.bmp");
+        fs::write(&banner, b"BM banner").unwrap();
+        let settings =
+            Settings::for_test(&format!("[windows]\nbanner_image = '{}'", banner.display()));
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        let bitmaps = create_binary_table(&mut package, &settings).unwrap();
+        assert_eq!(bitmaps, [BANNER_BITMAP]);
+        create_control_table(&mut package, None, &bitmaps).unwrap();
+
+        let binaries: Vec<msi::Value> = package
+            .select_rows(msi::Select::table("Binary"))
+            .unwrap()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(binaries, [msi::Value::from(BANNER_BITMAP)]);
+        let mut data = Vec::new();
+        package
+            .read_stream("Binary.BannerBitmap")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"BM banner");
+        let bitmap_controls: Vec<msi::Value> = package
+            .select_rows(msi::Select::table("Control"))
+            .unwrap()
+            .filter(|row| row[2] == msi::Value::from("Bitmap"))
+            .map(|row| row[1].clone())
+            .collect();
+        assert_eq!(bitmap_controls, [msi::Value::from("ProgressBannerBitmap")]);
+
+        let settings = Settings::for_test("[windows]\ndialog_image = \"dialog.png\"");
+        assert!(installer_images(&settings).is_err());
+    }
+
+    #[test]
+    fn arm64_platform() {
+        let platform = Platform::for_arch("aarch64");
+        assert_eq!(platform.template, "Arm64");
+        assert_eq!(platform.schema, 500);
+        assert!(platform.is_64bit);
+        assert!(!Platform::for_arch("x86").is_64bit);
+
+        let settings = Settings::for_test("");
+        let tmp = tempfile::tempdir().unwrap();
+        let mut package = new_empty_package(&tmp.path().join("test.msi")).unwrap();
+        set_summary_info(&mut package, Uuid::nil(), SystemTime::UNIX_EPOCH, &settings);
+        let platform = Platform::for_arch(settings.binary_arch());
+        assert_eq!(package.summary_info().arch(), Some(platform.template));
+    }
+
+    #[test]
+    fn product_codes() {
+        let settings = Settings::for_test("identifier = \"com.example.app\"");
+        let newer = Settings::for_test(
+            "identifier = \"com.example.app\"\n\
+             version = \"1.1.0\"\n",
+        );
+        assert_ne!(product_code(&settings), product_code(&newer));
+        assert_eq!(
+            upgrade_code(&settings).unwrap(),
+            upgrade_code(&newer).unwrap()
+        );
+    }
+}
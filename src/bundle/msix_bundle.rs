@@ -0,0 +1,264 @@
+// An MSIX package is a zip-like archive built by `makeappx` from a directory
+// containing the app files and an `AppxManifest.xml`:
+//
+// bundle/msix/foobar_1.2.3_x86_64/
+//     AppxManifest.xml        # The generated package manifest
+//     foobar.exe              # Binary executable file
+//     Assets/                 # Logos generated from the bundle icon
+//         Square150x150Logo.png
+//         Square44x44Logo.png
+//         StoreLogo.png
+//     Resources/...           # Other resource files
+//
+// The package is written to `bundle/msix/foobar_1.2.3_x86_64.msix`.  It still
+// has to be signed (e.g. with `signtool`) before it can be installed.
+
+use super::common;
+use crate::Settings;
+use anyhow::Context;
+use image::GenericImageView;
+use image::imageops::FilterType::Lanczos3;
+use quick_xml::escape::escape;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The logos required by the manifest, with their sizes in pixels.
+const LOGOS: &[(&str, u32)] = &[
+    ("Square150x150Logo.png", 150),
+    ("Square44x44Logo.png", 44),
+    ("StoreLogo.png", 50),
+];
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    if settings.bundle_identifier().is_empty() {
+        anyhow::bail!("MSIX packages require a bundle `identifier`");
+    }
+    let Some(publisher) = settings.msix_publisher() else {
+        anyhow::bail!(
+            "MSIX packages require the `msix_publisher` setting (the subject of the signing certificate)"
+        );
+    };
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    let package_name = format!("{package_base_name}.msix");
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/msix");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    // Stage the package contents.
+    let exe_name = settings.binary_path().file_name().unwrap();
+    common::copy_file(settings.binary_path(), &package_dir.join(exe_name))
+        .with_context(|| "Failed to copy binary file")?;
+    common::copy_external_binaries(settings, &package_dir)?;
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let dest = package_dir.join("Resources").join(&resource.dest);
+        common::copy_resource(&resource, &dest)?;
+    }
+    if let Some(build_info) = settings.build_info() {
+        build_info
+            .write_json(&package_dir.join("Resources"))
+            .with_context(|| "Failed to write build info")?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        sbom.write_json(&package_dir.join("Resources"))
+            .with_context(|| "Failed to write SBOM")?;
+    }
+    create_logos(settings, &package_dir.join("Assets"))
+        .with_context(|| "Failed to create MSIX logos")?;
+    let manifest = generate_manifest(settings, publisher, &exe_name.to_string_lossy());
+    let manifest_path = package_dir.join("AppxManifest.xml");
+    fs::write(&manifest_path, manifest)
+        .with_context(|| format!("Failed to write {manifest_path:?}"))?;
+
+    common::run_tool(
+        Command::new("makeappx")
+            .arg("pack")
+            .arg("/o")
+            .arg("/d")
+            .arg(&package_dir)
+            .arg("/p")
+            .arg(&package_path),
+        "makeappx",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Generates the logos required by the manifest from the largest PNG icon.
+fn create_logos(settings: &Settings, assets_dir: &Path) -> crate::Result<()> {
+    let mut largest: Option<image::DynamicImage> = None;
+    for icon_path in settings.icon_files() {
+        let icon_path = icon_path?;
+        if icon_path.extension() != Some(OsStr::new("png")) {
+            continue;
+        }
+        let icon = image::open(&icon_path)?;
+        if largest
+            .as_ref()
+            .is_none_or(|largest| icon.width() > largest.width())
+        {
+            largest = Some(icon);
+        }
+    }
+    let Some(icon) = largest else {
+        anyhow::bail!("MSIX packages require at least one PNG `icon`");
+    };
+    for &(name, size) in LOGOS {
+        let logo = if icon.dimensions() == (size, size) {
+            icon.clone()
+        } else {
+            icon.resize_exact(size, size, Lanczos3)
+        };
+        let mut file = common::create_file(&assets_dir.join(name))?;
+        logo.write_to(&mut file, image::ImageFormat::Png)?;
+    }
+    Ok(())
+}
+
+/// Converts a version string to the four-part numeric version MSIX requires,
+/// dropping any pre-release or build metadata (e.g. `1.2.3-beta` becomes
+/// `1.2.3.0`).
+fn msix_version(version: &str) -> String {
+    let release = version.split(['-', '+']).next().unwrap_or_default();
+    let mut parts: Vec<u16> = release
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .take(4)
+        .collect();
+    parts.resize(4, 0);
+    parts
+        .iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Returns the MSIX processor architecture name for a Rust target arch.
+pub(crate) fn msix_arch(binary_arch: &str) -> &str {
+    match binary_arch {
+        "x86_64" => "x64",
+        "x86" | "i386" | "i586" | "i686" => "x86",
+        "aarch64" => "arm64",
+        "arm" => "arm",
+        _ => "neutral",
+    }
+}
+
+/// Generates the contents of `AppxManifest.xml`.
+fn generate_manifest(settings: &Settings, publisher: &str, exe_name: &str) -> String {
+    let display_name = escape(settings.bundle_name());
+    let publisher_display_name = settings
+        .msix_publisher_display_name()
+        .map(str::to_string)
+        .or_else(|| settings.authors_comma_separated())
+        .unwrap_or_else(|| settings.bundle_name().to_string());
+    let mut description = settings.short_description().trim();
+    if description.is_empty() {
+        description = settings.bundle_name();
+    }
+    let description = escape(description);
+
+    let mut xml = String::new();
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"utf-8\"?>").unwrap();
+    writeln!(
+        xml,
+        "<Package xmlns=\"http://schemas.microsoft.com/appx/manifest/foundation/windows10\"\n  \
+         xmlns:uap=\"http://schemas.microsoft.com/appx/manifest/uap/windows10\"\n  \
+         xmlns:rescap=\"http://schemas.microsoft.com/appx/manifest/foundation/windows10/restrictedcapabilities\"\n  \
+         IgnorableNamespaces=\"uap rescap\">"
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "  <Identity Name=\"{}\" Publisher=\"{}\" Version=\"{}\" ProcessorArchitecture=\"{}\"/>",
+        escape(settings.bundle_identifier().as_ref()),
+        escape(publisher),
+        msix_version(&settings.version_string().to_string()),
+        msix_arch(settings.binary_arch())
+    )
+    .unwrap();
+    writeln!(xml, "  <Properties>").unwrap();
+    writeln!(xml, "    <DisplayName>{display_name}</DisplayName>").unwrap();
+    writeln!(
+        xml,
+        "    <PublisherDisplayName>{}</PublisherDisplayName>",
+        escape(&publisher_display_name)
+    )
+    .unwrap();
+    writeln!(xml, "    <Logo>Assets\\StoreLogo.png</Logo>").unwrap();
+    writeln!(xml, "    <Description>{description}</Description>").unwrap();
+    writeln!(xml, "  </Properties>").unwrap();
+    writeln!(xml, "  <Dependencies>").unwrap();
+    writeln!(
+        xml,
+        "    <TargetDeviceFamily Name=\"Windows.Desktop\" MinVersion=\"10.0.17763.0\" MaxVersionTested=\"10.0.22621.0\"/>"
+    )
+    .unwrap();
+    writeln!(xml, "  </Dependencies>").unwrap();
+    writeln!(xml, "  <Resources>").unwrap();
+    writeln!(xml, "    <Resource Language=\"en-us\"/>").unwrap();
+    writeln!(xml, "  </Resources>").unwrap();
+    writeln!(xml, "  <Applications>").unwrap();
+    writeln!(
+        xml,
+        "    <Application Id=\"App\" Executable=\"{}\" EntryPoint=\"Windows.FullTrustApplication\">",
+        escape(exe_name)
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "      <uap:VisualElements DisplayName=\"{display_name}\" Description=\"{description}\" \
+         BackgroundColor=\"transparent\" Square150x150Logo=\"Assets\\Square150x150Logo.png\" \
+         Square44x44Logo=\"Assets\\Square44x44Logo.png\"/>"
+    )
+    .unwrap();
+    writeln!(xml, "    </Application>").unwrap();
+    writeln!(xml, "  </Applications>").unwrap();
+    writeln!(xml, "  <Capabilities>").unwrap();
+    writeln!(xml, "    <rescap:Capability Name=\"runFullTrust\"/>").unwrap();
+    writeln!(xml, "  </Capabilities>").unwrap();
+    writeln!(xml, "</Package>").unwrap();
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msix_versions() {
+        assert_eq!(msix_version("1.2.3"), "1.2.3.0");
+        assert_eq!(msix_version("1.2.3-beta.1+build"), "1.2.3.0");
+        assert_eq!(msix_version("2"), "2.0.0.0");
+        assert_eq!(msix_version("1.2.3.4.5"), "1.2.3.4");
+    }
+
+    #[test]
+    fn manifest() {
+        let settings = Settings::for_test(
+            "name = \"Tom & Jerry\"\n\
+             identifier = \"com.example.App\"\n\
+             msix_publisher_display_name = \"Example Inc.\"\n",
+        );
+        let manifest = generate_manifest(&settings, "CN=Example", "example.exe");
+        assert!(manifest.contains(
+            "<Identity Name=\"com.example.App\" Publisher=\"CN=Example\" Version=\"1.0.0.0\""
+        ));
+        assert!(manifest.contains("<DisplayName>Tom &amp; Jerry</DisplayName>"));
+        assert!(manifest.contains("<PublisherDisplayName>Example Inc.</PublisherDisplayName>"));
+        assert!(manifest.contains("Executable=\"example.exe\""));
+    }
+}
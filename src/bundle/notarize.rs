@@ -0,0 +1,187 @@
+// Notarization submits a signed app or disk image to Apple's notary service,
+// which scans it for malicious content.  Once the submission is accepted, the
+// notarization ticket is stapled to the artifact, so that Gatekeeper can check
+// it while offline:
+//
+// xcrun notarytool submit foobar.dmg --wait ...  # Upload, and wait for the result
+// xcrun stapler staple foobar.dmg                # Attach the ticket
+//
+// App bundles cannot be uploaded as-is, so they are zipped with `ditto` first,
+// and the ticket is stapled to the app itself.  The notary service is
+// authenticated with credentials taken from the environment, so that it works
+// in CI.
+
+use super::common;
+use crate::Settings;
+use anyhow::Context;
+use serde_json::Value;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The credentials `notarytool` authenticates with.
+#[derive(Debug, PartialEq)]
+enum Credentials {
+    /// An App Store Connect API key.
+    ApiKey {
+        key_path: String,
+        key_id: String,
+        issuer: String,
+    },
+    /// A profile stored in the keychain by `notarytool store-credentials`.
+    KeychainProfile(String),
+}
+
+impl Credentials {
+    /// Reads the credentials from the `APPLE_API_KEY_PATH`, `APPLE_API_KEY_ID`
+    /// and `APPLE_API_ISSUER` variables, or else the `APPLE_KEYCHAIN_PROFILE`
+    /// variable, as looked up by `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> crate::Result<Credentials> {
+        if let Some(key_path) = var("APPLE_API_KEY_PATH") {
+            let (Some(key_id), Some(issuer)) = (var("APPLE_API_KEY_ID"), var("APPLE_API_ISSUER"))
+            else {
+                anyhow::bail!(
+                    "`APPLE_API_KEY_ID` and `APPLE_API_ISSUER` must be set along with \
+                     `APPLE_API_KEY_PATH` to notarize"
+                );
+            };
+            Ok(Credentials::ApiKey {
+                key_path,
+                key_id,
+                issuer,
+            })
+        } else if let Some(profile) = var("APPLE_KEYCHAIN_PROFILE") {
+            Ok(Credentials::KeychainProfile(profile))
+        } else {
+            anyhow::bail!(
+                "Notarizing requires either `APPLE_API_KEY_PATH`, `APPLE_API_KEY_ID` and \
+                 `APPLE_API_ISSUER`, or `APPLE_KEYCHAIN_PROFILE` to be set"
+            )
+        }
+    }
+
+    fn args(&self) -> Vec<OsString> {
+        let args: &[&str] = match self {
+            Credentials::ApiKey {
+                key_path,
+                key_id,
+                issuer,
+            } => &["--key", key_path, "--key-id", key_id, "--issuer", issuer],
+            Credentials::KeychainProfile(profile) => &["--keychain-profile", profile],
+        };
+        args.iter().map(OsString::from).collect()
+    }
+}
+
+/// Notarizes the app bundle or disk image at `path`, and staples the ticket to
+/// it.  The artifact must have been signed with a Developer ID identity.
+pub fn notarize(settings: &Settings, path: &Path) -> crate::Result<()> {
+    if settings
+        .osx_signing_identity()
+        .is_none_or(|identity| identity == "-")
+    {
+        anyhow::bail!(
+            "Notarizing requires signing with a Developer ID identity, set with \
+             `osx_signing_identity` or `--sign`"
+        );
+    }
+    if !settings.osx_hardened_runtime() {
+        anyhow::bail!(
+            "Notarizing requires the hardened runtime, disabled by `osx_hardened_runtime`"
+        );
+    }
+    let credentials = Credentials::from_vars(|name| std::env::var(name).ok())?;
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    common::print_notarizing(&file_name)?;
+    if path.extension().is_some_and(|ext| ext == "app") {
+        let zip_path = path.with_extension("zip");
+        common::run_tool(
+            Command::new("ditto")
+                .args(["-c", "-k", "--keepParent"])
+                .arg(path)
+                .arg(&zip_path),
+            "ditto",
+        )?;
+        let result = submit(&credentials, &zip_path);
+        fs::remove_file(&zip_path).with_context(|| format!("Failed to remove {zip_path:?}"))?;
+        result?;
+    } else {
+        submit(&credentials, path)?;
+    }
+    common::run_tool(
+        Command::new("xcrun").args(["stapler", "staple"]).arg(path),
+        "stapler",
+    )?;
+    Ok(())
+}
+
+/// Submits the file at `path` to the notary service and waits for the result,
+/// failing with the notary log if it is not accepted.
+fn submit(credentials: &Credentials, path: &Path) -> crate::Result<()> {
+    let output = common::run_tool(
+        Command::new("xcrun")
+            .args(["notarytool", "submit"])
+            .arg(path)
+            .args(["--wait", "--output-format", "json"])
+            .args(credentials.args()),
+        "notarytool",
+    )?;
+    let result: Value =
+        serde_json::from_slice(&output).with_context(|| "Failed to parse notarytool output")?;
+    let status = result["status"].as_str().unwrap_or_default();
+    if status == "Accepted" {
+        return Ok(());
+    }
+    let id = result["id"].as_str().unwrap_or_default();
+    let log = common::run_tool(
+        Command::new("xcrun")
+            .args(["notarytool", "log", id])
+            .args(credentials.args()),
+        "notarytool",
+    )
+    .map(|log| String::from_utf8_lossy(&log).into_owned())
+    .unwrap_or_else(|error| format!("(failed to fetch the log: {error})"));
+    anyhow::bail!(
+        "Notarization of {path:?} finished with status {status:?} (submission {id}):\n{log}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn credentials(vars: &[(&str, &str)]) -> crate::Result<Credentials> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        Credentials::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn credentials_from_vars() {
+        let api_key = credentials(&[
+            ("APPLE_API_KEY_PATH", "AuthKey_ABC.p8"),
+            ("APPLE_API_KEY_ID", "ABC"),
+            ("APPLE_API_ISSUER", "issuer-uuid"),
+            ("APPLE_KEYCHAIN_PROFILE", "ignored"),
+        ])
+        .unwrap();
+        assert_eq!(
+            api_key.args(),
+            [
+                "--key",
+                "AuthKey_ABC.p8",
+                "--key-id",
+                "ABC",
+                "--issuer",
+                "issuer-uuid"
+            ]
+        );
+        assert_eq!(
+            credentials(&[("APPLE_KEYCHAIN_PROFILE", "notary")]).unwrap(),
+            Credentials::KeychainProfile("notary".to_string())
+        );
+        assert!(credentials(&[("APPLE_API_KEY_PATH", "AuthKey_ABC.p8")]).is_err());
+        assert!(credentials(&[]).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+// An NSIS installer is compiled by `makensis` from a generated script.  We
+// stage the files to install next to the script:
+//
+// bundle/nsis/foobar_1.2.3_x86_64/
+//     installer.nsi           # The generated NSIS script
+//     license.txt             # The license, if there is one
+//     files/foobar.exe        # Binary executable file
+//     files/foobar.ico        # The app icon, if there is an ICO icon
+//     files/Resources/...     # Other resource files
+//
+// The installer is written to `bundle/nsis/foobar_1.2.3_x86_64-setup.exe`.  It
+// installs the files, Start Menu (and optionally desktop) shortcuts and an
+// uninstaller, and registers the app under "Apps & features".
+
+use super::common;
+use super::windows_registry::{self, CLASSES_KEY};
+use crate::Settings;
+use anyhow::Context;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const LICENSE_FILE_NAME: &str = "license.txt";
+const RTF_LICENSE_FILE_NAME: &str = "license.rtf";
+const HEADER_IMAGE_FILE_NAME: &str = "header.bmp";
+const SIDE_IMAGE_FILE_NAME: &str = "side.bmp";
+
+// Tells Explorer that file associations changed (`SHCNE_ASSOCCHANGED`).
+const SHELL_CHANGE_NOTIFY: &str =
+    "System::Call 'shell32::SHChangeNotify(i 0x08000000, i 0, p 0, p 0)'";
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    let package_name = format!("{package_base_name}-setup.exe");
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/nsis");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+
+    // Stage the files to install.
+    let files_dir = package_dir.join("files");
+    let exe_name = settings.binary_path().file_name().unwrap();
+    common::copy_file(settings.binary_path(), &files_dir.join(exe_name))
+        .with_context(|| "Failed to copy binary file")?;
+    common::copy_external_binaries(settings, &files_dir)?;
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let dest = files_dir.join("Resources").join(&resource.dest);
+        common::copy_resource(&resource, &dest)?;
+    }
+    if let Some(build_info) = settings.build_info() {
+        build_info
+            .write_json(&files_dir.join("Resources"))
+            .with_context(|| "Failed to write build info")?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        sbom.write_json(&files_dir.join("Resources"))
+            .with_context(|| "Failed to write SBOM")?;
+    }
+    for icon in windows_registry::icon_files(settings) {
+        common::copy_file(&icon, &files_dir.join(icon.file_name().unwrap()))
+            .with_context(|| format!("Failed to copy document icon {icon:?}"))?;
+    }
+    let icon_name = copy_app_icon(settings, &files_dir)?;
+    // The license page shows the bundle's license agreement, or else the
+    // package's license.
+    let license_name = if !settings.windows_license_dialog() {
+        None
+    } else if let Some(license) = settings.windows_license() {
+        common::copy_file(&license, &package_dir.join(RTF_LICENSE_FILE_NAME))
+            .with_context(|| format!("Failed to copy license agreement {license:?}"))?;
+        Some(RTF_LICENSE_FILE_NAME.to_string())
+    } else if let Some(license) = settings.license_content() {
+        fs::write(package_dir.join(LICENSE_FILE_NAME), license)
+            .with_context(|| "Failed to write license file")?;
+        Some(LICENSE_FILE_NAME.to_string())
+    } else {
+        None
+    };
+
+    let stage_image = |image: Option<PathBuf>, name: &str| -> crate::Result<Option<String>> {
+        let Some(image) = image else {
+            return Ok(None);
+        };
+        common::check_bmp(&image)?;
+        common::copy_file(&image, &package_dir.join(name))
+            .with_context(|| format!("Failed to copy installer image {image:?}"))?;
+        Ok(Some(name.to_string()))
+    };
+    let header_image = stage_image(settings.nsis_header_image(), HEADER_IMAGE_FILE_NAME)?;
+    let side_image = stage_image(settings.nsis_side_image(), SIDE_IMAGE_FILE_NAME)?;
+
+    let script = NsisScript {
+        exe_name: exe_name.to_string_lossy().into_owned(),
+        icon_name,
+        license_name,
+        header_image,
+        side_image,
+        out_file: package_path.clone(),
+    }
+    .generate(settings);
+    let script_path = package_dir.join("installer.nsi");
+    fs::write(&script_path, script).with_context(|| format!("Failed to write {script_path:?}"))?;
+
+    common::run_tool(
+        Command::new("makensis")
+            .arg("-V2")
+            .arg(&script_path)
+            .current_dir(&package_dir),
+        "makensis",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Copies the first ICO file among the bundle icons into `files_dir`, and
+/// returns its new file name.  NSIS can only use ICO icons.
+fn copy_app_icon(settings: &Settings, files_dir: &Path) -> crate::Result<Option<String>> {
+    for icon_path in settings.icon_files() {
+        let icon_path = icon_path?;
+        if icon_path.extension() == Some(OsStr::new("ico")) {
+            let icon_name = format!("{}.ico", settings.binary_name());
+            common::copy_file(&icon_path, &files_dir.join(&icon_name))
+                .with_context(|| format!("Failed to copy icon {icon_path:?}"))?;
+            return Ok(Some(icon_name));
+        }
+    }
+    Ok(None)
+}
+
+/// The details of the staged files that the NSIS script refers to.
+struct NsisScript {
+    exe_name: String,
+    icon_name: Option<String>,
+    license_name: Option<String>,
+    header_image: Option<String>,
+    side_image: Option<String>,
+    out_file: PathBuf,
+}
+
+impl NsisScript {
+    /// Writes the icons and pages of an installer with the classic UI.
+    fn write_pages(&self, nsi: &mut String) {
+        if let Some(ref icon_name) = self.icon_name {
+            writeln!(nsi, "Icon \"files\\{}\"", escape(icon_name)).unwrap();
+            writeln!(nsi, "UninstallIcon \"files\\{}\"", escape(icon_name)).unwrap();
+        }
+        writeln!(nsi).unwrap();
+        if let Some(ref license_name) = self.license_name {
+            writeln!(nsi, "LicenseData \"{license_name}\"").unwrap();
+            writeln!(nsi, "Page license").unwrap();
+        }
+        writeln!(nsi, "Page directory").unwrap();
+        writeln!(nsi, "Page instfiles").unwrap();
+        writeln!(nsi, "UninstPage uninstConfirm").unwrap();
+        writeln!(nsi, "UninstPage instfiles").unwrap();
+    }
+
+    /// Writes the icons and pages of an installer with the Modern UI, which
+    /// shows the header image at the top of its pages, and the side image on
+    /// its welcome and finish pages.
+    fn write_modern_ui_pages(&self, nsi: &mut String) {
+        writeln!(nsi, "!include \"MUI2.nsh\"").unwrap();
+        if let Some(ref icon_name) = self.icon_name {
+            writeln!(nsi, "!define MUI_ICON \"files\\{}\"", escape(icon_name)).unwrap();
+            writeln!(nsi, "!define MUI_UNICON \"files\\{}\"", escape(icon_name)).unwrap();
+        }
+        if let Some(ref header_image) = self.header_image {
+            writeln!(nsi, "!define MUI_HEADERIMAGE").unwrap();
+            writeln!(nsi, "!define MUI_HEADERIMAGE_BITMAP \"{header_image}\"").unwrap();
+        }
+        if let Some(ref side_image) = self.side_image {
+            writeln!(nsi, "!define MUI_WELCOMEFINISHPAGE_BITMAP \"{side_image}\"").unwrap();
+            writeln!(
+                nsi,
+                "!define MUI_UNWELCOMEFINISHPAGE_BITMAP \"{side_image}\""
+            )
+            .unwrap();
+        }
+        writeln!(nsi).unwrap();
+        if self.side_image.is_some() {
+            writeln!(nsi, "!insertmacro MUI_PAGE_WELCOME").unwrap();
+        }
+        if let Some(ref license_name) = self.license_name {
+            writeln!(nsi, "!insertmacro MUI_PAGE_LICENSE \"{license_name}\"").unwrap();
+        }
+        writeln!(nsi, "!insertmacro MUI_PAGE_DIRECTORY").unwrap();
+        writeln!(nsi, "!insertmacro MUI_PAGE_INSTFILES").unwrap();
+        if self.side_image.is_some() {
+            writeln!(nsi, "!insertmacro MUI_PAGE_FINISH").unwrap();
+        }
+        writeln!(nsi, "!insertmacro MUI_UNPAGE_CONFIRM").unwrap();
+        writeln!(nsi, "!insertmacro MUI_UNPAGE_INSTFILES").unwrap();
+        writeln!(nsi, "!insertmacro MUI_LANGUAGE \"English\"").unwrap();
+    }
+
+    /// Generates the `installer.nsi` script, which expects to be compiled from
+    /// the directory containing the staged files.
+    fn generate(&self, settings: &Settings) -> String {
+        let display_name = settings.bundle_name();
+        let file_name = settings.bundle_file_name();
+        let install_dir = match settings.nsis_install_dir() {
+            Some(dir) => dir.to_string(),
+            None => {
+                let program_files = match settings.binary_arch() {
+                    "x86_64" | "aarch64" => "$PROGRAMFILES64",
+                    _ => "$PROGRAMFILES",
+                };
+                format!("{program_files}\\{}", escape(&file_name))
+            }
+        };
+        let identifier = settings.bundle_identifier();
+        let uninstall_key = format!(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{}",
+            escape(if identifier.is_empty() {
+                &file_name
+            } else {
+                identifier.as_ref()
+            })
+        );
+        let shortcut_icon = match self.icon_name {
+            Some(ref icon_name) => format!("$INSTDIR\\{}", escape(icon_name)),
+            None => format!("$INSTDIR\\{}", escape(&self.exe_name)),
+        };
+        let start_menu_dir = format!("$SMPROGRAMS\\{}", escape(&file_name));
+        let shortcut_name = format!("{}.lnk", escape(&file_name));
+
+        let mut nsi = String::new();
+        writeln!(nsi, "Unicode true").unwrap();
+        writeln!(nsi, "Name \"{}\"", escape(display_name)).unwrap();
+        writeln!(
+            nsi,
+            "OutFile \"{}\"",
+            escape(&self.out_file.to_string_lossy())
+        )
+        .unwrap();
+        writeln!(nsi, "InstallDir \"{install_dir}\"").unwrap();
+        writeln!(nsi, "RequestExecutionLevel admin").unwrap();
+        if self.header_image.is_some() || self.side_image.is_some() {
+            self.write_modern_ui_pages(&mut nsi);
+        } else {
+            self.write_pages(&mut nsi);
+        }
+        writeln!(nsi).unwrap();
+
+        writeln!(nsi, "Section \"Install\"").unwrap();
+        writeln!(nsi, "  SetOutPath \"$INSTDIR\"").unwrap();
+        writeln!(nsi, "  File /r \"files\\*.*\"").unwrap();
+        writeln!(nsi, "  WriteUninstaller \"$INSTDIR\\uninstall.exe\"").unwrap();
+        writeln!(nsi, "  CreateDirectory \"{start_menu_dir}\"").unwrap();
+        writeln!(
+            nsi,
+            "  CreateShortCut \"{start_menu_dir}\\{shortcut_name}\" \"$INSTDIR\\{}\" \"\" \"{shortcut_icon}\"",
+            escape(&self.exe_name)
+        )
+        .unwrap();
+        if settings.nsis_desktop_shortcut() {
+            writeln!(
+                nsi,
+                "  CreateShortCut \"$DESKTOP\\{shortcut_name}\" \"$INSTDIR\\{}\" \"\" \"{shortcut_icon}\"",
+                escape(&self.exe_name)
+            )
+            .unwrap();
+        }
+        let registry_values = [
+            ("DisplayName", escape(display_name)),
+            (
+                "DisplayVersion",
+                escape(&settings.version_string().to_string()),
+            ),
+            ("DisplayIcon", shortcut_icon.clone()),
+            ("InstallLocation", "$INSTDIR".to_string()),
+            (
+                "UninstallString",
+                "$\\\"$INSTDIR\\uninstall.exe$\\\"".to_string(),
+            ),
+        ];
+        for (name, value) in registry_values {
+            writeln!(
+                nsi,
+                "  WriteRegStr HKLM \"{uninstall_key}\" \"{name}\" \"{value}\""
+            )
+            .unwrap();
+        }
+        if let Some(publisher) = settings.authors_comma_separated() {
+            writeln!(
+                nsi,
+                "  WriteRegStr HKLM \"{uninstall_key}\" \"Publisher\" \"{}\"",
+                escape(&publisher)
+            )
+            .unwrap();
+        }
+        let classes_values = windows_registry::registry_values(settings, "$INSTDIR\\", escape);
+        for value in &classes_values {
+            writeln!(
+                nsi,
+                "  WriteRegStr HKLM \"{CLASSES_KEY}\\{}\" \"{}\" \"{}\"",
+                value.key,
+                value.name.as_deref().unwrap_or_default(),
+                value.value
+            )
+            .unwrap();
+        }
+        if !classes_values.is_empty() {
+            writeln!(nsi, "  {SHELL_CHANGE_NOTIFY}").unwrap();
+        }
+        writeln!(nsi, "SectionEnd").unwrap();
+        writeln!(nsi).unwrap();
+
+        writeln!(nsi, "Section \"Uninstall\"").unwrap();
+        if settings.nsis_desktop_shortcut() {
+            writeln!(nsi, "  Delete \"$DESKTOP\\{shortcut_name}\"").unwrap();
+        }
+        writeln!(nsi, "  RMDir /r \"{start_menu_dir}\"").unwrap();
+        writeln!(nsi, "  RMDir /r \"$INSTDIR\"").unwrap();
+        writeln!(nsi, "  DeleteRegKey HKLM \"{uninstall_key}\"").unwrap();
+        // The extensions may be associated with other apps too, so only the
+        // app's own ProgIds are removed from them.
+        let owned_keys: Vec<String> = windows_registry::owned_keys(settings)
+            .iter()
+            .map(|key| escape(key))
+            .collect();
+        for value in &classes_values {
+            if let Some(name) = &value.name
+                && owned_keys.contains(name)
+            {
+                writeln!(
+                    nsi,
+                    "  DeleteRegValue HKLM \"{CLASSES_KEY}\\{}\" \"{name}\"",
+                    value.key
+                )
+                .unwrap();
+            }
+        }
+        for key in &owned_keys {
+            writeln!(nsi, "  DeleteRegKey HKLM \"{CLASSES_KEY}\\{key}\"").unwrap();
+        }
+        if !classes_values.is_empty() {
+            writeln!(nsi, "  {SHELL_CHANGE_NOTIFY}").unwrap();
+        }
+        writeln!(nsi, "SectionEnd").unwrap();
+        nsi
+    }
+}
+
+/// Escapes a string for use inside a double-quoted NSIS string, so that `$`
+/// and `"` are taken literally.
+fn escape(value: &str) -> String {
+    value.replace('$', "$$").replace('"', "$\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_strings() {
+        assert_eq!(escape("My \"$5\" App"), "My $\\\"$$5$\\\" App");
+    }
+
+    #[test]
+    fn nsi_script() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             identifier = \"com.example.App\"\n\
+             nsis_desktop_shortcut = true\n",
+        );
+        let script = NsisScript {
+            exe_name: "example.exe".to_string(),
+            icon_name: Some("example.ico".to_string()),
+            license_name: None,
+            header_image: None,
+            side_image: None,
+            out_file: PathBuf::from("example-setup.exe"),
+        }
+        .generate(&settings);
+        assert!(script.contains("Name \"My App\"\n"));
+        assert!(script.contains("OutFile \"example-setup.exe\"\n"));
+        assert!(script.contains("Icon \"files\\example.ico\"\n"));
+        assert!(!script.contains("Page license\n"));
+        assert!(script.contains(
+            "CreateShortCut \"$SMPROGRAMS\\My App\\My App.lnk\" \"$INSTDIR\\example.exe\" \"\" \"$INSTDIR\\example.ico\"\n"
+        ));
+        assert!(script.contains("CreateShortCut \"$DESKTOP\\My App.lnk\""));
+        assert!(script.contains(
+            "WriteRegStr HKLM \"Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\com.example.App\" \"DisplayVersion\" \"1.0.0\"\n"
+        ));
+        assert!(script.contains("WriteUninstaller \"$INSTDIR\\uninstall.exe\"\n"));
+        assert!(!script.contains("SHChangeNotify"));
+    }
+
+    #[test]
+    fn nsi_license_page() {
+        let settings = Settings::for_test("[windows]\nlicense = \"EULA.rtf\"");
+        let script = NsisScript {
+            exe_name: "example.exe".to_string(),
+            icon_name: None,
+            license_name: Some(RTF_LICENSE_FILE_NAME.to_string()),
+            header_image: None,
+            side_image: None,
+            out_file: PathBuf::from("example-setup.exe"),
+        }
+        .generate(&settings);
+        assert!(script.contains("LicenseData \"license.rtf\"\nPage license\nPage directory\n"));
+    }
+
+    #[test]
+    fn nsi_modern_ui_pages() {
+        let settings = Settings::for_test("name = \"My App\"");
+        let script = NsisScript {
+            exe_name: "example.exe".to_string(),
+            icon_name: Some("example.ico".to_string()),
+            license_name: Some(LICENSE_FILE_NAME.to_string()),
+            header_image: Some(HEADER_IMAGE_FILE_NAME.to_string()),
+            side_image: Some(SIDE_IMAGE_FILE_NAME.to_string()),
+            out_file: PathBuf::from("example-setup.exe"),
+        }
+        .generate(&settings);
+        assert!(script.contains("!define MUI_ICON \"files\\example.ico\"\n"));
+        assert!(!script.contains("\nIcon "));
+        assert!(script.contains(
+            "!define MUI_HEADERIMAGE\n\
+             !define MUI_HEADERIMAGE_BITMAP \"header.bmp\"\n\
+             !define MUI_WELCOMEFINISHPAGE_BITMAP \"side.bmp\"\n"
+        ));
+        assert!(script.contains(
+            "!insertmacro MUI_PAGE_WELCOME\n\
+             !insertmacro MUI_PAGE_LICENSE \"license.txt\"\n\
+             !insertmacro MUI_PAGE_DIRECTORY\n\
+             !insertmacro MUI_PAGE_INSTFILES\n\
+             !insertmacro MUI_PAGE_FINISH\n"
+        ));
+        assert!(!script.contains("Page license\n"));
+    }
+
+    #[test]
+    fn nsi_file_associations() {
+        let settings = Settings::for_test(
+            "identifier = \"com.example.App\"\n\
+             file_associations = [{ extensions = [\"foo\"], description = \"Foo \\\"$1\\\"\" }]\n",
+        );
+        let script = NsisScript {
+            exe_name: "example.exe".to_string(),
+            icon_name: None,
+            license_name: None,
+            header_image: None,
+            side_image: None,
+            out_file: PathBuf::from("example-setup.exe"),
+        }
+        .generate(&settings);
+        assert!(script.contains(
+            "  WriteRegStr HKLM \"Software\\Classes\\.foo\" \"\" \"com.example.App.foo\"\n\
+             \x20 WriteRegStr HKLM \"Software\\Classes\\.foo\\OpenWithProgids\" \"com.example.App.foo\" \"\"\n\
+             \x20 WriteRegStr HKLM \"Software\\Classes\\com.example.App.foo\" \"\" \"Foo $\\\"$$1$\\\"\"\n"
+        ));
+        assert!(script.contains(
+            "  WriteRegStr HKLM \"Software\\Classes\\com.example.App.foo\\shell\\open\\command\" \"\" \"$\\\"$INSTDIR\\example$\\\" $\\\"%1$\\\"\"\n"
+        ));
+        assert!(script.contains(
+            "  DeleteRegValue HKLM \"Software\\Classes\\.foo\\OpenWithProgids\" \"com.example.App.foo\"\n\
+             \x20 DeleteRegKey HKLM \"Software\\Classes\\com.example.App.foo\"\n\
+             \x20 System::Call 'shell32::SHChangeNotify(i 0x08000000, i 0, p 0, p 0)'\n"
+        ));
+    }
+}
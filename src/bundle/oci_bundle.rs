@@ -0,0 +1,305 @@
+// An OCI image is stored as an image layout: a directory of content-addressed
+// blobs, plus an index pointing to the image's manifest.  We build a single
+// layer image, with no base image (i.e. `FROM scratch`), and archive the
+// layout directory as a tarball that both `docker load` and `podman load`
+// accept:
+//
+// foobar_1.2.3_amd64.tar
+//     oci-layout              # Marks the directory as an OCI image layout
+//     index.json              # Points to the manifest, tagged `foobar:1.2.3`
+//     manifest.json           # The same, in the format of `docker save`
+//     blobs/sha256/...        # The manifest, the config and the layer:
+//         usr/bin/foobar          # Binary executable file
+//         usr/lib/foobar/...      # Other resource files
+//
+// The unarchived layout is left in `bundle/oci/foobar_1.2.3_amd64/layout`, for
+// tools like `skopeo` to push directly.  As the image has no libc, the binary
+// should be statically linked, e.g. by building for a `*-linux-musl` target.
+
+use super::build_info::build_time;
+use super::common;
+use super::linux::common::{
+    append_tar_entry, collect_entries, install_binary, transfer_resource_files,
+};
+use crate::Settings;
+use anyhow::Context;
+use serde_json::{Value, json};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let target = match settings.target_triple() {
+        Some(triple) => triple.to_string(),
+        None => super::host_triple()?,
+    };
+    if !target.contains("linux") {
+        anyhow::bail!("OCI images must be built for a Linux target, not {target}");
+    }
+    if !target.contains("musl") {
+        common::print_warning(
+            "OCI images have no base image, so a binary not built for a `*-linux-musl` target \
+             may fail to find its shared libraries.",
+        )?;
+    }
+    let (arch, variant) = oci_arch(settings.binary_arch());
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        arch
+    );
+    let package_name = format!("{package_base_name}.tar");
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.project_out_directory().join("bundle/oci");
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+    let mtime = build_time()?;
+
+    // Stage the files of the layer.
+    let data_dir = package_dir.join("data");
+    install_binary(settings, &data_dir)?;
+    transfer_resource_files(settings, &data_dir)?;
+
+    // The config refers to the layer by the digest of its uncompressed
+    // contents, and the manifest by the digest of the compressed blob.
+    let layout_dir = package_dir.join("layout");
+    let blobs_dir = layout_dir.join("blobs/sha256");
+    fs::create_dir_all(&blobs_dir)?;
+    let layer_tar_path = package_dir.join("layer.tar");
+    let mut tar_builder = tar::Builder::new(common::create_file(&layer_tar_path)?);
+    for entry in collect_entries(&data_dir)? {
+        append_tar_entry(&mut tar_builder, &data_dir, &entry.path, mtime.timestamp())?;
+    }
+    tar_builder.into_inner()?.flush()?;
+    let diff_id = common::sha256_hex(&layer_tar_path)?;
+    let layer_gz_path = package_dir.join("layer.tar.gz");
+    let mut encoder = common::gzip_encoder(common::create_file(&layer_gz_path)?)?;
+    io::copy(&mut File::open(&layer_tar_path)?, &mut encoder)?;
+    encoder.finish().into_result()?.flush()?;
+    let layer = move_blob(&layer_gz_path, &blobs_dir, LAYER_MEDIA_TYPE)?;
+
+    let config = generate_config(settings, &mtime.to_rfc3339(), &diff_id);
+    let config = write_blob(&config, &blobs_dir, CONFIG_MEDIA_TYPE)?;
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA_TYPE,
+        "config": config,
+        "layers": [layer],
+    });
+    let mut manifest = write_blob(&manifest, &blobs_dir, MANIFEST_MEDIA_TYPE)?;
+    let image_name = image_name(settings);
+    manifest["platform"] = platform(arch, variant);
+    manifest["annotations"] = json!({
+        "io.containerd.image.name": image_name,
+        "org.opencontainers.image.ref.name": image_tag(settings),
+    });
+    write_json(
+        &layout_dir.join("index.json"),
+        &json!({
+            "schemaVersion": 2,
+            "mediaType": INDEX_MEDIA_TYPE,
+            "manifests": [manifest],
+        }),
+    )?;
+    write_json(
+        &layout_dir.join("oci-layout"),
+        &json!({ "imageLayoutVersion": "1.0.0" }),
+    )?;
+    write_json(
+        &layout_dir.join("manifest.json"),
+        &json!([{
+            "Config": blob_path(&config),
+            "RepoTags": [image_name],
+            "Layers": [blob_path(&layer)],
+        }]),
+    )?;
+
+    let mut tar_builder = tar::Builder::new(common::create_file(&package_path)?);
+    for entry in collect_entries(&layout_dir)? {
+        append_tar_entry(
+            &mut tar_builder,
+            &layout_dir,
+            &entry.path,
+            mtime.timestamp(),
+        )?;
+    }
+    tar_builder.into_inner()?.flush()?;
+    Ok(vec![package_path])
+}
+
+/// Maps a Rust target architecture (e.g. `"x86_64"`) to the corresponding OCI
+/// architecture (e.g. `"amd64"`) and variant, if any.
+fn oci_arch(binary_arch: &str) -> (&str, Option<&str>) {
+    match binary_arch {
+        "x86_64" => ("amd64", None),
+        "x86" => ("386", None),
+        "aarch64" => ("arm64", Some("v8")),
+        "arm" => ("arm", Some("v7")),
+        "powerpc64" => ("ppc64le", None),
+        "riscv64" => ("riscv64", None),
+        other => (other, None),
+    }
+}
+
+fn platform(arch: &str, variant: Option<&str>) -> Value {
+    let mut platform = json!({ "architecture": arch, "os": "linux" });
+    if let Some(variant) = variant {
+        platform["variant"] = json!(variant);
+    }
+    platform
+}
+
+/// Returns the image's tag, which may not contain `+` (e.g. `1.2.3+build.5`
+/// becomes `1.2.3_build.5`).
+fn image_tag(settings: &Settings) -> String {
+    settings.version_string().to_string().replace('+', "_")
+}
+
+/// Returns the image's name and tag, e.g. `foobar:1.2.3`.
+fn image_name(settings: &Settings) -> String {
+    // Repository names follow the same rules as Debian package names, except
+    // that they may not contain `+`.
+    let repository = super::linux::deb_bundle::package_name(settings).replace('+', "-");
+    format!("{repository}:{}", image_tag(settings))
+}
+
+/// Generates the image configuration, whose single layer has the uncompressed
+/// digest `diff_id`.
+fn generate_config(settings: &Settings, created: &str, diff_id: &str) -> Value {
+    let (arch, variant) = oci_arch(settings.binary_arch());
+    let mut labels = json!({
+        "org.opencontainers.image.title": settings.bundle_name(),
+        "org.opencontainers.image.version": settings.version_string().to_string(),
+        "org.opencontainers.image.created": created,
+    });
+    let description = settings.short_description().trim();
+    if !description.is_empty() {
+        labels["org.opencontainers.image.description"] = json!(description);
+    }
+    if let Some(license) = settings.license() {
+        labels["org.opencontainers.image.licenses"] = json!(license);
+    }
+    if !settings.homepage_url().is_empty() {
+        labels["org.opencontainers.image.url"] = json!(settings.homepage_url());
+    }
+    if let Some(git_hash) = settings
+        .build_info()
+        .and_then(|info| info.git_hash.as_ref())
+    {
+        labels["org.opencontainers.image.revision"] = json!(git_hash);
+    }
+    let mut exposed_ports = json!({});
+    for port in settings.oci_exposed_ports() {
+        let port = if port.contains('/') {
+            port.clone()
+        } else {
+            format!("{port}/tcp")
+        };
+        exposed_ports[port] = json!({});
+    }
+    let mut config = platform(arch, variant);
+    config["created"] = json!(created);
+    config["config"] = json!({
+        "Entrypoint": [format!("/usr/bin/{}", settings.binary_name())],
+        "Env": ["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin"],
+        "WorkingDir": "/",
+        "ExposedPorts": exposed_ports,
+        "Labels": labels,
+    });
+    config["rootfs"] = json!({
+        "type": "layers",
+        "diff_ids": [format!("sha256:{diff_id}")],
+    });
+    config["history"] = json!([{
+        "created": created,
+        "created_by": format!("cargo-bundle {}", env!("CARGO_PKG_VERSION")),
+    }]);
+    config
+}
+
+fn write_json(path: &Path, value: &Value) -> crate::Result<()> {
+    fs::write(path, serde_json::to_string(value)?)
+        .with_context(|| format!("Failed to write {path:?}"))
+}
+
+/// Writes `value` as a blob, and returns its descriptor.
+fn write_blob(value: &Value, blobs_dir: &Path, media_type: &str) -> crate::Result<Value> {
+    let path = blobs_dir.join("pending");
+    write_json(&path, value)?;
+    move_blob(&path, blobs_dir, media_type)
+}
+
+/// Moves the file at `path` into the blobs directory, named after its digest,
+/// and returns its descriptor.
+fn move_blob(path: &Path, blobs_dir: &Path, media_type: &str) -> crate::Result<Value> {
+    let digest = common::sha256_hex(path)?;
+    let size = fs::metadata(path)?.len();
+    fs::rename(path, blobs_dir.join(&digest))
+        .with_context(|| format!("Failed to move {path:?} into the image"))?;
+    Ok(json!({
+        "mediaType": media_type,
+        "digest": format!("sha256:{digest}"),
+        "size": size,
+    }))
+}
+
+/// Returns the path of the blob a descriptor refers to, relative to the
+/// layout directory.
+fn blob_path(descriptor: &Value) -> String {
+    let digest = descriptor["digest"].as_str().unwrap();
+    format!("blobs/sha256/{}", digest.trim_start_matches("sha256:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arches() {
+        assert_eq!(oci_arch("x86_64"), ("amd64", None));
+        assert_eq!(oci_arch("aarch64"), ("arm64", Some("v8")));
+        assert_eq!(
+            platform("arm", Some("v7")),
+            json!({ "architecture": "arm", "os": "linux", "variant": "v7" })
+        );
+    }
+
+    #[test]
+    fn image_names() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             version = \"1.2.3+build.5\"\n",
+        );
+        assert_eq!(image_name(&settings), "my-app:1.2.3_build.5");
+    }
+
+    #[test]
+    fn config() {
+        let settings = Settings::for_test(
+            "short_description = \"An example\"\n\
+             oci_exposed_ports = [\"8080\", \"53/udp\"]\n",
+        );
+        let config = generate_config(&settings, "2024-01-01T00:00:00+00:00", "abc123");
+        assert_eq!(config["os"], "linux");
+        assert_eq!(config["config"]["Entrypoint"], json!(["/usr/bin/example"]));
+        assert_eq!(
+            config["config"]["ExposedPorts"],
+            json!({ "8080/tcp": {}, "53/udp": {} })
+        );
+        assert_eq!(
+            config["config"]["Labels"]["org.opencontainers.image.description"],
+            "An example"
+        );
+        assert_eq!(config["rootfs"]["diff_ids"], json!(["sha256:abc123"]));
+    }
+}
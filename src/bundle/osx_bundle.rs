@@ -8,6 +8,7 @@
 //             foobar_helper   # A helper application, possibly provitidng a CLI
 //         Resources      # Data files such as images, sounds, translations and nib files
 //             en.lproj        # Folder containing english translation strings/data
+//             man/man1/foobar.1  # The `man_pages`, by section
 //         Frameworks     # A directory containing private frameworks (shared libraries)
 //         PlugIns        # A directory containing Plugins
 //         ...            # Any other optional files the developer wants to place here
@@ -18,7 +19,12 @@
 // Currently, cargo-bundle does not support Frameworks, nor does it support placing arbitrary
 // files into the `Contents` directory of the bundle.
 
+use super::build_info::build_time;
 use super::common::{self, read_file};
+use super::debug_symbols;
+use super::notarize::notarize;
+use super::settings::{FileAssociation, OsxEntitlements, OsxSigningTool};
+use super::sparkle;
 use crate::Settings;
 use anyhow::Context;
 use image::imageops::FilterType::Lanczos3;
@@ -29,14 +35,52 @@ use std::fs::{self, File};
 use std::io::prelude::*;
 use std::io::{self, BufWriter};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    let app_bundle_name = format!("{}.app", settings.bundle_name());
+    let app_bundle_path = build_app_bundle(settings)?;
+    if settings.notarize() {
+        notarize(settings, &app_bundle_path)?;
+    }
+    let dsym_path = debug_symbols::copy_dsym(settings, &app_bundle_path)?;
+    let update = sparkle::create_update(settings, &app_bundle_path)
+        .with_context(|| "Failed to create Sparkle update")?;
+    Ok(std::iter::once(app_bundle_path)
+        .chain(dsym_path)
+        .chain(update)
+        .collect())
+}
+
+/// Builds (and signs, if a signing identity is set) the app bundle, which the
+/// `dmg` and `pkg` bundlers then package.
+pub fn build_app_bundle(settings: &Settings) -> crate::Result<PathBuf> {
+    let out_dir = settings.project_out_directory().join("bundle/osx");
+    let app_bundle_path = build_unsigned_app_bundle(settings, &out_dir)?;
+    if let Some(identity) = settings.osx_signing_identity() {
+        let app_bundle_name = app_bundle_path.file_name().unwrap().to_string_lossy();
+        common::print_signing(&app_bundle_name)?;
+        let entitlements = entitlements_file(settings, &app_bundle_path)?;
+        sign_app_bundle(
+            &app_bundle_path,
+            identity,
+            entitlements.as_deref(),
+            settings.osx_hardened_runtime(),
+            settings,
+        )
+        .with_context(|| format!("Failed to sign {app_bundle_name}"))?;
+        common::record_signed(&app_bundle_path);
+    }
+    Ok(app_bundle_path)
+}
+
+/// Builds the app bundle in `out_dir`, leaving it unsigned.
+pub(crate) fn build_unsigned_app_bundle(
+    settings: &Settings,
+    out_dir: &Path,
+) -> crate::Result<PathBuf> {
+    let app_bundle_name = format!("{}.app", settings.bundle_file_name());
     common::print_bundling(&app_bundle_name)?;
-    let app_bundle_path = settings
-        .project_out_directory()
-        .join("bundle/osx")
-        .join(&app_bundle_name);
+    let app_bundle_path = out_dir.join(&app_bundle_name);
     if app_bundle_path.exists() {
         fs::remove_dir_all(&app_bundle_path)
             .with_context(|| format!("Failed to remove old {app_bundle_name}"))?;
@@ -54,30 +98,371 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     create_info_plist(&bundle_directory, bundle_icon_file, settings)
         .with_context(|| "Failed to create Info.plist")?;
 
-    let copied = copy_frameworks_to_bundle(&bundle_directory, settings)
+    create_localized_strings(&resources_dir, settings)
+        .with_context(|| "Failed to create localized strings")?;
+
+    for association in settings.file_associations() {
+        if let Some(icon) = settings.file_association_icon(association, &["icns"]) {
+            common::copy_file(&icon, &resources_dir.join(icon.file_name().unwrap()))
+                .with_context(|| format!("Failed to copy document icon {icon:?}"))?;
+        }
+    }
+
+    let mut copied = copy_frameworks_to_bundle(&bundle_directory, settings)
         .with_context(|| "Failed to bundle frameworks")?;
+    if sparkle::copy_framework(&bundle_directory, settings)? {
+        copied += 1;
+    }
 
     copy_plugins_to_bundle(&bundle_directory, settings)
         .with_context(|| "Failed to bundle plugins")?;
 
-    for src in settings.resource_files() {
-        let src = src?;
-        let dest = resources_dir.join(common::resource_relpath(&src));
-        common::copy_file(&src, &dest)
-            .with_context(|| format!("Failed to copy resource file {src:?}"))?;
+    for helper in settings.osx_helpers() {
+        let helper_settings = settings.osx_helper_settings(helper)?;
+        build_unsigned_app_bundle(&helper_settings, &bundle_directory.join(helper.kind.dir()))
+            .with_context(|| format!("Failed to bundle helper app {:?}", helper.bin))?;
+    }
+
+    for resource in settings.resource_files() {
+        let resource = resource?;
+        let dest = resources_dir.join(&resource.dest);
+        common::copy_resource(&resource, &dest)?;
+    }
+
+    common::copy_man_pages(settings, &resources_dir.join("man"))?;
+
+    if let Some(build_info) = settings.build_info() {
+        build_info
+            .write_json(&resources_dir)
+            .with_context(|| "Failed to write build info")?;
+    }
+    if let Some(sbom) = settings.sbom() {
+        sbom.write_json(&resources_dir)
+            .with_context(|| "Failed to write SBOM")?;
     }
 
     copy_binary_to_bundle(&bundle_directory, settings)
         .with_context(|| format!("Failed to copy binary from {:?}", settings.binary_path()))?;
 
+    if settings.bundle_shared_libraries() {
+        copied += copy_shared_libraries_to_bundle(&bundle_directory, settings)
+            .with_context(|| "Failed to bundle shared libraries")?;
+    }
+
     if copied > 0 {
+        relink_bundled_libraries(&bundle_directory, settings)
+            .with_context(|| "Failed to relink bundled libraries")?;
         add_rpath(&bundle_directory, settings)?;
     }
 
-    Ok(vec![app_bundle_path])
+    Ok(app_bundle_path)
+}
+
+/// Signs the app bundle inside out: first the bundled frameworks, plugins,
+/// helper apps and helper binaries, then the app itself (with `entitlements`), whose signature
+/// is then verified.  The hardened runtime, which notarization requires, is
+/// enabled throughout if `runtime` is set.
+pub(crate) fn sign_app_bundle(
+    app_bundle_path: &Path,
+    identity: &str,
+    entitlements: Option<&Path>,
+    runtime: bool,
+    settings: &Settings,
+) -> crate::Result<()> {
+    let timestamp_url = settings.osx_timestamp_url();
+    if settings.osx_signing_tool() == OsxSigningTool::Rcodesign {
+        // rcodesign signs nested code itself.
+        let key = SigningKey::from_settings(settings, identity)?;
+        common::run_signing_tool(
+            settings,
+            &mut rcodesign_command(
+                key.as_ref(),
+                app_bundle_path,
+                entitlements,
+                runtime,
+                timestamp_url,
+            ),
+            "rcodesign",
+        )?;
+        return Ok(());
+    }
+    let contents_dir = app_bundle_path.join("Contents");
+    let mut nested = Vec::new();
+    for dir in [
+        "Frameworks",
+        "PlugIns",
+        "Helpers",
+        "Library/LoginItems",
+        "MacOS",
+    ] {
+        let dir = contents_dir.join(dir);
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.file_name() != Some(OsStr::new(settings.binary_name())) {
+                nested.push(path);
+            }
+        }
+    }
+    nested.sort();
+    for path in &nested {
+        common::run_signing_tool(
+            settings,
+            &mut codesign_command(identity, path, None, runtime, timestamp_url),
+            "codesign",
+        )?;
+    }
+    common::run_signing_tool(
+        settings,
+        &mut codesign_command(
+            identity,
+            app_bundle_path,
+            entitlements,
+            runtime,
+            timestamp_url,
+        ),
+        "codesign",
+    )?;
+    common::run_tool(
+        Command::new("codesign")
+            .args(["--verify", "--deep", "--strict", "--verbose=2"])
+            .arg(app_bundle_path),
+        "codesign",
+    )?;
+    Ok(())
+}
+
+/// Builds the `codesign` invocation that signs `path`, with the hardened
+/// runtime if `runtime` is set.  Signatures are timestamped by the server at
+/// `timestamp_url`, or else by Apple's, except for ad-hoc signatures (with the
+/// identity `-`), as timestamping requires a certificate.
+fn codesign_command(
+    identity: &str,
+    path: &Path,
+    entitlements: Option<&Path>,
+    runtime: bool,
+    timestamp_url: Option<&str>,
+) -> Command {
+    let mut command = Command::new("codesign");
+    command.args(["--force", "--deep"]);
+    if runtime {
+        command.args(["--options", "runtime"]);
+    }
+    command.arg("--sign").arg(identity);
+    match timestamp_url {
+        _ if identity == "-" => {}
+        Some(url) => {
+            command.arg(format!("--timestamp={url}"));
+        }
+        None => {
+            command.arg("--timestamp");
+        }
+    }
+    if let Some(entitlements) = entitlements {
+        command.arg("--entitlements").arg(entitlements);
+    }
+    command.arg(path);
+    command
+}
+
+/// Returns the path of the entitlements file to sign the app with.  Entitlements
+/// given as a table are written to `Foobar.entitlements`, next to the app.
+fn entitlements_file(
+    settings: &Settings,
+    app_bundle_path: &Path,
+) -> crate::Result<Option<PathBuf>> {
+    match entitlements(settings)? {
+        None => Ok(None),
+        Some(Entitlements::File(path)) => Ok(Some(path)),
+        Some(Entitlements::Keys(keys)) => {
+            let path = app_bundle_path.with_extension("entitlements");
+            let mut file = common::create_file(&path)?;
+            file.write_all(entitlements_plist(&keys)?.as_bytes())?;
+            file.flush()?;
+            Ok(Some(path))
+        }
+    }
+}
+
+/// The entitlements to sign a macOS app with, combining the `osx_entitlements`
+/// and `osx_sandbox` settings.
+pub(crate) enum Entitlements {
+    /// The path of an entitlements file.
+    File(PathBuf),
+    /// Entitlement keys and their values.
+    Keys(toml::Table),
+}
+
+/// Returns the entitlements to sign the app with: the `osx_entitlements` file,
+/// or else the keys of the `osx_entitlements` table along with the sandbox
+/// entitlements generated from `osx_sandbox`, which the table overrides.
+pub(crate) fn entitlements(settings: &Settings) -> crate::Result<Option<Entitlements>> {
+    let sandbox = settings.osx_sandbox().map(|sandbox| sandbox.entitlements());
+    match (settings.osx_entitlements(), sandbox) {
+        (Some(OsxEntitlements::File(_)), Some(_)) => anyhow::bail!(
+            "`osx_sandbox` cannot be combined with an `osx_entitlements` file; give \
+             `osx_entitlements` as a table instead"
+        ),
+        (Some(OsxEntitlements::File(path)), None) => Ok(Some(Entitlements::File(path))),
+        (Some(OsxEntitlements::Keys(keys)), sandbox) => {
+            let mut merged = sandbox.unwrap_or_default();
+            merged.extend(keys.clone());
+            Ok(Some(Entitlements::Keys(merged)))
+        }
+        (None, sandbox) => Ok(sandbox.map(Entitlements::Keys)),
+    }
+}
+
+/// Returns the contents of an entitlements file granting the given entitlements.
+pub(crate) fn entitlements_plist(keys: &toml::Table) -> crate::Result<String> {
+    let mut plist = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+         \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n",
+    );
+    write_plist_dict(&mut plist, keys, 0)?;
+    plist.push_str("</plist>\n");
+    Ok(plist)
+}
+
+fn write_plist_dict(plist: &mut String, keys: &toml::Table, depth: usize) -> crate::Result<()> {
+    let indent = "  ".repeat(depth);
+    plist.push_str(&format!("{indent}<dict>\n"));
+    for (key, value) in keys {
+        plist.push_str(&format!(
+            "{indent}  <key>{}</key>\n",
+            key.format_plist_entry()
+        ));
+        write_plist_value(plist, key, value, depth + 1)?;
+    }
+    plist.push_str(&format!("{indent}</dict>\n"));
+    Ok(())
+}
+
+fn write_plist_value(
+    plist: &mut String,
+    key: &str,
+    value: &toml::Value,
+    depth: usize,
+) -> crate::Result<()> {
+    let indent = "  ".repeat(depth);
+    match value {
+        toml::Value::Boolean(true) => plist.push_str(&format!("{indent}<true/>\n")),
+        toml::Value::Boolean(false) => plist.push_str(&format!("{indent}<false/>\n")),
+        toml::Value::String(string) => plist.push_str(&format!(
+            "{indent}<string>{}</string>\n",
+            string.format_plist_entry()
+        )),
+        toml::Value::Integer(integer) => {
+            plist.push_str(&format!("{indent}<integer>{integer}</integer>\n"))
+        }
+        toml::Value::Array(values) => {
+            plist.push_str(&format!("{indent}<array>\n"));
+            for value in values {
+                write_plist_value(plist, key, value, depth + 1)?;
+            }
+            plist.push_str(&format!("{indent}</array>\n"));
+        }
+        toml::Value::Table(table) => write_plist_dict(plist, table, depth)?,
+        toml::Value::Float(_) | toml::Value::Datetime(_) => {
+            anyhow::bail!("Unsupported plist value for {key:?}: {value}")
+        }
+    }
+    Ok(())
+}
+
+/// The environment variable holding the password of the certificate file that
+/// `rcodesign` signs with.
+const CERTIFICATE_PASSWORD_VAR: &str = "CARGO_BUNDLE_OSX_CERTIFICATE_PASSWORD";
+
+/// The environment variable holding the PIN of a hardware token.
+pub(crate) const TOKEN_PIN_VAR: &str = "CARGO_BUNDLE_TOKEN_PIN";
+
+/// The key for `rcodesign` to sign with, as it can't use the identities in a
+/// keychain.
+pub(crate) enum SigningKey {
+    /// A PKCS #12 file holding the certificate and private key.
+    P12 {
+        path: PathBuf,
+        password: Option<String>,
+    },
+    /// A slot of a PIV smartcard (such as a YubiKey), whose PIN is read from
+    /// the environment by `rcodesign` itself.
+    Smartcard { slot: String },
+}
+
+impl SigningKey {
+    /// Reads the `osx_smartcard_slot` or `osx_signing_certificate` setting,
+    /// and the certificate password from the environment.  Returns `None` for
+    /// the ad-hoc identity `-`.
+    pub(crate) fn from_settings(
+        settings: &Settings,
+        identity: &str,
+    ) -> crate::Result<Option<SigningKey>> {
+        if identity == "-" {
+            return Ok(None);
+        }
+        if let Some(slot) = settings.osx_smartcard_slot() {
+            return Ok(Some(SigningKey::Smartcard {
+                slot: slot.to_string(),
+            }));
+        }
+        let Some(path) = settings.osx_signing_certificate() else {
+            anyhow::bail!(
+                "Signing with rcodesign requires the certificate file to be set with \
+                 `osx_signing_certificate`, or a smartcard slot with `osx_smartcard_slot`"
+            );
+        };
+        Ok(Some(SigningKey::P12 {
+            path,
+            password: std::env::var(CERTIFICATE_PASSWORD_VAR).ok(),
+        }))
+    }
+}
+
+/// Builds the `rcodesign` invocation that signs `path` (and any code nested in
+/// it) with `key`, or ad hoc if there is none.  `runtime` enables the hardened
+/// runtime, which applies to code but not to disk images.  Signatures are
+/// timestamped by the server at `timestamp_url`, or else by Apple's.
+pub(crate) fn rcodesign_command(
+    key: Option<&SigningKey>,
+    path: &Path,
+    entitlements: Option<&Path>,
+    runtime: bool,
+    timestamp_url: Option<&str>,
+) -> Command {
+    let mut command = Command::new("rcodesign");
+    command.arg("sign");
+    match key {
+        Some(SigningKey::P12 { path, password }) => {
+            command.arg("--p12-file").arg(path);
+            if let Some(password) = password {
+                command.arg("--p12-password").arg(password);
+            }
+        }
+        Some(SigningKey::Smartcard { slot }) => {
+            command
+                .args(["--smartcard-slot", slot, "--smartcard-pin-env"])
+                .arg(TOKEN_PIN_VAR);
+        }
+        None => {}
+    }
+    if runtime {
+        command.args(["--code-signature-flags", "runtime"]);
+    }
+    if let Some(url) = timestamp_url {
+        command.arg("--timestamp-url").arg(url);
+    }
+    if let Some(entitlements) = entitlements {
+        command.arg("--entitlements-xml-file").arg(entitlements);
+    }
+    command.arg(path);
+    command
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Default)]
 struct DylibInfo {
     dylibs: Vec<PathBuf>,
@@ -165,7 +550,8 @@ fn copy_binary_to_bundle(bundle_directory: &Path, settings: &Settings) -> crate:
     common::copy_file(
         settings.binary_path(),
         &dest_dir.join(settings.binary_name()),
-    )
+    )?;
+    common::copy_external_binaries(settings, &dest_dir)
 }
 trait PlistEntryFormatter {
     fn format_plist_entry(&self) -> String;
@@ -174,13 +560,163 @@ trait PlistEntryFormatter {
 impl<T: AsRef<str>> PlistEntryFormatter for T {
     fn format_plist_entry(&self) -> String {
         let input = self.as_ref();
-        input.replace("&", "&amp;")
-        // add other necessary modifications here...
+        input
+            .replace("&", "&amp;")
+            .replace("<", "&lt;")
+            .replace(">", "&gt;")
     }
 }
 
 const FRAMEWORKS_RPATH: &str = "@executable_path/../Frameworks";
 
+/// Copies the dynamic libraries that the binary loads (directly or not) from
+/// outside the system into `Contents/Frameworks`, and makes the binary and the
+/// libraries load them from there, through the `@rpath`.  Returns the number
+/// of libraries copied.
+fn copy_shared_libraries_to_bundle(
+    bundle_directory: &Path,
+    settings: &Settings,
+) -> crate::Result<i32> {
+    let dest_dir = bundle_directory.join("Frameworks");
+    let mut pending = vec![bundle_directory.join("MacOS").join(settings.binary_name())];
+    let mut copied = 0;
+    while let Some(path) = pending.pop() {
+        for dylib in DylibInfo::inspect(&path)?.dylibs {
+            if !is_bundled_dylib(&dylib) {
+                continue;
+            }
+            let name = dylib.file_name().unwrap().to_string_lossy().into_owned();
+            let install_name = format!("@rpath/{name}");
+            run_install_name_tool(&[
+                OsStr::new("-change"),
+                dylib.as_os_str(),
+                OsStr::new(&install_name),
+                path.as_os_str(),
+            ])?;
+            let dest = dest_dir.join(&name);
+            if dest.exists() {
+                continue;
+            }
+            common::copy_file(&dylib, &dest)?;
+            // Libraries from package managers are often read-only.
+            common::set_mode(&dest, 0o755)?;
+            run_install_name_tool(&[
+                OsStr::new("-id"),
+                OsStr::new(&install_name),
+                dest.as_os_str(),
+            ])?;
+            pending.push(dest);
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Returns whether a library the binary loads is to be bundled: it is not if
+/// it is part of the system, or is already found relative to the binary.
+fn is_bundled_dylib(path: &Path) -> bool {
+    !(path.starts_with("/usr/lib")
+        || path.starts_with("/System")
+        || path.to_string_lossy().starts_with('@'))
+}
+
+/// Makes the binary and the bundled libraries load the frameworks and dylibs
+/// in `Contents/Frameworks` through the `@rpath`, rather than from wherever
+/// they were linked against, and gives the bundled dylibs install names
+/// relative to it.
+fn relink_bundled_libraries(bundle_directory: &Path, settings: &Settings) -> crate::Result<()> {
+    let frameworks_dir = bundle_directory.join("Frameworks");
+    let mut bundled = Vec::new();
+    let mut objects = vec![bundle_directory.join("MacOS").join(settings.binary_name())];
+    for entry in fs::read_dir(&frameworks_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if let Some(framework) = name.strip_suffix(".framework") {
+            // The binary of the current version of the framework, which its
+            // top-level symlink points to.
+            let binary = path.join(framework);
+            if binary.is_file() {
+                objects.push(fs::canonicalize(&binary)?);
+            }
+        } else if name.ends_with(".dylib") {
+            let install_name = format!("@rpath/{name}");
+            run_install_name_tool(&[
+                OsStr::new("-id"),
+                OsStr::new(&install_name),
+                path.as_os_str(),
+            ])?;
+            objects.push(path);
+        }
+        bundled.push(name);
+    }
+    for object in &objects {
+        for dylib in DylibInfo::inspect(object)?.dylibs {
+            if let Some(install_name) = rpath_install_name(&dylib, &bundled) {
+                run_install_name_tool(&[
+                    OsStr::new("-change"),
+                    dylib.as_os_str(),
+                    OsStr::new(&install_name),
+                    object.as_os_str(),
+                ])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the install name relative to the `@rpath` of the library loaded
+/// from `path`, if it is one of the `bundled` entries of `Contents/Frameworks`
+/// (or the binary of one of its frameworks), and is not loaded through the
+/// `@rpath` already.
+fn rpath_install_name(path: &Path, bundled: &[String]) -> Option<String> {
+    if !is_bundled_dylib(path) {
+        return None;
+    }
+    let components: Vec<String> = path
+        .iter()
+        .map(|component| component.to_string_lossy().into_owned())
+        .collect();
+    // The binary of a framework is loaded from within it, e.g. from
+    // `SDL2.framework/Versions/A/SDL2`.
+    let start = components
+        .iter()
+        .rposition(|component| component.ends_with(".framework") && bundled.contains(component))
+        .or_else(|| {
+            let last = components.len().checked_sub(1)?;
+            bundled.contains(&components[last]).then_some(last)
+        })?;
+    Some(format!("@rpath/{}", components[start..].join("/")))
+}
+
+fn run_install_name_tool(args: &[&OsStr]) -> crate::Result<()> {
+    let status = Command::new("install_name_tool")
+        .args(args)
+        .status()
+        .with_context(|| "Failed to run `install_name_tool`")?;
+    if !status.success() {
+        anyhow::bail!("`install_name_tool` failed with {status}");
+    }
+    Ok(())
+}
+
+/// Merges the binaries built for each of the targets of a universal macOS
+/// binary into it with `lipo`.
+pub fn create_universal_binary(settings: &Settings) -> crate::Result<()> {
+    let binary_path = settings.binary_path();
+    if let Some(dir) = binary_path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir:?}"))?;
+    }
+    common::run_tool(
+        Command::new("lipo")
+            .arg("-create")
+            .arg("-output")
+            .arg(binary_path)
+            .args(settings.universal_macos_binary_paths()),
+        "lipo",
+    )?;
+    Ok(())
+}
+
 fn add_rpath(bundle_directory: &Path, settings: &Settings) -> crate::Result<()> {
     let bin = bundle_directory.join("MacOS").join(settings.binary_name());
 
@@ -209,16 +745,10 @@ fn create_info_plist(
     bundle_icon_file: Option<PathBuf>,
     settings: &Settings,
 ) -> crate::Result<()> {
-    let build_number = chrono::Utc::now().format("%Y%m%d.%H%M%S");
-    let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
-    write!(
-        file,
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-            <!DOCTYPE plist PUBLIC \"-//Apple Computer//DTD PLIST 1.0//EN\" \
-            \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
-            <plist version=\"1.0\">\n\
-            <dict>\n"
-    )?;
+    let build_number = build_time()?.format("%Y%m%d.%H%M%S");
+    // The keys of the dict are generated first, so that the `osx_info_plist`
+    // keys can be merged on top of them.
+    let file = &mut Vec::new();
     write!(
         file,
         "  <key>CFBundleDevelopmentRegion</key>\n  \
@@ -234,6 +764,13 @@ fn create_info_plist(
         "  <key>CFBundleExecutable</key>\n  <string>{}</string>\n",
         settings.binary_name()
     )?;
+    if !settings.file_associations().is_empty() {
+        write!(file, "  <key>CFBundleDocumentTypes</key>\n  <array>\n")?;
+        for association in settings.file_associations() {
+            write_document_type(file, association, settings)?;
+        }
+        writeln!(file, "  </array>")?;
+    }
     if let Some(path) = bundle_icon_file {
         write!(
             file,
@@ -251,6 +788,14 @@ fn create_info_plist(
         "  <key>CFBundleInfoDictionaryVersion</key>\n  \
             <string>6.0</string>\n"
     )?;
+    if !settings.localizations().is_empty() {
+        write!(file, "  <key>CFBundleLocalizations</key>\n  <array>\n")?;
+        writeln!(file, "    <string>en</string>")?;
+        for locale in settings.localizations().keys() {
+            writeln!(file, "    <string>{}</string>", locale.format_plist_entry())?;
+        }
+        writeln!(file, "  </array>")?;
+    }
     write!(
         file,
         "  <key>CFBundleName</key>\n  <string>{}</string>\n",
@@ -263,9 +808,15 @@ fn create_info_plist(
     write!(
         file,
         "  <key>CFBundleShortVersionString</key>\n  <string>{}</string>\n",
-        settings.version_string()
+        short_version_string(&settings.version_string().to_string())
     )?;
-    if !settings.osx_url_schemes().is_empty() {
+    let mut url_schemes: Vec<&String> = settings.osx_url_schemes().iter().collect();
+    for scheme in settings.url_schemes() {
+        if !url_schemes.contains(&scheme) {
+            url_schemes.push(scheme);
+        }
+    }
+    if !url_schemes.is_empty() {
         write!(
             file,
             "  <key>CFBundleURLTypes</key>\n  \
@@ -279,7 +830,7 @@ fn create_info_plist(
                        <array>\n",
             settings.bundle_name().format_plist_entry()
         )?;
-        for scheme in settings.osx_url_schemes() {
+        for scheme in url_schemes {
             writeln!(
                 file,
                 "        <string>{}</string>",
@@ -325,16 +876,257 @@ fn create_info_plist(
             copyright.format_plist_entry()
         )?;
     }
+    write_type_declarations(file, settings)?;
+    if let Some(build_info) = settings.build_info() {
+        if let Some(git_hash) = &build_info.git_hash {
+            write!(
+                file,
+                "  <key>BuildGitHash</key>\n  <string>{}</string>\n",
+                git_hash.format_plist_entry()
+            )?;
+        }
+        write!(
+            file,
+            "  <key>BuildDate</key>\n  <string>{}</string>\n",
+            build_info.build_date
+        )?;
+    }
+    if let Some(sparkle) = settings.osx_sparkle() {
+        write!(
+            file,
+            "  <key>SUFeedURL</key>\n  <string>{}</string>\n",
+            sparkle.feed_url.format_plist_entry()
+        )?;
+        write!(
+            file,
+            "  <key>SUPublicEDKey</key>\n  <string>{}</string>\n",
+            sparkle.public_key.format_plist_entry()
+        )?;
+    }
     for plist in settings.osx_info_plist_exts() {
         let plist = plist?;
         let contents = read_file(&plist)?;
         write!(file, "{:}", contents.format_plist_entry())?
     }
-    write!(file, "</dict>\n</plist>\n")?;
+    let keys = String::from_utf8_lossy(file);
+    let keys = match settings.osx_info_plist() {
+        Some(overrides) => merge_plist_keys(&keys, overrides)?,
+        None => keys.into_owned(),
+    };
+    let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
+    write!(
+        file,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <!DOCTYPE plist PUBLIC \"-//Apple Computer//DTD PLIST 1.0//EN\" \
+            \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+            <plist version=\"1.0\">\n\
+            <dict>\n"
+    )?;
+    write!(file, "{keys}</dict>\n</plist>\n")?;
     file.flush()?;
     Ok(())
 }
 
+/// Merges the given `overrides` on top of the `keys` of a top-level plist dict
+/// (each starting with a `  <key>` line): the entries of overridden keys are
+/// dropped, and the overrides are appended.
+fn merge_plist_keys(keys: &str, overrides: &toml::Table) -> crate::Result<String> {
+    let overridden: Vec<String> = overrides
+        .keys()
+        .map(|key| format!("  <key>{}</key>", key.format_plist_entry()))
+        .collect();
+    let mut merged = String::new();
+    let mut dropping = false;
+    for line in keys.lines() {
+        if line.starts_with("  <key>") {
+            dropping = overridden.iter().any(|key| line.trim_end() == key);
+        }
+        if !dropping {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    for (key, value) in overrides {
+        merged.push_str(&format!("  <key>{}</key>\n", key.format_plist_entry()));
+        write_plist_value(&mut merged, key, value, 1)?;
+    }
+    Ok(merged)
+}
+
+/// Writes an `InfoPlist.strings` file into the `<locale>.lproj` directory of
+/// each of the `localizations` that names the app, translating its name.
+/// Converts a version string to a `CFBundleShortVersionString`, which is up to
+/// three integers separated by periods (e.g. `1.2.3-beta.1` becomes `1.2.3`).
+pub(crate) fn short_version_string(version: &str) -> String {
+    let release = version.split(['-', '+']).next().unwrap_or(version);
+    let numbers: Vec<String> = release
+        .split('.')
+        .take(3)
+        .map(|part| part.parse::<u32>().unwrap_or(0).to_string())
+        .collect();
+    numbers.join(".")
+}
+
+fn create_localized_strings(resources_dir: &Path, settings: &Settings) -> crate::Result<()> {
+    for (locale, localization) in settings.localizations() {
+        let Some(name) = &localization.name else {
+            continue;
+        };
+        let path = resources_dir.join(format!("{locale}.lproj/InfoPlist.strings"));
+        let file = &mut common::create_file(&path)?;
+        let name = name
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        writeln!(file, "\"CFBundleDisplayName\" = \"{name}\";")?;
+        writeln!(file, "\"CFBundleName\" = \"{name}\";")?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+/// Writes the `CFBundleDocumentTypes` entry of a `file_associations` entry.
+fn write_document_type<W: Write>(
+    file: &mut W,
+    association: &FileAssociation,
+    settings: &Settings,
+) -> crate::Result<()> {
+    writeln!(file, "    <dict>")?;
+    write!(
+        file,
+        "      <key>CFBundleTypeExtensions</key>\n      <array>\n"
+    )?;
+    for extension in &association.extensions {
+        writeln!(
+            file,
+            "        <string>{}</string>",
+            extension.format_plist_entry()
+        )?;
+    }
+    writeln!(file, "      </array>")?;
+    if let Some(icon) = document_icon_name(association, settings) {
+        write!(
+            file,
+            "      <key>CFBundleTypeIconFile</key>\n      <string>{icon}</string>\n"
+        )?;
+    }
+    write!(
+        file,
+        "      <key>CFBundleTypeMIMETypes</key>\n      \
+             <array>\n        <string>{}</string>\n      </array>\n",
+        association.mime_type().format_plist_entry()
+    )?;
+    write!(
+        file,
+        "      <key>CFBundleTypeName</key>\n      <string>{}</string>\n",
+        association.description().format_plist_entry()
+    )?;
+    write!(
+        file,
+        "      <key>CFBundleTypeRole</key>\n      <string>{}</string>\n",
+        association.role.plist_name()
+    )?;
+    if let Some(uti) = &association.uti {
+        write!(
+            file,
+            "      <key>LSItemContentTypes</key>\n      \
+                 <array>\n        <string>{}</string>\n      </array>\n",
+            uti.format_plist_entry()
+        )?;
+    }
+    writeln!(file, "    </dict>")?;
+    Ok(())
+}
+
+/// Returns the file name of the `.icns` icon of the documents, which is copied
+/// into `Resources`.
+fn document_icon_name(association: &FileAssociation, settings: &Settings) -> Option<String> {
+    let icon = settings.file_association_icon(association, &["icns"])?;
+    Some(icon.file_name()?.to_string_lossy().format_plist_entry())
+}
+
+/// Writes the `UTExportedTypeDeclarations` and `UTImportedTypeDeclarations`
+/// of the `file_associations` that have a `uti`.
+fn write_type_declarations<W: Write>(file: &mut W, settings: &Settings) -> crate::Result<()> {
+    for (key, imported) in [
+        ("UTExportedTypeDeclarations", false),
+        ("UTImportedTypeDeclarations", true),
+    ] {
+        let declared: Vec<_> = settings
+            .file_associations()
+            .iter()
+            .filter(|association| association.uti.is_some() && association.uti_imported == imported)
+            .collect();
+        if declared.is_empty() {
+            continue;
+        }
+        write!(file, "  <key>{key}</key>\n  <array>\n")?;
+        for association in declared {
+            write_type_declaration(file, association, settings)?;
+        }
+        writeln!(file, "  </array>")?;
+    }
+    Ok(())
+}
+
+fn write_type_declaration<W: Write>(
+    file: &mut W,
+    association: &FileAssociation,
+    settings: &Settings,
+) -> crate::Result<()> {
+    writeln!(file, "    <dict>")?;
+    write!(file, "      <key>UTTypeConformsTo</key>\n      <array>\n")?;
+    for conforms_to in association.uti_conforms_to() {
+        writeln!(
+            file,
+            "        <string>{}</string>",
+            conforms_to.format_plist_entry()
+        )?;
+    }
+    writeln!(file, "      </array>")?;
+    write!(
+        file,
+        "      <key>UTTypeDescription</key>\n      <string>{}</string>\n",
+        association.description().format_plist_entry()
+    )?;
+    if let Some(icon) = document_icon_name(association, settings) {
+        write!(
+            file,
+            "      <key>UTTypeIconFile</key>\n      <string>{icon}</string>\n"
+        )?;
+    }
+    write!(
+        file,
+        "      <key>UTTypeIdentifier</key>\n      <string>{}</string>\n",
+        association
+            .uti
+            .as_deref()
+            .unwrap_or_default()
+            .format_plist_entry()
+    )?;
+    write!(
+        file,
+        "      <key>UTTypeTagSpecification</key>\n      <dict>\n        \
+             <key>public.filename-extension</key>\n        <array>\n"
+    )?;
+    for extension in &association.extensions {
+        writeln!(
+            file,
+            "          <string>{}</string>",
+            extension.format_plist_entry()
+        )?;
+    }
+    write!(
+        file,
+        "        </array>\n        \
+             <key>public.mime-type</key>\n        <string>{}</string>\n      \
+         </dict>\n",
+        association.mime_type().format_plist_entry()
+    )?;
+    writeln!(file, "    </dict>")?;
+    Ok(())
+}
+
 fn copy_framework_from(dest_dir: &Path, framework: &str, src_dir: &Path) -> crate::Result<bool> {
     let src_name = format!("{framework}.framework");
     let src_path = src_dir.join(&src_name);
@@ -490,7 +1282,7 @@ fn create_icns_file(
     if !family.is_empty() {
         fs::create_dir_all(resources_dir)?;
         let mut dest_path = resources_dir.clone();
-        dest_path.push(settings.bundle_name());
+        dest_path.push(settings.bundle_file_name());
         dest_path.set_extension("icns");
         let icns_file = BufWriter::new(File::create(&dest_path)?);
         family.write(icns_file)?;
@@ -514,3 +1306,439 @@ fn make_icns_image(img: image::DynamicImage) -> io::Result<icns::Image> {
     };
     icns::Image::from_data(pixel_format, img.width(), img.height(), img.into_bytes())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_version_strings() {
+        assert_eq!(short_version_string("1.2.3"), "1.2.3");
+        assert_eq!(short_version_string("1.2"), "1.2");
+        assert_eq!(short_version_string("1.2.3-beta.1"), "1.2.3");
+        assert_eq!(short_version_string("1.2.3+5.gabc1234"), "1.2.3");
+        assert_eq!(short_version_string("1.2.3.4"), "1.2.3");
+    }
+
+    #[test]
+    fn info_plist_display_name() {
+        let settings = Settings::for_test("name = \"My Cool App\"");
+        assert_eq!(settings.bundle_file_name(), "My Cool App");
+        let tmp = tempfile::tempdir().unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains("<key>CFBundleDisplayName</key>\n  <string>My Cool App</string>\n"));
+    }
+
+    #[test]
+    fn localized_strings() {
+        let settings = Settings::for_test(
+            r#"
+            name = "My App"
+            [localizations.fr]
+            name = "Mon \"App\""
+            [localizations.de]
+            short_description = "Eine App"
+            "#,
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        create_localized_strings(tmp.path(), &settings).unwrap();
+        let strings =
+            std::fs::read_to_string(tmp.path().join("fr.lproj/InfoPlist.strings")).unwrap();
+        assert_eq!(
+            strings,
+            "\"CFBundleDisplayName\" = \"Mon \\\"App\\\"\";\n\
+             \"CFBundleName\" = \"Mon \\\"App\\\"\";\n"
+        );
+        assert!(!tmp.path().join("de.lproj").exists());
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains(
+            "  <key>CFBundleLocalizations</key>\n  <array>\n    <string>en</string>\n    \
+             <string>de</string>\n    <string>fr</string>\n  </array>\n"
+        ));
+    }
+
+    #[test]
+    fn info_plist_merged_keys() {
+        let settings = Settings::for_test(
+            r#"
+            name = "My App"
+            [osx_info_plist]
+            LSUIElement = true
+            NSHighResolutionCapable = false
+            NSCameraUsageDescription = "Scans <QR> codes"
+            LSEnvironment = { RUST_LOG = "info" }
+            "#,
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(!plist.contains("<key>NSHighResolutionCapable</key>\n  <true/>"));
+        assert!(plist.contains("<key>LSRequiresCarbon</key>\n  <true/>\n"));
+        assert!(plist.ends_with(
+            "  <key>LSEnvironment</key>\n  <dict>\n    <key>RUST_LOG</key>\n    \
+             <string>info</string>\n  </dict>\n  \
+             <key>LSUIElement</key>\n  <true/>\n  \
+             <key>NSCameraUsageDescription</key>\n  <string>Scans &lt;QR&gt; codes</string>\n  \
+             <key>NSHighResolutionCapable</key>\n  <false/>\n\
+             </dict>\n</plist>\n"
+        ));
+    }
+
+    #[test]
+    fn info_plist_url_schemes() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             osx_url_schemes = [\"com.example.app\", \"myapp\"]\n\
+             url_schemes = [\"myapp\", \"myapp-auth\"]\n",
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains(
+            "      <key>CFBundleURLSchemes</key>\n      <array>\n        \
+             <string>com.example.app</string>\n        <string>myapp</string>\n        \
+             <string>myapp-auth</string>\n      </array>\n"
+        ));
+    }
+
+    #[test]
+    fn info_plist_document_types() {
+        let settings = Settings::for_test(
+            r#"
+            file_associations = [
+                { extensions = ["md", "markdown"], mime_type = "text/markdown", description = "Markdown & text" },
+                { extensions = ["foo"], role = "viewer", icon = ["foo.png", "foo.icns"] },
+            ]
+            "#,
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains(
+            "  <key>CFBundleDocumentTypes</key>\n  <array>\n    <dict>\n      \
+             <key>CFBundleTypeExtensions</key>\n      <array>\n        \
+             <string>md</string>\n        <string>markdown</string>\n      </array>\n      \
+             <key>CFBundleTypeMIMETypes</key>\n      <array>\n        \
+             <string>text/markdown</string>\n      </array>\n      \
+             <key>CFBundleTypeName</key>\n      <string>Markdown &amp; text</string>\n      \
+             <key>CFBundleTypeRole</key>\n      <string>Editor</string>\n    </dict>\n"
+        ));
+        assert!(plist.contains(
+            "      <key>CFBundleTypeIconFile</key>\n      <string>foo.icns</string>\n      \
+             <key>CFBundleTypeMIMETypes</key>\n      <array>\n        \
+             <string>application/x-foo</string>\n      </array>\n      \
+             <key>CFBundleTypeName</key>\n      <string>FOO document</string>\n      \
+             <key>CFBundleTypeRole</key>\n      <string>Viewer</string>\n"
+        ));
+        assert!(!plist.contains("TypeDeclarations"));
+    }
+
+    #[test]
+    fn info_plist_type_declarations() {
+        let settings = Settings::for_test(
+            r#"
+            file_associations = [
+                { extensions = ["foo"], uti = "com.example.foo", icon = ["foo.icns"] },
+                { extensions = ["md"], mime_type = "text/markdown", uti = "net.daringfireball.markdown", uti_conforms_to = ["public.plain-text"], uti_imported = true },
+                { extensions = ["txt"] },
+            ]
+            "#,
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains(
+            "      <key>CFBundleTypeRole</key>\n      <string>Editor</string>\n      \
+             <key>LSItemContentTypes</key>\n      <array>\n        \
+             <string>com.example.foo</string>\n      </array>\n"
+        ));
+        assert!(plist.contains(
+            "  <key>UTExportedTypeDeclarations</key>\n  <array>\n    <dict>\n      \
+             <key>UTTypeConformsTo</key>\n      <array>\n        \
+             <string>public.data</string>\n      </array>\n      \
+             <key>UTTypeDescription</key>\n      <string>FOO document</string>\n      \
+             <key>UTTypeIconFile</key>\n      <string>foo.icns</string>\n      \
+             <key>UTTypeIdentifier</key>\n      <string>com.example.foo</string>\n      \
+             <key>UTTypeTagSpecification</key>\n      <dict>\n        \
+             <key>public.filename-extension</key>\n        <array>\n          \
+             <string>foo</string>\n        </array>\n        \
+             <key>public.mime-type</key>\n        <string>application/x-foo</string>\n      \
+             </dict>\n    </dict>\n  </array>\n  <key>UTImportedTypeDeclarations</key>\n"
+        ));
+        assert!(plist.contains(
+            "        <string>public.plain-text</string>\n      </array>\n      \
+             <key>UTTypeDescription</key>\n      <string>MD document</string>\n      \
+             <key>UTTypeIdentifier</key>\n      <string>net.daringfireball.markdown</string>\n"
+        ));
+    }
+    #[test]
+    fn rpath_install_names() {
+        let bundled = ["SDL2.framework".to_string(), "libtorch.dylib".to_string()];
+        let install_name = |path: &str| rpath_install_name(Path::new(path), &bundled);
+        assert_eq!(
+            install_name("/Library/Frameworks/SDL2.framework/Versions/A/SDL2").as_deref(),
+            Some("@rpath/SDL2.framework/Versions/A/SDL2")
+        );
+        assert_eq!(
+            install_name("/opt/libtorch/lib/libtorch.dylib").as_deref(),
+            Some("@rpath/libtorch.dylib")
+        );
+        assert_eq!(install_name("@rpath/libtorch.dylib"), None);
+        assert_eq!(install_name("/usr/lib/libSystem.B.dylib"), None);
+        assert_eq!(install_name("/opt/homebrew/lib/libpng.dylib"), None);
+    }
+
+    #[test]
+    fn codesign_arguments() {
+        let args = |command: Command| {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        let path = Path::new("Foo.app");
+        assert_eq!(
+            args(codesign_command("-", path, None, true, None)),
+            [
+                "--force",
+                "--deep",
+                "--options",
+                "runtime",
+                "--sign",
+                "-",
+                "Foo.app"
+            ]
+        );
+        assert_eq!(
+            args(codesign_command(
+                "Developer ID Application: Example",
+                path,
+                Some(Path::new("app.entitlements")),
+                true,
+                None
+            )),
+            [
+                "--force",
+                "--deep",
+                "--options",
+                "runtime",
+                "--sign",
+                "Developer ID Application: Example",
+                "--timestamp",
+                "--entitlements",
+                "app.entitlements",
+                "Foo.app"
+            ]
+        );
+    }
+
+    #[test]
+    fn codesign_without_hardened_runtime() {
+        let command = codesign_command("-", Path::new("Foo.app"), None, false, None);
+        assert!(!command.get_args().any(|arg| arg == "runtime"));
+    }
+
+    #[test]
+    fn codesign_timestamp_url() {
+        let command = codesign_command(
+            "Developer ID Application: Example",
+            Path::new("Foo.app"),
+            None,
+            true,
+            Some("http://timestamp.example"),
+        );
+        assert!(
+            command
+                .get_args()
+                .any(|arg| arg == "--timestamp=http://timestamp.example")
+        );
+        let command = rcodesign_command(
+            None,
+            Path::new("Foo.app"),
+            None,
+            true,
+            Some("http://timestamp.example"),
+        );
+        let args: Vec<_> = command.get_args().collect();
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["--timestamp-url", "http://timestamp.example"])
+        );
+    }
+
+    #[test]
+    fn entitlements_from_keys() {
+        let settings = Settings::for_test(
+            r#"
+            [osx_entitlements]
+            "com.apple.security.cs.allow-jit" = true
+            "com.apple.security.device.camera" = true
+            "com.apple.security.application-groups" = ["TEAMID.group"]
+            "#,
+        );
+        let Some(OsxEntitlements::Keys(keys)) = settings.osx_entitlements() else {
+            panic!("expected entitlement keys");
+        };
+        let plist = entitlements_plist(keys).unwrap();
+        assert!(plist.ends_with(
+            "<plist version=\"1.0\">\n\
+             <dict>\n  \
+               <key>com.apple.security.application-groups</key>\n  \
+               <array>\n    \
+                 <string>TEAMID.group</string>\n  \
+               </array>\n  \
+               <key>com.apple.security.cs.allow-jit</key>\n  \
+               <true/>\n  \
+               <key>com.apple.security.device.camera</key>\n  \
+               <true/>\n\
+             </dict>\n\
+             </plist>\n"
+        ));
+
+        let settings = Settings::for_test("osx_entitlements = \"app.entitlements\"");
+        assert!(matches!(
+            settings.osx_entitlements(),
+            Some(OsxEntitlements::File(path)) if path.ends_with("app.entitlements")
+        ));
+    }
+
+    #[test]
+    fn sandbox_entitlements() {
+        let settings = Settings::for_test(
+            r#"
+            [osx_sandbox]
+            network_client = true
+            files = ["user-selected.read-write", "pictures.read-only"]
+            devices = ["camera", "print"]
+            temporary_exceptions = { home_relative_path_read_only = ["/Library/Fonts/"] }
+            [osx_entitlements]
+            "com.apple.security.device.camera" = false
+            "com.apple.security.cs.allow-jit" = true
+            "#,
+        );
+        let Some(Entitlements::Keys(keys)) = entitlements(&settings).unwrap() else {
+            panic!("expected entitlement keys");
+        };
+        let granted: Vec<_> = keys
+            .iter()
+            .filter(|(_, value)| value.as_bool() == Some(true))
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(
+            granted,
+            [
+                "com.apple.security.app-sandbox",
+                "com.apple.security.assets.pictures.read-only",
+                "com.apple.security.cs.allow-jit",
+                "com.apple.security.files.user-selected.read-write",
+                "com.apple.security.network.client",
+                "com.apple.security.print",
+            ]
+        );
+        assert_eq!(
+            keys["com.apple.security.device.camera"].as_bool(),
+            Some(false)
+        );
+        assert_eq!(
+            keys["com.apple.security.temporary-exception.files.home-relative-path.read-only"]
+                .as_array()
+                .unwrap()[0]
+                .as_str(),
+            Some("/Library/Fonts/")
+        );
+
+        let settings = Settings::for_test(
+            "osx_entitlements = \"app.entitlements\"\n[osx_sandbox]\nnetwork_client = true\n",
+        );
+        assert!(entitlements(&settings).is_err());
+        assert!(entitlements(&Settings::for_test("")).unwrap().is_none());
+    }
+
+    #[test]
+    fn sandbox_rejects_unknown_keys() {
+        for sandbox in [
+            "network_clients = true",
+            "files = [\"documents.read-write\"]",
+            "devices = [\"microphone\"]",
+            "temporary_exceptions = { absolute_path = [\"/\"] }",
+        ] {
+            assert!(
+                toml::from_str::<super::super::settings::SandboxSettings>(sandbox).is_err(),
+                "{sandbox}"
+            );
+        }
+    }
+
+    #[test]
+    fn rcodesign_arguments() {
+        let args = |command: Command| {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        let path = Path::new("Foo.app");
+        assert_eq!(
+            args(rcodesign_command(None, path, None, true, None)),
+            ["sign", "--code-signature-flags", "runtime", "Foo.app"]
+        );
+        let certificate = SigningKey::P12 {
+            path: PathBuf::from("cert.p12"),
+            password: Some("secret".to_string()),
+        };
+        assert_eq!(
+            args(rcodesign_command(
+                Some(&certificate),
+                Path::new("Foo.dmg"),
+                None,
+                false,
+                None
+            )),
+            [
+                "sign",
+                "--p12-file",
+                "cert.p12",
+                "--p12-password",
+                "secret",
+                "Foo.dmg"
+            ]
+        );
+        let smartcard = SigningKey::Smartcard {
+            slot: "9c".to_string(),
+        };
+        assert_eq!(
+            args(rcodesign_command(Some(&smartcard), path, None, false, None)),
+            [
+                "sign",
+                "--smartcard-slot",
+                "9c",
+                "--smartcard-pin-env",
+                "CARGO_BUNDLE_TOKEN_PIN",
+                "Foo.app"
+            ]
+        );
+        let settings = Settings::for_test("osx_smartcard_slot = \"9c\"\n");
+        assert!(matches!(
+            SigningKey::from_settings(&settings, "Developer ID").unwrap(),
+            Some(SigningKey::Smartcard { slot }) if slot == "9c"
+        ));
+        let settings = Settings::for_test("osx_signing_tool = \"rcodesign\"\n");
+        assert_eq!(settings.osx_signing_tool(), OsxSigningTool::Rcodesign);
+        assert!(SigningKey::from_settings(&settings, "-").unwrap().is_none());
+        assert!(SigningKey::from_settings(&settings, "Developer ID").is_err());
+    }
+
+    #[test]
+    fn bundled_dylibs() {
+        assert!(is_bundled_dylib(Path::new(
+            "/opt/homebrew/opt/openssl@3/lib/libssl.3.dylib"
+        )));
+        assert!(!is_bundled_dylib(Path::new("/usr/lib/libSystem.B.dylib")));
+        assert!(!is_bundled_dylib(Path::new(
+            "/System/Library/Frameworks/AppKit.framework/Versions/C/AppKit"
+        )));
+        assert!(!is_bundled_dylib(Path::new("@rpath/libfoo.dylib")));
+    }
+}
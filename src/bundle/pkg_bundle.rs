@@ -0,0 +1,134 @@
+// A macOS installer package is built in two steps from the OSX app bundle:
+// `pkgbuild` wraps the `.app` in a component package, and `productbuild`
+// turns that into a distributable product archive:
+//
+// bundle/pkg/foobar_1.2.3_x86_64/
+//     foobar.pkg        # The component package built by `pkgbuild`
+//
+// The product archive is written to `bundle/pkg/foobar_1.2.3_x86_64.pkg`.
+
+use super::common;
+use super::osx_bundle;
+use crate::Settings;
+use anyhow::Context;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DEFAULT_INSTALL_LOCATION: &str = "/Applications";
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    if settings.bundle_identifier().is_empty() {
+        anyhow::bail!("pkg installers require a bundle `identifier`");
+    }
+    if settings.notarize() {
+        common::print_warning(
+            "pkg installers are not notarized, as they must be signed with a Developer ID \
+             Installer identity first.",
+        )?;
+    }
+    let app_bundle_path = &osx_bundle::build_app_bundle(settings)?;
+
+    let package_base_name = format!(
+        "{}_{}_{}",
+        settings.binary_name(),
+        settings.version_string(),
+        settings.binary_arch()
+    );
+    let package_name = format!("{package_base_name}.pkg");
+    common::print_bundling(&package_name)?;
+
+    let base_dir = settings.project_out_directory().join("bundle/pkg");
+    let component_dir = base_dir.join(&package_base_name);
+    if component_dir.exists() {
+        fs::remove_dir_all(&component_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    fs::create_dir_all(&component_dir)
+        .with_context(|| format!("Failed to create {component_dir:?}"))?;
+    let component_path = component_dir.join(format!("{}.pkg", settings.binary_name()));
+    let package_path = base_dir.join(&package_name);
+
+    common::run_tool(
+        &mut pkgbuild_command(settings, app_bundle_path, &component_path),
+        "pkgbuild",
+    )?;
+    common::run_tool(
+        &mut productbuild_command(settings, &component_path, &package_path),
+        "productbuild",
+    )?;
+    Ok(vec![package_path])
+}
+
+/// Builds the `pkgbuild` command that wraps the app bundle in a component
+/// package.
+fn pkgbuild_command(settings: &Settings, app_bundle_path: &Path, component_path: &Path) -> Command {
+    let install_location = settings
+        .osx_pkg_install_location()
+        .unwrap_or(DEFAULT_INSTALL_LOCATION);
+    let mut command = Command::new("pkgbuild");
+    command
+        .arg("--component")
+        .arg(app_bundle_path)
+        .arg("--install-location")
+        .arg(install_location)
+        .arg("--identifier")
+        .arg(settings.bundle_identifier().as_ref())
+        .arg("--version")
+        .arg(settings.version_string().to_string())
+        .arg(component_path);
+    command
+}
+
+/// Builds the `productbuild` command that turns the component package into a
+/// product archive.
+fn productbuild_command(
+    settings: &Settings,
+    component_path: &Path,
+    package_path: &Path,
+) -> Command {
+    let mut command = Command::new("productbuild");
+    command
+        .arg("--identifier")
+        .arg(settings.bundle_identifier().as_ref())
+        .arg("--version")
+        .arg(settings.version_string().to_string())
+        .arg("--package")
+        .arg(component_path)
+        .arg(package_path);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn pkgbuild_command_args() {
+        let settings = Settings::for_test(
+            "identifier = \"com.example.App\"\n\
+             osx_pkg_install_location = \"/Applications/Utilities\"\n",
+        );
+        let command = pkgbuild_command(
+            &settings,
+            Path::new("example.app"),
+            Path::new("example.pkg"),
+        );
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "--component",
+                "example.app",
+                "--install-location",
+                "/Applications/Utilities",
+                "--identifier",
+                "com.example.App",
+                "--version",
+                "1.0.0",
+                "example.pkg",
+            ]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+// When the `sbom` setting is set, a software bill of materials listing the
+// package and the crates it links (its normal dependencies, transitively, as
+// resolved by `cargo metadata`) is embedded into the bundle, next to the
+// build info:
+//
+// sbom.cdx.json       # CycloneDX 1.5, with `sbom = "cyclonedx"`
+// sbom.spdx.json      # SPDX 2.3, with `sbom = "spdx"`
+//
+// Build and dev dependencies are left out, as they are not shipped.
+
+use super::build_info::build_time;
+use super::common;
+use super::settings::SbomFormat;
+use cargo_metadata::{DependencyKind, Metadata, Package, PackageId};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::Path;
+
+/// A software bill of materials for the package being bundled.
+#[derive(Clone, Debug)]
+pub struct Sbom {
+    format: SbomFormat,
+    json: String,
+}
+
+impl Sbom {
+    /// Generates the bill of materials for `package` from the dependency graph
+    /// in `metadata`.
+    pub fn generate(
+        metadata: &Metadata,
+        package: &Package,
+        format: SbomFormat,
+    ) -> crate::Result<Sbom> {
+        let created = build_time()?.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let graph = DependencyGraph::resolve(metadata, package);
+        let json = match format {
+            SbomFormat::CycloneDx => cyclonedx(&graph, &created),
+            SbomFormat::Spdx => spdx(&graph, &created),
+        };
+        Ok(Sbom {
+            format,
+            json: serde_json::to_string_pretty(&json)? + "\n",
+        })
+    }
+
+    /// Returns the name of the file the bill of materials is written to.
+    pub const fn file_name(&self) -> &'static str {
+        match self.format {
+            SbomFormat::CycloneDx => "sbom.cdx.json",
+            SbomFormat::Spdx => "sbom.spdx.json",
+        }
+    }
+
+    pub fn to_json(&self) -> &str {
+        &self.json
+    }
+
+    /// Writes the bill of materials into the given directory, creating it if
+    /// needed.
+    pub fn write_json(&self, dir: &Path) -> crate::Result<()> {
+        let mut file = common::create_file(&dir.join(self.file_name()))?;
+        file.write_all(self.json.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// The package being bundled and the crates it depends on.
+struct DependencyGraph<'a> {
+    root: &'a Package,
+    /// The dependencies, sorted by id.
+    packages: Vec<&'a Package>,
+    /// The direct dependencies of the root and of each dependency.
+    edges: BTreeMap<&'a PackageId, BTreeSet<&'a PackageId>>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    fn resolve(metadata: &'a Metadata, root: &'a Package) -> DependencyGraph<'a> {
+        let nodes: BTreeMap<_, _> = metadata
+            .resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .map(|node| (&node.id, node))
+            .collect();
+        let mut edges = BTreeMap::new();
+        let mut pending = vec![&root.id];
+        while let Some(id) = pending.pop() {
+            if edges.contains_key(id) {
+                continue;
+            }
+            let deps: BTreeSet<_> = nodes
+                .get(id)
+                .into_iter()
+                .flat_map(|node| &node.deps)
+                .filter(|dep| {
+                    dep.dep_kinds
+                        .iter()
+                        .any(|info| info.kind == DependencyKind::Normal)
+                })
+                .map(|dep| &dep.pkg)
+                .collect();
+            pending.extend(deps.iter().copied());
+            edges.insert(id, deps);
+        }
+        let packages = metadata
+            .packages
+            .iter()
+            .filter(|package| package.id != root.id && edges.contains_key(&package.id))
+            .map(|package| (&package.id, package))
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .collect();
+        DependencyGraph {
+            root,
+            packages,
+            edges,
+        }
+    }
+
+    fn package(&self, id: &PackageId) -> Option<&'a Package> {
+        std::iter::once(self.root)
+            .chain(self.packages.iter().copied())
+            .find(|package| package.id == *id)
+    }
+}
+
+/// Returns the package URL of a crate, e.g. `pkg:cargo/serde@1.0.0`.
+fn purl(package: &Package) -> String {
+    format!("pkg:cargo/{}@{}", package.name, package.version)
+}
+
+fn cyclonedx(graph: &DependencyGraph, created: &str) -> Value {
+    let component = |package: &Package, kind: &str| {
+        let mut component = json!({
+            "type": kind,
+            "bom-ref": purl(package),
+            "name": package.name.as_str(),
+            "version": package.version.to_string(),
+            "purl": purl(package),
+        });
+        if let Some(license) = &package.license {
+            component["licenses"] = json!([{ "expression": license }]);
+        }
+        if let Some(description) = &package.description {
+            component["description"] = json!(description.trim());
+        }
+        component
+    };
+    let dependencies: Vec<_> = graph
+        .edges
+        .iter()
+        .filter_map(|(id, deps)| {
+            let depends_on: Vec<_> = deps
+                .iter()
+                .filter_map(|dep| graph.package(dep).map(purl))
+                .collect();
+            Some(json!({ "ref": purl(graph.package(id)?), "dependsOn": depends_on }))
+        })
+        .collect();
+    let root = graph.root;
+    let serial = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, purl(root).as_bytes());
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{serial}"),
+        "version": 1,
+        "metadata": {
+            "timestamp": created,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "cargo-bundle",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": component(root, "application"),
+        },
+        "components": graph
+            .packages
+            .iter()
+            .map(|package| component(package, "library"))
+            .collect::<Vec<_>>(),
+        "dependencies": dependencies,
+    })
+}
+
+/// Returns the SPDX identifier of a crate, which may only contain letters,
+/// digits, `.` and `-`.
+fn spdx_id(package: &Package) -> String {
+    let id = format!("{}-{}", package.name, package.version);
+    let id: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("SPDXRef-Package-{id}")
+}
+
+fn spdx(graph: &DependencyGraph, created: &str) -> Value {
+    let package = |package: &Package| {
+        let download_location = if package.source.as_ref().is_some_and(|s| s.is_crates_io()) {
+            format!(
+                "https://crates.io/api/v1/crates/{}/{}/download",
+                package.name, package.version
+            )
+        } else {
+            "NOASSERTION".to_string()
+        };
+        json!({
+            "SPDXID": spdx_id(package),
+            "name": package.name.as_str(),
+            "versionInfo": package.version.to_string(),
+            "downloadLocation": download_location,
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": package.license.as_deref().unwrap_or("NOASSERTION"),
+            "copyrightText": "NOASSERTION",
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": purl(package),
+            }],
+        })
+    };
+    let root = graph.root;
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": spdx_id(root),
+    })];
+    for (id, deps) in &graph.edges {
+        let Some(package) = graph.package(id) else {
+            continue;
+        };
+        for dep in deps.iter().filter_map(|dep| graph.package(dep)) {
+            relationships.push(json!({
+                "spdxElementId": spdx_id(package),
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": spdx_id(dep),
+            }));
+        }
+    }
+    let name = format!("{}-{}", root.name, root.version);
+    let namespace = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, purl(root).as_bytes());
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{name}-{namespace}"),
+        "creationInfo": {
+            "created": created,
+            "creators": [format!("Tool: cargo-bundle-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": std::iter::once(root)
+            .chain(graph.packages.iter().copied())
+            .map(package)
+            .collect::<Vec<_>>(),
+        "relationships": relationships,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A root crate depending on `a` (which depends on `b`), with `c` only as
+    /// a build dependency.
+    fn metadata() -> Metadata {
+        let package = |name: &str, license: Option<&str>| {
+            json!({
+                "name": name,
+                "version": "1.0.0",
+                "id": format!("{name} 1.0.0"),
+                "license": license,
+                "source": if name == "root" { None } else {
+                    Some("registry+https://github.com/rust-lang/crates.io-index")
+                },
+                "dependencies": [],
+                "targets": [],
+                "features": {},
+                "manifest_path": format!("/{name}/Cargo.toml"),
+            })
+        };
+        let dep = |name: &str, kind: Option<&str>| {
+            json!({
+                "name": name,
+                "pkg": format!("{name} 1.0.0"),
+                "dep_kinds": [{ "kind": kind, "target": null }],
+            })
+        };
+        let node = |name: &str, deps: Vec<Value>| {
+            json!({
+                "id": format!("{name} 1.0.0"),
+                "deps": deps,
+                "dependencies": [],
+                "features": [],
+            })
+        };
+        serde_json::from_value(json!({
+            "packages": [
+                package("root", Some("MIT")),
+                package("a", Some("MIT OR Apache-2.0")),
+                package("b", None),
+                package("c", None),
+            ],
+            "workspace_members": ["root 1.0.0"],
+            "resolve": {
+                "nodes": [
+                    node("root", vec![dep("a", None), dep("c", Some("build"))]),
+                    node("a", vec![dep("b", None)]),
+                    node("b", vec![]),
+                    node("c", vec![]),
+                ],
+                "root": "root 1.0.0",
+            },
+            "target_directory": "/target",
+            "version": 1,
+            "workspace_root": "/",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cyclonedx_components() {
+        let metadata = metadata();
+        let graph = DependencyGraph::resolve(&metadata, &metadata.packages[0]);
+        let bom = cyclonedx(&graph, "2024-01-01T00:00:00Z");
+        assert_eq!(bom["metadata"]["component"]["purl"], "pkg:cargo/root@1.0.0");
+        let names: Vec<_> = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|component| component["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(
+            bom["components"][0]["licenses"],
+            json!([{ "expression": "MIT OR Apache-2.0" }])
+        );
+        assert!(bom["dependencies"].as_array().unwrap().contains(&json!({
+            "ref": "pkg:cargo/a@1.0.0",
+            "dependsOn": ["pkg:cargo/b@1.0.0"],
+        })));
+    }
+
+    #[test]
+    fn spdx_packages() {
+        let metadata = metadata();
+        let graph = DependencyGraph::resolve(&metadata, &metadata.packages[0]);
+        let document = spdx(&graph, "2024-01-01T00:00:00Z");
+        assert_eq!(document["packages"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            document["packages"][1]["downloadLocation"],
+            "https://crates.io/api/v1/crates/a/1.0.0/download"
+        );
+        assert_eq!(document["packages"][2]["licenseDeclared"], "NOASSERTION");
+        assert!(
+            document["relationships"]
+                .as_array()
+                .unwrap()
+                .contains(&json!({
+                    "spdxElementId": "SPDXRef-Package-root-1.0.0",
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": "SPDXRef-Package-a-1.0.0",
+                }))
+        );
+    }
+}
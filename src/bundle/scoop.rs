@@ -0,0 +1,154 @@
+// A Scoop manifest is a JSON file describing where to download an app from,
+// how to verify the download, and which executables and shortcuts to create
+// once it has been extracted.  When `scoop_url` is set, we write a manifest
+// next to each Windows artifact that Scoop can extract:
+//
+// bundle/msi/foobar.msi
+// bundle/msi/foobar.json      # Installs the files the MSI would install
+// bundle/archive/foobar-1.2.3-x86_64-pc-windows-msvc.zip
+// bundle/archive/foobar-1.2.3-x86_64-pc-windows-msvc.json
+//
+// The generated manifest can be committed to a bucket as-is, once the artifact
+// has been uploaded to the URL it refers to.
+
+use super::{archive_bundle, common};
+use crate::Settings;
+use anyhow::Context;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct ScoopManifest {
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    url: String,
+    hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extract_dir: Option<String>,
+    bin: String,
+    shortcuts: Vec<[String; 2]>,
+}
+
+/// Writes a manifest for each of the `paths` that Scoop can install, and
+/// returns the paths of the written files.
+pub fn generate_manifests(
+    settings: &Settings,
+    url_template: &str,
+    paths: &[PathBuf],
+) -> crate::Result<Vec<PathBuf>> {
+    let mut manifests = Vec::new();
+    for path in paths {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let extract_dir = if file_name.ends_with(".msi") {
+            // `lessmsi`, which Scoop extracts MSIs with, puts the contents of
+            // the Program Files folder in `PFiles`.
+            format!("PFiles\\{}", settings.bundle_file_name())
+        } else if file_name.ends_with(".zip") {
+            archive_bundle::top_level_dir(settings)?
+        } else {
+            continue;
+        };
+        let url = download_url(settings, url_template, &file_name)?;
+        let manifest = ScoopManifest {
+            hash: common::sha256_hex(path)?,
+            extract_dir: Some(extract_dir),
+            ..generate_manifest(settings, url)
+        };
+        let manifest_path = path.with_extension("json");
+        common::print_bundling(&manifest_path.file_name().unwrap().to_string_lossy())?;
+        let json = serde_json::to_string_pretty(&manifest)? + "\n";
+        std::fs::write(&manifest_path, json)
+            .with_context(|| format!("Failed to write {manifest_path:?}"))?;
+        manifests.push(manifest_path);
+    }
+    Ok(manifests)
+}
+
+/// Expands the `scoop_url` template for the artifact named `file_name`.
+fn download_url(settings: &Settings, url_template: &str, file_name: &str) -> crate::Result<String> {
+    let version = settings.version_string().to_string();
+    let tokens = [
+        ("name", settings.binary_name()),
+        ("version", version.as_str()),
+        ("file", file_name),
+    ];
+    common::expand_template(url_template, &tokens).context("Failed to expand `scoop_url`")
+}
+
+/// Generates the manifest for an artifact downloaded from `url`, leaving its
+/// hash and extraction directory to be filled in.
+fn generate_manifest(settings: &Settings, url: String) -> ScoopManifest {
+    let description = settings.short_description().trim();
+    let exe_name = settings
+        .binary_path()
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    ScoopManifest {
+        version: settings.version_string().to_string(),
+        description: (!description.is_empty()).then(|| description.to_string()),
+        homepage: (!settings.homepage_url().is_empty())
+            .then(|| settings.homepage_url().to_string()),
+        license: settings.license().map(str::to_string),
+        url,
+        hash: String::new(),
+        extract_dir: None,
+        bin: exe_name.clone(),
+        shortcuts: vec![[exe_name, settings.bundle_name().to_string()]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("src")).unwrap();
+        std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"example\"\nversion = \"1.0.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nname = \"My App\"\nidentifier = \"com.example.app\"\n\
+             short_description = \"Does things\"\n",
+        )
+        .unwrap();
+        let cli = <crate::Cli as clap::Parser>::parse_from([
+            "cargo-bundle",
+            "--target",
+            "x86_64-pc-windows-msvc",
+            "--format",
+            "msi",
+        ]);
+        let settings = Settings::all(tmp.path(), &cli).unwrap().remove(0);
+        let url = download_url(
+            &settings,
+            "https://example.com/v{version}/{file}",
+            "example.msi",
+        )
+        .unwrap();
+        let manifest = ScoopManifest {
+            hash: "abc123".to_string(),
+            ..generate_manifest(&settings, url)
+        };
+        let json: serde_json::Value = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": "1.0.0",
+                "description": "Does things",
+                "url": "https://example.com/v1.0.0/example.msi",
+                "hash": "abc123",
+                "bin": "example.exe",
+                "shortcuts": [["example.exe", "My App"]],
+            })
+        );
+    }
+}
@@ -1,12 +1,15 @@
+use super::build_info::BuildInfo;
 use super::category::AppCategory;
-use super::common::print_warning;
+use super::common::{print_note, print_warning};
+use super::sbom::Sbom;
+use anyhow::Context;
 use cargo_metadata::{Metadata, MetadataCommand, Package, TargetKind};
-use serde_json::Value;
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::ffi::OsString;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
 use std::fmt::Display;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 use target_build_utils::TargetInfo;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -17,7 +20,26 @@ pub enum PackageType {
     WxsMsi,
     Deb,
     Rpm,
+    Srpm,
     AppImage,
+    Flatpak,
+    Snap,
+    Dmg,
+    Pkg,
+    Nsis,
+    Msix,
+    Pacman,
+    Apk,
+    Nix,
+    Choco,
+    Archive,
+    Aur,
+    FreeBsd,
+    Android,
+    Ipk,
+    Oci,
+    Portable,
+    AppStore,
 }
 
 impl std::str::FromStr for PackageType {
@@ -56,7 +78,6 @@ impl TryFrom<String> for PackageType {
 
 impl PackageType {
     pub fn from_short_name(name: &str) -> Option<PackageType> {
-        // Other types we may eventually want to support: apk
         match name {
             "deb" => Some(PackageType::Deb),
             "ios" => Some(PackageType::IosBundle),
@@ -64,7 +85,26 @@ impl PackageType {
             "wxsmsi" => Some(PackageType::WxsMsi),
             "osx" => Some(PackageType::OsxBundle),
             "rpm" => Some(PackageType::Rpm),
+            "srpm" => Some(PackageType::Srpm),
             "appimage" => Some(PackageType::AppImage),
+            "flatpak" => Some(PackageType::Flatpak),
+            "snap" => Some(PackageType::Snap),
+            "dmg" => Some(PackageType::Dmg),
+            "pkg" => Some(PackageType::Pkg),
+            "nsis" => Some(PackageType::Nsis),
+            "msix" => Some(PackageType::Msix),
+            "pacman" => Some(PackageType::Pacman),
+            "apk" => Some(PackageType::Apk),
+            "nix" => Some(PackageType::Nix),
+            "choco" => Some(PackageType::Choco),
+            "archive" => Some(PackageType::Archive),
+            "aur" => Some(PackageType::Aur),
+            "freebsd" => Some(PackageType::FreeBsd),
+            "android" => Some(PackageType::Android),
+            "ipk" => Some(PackageType::Ipk),
+            "oci" => Some(PackageType::Oci),
+            "portable" => Some(PackageType::Portable),
+            "appstore" => Some(PackageType::AppStore),
             _ => None,
         }
     }
@@ -77,15 +117,862 @@ impl PackageType {
             PackageType::WxsMsi => "wxsmsi",
             PackageType::OsxBundle => "osx",
             PackageType::Rpm => "rpm",
+            PackageType::Srpm => "srpm",
             PackageType::AppImage => "appimage",
+            PackageType::Flatpak => "flatpak",
+            PackageType::Snap => "snap",
+            PackageType::Dmg => "dmg",
+            PackageType::Pkg => "pkg",
+            PackageType::Nsis => "nsis",
+            PackageType::Msix => "msix",
+            PackageType::Pacman => "pacman",
+            PackageType::Apk => "apk",
+            PackageType::Nix => "nix",
+            PackageType::Choco => "choco",
+            PackageType::Archive => "archive",
+            PackageType::Aur => "aur",
+            PackageType::FreeBsd => "freebsd",
+            PackageType::Android => "android",
+            PackageType::Ipk => "ipk",
+            PackageType::Oci => "oci",
+            PackageType::Portable => "portable",
+            PackageType::AppStore => "appstore",
+        }
+    }
+
+    /// Returns the OS whose `[package.metadata.bundle.<os>]` table applies to
+    /// bundles of this type, or `None` for formats that aren't tied to one OS.
+    pub const fn platform(&self) -> Option<&'static str> {
+        match *self {
+            PackageType::OsxBundle
+            | PackageType::Dmg
+            | PackageType::Pkg
+            | PackageType::AppStore => Some("macos"),
+            PackageType::IosBundle => Some("ios"),
+            PackageType::WindowsMsi
+            | PackageType::WxsMsi
+            | PackageType::Nsis
+            | PackageType::Msix
+            | PackageType::Choco => Some("windows"),
+            PackageType::Deb
+            | PackageType::Rpm
+            | PackageType::Srpm
+            | PackageType::AppImage
+            | PackageType::Flatpak
+            | PackageType::Snap
+            | PackageType::Pacman
+            | PackageType::Apk
+            | PackageType::Nix
+            | PackageType::Aur
+            | PackageType::Ipk
+            | PackageType::Portable => Some("linux"),
+            PackageType::Archive
+            | PackageType::FreeBsd
+            | PackageType::Android
+            | PackageType::Oci => None,
+        }
+    }
+
+    /// Returns true if bundles of this type are reproducible: built by us (or by
+    /// tools honoring `SOURCE_DATE_EPOCH`) with fixed timestamps, ownership and
+    /// file order, rather than by tools that embed the time of the build.
+    pub const fn is_reproducible(&self) -> bool {
+        !matches!(
+            *self,
+            PackageType::AppImage
+                | PackageType::Flatpak
+                | PackageType::Snap
+                | PackageType::Dmg
+                | PackageType::Pkg
+                | PackageType::AppStore
+                | PackageType::Nsis
+                | PackageType::Msix
+                | PackageType::WxsMsi
+                | PackageType::Android
+                | PackageType::Srpm
+        )
+    }
+
+    /// Returns the external tools that the bundler for this type always runs,
+    /// leaving out those only needed for optional steps or settings, such as
+    /// signing.  These must be on the `PATH`, except for those of `android`,
+    /// which are build tools of the Android SDK.
+    pub const fn required_tools(&self) -> &'static [&'static str] {
+        match *self {
+            PackageType::AppImage | PackageType::Portable => &["mksquashfs"],
+            PackageType::Flatpak => &["flatpak", "flatpak-builder"],
+            PackageType::Snap => &["snapcraft"],
+            PackageType::Pacman => &["zstd"],
+            PackageType::Dmg => &["hdiutil"],
+            PackageType::Pkg => &["pkgbuild", "productbuild"],
+            PackageType::AppStore => &["codesign", "productbuild"],
+            PackageType::WxsMsi => &["dotnet"],
+            PackageType::Nsis => &["makensis"],
+            PackageType::Msix => &["makeappx"],
+            PackageType::Android => &["aapt", "zipalign", "apksigner"],
+            PackageType::Deb
+            | PackageType::Rpm
+            | PackageType::Srpm
+            | PackageType::Aur
+            | PackageType::Apk
+            | PackageType::FreeBsd
+            | PackageType::Oci
+            | PackageType::OsxBundle
+            | PackageType::IosBundle
+            | PackageType::WindowsMsi
+            | PackageType::Nix
+            | PackageType::Choco
+            | PackageType::Archive
+            | PackageType::Ipk => &[],
         }
     }
 
     pub const fn all() -> &'static [&'static str] {
-        &["deb", "ios", "msi", "wxsmsi", "osx", "rpm", "appimage"]
+        &[
+            "deb", "ios", "msi", "wxsmsi", "osx", "rpm", "srpm", "appimage", "flatpak", "snap",
+            "dmg", "pkg", "nsis", "msix", "pacman", "apk", "nix", "choco", "archive", "aur",
+            "freebsd", "android", "ipk", "oci", "portable", "appstore",
+        ]
+    }
+}
+
+/// The compression of the `control.tar` and `data.tar` members of deb
+/// packages.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebCompression {
+    #[default]
+    Gzip,
+    Xz,
+    Zstd,
+    None,
+}
+
+impl DebCompression {
+    /// Returns the extension of the compressed members, e.g. `tar.gz`.
+    pub const fn extension(&self) -> &'static str {
+        match *self {
+            DebCompression::Gzip => "tar.gz",
+            DebCompression::Xz => "tar.xz",
+            DebCompression::Zstd => "tar.zst",
+            DebCompression::None => "tar",
+        }
+    }
+}
+
+/// A shell that `shell_completions` are given for.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// The compression algorithm used for the squashfs image embedded in an
+/// AppImage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppImageCompression {
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl AppImageCompression {
+    /// Returns the name of this algorithm as understood by `mksquashfs -comp`.
+    pub const fn name(&self) -> &'static str {
+        match *self {
+            AppImageCompression::Gzip => "gzip",
+            AppImageCompression::Xz => "xz",
+            AppImageCompression::Zstd => "zstd",
+        }
+    }
+}
+
+/// The release grade of a snap, which controls which channels it may be
+/// published to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapGrade {
+    Stable,
+    Devel,
+}
+
+impl SnapGrade {
+    /// Returns the name of this grade as written in `snapcraft.yaml`.
+    pub const fn name(&self) -> &'static str {
+        match *self {
+            SnapGrade::Stable => "stable",
+            SnapGrade::Devel => "devel",
+        }
+    }
+}
+
+/// The confinement level of a snap.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapConfinement {
+    Strict,
+    Classic,
+    Devmode,
+}
+
+impl SnapConfinement {
+    /// Returns the name of this confinement level as written in
+    /// `snapcraft.yaml`.
+    pub const fn name(&self) -> &'static str {
+        match *self {
+            SnapConfinement::Strict => "strict",
+            SnapConfinement::Classic => "classic",
+            SnapConfinement::Devmode => "devmode",
+        }
+    }
+}
+
+/// The format of the software bill of materials embedded in bundles.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+/// Where the bundle version comes from, instead of the `version` setting or
+/// `Cargo.toml`.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionSource {
+    /// `git describe`: the latest tag, followed by the number of commits since
+    /// and the hash of `HEAD` if it is not the tagged commit.
+    Git,
+    /// The output of a shell command.
+    Command(String),
+}
+
+/// What to do with the debug information in the binary.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugSymbols {
+    /// Leave it in the binary.
+    #[default]
+    Keep,
+    /// Strip it from the binary.
+    Strip,
+    /// Strip it from the binary, and ship it separately.
+    Split,
+}
+
+/// The kinds of GPG signature made for deb packages.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebSignature {
+    /// A `_gpgorigin` member embedded in the package, as made by `debsigs`.
+    Origin,
+    /// A detached, ASCII-armored `.asc` file next to the package.
+    Detached,
+    /// Both of the above.
+    Both,
+}
+
+impl DebSignature {
+    /// Returns whether the package embeds a `_gpgorigin` signature.
+    pub const fn origin(&self) -> bool {
+        matches!(*self, DebSignature::Origin | DebSignature::Both)
+    }
+
+    /// Returns whether a detached `.asc` signature is written.
+    pub const fn detached(&self) -> bool {
+        matches!(*self, DebSignature::Detached | DebSignature::Both)
+    }
+}
+
+/// The tool that signs macOS app bundles and disk images.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsxSigningTool {
+    /// Apple's `codesign`, which is only available on macOS.
+    Codesign,
+    /// `rcodesign` from apple-codesign, which runs on any system.
+    Rcodesign,
+}
+
+/// The `osx_entitlements` setting: the path of an entitlements file, or a table
+/// of the entitlements themselves.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum EntitlementsSetting {
+    Path(String),
+    Keys(toml::Table),
+}
+
+/// The entitlements to sign a macOS app with.
+#[derive(Clone, Debug)]
+pub enum OsxEntitlements<'a> {
+    /// The path of an entitlements file.
+    File(PathBuf),
+    /// Entitlement keys (e.g. `com.apple.security.device.camera`) and their
+    /// values, to be written to an entitlements file.
+    Keys(&'a toml::Table),
+}
+
+/// An `[[package.metadata.bundle.osx_helpers]]` entry, an app built from
+/// another `bin` target of the package, which is embedded in the macOS app.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct OsxHelper {
+    /// The name of the `bin` target.  The helper app has the settings of its
+    /// `[package.metadata.bundle.bin.<name>]` section.
+    pub bin: String,
+    /// Where the helper app is embedded, which defaults to `Contents/Helpers`.
+    #[serde(default)]
+    pub kind: OsxHelperKind,
+}
+
+/// Where an `OsxHelper` is embedded in the macOS app.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OsxHelperKind {
+    /// `Contents/Helpers`, e.g. for a privileged helper or an agent that the
+    /// app launches itself.
+    #[default]
+    Helper,
+    /// `Contents/Library/LoginItems`, where `SMAppService` looks for the apps
+    /// to launch at login.
+    LoginItem,
+}
+
+impl OsxHelperKind {
+    /// Returns the directory of the `Contents` directory that the helper app
+    /// is embedded in.
+    pub fn dir(self) -> &'static str {
+        match self {
+            OsxHelperKind::Helper => "Helpers",
+            OsxHelperKind::LoginItem => "Library/LoginItems",
+        }
+    }
+}
+
+/// The `[package.metadata.bundle.osx_sparkle]` table, which makes the macOS
+/// app update itself with the Sparkle framework.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct SparkleSettings {
+    /// The path of `Sparkle.framework`, relative to `Cargo.toml`, which is
+    /// embedded in the app.
+    pub framework: String,
+    /// The URL of the appcast that the app checks for updates (`SUFeedURL`).
+    pub feed_url: String,
+    /// The base64-encoded EdDSA public key that updates must be signed with
+    /// (`SUPublicEDKey`).
+    pub public_key: String,
+    /// The template of the URL that the update is downloaded from, with the
+    /// same tokens as `homebrew_url`.  If set, the app is archived as an
+    /// update, and an appcast listing it is written next to it.
+    pub url: Option<String>,
+}
+
+/// The `[package.metadata.bundle.osx_sandbox]` table, from which the App
+/// Sandbox entitlements of the macOS app are generated.  Unknown keys are
+/// rejected, so that misspelt entitlements don't go unnoticed.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxSettings {
+    /// Whether the app may make outgoing network connections.
+    #[serde(default)]
+    pub network_client: bool,
+    /// Whether the app may accept incoming network connections.
+    #[serde(default)]
+    pub network_server: bool,
+    /// The locations of files that the app may access.
+    #[serde(default)]
+    pub files: Vec<SandboxFileAccess>,
+    /// The devices that the app may use.
+    #[serde(default)]
+    pub devices: Vec<SandboxDevice>,
+    /// Access beyond what the sandbox otherwise allows, which App Review only
+    /// grants with a justification.
+    #[serde(default)]
+    pub temporary_exceptions: SandboxExceptions,
+}
+
+impl SandboxSettings {
+    /// Returns the entitlements that enable the sandbox, and grant the access
+    /// that these settings allow.
+    pub fn entitlements(&self) -> toml::Table {
+        let mut keys = toml::Table::new();
+        let mut grant = |key: &str| {
+            keys.insert(
+                format!("com.apple.security.{key}"),
+                toml::Value::Boolean(true),
+            );
+        };
+        grant("app-sandbox");
+        if self.network_client {
+            grant("network.client");
+        }
+        if self.network_server {
+            grant("network.server");
+        }
+        for access in &self.files {
+            grant(access.entitlement());
+        }
+        for device in &self.devices {
+            grant(device.entitlement());
+        }
+        let exceptions = &self.temporary_exceptions;
+        for (key, values) in [
+            (
+                "files.absolute-path.read-only",
+                &exceptions.absolute_path_read_only,
+            ),
+            (
+                "files.absolute-path.read-write",
+                &exceptions.absolute_path_read_write,
+            ),
+            (
+                "files.home-relative-path.read-only",
+                &exceptions.home_relative_path_read_only,
+            ),
+            (
+                "files.home-relative-path.read-write",
+                &exceptions.home_relative_path_read_write,
+            ),
+            (
+                "mach-lookup.global-name",
+                &exceptions.mach_lookup_global_name,
+            ),
+        ] {
+            if !values.is_empty() {
+                keys.insert(
+                    format!("com.apple.security.temporary-exception.{key}"),
+                    toml::Value::Array(values.iter().cloned().map(toml::Value::String).collect()),
+                );
+            }
+        }
+        keys
+    }
+}
+
+/// A location of files that a sandboxed app may access, e.g.
+/// `"user-selected.read-write"` for the files that the user opens or saves.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+pub enum SandboxFileAccess {
+    #[serde(rename = "user-selected.read-only")]
+    UserSelectedReadOnly,
+    #[serde(rename = "user-selected.read-write")]
+    UserSelectedReadWrite,
+    #[serde(rename = "user-selected.executable")]
+    UserSelectedExecutable,
+    #[serde(rename = "downloads.read-only")]
+    DownloadsReadOnly,
+    #[serde(rename = "downloads.read-write")]
+    DownloadsReadWrite,
+    #[serde(rename = "pictures.read-only")]
+    PicturesReadOnly,
+    #[serde(rename = "pictures.read-write")]
+    PicturesReadWrite,
+    #[serde(rename = "music.read-only")]
+    MusicReadOnly,
+    #[serde(rename = "music.read-write")]
+    MusicReadWrite,
+    #[serde(rename = "movies.read-only")]
+    MoviesReadOnly,
+    #[serde(rename = "movies.read-write")]
+    MoviesReadWrite,
+}
+
+impl SandboxFileAccess {
+    /// Returns the entitlement granting the access, without the
+    /// `com.apple.security.` prefix.
+    fn entitlement(self) -> &'static str {
+        match self {
+            SandboxFileAccess::UserSelectedReadOnly => "files.user-selected.read-only",
+            SandboxFileAccess::UserSelectedReadWrite => "files.user-selected.read-write",
+            SandboxFileAccess::UserSelectedExecutable => "files.user-selected.executable",
+            SandboxFileAccess::DownloadsReadOnly => "files.downloads.read-only",
+            SandboxFileAccess::DownloadsReadWrite => "files.downloads.read-write",
+            SandboxFileAccess::PicturesReadOnly => "assets.pictures.read-only",
+            SandboxFileAccess::PicturesReadWrite => "assets.pictures.read-write",
+            SandboxFileAccess::MusicReadOnly => "assets.music.read-only",
+            SandboxFileAccess::MusicReadWrite => "assets.music.read-write",
+            SandboxFileAccess::MoviesReadOnly => "assets.movies.read-only",
+            SandboxFileAccess::MoviesReadWrite => "assets.movies.read-write",
+        }
+    }
+}
+
+/// A device that a sandboxed app may use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SandboxDevice {
+    Camera,
+    /// The microphone.
+    AudioInput,
+    Usb,
+    Bluetooth,
+    Serial,
+    /// Printers.
+    Print,
+}
+
+impl SandboxDevice {
+    /// Returns the entitlement granting the use of the device, without the
+    /// `com.apple.security.` prefix.
+    fn entitlement(self) -> &'static str {
+        match self {
+            SandboxDevice::Camera => "device.camera",
+            SandboxDevice::AudioInput => "device.audio-input",
+            SandboxDevice::Usb => "device.usb",
+            SandboxDevice::Bluetooth => "device.bluetooth",
+            SandboxDevice::Serial => "device.serial",
+            SandboxDevice::Print => "print",
+        }
+    }
+}
+
+/// The `temporary_exceptions` of `SandboxSettings`.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxExceptions {
+    /// Absolute paths that the app may read.
+    #[serde(default)]
+    pub absolute_path_read_only: Vec<String>,
+    /// Absolute paths that the app may read and write.
+    #[serde(default)]
+    pub absolute_path_read_write: Vec<String>,
+    /// Paths relative to the home directory that the app may read.
+    #[serde(default)]
+    pub home_relative_path_read_only: Vec<String>,
+    /// Paths relative to the home directory that the app may read and write.
+    #[serde(default)]
+    pub home_relative_path_read_write: Vec<String>,
+    /// The global Mach services that the app may look up.
+    #[serde(default)]
+    pub mach_lookup_global_name: Vec<String>,
+}
+
+/// The tool that signs Windows binaries and installers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowsSigningTool {
+    /// `signtool` from the Windows SDK, which is only available on Windows.
+    Signtool,
+    /// `osslsigncode`, which runs on any system.
+    Osslsigncode,
+    /// `jsign`, which runs on any system with Java, and can sign with cloud
+    /// services.
+    Jsign,
+}
+
+/// The digest algorithm of an Authenticode signature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// Returns the name of this algorithm as understood by `signtool` and
+    /// `osslsigncode`.
+    pub const fn name(&self) -> &'static str {
+        match *self {
+            DigestAlgorithm::Sha1 => "sha1",
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Sha384 => "sha384",
+            DigestAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+/// The `[package.metadata.bundle.windows]` table.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+struct WindowsSettings {
+    sign: Option<WindowsSignSettings>,
+    install_scope: Option<InstallScope>,
+    services: Option<Vec<WindowsService>>,
+    registry: Option<Vec<RegistryEntry>>,
+    license: Option<String>,
+    license_dialog: Option<bool>,
+    banner_image: Option<String>,
+    dialog_image: Option<String>,
+    prerequisites: Option<Vec<Prerequisite>>,
+}
+
+/// A `[[package.metadata.bundle.windows.prerequisites]]` entry, an installer
+/// of a runtime that the app needs, which the `setup.exe` built along with the
+/// `wxsmsi` installer runs before installing it.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Prerequisite {
+    /// A runtime whose name, arguments and detection are known.
+    pub kind: Option<PrerequisiteKind>,
+    /// The name shown while installing it.  Defaults to that of the `kind`,
+    /// or else to the file name of the `source`.
+    pub name: Option<String>,
+    /// The path of the installer, relative to `Cargo.toml`, which is embedded
+    /// in the `setup.exe`.
+    pub source: String,
+    /// The arguments that the installer is run with, which default to those
+    /// of the `kind` that install it silently.
+    pub arguments: Option<String>,
+    /// A Burn condition that is true if the runtime is installed already, in
+    /// which case the installer isn't run.  Defaults to that of the `kind`.
+    pub detect_condition: Option<String>,
+}
+
+/// A runtime known to `Prerequisite`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrerequisiteKind {
+    /// The Microsoft Edge WebView2 Evergreen Runtime, installed by its
+    /// bootstrapper (`MicrosoftEdgeWebview2Setup.exe`).
+    Webview2,
+    /// The Microsoft Visual C++ Redistributable (`vc_redist.<arch>.exe`).
+    Vcredist,
+}
+
+/// A `[[package.metadata.bundle.windows.registry]]` entry, a registry value
+/// that the `msi` and `wxsmsi` installers write, and remove on uninstall.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct RegistryEntry {
+    pub root: RegistryRoot,
+    /// The key of the value, relative to the `root`, e.g. `Software\Example`.
+    pub key: String,
+    /// The name of the value, or `None` for the default value of the key.
+    pub name: Option<String>,
+    pub value: RegistryData,
+}
+
+/// The root key of a `RegistryEntry`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RegistryRoot {
+    Hkcr,
+    Hkcu,
+    Hklm,
+    Hku,
+    /// HKLM for per-machine installs, and HKCU for per-user ones.
+    Hkmu,
+}
+
+/// The data of a `RegistryEntry`: a `REG_DWORD` for integers, and a `REG_SZ`
+/// for strings.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(untagged)]
+pub enum RegistryData {
+    Dword(u32),
+    String(String),
+}
+
+/// A `[[package.metadata.bundle.windows.services]]` entry, a Windows service
+/// that the `msi` and `wxsmsi` installers register the binary as.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct WindowsService {
+    /// The name of the service, e.g. for `sc start <name>`.
+    pub name: String,
+    /// The name shown in the Services console.  Defaults to the `name`.
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub start_type: ServiceStartType,
+    /// The account that the service runs as, e.g. `NT AUTHORITY\LocalService`.
+    /// Defaults to `LocalSystem`.
+    pub account: Option<String>,
+    /// The arguments that the binary is started with.
+    pub arguments: Option<String>,
+}
+
+/// When a Windows service starts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceStartType {
+    /// When the system starts, and right after it is installed.
+    #[default]
+    Auto,
+    /// When it is started by hand, or by another program.
+    Demand,
+    Disabled,
+}
+
+/// Whom the `msi` and `wxsmsi` installers install the app for.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstallScope {
+    /// All users of the machine, under `Program Files`, which requires
+    /// elevation.
+    #[default]
+    PerMachine,
+    /// The current user, under `%LOCALAPPDATA%\Programs`, without elevation.
+    PerUser,
+    /// The current user by default, or all users when the installer is run
+    /// with `ALLUSERS=1`.
+    Dual,
+}
+
+/// The `[package.metadata.bundle.windows.sign]` table, configuring the
+/// Authenticode signing of Windows binaries and installers.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct WindowsSignSettings {
+    /// The tool to sign with.  Defaults to `signtool` on Windows (unless a
+    /// PKCS #11 module is set, or Trusted Signing is used without a dlib), and
+    /// `osslsigncode` (or `jsign`, for Trusted Signing) elsewhere.
+    pub tool: Option<WindowsSigningTool>,
+    /// The path of a PKCS #12 (`.pfx`) certificate file, relative to the
+    /// directory containing `Cargo.toml`.
+    pub certificate: Option<String>,
+    /// The SHA-1 thumbprint of a certificate in the Windows certificate store.
+    pub certificate_thumbprint: Option<String>,
+    pub digest_algorithm: Option<DigestAlgorithm>,
+    /// The URL of an RFC 3161 timestamp server.
+    pub timestamp_url: Option<String>,
+    /// The path of a PKCS #11 module, to sign with a key held in a hardware
+    /// token or HSM, relative to the directory containing `Cargo.toml`.
+    pub pkcs11_module: Option<String>,
+    /// The PKCS #11 URI of the key in the token (e.g.
+    /// `pkcs11:token=Foo;object=codesign`).
+    pub pkcs11_key: Option<String>,
+    /// Signs with a certificate profile of an Azure Trusted Signing account,
+    /// instead of a local certificate.
+    pub trusted_signing: Option<TrustedSigningSettings>,
+}
+
+/// The `[package.metadata.bundle.windows.sign.trusted_signing]` table, naming
+/// the Azure Trusted Signing certificate profile to sign with.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct TrustedSigningSettings {
+    /// The endpoint of the account's region, e.g.
+    /// `https://eus.codesigning.azure.net`.
+    pub endpoint: String,
+    /// The name of the Trusted Signing account.
+    pub account: String,
+    /// The name of the certificate profile in the account.
+    pub certificate_profile: String,
+    /// The path of `Azure.CodeSigning.Dlib.dll`, through which `signtool`
+    /// signs, relative to the directory containing `Cargo.toml`.
+    pub dlib: Option<String>,
+}
+
+/// An entry of the `linux_desktop_actions` list: an additional action of the
+/// app, such as opening a new window, which desktops offer in the launcher's
+/// context menu.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct DesktopAction {
+    /// The identifier of the action, which names its `[Desktop Action <id>]`
+    /// group.
+    pub id: String,
+    /// The name of the action, as shown to users.
+    pub name: String,
+    /// The arguments to run the binary with, as in `linux_exec_args`.
+    pub args: Option<String>,
+    /// The icon of the action, as a name from the icon theme or a path.
+    pub icon: Option<String>,
+}
+
+/// An entry of the `linux_screenshots` list: a screenshot of the app, which
+/// software centers show on its page.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Screenshot {
+    /// The URL of the image.
+    pub url: String,
+    /// A short description of what the screenshot shows.
+    pub caption: Option<String>,
+}
+
+/// The role the app claims for the files of a `file_associations` entry.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileAssociationRole {
+    /// The app reads and writes the files.
+    #[default]
+    Editor,
+    /// The app only reads the files.
+    Viewer,
+    /// The app provides runtime services for the files.
+    Shell,
+    /// The app declares the file type, but does not open the files.
+    None,
+}
+
+impl FileAssociationRole {
+    /// Returns the name of this role as a `CFBundleTypeRole` value.
+    pub const fn plist_name(&self) -> &'static str {
+        match *self {
+            FileAssociationRole::Editor => "Editor",
+            FileAssociationRole::Viewer => "Viewer",
+            FileAssociationRole::Shell => "Shell",
+            FileAssociationRole::None => "None",
+        }
+    }
+}
+
+/// An entry of the `file_associations` list: a type of documents that the app
+/// opens, which the bundles register with the system.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct FileAssociation {
+    /// The file name extensions of the documents, without the leading dot.
+    pub extensions: Vec<String>,
+    /// The MIME type of the documents.  Defaults to `application/x-<ext>`,
+    /// after the first extension.
+    pub mime_type: Option<String>,
+    /// The name of the document type, as shown to users.  Defaults to
+    /// `<EXT> document`.
+    pub description: Option<String>,
+    #[serde(default)]
+    pub role: FileAssociationRole,
+    /// The paths of the document icons, relative to the directory containing
+    /// `Cargo.toml`.  Each bundle uses the first icon in a format it supports.
+    pub icon: Option<Vec<String>>,
+    /// The Uniform Type Identifier of the documents on macOS, e.g.
+    /// `com.example.foo-document`, which is declared in the `Info.plist`.
+    pub uti: Option<String>,
+    /// The identifiers of the types that the `uti` conforms to.  Defaults to
+    /// `public.data`.
+    pub uti_conforms_to: Option<Vec<String>>,
+    /// Whether the `uti` is owned by another app, so that it is declared as
+    /// imported rather than exported.  Defaults to false.
+    #[serde(default)]
+    pub uti_imported: bool,
+}
+
+impl FileAssociation {
+    fn first_extension(&self) -> &str {
+        self.extensions.first().map(String::as_str).unwrap_or("")
+    }
+
+    pub fn mime_type(&self) -> Cow<'_, str> {
+        match self.mime_type {
+            Some(ref mime_type) => mime_type.into(),
+            None => format!("application/x-{}", self.first_extension()).into(),
+        }
+    }
+
+    pub fn description(&self) -> Cow<'_, str> {
+        match self.description {
+            Some(ref description) => description.into(),
+            None => format!("{} document", self.first_extension().to_uppercase()).into(),
+        }
+    }
+
+    pub fn uti_conforms_to(&self) -> Vec<&str> {
+        match self.uti_conforms_to {
+            Some(ref types) => types.iter().map(String::as_str).collect(),
+            None => vec!["public.data"],
+        }
     }
 }
 
+/// An entry of the `localizations` table: the texts describing the app in one
+/// language, which replace the default ones there.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct Localization {
+    pub name: Option<String>,
+    pub short_description: Option<String>,
+    /// The tooltip of the app's launcher, i.e. the `Comment` of the `.desktop`
+    /// file.  Defaults to the short description.
+    pub comment: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub enum BuildArtifact {
     Main,
@@ -93,6 +980,95 @@ pub enum BuildArtifact {
     Example(String),
 }
 
+/// An entry of the `resources` setting: either a path or glob whose files are
+/// placed at the same relative path inside the bundle, or a `{ src, dest, mode }`
+/// table placing the files under a chosen destination, with chosen permissions.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+#[serde(untagged)]
+enum ResourceSetting {
+    Path(String),
+    Mapped {
+        src: String,
+        dest: Option<String>,
+        mode: Option<String>,
+    },
+}
+
+/// A maintainer script of deb packages, or a scriptlet of rpm packages: either
+/// the path of the script, or a `{ script }` table giving its contents inline.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+#[serde(untagged)]
+enum MaintainerScript {
+    Path(String),
+    Inline { script: String },
+}
+
+impl ResourceSetting {
+    fn src(&self) -> &String {
+        match self {
+            ResourceSetting::Path(src) | ResourceSetting::Mapped { src, .. } => src,
+        }
+    }
+
+    /// Returns the permissions given to the files of this entry, if any.
+    fn mode(&self) -> crate::Result<Option<u32>> {
+        let ResourceSetting::Mapped {
+            mode: Some(mode), ..
+        } = self
+        else {
+            return Ok(None);
+        };
+        match u32::from_str_radix(mode.trim_start_matches("0o"), 8) {
+            Ok(bits) if bits <= 0o7777 => Ok(Some(bits)),
+            _ => {
+                anyhow::bail!("Invalid resource mode {mode:?}, expected octal digits like \"0755\"")
+            }
+        }
+    }
+
+    /// Returns where the file at `path` (relative to `base_dir`, the directory
+    /// containing `Cargo.toml`), matched by this entry, goes inside the
+    /// bundle's resources.
+    fn destination(&self, path: &Path, base_dir: &Path) -> crate::Result<PathBuf> {
+        let (src, dest) = match self {
+            ResourceSetting::Mapped {
+                src,
+                dest: Some(dest),
+                ..
+            } => (src, dest),
+            _ => return Ok(super::common::resource_relpath(path)),
+        };
+        let dest_path = Path::new(dest);
+        if !dest_path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            anyhow::bail!(
+                "Resource destination {dest:?} must be a relative path inside the bundle"
+            );
+        }
+        let is_glob = src.contains(['*', '?', '[']);
+        // A single file may be renamed, unless the destination is a directory.
+        if !is_glob && !base_dir.join(src).is_dir() {
+            if dest.ends_with('/') || dest.is_empty() {
+                return Ok(dest_path.join(path.file_name().unwrap_or_default()));
+            }
+            return Ok(dest_path.to_path_buf());
+        }
+        // Otherwise, the files keep their path relative to the directory, or
+        // to the part of the glob before its first wildcard.
+        let base: PathBuf = Path::new(src)
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+            .collect();
+        let relpath = match path.strip_prefix(&base) {
+            Ok(relpath) => relpath,
+            Err(_) => Path::new(path.file_name().unwrap_or_default()),
+        };
+        Ok(dest_path.join(relpath))
+    }
+}
+
 #[derive(Clone, Debug, Default, serde::Deserialize)]
 struct BundleSettings {
     // General settings:
@@ -100,21 +1076,146 @@ struct BundleSettings {
     identifier: Option<String>,
     icon: Option<Vec<String>>,
     version: Option<String>,
-    resources: Option<Vec<String>>,
+    version_from: Option<VersionSource>,
+    resources: Option<Vec<ResourceSetting>>,
+    exclude: Option<Vec<String>>,
+    external_binaries: Option<Vec<String>>,
+    man_pages: Option<Vec<String>>,
+    shell_completions: Option<BTreeMap<Shell, String>>,
+    bundle_shared_libraries: Option<bool>,
     copyright: Option<String>,
     category: Option<AppCategory>,
     short_description: Option<String>,
     long_description: Option<String>,
+    artifact_name_template: Option<String>,
+    build_info: Option<bool>,
+    sbom: Option<SbomFormat>,
+    debug_symbols: Option<DebugSymbols>,
+    deny_warnings: Option<bool>,
+    timestamp_retries: Option<u32>,
+    timestamp_retry_delay: Option<u64>,
+    homebrew_url: Option<String>,
+    scoop_url: Option<String>,
+    winget_url: Option<String>,
+    winget_identifier: Option<String>,
+    file_associations: Option<Vec<FileAssociation>>,
+    url_schemes: Option<Vec<String>>,
+    localizations: Option<BTreeMap<String, Localization>>,
     // OS-specific settings:
     linux_mime_types: Option<Vec<String>>,
     linux_exec_args: Option<String>,
     linux_use_terminal: Option<bool>,
+    linux_keywords: Option<Vec<String>>,
+    linux_startup_wm_class: Option<String>,
+    linux_no_display: Option<bool>,
+    linux_desktop_actions: Option<Vec<DesktopAction>>,
+    linux_desktop_entries: Option<BTreeMap<String, String>>,
+    linux_systemd_units: Option<Vec<String>>,
+    linux_metainfo: Option<String>,
+    linux_screenshots: Option<Vec<Screenshot>>,
+    linux_systemd_enable: Option<bool>,
+    linux_systemd_start: Option<bool>,
+    linux_auto_depends: Option<bool>,
     deb_depends: Option<Vec<String>>,
+    deb_signature: Option<DebSignature>,
+    deb_preinst: Option<MaintainerScript>,
+    deb_postinst: Option<MaintainerScript>,
+    deb_prerm: Option<MaintainerScript>,
+    deb_postrm: Option<MaintainerScript>,
+    rpm_pre: Option<MaintainerScript>,
+    rpm_post: Option<MaintainerScript>,
+    rpm_preun: Option<MaintainerScript>,
+    rpm_postun: Option<MaintainerScript>,
+    rpm_requires: Option<Vec<String>>,
+    rpm_provides: Option<Vec<String>>,
+    rpm_conflicts: Option<Vec<String>>,
+    rpm_obsoletes: Option<Vec<String>>,
+    rpm_epoch: Option<u32>,
+    rpm_group: Option<String>,
+    rpm_url: Option<String>,
+    rpm_srpm: Option<bool>,
+    deb_conffiles: Option<BTreeMap<String, String>>,
+    deb_changelog: Option<String>,
+    deb_compression: Option<DebCompression>,
+    deb_compression_level: Option<u32>,
+    deb_section: Option<String>,
+    deb_priority: Option<String>,
+    gpg_key: Option<String>,
+    ipk_depends: Option<Vec<String>>,
+    ipk_arch: Option<String>,
+    pacman_depends: Option<Vec<String>>,
+    aur_source_url: Option<String>,
+    apk_depends: Option<Vec<String>>,
+    apk_signing_key: Option<String>,
+    nix_build_inputs: Option<Vec<String>>,
+    freebsd_version: Option<String>,
+    oci_exposed_ports: Option<Vec<String>>,
+    portable_unit: Option<String>,
+    appimage_compression: Option<AppImageCompression>,
+    flatpak_runtime: Option<String>,
+    flatpak_runtime_version: Option<String>,
+    flatpak_sdk: Option<String>,
+    flatpak_finish_args: Option<Vec<String>>,
+    snap_base: Option<String>,
+    snap_grade: Option<SnapGrade>,
+    snap_confinement: Option<SnapConfinement>,
+    snap_plugs: Option<Vec<String>>,
     osx_frameworks: Option<Vec<String>>,
     osx_plugins: Option<Vec<String>>,
     osx_minimum_system_version: Option<String>,
     osx_url_schemes: Option<Vec<String>>,
     osx_info_plist_exts: Option<Vec<String>>,
+    osx_info_plist: Option<toml::Table>,
+    osx_sparkle: Option<SparkleSettings>,
+    osx_helpers: Option<Vec<OsxHelper>>,
+    dmg_background: Option<String>,
+    dmg_volume_icon: Option<String>,
+    dmg_window_size: Option<[u32; 2]>,
+    dmg_icon_size: Option<u32>,
+    dmg_app_position: Option<[u32; 2]>,
+    dmg_applications_position: Option<[u32; 2]>,
+    osx_pkg_install_location: Option<String>,
+    osx_signing_identity: Option<String>,
+    osx_entitlements: Option<EntitlementsSetting>,
+    osx_sandbox: Option<SandboxSettings>,
+    osx_hardened_runtime: Option<bool>,
+    osx_signing_tool: Option<OsxSigningTool>,
+    osx_signing_certificate: Option<String>,
+    osx_timestamp_url: Option<String>,
+    osx_smartcard_slot: Option<String>,
+    ios_signing_identity: Option<String>,
+    ios_provisioning_profile: Option<String>,
+    ios_entitlements: Option<String>,
+    ios_asset_catalog: Option<String>,
+    ios_launch_storyboard: Option<String>,
+    appstore_signing_identity: Option<String>,
+    appstore_installer_identity: Option<String>,
+    appstore_provisioning_profile: Option<String>,
+    nsis_install_dir: Option<String>,
+    nsis_desktop_shortcut: Option<bool>,
+    nsis_header_image: Option<String>,
+    nsis_side_image: Option<String>,
+    msi_language: Option<String>,
+    msi_languages: Option<Vec<String>>,
+    msi_upgrade_code: Option<String>,
+    msi_start_menu_shortcut: Option<bool>,
+    msi_desktop_shortcut: Option<bool>,
+    msi_shortcut_args: Option<String>,
+    msi_publisher: Option<String>,
+    msi_help_url: Option<String>,
+    msi_update_url: Option<String>,
+    msi_no_modify: Option<bool>,
+    msi_no_repair: Option<bool>,
+    wix_template: Option<String>,
+    wix_fragments: Option<Vec<String>>,
+    wix_component_groups: Option<Vec<String>>,
+    msix_publisher: Option<String>,
+    msix_publisher_display_name: Option<String>,
+    windows: Option<WindowsSettings>,
+    android_min_sdk: Option<u32>,
+    android_target_sdk: Option<u32>,
+    android_permissions: Option<Vec<String>>,
+    android_keystore: Option<String>,
     // Bundles for other binaries/examples:
     bin: Option<HashMap<String, BundleSettings>>,
     example: Option<HashMap<String, BundleSettings>>,
@@ -131,29 +1232,92 @@ pub struct Settings {
     profile: String,
     all_features: bool,
     no_default_features: bool,
+    notarize: bool,
+    reproducible: bool,
     binary_path: PathBuf,
     binary_name: String,
     bundle_settings: BundleSettings,
+    build_info: Option<BuildInfo>,
+    sbom: Option<Sbom>,
 }
 
 /// Try to load `Cargo.toml` file in the specified directory
+/// Runs `cargo metadata` in `dir`, so that cargo finds the manifest of the
+/// package (and the workspace) containing it.
 fn load_metadata(dir: &Path) -> crate::Result<Metadata> {
-    let cargo_file_path = dir.join("Cargo.toml");
-    Ok(MetadataCommand::new()
-        .manifest_path(cargo_file_path)
-        .exec()?)
+    Ok(MetadataCommand::new().current_dir(dir).exec()?)
 }
 
 impl Settings {
-    pub fn new(current_dir: PathBuf, cli: &crate::Cli) -> crate::Result<Self> {
-        let package_type = cli.format;
-        let build_artifact = if let Some(bin) = cli.bin.as_ref() {
-            BuildArtifact::Bin(bin.to_string())
-        } else if let Some(example) = cli.example.as_ref() {
-            BuildArtifact::Example(example.to_string())
+    /// Returns the settings of each bundle to build: one for each binary and
+    /// example selected with `--bin` and `--example` (or else for the main
+    /// binary) of each package to bundle.  With `--workspace`, those are the
+    /// workspace members that have a `[package.metadata.bundle]` section, and
+    /// otherwise the package selected with `--package`, or else the one
+    /// containing `current_dir`.  Their settings are read from their
+    /// `Bundle.toml` or `[package.metadata.bundle]`, or from the `--config`
+    /// file.
+    pub fn all(current_dir: &Path, cli: &crate::Cli) -> crate::Result<Vec<Self>> {
+        let metadata = load_metadata(current_dir)?;
+        let packages = if cli.workspace {
+            let members: Vec<_> = metadata
+                .workspace_packages()
+                .into_iter()
+                .filter(|package| {
+                    package.metadata.get("bundle").is_some()
+                        || package
+                            .manifest_path
+                            .with_file_name(BUNDLE_CONFIG_FILE)
+                            .is_file()
+                })
+                .collect();
+            if members.is_empty() {
+                anyhow::bail!(
+                    "No workspace member has a [package.metadata.bundle] section or \
+                     {BUNDLE_CONFIG_FILE}"
+                );
+            }
+            members
         } else {
-            BuildArtifact::Main
+            vec![Settings::find_bundle_package(
+                cli.package.as_deref(),
+                &metadata,
+            )?]
         };
+        let mut build_artifacts: Vec<_> = cli
+            .bin
+            .iter()
+            .map(|bin| BuildArtifact::Bin(bin.clone()))
+            .chain(
+                cli.example
+                    .iter()
+                    .map(|example| BuildArtifact::Example(example.clone())),
+            )
+            .collect();
+        if build_artifacts.is_empty() {
+            build_artifacts.push(BuildArtifact::Main);
+        }
+        let mut all_settings = Vec::new();
+        for package in packages {
+            for build_artifact in &build_artifacts {
+                all_settings.push(Settings::new(
+                    &metadata,
+                    package,
+                    build_artifact.clone(),
+                    cli,
+                )?);
+            }
+        }
+        Ok(all_settings)
+    }
+
+    fn new(
+        cargo_settings: &Metadata,
+        package: &Package,
+        build_artifact: BuildArtifact,
+        cli: &crate::Cli,
+    ) -> crate::Result<Self> {
+        let package_type = cli.format;
         let profile = if cli.release {
             "release".to_string()
         } else if let Some(profile) = cli.profile.as_ref() {
@@ -167,44 +1331,110 @@ impl Settings {
         let all_features = cli.all_features;
         let no_default_features = cli.no_default_features;
         let target = if let Some(triple) = cli.target.as_ref() {
-            Some((triple.to_string(), TargetInfo::from_str(triple)?))
+            // A universal binary is described as its Apple silicon slice, and a
+            // simulator binary as one for devices.
+            let info_triple = match triple.as_str() {
+                UNIVERSAL_MACOS_TARGET => UNIVERSAL_MACOS_TARGETS[1],
+                triple => triple.strip_suffix("-sim").unwrap_or(triple),
+            };
+            Some((triple.to_string(), TargetInfo::from_str(info_triple)?))
         } else {
             None
         };
         let features = cli.features.as_ref().map(|features| features.into());
-        let cargo_settings = load_metadata(&current_dir)?;
-        let package = Settings::find_bundle_package(cli.package.as_deref(), &cargo_settings)?;
-        let bundle_settings = Settings::bundle_settings_of_package(package)?;
-        let workspace_dir = Settings::get_workspace_dir(current_dir);
-        let target_dir =
-            Settings::get_target_dir(&workspace_dir, &target, &profile, &build_artifact);
-        let (bundle_settings, mut binary_name) = match &build_artifact {
+        // Bundles for an OS use its override table, as do all bundles for a
+        // target OS when no format is given.
+        let platform = package_type
+            .and_then(|package_type| package_type.platform())
+            .unwrap_or(match &target {
+                Some((_, info)) => info.target_os(),
+                None => std::env::consts::OS,
+            });
+        let placeholders = Placeholders {
+            version: package.version.to_string(),
+            target: target.as_ref().map(|(triple, _)| triple.as_str()),
+            profile: &profile,
+        };
+        let bundle_settings =
+            Settings::bundle_settings_of_package(package, cli, platform, &placeholders)?;
+        let target_dir = Settings::get_target_dir(
+            cargo_settings.target_directory.as_std_path(),
+            &target,
+            &profile,
+            &build_artifact,
+        );
+        // The helper apps of the bundle of any artifact have the settings of
+        // the `bin` sections of the package.
+        let bins = bundle_settings.bin.clone();
+        let (mut bundle_settings, binary_name) = match &build_artifact {
             BuildArtifact::Main => {
+                // Android apps are shared libraries loaded by `NativeActivity`.
+                let kind = if package_type == Some(PackageType::Android) {
+                    TargetKind::CDyLib
+                } else {
+                    TargetKind::Bin
+                };
                 if let Some(target) = package
                     .targets
                     .iter()
-                    .find(|target| target.kind.contains(&TargetKind::Bin))
+                    .find(|target| target.kind.contains(&kind))
                 {
                     (bundle_settings, target.name.clone())
                 } else {
-                    anyhow::bail!("No `bin` target is found in package '{}'", package.name)
+                    anyhow::bail!("No `{kind}` target is found in package '{}'", package.name)
                 }
             }
-            BuildArtifact::Bin(name) => (
-                bundle_settings_from_table(&bundle_settings.bin, "bin", name)?,
-                name.clone(),
-            ),
-            BuildArtifact::Example(name) => (
-                bundle_settings_from_table(&bundle_settings.example, "example", name)?,
-                name.clone(),
-            ),
+            BuildArtifact::Bin(name) => {
+                Settings::check_target_exists(package, TargetKind::Bin, name)?;
+                (
+                    bundle_settings_from_table(&bundle_settings.bin, name, &bundle_settings),
+                    name.clone(),
+                )
+            }
+            BuildArtifact::Example(name) => {
+                Settings::check_target_exists(package, TargetKind::Example, name)?;
+                (
+                    bundle_settings_from_table(&bundle_settings.example, name, &bundle_settings),
+                    name.clone(),
+                )
+            }
+        };
+        if bundle_settings.bin.is_none() {
+            bundle_settings.bin = bins;
+        }
+        if let Some(identity) = cli.sign.as_ref() {
+            bundle_settings.osx_signing_identity = Some(identity.clone());
+        }
+        if let Some(key) = cli.gpg_key.as_ref() {
+            bundle_settings.gpg_key = Some(key.clone());
+        }
+        let binary_name = match package_type {
+            Some(PackageType::Android) => format!("lib{}.so", binary_name.replace('-', "_")),
+            _ => binary_name,
+        };
+        let target_os = match target {
+            Some((_, ref info)) => info.target_os(),
+            None => std::env::consts::OS,
         };
-        let binary_extension = match package_type {
-            Some(PackageType::WindowsMsi) | Some(PackageType::WxsMsi) => ".exe",
-            _ => "",
+        // Windows executables are named after the binary target, plus `.exe`.
+        let binary_path = if target_os == "windows" {
+            target_dir.join(format!("{binary_name}.exe"))
+        } else {
+            target_dir.join(&binary_name)
+        };
+        let build_info = if bundle_settings.build_info.unwrap_or(false) {
+            let package_dir = package
+                .manifest_path
+                .parent()
+                .map_or(Path::new("."), |dir| dir.as_std_path());
+            Some(BuildInfo::capture(package_dir)?)
+        } else {
+            None
+        };
+        let sbom = match bundle_settings.sbom {
+            Some(format) => Some(Sbom::generate(cargo_settings, package, format)?),
+            None => None,
         };
-        binary_name += binary_extension;
-        let binary_path = target_dir.join(&binary_name);
         Ok(Settings {
             package: package.clone(),
             package_type,
@@ -214,10 +1444,14 @@ impl Settings {
             profile,
             all_features,
             no_default_features,
+            notarize: cli.notarize,
+            reproducible: cli.reproducible,
             project_out_directory: target_dir,
             binary_path,
             binary_name,
             bundle_settings,
+            build_info,
+            sbom,
         })
     }
 
@@ -225,35 +1459,17 @@ impl Settings {
         Path::new(&self.package.manifest_path)
     }
 
-    /*
-        The target_dir where binaries will be compiled to by cargo can vary:
-            - this directory is a member of a workspace project
-            - overridden by CARGO_TARGET_DIR environment variable
-            - specified in build.target-dir configuration key
-            - if the build is a 'release' or 'debug' build
-
-        This function determines where 'target' dir is and suffixes it with 'release' or 'debug'
-        to determine where the compiled binary will be located.
-    */
+    /// Returns the directory that cargo writes the binary to: a directory of
+    /// the workspace's target directory (as resolved by cargo, which honours
+    /// `CARGO_TARGET_DIR` and `build.target-dir`) named after the target triple
+    /// and profile.
     fn get_target_dir(
-        project_root_dir: &Path,
+        target_directory: &Path,
         target: &Option<(String, TargetInfo)>,
         profile: &str,
         build_artifact: &BuildArtifact,
     ) -> PathBuf {
-        let mut cargo = std::process::Command::new(
-            std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")),
-        );
-        cargo.args(["metadata", "--no-deps", "--format-version", "1"]);
-
-        let target_dir = cargo.output().ok().and_then(|output| {
-            let json_string = String::from_utf8(output.stdout).ok()?;
-            let json: Value = serde_json::from_str(&json_string).ok()?;
-            Some(PathBuf::from(json.get("target_directory")?.as_str()?))
-        });
-
-        let mut path = target_dir.unwrap_or(project_root_dir.join("target"));
-
+        let mut path = target_directory.to_path_buf();
         if let &Some((ref triple, _)) = target {
             path.push(triple);
         }
@@ -264,57 +1480,106 @@ impl Settings {
         path
     }
 
-    /*
-        The specification of the Cargo.toml Manifest that covers the "workspace" section is here:
-        https://doc.rust-lang.org/cargo/reference/manifest.html#the-workspace-section
-
-        Determining if the current project folder is part of a workspace:
-            - Walk up the file system, looking for a Cargo.toml file.
-            - Stop at the first one found.
-            - If one is found before reaching "/" then this folder belongs to that parent workspace
-    */
-    fn get_workspace_dir(current_dir: PathBuf) -> PathBuf {
-        let mut dir = current_dir.clone();
-        let set = load_metadata(&dir);
-        if set.is_ok() {
-            return dir;
-        }
-        while dir.pop() {
-            let set = load_metadata(&dir);
-            if set.is_ok() {
-                return dir;
-            }
-        }
-
-        // Nothing found walking up the file system, return the starting directory
-        current_dir
-    }
-
+    /// Returns the workspace member named `package`, or else the package
+    /// containing the directory cargo was run in.
     fn find_bundle_package<'a>(
         package: Option<&'a str>,
         metadata: &'a Metadata,
     ) -> crate::Result<&'a Package> {
         match package {
             Some(package) => metadata
-                .packages
-                .iter()
+                .workspace_packages()
+                .into_iter()
                 .find(|p| p.name.as_str() == package)
                 .ok_or_else(|| anyhow::anyhow!("Package '{package}' not found in workspace")),
-            None => metadata
-                .root_package()
-                .ok_or_else(|| anyhow::anyhow!("No root package found in workspace")),
+            None => metadata.root_package().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No root package found in workspace; select a member with `--package`, \
+                     or bundle all of them with `--workspace`"
+                )
+            }),
         }
     }
 
-    fn bundle_settings_of_package(package: &Package) -> crate::Result<BundleSettings> {
-        if let Some(bundle) = package.metadata.get("bundle") {
-            return Ok(serde_json::from_value::<BundleSettings>(bundle.clone())?);
+    /// Fails if `package` has no `kind` target named `name`, listing the ones
+    /// it has, as `cargo build` does.
+    fn check_target_exists(package: &Package, kind: TargetKind, name: &str) -> crate::Result<()> {
+        let names: Vec<_> = package
+            .targets
+            .iter()
+            .filter(|target| target.kind.contains(&kind))
+            .map(|target| target.name.as_str())
+            .collect();
+        if names.contains(&name) {
+            return Ok(());
         }
-        print_warning(&format!(
-            "No [package.metadata.bundle] section in package \"{}\"",
-            package.name
-        ))?;
-        Ok(BundleSettings::default())
+        let kind = if kind == TargetKind::Bin {
+            "binary"
+        } else {
+            "example"
+        };
+        if names.is_empty() {
+            anyhow::bail!(
+                "No {kind} target `{name}` is found in package '{}'",
+                package.name
+            )
+        }
+        anyhow::bail!(
+            "No {kind} target `{name}` is found in package '{}'; available {kind} targets: {}",
+            package.name,
+            names.join(", ")
+        )
+    }
+
+    /// Returns the bundle section of `package`: the contents of the file given
+    /// with `--config`, or else of the `Bundle.toml` next to its `Cargo.toml`,
+    /// or else its `[package.metadata.bundle]` table.
+    fn bundle_section(
+        package: &Package,
+        config: Option<&Path>,
+    ) -> crate::Result<Option<serde_json::Value>> {
+        let path = match config {
+            Some(path) => Some(path.to_path_buf()),
+            None => package
+                .manifest_path
+                .parent()
+                .map(|dir| dir.join(BUNDLE_CONFIG_FILE).into_std_path_buf())
+                .filter(|path| path.is_file()),
+        };
+        let Some(path) = path else {
+            return Ok(package.metadata.get("bundle").cloned());
+        };
+        let contents =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+        let table: toml::Table =
+            toml::from_str(&contents).with_context(|| format!("Failed to parse {path:?}"))?;
+        Ok(Some(serde_json::to_value(table)?))
+    }
+
+    fn bundle_settings_of_package(
+        package: &Package,
+        cli: &crate::Cli,
+        platform: &str,
+        placeholders: &Placeholders,
+    ) -> crate::Result<BundleSettings> {
+        let mut bundle = match Settings::bundle_section(package, cli.config.as_deref())? {
+            Some(bundle) => bundle,
+            None if !cli.set.is_empty() => serde_json::json!({}),
+            None => {
+                print_warning(&format!(
+                    "No [package.metadata.bundle] section or {BUNDLE_CONFIG_FILE} in package \"{}\"",
+                    package.name
+                ))?;
+                return Ok(BundleSettings::default());
+            }
+        };
+        apply_platform_overrides(&mut bundle, platform);
+        for assignment in &cli.set {
+            apply_set_override(&mut bundle, assignment, platform)?;
+        }
+        merge_artifact_sections(&mut bundle);
+        placeholders.substitute(&mut bundle)?;
+        Ok(serde_json::from_value::<BundleSettings>(bundle)?)
     }
 
     /// Returns the directory where the bundle should be placed.
@@ -325,13 +1590,59 @@ impl Settings {
     /// Returns the architecture for the binary being bundled (e.g. "arm" or
     /// "x86" or "x86_64").
     pub fn binary_arch(&self) -> &str {
-        if let Some((_, ref info)) = self.target {
+        if self.is_universal_macos() {
+            "universal"
+        } else if let Some((_, ref info)) = self.target {
             info.target_arch()
         } else {
             std::env::consts::ARCH
         }
     }
 
+    /// Returns true if the binary is a universal macOS binary, merged from
+    /// those built for each of the `UNIVERSAL_MACOS_TARGETS`.
+    pub fn is_universal_macos(&self) -> bool {
+        self.target_triple() == Some(UNIVERSAL_MACOS_TARGET)
+    }
+
+    /// Returns true if the binary is built for the iOS simulator rather than
+    /// for devices.
+    pub fn is_ios_simulator(&self) -> bool {
+        matches!(self.target_triple(), Some(triple) if triple.ends_with("-apple-ios-sim") || triple == "x86_64-apple-ios")
+    }
+
+    /// Returns the paths of the binaries that the universal macOS binary is
+    /// merged from, which cargo builds for each of the
+    /// `UNIVERSAL_MACOS_TARGETS`.
+    pub fn universal_macos_binary_paths(&self) -> Vec<PathBuf> {
+        UNIVERSAL_MACOS_TARGETS
+            .iter()
+            .map(|triple| {
+                self.project_out_directory
+                    .components()
+                    .map(|component| match component.as_os_str() {
+                        dir if dir == UNIVERSAL_MACOS_TARGET => OsStr::new(triple),
+                        dir => dir,
+                    })
+                    .collect::<PathBuf>()
+                    .join(&self.binary_name)
+            })
+            .collect()
+    }
+
+    /// Returns true if the binary is built for the `--target` of another
+    /// architecture or OS than the host's, so that the tools of the host know
+    /// nothing of the libraries it links to.
+    pub fn is_cross_compiling(&self) -> bool {
+        match self.target {
+            Some((_, ref info)) => {
+                info.target_arch() != std::env::consts::ARCH
+                    || info.target_os() != std::env::consts::OS
+            }
+            None => false,
+        }
+    }
+
     /// Returns the file name of the binary being bundled.
     pub fn binary_name(&self) -> &str {
         &self.binary_name
@@ -342,6 +1653,12 @@ impl Settings {
         &self.binary_path
     }
 
+    /// Makes the bundles contain the binary at `path` instead, such as a
+    /// stripped copy of it.
+    pub(crate) fn set_binary_path(&mut self, path: PathBuf) {
+        self.binary_path = path;
+    }
+
     /// If a specific package type was specified by the command-line, returns
     /// that package type; otherwise, if a target triple was specified by the
     /// command-line, returns the native package type(s) for that target;
@@ -401,13 +1718,43 @@ impl Settings {
         self.no_default_features
     }
 
-    pub fn bundle_name(&self) -> &str {
+    /// Returns true if macOS app bundles and disk images should be notarized.
+    pub fn notarize(&self) -> bool {
+        self.notarize
+    }
+
+    /// Returns true if only bundles that are byte-for-byte reproducible should
+    /// be built.
+    pub fn reproducible(&self) -> bool {
+        self.reproducible
+    }
+
+    /// Returns the name of the Cargo package being bundled.
+    pub fn package_name(&self) -> &str {
+        &self.package.name
+    }
+
+    /// Returns the version of the Cargo package being bundled, which, unlike
+    /// the `version_string`, is never overridden.
+    pub fn package_version(&self) -> String {
+        self.package.version.to_string()
+    }
+
+    /// Returns the human-readable name of the bundle, for display purposes
+    /// (e.g. `CFBundleDisplayName` or the `Name=` of a `.desktop` file).
+    pub fn bundle_name(&self) -> &str {
         self.bundle_settings
             .name
             .as_ref()
             .unwrap_or(&self.package.name)
     }
 
+    /// Returns a version of the bundle name that is safe to use in file and
+    /// directory names.
+    pub fn bundle_file_name(&self) -> String {
+        super::common::file_name_slug(self.bundle_name())
+    }
+
     pub fn bundle_identifier(&self) -> Cow<'_, str> {
         if let Some(identifier) = &self.bundle_settings.identifier {
             identifier.into()
@@ -424,18 +1771,162 @@ impl Settings {
 
     /// Returns an iterator over the icon files to be used for this bundle.
     pub fn icon_files(&self) -> ResourcePaths<'_> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
         match self.bundle_settings.icon {
-            Some(ref paths) => ResourcePaths::new(paths.as_slice(), false),
-            None => ResourcePaths::new(&[], false),
+            Some(ref paths) => ResourcePaths::new(dir, paths.as_slice(), false),
+            None => ResourcePaths::new(dir, &[], false),
+        }
+    }
+
+    /// Makes the bundles use the icon files at `paths` instead, such as icons
+    /// generated from the one given.
+    pub(crate) fn set_icon_files(&mut self, paths: &[PathBuf]) {
+        let patterns = paths
+            .iter()
+            .map(|path| glob::Pattern::escape(&path.to_string_lossy()))
+            .collect();
+        self.bundle_settings.icon = Some(patterns);
+    }
+
+    /// Returns the pre-built executables to ship next to the binary.  For each
+    /// `external_binaries` path, a file suffixed with the target triple (e.g.
+    /// `bin/ffmpeg-x86_64-unknown-linux-gnu`) is preferred over the path itself.
+    pub fn external_binaries(&self) -> crate::Result<Vec<ExternalBinary>> {
+        let Some(ref paths) = self.bundle_settings.external_binaries else {
+            return Ok(Vec::new());
+        };
+        let triple = match self.target_triple() {
+            Some(triple) => triple.to_string(),
+            None => super::host_triple()?,
+        };
+        let exe_suffix = if triple.contains("windows") {
+            ".exe"
+        } else {
+            ""
+        };
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        paths
+            .iter()
+            .map(|path| {
+                let path = dir.join(path);
+                let Some(file_name) = path.file_name() else {
+                    anyhow::bail!("External binary {path:?} is not a file path");
+                };
+                let file_name = file_name.to_string_lossy();
+                let stem = file_name.strip_suffix(exe_suffix).unwrap_or(&file_name);
+                let name = format!("{stem}{exe_suffix}");
+                let candidates = [
+                    path.with_file_name(format!("{stem}-{triple}{exe_suffix}")),
+                    path.with_file_name(&name),
+                ];
+                match candidates.iter().find(|candidate| candidate.is_file()) {
+                    Some(src) => Ok(ExternalBinary {
+                        src: src.clone(),
+                        name,
+                    }),
+                    None => anyhow::bail!(
+                        "External binary not found at {:?} or {:?}",
+                        candidates[0],
+                        candidates[1]
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the types of documents that the app opens.
+    pub fn file_associations(&self) -> &[FileAssociation] {
+        match self.bundle_settings.file_associations {
+            Some(ref associations) => associations.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the URL schemes (e.g. `myapp` for `myapp://` URLs) that the app
+    /// handles.
+    pub fn url_schemes(&self) -> &[String] {
+        match self.bundle_settings.url_schemes {
+            Some(ref schemes) => schemes.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the path of the first icon of `association` with one of the
+    /// `extensions`, if it has one.
+    pub fn file_association_icon(
+        &self,
+        association: &FileAssociation,
+        extensions: &[&str],
+    ) -> Option<PathBuf> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        association
+            .icon
+            .iter()
+            .flatten()
+            .map(|icon| dir.join(icon))
+            .find(|icon| {
+                icon.extension()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase().as_str()))
+            })
+    }
+
+    /// Returns whether the shared libraries the binary loads are to be found
+    /// and shipped with it.
+    pub fn bundle_shared_libraries(&self) -> bool {
+        self.bundle_settings
+            .bundle_shared_libraries
+            .unwrap_or(false)
+    }
+
+    /// Returns the paths of the `man_pages`, resolved against the directory of
+    /// `Cargo.toml`.
+    pub fn man_pages(&self) -> Vec<PathBuf> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        match self.bundle_settings.man_pages {
+            Some(ref pages) => pages.iter().map(|page| dir.join(page)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the pre-generated completion scripts, by shell, with their paths
+    /// resolved against the directory of `Cargo.toml`.
+    pub fn shell_completions(&self) -> Vec<(Shell, PathBuf)> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        match self.bundle_settings.shell_completions {
+            Some(ref completions) => completions
+                .iter()
+                .map(|(shell, script)| (*shell, dir.join(script)))
+                .collect(),
+            None => Vec::new(),
         }
     }
 
     /// Returns an iterator over the resource files to be included in this
-    /// bundle.
-    pub fn resource_files(&self) -> ResourcePaths<'_> {
-        match self.bundle_settings.resources {
-            Some(ref paths) => ResourcePaths::new(paths.as_slice(), true),
-            None => ResourcePaths::new(&[], true),
+    /// bundle, along with their paths relative to the bundle's resources.
+    /// Files matching the `exclude` patterns are skipped.
+    pub fn resource_files(&self) -> ResourceFiles<'_> {
+        let entries = self.bundle_settings.resources.as_deref().unwrap_or(&[]);
+        let exclude = self
+            .bundle_settings
+            .exclude
+            .iter()
+            .flatten()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .with_context(|| format!("Invalid exclude pattern {pattern:?}"))
+            })
+            .collect::<crate::Result<Vec<_>>>();
+        let (exclude, error) = match exclude {
+            Ok(exclude) => (exclude, None),
+            Err(error) => (Vec::new(), Some(error)),
+        };
+        ResourceFiles {
+            base_dir: self.manifest_path().parent().unwrap_or(Path::new(".")),
+            entry_iter: entries.iter(),
+            current: None,
+            exclude,
+            error,
         }
     }
 
@@ -446,6 +1937,51 @@ impl Settings {
         }
     }
 
+    /// Returns where the `version_from` setting asks for the version to be
+    /// derived from, if anywhere.
+    pub fn version_from(&self) -> Option<&VersionSource> {
+        self.bundle_settings.version_from.as_ref()
+    }
+
+    /// Makes the bundles use `version` instead, such as one derived from git.
+    pub(crate) fn set_version(&mut self, version: String) {
+        self.bundle_settings.version = Some(version);
+    }
+
+    /// Returns true if the `deny_warnings` setting asks for warnings to be
+    /// treated as errors.
+    pub fn deny_warnings(&self) -> bool {
+        self.bundle_settings.deny_warnings.unwrap_or(false)
+    }
+
+    /// Returns how many times to retry signing when it fails, as contacting
+    /// a timestamp server often does transiently.  Defaults to 0.
+    pub fn timestamp_retries(&self) -> u32 {
+        self.bundle_settings.timestamp_retries.unwrap_or(0)
+    }
+
+    /// Returns how long to wait before retrying signing.  Defaults to 5
+    /// seconds.
+    pub fn timestamp_retry_delay(&self) -> Duration {
+        Duration::from_secs(self.bundle_settings.timestamp_retry_delay.unwrap_or(5))
+    }
+
+    /// Returns the build metadata to embed into the bundle, if the
+    /// `build_info` setting is enabled.
+    pub fn build_info(&self) -> Option<&BuildInfo> {
+        self.build_info.as_ref()
+    }
+
+    /// Returns the software bill of materials to embed, if the `sbom` setting
+    /// is set.
+    pub fn sbom(&self) -> Option<&Sbom> {
+        self.sbom.as_ref()
+    }
+
+    pub fn debug_symbols(&self) -> DebugSymbols {
+        self.bundle_settings.debug_symbols.unwrap_or_default()
+    }
+
     pub fn copyright_string(&self) -> Option<&str> {
         self.bundle_settings.copyright.as_deref()
     }
@@ -467,6 +2003,11 @@ impl Settings {
         self.package.homepage.as_deref().unwrap_or("")
     }
 
+    /// Returns the URL of the package's source repository, if it has one.
+    pub fn repository_url(&self) -> Option<&str> {
+        self.package.repository.as_deref()
+    }
+
     pub fn app_category(&self) -> Option<AppCategory> {
         self.bundle_settings.category
     }
@@ -478,22 +2019,89 @@ impl Settings {
             .unwrap_or_else(|| self.package.description.as_deref().unwrap_or(""))
     }
 
+    /// Returns the texts describing the app in other languages, by language
+    /// tag (e.g. `fr` or `pt-BR`).
+    pub fn localizations(&self) -> &BTreeMap<String, Localization> {
+        static EMPTY: BTreeMap<String, Localization> = BTreeMap::new();
+        self.bundle_settings
+            .localizations
+            .as_ref()
+            .unwrap_or(&EMPTY)
+    }
+
+    /// Returns the localization for the language with the tag `locale`, or
+    /// else for its primary language (e.g. `fr` for `fr-CA`).
+    fn localization(&self, locale: &str) -> Option<&Localization> {
+        let localizations = self.localizations();
+        let language = locale.split(['-', '_']).next().unwrap_or(locale);
+        localizations
+            .get(locale)
+            .or_else(|| localizations.get(language))
+    }
+
+    /// Returns the bundle name in the language with the tag `locale`.
+    pub fn localized_bundle_name(&self, locale: &str) -> &str {
+        self.localization(locale)
+            .and_then(|localization| localization.name.as_deref())
+            .unwrap_or_else(|| self.bundle_name())
+    }
+
+    /// Returns the short description in the language with the tag `locale`.
+    pub fn localized_short_description(&self, locale: &str) -> &str {
+        self.localization(locale)
+            .and_then(|localization| localization.short_description.as_deref())
+            .unwrap_or_else(|| self.short_description())
+    }
+
     pub fn long_description(&self) -> Option<&str> {
         self.bundle_settings.long_description.as_deref()
     }
 
+    /// Returns the template used to rename produced artifacts, if any.
+    pub fn artifact_name_template(&self) -> Option<&str> {
+        self.bundle_settings.artifact_name_template.as_deref()
+    }
+
+    /// Returns the template of the URL that Homebrew should download the
+    /// produced artifacts from, if a cask or formula should be generated.
+    pub fn homebrew_url(&self) -> Option<&str> {
+        self.bundle_settings.homebrew_url.as_deref()
+    }
+
+    /// Returns the template of the URL that Scoop should download the
+    /// produced artifacts from, if a manifest should be generated.
+    pub fn scoop_url(&self) -> Option<&str> {
+        self.bundle_settings.scoop_url.as_deref()
+    }
+
+    /// Returns the template of the URL that winget should download the
+    /// produced installers from, if manifests should be generated.
+    pub fn winget_url(&self) -> Option<&str> {
+        self.bundle_settings.winget_url.as_deref()
+    }
+
+    /// Returns the winget package identifier, if set.
+    pub fn winget_identifier(&self) -> Option<&str> {
+        self.bundle_settings.winget_identifier.as_deref()
+    }
+
+    /// Returns the package's license, as an SPDX expression.
+    pub fn license(&self) -> Option<&str> {
+        self.package.license.as_deref()
+    }
+
+    /// Returns the path of the package's `license-file`, resolved against the
+    /// directory of `Cargo.toml`, if it has one.
+    pub fn license_file(&self) -> Option<PathBuf> {
+        let license_file = self.package.license_file.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(license_file))
+    }
+
     pub fn license_content(&self) -> Option<String> {
-        self.package
-            .license_file
-            .as_ref()
-            .and_then(|license_file| {
-                let dir = self
-                    .manifest_path()
-                    .parent()
-                    .unwrap_or_else(|| Path::new("."));
-
-                let license_path = dir.join(license_file);
-                match std::fs::read_to_string(&license_path) {
+        self.license_file()
+            .and_then(
+                |license_path| match std::fs::read_to_string(&license_path) {
                     Ok(content) => Some(content),
                     Err(err) => {
                         print_warning(&format!(
@@ -502,8 +2110,8 @@ impl Settings {
                         .ok();
                         None
                     }
-                }
-            })
+                },
+            )
             .or_else(|| self.package.license.as_ref().map(|s| s.to_string()))
     }
 
@@ -513,72 +2121,1388 @@ impl Settings {
             None => &[],
         }
     }
-
-    pub fn linux_mime_types(&self) -> &[String] {
-        match self.bundle_settings.linux_mime_types {
-            Some(ref mime_types) => mime_types.as_slice(),
-            None => &[],
-        }
+
+    /// Returns the kind of GPG signature to make for deb packages, or `None`
+    /// to leave them unsigned.  Setting a GPG key alone embeds a signature.
+    pub fn deb_signature(&self) -> Option<DebSignature> {
+        match self.bundle_settings.deb_signature {
+            Some(signature) => Some(signature),
+            None if self.bundle_settings.gpg_key.is_some() => Some(DebSignature::Origin),
+            None => None,
+        }
+    }
+
+    /// Returns the contents of the deb maintainer script called `name`, e.g.
+    /// `postinst` for the `deb_postinst` setting, reading it from its file if
+    /// the setting is a path.
+    pub fn deb_maintainer_script(&self, name: &str) -> crate::Result<Option<String>> {
+        let script = match name {
+            "preinst" => &self.bundle_settings.deb_preinst,
+            "postinst" => &self.bundle_settings.deb_postinst,
+            "prerm" => &self.bundle_settings.deb_prerm,
+            "postrm" => &self.bundle_settings.deb_postrm,
+            _ => return Ok(None),
+        };
+        self.read_script(script.as_ref(), name)
+    }
+
+    /// Returns the contents of the rpm scriptlet called `name`, e.g. `post`
+    /// for the `rpm_post` setting, reading it from its file if the setting is
+    /// a path.
+    pub fn rpm_scriptlet(&self, name: &str) -> crate::Result<Option<String>> {
+        let script = match name {
+            "pre" => &self.bundle_settings.rpm_pre,
+            "post" => &self.bundle_settings.rpm_post,
+            "preun" => &self.bundle_settings.rpm_preun,
+            "postun" => &self.bundle_settings.rpm_postun,
+            _ => return Ok(None),
+        };
+        self.read_script(script.as_ref(), name)
+    }
+
+    /// Returns the packages that rpm packages require, e.g. `openssl-libs >= 3`,
+    /// besides those found by `linux_auto_depends`.
+    pub fn rpm_requires(&self) -> &[String] {
+        self.bundle_settings
+            .rpm_requires
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Returns the capabilities that rpm packages provide besides their name.
+    pub fn rpm_provides(&self) -> &[String] {
+        self.bundle_settings
+            .rpm_provides
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    pub fn rpm_conflicts(&self) -> &[String] {
+        self.bundle_settings
+            .rpm_conflicts
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Returns the packages that rpm packages replace, e.g. under an old name.
+    pub fn rpm_obsoletes(&self) -> &[String] {
+        self.bundle_settings
+            .rpm_obsoletes
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    pub fn rpm_epoch(&self) -> Option<u32> {
+        self.bundle_settings.rpm_epoch
+    }
+
+    pub fn rpm_group(&self) -> &str {
+        self.bundle_settings
+            .rpm_group
+            .as_deref()
+            .unwrap_or("Unspecified")
+    }
+
+    /// Returns the URL of rpm packages: the `rpm_url` setting, or else the
+    /// homepage or the repository of the package.
+    pub fn rpm_url(&self) -> Option<&str> {
+        self.bundle_settings
+            .rpm_url
+            .as_deref()
+            .or(Some(self.homepage_url()).filter(|url| !url.is_empty()))
+            .or(self.repository_url())
+    }
+
+    /// Returns true if the `srpm` bundler should build the source RPM with
+    /// `rpmbuild`, besides writing the spec file and its sources.
+    pub fn rpm_srpm(&self) -> bool {
+        self.bundle_settings.rpm_srpm.unwrap_or(false)
+    }
+
+    fn read_script(
+        &self,
+        script: Option<&MaintainerScript>,
+        name: &str,
+    ) -> crate::Result<Option<String>> {
+        match script {
+            None => Ok(None),
+            Some(MaintainerScript::Inline { script }) => Ok(Some(script.clone())),
+            Some(MaintainerScript::Path(path)) => {
+                let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+                let path = dir.join(path);
+                let script = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read the {name} script {path:?}"))?;
+                Ok(Some(script))
+            }
+        }
+    }
+
+    /// Returns the configuration files that deb packages install, as pairs of
+    /// the installed path (e.g. `/etc/foobar/config.toml`) and the path of the
+    /// default file, resolved against the directory of `Cargo.toml`.
+    pub fn deb_conffiles(&self) -> Vec<(&str, PathBuf)> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        match self.bundle_settings.deb_conffiles {
+            Some(ref conffiles) => conffiles
+                .iter()
+                .map(|(dest, src)| (dest.as_str(), dir.join(src)))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the path of the changelog that deb packages ship, resolved
+    /// against the directory of `Cargo.toml`, if the `deb_changelog` setting
+    /// gives one.
+    pub fn deb_changelog(&self) -> Option<PathBuf> {
+        let changelog = self.bundle_settings.deb_changelog.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(changelog))
+    }
+
+    /// Returns the compression of the members of deb packages, which is gzip
+    /// by default.
+    pub fn deb_compression(&self) -> DebCompression {
+        self.bundle_settings.deb_compression.unwrap_or_default()
+    }
+
+    /// Returns the level to compress the members of deb packages at, or
+    /// `None` for the default of the compression.
+    pub fn deb_compression_level(&self) -> Option<u32> {
+        self.bundle_settings.deb_compression_level
+    }
+
+    /// Returns the section of the Debian archive that deb packages are filed
+    /// under: the `deb_section` setting, or else the closest section to the
+    /// `category`, or `utils`.
+    pub fn deb_section(&self) -> &str {
+        match self.bundle_settings.deb_section {
+            Some(ref section) => section,
+            None => self
+                .app_category()
+                .map_or("utils", |category| category.debian_section()),
+        }
+    }
+
+    /// Returns the priority of deb packages, which is `optional` by default.
+    pub fn deb_priority(&self) -> &str {
+        self.bundle_settings
+            .deb_priority
+            .as_deref()
+            .unwrap_or("optional")
+    }
+
+    /// Returns the id of the GPG key to sign packages with, or `None` to use
+    /// the default key of `gpg`.
+    pub fn gpg_key(&self) -> Option<&str> {
+        self.bundle_settings.gpg_key.as_deref()
+    }
+
+    pub fn ipk_dependencies(&self) -> &[String] {
+        match self.bundle_settings.ipk_depends {
+            Some(ref dependencies) => dependencies.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the OpenWrt package architecture (e.g. `aarch64_cortex-a53`),
+    /// or `None` to derive it from the target architecture.
+    pub fn ipk_arch(&self) -> Option<&str> {
+        self.bundle_settings.ipk_arch.as_deref()
+    }
+
+    pub fn pacman_dependencies(&self) -> &[String] {
+        match self.bundle_settings.pacman_depends {
+            Some(ref dependencies) => dependencies.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the template of the URL that the AUR package downloads the
+    /// archive from, if any.
+    pub fn aur_source_url(&self) -> Option<&str> {
+        self.bundle_settings.aur_source_url.as_deref()
+    }
+
+    pub fn apk_dependencies(&self) -> &[String] {
+        match self.bundle_settings.apk_depends {
+            Some(ref dependencies) => dependencies.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the path of the RSA private key to sign apk packages with,
+    /// relative to the directory containing `Cargo.toml`.
+    pub fn apk_signing_key(&self) -> Option<PathBuf> {
+        let key = self.bundle_settings.apk_signing_key.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(key))
+    }
+
+    /// Returns the nixpkgs attributes of the libraries the binary links
+    /// against, e.g. `gtk3`.
+    pub fn nix_build_inputs(&self) -> &[String] {
+        match self.bundle_settings.nix_build_inputs {
+            Some(ref inputs) => inputs.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the major version of FreeBSD that packages are built for, if
+    /// set.
+    pub fn freebsd_version(&self) -> Option<&str> {
+        self.bundle_settings.freebsd_version.as_deref()
+    }
+
+    /// Returns the path of the template of the service unit in a portable
+    /// service image, relative to the directory containing `Cargo.toml`.
+    pub fn portable_unit(&self) -> Option<PathBuf> {
+        let unit = self.bundle_settings.portable_unit.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(unit))
+    }
+
+    /// Returns the ports an OCI image exposes, e.g. `8080/tcp`.
+    pub fn oci_exposed_ports(&self) -> &[String] {
+        match self.bundle_settings.oci_exposed_ports {
+            Some(ref ports) => ports.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn linux_mime_types(&self) -> &[String] {
+        match self.bundle_settings.linux_mime_types {
+            Some(ref mime_types) => mime_types.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn linux_use_terminal(&self) -> Option<bool> {
+        self.bundle_settings.linux_use_terminal
+    }
+
+    pub fn linux_exec_args(&self) -> Option<&str> {
+        self.bundle_settings.linux_exec_args.as_deref()
+    }
+
+    /// Returns the words the app is found by in application launchers, besides
+    /// its name.
+    pub fn linux_keywords(&self) -> &[String] {
+        match self.bundle_settings.linux_keywords {
+            Some(ref keywords) => keywords.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the `WM_CLASS` of the app's windows, which desktops match them
+    /// to its launcher by.
+    pub fn linux_startup_wm_class(&self) -> Option<&str> {
+        self.bundle_settings.linux_startup_wm_class.as_deref()
+    }
+
+    /// Returns whether the app is hidden from application launchers, e.g.
+    /// because it is only run to open files.
+    pub fn linux_no_display(&self) -> bool {
+        self.bundle_settings.linux_no_display.unwrap_or(false)
+    }
+
+    pub fn linux_desktop_actions(&self) -> &[DesktopAction] {
+        match self.bundle_settings.linux_desktop_actions {
+            Some(ref actions) => actions.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the additional keys of the `[Desktop Entry]` group, which take
+    /// precedence over the generated ones.
+    pub fn linux_desktop_entries(&self) -> &BTreeMap<String, String> {
+        static EMPTY: BTreeMap<String, String> = BTreeMap::new();
+        self.bundle_settings
+            .linux_desktop_entries
+            .as_ref()
+            .unwrap_or(&EMPTY)
+    }
+
+    /// Returns true if the packages providing the shared libraries the binary
+    /// needs should be added to the dependencies of deb and rpm packages.
+    pub fn linux_auto_depends(&self) -> bool {
+        self.bundle_settings.linux_auto_depends.unwrap_or(false)
+    }
+
+    /// Returns the path of the AppStream metainfo file to install instead of
+    /// the generated one, resolved against the directory of `Cargo.toml`.
+    pub fn linux_metainfo(&self) -> Option<PathBuf> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        self.bundle_settings
+            .linux_metainfo
+            .as_ref()
+            .map(|path| dir.join(path))
+    }
+
+    pub fn linux_screenshots(&self) -> &[Screenshot] {
+        match self.bundle_settings.linux_screenshots {
+            Some(ref screenshots) => screenshots.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the paths of the systemd unit files to install, resolved
+    /// against the directory of `Cargo.toml`.
+    pub fn linux_systemd_units(&self) -> Vec<PathBuf> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        match self.bundle_settings.linux_systemd_units {
+            Some(ref units) => units.iter().map(|unit| dir.join(unit)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns true if the systemd units should be enabled when the package
+    /// is installed, which they are by default.
+    pub fn linux_systemd_enable(&self) -> bool {
+        self.bundle_settings.linux_systemd_enable.unwrap_or(true)
+    }
+
+    /// Returns true if the systemd units should be started when the package
+    /// is installed (and restarted when it is upgraded), which they are by
+    /// default.
+    pub fn linux_systemd_start(&self) -> bool {
+        self.bundle_settings.linux_systemd_start.unwrap_or(true)
+    }
+
+    /// Returns the squashfs compression to use for AppImage bundles, or `None`
+    /// to use the default of `mksquashfs`.
+    pub fn appimage_compression(&self) -> Option<AppImageCompression> {
+        self.bundle_settings.appimage_compression
+    }
+
+    pub fn flatpak_runtime(&self) -> Option<&str> {
+        self.bundle_settings.flatpak_runtime.as_deref()
+    }
+
+    pub fn flatpak_runtime_version(&self) -> Option<&str> {
+        self.bundle_settings.flatpak_runtime_version.as_deref()
+    }
+
+    pub fn flatpak_sdk(&self) -> Option<&str> {
+        self.bundle_settings.flatpak_sdk.as_deref()
+    }
+
+    /// Returns the sandbox permissions to grant the Flatpak app, or `None` to
+    /// use the defaults.
+    pub fn flatpak_finish_args(&self) -> Option<&[String]> {
+        self.bundle_settings.flatpak_finish_args.as_deref()
+    }
+
+    pub fn snap_base(&self) -> Option<&str> {
+        self.bundle_settings.snap_base.as_deref()
+    }
+
+    pub fn snap_grade(&self) -> Option<SnapGrade> {
+        self.bundle_settings.snap_grade
+    }
+
+    pub fn snap_confinement(&self) -> Option<SnapConfinement> {
+        self.bundle_settings.snap_confinement
+    }
+
+    /// Returns the interfaces the snap's app plugs into, or `None` to use the
+    /// defaults.
+    pub fn snap_plugs(&self) -> Option<&[String]> {
+        self.bundle_settings.snap_plugs.as_deref()
+    }
+
+    pub fn osx_frameworks(&self) -> &[String] {
+        match self.bundle_settings.osx_frameworks {
+            Some(ref frameworks) => frameworks.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn osx_plugins(&self) -> &[String] {
+        match self.bundle_settings.osx_plugins {
+            Some(ref plugins) => plugins.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn osx_minimum_system_version(&self) -> Option<&str> {
+        self.bundle_settings.osx_minimum_system_version.as_deref()
+    }
+
+    pub fn osx_url_schemes(&self) -> &[String] {
+        match self.bundle_settings.osx_url_schemes {
+            Some(ref urlosx_url_schemes) => urlosx_url_schemes.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns an iterator over the plist files for this bundle
+    pub fn osx_info_plist_exts(&self) -> ResourcePaths<'_> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        match self.bundle_settings.osx_info_plist_exts {
+            Some(ref paths) => ResourcePaths::new(dir, paths.as_slice(), false),
+            None => ResourcePaths::new(dir, &[], false),
+        }
+    }
+
+    /// Returns the keys (e.g. `LSUIElement`) to merge on top of the generated
+    /// `Info.plist`, replacing any generated values of the same keys.
+    pub fn osx_info_plist(&self) -> Option<&toml::Table> {
+        self.bundle_settings.osx_info_plist.as_ref()
+    }
+
+    /// Returns the Sparkle settings of the app, if it updates itself.
+    pub fn osx_sparkle(&self) -> Option<&SparkleSettings> {
+        self.bundle_settings.osx_sparkle.as_ref()
+    }
+
+    /// Returns the path of the Sparkle framework to embed in the app, if any,
+    /// resolved against the directory containing `Cargo.toml`.
+    pub fn osx_sparkle_framework(&self) -> Option<PathBuf> {
+        let sparkle = self.osx_sparkle()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(&sparkle.framework))
+    }
+
+    /// Returns the helper apps to embed in the macOS app.
+    pub fn osx_helpers(&self) -> &[OsxHelper] {
+        self.bundle_settings
+            .osx_helpers
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Returns the settings of the app bundle of `helper`: those of its
+    /// `[package.metadata.bundle.bin.<name>]` section, for its binary, built
+    /// along with that of the app.
+    pub fn osx_helper_settings(&self, helper: &OsxHelper) -> crate::Result<Settings> {
+        Settings::check_target_exists(&self.package, TargetKind::Bin, &helper.bin)?;
+        let mut settings = self.clone();
+        settings.bundle_settings = bundle_settings_from_table(
+            &self.bundle_settings.bin,
+            &helper.bin,
+            &self.bundle_settings,
+        );
+        settings.build_artifact = BuildArtifact::Bin(helper.bin.clone());
+        settings.binary_name = helper.bin.clone();
+        // Examples are built into an `examples` directory of their own.
+        let dir = match self.build_artifact {
+            BuildArtifact::Example(_) => self.project_out_directory.parent().unwrap(),
+            _ => &self.project_out_directory,
+        };
+        settings.binary_path = dir.join(&helper.bin);
+        Ok(settings)
+    }
+
+    /// Returns the path of the background image of the `dmg` window, resolved
+    /// against the directory of `Cargo.toml`.
+    pub fn dmg_background(&self) -> Option<PathBuf> {
+        let image = self.bundle_settings.dmg_background.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(image))
+    }
+
+    /// Returns the path of the `.icns` icon of the `dmg` volume, resolved
+    /// against the directory of `Cargo.toml`.
+    pub fn dmg_volume_icon(&self) -> Option<PathBuf> {
+        let icon = self.bundle_settings.dmg_volume_icon.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(icon))
+    }
+
+    /// Returns the width and height of the `dmg` window.  Defaults to 660x400.
+    pub fn dmg_window_size(&self) -> [u32; 2] {
+        self.bundle_settings.dmg_window_size.unwrap_or([660, 400])
+    }
+
+    /// Returns the size of the icons in the `dmg` window.  Defaults to 128.
+    pub fn dmg_icon_size(&self) -> u32 {
+        self.bundle_settings.dmg_icon_size.unwrap_or(128)
+    }
+
+    /// Returns the position of the center of the app's icon in the `dmg`
+    /// window.  Defaults to `[180, 170]`.
+    pub fn dmg_app_position(&self) -> [u32; 2] {
+        self.bundle_settings.dmg_app_position.unwrap_or([180, 170])
+    }
+
+    /// Returns the position of the center of the `Applications` symlink's icon
+    /// in the `dmg` window.  Defaults to `[480, 170]`.
+    pub fn dmg_applications_position(&self) -> [u32; 2] {
+        self.bundle_settings
+            .dmg_applications_position
+            .unwrap_or([480, 170])
+    }
+
+    /// Returns true if any of the `dmg_*` settings customizes the appearance
+    /// of the `dmg` window or volume.
+    pub fn dmg_customized(&self) -> bool {
+        let settings = &self.bundle_settings;
+        settings.dmg_background.is_some()
+            || settings.dmg_volume_icon.is_some()
+            || settings.dmg_window_size.is_some()
+            || settings.dmg_icon_size.is_some()
+            || settings.dmg_app_position.is_some()
+            || settings.dmg_applications_position.is_some()
+    }
+
+    /// Returns the directory a `pkg` installer places the app bundle in, or
+    /// `None` to use `/Applications`.
+    pub fn osx_pkg_install_location(&self) -> Option<&str> {
+        self.bundle_settings.osx_pkg_install_location.as_deref()
+    }
+
+    /// Returns the identity to sign macOS app bundles with (e.g.
+    /// `Developer ID Application: Example Inc. (TEAMID)`, or `-` for ad-hoc
+    /// signing), or `None` to leave them unsigned.
+    pub fn osx_signing_identity(&self) -> Option<&str> {
+        self.bundle_settings.osx_signing_identity.as_deref()
+    }
+
+    /// Returns the entitlements to sign the app with, if any.  The path of an
+    /// entitlements file is relative to the directory containing `Cargo.toml`.
+    pub fn osx_entitlements(&self) -> Option<OsxEntitlements<'_>> {
+        match self.bundle_settings.osx_entitlements.as_ref()? {
+            EntitlementsSetting::Path(path) => {
+                let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+                Some(OsxEntitlements::File(dir.join(path)))
+            }
+            EntitlementsSetting::Keys(keys) => Some(OsxEntitlements::Keys(keys)),
+        }
+    }
+
+    /// Returns the App Sandbox settings of the macOS app, if it is sandboxed.
+    pub fn osx_sandbox(&self) -> Option<&SandboxSettings> {
+        self.bundle_settings.osx_sandbox.as_ref()
+    }
+
+    /// Returns true if macOS apps should be signed with the hardened runtime,
+    /// which notarization requires.  Defaults to true.
+    pub fn osx_hardened_runtime(&self) -> bool {
+        self.bundle_settings.osx_hardened_runtime.unwrap_or(true)
+    }
+
+    /// Returns the tool to sign macOS bundles with, which defaults to
+    /// `codesign` on macOS and `rcodesign` elsewhere.
+    pub fn osx_signing_tool(&self) -> OsxSigningTool {
+        match self.bundle_settings.osx_signing_tool {
+            Some(tool) => tool,
+            None if cfg!(target_os = "macos") => OsxSigningTool::Codesign,
+            None => OsxSigningTool::Rcodesign,
+        }
+    }
+
+    /// Returns the path of the PKCS #12 certificate file that `rcodesign`
+    /// signs with, relative to the directory containing `Cargo.toml`.
+    pub fn osx_signing_certificate(&self) -> Option<PathBuf> {
+        let certificate = self.bundle_settings.osx_signing_certificate.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(certificate))
+    }
+
+    /// Returns the URL of the RFC 3161 timestamp server to timestamp macOS
+    /// signatures with, or `None` to use Apple's.
+    pub fn osx_timestamp_url(&self) -> Option<&str> {
+        self.bundle_settings.osx_timestamp_url.as_deref()
+    }
+
+    /// Returns the PIV smartcard slot (e.g. `9c`) holding the key that
+    /// `rcodesign` signs with, used instead of `osx_signing_certificate`.
+    pub fn osx_smartcard_slot(&self) -> Option<&str> {
+        self.bundle_settings.osx_smartcard_slot.as_deref()
+    }
+
+    /// Returns the identity to sign iOS apps with (e.g. `Apple Distribution:
+    /// Example Inc. (TEAMID)`), or `None` to leave them unsigned.
+    pub fn ios_signing_identity(&self) -> Option<&str> {
+        self.bundle_settings.ios_signing_identity.as_deref()
+    }
+
+    /// Returns the path of the provisioning profile to embed in iOS apps,
+    /// relative to the directory containing `Cargo.toml`.
+    pub fn ios_provisioning_profile(&self) -> Option<PathBuf> {
+        let profile = self.bundle_settings.ios_provisioning_profile.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(profile))
+    }
+
+    /// Returns the path of the entitlements file to sign iOS apps with,
+    /// relative to the directory containing `Cargo.toml`.
+    pub fn ios_entitlements(&self) -> Option<PathBuf> {
+        let entitlements = self.bundle_settings.ios_entitlements.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(entitlements))
+    }
+
+    /// Returns the path of the asset catalog (an `.xcassets` directory, or an
+    /// `Assets.car` compiled already) holding the `AppIcon` of iOS apps,
+    /// relative to the directory containing `Cargo.toml`.
+    pub fn ios_asset_catalog(&self) -> Option<PathBuf> {
+        let catalog = self.bundle_settings.ios_asset_catalog.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(catalog))
+    }
+
+    /// Returns the path of the launch storyboard of iOS apps (a `.storyboard`,
+    /// or a `.storyboardc` compiled already), relative to the directory
+    /// containing `Cargo.toml`.
+    pub fn ios_launch_storyboard(&self) -> Option<PathBuf> {
+        let storyboard = self.bundle_settings.ios_launch_storyboard.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(storyboard))
+    }
+
+    /// Returns the identity to sign Mac App Store apps with (e.g. `3rd Party
+    /// Mac Developer Application: Example Inc. (TEAMID)`).
+    pub fn appstore_signing_identity(&self) -> Option<&str> {
+        self.bundle_settings.appstore_signing_identity.as_deref()
+    }
+
+    /// Returns the identity to sign Mac App Store installer packages with
+    /// (e.g. `3rd Party Mac Developer Installer: Example Inc. (TEAMID)`).
+    pub fn appstore_installer_identity(&self) -> Option<&str> {
+        self.bundle_settings.appstore_installer_identity.as_deref()
+    }
+
+    /// Returns the path of the provisioning profile to embed in Mac App Store
+    /// apps, relative to the directory containing `Cargo.toml`.
+    pub fn appstore_provisioning_profile(&self) -> Option<PathBuf> {
+        let profile = self
+            .bundle_settings
+            .appstore_provisioning_profile
+            .as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(profile))
+    }
+
+    /// Returns the default installation directory for an NSIS installer, as
+    /// an NSIS path (e.g. `$LOCALAPPDATA\Programs\Foo`), or `None` to install
+    /// into Program Files.
+    pub fn nsis_install_dir(&self) -> Option<&str> {
+        self.bundle_settings.nsis_install_dir.as_deref()
+    }
+
+    /// Returns the tag of the language that the `msi` and `wxsmsi` installers
+    /// are built for.
+    pub fn msi_language(&self) -> &str {
+        self.bundle_settings
+            .msi_language
+            .as_deref()
+            .unwrap_or("en-US")
+    }
+
+    /// Returns the tags of the languages that the `msi` and `wxsmsi`
+    /// installers are built for, one installer each: the `msi_languages`, or
+    /// else the `msi_language`.
+    pub fn msi_languages(&self) -> Vec<String> {
+        match &self.bundle_settings.msi_languages {
+            Some(languages) if !languages.is_empty() => languages.clone(),
+            _ => vec![self.msi_language().to_string()],
+        }
+    }
+
+    /// Returns a copy of the settings for building the `msi` and `wxsmsi`
+    /// installers in the given `language`, one of the `msi_languages`.
+    pub fn for_msi_language(&self, language: &str) -> Settings {
+        let mut settings = self.clone();
+        settings.bundle_settings.msi_language = Some(language.to_string());
+        settings
+    }
+
+    /// Returns the upgrade code that the `msi` and `wxsmsi` installers share
+    /// across the versions of the product, if it is set.
+    pub fn msi_upgrade_code(&self) -> crate::Result<Option<uuid::Uuid>> {
+        let Some(code) = &self.bundle_settings.msi_upgrade_code else {
+            return Ok(None);
+        };
+        let uuid = uuid::Uuid::parse_str(code.trim_matches(['{', '}']))
+            .with_context(|| format!("Invalid `msi_upgrade_code` {code:?}"))?;
+        Ok(Some(uuid))
+    }
+
+    /// Returns true if the `msi` and `wxsmsi` installers create a Start Menu
+    /// shortcut to the binary.
+    pub fn msi_start_menu_shortcut(&self) -> bool {
+        self.bundle_settings.msi_start_menu_shortcut.unwrap_or(true)
+    }
+
+    /// Returns true if the `msi` and `wxsmsi` installers create a desktop
+    /// shortcut to the binary.
+    pub fn msi_desktop_shortcut(&self) -> bool {
+        self.bundle_settings.msi_desktop_shortcut.unwrap_or(false)
+    }
+
+    /// Returns the arguments that the shortcuts of the `msi` and `wxsmsi`
+    /// installers run the binary with, if any.
+    pub fn msi_shortcut_args(&self) -> Option<&str> {
+        self.bundle_settings.msi_shortcut_args.as_deref()
+    }
+
+    /// Returns the publisher that Add/Remove Programs shows for the `msi` and
+    /// `wxsmsi` installers, if it is set.
+    pub fn msi_publisher(&self) -> Option<&str> {
+        self.bundle_settings.msi_publisher.as_deref()
+    }
+
+    /// Returns the help URL that Add/Remove Programs shows for the `msi` and
+    /// `wxsmsi` installers, if any.
+    pub fn msi_help_url(&self) -> Option<&str> {
+        self.bundle_settings.msi_help_url.as_deref()
+    }
+
+    /// Returns the URL of updates that Add/Remove Programs shows for the `msi`
+    /// and `wxsmsi` installers, if any.
+    pub fn msi_update_url(&self) -> Option<&str> {
+        self.bundle_settings.msi_update_url.as_deref()
+    }
+
+    /// Returns true if Add/Remove Programs has no "Modify" button for the
+    /// installed product.
+    pub fn msi_no_modify(&self) -> bool {
+        self.bundle_settings.msi_no_modify.unwrap_or(false)
+    }
+
+    /// Returns true if Add/Remove Programs has no "Repair" button for the
+    /// installed product.
+    pub fn msi_no_repair(&self) -> bool {
+        self.bundle_settings.msi_no_repair.unwrap_or(false)
+    }
+
+    pub fn nsis_desktop_shortcut(&self) -> bool {
+        self.bundle_settings.nsis_desktop_shortcut.unwrap_or(false)
+    }
+
+    /// Returns the path of the bitmap shown at the top of the pages of an NSIS
+    /// installer, resolved against the directory of `Cargo.toml`.
+    pub fn nsis_header_image(&self) -> Option<PathBuf> {
+        let image = self.bundle_settings.nsis_header_image.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(image))
+    }
+
+    /// Returns the path of the bitmap shown on the welcome and finish pages of
+    /// an NSIS installer, resolved against the directory of `Cargo.toml`.
+    pub fn nsis_side_image(&self) -> Option<PathBuf> {
+        let image = self.bundle_settings.nsis_side_image.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(image))
+    }
+
+    /// Returns the path of the WiX source that replaces the generated main
+    /// source of a `wxsmsi` installer, if any.
+    pub fn wix_template(&self) -> Option<PathBuf> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        self.bundle_settings
+            .wix_template
+            .as_ref()
+            .map(|path| dir.join(path))
+    }
+
+    /// Returns the paths of the additional WiX sources compiled into a
+    /// `wxsmsi` installer.
+    pub fn wix_fragments(&self) -> Vec<PathBuf> {
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        let fragments = self.bundle_settings.wix_fragments.as_deref();
+        fragments
+            .unwrap_or_default()
+            .iter()
+            .map(|path| dir.join(path))
+            .collect()
+    }
+
+    /// Returns the ids of the component groups of the `wix_fragments` that the
+    /// feature of a generated `wxsmsi` installer installs.
+    pub fn wix_component_groups(&self) -> &[String] {
+        self.bundle_settings
+            .wix_component_groups
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Returns the publisher of an MSIX package, which must match the subject
+    /// of the certificate it is signed with (e.g. `CN=Example Inc.`).
+    pub fn msix_publisher(&self) -> Option<&str> {
+        self.bundle_settings.msix_publisher.as_deref()
+    }
+
+    pub fn msix_publisher_display_name(&self) -> Option<&str> {
+        self.bundle_settings.msix_publisher_display_name.as_deref()
+    }
+
+    /// Returns the settings for signing Windows binaries and installers, if
+    /// they should be signed.
+    pub fn windows_sign(&self) -> Option<&WindowsSignSettings> {
+        self.bundle_settings.windows.as_ref()?.sign.as_ref()
+    }
+
+    /// Returns the Windows services that the binary is registered as.
+    pub fn windows_services(&self) -> &[WindowsService] {
+        self.bundle_settings
+            .windows
+            .as_ref()
+            .and_then(|windows| windows.services.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the installers of the runtimes that the app needs, which a
+    /// `setup.exe` runs before the `wxsmsi` installer.
+    pub fn windows_prerequisites(&self) -> &[Prerequisite] {
+        self.bundle_settings
+            .windows
+            .as_ref()
+            .and_then(|windows| windows.prerequisites.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the registry values that the Windows installers write.
+    pub fn windows_registry(&self) -> &[RegistryEntry] {
+        self.bundle_settings
+            .windows
+            .as_ref()
+            .and_then(|windows| windows.registry.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the path of the RTF license agreement that the Windows
+    /// installers show, resolved against the directory of `Cargo.toml`, if the
+    /// `windows` table has a `license`.
+    pub fn windows_license(&self) -> Option<PathBuf> {
+        let license = self.bundle_settings.windows.as_ref()?.license.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(license))
+    }
+
+    /// Returns the path of the bitmap shown across the top of the installer
+    /// dialogs, resolved against the directory of `Cargo.toml`, if the
+    /// `windows` table has a `banner_image`.
+    pub fn windows_banner_image(&self) -> Option<PathBuf> {
+        let image = self
+            .bundle_settings
+            .windows
+            .as_ref()?
+            .banner_image
+            .as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(image))
+    }
+
+    /// Returns the path of the bitmap shown in the background of the welcome
+    /// and completion dialogs of the installers, resolved against the
+    /// directory of `Cargo.toml`, if the `windows` table has a `dialog_image`.
+    pub fn windows_dialog_image(&self) -> Option<PathBuf> {
+        let image = self
+            .bundle_settings
+            .windows
+            .as_ref()?
+            .dialog_image
+            .as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(image))
+    }
+
+    /// Returns whether the Windows installers show a license agreement dialog,
+    /// which the user has to accept to install the app.
+    pub fn windows_license_dialog(&self) -> bool {
+        self.bundle_settings
+            .windows
+            .as_ref()
+            .and_then(|windows| windows.license_dialog)
+            .unwrap_or(true)
+    }
+
+    /// Returns whom the Windows installers install the app for.
+    pub fn windows_install_scope(&self) -> InstallScope {
+        self.bundle_settings
+            .windows
+            .as_ref()
+            .and_then(|windows| windows.install_scope)
+            .unwrap_or_default()
+    }
+
+    /// Returns the minimum Android API level the app supports.
+    pub fn android_min_sdk(&self) -> u32 {
+        self.bundle_settings.android_min_sdk.unwrap_or(23)
+    }
+
+    /// Returns the Android API level the app targets, or `None` to target the
+    /// newest platform installed in the Android SDK.
+    pub fn android_target_sdk(&self) -> Option<u32> {
+        self.bundle_settings.android_target_sdk
+    }
+
+    /// Returns the Android permissions the app requests, e.g.
+    /// `android.permission.INTERNET`.
+    pub fn android_permissions(&self) -> &[String] {
+        match self.bundle_settings.android_permissions {
+            Some(ref permissions) => permissions.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the path of the keystore to sign APKs with, relative to the
+    /// directory containing `Cargo.toml`.
+    pub fn android_keystore(&self) -> Option<PathBuf> {
+        let keystore = self.bundle_settings.android_keystore.as_ref()?;
+        let dir = self.manifest_path().parent().unwrap_or(Path::new("."));
+        Some(dir.join(keystore))
+    }
+}
+
+/// Warns if two of the given bundles share a bundle identifier.  Packages with
+/// the same identifier overwrite each other when installed (and confuse Launch
+/// Services on macOS), so this should run after the settings for every package
+/// have been resolved, but before anything is bundled.  With `--deny-warnings`,
+/// the warning fails the build, right away or once bundling has finished.
+pub fn check_identifier_collisions(all_settings: &[Settings]) -> crate::Result<()> {
+    match identifier_collisions_message(all_settings) {
+        Some(message) => print_warning(&message),
+        None => Ok(()),
+    }
+}
+
+// Describes the bundle identifiers shared by several of the bundles, if any.
+fn identifier_collisions_message(all_settings: &[Settings]) -> Option<String> {
+    let names: Vec<_> = all_settings
+        .iter()
+        .map(|settings| match &settings.build_artifact {
+            BuildArtifact::Bin(name) => format!("{} (binary {name})", settings.package_name()),
+            _ => settings.package_name().to_string(),
+        })
+        .collect();
+    let collisions = find_identifier_collisions(
+        names
+            .iter()
+            .map(String::as_str)
+            .zip(all_settings.iter().map(Settings::bundle_identifier)),
+    );
+    if collisions.is_empty() {
+        return None;
+    }
+    let message = collisions
+        .iter()
+        .map(|(identifier, packages)| {
+            format!(
+                "identifier \"{identifier}\" is shared by packages {}",
+                packages.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    Some(format!("Bundle identifiers collide: {message}"))
+}
+
+/// Given `(package name, bundle identifier)` pairs, returns each identifier
+/// that is used by more than one package, along with the packages using it.
+/// Empty identifiers are ignored.
+fn find_identifier_collisions<'a, I, S>(bundles: I) -> Vec<(String, Vec<String>)>
+where
+    I: IntoIterator<Item = (&'a str, S)>,
+    S: AsRef<str>,
+{
+    let mut packages_by_identifier = BTreeMap::<String, Vec<String>>::new();
+    for (package, identifier) in bundles {
+        let identifier = identifier.as_ref();
+        if identifier.is_empty() {
+            continue;
+        }
+        packages_by_identifier
+            .entry(identifier.to_string())
+            .or_default()
+            .push(package.to_string());
+    }
+    packages_by_identifier
+        .into_iter()
+        .filter(|(_, packages)| packages.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+impl Settings {
+    /// Returns settings for a fake `example` package whose
+    /// `[package.metadata.bundle]` section is given by `bundle_toml`, for use
+    /// in tests.
+    pub(crate) fn for_test(bundle_toml: &str) -> Settings {
+        let package = serde_json::from_value(serde_json::json!({
+            "name": "example",
+            "version": "1.0.0",
+            "id": "example 1.0.0",
+            "authors": ["Jane Doe <jane@example.com>"],
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": "/nonexistent/example/Cargo.toml",
+        }))
+        .unwrap();
+        let project_out_directory = PathBuf::from("/nonexistent/example/target/debug");
+        Settings {
+            package,
+            package_type: None,
+            target: None,
+            features: None,
+            binary_path: project_out_directory.join("example"),
+            project_out_directory,
+            build_artifact: BuildArtifact::Main,
+            profile: "dev".to_string(),
+            all_features: false,
+            no_default_features: false,
+            notarize: false,
+            reproducible: false,
+            binary_name: "example".to_string(),
+            bundle_settings: toml::from_str(bundle_toml).unwrap(),
+            build_info: None,
+            sbom: None,
+        }
+    }
+}
+
+/// The `--target` that bundles a universal macOS binary, which is merged with
+/// `lipo` from the binaries built for each of the `UNIVERSAL_MACOS_TARGETS`.
+pub const UNIVERSAL_MACOS_TARGET: &str = "universal-apple-darwin";
+
+/// The targets that a universal macOS binary is built for.
+pub const UNIVERSAL_MACOS_TARGETS: [&str; 2] = ["x86_64-apple-darwin", "aarch64-apple-darwin"];
+
+/// The file, next to `Cargo.toml`, that bundle settings may be kept in instead
+/// of the `[package.metadata.bundle]` table.
+const BUNDLE_CONFIG_FILE: &str = "Bundle.toml";
+
+/// The `[package.metadata.bundle.<os>]` tables, whose keys override the shared
+/// settings when bundling for that OS.
+const PLATFORM_TABLES: [&str; 4] = ["linux", "windows", "macos", "ios"];
+
+/// The keys of the `[package.metadata.bundle.windows]` table that are settings
+/// of the table itself, rather than overrides of the shared settings.
+const WINDOWS_TABLE_KEYS: [&str; 9] = [
+    "sign",
+    "install_scope",
+    "services",
+    "registry",
+    "license",
+    "license_dialog",
+    "banner_image",
+    "dialog_image",
+    "prerequisites",
+];
+
+/// Overlays the keys of the table for `platform` onto a bundle section (and
+/// likewise in its `bin` and `example` sections), dropping the tables for
+/// other platforms.  The `sign` table and the installer settings of `windows`
+/// (the `WINDOWS_TABLE_KEYS`) are Windows settings of their own, so they are
+/// kept where they are.
+fn apply_platform_overrides(bundle: &mut serde_json::Value, platform: &str) {
+    let Some(section) = bundle.as_object_mut() else {
+        return;
+    };
+    let mut overrides = None;
+    for name in PLATFORM_TABLES {
+        let Some(serde_json::Value::Object(mut table)) = section.remove(name) else {
+            continue;
+        };
+        if name == "windows" {
+            let kept: serde_json::Map<_, _> = WINDOWS_TABLE_KEYS
+                .into_iter()
+                .filter_map(|key| Some((key.to_string(), table.remove(key)?)))
+                .collect();
+            if !kept.is_empty() {
+                section.insert(name.to_string(), serde_json::Value::Object(kept));
+            }
+        }
+        if name == platform {
+            overrides = Some(table);
+        }
+    }
+    for name in ["bin", "example"] {
+        if let Some(serde_json::Value::Object(sections)) = section.get_mut(name) {
+            for section in sections.values_mut() {
+                apply_platform_overrides(section, platform);
+            }
+        }
+    }
+    if let Some(overrides) = overrides {
+        section.extend(overrides);
+    }
+}
+
+/// The tables of a bundle section that the dotted keys of `--set` overrides
+/// descend into.  Other dots are joined with `_`, as in the names of the flat
+/// settings (so `deb.depends` is `deb_depends`).
+const NESTED_TABLES: [&str; 8] = [
+    "bin",
+    "example",
+    "linux",
+    "windows",
+    "macos",
+    "ios",
+    "sign",
+    "trusted_signing",
+];
+
+/// Applies a `--set key=value` override to a bundle section whose platform
+/// tables have already been applied for `platform`.  A key ending in `[]`
+/// appends the value to an array setting.  Keys that name no setting are
+/// rejected, as serde would otherwise drop them without a word; overrides for
+/// the tables of other platforms are checked, but have no effect.
+fn apply_set_override(
+    bundle: &mut serde_json::Value,
+    assignment: &str,
+    platform: &str,
+) -> crate::Result<()> {
+    let Some((key, value)) = assignment.split_once('=') else {
+        anyhow::bail!("Invalid override {assignment:?}, expected KEY=VALUE");
+    };
+    let (path, append) = match key.strip_suffix("[]") {
+        Some(path) => (path, true),
+        None => (key, false),
+    };
+    let mut segments: Vec<&str> = path.split('.').collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        anyhow::bail!("Invalid override key {key:?}");
+    }
+    // The settings of the `windows` table itself stay in that table, on every
+    // platform (see `apply_platform_overrides`).
+    let windows_table_key = segments[0] == "windows"
+        && segments
+            .get(1)
+            .is_some_and(|key| WINDOWS_TABLE_KEYS.contains(key));
+    // Overrides for other platforms are applied to a scratch table, so that
+    // their keys are still checked.
+    let mut scratch = serde_json::json!({});
+    let mut bundle = bundle;
+    let mut other_platform = None;
+    if PLATFORM_TABLES.contains(&segments[0]) && !windows_table_key {
+        if segments[0] != platform {
+            other_platform = Some(segments[0]);
+            bundle = &mut scratch;
+        }
+        segments.remove(0);
+    }
+    let mut fields = field_names::<BundleSettings>();
+    let mut index = 0;
+    // The sections of `bin` and `example` are named by the next segment.
+    let mut in_sections = false;
+    while index + 1 < segments.len() && (in_sections || NESTED_TABLES.contains(&segments[index])) {
+        let segment = segments[index];
+        fields = match segment {
+            _ if in_sections => field_names::<BundleSettings>(),
+            "windows" => field_names::<WindowsSettings>(),
+            "sign" => field_names::<WindowsSignSettings>(),
+            "trusted_signing" => field_names::<TrustedSigningSettings>(),
+            _ => field_names::<BundleSettings>(),
+        };
+        in_sections = !in_sections && matches!(segment, "bin" | "example");
+        index += 1;
+    }
+    let name = segments[index..].join("_");
+    if !in_sections && !fields.contains(&name.as_str()) {
+        anyhow::bail!("Unknown bundle setting {key:?}");
+    }
+    let Some(mut table) = bundle.as_object_mut() else {
+        anyhow::bail!("The bundle settings are not a table");
+    };
+    for segment in &segments[..index] {
+        table = table
+            .entry(*segment)
+            .or_insert_with(|| serde_json::json!({}))
+            .as_object_mut()
+            .with_context(|| format!("Cannot override {key:?}, as `{segment}` is not a table"))?;
+    }
+    let value = parse_override_value(value)?;
+    if append {
+        table
+            .entry(name)
+            .or_insert_with(|| serde_json::json!([]))
+            .as_array_mut()
+            .with_context(|| format!("Cannot append to {key:?}, as it is not an array"))?
+            .push(value);
+    } else {
+        table.insert(name, value);
+    }
+    if let Some(other_platform) = other_platform {
+        print_note(&format!(
+            "Ignoring --set {key}, as it only applies when bundling for {other_platform}"
+        ))?;
     }
+    Ok(())
+}
 
-    pub fn linux_use_terminal(&self) -> Option<bool> {
-        self.bundle_settings.linux_use_terminal
-    }
+/// Returns the names of the fields of the struct `T`, which are the keys that
+/// serde accepts for it.
+fn field_names<T: serde::de::DeserializeOwned>() -> &'static [&'static str] {
+    // A deserializer that records the fields it is asked for, and then fails.
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
 
-    pub fn linux_exec_args(&self) -> Option<&str> {
-        self.bundle_settings.linux_exec_args.as_deref()
-    }
+    impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+        type Error = serde::de::value::Error;
 
-    pub fn osx_frameworks(&self) -> &[String] {
-        match self.bundle_settings.osx_frameworks {
-            Some(ref frameworks) => frameworks.as_slice(),
-            None => &[],
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("not a struct"))
         }
-    }
 
-    pub fn osx_plugins(&self) -> &[String] {
-        match self.bundle_settings.osx_plugins {
-            Some(ref plugins) => plugins.as_slice(),
-            None => &[],
+        fn deserialize_struct<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
         }
     }
 
-    pub fn osx_minimum_system_version(&self) -> Option<&str> {
-        self.bundle_settings.osx_minimum_system_version.as_deref()
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Parses the value of a `--set` override: booleans, integers, arrays, inline
+/// tables and quoted strings as in TOML, and anything else (including numbers
+/// like `1.2`, which are more likely versions than floats) as a bare string.
+fn parse_override_value(value: &str) -> crate::Result<serde_json::Value> {
+    let parsed = toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+    Ok(match parsed {
+        Some(
+            parsed @ (toml::Value::Boolean(_)
+            | toml::Value::Integer(_)
+            | toml::Value::String(_)
+            | toml::Value::Array(_)
+            | toml::Value::Table(_)),
+        ) => serde_json::to_value(parsed)?,
+        _ => serde_json::Value::String(value.to_string()),
+    })
+}
+
+/// The values of the built-in `${name}` placeholders in metadata strings.
+/// Any other name refers to an environment variable.
+struct Placeholders<'a> {
+    /// The package version, from `Cargo.toml`.
+    version: String,
+    /// The target triple, or `None` for the host's.
+    target: Option<&'a str>,
+    /// The name of the build profile, e.g. `dev` or `release`.
+    profile: &'a str,
+}
+
+impl Placeholders<'_> {
+    fn value(&self, name: &str) -> crate::Result<String> {
+        match name {
+            "version" => Ok(self.version.clone()),
+            "target" => match self.target {
+                Some(triple) => Ok(triple.to_string()),
+                None => super::host_triple(),
+            },
+            "profile" => Ok(self.profile.to_string()),
+            _ => std::env::var(name).map_err(|_| {
+                anyhow::anyhow!("Environment variable {name} used in bundle metadata is not set")
+            }),
+        }
     }
 
-    pub fn osx_url_schemes(&self) -> &[String] {
-        match self.bundle_settings.osx_url_schemes {
-            Some(ref urlosx_url_schemes) => urlosx_url_schemes.as_slice(),
-            None => &[],
+    /// Expands the placeholders in every string of a bundle section, except
+    /// the bodies of inline maintainer scripts and scriptlets, which are shell
+    /// scripts whose `${...}` must be left alone.
+    fn substitute(&self, value: &mut serde_json::Value) -> crate::Result<()> {
+        match value {
+            serde_json::Value::String(string) => *string = self.expand(string)?,
+            serde_json::Value::Array(values) => {
+                for value in values {
+                    self.substitute(value)?;
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if key == "script" && value.is_string() {
+                        continue;
+                    }
+                    self.substitute(value)?;
+                }
+            }
+            _ => {}
         }
+        Ok(())
     }
 
-    /// Returns an iterator over the plist files for this bundle
-    pub fn osx_info_plist_exts(&self) -> ResourcePaths<'_> {
-        match self.bundle_settings.osx_info_plist_exts {
-            Some(ref paths) => ResourcePaths::new(paths.as_slice(), false),
-            None => ResourcePaths::new(&[], false),
+    /// Expands the `${name}` placeholders in `string`; `$$` stands for `$`.
+    fn expand(&self, string: &str) -> crate::Result<String> {
+        let mut expanded = String::new();
+        let mut rest = string;
+        while let Some(start) = rest.find('$') {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                expanded.push('$');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('{') {
+                let Some(end) = after.find('}') else {
+                    anyhow::bail!("Unterminated placeholder in {string:?}");
+                };
+                expanded.push_str(&self.value(&after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                expanded.push('$');
+            }
         }
+        expanded.push_str(rest);
+        Ok(expanded)
     }
 }
 
+/// Returns the settings of the `bin` or `example` section for `bundle_name`,
+/// which are merged over those of the package, or else the settings of the
+/// package.
 fn bundle_settings_from_table(
     opt_map: &Option<HashMap<String, BundleSettings>>,
-    map_name: &str,
     bundle_name: &str,
-) -> crate::Result<BundleSettings> {
-    if let Some(bundle_settings) = opt_map.as_ref().and_then(|map| map.get(bundle_name)) {
-        Ok(bundle_settings.clone())
-    } else {
-        print_warning(&format!(
-            "No [package.metadata.bundle.{map_name}.{bundle_name}] section in Cargo.toml"
-        ))?;
-        Ok(BundleSettings::default())
+    package_settings: &BundleSettings,
+) -> BundleSettings {
+    match opt_map.as_ref().and_then(|map| map.get(bundle_name)) {
+        Some(bundle_settings) => bundle_settings.clone(),
+        None => package_settings.clone(),
+    }
+}
+
+/// Merges the `[bin.<name>]` and `[example.<name>]` sections of a bundle
+/// section over the settings of the package, so that the sections only need
+/// the keys that differ.  As with the platform tables, a key of the section
+/// replaces the package's setting as a whole.  The `osx_helpers` are not
+/// inherited, since helper apps are bundled with the settings of their `bin`
+/// section, and have no helpers of their own.
+fn merge_artifact_sections(bundle: &mut serde_json::Value) {
+    let Some(section) = bundle.as_object_mut() else {
+        return;
+    };
+    let package_settings: serde_json::Map<_, _> = section
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "bin" | "example" | "osx_helpers"))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    for name in ["bin", "example"] {
+        let Some(serde_json::Value::Object(sections)) = section.get_mut(name) else {
+            continue;
+        };
+        for section in sections.values_mut() {
+            if let serde_json::Value::Object(keys) = section {
+                let mut merged = package_settings.clone();
+                merged.extend(std::mem::take(keys));
+                *keys = merged;
+            }
+        }
     }
 }
 
 pub struct ResourcePaths<'a> {
+    /// The directory that relative patterns are matched in.
+    base_dir: &'a Path,
     pattern_iter: std::slice::Iter<'a, String>,
     glob_iter: Option<glob::Paths>,
     walk_iter: Option<walkdir::IntoIter>,
@@ -586,8 +3510,9 @@ pub struct ResourcePaths<'a> {
 }
 
 impl<'a> ResourcePaths<'a> {
-    fn new(patterns: &'a [String], allow_walk: bool) -> ResourcePaths<'a> {
+    fn new(base_dir: &'a Path, patterns: &'a [String], allow_walk: bool) -> ResourcePaths<'a> {
         ResourcePaths {
+            base_dir,
             pattern_iter: patterns.iter(),
             glob_iter: None,
             walk_iter: None,
@@ -624,7 +3549,7 @@ impl Iterator for ResourcePaths<'_> {
                 };
                 if path.is_dir() {
                     if self.allow_walk {
-                        let walk = walkdir::WalkDir::new(path);
+                        let walk = walkdir::WalkDir::new(path).sort_by_file_name();
                         self.walk_iter = Some(walk.into_iter());
                         continue;
                     } else {
@@ -635,7 +3560,9 @@ impl Iterator for ResourcePaths<'_> {
             }
             self.glob_iter = None;
             if let Some(pattern) = self.pattern_iter.next() {
-                let glob = match glob::glob(pattern) {
+                let base_dir = glob::Pattern::escape(&self.base_dir.to_string_lossy());
+                let pattern = Path::new(&base_dir).join(pattern);
+                let glob = match glob::glob(&pattern.to_string_lossy()) {
                     Ok(glob) => glob,
                     Err(error) => return Some(Err(anyhow::Error::from(error))),
                 };
@@ -647,9 +3574,99 @@ impl Iterator for ResourcePaths<'_> {
     }
 }
 
+/// A pre-built executable to be copied into a bundle next to the binary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalBinary {
+    /// The path of the executable to copy.
+    pub src: PathBuf,
+    /// The file name of the copy, e.g. `ffmpeg.exe` for Windows targets.
+    pub name: String,
+}
+
+/// A resource file to be copied into a bundle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceFile {
+    /// The path of the file to copy.
+    pub src: PathBuf,
+    /// The path of the copy, relative to the bundle's resources.
+    pub dest: PathBuf,
+    /// The permissions of the copy, if they were set explicitly.
+    pub mode: Option<u32>,
+}
+
+pub struct ResourceFiles<'a> {
+    /// The directory containing `Cargo.toml`, that resources are relative to.
+    base_dir: &'a Path,
+    entry_iter: std::slice::Iter<'a, ResourceSetting>,
+    current: Option<(&'a ResourceSetting, ResourcePaths<'a>)>,
+    exclude: Vec<glob::Pattern>,
+    /// An invalid `exclude` pattern, reported instead of any file.
+    error: Option<anyhow::Error>,
+}
+
+impl ResourceFiles<'_> {
+    /// Returns whether `path` matches one of the `exclude` patterns, either as
+    /// a whole or by the name of one of its directories or of the file.
+    fn is_excluded(&self, path: &Path) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+        self.exclude.iter().any(|pattern| {
+            pattern.matches_path_with(path, options)
+                || path.components().any(|component| match component {
+                    Component::Normal(name) => {
+                        pattern.matches_with(&name.to_string_lossy(), options)
+                    }
+                    _ => false,
+                })
+        })
+    }
+}
+
+impl Iterator for ResourceFiles<'_> {
+    type Item = crate::Result<ResourceFile>;
+
+    fn next(&mut self) -> Option<crate::Result<ResourceFile>> {
+        if let Some(error) = self.error.take() {
+            self.entry_iter = [].iter();
+            return Some(Err(error));
+        }
+        loop {
+            if let Some((entry, ref mut paths)) = self.current
+                && let Some(src) = paths.next()
+            {
+                let base_dir = self.base_dir;
+                let relative = |src: &Path| src.strip_prefix(base_dir).unwrap_or(src).to_path_buf();
+                if src
+                    .as_ref()
+                    .is_ok_and(|src| self.is_excluded(&relative(src)))
+                {
+                    continue;
+                }
+                return Some(src.and_then(|src| {
+                    let dest = entry.destination(&relative(&src), base_dir)?;
+                    let mode = entry.mode()?;
+                    Ok(ResourceFile { src, dest, mode })
+                }));
+            }
+            let entry = self.entry_iter.next()?;
+            let paths = ResourcePaths::new(self.base_dir, std::slice::from_ref(entry.src()), true);
+            self.current = Some((entry, paths));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{AppCategory, BundleSettings};
+    use super::{
+        AppCategory, AppImageCompression, BundleSettings, InstallScope, OsxHelperKind, PackageType,
+        Placeholders, ResourceFile, ResourceSetting, Settings, Shell, VersionSource,
+        apply_platform_overrides, apply_set_override, check_identifier_collisions,
+        find_identifier_collisions, identifier_collisions_message,
+    };
+    use std::fs;
+    use std::path::PathBuf;
 
     #[test]
     fn parse_cargo_toml() {
@@ -669,7 +3686,10 @@ mod tests {
         assert_eq!(bundle.version, None);
         assert_eq!(
             bundle.resources,
-            Some(vec!["data".to_string(), "foo/bar".to_string()])
+            Some(vec![
+                ResourceSetting::Path("data".to_string()),
+                ResourceSetting::Path("foo/bar".to_string())
+            ])
         );
         assert_eq!(bundle.category, Some(AppCategory::PuzzleGame));
         assert_eq!(
@@ -709,4 +3729,842 @@ mod tests {
         let baz: &BundleSettings = examples.get("baz").unwrap();
         assert_eq!(baz.name, Some("Baz Example".to_string()));
     }
+
+    #[test]
+    fn package_type_short_names() {
+        for &name in PackageType::all() {
+            let package_type = PackageType::from_short_name(name).unwrap();
+            assert_eq!(package_type.short_name(), name);
+        }
+    }
+
+    #[test]
+    fn required_tools_per_format() {
+        // The tools each bundler runs whatever the settings, as `cargo bundle
+        // list` reports them.
+        let expected: &[(&str, &[&str])] = &[
+            ("deb", &[]),
+            ("ios", &[]),
+            ("msi", &[]),
+            ("wxsmsi", &["dotnet"]),
+            ("osx", &[]),
+            ("rpm", &[]),
+            ("srpm", &[]),
+            ("appimage", &["mksquashfs"]),
+            ("flatpak", &["flatpak", "flatpak-builder"]),
+            ("snap", &["snapcraft"]),
+            ("dmg", &["hdiutil"]),
+            ("pkg", &["pkgbuild", "productbuild"]),
+            ("nsis", &["makensis"]),
+            ("msix", &["makeappx"]),
+            ("pacman", &["zstd"]),
+            ("apk", &[]),
+            ("nix", &[]),
+            ("choco", &[]),
+            ("archive", &[]),
+            ("aur", &[]),
+            ("freebsd", &[]),
+            ("android", &["aapt", "zipalign", "apksigner"]),
+            ("ipk", &[]),
+            ("oci", &[]),
+            ("portable", &["mksquashfs"]),
+            ("appstore", &["codesign", "productbuild"]),
+        ];
+        assert_eq!(expected.len(), PackageType::all().len());
+        for &(name, tools) in expected {
+            let package_type = PackageType::from_short_name(name).unwrap();
+            assert_eq!(package_type.required_tools(), tools, "{name}");
+        }
+    }
+
+    #[test]
+    fn parse_appimage_compression() {
+        let bundle: BundleSettings = toml::from_str("appimage_compression = \"zstd\"").unwrap();
+        assert_eq!(bundle.appimage_compression, Some(AppImageCompression::Zstd));
+        assert!(toml::from_str::<BundleSettings>("appimage_compression = \"lz4\"").is_err());
+    }
+
+    #[test]
+    fn identifier_collisions() {
+        let collisions = find_identifier_collisions([
+            ("foo", "com.example.app"),
+            ("bar", "com.example.bar"),
+            ("baz", "com.example.app"),
+            ("qux", ""),
+            ("quux", ""),
+        ]);
+        assert_eq!(
+            collisions,
+            vec![(
+                "com.example.app".to_string(),
+                vec!["foo".to_string(), "baz".to_string()]
+            )]
+        );
+        assert!(find_identifier_collisions([("foo", "a"), ("bar", "b")]).is_empty());
+
+        let app = Settings::for_test("identifier = \"com.example.app\"");
+        let other = Settings::for_test("identifier = \"com.example.other\"");
+        assert_eq!(identifier_collisions_message(&[app.clone(), other]), None);
+        let all = [app.clone(), app];
+        assert_eq!(
+            identifier_collisions_message(&all).as_deref(),
+            Some(
+                "Bundle identifiers collide: identifier \"com.example.app\" is shared by \
+                 packages example, example"
+            )
+        );
+        // Without `--deny-warnings`, a collision is only a warning.
+        assert!(check_identifier_collisions(&all).is_ok());
+    }
+
+    #[test]
+    fn platform_overrides() {
+        let bundle = serde_json::json!({
+            "identifier": "com.example.app",
+            "icon": ["icon.png"],
+            "linux": { "identifier": "com.example.app-linux" },
+            "windows": {
+                "icon": ["icon.ico"],
+                "sign": { "certificate": "cert.pfx" },
+                "install_scope": "perUser",
+                "license": "EULA.rtf",
+                "license_dialog": false,
+                "banner_image": "banner.bmp",
+            },
+            "bin": {
+                "tool": {
+                    "identifier": "com.example.tool",
+                    "macos": { "identifier": "com.example.tool-macos" },
+                },
+            },
+        });
+        let settings = |platform| {
+            let mut bundle = bundle.clone();
+            apply_platform_overrides(&mut bundle, platform);
+            serde_json::from_value::<BundleSettings>(bundle).unwrap()
+        };
+
+        let linux = settings("linux");
+        assert_eq!(linux.identifier.as_deref(), Some("com.example.app-linux"));
+        assert_eq!(linux.icon, Some(vec!["icon.png".to_string()]));
+        assert!(linux.windows.unwrap().sign.is_some());
+        let windows = settings("windows");
+        assert_eq!(windows.identifier.as_deref(), Some("com.example.app"));
+        assert_eq!(windows.icon, Some(vec!["icon.ico".to_string()]));
+        let windows_table = windows.windows.unwrap();
+        assert_eq!(windows_table.install_scope, Some(InstallScope::PerUser));
+        assert_eq!(windows_table.license.as_deref(), Some("EULA.rtf"));
+        assert_eq!(windows_table.license_dialog, Some(false));
+        assert_eq!(windows_table.banner_image.as_deref(), Some("banner.bmp"));
+        let macos = settings("macos");
+        assert_eq!(
+            macos.bin.unwrap()["tool"].identifier.as_deref(),
+            Some("com.example.tool-macos")
+        );
+        assert_eq!(PackageType::Dmg.platform(), Some("macos"));
+        assert_eq!(PackageType::Archive.platform(), None);
+    }
+
+    #[test]
+    fn set_overrides() {
+        let mut bundle = serde_json::json!({
+            "identifier": "com.example.app",
+            "deb_depends": ["libc6"],
+        });
+        for assignment in [
+            "identifier=com.example.beta",
+            "version=1.2",
+            "deb.depends[]=libfoo",
+            "nsis_desktop_shortcut=true",
+            "android_min_sdk=24",
+            "osx_frameworks=[\"Foo\"]",
+            "bin.tool.name=Tool",
+            "windows.sign.timestamp_url=http://timestamp.example.com",
+            "linux.short_description=Linux only",
+            "macos.short_description=macOS only",
+        ] {
+            apply_set_override(&mut bundle, assignment, "linux").unwrap();
+        }
+        assert_eq!(
+            bundle,
+            serde_json::json!({
+                "identifier": "com.example.beta",
+                "version": "1.2",
+                "deb_depends": ["libc6", "libfoo"],
+                "nsis_desktop_shortcut": true,
+                "android_min_sdk": 24,
+                "osx_frameworks": ["Foo"],
+                "bin": { "tool": { "name": "Tool" } },
+                "windows": { "sign": { "timestamp_url": "http://timestamp.example.com" } },
+                "short_description": "Linux only",
+            })
+        );
+        let settings: BundleSettings = serde_json::from_value(bundle.clone()).unwrap();
+        assert_eq!(settings.android_min_sdk, Some(24));
+        assert!(apply_set_override(&mut bundle, "identifier", "linux").is_err());
+        assert!(apply_set_override(&mut bundle, "identifier[]=x", "linux").is_err());
+    }
+
+    #[test]
+    fn set_override_unknown_keys() {
+        let mut bundle = serde_json::json!({});
+        for assignment in [
+            "identifer=com.example.beta",
+            "deb.depend[]=libfoo",
+            "bin.tool.nmae=Tool",
+            "windows.sign.timestamp=http://timestamp.example.com",
+            "macos.short_descripton=macOS only",
+        ] {
+            let error = apply_set_override(&mut bundle, assignment, "linux").unwrap_err();
+            let key = assignment.split('=').next().unwrap();
+            assert_eq!(error.to_string(), format!("Unknown bundle setting {key:?}"));
+        }
+        assert_eq!(bundle, serde_json::json!({}));
+        // The settings of the `windows` table stay in that table.
+        apply_set_override(&mut bundle, "windows.license=LICENSE.rtf", "windows").unwrap();
+        apply_set_override(&mut bundle, "windows.nsis_desktop_shortcut=true", "windows").unwrap();
+        assert_eq!(
+            bundle["windows"],
+            serde_json::json!({ "license": "LICENSE.rtf" })
+        );
+        assert_eq!(bundle["nsis_desktop_shortcut"], true);
+    }
+
+    #[test]
+    fn placeholders() {
+        let placeholders = Placeholders {
+            version: "1.2.3".to_string(),
+            target: Some("aarch64-apple-darwin"),
+            profile: "release",
+        };
+        let mut bundle = serde_json::json!({
+            "identifier": "com.example.${profile}",
+            "resources": ["assets/${target}/*", "$${literal}"],
+            "copyright": "${CARGO_MANIFEST_DIR} v${version}, $5",
+            "deb_postinst": { "script": "echo ${HOME} $$" },
+            "rpm_post": "scripts/${profile}/post.sh",
+        });
+        placeholders.substitute(&mut bundle).unwrap();
+        assert_eq!(
+            bundle,
+            serde_json::json!({
+                "identifier": "com.example.release",
+                "resources": ["assets/aarch64-apple-darwin/*", "${literal}"],
+                "copyright": format!("{} v1.2.3, $5", env!("CARGO_MANIFEST_DIR")),
+                // Inline scripts are kept as written.
+                "deb_postinst": { "script": "echo ${HOME} $$" },
+                "rpm_post": "scripts/release/post.sh",
+            })
+        );
+        assert!(
+            placeholders
+                .expand("${CARGO_BUNDLE_UNSET_VARIABLE}")
+                .is_err()
+        );
+        assert!(placeholders.expand("${version").is_err());
+    }
+
+    #[test]
+    fn bundle_config_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.metadata\"\n",
+        )
+        .unwrap();
+        let cli = |args: &[&str]| {
+            <crate::Cli as clap::Parser>::parse_from(
+                std::iter::once("cargo-bundle").chain(args.iter().copied()),
+            )
+        };
+        let identifier = |args: &[&str]| {
+            let all = Settings::all(tmp.path(), &cli(args)).unwrap();
+            all[0].bundle_identifier().into_owned()
+        };
+
+        assert_eq!(identifier(&[]), "com.example.metadata");
+        fs::write(
+            tmp.path().join("Bundle.toml"),
+            "identifier = \"com.example.file\"\n[linux]\nname = \"App\"\n",
+        )
+        .unwrap();
+        assert_eq!(identifier(&[]), "com.example.file");
+        let beta = tmp.path().join("beta.toml");
+        fs::write(&beta, "identifier = \"com.example.beta\"\n").unwrap();
+        assert_eq!(
+            identifier(&["--config", beta.to_str().unwrap()]),
+            "com.example.beta"
+        );
+    }
+
+    #[test]
+    fn workspace_members() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\"]\nresolver = \"2\"\n",
+        )
+        .unwrap();
+        for member in ["a", "b", "c"] {
+            let dir = tmp.path().join(member);
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+            let mut manifest = format!(
+                "[package]\nname = \"{member}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"
+            );
+            if member != "c" {
+                manifest +=
+                    &format!("[package.metadata.bundle]\nidentifier = \"com.example.{member}\"\n");
+            }
+            fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+        }
+        let cli = |args: &[&str]| {
+            <crate::Cli as clap::Parser>::parse_from(
+                std::iter::once("cargo-bundle").chain(args.iter().copied()),
+            )
+        };
+
+        let all = Settings::all(tmp.path(), &cli(&["--workspace"])).unwrap();
+        let names: Vec<_> = all.iter().map(Settings::package_name).collect();
+        assert_eq!(names, ["a", "b"]);
+        let target_dir = tmp.path().canonicalize().unwrap().join("target/debug");
+        assert_eq!(all[0].project_out_directory(), target_dir);
+
+        let all = Settings::all(&tmp.path().join("b/src"), &cli(&[])).unwrap();
+        assert_eq!(all[0].package_name(), "b");
+        assert_eq!(all[0].project_out_directory(), target_dir);
+        let all = Settings::all(tmp.path(), &cli(&["-p", "c"])).unwrap();
+        assert_eq!(all[0].package_name(), "c");
+        assert!(Settings::all(tmp.path(), &cli(&[])).is_err());
+    }
+
+    #[test]
+    fn multiple_targets() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src/bin")).unwrap();
+        fs::create_dir_all(tmp.path().join("examples")).unwrap();
+        fs::write(tmp.path().join("examples/demo.rs"), "fn main() {}\n").unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(tmp.path().join("src/bin/tool.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle.bin.app]\nidentifier = \"com.example.app\"\n\
+             [package.metadata.bundle.bin.tool]\nidentifier = \"com.example.tool\"\n\
+             [package.metadata.bundle.example.demo]\nidentifier = \"com.example.demo\"\n",
+        )
+        .unwrap();
+        let cli = |args: &[&str]| {
+            <crate::Cli as clap::Parser>::parse_from(
+                std::iter::once("cargo-bundle").chain(args.iter().copied()),
+            )
+        };
+
+        let all = Settings::all(tmp.path(), &cli(&["--bin", "app", "--bin", "tool"])).unwrap();
+        let identifiers: Vec<_> = all.iter().map(Settings::bundle_identifier).collect();
+        assert_eq!(identifiers, ["com.example.app", "com.example.tool"]);
+        assert_eq!(all[1].binary_name(), "tool");
+        assert!(Settings::all(tmp.path(), &cli(&["--bin", "missing"])).is_err());
+
+        let all = Settings::all(tmp.path(), &cli(&["--bin", "tool", "--example", "demo"])).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].bundle_identifier(), "com.example.demo");
+        assert!(all[1].binary_path().ends_with("debug/examples/demo"));
+        let error = Settings::all(tmp.path(), &cli(&["--example", "missing"])).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .ends_with("available example targets: demo")
+        );
+    }
+
+    #[test]
+    fn osx_helper_settings() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src/bin")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(tmp.path().join("src/bin/launcher.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.app\"\n\
+             copyright = \"Copyright (c) Example\"\n\
+             osx_helpers = [{ bin = \"launcher\", kind = \"login-item\" }, { bin = \"missing\" }]\n\
+             [package.metadata.bundle.bin.launcher]\nname = \"Launcher\"\n\
+             identifier = \"com.example.app.launcher\"\n",
+        )
+        .unwrap();
+        let cli = <crate::Cli as clap::Parser>::parse_from(["cargo-bundle"]);
+
+        let all = Settings::all(tmp.path(), &cli).unwrap();
+        let settings = &all[0];
+        let helpers = settings.osx_helpers();
+        assert_eq!(helpers[0].kind.dir(), "Library/LoginItems");
+        assert_eq!(helpers[1].kind, OsxHelperKind::Helper);
+        let helper = settings.osx_helper_settings(&helpers[0]).unwrap();
+        assert_eq!(helper.bundle_name(), "Launcher");
+        assert_eq!(helper.bundle_identifier(), "com.example.app.launcher");
+        // The other settings are those of the package.
+        assert_eq!(helper.copyright_string(), Some("Copyright (c) Example"));
+        assert_eq!(helper.binary_name(), "launcher");
+        assert_eq!(
+            helper.binary_path(),
+            settings.binary_path().with_file_name("launcher")
+        );
+        assert!(helper.osx_helpers().is_empty());
+        assert!(settings.osx_helper_settings(&helpers[1]).is_err());
+    }
+
+    #[test]
+    fn artifact_sections_merge() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src/bin")).unwrap();
+        fs::create_dir_all(tmp.path().join("examples")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(tmp.path().join("src/bin/tool.rs"), "fn main() {}\n").unwrap();
+        fs::write(tmp.path().join("examples/demo.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nname = \"App\"\nidentifier = \"com.example.app\"\n\
+             copyright = \"Copyright (c) Example\"\n\
+             [package.metadata.bundle.bin.tool]\nname = \"Tool\"\n\
+             identifier = \"com.example.tool\"\n",
+        )
+        .unwrap();
+        let cli = <crate::Cli as clap::Parser>::parse_from([
+            "cargo-bundle",
+            "--bin",
+            "tool",
+            "--example",
+            "demo",
+        ]);
+        let all = Settings::all(tmp.path(), &cli).unwrap();
+        // The section of the binary is merged over the package's settings.
+        assert_eq!(all[0].bundle_name(), "Tool");
+        assert_eq!(all[0].bundle_identifier(), "com.example.tool");
+        assert_eq!(all[0].copyright_string(), Some("Copyright (c) Example"));
+        // Without a section, the package's settings are used.
+        assert_eq!(all[1].bundle_name(), "App");
+        assert_eq!(all[1].copyright_string(), Some("Copyright (c) Example"));
+    }
+
+    #[test]
+    fn resources_relative_to_manifest() {
+        // The tests run in the repository, not in the package directory.
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src")).unwrap();
+        fs::create_dir_all(tmp.path().join("assets/sub")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(tmp.path().join("assets/x.txt"), "x").unwrap();
+        fs::write(tmp.path().join("assets/sub/y.txt"), "y").unwrap();
+        fs::write(tmp.path().join("icon.png"), "").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.app\"\n\
+             icon = [\"*.png\"]\nresources = [\"assets/*\"]\nexclude = [\"assets/sub/*\"]\n",
+        )
+        .unwrap();
+        let cli = <crate::Cli as clap::Parser>::parse_from(["cargo-bundle"]);
+        let settings = Settings::all(tmp.path(), &cli).unwrap().remove(0);
+
+        let icons: Vec<_> = settings.icon_files().map(Result::unwrap).collect();
+        assert_eq!(icons, [tmp.path().join("icon.png")]);
+        let resources: Vec<_> = settings.resource_files().map(Result::unwrap).collect();
+        assert_eq!(
+            resources,
+            [ResourceFile {
+                src: tmp.path().join("assets/x.txt"),
+                dest: PathBuf::from("assets/x.txt"),
+                mode: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn windows_binary_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.app\"\n",
+        )
+        .unwrap();
+        for format in ["nsis", "msix", "choco", "archive", "msi"] {
+            let cli = <crate::Cli as clap::Parser>::parse_from([
+                "cargo-bundle",
+                "--target",
+                "x86_64-pc-windows-msvc",
+                "--format",
+                format,
+            ]);
+            let settings = Settings::all(tmp.path(), &cli).unwrap().remove(0);
+            assert_eq!(settings.binary_name(), "app");
+            assert!(
+                settings
+                    .binary_path()
+                    .ends_with("x86_64-pc-windows-msvc/debug/app.exe"),
+                "{format}: {:?}",
+                settings.binary_path()
+            );
+        }
+        let cli = <crate::Cli as clap::Parser>::parse_from([
+            "cargo-bundle",
+            "--target",
+            "x86_64-unknown-linux-gnu",
+            "--format",
+            "archive",
+        ]);
+        let settings = Settings::all(tmp.path(), &cli).unwrap().remove(0);
+        assert!(
+            settings
+                .binary_path()
+                .ends_with("x86_64-unknown-linux-gnu/debug/app")
+        );
+    }
+
+    #[test]
+    fn universal_macos_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("examples")).unwrap();
+        fs::write(tmp.path().join("examples/demo.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle.example.demo]\nidentifier = \"com.example.demo\"\n",
+        )
+        .unwrap();
+        let cli = <crate::Cli as clap::Parser>::parse_from([
+            "cargo-bundle",
+            "--target",
+            "universal-apple-darwin",
+            "--release",
+            "--example",
+            "demo",
+        ]);
+
+        let all = Settings::all(tmp.path(), &cli).unwrap();
+        let settings = &all[0];
+        assert!(settings.is_universal_macos());
+        assert_eq!(settings.binary_arch(), "universal");
+        assert_eq!(settings.package_types().unwrap(), [PackageType::OsxBundle]);
+        assert!(
+            settings
+                .binary_path()
+                .ends_with("universal-apple-darwin/release/examples/demo")
+        );
+        let thin_binaries = settings.universal_macos_binary_paths();
+        assert!(thin_binaries[0].ends_with("x86_64-apple-darwin/release/examples/demo"));
+        assert!(thin_binaries[1].ends_with("aarch64-apple-darwin/release/examples/demo"));
+    }
+
+    #[test]
+    fn ios_simulator_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("src")).unwrap();
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.app\"\n",
+        )
+        .unwrap();
+        let settings_for = |triple: &str| {
+            let cli =
+                <crate::Cli as clap::Parser>::parse_from(["cargo-bundle", "--target", triple]);
+            Settings::all(tmp.path(), &cli).unwrap().remove(0)
+        };
+
+        let simulator = settings_for("aarch64-apple-ios-sim");
+        assert!(simulator.is_ios_simulator());
+        assert_eq!(simulator.package_types().unwrap(), [PackageType::IosBundle]);
+        assert!(settings_for("x86_64-apple-ios").is_ios_simulator());
+        assert!(!settings_for("aarch64-apple-ios").is_ios_simulator());
+    }
+
+    #[test]
+    fn mapped_resources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_str().unwrap();
+        for file in [
+            "assets/a.dat",
+            "assets/sub/b.dat",
+            "assets/c.txt",
+            "config/default.toml",
+        ] {
+            let path = tmp.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let settings = Settings::for_test(&format!(
+            "resources = [\n\
+                 {{ src = \"{root}/assets/**/*.dat\", dest = \"data/\" }},\n\
+                 {{ src = \"{root}/assets\", dest = \"all\" }},\n\
+                 {{ src = \"{root}/config/default.toml\", dest = \"config.toml\" }},\n\
+                 {{ src = \"{root}/config/default.toml\", dest = \"etc/\" }},\n\
+             ]\n"
+        ));
+        let files = settings
+            .resource_files()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let dests: Vec<_> = files
+            .iter()
+            .map(|file| file.dest.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            dests,
+            [
+                "data/a.dat",
+                "data/sub/b.dat",
+                "all/a.dat",
+                "all/c.txt",
+                "all/sub/b.dat",
+                "config.toml",
+                "etc/default.toml",
+            ]
+        );
+        assert_eq!(
+            files[1],
+            ResourceFile {
+                src: tmp.path().join("assets/sub/b.dat"),
+                dest: "data/sub/b.dat".into(),
+                mode: None,
+            }
+        );
+
+        let settings = Settings::for_test(&format!(
+            "resources = [{{ src = \"{root}/assets/a.dat\", dest = \"../a.dat\" }}]\n"
+        ));
+        assert!(settings.resource_files().next().unwrap().is_err());
+        assert_eq!(
+            toml::from_str::<ResourceSetting>("src = \"a\"\ndest = \"b\"").unwrap(),
+            ResourceSetting::Mapped {
+                src: "a".to_string(),
+                dest: Some("b".to_string()),
+                mode: None,
+            }
+        );
+    }
+
+    #[test]
+    fn excluded_resources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_str().unwrap();
+        for file in [
+            "assets/logo.png",
+            "assets/logo.psd",
+            "assets/.git/HEAD",
+            "assets/fixtures/test.png",
+        ] {
+            let path = tmp.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let settings = Settings::for_test(&format!(
+            "resources = [\"{root}/assets\"]\n\
+             exclude = [\".git\", \"*.psd\", \"{root}/assets/fixtures/**\"]\n"
+        ));
+        let files = settings
+            .resource_files()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].src, tmp.path().join("assets/logo.png"));
+
+        let settings = Settings::for_test(&format!(
+            "resources = [\"{root}/assets\"]\nexclude = [\"[\"]\n"
+        ));
+        let mut files = settings.resource_files();
+        assert!(files.next().unwrap().is_err());
+        assert!(files.next().is_none());
+    }
+
+    #[test]
+    fn resource_modes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_str().unwrap();
+        fs::create_dir_all(tmp.path().join("scripts")).unwrap();
+        fs::write(tmp.path().join("scripts/helper.sh"), "").unwrap();
+        let settings = Settings::for_test(&format!(
+            "resources = [{{ src = \"{root}/scripts/helper.sh\", mode = \"0755\" }}]\n"
+        ));
+        let files = settings
+            .resource_files()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(files[0].mode, Some(0o755));
+        assert_eq!(
+            files[0].dest,
+            super::super::common::resource_relpath(&tmp.path().join("scripts/helper.sh"))
+        );
+
+        for mode in ["0789", "77777", "rwx"] {
+            let settings = Settings::for_test(&format!(
+                "resources = [{{ src = \"{root}/scripts/helper.sh\", mode = \"{mode}\" }}]\n"
+            ));
+            assert!(settings.resource_files().next().unwrap().is_err());
+        }
+    }
+
+    #[test]
+    fn external_binaries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().to_str().unwrap();
+        let host = super::super::host_triple().unwrap();
+        fs::create_dir_all(tmp.path().join("bin")).unwrap();
+        fs::write(tmp.path().join(format!("bin/ffmpeg-{host}")), "").unwrap();
+        fs::write(tmp.path().join("bin/ffmpeg"), "").unwrap();
+        fs::write(tmp.path().join("bin/helper"), "").unwrap();
+        let settings = Settings::for_test(&format!(
+            "external_binaries = [\"{root}/bin/ffmpeg\", \"{root}/bin/helper\"]\n"
+        ));
+        let binaries = settings.external_binaries().unwrap();
+        let sources: Vec<_> = binaries.iter().map(|binary| binary.src.clone()).collect();
+        assert_eq!(
+            sources,
+            [
+                tmp.path().join(format!("bin/ffmpeg-{host}")),
+                tmp.path().join("bin/helper")
+            ]
+        );
+        assert_eq!(binaries[0].name, "ffmpeg");
+
+        let settings =
+            Settings::for_test(&format!("external_binaries = [\"{root}/bin/missing\"]\n"));
+        assert!(settings.external_binaries().is_err());
+    }
+
+    #[test]
+    fn localized_texts() {
+        let settings = Settings::for_test(
+            r#"
+            name = "Example"
+            short_description = "An example"
+            [localizations.fr]
+            name = "Exemple"
+            [localizations.fr-CA]
+            short_description = "Un exemple"
+            "#,
+        );
+        assert_eq!(settings.msi_language(), "en-US");
+        assert_eq!(settings.msi_upgrade_code().unwrap(), None);
+        assert_eq!(settings.localized_bundle_name("en-US"), "Example");
+        assert_eq!(settings.localized_bundle_name("fr-FR"), "Exemple");
+        assert_eq!(settings.localized_short_description("fr-FR"), "An example");
+        assert_eq!(settings.localized_short_description("fr-CA"), "Un exemple");
+        // An entry for the exact tag replaces the one for its language.
+        assert_eq!(settings.localized_bundle_name("fr-CA"), "Example");
+    }
+    #[test]
+    fn version_sources() {
+        let settings = Settings::for_test("version_from = \"git\"");
+        assert_eq!(settings.version_from(), Some(&VersionSource::Git));
+        let mut settings = Settings::for_test("version_from = { command = \"./version.sh\" }");
+        assert_eq!(
+            settings.version_from(),
+            Some(&VersionSource::Command("./version.sh".to_string()))
+        );
+        assert_eq!(settings.version_string().to_string(), "1.0.0");
+        settings.set_version("1.0.1+3.gabc1234".to_string());
+        assert_eq!(settings.version_string().to_string(), "1.0.1+3.gabc1234");
+    }
+
+    #[test]
+    fn deb_maintainer_scripts() {
+        let settings = Settings::for_test(
+            r#"
+            deb_preinst = "debian/missing-preinst"
+            deb_postinst = { script = "adduser --system example\n" }
+            "#,
+        );
+        assert!(settings.deb_maintainer_script("preinst").is_err());
+        assert_eq!(
+            settings
+                .deb_maintainer_script("postinst")
+                .unwrap()
+                .as_deref(),
+            Some("adduser --system example\n")
+        );
+        assert_eq!(settings.deb_maintainer_script("prerm").unwrap(), None);
+    }
+
+    #[test]
+    fn rpm_scriptlets() {
+        let settings = Settings::for_test(
+            r#"
+            rpm_pre = { script = "useradd -r example\n" }
+            rpm_postun = "rpm/missing-postun"
+            "#,
+        );
+        assert_eq!(
+            settings.rpm_scriptlet("pre").unwrap().as_deref(),
+            Some("useradd -r example\n")
+        );
+        assert_eq!(settings.rpm_scriptlet("post").unwrap(), None);
+        assert!(settings.rpm_scriptlet("postun").is_err());
+    }
+
+    #[test]
+    fn rpm_metadata() {
+        let settings = Settings::for_test("");
+        assert_eq!(settings.rpm_requires(), [] as [String; 0]);
+        assert_eq!(settings.rpm_epoch(), None);
+        assert_eq!(settings.rpm_group(), "Unspecified");
+        assert_eq!(settings.rpm_url(), None);
+        let settings = Settings::for_test(
+            r#"
+            rpm_obsoletes = ["old-example < 2.0"]
+            rpm_epoch = 1
+            rpm_group = "Applications/System"
+            rpm_url = "https://example.com/rpm"
+            "#,
+        );
+        assert_eq!(settings.rpm_obsoletes(), ["old-example < 2.0"]);
+        assert_eq!(settings.rpm_epoch(), Some(1));
+        assert_eq!(settings.rpm_group(), "Applications/System");
+        assert_eq!(settings.rpm_url(), Some("https://example.com/rpm"));
+    }
+
+    #[test]
+    fn deb_sections() {
+        let settings = Settings::for_test("");
+        assert_eq!(settings.deb_section(), "utils");
+        assert_eq!(settings.deb_priority(), "optional");
+        let settings = Settings::for_test("category = \"Developer Tool\"");
+        assert_eq!(settings.deb_section(), "devel");
+        let settings = Settings::for_test(
+            "category = \"Developer Tool\"\ndeb_section = \"rust\"\ndeb_priority = \"extra\"",
+        );
+        assert_eq!(settings.deb_section(), "rust");
+        assert_eq!(settings.deb_priority(), "extra");
+    }
+
+    #[test]
+    fn shell_completions() {
+        let settings = Settings::for_test(
+            r#"
+            shell_completions = { zsh = "completions/_example", bash = "completions/example.bash" }
+            "#,
+        );
+        assert_eq!(
+            settings.shell_completions(),
+            [
+                (
+                    Shell::Bash,
+                    PathBuf::from("/nonexistent/example/completions/example.bash")
+                ),
+                (
+                    Shell::Zsh,
+                    PathBuf::from("/nonexistent/example/completions/_example")
+                ),
+            ]
+        );
+    }
 }
@@ -0,0 +1,418 @@
+// With `bundle_shared_libraries = true`, the shared libraries the binary loads
+// are found and shipped with it, so that the bundle runs where they are not
+// installed:
+//
+// Linux       `ldd` lists them; all but the core system libraries go in
+//             `usr/lib/<binary>/lib`, the binary moves to `usr/lib/<binary>`,
+//             and `usr/bin/<binary>` becomes a wrapper script setting
+//             `LD_LIBRARY_PATH`.
+// Mac OS X    `otool -l` lists them; those outside `/usr/lib` and `/System` go
+//             in `Contents/Frameworks`, and are loaded from there through the
+//             `@rpath` (see `osx_bundle.rs`).
+// Windows     The PE import tables list them; DLLs found next to the binary or
+//             on the `PATH`, outside the Windows directory, go next to the
+//             `.exe`.
+
+use super::settings::{ExternalBinary, Settings};
+use anyhow::Context;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The Linux libraries that are part of the base system, or tied to its
+/// drivers, which are never bundled.
+const LINUX_SYSTEM_LIBRARIES: &[&str] = &[
+    "linux-vdso.so",
+    "linux-gate.so",
+    "ld-linux",
+    "libc.so",
+    "libm.so",
+    "libdl.so",
+    "libpthread.so",
+    "librt.so",
+    "libresolv.so",
+    "libutil.so",
+    "libgcc_s.so",
+    "libstdc++.so",
+    "libGL.so",
+    "libGLX.so",
+    "libGLdispatch.so",
+    "libEGL.so",
+    "libOpenGL.so",
+    "libdrm.so",
+    "libX11.so",
+    "libxcb.so",
+    "libasound.so",
+    "libfontconfig.so",
+    "libfreetype.so",
+];
+
+/// Returns the external binaries to ship next to the binary, along with the
+/// DLLs it needs when bundling shared libraries for Windows.
+pub fn bundled_executables(settings: &Settings) -> crate::Result<Vec<ExternalBinary>> {
+    let mut executables = settings.external_binaries()?;
+    if settings.bundle_shared_libraries() && is_windows_target(settings) {
+        let mut binaries = vec![settings.binary_path().to_path_buf()];
+        binaries.extend(executables.iter().map(|binary| binary.src.clone()));
+        for dll in windows_dlls(&binaries, &dll_search_path(settings))? {
+            let name = dll.file_name().unwrap().to_string_lossy().into_owned();
+            if !executables.iter().any(|binary| binary.name == name) {
+                executables.push(ExternalBinary { src: dll, name });
+            }
+        }
+    }
+    Ok(executables)
+}
+
+fn is_windows_target(settings: &Settings) -> bool {
+    match settings.target_triple() {
+        Some(triple) => triple.contains("windows"),
+        None => cfg!(windows),
+    }
+}
+
+/// Returns the non-system shared libraries that the Linux binary at `binary`
+/// loads, directly or not.
+pub fn linux_libraries(binary: &Path) -> crate::Result<Vec<PathBuf>> {
+    let output = Command::new("ldd")
+        .arg(binary)
+        .output()
+        .with_context(|| "Failed to run `ldd`")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`ldd {binary:?}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    parse_ldd(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the output of `ldd`, keeping the libraries to bundle.
+fn parse_ldd(output: &str) -> crate::Result<Vec<PathBuf>> {
+    let mut libraries = Vec::new();
+    let mut missing = Vec::new();
+    for line in output.lines() {
+        let Some((name, location)) = line.trim().split_once(" => ") else {
+            // The vDSO and the dynamic loader have no `=>`.
+            continue;
+        };
+        if is_linux_system_library(name) {
+            continue;
+        }
+        if location.trim() == "not found" {
+            missing.push(name);
+            continue;
+        }
+        let path = match location.rfind(" (") {
+            Some(end) => &location[..end],
+            None => location,
+        };
+        libraries.push(PathBuf::from(path.trim()));
+    }
+    if !missing.is_empty() {
+        anyhow::bail!("Shared libraries not found: {}", missing.join(", "));
+    }
+    Ok(libraries)
+}
+
+/// Returns true if the Linux library `name` is part of the base system, such
+/// as `libc.so.6`, which is never bundled.
+pub fn is_linux_system_library(name: &str) -> bool {
+    LINUX_SYSTEM_LIBRARIES
+        .iter()
+        .any(|system| name.starts_with(system))
+}
+
+/// Returns the directories the DLLs of a Windows binary are looked for in.
+fn dll_search_path(settings: &Settings) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = settings
+        .binary_path()
+        .parent()
+        .into_iter()
+        .map(Path::to_path_buf)
+        .collect();
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    dirs
+}
+
+/// Returns the DLLs that the Windows `binaries` import, directly or not,
+/// which are found in the `search_path`, leaving out those in the Windows
+/// directory.  DLLs that are not found are assumed to be part of the system.
+fn windows_dlls(binaries: &[PathBuf], search_path: &[PathBuf]) -> crate::Result<Vec<PathBuf>> {
+    let system_root = std::env::var_os("SystemRoot").map(|root| lowercase(&root));
+    let mut seen = BTreeSet::new();
+    let mut dlls = Vec::new();
+    let mut pending = binaries.to_vec();
+    while let Some(binary) = pending.pop() {
+        let data = fs::read(&binary).with_context(|| format!("Failed to read {binary:?}"))?;
+        let imports = pe_imports(&data)
+            .with_context(|| format!("Failed to read the imports of {binary:?}"))?;
+        for name in imports {
+            let name = name.to_lowercase();
+            if name.starts_with("api-ms-win-") || name.starts_with("ext-ms-") {
+                continue;
+            }
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let Some(path) = find_file(search_path, &name) else {
+                continue;
+            };
+            if system_root
+                .as_ref()
+                .is_some_and(|root| lowercase(path.as_os_str()).starts_with(root))
+            {
+                continue;
+            }
+            dlls.push(path.clone());
+            pending.push(path);
+        }
+    }
+    dlls.sort();
+    Ok(dlls)
+}
+
+fn lowercase(path: &std::ffi::OsStr) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// Finds the file called `name` (ignoring case, as Windows does) in the first
+/// of `dirs` that has it.
+fn find_file(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter().find_map(|dir| {
+        fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name())
+            .find(|file_name: &OsString| file_name.to_string_lossy().to_lowercase() == name)
+            .map(|file_name| dir.join(file_name))
+    })
+}
+
+/// Returns the names of the DLLs listed in the import table of a PE file.
+fn pe_imports(data: &[u8]) -> crate::Result<Vec<String>> {
+    let u16_at = |offset: usize| -> crate::Result<u16> {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| anyhow::anyhow!("Truncated PE file"))
+    };
+    let u32_at = |offset: usize| -> crate::Result<u32> {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| anyhow::anyhow!("Truncated PE file"))
+    };
+    if !data.starts_with(b"MZ") {
+        anyhow::bail!("Not a PE file");
+    }
+    let pe = u32_at(0x3c)? as usize;
+    if data.get(pe..pe + 4) != Some(b"PE\0\0") {
+        anyhow::bail!("Not a PE file");
+    }
+    let coff = pe + 4;
+    let section_count = u16_at(coff + 2)? as usize;
+    let optional = coff + 20;
+    let data_directories = match u16_at(optional)? {
+        0x10b => optional + 96,
+        0x20b => optional + 112,
+        magic => anyhow::bail!("Unknown PE optional header magic {magic:#x}"),
+    };
+    let sections = optional + u16_at(coff + 16)? as usize;
+    // Maps a relative virtual address to an offset in the file.
+    let offset_of = |rva: u32| -> crate::Result<usize> {
+        for index in 0..section_count {
+            let section = sections + index * 40;
+            let virtual_size = u32_at(section + 8)?;
+            let address = u32_at(section + 12)?;
+            let raw_size = u32_at(section + 16)?;
+            if rva >= address && rva - address < virtual_size.max(raw_size) {
+                return Ok((rva - address + u32_at(section + 20)?) as usize);
+            }
+        }
+        anyhow::bail!("Address {rva:#x} is outside the PE sections")
+    };
+    let import_table = u32_at(data_directories + 8)?;
+    if import_table == 0 {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    let mut descriptor = offset_of(import_table)?;
+    loop {
+        let name_rva = u32_at(descriptor + 12)?;
+        if name_rva == 0 {
+            break;
+        }
+        let name = data.get(offset_of(name_rva)?..).unwrap_or_default();
+        let Some(length) = name.iter().position(|&byte| byte == 0) else {
+            anyhow::bail!("Truncated PE file");
+        };
+        names.push(String::from_utf8_lossy(&name[..length]).into_owned());
+        descriptor += 20;
+    }
+    Ok(names)
+}
+
+/// Returns the names of the shared libraries an ELF file needs directly, as
+/// listed in its dynamic section, e.g. `libc.so.6`.  Static binaries need
+/// none.
+pub fn elf_needed(data: &[u8]) -> crate::Result<Vec<String>> {
+    const SHT_DYNAMIC: u32 = 6;
+    const DT_NEEDED: u64 = 1;
+    if !data.starts_with(b"\x7fELF") || data.len() < 6 {
+        anyhow::bail!("Not an ELF file");
+    }
+    let is_64 = data[4] == 2;
+    let is_big_endian = data[5] == 2;
+    let uint_at = |offset: usize, size: usize| -> crate::Result<u64> {
+        let bytes = data
+            .get(offset..offset + size)
+            .ok_or_else(|| anyhow::anyhow!("Truncated ELF file"))?;
+        let mut value = 0;
+        for index in 0..size {
+            let byte = if is_big_endian {
+                bytes[index]
+            } else {
+                bytes[size - 1 - index]
+            };
+            value = value << 8 | u64::from(byte);
+        }
+        Ok(value)
+    };
+    // The word size, and the offsets of the fields used in the file and
+    // section headers.
+    let (word, shoff, shentsize, shnum, sh_offset, sh_size, sh_link) = if is_64 {
+        (8, 0x28, 0x3a, 0x3c, 0x18, 0x20, 0x28)
+    } else {
+        (4, 0x20, 0x2e, 0x30, 0x10, 0x14, 0x18)
+    };
+    let section_headers = uint_at(shoff, word)? as usize;
+    let section_size = uint_at(shentsize, 2)? as usize;
+    let section = |index: usize| section_headers + index * section_size;
+    let mut names = Vec::new();
+    for index in 0..uint_at(shnum, 2)? as usize {
+        let header = section(index);
+        if uint_at(header + 4, 4)? != u64::from(SHT_DYNAMIC) {
+            continue;
+        }
+        let strings = uint_at(
+            section(uint_at(header + sh_link, 4)? as usize) + sh_offset,
+            word,
+        )?;
+        let start = uint_at(header + sh_offset, word)? as usize;
+        let end = start + uint_at(header + sh_size, word)? as usize;
+        for entry in (start..end).step_by(word * 2) {
+            match uint_at(entry, word)? {
+                0 => break,
+                DT_NEEDED => {
+                    let name = strings as usize + uint_at(entry + word, word)? as usize;
+                    let name = data.get(name..).unwrap_or_default();
+                    let Some(length) = name.iter().position(|&byte| byte == 0) else {
+                        anyhow::bail!("Truncated ELF file");
+                    };
+                    names.push(String::from_utf8_lossy(&name[..length]).into_owned());
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ldd_output() {
+        let output = "\
+            \tlinux-vdso.so.1 (0x00007ffd5a7f2000)\n\
+            \tlibssl.so.3 => /opt/openssl/lib/libssl.so.3 (0x00007f1c2a000000)\n\
+            \tlibgtk-3.so.0 => /usr/lib/x86_64-linux-gnu/libgtk-3.so.0 (0x00007f1c29800000)\n\
+            \tlibc.so.6 => /lib/x86_64-linux-gnu/libc.so.6 (0x00007f1c29400000)\n\
+            \t/lib64/ld-linux-x86-64.so.2 (0x00007f1c2a200000)\n";
+        assert_eq!(
+            parse_ldd(output).unwrap(),
+            [
+                PathBuf::from("/opt/openssl/lib/libssl.so.3"),
+                PathBuf::from("/usr/lib/x86_64-linux-gnu/libgtk-3.so.0")
+            ]
+        );
+        let error = parse_ldd("\tlibfoo.so.1 => not found\n").unwrap_err();
+        assert_eq!(error.to_string(), "Shared libraries not found: libfoo.so.1");
+    }
+
+    /// Builds a PE32+ file with one section, holding an import table that
+    /// lists `dlls`.
+    fn pe_file(dlls: &[&str]) -> Vec<u8> {
+        const SECTION_RVA: u32 = 0x1000;
+        const SECTION_OFFSET: usize = 0x200;
+        let mut data = vec![0; SECTION_OFFSET];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let coff = 0x44;
+        data[coff + 2..coff + 4].copy_from_slice(&1u16.to_le_bytes());
+        data[coff + 16..coff + 18].copy_from_slice(&240u16.to_le_bytes());
+        let optional = coff + 20;
+        data[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        let import_directory = optional + 112 + 8;
+        data[import_directory..import_directory + 4].copy_from_slice(&SECTION_RVA.to_le_bytes());
+
+        // The section holds the descriptors, then a null one, then the names.
+        let mut section = vec![0; (dlls.len() + 1) * 20];
+        for (index, dll) in dlls.iter().enumerate() {
+            let name_rva = SECTION_RVA + section.len() as u32;
+            section[index * 20 + 12..index * 20 + 16].copy_from_slice(&name_rva.to_le_bytes());
+            section.extend_from_slice(dll.as_bytes());
+            section.push(0);
+        }
+        let header = optional + 240;
+        data[header..header + 8].copy_from_slice(b".idata\0\0");
+        data[header + 8..header + 12].copy_from_slice(&(section.len() as u32).to_le_bytes());
+        data[header + 12..header + 16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        data[header + 16..header + 20].copy_from_slice(&(section.len() as u32).to_le_bytes());
+        data[header + 20..header + 24].copy_from_slice(&(SECTION_OFFSET as u32).to_le_bytes());
+        data.extend_from_slice(&section);
+        data
+    }
+
+    #[test]
+    fn pe_import_table() {
+        let data = pe_file(&["KERNEL32.dll", "libssl-3-x64.dll"]);
+        assert_eq!(
+            pe_imports(&data).unwrap(),
+            ["KERNEL32.dll", "libssl-3-x64.dll"]
+        );
+        assert!(pe_imports(b"\x7fELF").is_err());
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[test]
+    fn elf_dynamic_section() {
+        let data = fs::read(std::env::current_exe().unwrap()).unwrap();
+        let needed = elf_needed(&data).unwrap();
+        assert!(needed.iter().any(|name| name == "libc.so.6"), "{needed:?}");
+        assert!(elf_needed(b"MZ").is_err());
+    }
+
+    #[test]
+    fn dll_dependencies() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bin_dir = tmp.path().join("bin");
+        let sdk_dir = tmp.path().join("sdk");
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::create_dir_all(&sdk_dir).unwrap();
+        let exe = bin_dir.join("app.exe");
+        fs::write(&exe, pe_file(&["KERNEL32.dll", "libssl-3-x64.dll"])).unwrap();
+        fs::write(bin_dir.join("libssl-3-x64.dll"), pe_file(&["VENDOR.DLL"])).unwrap();
+        fs::write(sdk_dir.join("vendor.dll"), pe_file(&["KERNEL32.dll"])).unwrap();
+        let dlls = windows_dlls(&[exe], &[bin_dir.clone(), sdk_dir.clone()]).unwrap();
+        assert_eq!(
+            dlls,
+            [bin_dir.join("libssl-3-x64.dll"), sdk_dir.join("vendor.dll")]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+// Sparkle (https://sparkle-project.org) updates macOS apps from an appcast, an
+// RSS feed listing the released versions of the app.  With `osx_sparkle` set,
+// the Sparkle framework is embedded in the app, whose `Info.plist` then names
+// the appcast (`SUFeedURL`) and the public key that updates must be signed with
+// (`SUPublicEDKey`).  If the `url` that updates are downloaded from is set too,
+// the app is archived as an update, and an appcast listing it is written next
+// to it:
+//
+// bundle/osx/
+//     Foobar.app
+//     Foobar-1.2.3.zip    # The update, archived with `ditto`
+//     appcast.xml         # Lists the update, with the EdDSA signature that
+//                         # Sparkle's `sign_update` tool made of it
+//
+// `sign_update` reads the private key from the keychain, or else from the file
+// named by the `SPARKLE_ED_KEY_FILE` environment variable.
+
+use super::build_info::build_time;
+use super::common;
+use super::osx_bundle::short_version_string;
+use crate::Settings;
+use anyhow::Context;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The name of the framework directory in `Contents/Frameworks`.
+const FRAMEWORK_NAME: &str = "Sparkle.framework";
+
+/// The environment variable naming the file that holds the private EdDSA key.
+const ED_KEY_FILE_VAR: &str = "SPARKLE_ED_KEY_FILE";
+
+/// Copies the Sparkle framework into the `Frameworks` directory of the bundle,
+/// returning true if `osx_sparkle` is set.
+pub fn copy_framework(bundle_directory: &Path, settings: &Settings) -> crate::Result<bool> {
+    let Some(framework) = settings.osx_sparkle_framework() else {
+        return Ok(false);
+    };
+    let dest = bundle_directory.join("Frameworks").join(FRAMEWORK_NAME);
+    common::copy_dir(&framework, &dest)
+        .with_context(|| format!("Failed to copy Sparkle framework from {framework:?}"))?;
+    Ok(true)
+}
+
+/// Archives the app bundle as an update, and writes an appcast listing it,
+/// returning their paths.  Does nothing unless the `url` of `osx_sparkle` is
+/// set.
+pub fn create_update(settings: &Settings, app_bundle_path: &Path) -> crate::Result<Vec<PathBuf>> {
+    let Some(url_template) = settings
+        .osx_sparkle()
+        .and_then(|sparkle| sparkle.url.as_deref())
+    else {
+        return Ok(Vec::new());
+    };
+    let out_dir = app_bundle_path.parent().unwrap();
+    let archive_name = format!(
+        "{}-{}.zip",
+        settings.bundle_file_name(),
+        settings.version_string()
+    );
+    common::print_bundling(&archive_name)?;
+    let archive_path = out_dir.join(&archive_name);
+    if archive_path.exists() {
+        fs::remove_file(&archive_path)
+            .with_context(|| format!("Failed to remove old {archive_name}"))?;
+    }
+    common::run_tool(
+        Command::new("ditto")
+            .args(["-c", "-k", "--sequesterRsrc", "--keepParent"])
+            .arg(app_bundle_path)
+            .arg(&archive_path),
+        "ditto",
+    )?;
+    let mut sign_update = Command::new(sign_update_path(settings));
+    if let Some(key_file) = std::env::var_os(ED_KEY_FILE_VAR) {
+        sign_update.arg("--ed-key-file").arg(key_file);
+    }
+    let signature = common::run_tool(sign_update.arg(&archive_path), "sign_update")?;
+    let url = download_url(settings, url_template, &archive_name)?;
+    let bundle_version = bundle_version(&app_bundle_path.join("Contents/Info.plist"))?;
+    let appcast_path = out_dir.join("appcast.xml");
+    let mut file = common::create_file(&appcast_path)?;
+    file.write_all(
+        appcast(
+            settings,
+            &bundle_version,
+            &url,
+            String::from_utf8_lossy(&signature).trim(),
+        )?
+        .as_bytes(),
+    )?;
+    file.flush()?;
+    Ok(vec![archive_path, appcast_path])
+}
+
+/// Returns the path of the `sign_update` tool: the one in the `bin` directory
+/// next to the framework, as laid out in Sparkle's release archives, or else
+/// the one on the `PATH`.
+fn sign_update_path(settings: &Settings) -> PathBuf {
+    settings
+        .osx_sparkle_framework()
+        .and_then(|framework| {
+            let path = framework.parent()?.join("bin/sign_update");
+            path.is_file().then_some(path)
+        })
+        .unwrap_or_else(|| PathBuf::from("sign_update"))
+}
+
+/// Expands the `url` template of `osx_sparkle` for the update named
+/// `file_name`.
+fn download_url(settings: &Settings, url_template: &str, file_name: &str) -> crate::Result<String> {
+    let version = settings.version_string().to_string();
+    let tokens = [
+        ("name", settings.binary_name()),
+        ("version", version.as_str()),
+        ("file", file_name),
+    ];
+    common::expand_template(url_template, &tokens)
+        .context("Failed to expand the `url` of `osx_sparkle`")
+}
+
+/// Reads the `CFBundleVersion` of the app from its `Info.plist`, which Sparkle
+/// compares with that of the updates.
+fn bundle_version(info_plist: &Path) -> crate::Result<String> {
+    let plist = common::read_file(info_plist)?;
+    let mut lines = plist.lines();
+    lines
+        .find(|line| line.trim() == "<key>CFBundleVersion</key>")
+        .and_then(|_| lines.next())
+        .and_then(|line| {
+            line.trim()
+                .strip_prefix("<string>")?
+                .strip_suffix("</string>")
+        })
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No CFBundleVersion in {info_plist:?}"))
+}
+
+/// Returns an appcast listing the update downloaded from `url`, whose
+/// `enclosure` has the `sparkle:edSignature` and `length` attributes printed
+/// by `sign_update`.
+fn appcast(
+    settings: &Settings,
+    bundle_version: &str,
+    url: &str,
+    signature: &str,
+) -> crate::Result<String> {
+    let version = settings.version_string().to_string();
+    let mut item = format!(
+        "    <item>\n      \
+               <title>{}</title>\n      \
+               <pubDate>{}</pubDate>\n      \
+               <sparkle:version>{}</sparkle:version>\n      \
+               <sparkle:shortVersionString>{}</sparkle:shortVersionString>\n",
+        xml_escape(&version),
+        build_time()?.to_rfc2822(),
+        xml_escape(bundle_version),
+        short_version_string(&version),
+    );
+    if let Some(version) = settings.osx_minimum_system_version() {
+        item.push_str(&format!(
+            "      <sparkle:minimumSystemVersion>{}</sparkle:minimumSystemVersion>\n",
+            xml_escape(version)
+        ));
+    }
+    item.push_str(&format!(
+        "      <enclosure url=\"{}\" {signature} type=\"application/octet-stream\"/>\n    \
+           </item>\n",
+        xml_escape(url)
+    ));
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <rss version=\"2.0\" xmlns:sparkle=\"http://www.andymatuschak.org/xml-namespaces/sparkle\">\n  \
+           <channel>\n    \
+             <title>{}</title>\n\
+         {item}  \
+           </channel>\n\
+         </rss>\n",
+        xml_escape(settings.bundle_name())
+    ))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appcast_item() {
+        let settings = Settings::for_test(
+            r#"
+            name = "My App"
+            osx_minimum_system_version = "10.13"
+            [osx_sparkle]
+            framework = "Sparkle/Sparkle.framework"
+            feed_url = "https://example.com/appcast.xml"
+            public_key = "pfIShU4dEXqPd5ObYNfDBiQWcXozk7estwzTnF9BamQ="
+            url = "https://example.com/{version}/{file}"
+            "#,
+        );
+        assert_eq!(
+            settings.osx_sparkle_framework().unwrap(),
+            Path::new("/nonexistent/example/Sparkle/Sparkle.framework")
+        );
+        let url = download_url(
+            &settings,
+            settings.osx_sparkle().unwrap().url.as_deref().unwrap(),
+            "My App-1.0.0.zip",
+        )
+        .unwrap();
+        assert_eq!(url, "https://example.com/1.0.0/My App-1.0.0.zip");
+        let appcast = appcast(
+            &settings,
+            "20261015.120000",
+            &url,
+            "sparkle:edSignature=\"c2ln\" length=\"1234\"",
+        )
+        .unwrap();
+        assert!(appcast.contains("    <title>My App</title>\n    <item>\n"));
+        assert!(appcast.contains(
+            "      <sparkle:version>20261015.120000</sparkle:version>\n      \
+             <sparkle:shortVersionString>1.0.0</sparkle:shortVersionString>\n      \
+             <sparkle:minimumSystemVersion>10.13</sparkle:minimumSystemVersion>\n      \
+             <enclosure url=\"https://example.com/1.0.0/My App-1.0.0.zip\" \
+             sparkle:edSignature=\"c2ln\" length=\"1234\" type=\"application/octet-stream\"/>\n"
+        ));
+    }
+
+    #[test]
+    fn bundle_version_from_info_plist() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("Info.plist");
+        fs::write(
+            &path,
+            "<dict>\n  <key>CFBundleVersion</key>\n  <string>20261015.120000</string>\n</dict>\n",
+        )
+        .unwrap();
+        assert_eq!(bundle_version(&path).unwrap(), "20261015.120000");
+    }
+}
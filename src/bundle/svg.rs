@@ -0,0 +1,113 @@
+// Rasterizes SVG icons into the PNG, ICO and ICNS icons that the bundle
+// formats need, with resvg.  Text is not drawn, since no fonts are loaded, and
+// neither are `foreignObject` elements; icons using them get a warning.
+
+use anyhow::Context;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg;
+
+/// Elements that are parsed, but not drawn.
+const SKIPPED_ELEMENTS: &[&str] = &["text", "foreignObject"];
+
+/// A parsed SVG document.
+pub struct Document {
+    // Boxed, as trees are large.
+    tree: Box<usvg::Tree>,
+    /// The names of the elements of the document that are not drawn.
+    skipped_elements: Vec<String>,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> crate::Result<Document> {
+        let tree = usvg::Tree::from_str(text, &usvg::Options::default())
+            .context("Not a valid SVG document")?;
+        let mut skipped_elements = Vec::new();
+        let mut reader = Reader::from_str(text);
+        loop {
+            let name = match reader.read_event()? {
+                Event::Start(start) | Event::Empty(start) => {
+                    String::from_utf8_lossy(start.local_name().as_ref()).into_owned()
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            if SKIPPED_ELEMENTS.contains(&name.as_str()) && !skipped_elements.contains(&name) {
+                skipped_elements.push(name);
+            }
+        }
+        Ok(Document {
+            tree: Box::new(tree),
+            skipped_elements,
+        })
+    }
+
+    /// Returns the names of the elements of the document that are not drawn,
+    /// such as `text`.
+    pub fn skipped_elements(&self) -> &[String] {
+        &self.skipped_elements
+    }
+
+    /// Renders the document into a `size`×`size` image, scaled to fit and
+    /// centered as with the default `preserveAspectRatio`.
+    pub fn render(&self, size: u32) -> crate::Result<image::RgbaImage> {
+        let mut pixmap = Pixmap::new(size, size).context("Invalid icon size")?;
+        let (width, height) = (self.tree.size().width(), self.tree.size().height());
+        let scale = (size as f32 / width).min(size as f32 / height);
+        let transform = Transform::from_row(
+            scale,
+            0.0,
+            0.0,
+            scale,
+            (size as f32 - width * scale) / 2.0,
+            (size as f32 - height * scale) / 2.0,
+        );
+        resvg::render(&self.tree, transform, &mut pixmap.as_mut());
+        let mut image = image::RgbaImage::new(size, size);
+        for (pixel, color) in image.pixels_mut().zip(pixmap.pixels()) {
+            let color = color.demultiply();
+            *pixel = image::Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
+        }
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let document = Document::parse(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 4">
+                <defs>
+                  <linearGradient id="g"><stop offset="0" stop-color="#00f"/></linearGradient>
+                </defs>
+                <rect width="4" height="4" style="fill: red"/>
+                <g opacity="0.5" fill="url(#g)"><rect x="4" width="4" height="4"/></g>
+                <circle cx="2" cy="2" r="1" display="none"/>
+              </svg>"##,
+        )
+        .unwrap();
+        assert!(document.skipped_elements().is_empty());
+        let image = document.render(16).unwrap();
+        // The wide view box is centered vertically.
+        assert_eq!(image.get_pixel(4, 2).0, [0, 0, 0, 0]);
+        assert_eq!(image.get_pixel(4, 8).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(12, 8).0, [0, 0, 255, 128]);
+        assert!(Document::parse("<html/>").is_err());
+    }
+
+    #[test]
+    fn skipped_elements() {
+        let document = Document::parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 4 4">
+                <text x="0" y="4">A</text><g><text>B</text></g>
+                <rect width="4" height="4" clip-path="url(#c)"/>
+              </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(document.skipped_elements(), ["text"]);
+    }
+}
@@ -0,0 +1,195 @@
+// With the `version_from` setting, the version of the bundles is derived
+// before bundling, instead of being read from the `version` setting or
+// `Cargo.toml`:
+//
+// version_from = "git"                         # From `git describe --tags`:
+//                                              # `v1.2.3` on the tagged commit,
+//                                              # `1.2.3+5.gabc1234` five commits
+//                                              # later
+// version_from = { command = "./version.sh" }  # The output of a shell command,
+//                                              # run in the package directory
+//
+// A leading `v` is dropped either way.  If the repository has no tags, the
+// commits are counted from the start, after the version from `Cargo.toml`.
+// The bundlers then normalize the version as their package format requires,
+// e.g. `1.2.3~beta.1` for deb and rpm packages, or `1.2.3` for MSI installers.
+
+use super::build_info::git_output;
+use super::common;
+use super::settings::{Settings, VersionSource};
+use anyhow::Context;
+use std::path::Path;
+use std::process::Command;
+
+/// Derives the version as the `version_from` setting asks, if it is set, and
+/// makes `settings` use it.
+pub fn resolve_version(settings: &mut Settings) -> crate::Result<()> {
+    let Some(source) = settings.version_from().cloned() else {
+        return Ok(());
+    };
+    let dir = settings.manifest_path().parent().unwrap_or(Path::new("."));
+    let version = match source {
+        VersionSource::Git => git_version(dir, &settings.version_string().to_string())?,
+        VersionSource::Command(command) => command_version(dir, &command)?,
+    };
+    settings.set_version(version);
+    Ok(())
+}
+
+/// Returns the version described by git for the repository containing `dir`,
+/// or `fallback` followed by the number of commits if it has no tags.
+fn git_version(dir: &Path, fallback: &str) -> crate::Result<String> {
+    if let Some(description) = git_output(dir, &["describe", "--tags", "--long"]) {
+        return describe_version(&description).with_context(|| {
+            format!("Failed to parse the output of git describe, {description:?}")
+        });
+    }
+    let (Some(count), Some(hash)) = (
+        git_output(dir, &["rev-list", "--count", "HEAD"]),
+        git_output(dir, &["rev-parse", "--short", "HEAD"]),
+    ) else {
+        anyhow::bail!("version_from = \"git\" needs the package to be in a git repository");
+    };
+    Ok(commits_version(fallback, &count, &hash))
+}
+
+/// Converts the output of `git describe --long`, `<tag>-<count>-g<hash>`, to a
+/// version.
+fn describe_version(description: &str) -> crate::Result<String> {
+    let mut parts = description.rsplitn(3, '-');
+    let (Some(hash), Some(count), Some(tag)) = (parts.next(), parts.next(), parts.next()) else {
+        anyhow::bail!("Expected <tag>-<count>-g<hash>");
+    };
+    let hash = hash
+        .strip_prefix('g')
+        .context("Expected a hash starting with g")?;
+    let count: u32 = count.parse().context("Invalid commit count")?;
+    let tag = strip_v(tag);
+    if count == 0 {
+        Ok(tag.to_string())
+    } else {
+        Ok(commits_version(tag, &count.to_string(), hash))
+    }
+}
+
+/// Appends the number of commits since `base` and the commit hash to `base`,
+/// as semver build metadata.
+fn commits_version(base: &str, count: &str, hash: &str) -> String {
+    let separator = if base.contains('+') { '.' } else { '+' };
+    format!("{base}{separator}{count}.g{hash}")
+}
+
+/// Runs `command` with the shell in `dir`, and returns its trimmed output.
+fn command_version(dir: &Path, command: &str) -> crate::Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command).current_dir(dir);
+    let name = format!("version command {command:?}");
+    let output = common::run_tool(&mut shell, &name)?;
+    let output = String::from_utf8(output).with_context(|| format!("Invalid output of {name}"))?;
+    let version = strip_v(output.trim());
+    if version.is_empty() {
+        anyhow::bail!("The {name} printed no version");
+    }
+    Ok(version.to_string())
+}
+
+/// Drops the `v` that tags often start with, as in `v1.2.3`.
+fn strip_v(version: &str) -> &str {
+    match version.strip_prefix(['v', 'V']) {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => version,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_versions() {
+        assert_eq!(describe_version("v1.2.3-0-gabc1234").unwrap(), "1.2.3");
+        assert_eq!(
+            describe_version("v1.2.3-5-gabc1234").unwrap(),
+            "1.2.3+5.gabc1234"
+        );
+        assert_eq!(
+            describe_version("release-1.2.3-beta.1-12-gabc1234").unwrap(),
+            "release-1.2.3-beta.1+12.gabc1234"
+        );
+        assert_eq!(
+            describe_version("1.2.3+linux-2-gabc1234").unwrap(),
+            "1.2.3+linux.2.gabc1234"
+        );
+        assert!(describe_version("abc1234").is_err());
+        assert!(describe_version("v1.2.3-x-gabc1234").is_err());
+    }
+
+    #[test]
+    fn v_prefixes() {
+        assert_eq!(strip_v("v1.2.3"), "1.2.3");
+        assert_eq!(strip_v("V2"), "2");
+        assert_eq!(strip_v("very"), "very");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(
+            command_version(tmp.path(), "echo v1.2.3-nightly.20240102").unwrap(),
+            "1.2.3-nightly.20240102"
+        );
+        assert!(command_version(tmp.path(), "true").is_err());
+        assert!(command_version(tmp.path(), "exit 1").is_err());
+    }
+
+    #[test]
+    fn git_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(tmp.path())
+                .env("GIT_AUTHOR_NAME", "Jane Doe")
+                .env("GIT_AUTHOR_EMAIL", "jane@example.com")
+                .env("GIT_COMMITTER_NAME", "Jane Doe")
+                .env("GIT_COMMITTER_EMAIL", "jane@example.com")
+                .status();
+            status.is_ok_and(|status| status.success())
+        };
+        if !git(&["init", "-q"]) {
+            // git is not installed.
+            return;
+        }
+        assert!(git(&[
+            "commit",
+            "-q",
+            "--no-gpg-sign",
+            "--allow-empty",
+            "-m",
+            "first"
+        ]));
+        let version = git_version(tmp.path(), "0.1.0").unwrap();
+        assert!(version.starts_with("0.1.0+1.g"), "{version}");
+        assert!(git(&["tag", "v0.2.0"]));
+        assert_eq!(git_version(tmp.path(), "0.1.0").unwrap(), "0.2.0");
+        assert!(git(&[
+            "commit",
+            "-q",
+            "--no-gpg-sign",
+            "--allow-empty",
+            "-m",
+            "second"
+        ]));
+        let version = git_version(tmp.path(), "0.1.0").unwrap();
+        assert!(version.starts_with("0.2.0+1.g"), "{version}");
+    }
+}
@@ -0,0 +1,286 @@
+// The Windows installers register the `file_associations` and `url_schemes` by
+// writing these values under `Software\Classes`, of HKLM for machine-wide
+// installs (or HKCU for per-user ones):
+//
+// Software\Classes\
+//     .foo                          # (Default) = the ProgId below, and
+//                                   # Content Type = the MIME type
+//         OpenWithProgids           # com.example.foobar.foo = ""
+//     com.example.foobar.foo        # The ProgId; (Default) = the description
+//         DefaultIcon               # (Default) = the ICO icon, or the binary's
+//         shell\open\command        # (Default) = "C:\...\foobar.exe" "%1"
+//     foobar                        # A URL scheme; (Default) = URL:Foobar, and
+//                                   # URL Protocol = ""
+//         DefaultIcon               # (Default) = the binary's icon
+//         shell\open\command        # (Default) = "C:\...\foobar.exe" "%1"
+//
+// See https://learn.microsoft.com/en-us/windows/win32/shell/fa-progids and
+// https://learn.microsoft.com/en-us/previous-versions/windows/internet-explorer/ie-developer/platform-apis/aa767914(v=vs.85)
+// for more information.
+
+use super::settings::{FileAssociation, Settings};
+use std::path::PathBuf;
+
+/// The key under which the values are written.
+pub const CLASSES_KEY: &str = "Software\\Classes";
+
+/// A value to write in the registry, under the `CLASSES_KEY`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegistryValue {
+    /// The key of the value, relative to the `CLASSES_KEY`.
+    pub key: String,
+    /// The name of the value, or `None` for the default value of the key.
+    pub name: Option<String>,
+    pub value: String,
+}
+
+impl RegistryValue {
+    fn new(key: String, name: Option<&str>, value: String) -> RegistryValue {
+        RegistryValue {
+            key,
+            name: name.map(str::to_string),
+            value,
+        }
+    }
+}
+
+/// Returns the ProgId of `association`, which identifies its file type.
+fn prog_id(settings: &Settings, association: &FileAssociation) -> String {
+    let identifier = settings.bundle_identifier();
+    let app = if identifier.is_empty() {
+        settings.bundle_file_name()
+    } else {
+        identifier.into_owned()
+    };
+    let extension = association.extensions.first().map(String::as_str);
+    let prog_id = format!("{app}.{}", extension.unwrap_or_default());
+    // ProgIds may only contain letters, digits and periods.
+    prog_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '.')
+        .collect()
+}
+
+/// Returns the keys that belong to the app alone, which uninstallers delete
+/// entirely, relative to the `CLASSES_KEY`: the ProgIds and the URL schemes.
+pub fn owned_keys(settings: &Settings) -> Vec<String> {
+    let prog_ids = settings
+        .file_associations()
+        .iter()
+        .map(|association| prog_id(settings, association));
+    prog_ids
+        .chain(settings.url_schemes().iter().cloned())
+        .collect()
+}
+
+/// Returns the ICO icons of the `file_associations`, which the installers
+/// install next to the binary.
+pub fn icon_files(settings: &Settings) -> Vec<PathBuf> {
+    let mut icons = Vec::new();
+    for association in settings.file_associations() {
+        if let Some(icon) = settings.file_association_icon(association, &["ico"])
+            && !icons.contains(&icon)
+        {
+            icons.push(icon);
+        }
+    }
+    icons
+}
+
+/// Returns the values registering the `file_associations` and the
+/// `url_schemes`.  Installed files
+/// are referred to by their name appended to `install_dir`, e.g. `$INSTDIR\`,
+/// and `escape` is applied to everything else, as the installer's syntax
+/// requires.
+pub fn registry_values(
+    settings: &Settings,
+    install_dir: &str,
+    escape: fn(&str) -> String,
+) -> Vec<RegistryValue> {
+    let installed = |name: &str| format!("{install_dir}{}", escape(name));
+    let exe_name = settings.binary_path().file_name().unwrap_or_default();
+    let exe = installed(&exe_name.to_string_lossy());
+    let command = format!("{q}{exe}{q} {q}%1{q}", q = escape("\""));
+    let mut values = Vec::new();
+    for association in settings.file_associations() {
+        let prog_id = escape(&prog_id(settings, association));
+        for extension in &association.extensions {
+            let key = escape(&format!(".{extension}"));
+            values.push(RegistryValue::new(key.clone(), None, prog_id.clone()));
+            if association.mime_type.is_some() {
+                let mime_type = escape(&association.mime_type());
+                values.push(RegistryValue::new(
+                    key.clone(),
+                    Some("Content Type"),
+                    mime_type,
+                ));
+            }
+            values.push(RegistryValue::new(
+                format!("{key}\\OpenWithProgids"),
+                Some(&prog_id),
+                String::new(),
+            ));
+        }
+        let description = escape(&association.description());
+        values.push(RegistryValue::new(prog_id.clone(), None, description));
+        let icon = match settings.file_association_icon(association, &["ico"]) {
+            Some(icon) => installed(&icon.file_name().unwrap().to_string_lossy()),
+            None => format!("{exe},0"),
+        };
+        values.push(RegistryValue::new(
+            format!("{prog_id}\\DefaultIcon"),
+            None,
+            icon,
+        ));
+        values.push(RegistryValue::new(
+            format!("{prog_id}\\shell\\open\\command"),
+            None,
+            command.clone(),
+        ));
+    }
+    for scheme in settings.url_schemes() {
+        let key = escape(scheme);
+        let description = escape(&format!("URL:{}", settings.bundle_name()));
+        values.push(RegistryValue::new(key.clone(), None, description));
+        values.push(RegistryValue::new(
+            key.clone(),
+            Some("URL Protocol"),
+            String::new(),
+        ));
+        values.push(RegistryValue::new(
+            format!("{key}\\DefaultIcon"),
+            None,
+            format!("{exe},0"),
+        ));
+        values.push(RegistryValue::new(
+            format!("{key}\\shell\\open\\command"),
+            None,
+            command.clone(),
+        ));
+    }
+    values
+}
+
+/// Returns a string of a `registry` entry of the `[package.metadata.bundle.windows]`
+/// table, in which `[INSTALLDIR]` stands for `install_dir`, with `escape`
+/// applied to the rest.
+pub fn expand_install_dir(value: &str, install_dir: &str, escape: fn(&str) -> String) -> String {
+    value
+        .split("[INSTALLDIR]")
+        .map(escape)
+        .collect::<Vec<_>>()
+        .join(install_dir)
+}
+
+/// Escapes the characters that have a meaning in the formatted strings of
+/// Windows Installer databases, so that they are taken literally.
+pub fn escape_formatted(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '[' | ']' | '{' | '}' => escaped.push_str(&format!("[\\{c}]")),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_scheme_registry_values() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             url_schemes = [\"myapp\"]\n",
+        );
+        let values = registry_values(&settings, "$INSTDIR\\", str::to_string);
+        let value = |key: &str, name: Option<&str>, value: &str| {
+            RegistryValue::new(key.to_string(), name, value.to_string())
+        };
+        assert_eq!(
+            values,
+            [
+                value("myapp", None, "URL:My App"),
+                value("myapp", Some("URL Protocol"), ""),
+                value("myapp\\DefaultIcon", None, "$INSTDIR\\example,0"),
+                value(
+                    "myapp\\shell\\open\\command",
+                    None,
+                    "\"$INSTDIR\\example\" \"%1\""
+                ),
+            ]
+        );
+        assert_eq!(owned_keys(&settings), ["myapp"]);
+    }
+
+    #[test]
+    fn formatted_strings() {
+        assert_eq!(escape_formatted("a [b] {c}"), "a [\\[]b[\\]] [\\{]c[\\}]");
+    }
+
+    #[test]
+    fn file_association_registry_values() {
+        let settings = Settings::for_test(
+            r#"
+            identifier = "com.example.my-app"
+            file_associations = [
+                { extensions = ["md", "markdown"], mime_type = "text/markdown", icon = ["md.png", "md.ico"] },
+                { extensions = ["foo"], description = "Foo file" },
+            ]
+            "#,
+        );
+        let values = registry_values(&settings, "[INSTALLDIR]", str::to_string);
+        let value = |key: &str, name: Option<&str>, value: &str| {
+            RegistryValue::new(key.to_string(), name, value.to_string())
+        };
+        assert_eq!(
+            values,
+            [
+                value(".md", None, "com.example.myapp.md"),
+                value(".md", Some("Content Type"), "text/markdown"),
+                value(".md\\OpenWithProgids", Some("com.example.myapp.md"), ""),
+                value(".markdown", None, "com.example.myapp.md"),
+                value(".markdown", Some("Content Type"), "text/markdown"),
+                value(
+                    ".markdown\\OpenWithProgids",
+                    Some("com.example.myapp.md"),
+                    ""
+                ),
+                value("com.example.myapp.md", None, "MD document"),
+                value(
+                    "com.example.myapp.md\\DefaultIcon",
+                    None,
+                    "[INSTALLDIR]md.ico"
+                ),
+                value(
+                    "com.example.myapp.md\\shell\\open\\command",
+                    None,
+                    "\"[INSTALLDIR]example\" \"%1\""
+                ),
+                value(".foo", None, "com.example.myapp.foo"),
+                value(".foo\\OpenWithProgids", Some("com.example.myapp.foo"), ""),
+                value("com.example.myapp.foo", None, "Foo file"),
+                value(
+                    "com.example.myapp.foo\\DefaultIcon",
+                    None,
+                    "[INSTALLDIR]example,0"
+                ),
+                value(
+                    "com.example.myapp.foo\\shell\\open\\command",
+                    None,
+                    "\"[INSTALLDIR]example\" \"%1\""
+                ),
+            ]
+        );
+        assert_eq!(
+            owned_keys(&settings),
+            ["com.example.myapp.md", "com.example.myapp.foo"]
+        );
+        assert_eq!(
+            icon_files(&settings),
+            [PathBuf::from("/nonexistent/example/md.ico")]
+        );
+    }
+}
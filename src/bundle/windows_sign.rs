@@ -0,0 +1,549 @@
+// When `[package.metadata.bundle.windows.sign]` is set, the binary and the
+// installers built by the Windows bundlers are given Authenticode signatures.
+// On Windows this is done with `signtool` from the Windows SDK:
+//
+// signtool sign /fd sha256 /f cert.pfx /p ... /tr http://timestamp.example /td sha256 foobar.msi
+//
+// When cross-building from other systems, `osslsigncode` is used instead (the
+// tool can also be chosen with the `tool` setting), which writes the signed
+// file to a new path:
+//
+// osslsigncode sign -h sha256 -pkcs12 cert.pfx -pass ... -ts http://timestamp.example -in foobar.msi -out foobar.msi.signed
+//
+// Keys held in a hardware token or HSM (as EV certificates must be) are used
+// through a PKCS #11 module, which only `osslsigncode` supports, with the
+// certificate in a separate file:
+//
+// osslsigncode sign -h sha256 -pkcs11module libtoken.so -key pkcs11:... -certs cert.pem -pass PIN ...
+//
+// Instead of a local certificate, a certificate profile of an Azure Trusted
+// Signing account can sign, so that CI needs no certificate material.  On
+// Windows, `signtool` signs through Microsoft's dlib, given the account in a
+// metadata file:
+//
+// signtool sign /fd sha256 /dlib Azure.CodeSigning.Dlib.dll /dmdf trusted-signing.json /tr ... foobar.msi
+//
+// Elsewhere (or without a dlib), `jsign` is used, with an access token for
+// the account:
+//
+// jsign --storetype TRUSTEDSIGNING --keystore eus.codesigning.azure.net --storepass TOKEN --alias account/profile ... foobar.msi
+//
+// The binary is signed in place, before it is packaged.
+
+use super::common;
+use super::settings::{
+    DigestAlgorithm, TrustedSigningSettings, WindowsSignSettings, WindowsSigningTool,
+};
+use crate::Settings;
+use anyhow::Context;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The environment variable holding the password of the certificate file.
+const CERTIFICATE_PASSWORD_VAR: &str = "CARGO_BUNDLE_WINDOWS_CERTIFICATE_PASSWORD";
+
+/// The environment variable holding the PIN of a hardware token.
+const TOKEN_PIN_VAR: &str = "CARGO_BUNDLE_TOKEN_PIN";
+
+/// The environment variable holding an access token for Azure Trusted Signing.
+const AZURE_ACCESS_TOKEN_VAR: &str = "CARGO_BUNDLE_AZURE_ACCESS_TOKEN";
+
+/// The timestamp server of Azure Trusted Signing, used unless `timestamp_url`
+/// is set.
+const TRUSTED_SIGNING_TIMESTAMP_URL: &str = "http://timestamp.acs.microsoft.com";
+
+/// Where the key that signs Windows files is held.
+enum SigningKey<'a> {
+    /// Whichever certificate in the Windows certificate store `signtool`
+    /// deems best.
+    Automatic,
+    /// A certificate in the Windows certificate store, by SHA-1 thumbprint.
+    Thumbprint(&'a str),
+    /// A PKCS #12 file holding the certificate and private key.
+    Certificate {
+        path: PathBuf,
+        password: Option<String>,
+    },
+    /// A key in a hardware token, used through a PKCS #11 module, with its
+    /// certificate in a separate file.
+    Pkcs11 {
+        module: PathBuf,
+        key: &'a str,
+        certificate: PathBuf,
+        pin: Option<String>,
+    },
+    /// A certificate profile of an Azure Trusted Signing account.
+    TrustedSigning {
+        account: &'a TrustedSigningSettings,
+        /// The dlib and metadata file `signtool` signs through.
+        dlib: Option<(PathBuf, PathBuf)>,
+        /// The access token `jsign` authenticates with.
+        access_token: Option<String>,
+    },
+}
+
+impl<'a> SigningKey<'a> {
+    /// Reads the key settings, and any password or token from the
+    /// environment, as needed to sign with `tool`.
+    fn from_settings(
+        settings: &Settings,
+        sign_settings: &'a WindowsSignSettings,
+        tool: WindowsSigningTool,
+    ) -> crate::Result<SigningKey<'a>> {
+        let dir = settings.manifest_path().parent().unwrap_or(Path::new("."));
+        let certificate = sign_settings
+            .certificate
+            .as_ref()
+            .map(|certificate| dir.join(certificate));
+        if let Some(account) = &sign_settings.trusted_signing {
+            let mut dlib = None;
+            let mut access_token = None;
+            match tool {
+                WindowsSigningTool::Signtool => {
+                    let Some(path) = &account.dlib else {
+                        anyhow::bail!(
+                            "Signing with Trusted Signing through signtool requires `dlib` to \
+                             be set in `[package.metadata.bundle.windows.sign.trusted_signing]`"
+                        );
+                    };
+                    let metadata = settings
+                        .project_out_directory()
+                        .join("trusted-signing.json");
+                    fs::write(&metadata, trusted_signing_metadata(account)?)
+                        .with_context(|| format!("Failed to write {metadata:?}"))?;
+                    dlib = Some((dir.join(path), metadata));
+                }
+                WindowsSigningTool::Jsign => access_token = Some(azure_access_token()?),
+                WindowsSigningTool::Osslsigncode => {}
+            }
+            return Ok(SigningKey::TrustedSigning {
+                account,
+                dlib,
+                access_token,
+            });
+        }
+        if let Some(module) = &sign_settings.pkcs11_module {
+            let Some(key) = &sign_settings.pkcs11_key else {
+                anyhow::bail!("Signing with a PKCS #11 module requires `pkcs11_key` to be set");
+            };
+            let Some(certificate) = certificate else {
+                anyhow::bail!(
+                    "Signing with a PKCS #11 module requires the `certificate` matching the key"
+                );
+            };
+            return Ok(SigningKey::Pkcs11 {
+                module: dir.join(module),
+                key,
+                certificate,
+                pin: std::env::var(TOKEN_PIN_VAR).ok(),
+            });
+        }
+        Ok(match (certificate, &sign_settings.certificate_thumbprint) {
+            (Some(path), _) => SigningKey::Certificate {
+                path,
+                password: std::env::var(CERTIFICATE_PASSWORD_VAR).ok(),
+            },
+            (None, Some(thumbprint)) => SigningKey::Thumbprint(thumbprint),
+            (None, None) => SigningKey::Automatic,
+        })
+    }
+}
+
+/// Signs the file at `path` in place, according to the `windows.sign`
+/// settings.  Does nothing if they are not set.
+pub fn sign_file(settings: &Settings, path: &Path) -> crate::Result<()> {
+    let Some(sign_settings) = settings.windows_sign() else {
+        return Ok(());
+    };
+    let tool = match (sign_settings.tool, &sign_settings.trusted_signing) {
+        (Some(tool), _) => tool,
+        (None, Some(account)) if cfg!(windows) && account.dlib.is_some() => {
+            WindowsSigningTool::Signtool
+        }
+        (None, Some(_)) => WindowsSigningTool::Jsign,
+        (None, None) if cfg!(windows) && sign_settings.pkcs11_module.is_none() => {
+            WindowsSigningTool::Signtool
+        }
+        (None, None) => WindowsSigningTool::Osslsigncode,
+    };
+    let key = SigningKey::from_settings(settings, sign_settings, tool)?;
+    common::print_signing(&path.file_name().unwrap().to_string_lossy())?;
+    match tool {
+        WindowsSigningTool::Signtool => {
+            let mut command = Command::new("signtool");
+            command.args(sign_args(tool, sign_settings, &key)?);
+            command.arg(path);
+            common::run_signing_tool(settings, &mut command, "signtool")?;
+        }
+        WindowsSigningTool::Osslsigncode => {
+            let mut signed_path = path.as_os_str().to_owned();
+            signed_path.push(".signed");
+            let signed_path = PathBuf::from(signed_path);
+            let mut command = Command::new("osslsigncode");
+            command.args(sign_args(tool, sign_settings, &key)?);
+            command.arg("-in").arg(path).arg("-out").arg(&signed_path);
+            common::run_signing_tool(settings, &mut command, "osslsigncode")?;
+            fs::rename(&signed_path, path)
+                .with_context(|| format!("Failed to replace {path:?} with its signed copy"))?;
+        }
+        WindowsSigningTool::Jsign => {
+            let mut command = Command::new("jsign");
+            command.args(sign_args(tool, sign_settings, &key)?);
+            command.arg(path);
+            common::run_signing_tool(settings, &mut command, "jsign")?;
+        }
+    }
+    common::record_signed(path);
+    Ok(())
+}
+
+/// Returns the metadata file that tells the Trusted Signing dlib which
+/// certificate profile to sign with.
+fn trusted_signing_metadata(account: &TrustedSigningSettings) -> crate::Result<String> {
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "Endpoint": account.endpoint,
+        "CodeSigningAccountName": account.account,
+        "CertificateProfileName": account.certificate_profile,
+    }))?)
+}
+
+/// Returns an access token for Azure Trusted Signing, from the environment or
+/// else from the Azure CLI (signed in with `az login`).
+fn azure_access_token() -> crate::Result<String> {
+    if let Ok(token) = std::env::var(AZURE_ACCESS_TOKEN_VAR) {
+        return Ok(token);
+    }
+    let output = common::run_tool(
+        Command::new("az").args([
+            "account",
+            "get-access-token",
+            "--resource",
+            "https://codesigning.azure.net",
+            "--query",
+            "accessToken",
+            "--output",
+            "tsv",
+        ]),
+        "az",
+    )
+    .with_context(|| {
+        format!("Failed to get an access token for Trusted Signing; set {AZURE_ACCESS_TOKEN_VAR}")
+    })?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Returns the arguments that sign a file with `tool` and `key`, except for
+/// the file itself.
+fn sign_args(
+    tool: WindowsSigningTool,
+    sign_settings: &WindowsSignSettings,
+    key: &SigningKey,
+) -> crate::Result<Vec<String>> {
+    let digest_algorithm = sign_settings
+        .digest_algorithm
+        .unwrap_or(DigestAlgorithm::Sha256);
+    let digest = digest_algorithm.name();
+    let timestamp_url = match key {
+        SigningKey::TrustedSigning { .. } => Some(
+            sign_settings
+                .timestamp_url
+                .as_deref()
+                .unwrap_or(TRUSTED_SIGNING_TIMESTAMP_URL),
+        ),
+        _ => sign_settings.timestamp_url.as_deref(),
+    };
+    let path_arg = |path: &Path| path.to_string_lossy().into_owned();
+    let mut args = Vec::new();
+    match tool {
+        WindowsSigningTool::Signtool => {
+            args.extend(["sign".to_string(), "/fd".to_string(), digest.to_string()]);
+            match key {
+                SigningKey::Automatic => args.push("/a".to_string()),
+                SigningKey::Thumbprint(thumbprint) => {
+                    args.extend(["/sha1".to_string(), thumbprint.to_string()]);
+                }
+                SigningKey::Certificate { path, password } => {
+                    args.extend(["/f".to_string(), path_arg(path)]);
+                    if let Some(password) = password {
+                        args.extend(["/p".to_string(), password.clone()]);
+                    }
+                }
+                SigningKey::Pkcs11 { .. } => anyhow::bail!(
+                    "signtool cannot sign with a PKCS #11 module; use osslsigncode, or \
+                     `certificate_thumbprint` with the token's minidriver"
+                ),
+                SigningKey::TrustedSigning { dlib, .. } => {
+                    let Some((dlib, metadata)) = dlib else {
+                        anyhow::bail!(
+                            "Signing with Trusted Signing through signtool requires `dlib`"
+                        );
+                    };
+                    args.extend([
+                        "/dlib".to_string(),
+                        path_arg(dlib),
+                        "/dmdf".to_string(),
+                        path_arg(metadata),
+                    ]);
+                }
+            }
+            if let Some(url) = timestamp_url {
+                args.extend([
+                    "/tr".to_string(),
+                    url.to_string(),
+                    "/td".to_string(),
+                    digest.to_string(),
+                ]);
+            }
+        }
+        WindowsSigningTool::Osslsigncode => {
+            args.extend(["sign".to_string(), "-h".to_string(), digest.to_string()]);
+            let password = match key {
+                SigningKey::Certificate { path, password } => {
+                    args.extend(["-pkcs12".to_string(), path_arg(path)]);
+                    password
+                }
+                SigningKey::Pkcs11 {
+                    module,
+                    key,
+                    certificate,
+                    pin,
+                } => {
+                    args.extend([
+                        "-pkcs11module".to_string(),
+                        path_arg(module),
+                        "-key".to_string(),
+                        key.to_string(),
+                        "-certs".to_string(),
+                        path_arg(certificate),
+                    ]);
+                    pin
+                }
+                SigningKey::TrustedSigning { .. } => anyhow::bail!(
+                    "osslsigncode cannot sign with Trusted Signing; use signtool or jsign"
+                ),
+                SigningKey::Automatic | SigningKey::Thumbprint(_) => anyhow::bail!(
+                    "Signing with osslsigncode requires a `certificate` file in \
+                     `[package.metadata.bundle.windows.sign]`"
+                ),
+            };
+            if let Some(password) = password {
+                args.extend(["-pass".to_string(), password.clone()]);
+            }
+            if let Some(url) = timestamp_url {
+                args.extend(["-ts".to_string(), url.to_string()]);
+            }
+        }
+        WindowsSigningTool::Jsign => {
+            match key {
+                SigningKey::Certificate { path, password } => {
+                    args.extend(["--keystore".to_string(), path_arg(path)]);
+                    if let Some(password) = password {
+                        args.extend(["--storepass".to_string(), password.clone()]);
+                    }
+                }
+                SigningKey::TrustedSigning {
+                    account,
+                    access_token,
+                    ..
+                } => {
+                    let endpoint = account.endpoint.trim_start_matches("https://");
+                    args.extend([
+                        "--storetype".to_string(),
+                        "TRUSTEDSIGNING".to_string(),
+                        "--keystore".to_string(),
+                        endpoint.trim_end_matches('/').to_string(),
+                        "--alias".to_string(),
+                        format!("{}/{}", account.account, account.certificate_profile),
+                    ]);
+                    if let Some(token) = access_token {
+                        args.extend(["--storepass".to_string(), token.clone()]);
+                    }
+                }
+                SigningKey::Automatic | SigningKey::Thumbprint(_) | SigningKey::Pkcs11 { .. } => {
+                    anyhow::bail!(
+                        "Signing with jsign requires a `certificate` file or a \
+                         `trusted_signing` account"
+                    )
+                }
+            }
+            let algorithm = match digest_algorithm {
+                DigestAlgorithm::Sha1 => "SHA-1",
+                DigestAlgorithm::Sha256 => "SHA-256",
+                DigestAlgorithm::Sha384 => "SHA-384",
+                DigestAlgorithm::Sha512 => "SHA-512",
+            };
+            args.extend(["--alg".to_string(), algorithm.to_string()]);
+            if let Some(url) = timestamp_url {
+                args.extend([
+                    "--tsaurl".to_string(),
+                    url.to_string(),
+                    "--tsmode".to_string(),
+                    "RFC3161".to_string(),
+                ]);
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign_settings(toml: &str) -> WindowsSignSettings {
+        let settings = Settings::for_test(toml);
+        settings.windows_sign().unwrap().clone()
+    }
+
+    #[test]
+    fn signtool_args() {
+        let sign_settings = sign_settings(
+            "[windows.sign]\n\
+             certificate_thumbprint = \"ABC123\"\n\
+             timestamp_url = \"http://timestamp.example.com\"\n",
+        );
+        assert_eq!(
+            sign_args(
+                WindowsSigningTool::Signtool,
+                &sign_settings,
+                &SigningKey::Thumbprint("ABC123")
+            )
+            .unwrap(),
+            [
+                "sign",
+                "/fd",
+                "sha256",
+                "/sha1",
+                "ABC123",
+                "/tr",
+                "http://timestamp.example.com",
+                "/td",
+                "sha256"
+            ]
+        );
+    }
+
+    #[test]
+    fn osslsigncode_args() {
+        let sign_settings = sign_settings(
+            "[windows.sign]\n\
+             certificate = \"cert.pfx\"\n\
+             digest_algorithm = \"sha384\"\n",
+        );
+        let key = SigningKey::Certificate {
+            path: PathBuf::from("cert.pfx"),
+            password: Some("secret".to_string()),
+        };
+        assert_eq!(
+            sign_args(WindowsSigningTool::Osslsigncode, &sign_settings, &key).unwrap(),
+            [
+                "sign", "-h", "sha384", "-pkcs12", "cert.pfx", "-pass", "secret"
+            ]
+        );
+        assert!(
+            sign_args(
+                WindowsSigningTool::Osslsigncode,
+                &sign_settings,
+                &SigningKey::Automatic
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn pkcs11_args() {
+        let sign_settings = sign_settings(
+            "[windows.sign]\n\
+             certificate = \"cert.pem\"\n\
+             pkcs11_module = \"/usr/lib/libtoken.so\"\n\
+             pkcs11_key = \"pkcs11:token=Foo;object=codesign\"\n",
+        );
+        let key = SigningKey::Pkcs11 {
+            module: PathBuf::from("/usr/lib/libtoken.so"),
+            key: sign_settings.pkcs11_key.as_deref().unwrap(),
+            certificate: PathBuf::from("cert.pem"),
+            pin: Some("1234".to_string()),
+        };
+        assert_eq!(
+            sign_args(WindowsSigningTool::Osslsigncode, &sign_settings, &key).unwrap(),
+            [
+                "sign",
+                "-h",
+                "sha256",
+                "-pkcs11module",
+                "/usr/lib/libtoken.so",
+                "-key",
+                "pkcs11:token=Foo;object=codesign",
+                "-certs",
+                "cert.pem",
+                "-pass",
+                "1234"
+            ]
+        );
+        assert!(sign_args(WindowsSigningTool::Signtool, &sign_settings, &key).is_err());
+    }
+
+    #[test]
+    fn trusted_signing_args() {
+        let sign_settings = sign_settings(
+            "[windows.sign.trusted_signing]\n\
+             endpoint = \"https://eus.codesigning.azure.net/\"\n\
+             account = \"example\"\n\
+             certificate_profile = \"release\"\n",
+        );
+        let account = sign_settings.trusted_signing.as_ref().unwrap();
+        let key = SigningKey::TrustedSigning {
+            account,
+            dlib: None,
+            access_token: Some("token".to_string()),
+        };
+        assert_eq!(
+            sign_args(WindowsSigningTool::Jsign, &sign_settings, &key).unwrap(),
+            [
+                "--storetype",
+                "TRUSTEDSIGNING",
+                "--keystore",
+                "eus.codesigning.azure.net",
+                "--alias",
+                "example/release",
+                "--storepass",
+                "token",
+                "--alg",
+                "SHA-256",
+                "--tsaurl",
+                "http://timestamp.acs.microsoft.com",
+                "--tsmode",
+                "RFC3161"
+            ]
+        );
+        assert!(sign_args(WindowsSigningTool::Signtool, &sign_settings, &key).is_err());
+        assert!(sign_args(WindowsSigningTool::Osslsigncode, &sign_settings, &key).is_err());
+
+        let key = SigningKey::TrustedSigning {
+            account,
+            dlib: Some((PathBuf::from("dlib.dll"), PathBuf::from("metadata.json"))),
+            access_token: None,
+        };
+        assert_eq!(
+            sign_args(WindowsSigningTool::Signtool, &sign_settings, &key).unwrap(),
+            [
+                "sign",
+                "/fd",
+                "sha256",
+                "/dlib",
+                "dlib.dll",
+                "/dmdf",
+                "metadata.json",
+                "/tr",
+                "http://timestamp.acs.microsoft.com",
+                "/td",
+                "sha256"
+            ]
+        );
+        let metadata: serde_json::Value =
+            serde_json::from_str(&trusted_signing_metadata(account).unwrap()).unwrap();
+        assert_eq!(metadata["CodeSigningAccountName"], "example");
+        assert_eq!(metadata["CertificateProfileName"], "release");
+    }
+}
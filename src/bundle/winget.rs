@@ -0,0 +1,251 @@
+// A winget package version is described by three YAML manifests, which are
+// submitted together to the winget-pkgs repository.  When `winget_url` is set,
+// we write them for the MSI and MSIX installers that were bundled:
+//
+// bundle/winget/
+//     Foo.Bar.yaml                # The version manifest, tying the others together
+//     Foo.Bar.installer.yaml      # Where to download each installer, and its hash
+//     Foo.Bar.locale.en-US.yaml   # The package's name, publisher, description, etc.
+//
+// The manifests can be copied into winget-pkgs as-is, once the installers have
+// been uploaded to the URLs they refer to.
+
+use super::{PackageType, common, msi_bundle, msix_bundle, wxsmsi_bundle};
+use crate::Settings;
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+const MANIFEST_VERSION: &str = "1.6.0";
+const DEFAULT_LOCALE: &str = "en-US";
+
+/// An installer to list in the installer manifest.
+struct Installer {
+    installer_type: &'static str,
+    url: String,
+    sha256: String,
+    product_code: Option<String>,
+}
+
+/// Writes the manifests for the installers among `artifacts`, and returns the
+/// paths of the written files.  Nothing is written if there are no installers.
+pub fn generate_manifests(
+    settings: &Settings,
+    url_template: &str,
+    artifacts: &[(PackageType, PathBuf)],
+) -> crate::Result<Vec<PathBuf>> {
+    let mut installers = Vec::new();
+    for (package_type, path) in artifacts {
+        let (installer_type, product_code) = match package_type {
+            PackageType::WindowsMsi => ("msi", Some(msi_bundle::product_code(settings))),
+            PackageType::WxsMsi => ("wix", Some(wxsmsi_bundle::product_code(settings))),
+            PackageType::Msix => ("msix", None),
+            _ => continue,
+        };
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        installers.push(Installer {
+            installer_type,
+            url: download_url(settings, url_template, &file_name)?,
+            sha256: common::sha256_hex(path)?.to_uppercase(),
+            product_code: product_code.map(|code| format!("{{{}}}", code).to_uppercase()),
+        });
+    }
+    if installers.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let identifier = package_identifier(settings)?;
+    let base_dir = settings.project_out_directory().join("bundle/winget");
+    fs::create_dir_all(&base_dir)?;
+    let manifests = [
+        (
+            format!("{identifier}.yaml"),
+            generate_version_manifest(settings, &identifier),
+        ),
+        (
+            format!("{identifier}.installer.yaml"),
+            generate_installer_manifest(settings, &identifier, &installers),
+        ),
+        (
+            format!("{identifier}.locale.{DEFAULT_LOCALE}.yaml"),
+            generate_locale_manifest(settings, &identifier),
+        ),
+    ];
+    let mut paths = Vec::new();
+    for (file_name, contents) in manifests {
+        common::print_bundling(&file_name)?;
+        let path = base_dir.join(file_name);
+        fs::write(&path, contents).with_context(|| format!("Failed to write {path:?}"))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Expands the `winget_url` template for the installer named `file_name`.
+fn download_url(settings: &Settings, url_template: &str, file_name: &str) -> crate::Result<String> {
+    let version = settings.version_string().to_string();
+    let tokens = [
+        ("name", settings.binary_name()),
+        ("version", version.as_str()),
+        ("file", file_name),
+    ];
+    common::expand_template(url_template, &tokens).context("Failed to expand `winget_url`")
+}
+
+/// Returns the winget package identifier (e.g. `Publisher.App`): the
+/// `winget_identifier` setting, or else the bundle identifier.
+fn package_identifier(settings: &Settings) -> crate::Result<String> {
+    let identifier = match settings.winget_identifier() {
+        Some(identifier) => identifier.to_string(),
+        None => settings.bundle_identifier().into_owned(),
+    };
+    if identifier.split('.').count() < 2 {
+        anyhow::bail!(
+            "winget manifests require a `winget_identifier` (or bundle `identifier`) of the form \
+             `Publisher.App`"
+        );
+    }
+    Ok(identifier)
+}
+
+/// Quotes a string as a YAML double-quoted scalar.
+fn yaml_string(value: &str) -> String {
+    // JSON strings are valid YAML double-quoted scalars.
+    serde_json::to_string(value).unwrap()
+}
+
+/// Writes the header fields shared by all three manifests.
+fn write_header(yaml: &mut String, settings: &Settings, identifier: &str, manifest_type: &str) {
+    writeln!(
+        yaml,
+        "# yaml-language-server: $schema=https://aka.ms/winget-manifest.{manifest_type}.{MANIFEST_VERSION}.schema.json"
+    )
+    .unwrap();
+    writeln!(yaml).unwrap();
+    writeln!(yaml, "PackageIdentifier: {}", yaml_string(identifier)).unwrap();
+    writeln!(
+        yaml,
+        "PackageVersion: {}",
+        yaml_string(&settings.version_string().to_string())
+    )
+    .unwrap();
+}
+
+fn write_footer(yaml: &mut String, manifest_type: &str) {
+    writeln!(yaml, "ManifestType: {manifest_type}").unwrap();
+    writeln!(yaml, "ManifestVersion: {MANIFEST_VERSION}").unwrap();
+}
+
+fn generate_version_manifest(settings: &Settings, identifier: &str) -> String {
+    let mut yaml = String::new();
+    write_header(&mut yaml, settings, identifier, "version");
+    writeln!(yaml, "DefaultLocale: {DEFAULT_LOCALE}").unwrap();
+    write_footer(&mut yaml, "version");
+    yaml
+}
+
+fn generate_installer_manifest(
+    settings: &Settings,
+    identifier: &str,
+    installers: &[Installer],
+) -> String {
+    let mut yaml = String::new();
+    write_header(&mut yaml, settings, identifier, "installer");
+    writeln!(yaml, "Installers:").unwrap();
+    for installer in installers {
+        writeln!(
+            yaml,
+            "- Architecture: {}",
+            msix_bundle::msix_arch(settings.binary_arch())
+        )
+        .unwrap();
+        writeln!(yaml, "  InstallerType: {}", installer.installer_type).unwrap();
+        writeln!(yaml, "  InstallerUrl: {}", yaml_string(&installer.url)).unwrap();
+        writeln!(yaml, "  InstallerSha256: {}", installer.sha256).unwrap();
+        if let Some(product_code) = &installer.product_code {
+            writeln!(yaml, "  ProductCode: {}", yaml_string(product_code)).unwrap();
+        }
+    }
+    write_footer(&mut yaml, "installer");
+    yaml
+}
+
+fn generate_locale_manifest(settings: &Settings, identifier: &str) -> String {
+    let mut yaml = String::new();
+    write_header(&mut yaml, settings, identifier, "defaultLocale");
+    writeln!(yaml, "PackageLocale: {DEFAULT_LOCALE}").unwrap();
+    let publisher = settings
+        .authors_comma_separated()
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| settings.bundle_name().to_string());
+    writeln!(yaml, "Publisher: {}", yaml_string(&publisher)).unwrap();
+    writeln!(yaml, "PackageName: {}", yaml_string(settings.bundle_name())).unwrap();
+    if !settings.homepage_url().is_empty() {
+        writeln!(yaml, "PackageUrl: {}", yaml_string(settings.homepage_url())).unwrap();
+    }
+    let license = settings.license().unwrap_or("Proprietary");
+    writeln!(yaml, "License: {}", yaml_string(license)).unwrap();
+    if let Some(copyright) = settings.copyright_string() {
+        writeln!(yaml, "Copyright: {}", yaml_string(copyright)).unwrap();
+    }
+    let mut short_description = settings.short_description().trim();
+    if short_description.is_empty() {
+        short_description = settings.bundle_name();
+    }
+    writeln!(yaml, "ShortDescription: {}", yaml_string(short_description)).unwrap();
+    if let Some(description) = settings
+        .long_description()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+    {
+        writeln!(yaml, "Description: {}", yaml_string(description)).unwrap();
+    }
+    write_footer(&mut yaml, "defaultLocale");
+    yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_identifiers() {
+        let settings = Settings::for_test("winget_identifier = \"Example.App\"\n");
+        assert_eq!(package_identifier(&settings).unwrap(), "Example.App");
+        let settings = Settings::for_test("identifier = \"example\"\n");
+        assert!(package_identifier(&settings).is_err());
+    }
+
+    #[test]
+    fn installer_manifest() {
+        let settings = Settings::for_test("");
+        let installers = [Installer {
+            installer_type: "msi",
+            url: "https://example.com/example.msi".to_string(),
+            sha256: "ABC123".to_string(),
+            product_code: Some("{12345678-ABCD-ABCD-ABCD-123456789ABC}".to_string()),
+        }];
+        let yaml = generate_installer_manifest(&settings, "Example.App", &installers);
+        assert!(yaml.contains("PackageIdentifier: \"Example.App\"\nPackageVersion: \"1.0.0\"\n"));
+        assert!(yaml.contains(
+            "  InstallerType: msi\n  \
+             InstallerUrl: \"https://example.com/example.msi\"\n  \
+             InstallerSha256: ABC123\n  \
+             ProductCode: \"{12345678-ABCD-ABCD-ABCD-123456789ABC}\"\n"
+        ));
+        assert!(yaml.ends_with("ManifestType: installer\nManifestVersion: 1.6.0\n"));
+    }
+
+    #[test]
+    fn locale_manifest() {
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             short_description = \"Does things\"\n",
+        );
+        let yaml = generate_locale_manifest(&settings, "Example.App");
+        assert!(yaml.contains("PackageLocale: en-US\n"));
+        assert!(yaml.contains("PackageName: \"My App\"\n"));
+        assert!(yaml.contains("ShortDescription: \"Does things\"\n"));
+    }
+}
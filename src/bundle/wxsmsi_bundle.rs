@@ -1,4 +1,10 @@
-use super::settings::Settings;
+use super::common;
+use super::settings::{
+    InstallScope, Prerequisite, PrerequisiteKind, RegistryData, RegistryRoot, ServiceStartType,
+    Settings, WindowsService,
+};
+use super::shared_libs;
+use super::windows_registry;
 use quick_xml::se::Serializer;
 use serde::Serialize;
 use std::path::{Path, PathBuf};
@@ -10,64 +16,423 @@ const UUID_NAMESPACE: uuid::Uuid = uuid::Uuid::from_bytes([
 ]);
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    crate::bundle::common::print_warning("MSI bundle support by wix is still experimental.")?;
+    common::print_warning("MSI bundle support by wix is still experimental.")?;
+    // The installers for every language share the upgrade code, so it is
+    // worked out (and any note about it printed) once.
+    let upgrade_code = upgrade_code(settings)?;
+    let mut msi_paths = Vec::new();
+    for language in settings.msi_languages() {
+        msi_paths.extend(bundle_language(
+            &settings.for_msi_language(&language),
+            upgrade_code,
+        )?);
+    }
+    Ok(msi_paths)
+}
 
-    let base_dir = settings
+/// Returns what the names of the `msi` and `wxsmsi` installers end with: the
+/// `msi_language`, e.g. `_fr-FR`, when they are built for several languages.
+pub fn language_suffix(settings: &Settings) -> String {
+    if settings.msi_languages().len() > 1 {
+        format!("_{}", settings.msi_language())
+    } else {
+        String::new()
+    }
+}
+
+/// Builds the installer for the `msi_language` of the settings, whose WiX
+/// project is in a directory of its own, and the `setup.exe` installing its
+/// prerequisites along with it if it has any.
+fn bundle_language(settings: &Settings, upgrade_code: uuid::Uuid) -> crate::Result<Vec<PathBuf>> {
+    let mut base_dir = settings
         .project_out_directory()
         .join("bundle")
         .join("wxsmsi");
+    if settings.msi_languages().len() > 1 {
+        base_dir.push(settings.msi_language());
+    }
     std::fs::create_dir_all(&base_dir)?;
 
+    // Generate .wxs file, which only holds the components when the main
+    // source is the `wix_template`
+    let wxs_path = base_dir.join("installer.wxs");
+    let variables = generate_wxs_file(&wxs_path, settings, upgrade_code)?;
+    let mut sources = vec![wxs_path];
+    for path in settings
+        .wix_template()
+        .into_iter()
+        .chain(settings.wix_fragments())
+    {
+        if !path.is_file() {
+            anyhow::bail!("WiX source {path:?} does not exist");
+        }
+        sources.push(path);
+    }
+
     // Generate .wixproj file
     let wixproj_path = base_dir.join("installer.wixproj");
-    std::fs::write(&wixproj_path, generate_wixproj_file(settings))?;
-
-    // Generate .wxs file
-    let wxs_path = base_dir.join("installer.wxs");
-    generate_wxs_file(&wxs_path, settings)?;
+    std::fs::write(
+        &wixproj_path,
+        generate_wixproj_file(settings, &sources, &variables),
+    )?;
 
     // Run dotnet build to generate MSI
     // For example: `dotnet build path/to/installer.wixproj -c Release`
-    let configuration = match settings.build_profile() {
+    dotnet_build(
+        &base_dir,
+        "installer.wixproj",
+        configuration(settings),
+        "MSI",
+    )?;
+
+    let msi_path = output_path(
+        settings,
+        &base_dir,
+        &format!("{}.msi", output_name(settings)),
+    );
+    if settings.windows_prerequisites().is_empty() {
+        return Ok(vec![msi_path]);
+    }
+    let setup_path = bundle_setup(settings, upgrade_code, &base_dir.join("setup"), &msi_path)?;
+    Ok(vec![msi_path, setup_path])
+}
+
+/// Returns the path that `dotnet build` writes `file_name` to, for a WiX
+/// project in `dir`.
+fn output_path(settings: &Settings, dir: &Path, file_name: &str) -> PathBuf {
+    dir.join("bin")
+        .join(configuration(settings))
+        .join(file_name)
+}
+
+/// Returns the configuration that the WiX projects are built in.
+fn configuration(settings: &Settings) -> &'static str {
+    match settings.build_profile() {
         "release" => "Release",
         _ => "Debug",
-    };
+    }
+}
+
+/// Runs `dotnet build` on the `project` in `dir`, which builds the installer
+/// described by `what`.
+fn dotnet_build(dir: &Path, project: &str, configuration: &str, what: &str) -> crate::Result<()> {
     // Run dotnet build from the directory containing the wixproj file
     let output = std::process::Command::new("dotnet")
-        .args(["build", "installer.wixproj", "-c", configuration])
-        .current_dir(&base_dir)
+        .args(["build", project, "-c", configuration])
+        .current_dir(dir)
         .output()?;
 
     if !output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(anyhow::anyhow!(
-            "Failed to build MSI.\nWorking directory: {:?}\nStdout: {}\nStderr: {}",
-            base_dir,
+            "Failed to build {}.\nWorking directory: {:?}\nStdout: {}\nStderr: {}",
+            what,
+            dir,
             stdout,
             stderr
         ));
     }
+    Ok(())
+}
+
+/// Builds a Burn bundle, `<name>-setup.exe`, in `dir`, which installs the
+/// `windows.prerequisites` that are missing and then the MSI at `msi_path`,
+/// whose upgrade code is `upgrade_code`.
+fn bundle_setup(
+    settings: &Settings,
+    upgrade_code: uuid::Uuid,
+    dir: &Path,
+    msi_path: &Path,
+) -> crate::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    for prerequisite in settings.windows_prerequisites() {
+        let source = prerequisite_source(settings, prerequisite);
+        if !source.is_file() {
+            anyhow::bail!("Prerequisite installer {source:?} does not exist");
+        }
+    }
+    let license = settings
+        .windows_license_dialog()
+        .then(|| license_rtf(settings))
+        .transpose()?;
+    let wxs_path = dir.join("setup.wxs");
+    std::fs::write(
+        &wxs_path,
+        generate_setup_wxs(settings, upgrade_code, msi_path, license.as_deref())?,
+    )?;
+    std::fs::write(
+        dir.join("setup.wixproj"),
+        generate_setup_wixproj_file(settings, &wxs_path),
+    )?;
+
+    dotnet_build(dir, "setup.wixproj", configuration(settings), "setup.exe")?;
+    Ok(output_path(
+        settings,
+        dir,
+        &format!("{}.exe", setup_output_name(settings)),
+    ))
+}
 
-    let output_name = sanitize_identifier(settings.bundle_name(), '-', true);
-    let msi_path = base_dir
-        .join("bin")
-        .join(configuration)
-        .join(format!("{output_name}.msi"));
-    Ok(vec![msi_path])
+/// Returns the name of the `setup.exe` that the Burn project builds, without
+/// its extension.
+fn setup_output_name(settings: &Settings) -> String {
+    format!("{}-setup", output_name(settings))
 }
 
-fn generate_wixproj_file(settings: &Settings) -> String {
-    let output_name = sanitize_identifier(settings.bundle_name(), '-', true);
+/// Returns the path of the installer of a `prerequisite`, resolved against the
+/// directory of `Cargo.toml`.
+fn prerequisite_source(settings: &Settings, prerequisite: &Prerequisite) -> PathBuf {
+    let dir = settings.manifest_path().parent().unwrap_or(Path::new("."));
+    dir.join(&prerequisite.source)
+}
+
+/// Returns the upgrade code of the `setup.exe`, which has to differ from the
+/// `upgrade_code` of the MSI it installs.
+fn setup_upgrade_code(upgrade_code: uuid::Uuid) -> uuid::Uuid {
+    uuid::Uuid::new_v5(&upgrade_code, b"setup.exe")
+}
+
+/// The name, arguments and detection of a known prerequisite for the target
+/// `arch`.
+fn known_prerequisite(
+    kind: PrerequisiteKind,
+    arch: &str,
+) -> (&'static str, &'static str, Vec<RegistrySearch>, String) {
+    match kind {
+        PrerequisiteKind::Webview2 => {
+            // The runtime is installed either per-machine, whose key is in the
+            // 32-bit view of the registry, or per-user.
+            let key =
+                "SOFTWARE\\Microsoft\\EdgeUpdate\\Clients\\{F3017226-FE2A-4295-8BDF-00C3A9A7E4C5}";
+            let searches = [
+                ("WebView2MachineVersion", "HKLM", Some("always32")),
+                ("WebView2UserVersion", "HKCU", None),
+            ]
+            .into_iter()
+            .map(|(variable, root, bitness)| RegistrySearch {
+                id: format!("{variable}Search"),
+                root: root.to_string(),
+                key: key.to_string(),
+                value: "pv".to_string(),
+                variable: variable.to_string(),
+                result: "value".to_string(),
+                bitness: bitness.map(str::to_string),
+            })
+            .collect();
+            (
+                "Microsoft Edge WebView2 Runtime",
+                "/silent /install",
+                searches,
+                "(WebView2MachineVersion AND WebView2MachineVersion <> \"0.0.0.0\") OR \
+                 (WebView2UserVersion AND WebView2UserVersion <> \"0.0.0.0\")"
+                    .to_string(),
+            )
+        }
+        PrerequisiteKind::Vcredist => {
+            let (runtime, bitness) = match arch {
+                "x86_64" => ("x64", "always64"),
+                "aarch64" => ("arm64", "always64"),
+                _ => ("x86", "always32"),
+            };
+            let search = RegistrySearch {
+                id: "VCRedistInstalledSearch".to_string(),
+                root: "HKLM".to_string(),
+                key: format!("SOFTWARE\\Microsoft\\VisualStudio\\14.0\\VC\\Runtimes\\{runtime}"),
+                value: "Installed".to_string(),
+                variable: "VCRedistInstalled".to_string(),
+                result: "value".to_string(),
+                bitness: Some(bitness.to_string()),
+            };
+            (
+                "Microsoft Visual C++ Redistributable",
+                "/install /quiet /norestart",
+                vec![search],
+                "VCRedistInstalled = 1".to_string(),
+            )
+        }
+    }
+}
 
+/// Generates the Burn bundle that installs the prerequisites and then the MSI
+/// at `msi_path`, whose upgrade code is `upgrade_code`, showing the `license`
+/// before installing if there is one.
+fn generate_setup_wxs(
+    settings: &Settings,
+    upgrade_code: uuid::Uuid,
+    msi_path: &Path,
+    license: Option<&Path>,
+) -> crate::Result<String> {
+    let mut registry_searches = Vec::new();
+    let mut exe_packages = Vec::new();
+    for (index, prerequisite) in settings.windows_prerequisites().iter().enumerate() {
+        let source = prerequisite_source(settings, prerequisite);
+        let (name, arguments, detect_condition) = match prerequisite.kind {
+            Some(kind) => {
+                let (name, arguments, searches, detect_condition) =
+                    known_prerequisite(kind, settings.binary_arch());
+                registry_searches.extend(searches);
+                (
+                    name.to_string(),
+                    Some(arguments.to_string()),
+                    Some(detect_condition),
+                )
+            }
+            None => (
+                source
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                None,
+                None,
+            ),
+        };
+        exe_packages.push(ExePackage {
+            id: format!("Prerequisite{index}"),
+            display_name: prerequisite.name.clone().unwrap_or(name),
+            source_file: source.to_string_lossy().into_owned(),
+            install_arguments: prerequisite.arguments.clone().or(arguments),
+            detect_condition: prerequisite.detect_condition.clone().or(detect_condition),
+            // Other apps may need the runtimes, so they stay installed.
+            permanent: "yes".to_string(),
+            vital: "yes".to_string(),
+        });
+    }
+
+    let document = SetupDocument {
+        xmlns: "http://wixtoolset.org/schemas/v4/wxs".to_string(),
+        xmlns_bal: "http://wixtoolset.org/schemas/v4/wxs/bal".to_string(),
+        xmlns_util: "http://wixtoolset.org/schemas/v4/wxs/util".to_string(),
+        bundle: Bundle {
+            name: settings
+                .localized_bundle_name(settings.msi_language())
+                .to_string(),
+            version: sanitize_version_for_wix(&settings.version_string().to_string()),
+            manufacturer: publisher(settings),
+            upgrade_code: setup_upgrade_code(upgrade_code).to_string().to_uppercase(),
+            bootstrapper_application: BootstrapperApplication {
+                // Without a license file, the standard bootstrapper has no
+                // license to accept.
+                standard: StandardBootstrapperApplication {
+                    theme: match license {
+                        Some(_) => "rtfLicense",
+                        None => "hyperlinkLicense",
+                    }
+                    .to_string(),
+                    license_file: license.map(|path| path.to_string_lossy().into_owned()),
+                },
+            },
+            registry_searches,
+            chain: Chain {
+                exe_packages,
+                msi_package: MsiPackage {
+                    id: "MainPackage".to_string(),
+                    source_file: msi_path.to_string_lossy().into_owned(),
+                },
+            },
+        },
+    };
+
+    let mut buffer = String::new();
+    let mut serializer = Serializer::new(&mut buffer);
+    serializer.indent(' ', 2);
+    document.serialize(serializer).unwrap();
+    Ok(buffer)
+}
+
+/// Generates the project building the Burn bundle from the `source`.
+fn generate_setup_wixproj_file(settings: &Settings, source: &Path) -> String {
     let wix_project = WixProject {
         sdk: "WixToolset.Sdk/6.0.2".to_string(),
-        property_group: PropertyGroup { output_name },
+        property_group: PropertyGroup {
+            output_name: setup_output_name(settings),
+            output_type: Some("Bundle".to_string()),
+            // The dialogs of the bootstrapper are translated into the culture.
+            cultures: settings.msi_language().to_string(),
+            // Bundles run on every platform, whatever that of the MSI.
+            installer_platform: None,
+            enable_default_compile_items: false,
+            define_constants: String::new(),
+            suppress_ices: None,
+        },
+        item_group: ItemGroup {
+            package_references: ["WixToolset.Bal.wixext", "WixToolset.Util.wixext"]
+                .into_iter()
+                .map(|include| PackageReference {
+                    include: include.to_string(),
+                    version: "6.0.2".to_string(),
+                })
+                .collect(),
+            compile: vec![Compile {
+                include: msbuild_escape(&source.to_string_lossy()),
+            }],
+        },
+    };
+
+    let mut buffer = String::new();
+    let mut serializer = Serializer::new(&mut buffer);
+    serializer.indent(' ', 2);
+    wix_project.serialize(serializer).unwrap();
+    buffer
+}
+
+/// Returns the platform of installers for the target `arch`, e.g. `arm64` for
+/// `aarch64`, which sets the platform of the package and of its components.
+fn installer_platform(arch: &str) -> &'static str {
+    match arch {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        _ => "x86",
+    }
+}
+
+/// Returns the name of the installer that the WiX project builds, without its
+/// extension.
+fn output_name(settings: &Settings) -> String {
+    sanitize_identifier(settings.bundle_name(), '-', true) + &language_suffix(settings)
+}
+
+/// Generates the project compiling the `sources`, in which the preprocessor
+/// `variables` are defined, e.g. `$(var.ProductCode)`.
+fn generate_wixproj_file(
+    settings: &Settings,
+    sources: &[PathBuf],
+    variables: &[(&str, String)],
+) -> String {
+    let output_name = output_name(settings);
+    let define_constants: Vec<String> = variables
+        .iter()
+        .map(|(name, value)| format!("{name}={}", msbuild_escape(value)))
+        .collect();
+
+    let wix_project = WixProject {
+        sdk: "WixToolset.Sdk/6.0.2".to_string(),
+        property_group: PropertyGroup {
+            output_name,
+            output_type: None,
+            // The dialogs of the WiX UI are translated into the culture.
+            cultures: settings.msi_language().to_string(),
+            installer_platform: Some(installer_platform(settings.binary_arch()).to_string()),
+            // Only the `sources` are compiled, rather than every source in
+            // the directory, such as a template copied there earlier.
+            enable_default_compile_items: false,
+            define_constants: define_constants.join(";"),
+            // The files of per-user installs are in the user profile, where
+            // the validation expects components keyed by registry values.
+            suppress_ices: (settings.windows_install_scope() == InstallScope::PerUser)
+                .then(|| "ICE38;ICE64;ICE91".to_string()),
+        },
         item_group: ItemGroup {
-            package_reference: PackageReference {
+            package_references: vec![PackageReference {
                 include: "WixToolset.UI.wixext".to_string(),
                 version: "6.0.2".to_string(),
-            },
+            }],
+            compile: sources
+                .iter()
+                .map(|source| Compile {
+                    include: msbuild_escape(&source.to_string_lossy()),
+                })
+                .collect(),
         },
     };
 
@@ -80,31 +445,166 @@ fn generate_wixproj_file(settings: &Settings) -> String {
     buffer
 }
 
-fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()> {
-    let product_name = settings.bundle_name();
-    let version = sanitize_version_for_wix(&settings.version_string().to_string());
-    let manufacturer = settings
+/// Returns the publisher that Add/Remove Programs shows for the `msi` and
+/// `wxsmsi` installers: the `msi_publisher`, or else the authors, or else the
+/// bundle name.
+pub fn publisher(settings: &Settings) -> String {
+    match settings.msi_publisher() {
+        Some(publisher) => publisher.to_string(),
+        None => manufacturer(settings),
+    }
+}
+
+/// Returns the Add/Remove Programs properties of the `msi` and `wxsmsi`
+/// installers, whose product icon is the `icon` of the Icon table.
+pub fn arp_properties(settings: &Settings, icon: &str) -> Vec<(&'static str, String)> {
+    let mut properties = vec![("ARPPRODUCTICON", icon.to_string())];
+    let urls = [
+        ("ARPURLINFOABOUT", Some(settings.homepage_url())),
+        ("ARPHELPLINK", settings.msi_help_url()),
+        ("ARPURLUPDATEINFO", settings.msi_update_url()),
+    ];
+    for (property, url) in urls {
+        if let Some(url) = url.filter(|url| !url.is_empty()) {
+            properties.push((property, url.to_string()));
+        }
+    }
+    if settings.msi_no_modify() {
+        properties.push(("ARPNOMODIFY", "1".to_string()));
+    }
+    if settings.msi_no_repair() {
+        properties.push(("ARPNOREPAIR", "1".to_string()));
+    }
+    properties
+}
+
+/// The artwork of the Windows installers.
+pub struct InstallerImages {
+    /// The bitmap shown across the top of the installer dialogs.
+    pub banner: Option<PathBuf>,
+    /// The bitmap shown in the background of the welcome and completion
+    /// dialogs.
+    pub dialog: Option<PathBuf>,
+}
+
+/// Returns the artwork of the `msi` and `wxsmsi` installers, whose images have
+/// to be BMP files.
+pub fn installer_images(settings: &Settings) -> crate::Result<InstallerImages> {
+    let images = InstallerImages {
+        banner: settings.windows_banner_image(),
+        dialog: settings.windows_dialog_image(),
+    };
+    for image in images.banner.iter().chain(&images.dialog) {
+        common::check_bmp(image)?;
+    }
+    Ok(images)
+}
+
+/// Returns the manufacturer that the upgrade and product codes are derived
+/// from, so that setting the `msi_publisher` does not change them.
+fn manufacturer(settings: &Settings) -> String {
+    settings
         .authors_comma_separated()
         .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| product_name.to_string());
-    let name = product_name.to_string() + manufacturer.as_str();
-    let upgrade_code = uuid::Uuid::new_v5(&UUID_NAMESPACE, name.as_bytes())
-        .to_string()
-        .to_uppercase();
+        .unwrap_or_else(|| settings.bundle_name().to_string())
+}
+
+/// Returns the upgrade code of the `msi` and `wxsmsi` installers, which is the
+/// same for every version of the product, so that installing a newer version
+/// replaces the older one.  Unless it is set with `msi_upgrade_code`, it is
+/// derived from the bundle name and the authors, which keeps upgrades working
+/// from one build to the next; a note suggests setting it, as changing either
+/// would break them.
+pub fn upgrade_code(settings: &Settings) -> crate::Result<uuid::Uuid> {
+    if let Some(code) = settings.msi_upgrade_code()? {
+        return Ok(code);
+    }
+    let name = settings.bundle_name().to_string() + manufacturer(settings).as_str();
+    let code = uuid::Uuid::new_v5(&UUID_NAMESPACE, name.as_bytes());
+    common::print_note(&format!(
+        "`msi_upgrade_code` is not set, so the upgrade code is derived from the bundle name and \
+         authors; set `msi_upgrade_code = \"{}\"` to keep upgrades working if they change.",
+        code.to_string().to_uppercase()
+    ))?;
+    Ok(code)
+}
+
+/// Returns the product code of the package.  Unlike the upgrade code, it
+/// changes with each version, as `MajorUpgrade` requires.
+pub fn product_code(settings: &Settings) -> uuid::Uuid {
+    let name = format!(
+        "{}{}/{}",
+        settings.bundle_name(),
+        manufacturer(settings),
+        settings.version_string()
+    );
+    uuid::Uuid::new_v5(&UUID_NAMESPACE, name.as_bytes())
+}
+
+/// Returns the Windows services that the `msi` and `wxsmsi` installers
+/// register the binary as, which only per-machine installs can do.
+pub fn windows_services(settings: &Settings) -> crate::Result<&[WindowsService]> {
+    let services = settings.windows_services();
+    if !services.is_empty() && settings.windows_install_scope() != InstallScope::PerMachine {
+        anyhow::bail!(
+            "Windows services can only be installed with `install_scope = \"perMachine\"`"
+        );
+    }
+    Ok(services)
+}
+
+/// Generates the main source of the installer, or only its components when
+/// there is a `wix_template`.  Returns the preprocessor variables that give
+/// the ids and codes of the installer to the `wix_template` and the
+/// `wix_fragments`.
+fn generate_wxs_file(
+    wxs_path: &Path,
+    settings: &Settings,
+    upgrade_code: uuid::Uuid,
+) -> crate::Result<Vec<(&'static str, String)>> {
+    let product_name = settings.bundle_name();
+    // The name shown to users is localized, unlike the one the codes and
+    // registry keys derive from.
+    let language = settings.msi_language();
+    let display_name = settings.localized_bundle_name(language);
+    let version = sanitize_version_for_wix(&settings.version_string().to_string());
+    let manufacturer = manufacturer(settings);
+    let upgrade_code = upgrade_code.to_string().to_uppercase();
+    let product_code = product_code(settings).to_string().to_uppercase();
+    let mut component_group_refs = vec![ComponentGroupRef {
+        id: "ProductComponents".to_string(),
+    }];
+    for id in settings.wix_component_groups() {
+        component_group_refs.push(ComponentGroupRef { id: id.clone() });
+    }
 
     // Generate dynamic executable ID from binary name
     let exe_id = sanitize_identifier(settings.binary_name(), '_', false);
 
-    // Generate license RTF file
-    let license_rtf_path = settings.project_out_directory().join("License.rtf");
-    generate_license_rtf(&license_rtf_path, settings)?;
+    let license_rtf_path = license_rtf(settings)?;
+    // The license and the artwork of the WixUI dialogs
+    let images = installer_images(settings)?;
+    let wix_variables = [
+        ("WixUILicenseRtf", Some(license_rtf_path.clone())),
+        ("WixUIBannerBmp", images.banner),
+        ("WixUIDialogBmp", images.dialog),
+    ]
+    .into_iter()
+    .filter_map(|(id, path)| {
+        Some(WixVariable {
+            id: id.to_string(),
+            value: path?.to_string_lossy().into_owned(),
+        })
+    })
+    .collect();
 
     // Build components from binary and resources
     let mut installfolder_components = Vec::new();
     let mut component_refs = Vec::new();
 
-    // Main executable component
+    // Main executable component, which also registers the services
     if let Some(binary_path) = settings.binary_path().to_str() {
+        let services = windows_services(settings)?;
         let comp = Component {
             id: Some("MainExecutableComponent".to_string()),
             guid: Some("*".to_string()),
@@ -112,7 +612,10 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
                 id: Some(exe_id.clone()),
                 source: binary_path.to_string(),
                 key_path: Some("yes".to_string()),
+                ..File::default()
             }),
+            service_installs: services.iter().map(ServiceInstall::new).collect(),
+            service_controls: services.iter().map(ServiceControl::new).collect(),
             ..Component::default()
         };
         installfolder_components.push(comp);
@@ -129,10 +632,14 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
         .unwrap_or_else(|| Path::new("."));
 
     // Search for DLL files from binary_path and add them as components
-    let dll_components: Vec<Component> = std::fs::read_dir(bin_dir)
+    let mut dll_entries: Vec<_> = std::fs::read_dir(bin_dir)
         .into_iter()
         .flatten()
         .filter_map(|entry| entry.ok())
+        .collect();
+    dll_entries.sort_by_key(|entry| entry.file_name());
+    let dll_components: Vec<Component> = dll_entries
+        .into_iter()
         .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
         .filter_map(|entry| {
             let filename = entry.file_name().to_str()?.to_lowercase();
@@ -160,6 +667,113 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
 
     installfolder_components.extend(dll_components.clone());
 
+    // External binaries (and DLLs from elsewhere than the binary's directory)
+    // are installed next to the main executable
+    for binary in shared_libs::bundled_executables(settings)? {
+        if binary.src.parent() == Some(bin_dir) && binary.name.to_lowercase().ends_with(".dll") {
+            continue;
+        }
+        let comp_id = format!(
+            "{}_Component",
+            sanitize_identifier(&binary.name.to_lowercase(), '_', true)
+        );
+        installfolder_components.push(Component {
+            id: Some(comp_id.clone()),
+            guid: Some("*".to_string()),
+            file: Some(File {
+                source: binary.src.to_string_lossy().into_owned(),
+                name: Some(binary.name),
+                ..File::default()
+            }),
+            ..Component::default()
+        });
+        component_refs.push(ComponentRef { id: comp_id });
+    }
+
+    // File associations (whose icons are installed next to the main executable)
+    // and URL schemes
+    let registry_values: Vec<RegistryValue> = windows_registry::registry_values(
+        settings,
+        "[INSTALLFOLDER]",
+        windows_registry::escape_formatted,
+    )
+    .into_iter()
+    .enumerate()
+    .map(|(index, value)| RegistryValue {
+        root: "HKMU".to_string(),
+        key: format!("{}\\{}", windows_registry::CLASSES_KEY, value.key),
+        name: value.name,
+        value_type: "string".to_string(),
+        value: value.value,
+        key_path: (index == 0).then(|| "yes".to_string()),
+    })
+    .collect();
+    if !registry_values.is_empty() {
+        installfolder_components.push(Component {
+            id: Some("RegistryClassesComponent".to_string()),
+            guid: Some("*".to_string()),
+            registry_values,
+            ..Component::default()
+        });
+        component_refs.push(ComponentRef {
+            id: "RegistryClassesComponent".to_string(),
+        });
+    }
+    // The authored registry values, each the key path of a component of its own
+    let formatted = |value: &str| {
+        windows_registry::expand_install_dir(
+            value,
+            "[INSTALLFOLDER]",
+            windows_registry::escape_formatted,
+        )
+    };
+    for (index, entry) in settings.windows_registry().iter().enumerate() {
+        let root = match entry.root {
+            RegistryRoot::Hkcr => "HKCR",
+            RegistryRoot::Hkcu => "HKCU",
+            RegistryRoot::Hklm => "HKLM",
+            RegistryRoot::Hku => "HKU",
+            RegistryRoot::Hkmu => "HKMU",
+        };
+        let (value_type, value) = match &entry.value {
+            RegistryData::Dword(value) => ("integer", value.to_string()),
+            RegistryData::String(value) => ("string", formatted(value)),
+        };
+        let comp_id = format!("RegistryEntry{index}_Component");
+        installfolder_components.push(Component {
+            id: Some(comp_id.clone()),
+            guid: Some("*".to_string()),
+            registry_values: vec![RegistryValue {
+                root: root.to_string(),
+                key: formatted(&entry.key),
+                name: entry.name.as_deref().map(formatted),
+                value_type: value_type.to_string(),
+                value,
+                key_path: Some("yes".to_string()),
+            }],
+            ..Component::default()
+        });
+        component_refs.push(ComponentRef { id: comp_id });
+    }
+    for icon in windows_registry::icon_files(settings) {
+        let name = icon.file_name().unwrap().to_string_lossy().into_owned();
+        let comp_id = format!(
+            "{}_Component",
+            sanitize_identifier(&name.to_lowercase(), '_', true)
+        );
+        installfolder_components.push(Component {
+            id: Some(comp_id.clone()),
+            guid: Some("*".to_string()),
+            file: Some(File {
+                source: icon.to_string_lossy().into_owned(),
+                name: Some(name),
+                ..File::default()
+            }),
+            ..Component::default()
+        });
+        component_refs.push(ComponentRef { id: comp_id });
+    }
+
     let package_dir = settings
         .manifest_path()
         .parent()
@@ -168,8 +782,9 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
     // Build directory structure from resource files
     let mut root_directories = Vec::new();
 
-    for relative_path in settings.resource_files().flatten() {
-        let full_path = package_dir.join(&relative_path);
+    for resource in settings.resource_files().flatten() {
+        let full_path = package_dir.join(&resource.src);
+        let relative_path = resource.dest;
 
         // Generate component ID based on full relative path with proper capitalization
         let path_str = relative_path.to_str().unwrap_or("");
@@ -202,34 +817,175 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
 
     let main_icon_id = "main_ico_id";
 
-    let icon_path = get_icon_path(settings);
+    let scope = match settings.windows_install_scope() {
+        InstallScope::PerMachine => "perMachine",
+        InstallScope::PerUser => "perUser",
+        InstallScope::Dual => "perUserOrMachine",
+    };
 
-    // ProgramMenuFolder GUID
-    let program_menu_folder_guid = uuid::Uuid::new_v5(
-        &UUID_NAMESPACE,
-        format!("{manufacturer}{product_name}ProgramMenuFolder").as_bytes(),
-    );
+    let icon_path = get_icon_path(settings);
 
-    // DesktopFolderShortcut GUID
-    let desktop_folder_shortcut_guid = uuid::Uuid::new_v5(
-        &UUID_NAMESPACE,
-        format!("{manufacturer}{product_name}DesktopFolderShortcut").as_bytes(),
-    );
+    let mut variables = vec![
+        ("ProductName", display_name.to_string()),
+        ("Manufacturer", publisher(settings)),
+        ("ProductVersion", version.clone()),
+        ("ProductCode", product_code.clone()),
+        ("UpgradeCode", upgrade_code.clone()),
+        ("FeatureId", "ProductFeature".to_string()),
+        ("ComponentGroupId", "ProductComponents".to_string()),
+        ("MainExecutableId", exe_id.clone()),
+        ("InstallFolderId", "INSTALLFOLDER".to_string()),
+        ("InstallScope", scope.to_string()),
+        ("IconId", main_icon_id.to_string()),
+        (
+            "LicenseRtf",
+            license_rtf_path.to_string_lossy().into_owned(),
+        ),
+    ];
+
+    // The shortcuts, each in a component of its own, whose key path is a
+    // registry value
+    let mut shortcut_component_refs = Vec::new();
+    let install_folder = Directory {
+        id: "INSTALLFOLDER".to_string(),
+        name: settings.bundle_file_name(),
+        directories: root_directories,
+        components: installfolder_components,
+    };
+    // Per-user installs go to `%LOCALAPPDATA%\Programs`, which is where
+    // Windows redirects Program Files to for dual-purpose packages, and the
+    // others to the Program Files folder of the `InstallerPlatform`.
+    let mut standard_directories = vec![match settings.windows_install_scope() {
+        InstallScope::PerUser => StandardDirectory {
+            id: "LocalAppDataFolder".to_string(),
+            directory: Some(Directory {
+                id: "LocalProgramsFolder".to_string(),
+                name: "Programs".to_string(),
+                components: Vec::new(),
+                directories: vec![install_folder],
+            }),
+            component: None,
+        },
+        InstallScope::PerMachine | InstallScope::Dual => StandardDirectory {
+            id: "ProgramFiles6432Folder".to_string(),
+            directory: Some(install_folder),
+            component: None,
+        },
+    }];
+    if settings.msi_start_menu_shortcut() {
+        variables.push(("StartMenuComponentId", "RegistryComponent".to_string()));
+        shortcut_component_refs.push(ComponentRef {
+            id: "RegistryComponent".to_string(),
+        });
+        // ProgramMenuFolder GUID
+        let program_menu_folder_guid = uuid::Uuid::new_v5(
+            &UUID_NAMESPACE,
+            format!("{manufacturer}{product_name}ProgramMenuFolder").as_bytes(),
+        );
+        standard_directories.push(StandardDirectory {
+            id: "ProgramMenuFolder".to_string(),
+            directory: Some(Directory {
+                id: "ApplicationProgramsFolder".to_string(),
+                name: settings.bundle_file_name(),
+                components: vec![Component {
+                    id: Some("RegistryComponent".to_string()),
+                    guid: Some(program_menu_folder_guid.to_string()),
+                    registry_values: vec![RegistryValue {
+                        root: "HKCU".to_string(),
+                        key: format!("Software\\{}\\{product_name}", manufacturer.to_lowercase()),
+                        name: Some("installed".to_string()),
+                        value_type: "integer".to_string(),
+                        value: "1".to_string(),
+                        key_path: Some("yes".to_string()),
+                    }],
+                    shortcut: Some(Shortcut {
+                        id: "ApplicationStartMenuShortcut".to_string(),
+                        name: display_name.to_string(),
+                        description: Some(display_name.to_string()),
+                        arguments: settings.msi_shortcut_args().map(str::to_string),
+                        target: format!("[#{exe_id}]"),
+                        icon: main_icon_id.to_string(),
+                        working_directory: "INSTALLFOLDER".to_string(),
+                    }),
+                    remove_folder: Some(RemoveFolder {
+                        id: "RemoveAppProgramsFolder".to_string(),
+                        directory: "ApplicationProgramsFolder".to_string(),
+                        on: "uninstall".to_string(),
+                    }),
+                    remove_file: Some(RemoveFile {
+                        id: "RemoveAppPrograms".to_string(),
+                        directory: "ApplicationProgramsFolder".to_string(),
+                        name: "*.*".to_string(),
+                        on: "uninstall".to_string(),
+                    }),
+                    ..Component::default()
+                }],
+                directories: vec![],
+            }),
+            component: None,
+        });
+    }
+    if settings.msi_desktop_shortcut() {
+        variables.push((
+            "DesktopShortcutComponentId",
+            "DesktopFolderShortcut".to_string(),
+        ));
+        shortcut_component_refs.push(ComponentRef {
+            id: "DesktopFolderShortcut".to_string(),
+        });
+        // DesktopFolderShortcut GUID
+        let desktop_folder_shortcut_guid = uuid::Uuid::new_v5(
+            &UUID_NAMESPACE,
+            format!("{manufacturer}{product_name}DesktopFolderShortcut").as_bytes(),
+        );
+        standard_directories.push(StandardDirectory {
+            id: "DesktopFolder".to_string(),
+            directory: None,
+            component: Some(Component {
+                id: Some("DesktopFolderShortcut".to_string()),
+                guid: Some(desktop_folder_shortcut_guid.to_string()),
+                registry_values: vec![RegistryValue {
+                    root: "HKCU".to_string(),
+                    key: format!("Software\\{}\\{product_name}", manufacturer.to_lowercase()),
+                    name: Some("installed".to_string()),
+                    value_type: "integer".to_string(),
+                    value: "1".to_string(),
+                    key_path: Some("yes".to_string()),
+                }],
+                shortcut: Some(Shortcut {
+                    id: "DesktopShortcut".to_string(),
+                    name: display_name.to_string(),
+                    description: None,
+                    arguments: settings.msi_shortcut_args().map(str::to_string),
+                    target: format!("[#{exe_id}]"),
+                    icon: main_icon_id.to_string(),
+                    working_directory: "INSTALLFOLDER".to_string(),
+                }),
+                ..Component::default()
+            }),
+        });
+    }
 
     // Build the complete WiX document structure
     let wix_doc = WixDocument {
         xmlns: "http://wixtoolset.org/schemas/v4/wxs".to_string(),
         xmlns_ui: "http://wixtoolset.org/schemas/v4/wxs/ui".to_string(),
         xmlns_util: "http://wixtoolset.org/schemas/v4/wxs/util".to_string(),
-        package: Package {
+        package: settings.wix_template().is_none().then(|| Package {
             id: package_id,
-            name: product_name.to_string(),
-            manufacturer: manufacturer.clone(),
+            name: display_name.to_string(),
+            language: msi::Language::from_tag(language).code().to_string(),
+            manufacturer: publisher(settings),
             version: version.to_string(),
             upgrade_code,
+            product_code,
+            scope: scope.to_string(),
             major_upgrade: MajorUpgrade {
+                // Versions differing only in their pre-release tag have the
+                // same four-part version, and must replace each other too.
+                allow_same_version_upgrades: "yes".to_string(),
                 downgrade_error_message: format!(
-                    "A newer version of {product_name} is already installed.",
+                    "A newer version of {display_name} is already installed.",
                 ),
             },
             media_template: MediaTemplate {
@@ -237,19 +993,10 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
             },
             feature: Feature {
                 id: "ProductFeature".to_string(),
-                title: product_name.to_string(),
+                title: display_name.to_string(),
                 level: "1".to_string(),
-                component_group_ref: ComponentGroupRef {
-                    id: "ProductComponents".to_string(),
-                },
-                component_ref: vec![
-                    ComponentRef {
-                        id: "RegistryComponent".to_string(),
-                    },
-                    ComponentRef {
-                        id: "DesktopFolderShortcut".to_string(),
-                    },
-                ],
+                component_group_refs,
+                component_ref: shortcut_component_refs,
             },
             wix_ui: WixUI {
                 id: "WixUI_InstallDir".to_string(),
@@ -261,13 +1008,23 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
                 },
                 Property {
                     id: "WIXUI_EXITDIALOGOPTIONALCHECKBOXTEXT".to_string(),
-                    value: format!("Launch {product_name}"),
+                    value: format!("Launch {display_name}"),
                 },
                 Property {
                     id: "WIXUI_EXITDIALOGOPTIONALCHECKBOX".to_string(),
                     value: "1".to_string(),
                 },
-            ],
+            ]
+            .into_iter()
+            .chain(
+                arp_properties(settings, main_icon_id)
+                    .into_iter()
+                    .map(|(id, value)| Property {
+                        id: id.to_string(),
+                        value,
+                    }),
+            )
+            .collect(),
             custom_action: CustomAction {
                 id: "LaunchApplication".to_string(),
                 directory: "INSTALLFOLDER".to_string(),
@@ -277,112 +1034,22 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
             },
             ui: UI {
                 id: "UI".to_string(),
-                publish: Publish {
-                    dialog: "ExitDialog".to_string(),
-                    control: "Finish".to_string(),
-                    event: "DoAction".to_string(),
-                    value: "LaunchApplication".to_string(),
-                    condition: "WIXUI_EXITDIALOGOPTIONALCHECKBOX = 1 and NOT Installed".to_string(),
-                },
+                publishes: ui_publishes(settings),
             },
-            wix_variable: WixVariable {
-                id: "WixUILicenseRtf".to_string(),
-                value: license_rtf_path.to_str().unwrap_or("").to_string(),
-            },
-            icon: Some(Icon {
-                id: main_icon_id.to_string(),
-                source_file: icon_path.to_str().unwrap_or("").to_string(),
-            }),
-        },
+            wix_variables,
+        }),
         fragments: vec![
             Fragment {
-                standard_directories: Some(vec![
-                    StandardDirectory {
-                        id: "ProgramFilesFolder".to_string(),
-                        directory: Some(Directory {
-                            id: "INSTALLFOLDER".to_string(),
-                            name: product_name.to_string(),
-                            directories: root_directories,
-                            components: installfolder_components,
-                        }),
-                        component: None,
-                    },
-                    StandardDirectory {
-                        id: "ProgramMenuFolder".to_string(),
-                        directory: Some(Directory {
-                            id: "ApplicationProgramsFolder".to_string(),
-                            name: product_name.to_string(),
-                            components: vec![Component {
-                                id: Some("RegistryComponent".to_string()),
-                                guid: Some(program_menu_folder_guid.to_string()),
-                                registry_value: Some(RegistryValue {
-                                    root: "HKCU".to_string(),
-                                    key: format!(
-                                        "Software\\{}\\{product_name}",
-                                        manufacturer.to_lowercase(),
-                                    ),
-                                    name: "installed".to_string(),
-                                    value_type: "integer".to_string(),
-                                    value: "1".to_string(),
-                                    key_path: "yes".to_string(),
-                                }),
-                                shortcut: Some(Shortcut {
-                                    id: "ApplicationStartMenuShortcut".to_string(),
-                                    name: product_name.to_string(),
-                                    description: Some(product_name.to_string()),
-                                    target: format!("[#{exe_id}]"),
-                                    icon: main_icon_id.to_string(),
-                                    working_directory: "INSTALLFOLDER".to_string(),
-                                }),
-                                remove_folder: Some(RemoveFolder {
-                                    id: "RemoveAppProgramsFolder".to_string(),
-                                    directory: "ApplicationProgramsFolder".to_string(),
-                                    on: "uninstall".to_string(),
-                                }),
-                                remove_file: Some(RemoveFile {
-                                    id: "RemoveAppPrograms".to_string(),
-                                    directory: "ApplicationProgramsFolder".to_string(),
-                                    name: "*.*".to_string(),
-                                    on: "uninstall".to_string(),
-                                }),
-                                file: None,
-                            }],
-                            directories: vec![],
-                        }),
-                        component: None,
-                    },
-                    StandardDirectory {
-                        id: "DesktopFolder".to_string(),
-                        directory: None,
-                        component: Some(Component {
-                            id: Some("DesktopFolderShortcut".to_string()),
-                            guid: Some(desktop_folder_shortcut_guid.to_string()),
-                            registry_value: Some(RegistryValue {
-                                root: "HKCU".to_string(),
-                                key: format!(
-                                    "Software\\{}\\{product_name}",
-                                    manufacturer.to_lowercase(),
-                                ),
-                                name: "installed".to_string(),
-                                value_type: "integer".to_string(),
-                                value: "1".to_string(),
-                                key_path: "yes".to_string(),
-                            }),
-                            shortcut: Some(Shortcut {
-                                id: "DesktopShortcut".to_string(),
-                                name: product_name.to_string(),
-                                description: None,
-                                target: format!("[#{exe_id}]"),
-                                icon: main_icon_id.to_string(),
-                                working_directory: "INSTALLFOLDER".to_string(),
-                            }),
-                            ..Component::default()
-                        }),
-                    },
-                ]),
+                // The shortcuts of this fragment use the icon.
+                icon: Some(Icon {
+                    id: main_icon_id.to_string(),
+                    source_file: icon_path.to_str().unwrap_or("").to_string(),
+                }),
+                standard_directories: Some(standard_directories),
                 component_group: None,
             },
             Fragment {
+                icon: None,
                 standard_directories: None,
                 component_group: Some(ComponentGroup {
                     id: "ProductComponents".to_string(),
@@ -404,7 +1071,7 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
     let xml_content = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{buffer}");
 
     std::fs::write(wxs_path, xml_content)?;
-    Ok(())
+    Ok(variables)
 }
 
 // WiX XML structure definitions
@@ -417,8 +1084,8 @@ struct WixDocument {
     xmlns_ui: String,
     #[serde(rename = "@xmlns:util")]
     xmlns_util: String,
-    #[serde(rename = "Package")]
-    package: Package,
+    #[serde(rename = "Package", skip_serializing_if = "Option::is_none")]
+    package: Option<Package>,
     #[serde(rename = "Fragment")]
     fragments: Vec<Fragment>,
 }
@@ -429,12 +1096,18 @@ struct Package {
     id: String,
     #[serde(rename = "@Name")]
     name: String,
+    #[serde(rename = "@Language")]
+    language: String,
     #[serde(rename = "@Manufacturer")]
     manufacturer: String,
     #[serde(rename = "@Version")]
     version: String,
     #[serde(rename = "@UpgradeCode")]
     upgrade_code: String,
+    #[serde(rename = "@ProductCode")]
+    product_code: String,
+    #[serde(rename = "@Scope")]
+    scope: String,
     #[serde(rename = "MajorUpgrade")]
     major_upgrade: MajorUpgrade,
     #[serde(rename = "MediaTemplate")]
@@ -450,13 +1123,13 @@ struct Package {
     #[serde(rename = "UI")]
     ui: UI,
     #[serde(rename = "WixVariable")]
-    wix_variable: WixVariable,
-    #[serde(rename = "Icon", skip_serializing_if = "Option::is_none")]
-    icon: Option<Icon>,
+    wix_variables: Vec<WixVariable>,
 }
 
 #[derive(Serialize)]
 struct MajorUpgrade {
+    #[serde(rename = "@AllowSameVersionUpgrades")]
+    allow_same_version_upgrades: String,
     #[serde(rename = "@DowngradeErrorMessage")]
     downgrade_error_message: String,
 }
@@ -476,7 +1149,7 @@ struct Feature {
     #[serde(rename = "@Level")]
     level: String,
     #[serde(rename = "ComponentGroupRef")]
-    component_group_ref: ComponentGroupRef,
+    component_group_refs: Vec<ComponentGroupRef>,
     #[serde(rename = "ComponentRef", skip_serializing_if = "Vec::is_empty")]
     component_ref: Vec<ComponentRef>,
 }
@@ -526,7 +1199,7 @@ struct UI {
     #[serde(rename = "@Id")]
     id: String,
     #[serde(rename = "Publish")]
-    publish: Publish,
+    publishes: Vec<Publish>,
 }
 
 #[derive(Serialize)]
@@ -541,6 +1214,8 @@ struct Publish {
     value: String,
     #[serde(rename = "@Condition")]
     condition: String,
+    #[serde(rename = "@Order", skip_serializing_if = "Option::is_none")]
+    order: Option<u32>,
 }
 
 #[derive(Serialize)]
@@ -561,6 +1236,8 @@ struct Icon {
 
 #[derive(Serialize)]
 struct Fragment {
+    #[serde(rename = "Icon", skip_serializing_if = "Option::is_none")]
+    icon: Option<Icon>,
     #[serde(rename = "StandardDirectory", skip_serializing_if = "Option::is_none")]
     standard_directories: Option<Vec<StandardDirectory>>,
     #[serde(rename = "ComponentGroup", skip_serializing_if = "Option::is_none")]
@@ -595,8 +1272,8 @@ struct Component {
     id: Option<String>,
     #[serde(rename = "@Guid", skip_serializing_if = "Option::is_none")]
     guid: Option<String>,
-    #[serde(rename = "RegistryValue", skip_serializing_if = "Option::is_none")]
-    registry_value: Option<RegistryValue>,
+    #[serde(rename = "RegistryValue", skip_serializing_if = "Vec::is_empty")]
+    registry_values: Vec<RegistryValue>,
     #[serde(rename = "Shortcut", skip_serializing_if = "Option::is_none")]
     shortcut: Option<Shortcut>,
     #[serde(rename = "RemoveFolder", skip_serializing_if = "Option::is_none")]
@@ -605,6 +1282,87 @@ struct Component {
     remove_file: Option<RemoveFile>,
     #[serde(rename = "File", skip_serializing_if = "Option::is_none")]
     file: Option<File>,
+    #[serde(rename = "ServiceInstall", skip_serializing_if = "Vec::is_empty")]
+    service_installs: Vec<ServiceInstall>,
+    #[serde(rename = "ServiceControl", skip_serializing_if = "Vec::is_empty")]
+    service_controls: Vec<ServiceControl>,
+}
+
+#[derive(Clone, Serialize)]
+struct ServiceInstall {
+    #[serde(rename = "@Name")]
+    name: String,
+    #[serde(rename = "@DisplayName", skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    #[serde(rename = "@Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "@Type")]
+    service_type: String,
+    #[serde(rename = "@Start")]
+    start: String,
+    #[serde(rename = "@ErrorControl")]
+    error_control: String,
+    #[serde(rename = "@Account", skip_serializing_if = "Option::is_none")]
+    account: Option<String>,
+    #[serde(rename = "@Arguments", skip_serializing_if = "Option::is_none")]
+    arguments: Option<String>,
+    #[serde(rename = "@Vital")]
+    vital: String,
+}
+
+impl ServiceInstall {
+    fn new(service: &WindowsService) -> Self {
+        let start = match service.start_type {
+            ServiceStartType::Auto => "auto",
+            ServiceStartType::Demand => "demand",
+            ServiceStartType::Disabled => "disabled",
+        };
+        // The attributes are formatted strings.
+        let escape = |value: &String| windows_registry::escape_formatted(value);
+        ServiceInstall {
+            name: escape(&service.name),
+            display_name: service.display_name.as_ref().map(escape),
+            description: service.description.as_ref().map(escape),
+            service_type: "ownProcess".to_string(),
+            start: start.to_string(),
+            error_control: "normal".to_string(),
+            account: service.account.as_ref().map(escape),
+            arguments: service.arguments.as_ref().map(escape),
+            vital: "yes".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ServiceControl {
+    #[serde(rename = "@Id")]
+    id: String,
+    #[serde(rename = "@Name")]
+    name: String,
+    #[serde(rename = "@Start", skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    #[serde(rename = "@Stop")]
+    stop: String,
+    #[serde(rename = "@Remove")]
+    remove: String,
+    #[serde(rename = "@Wait")]
+    wait: String,
+}
+
+impl ServiceControl {
+    /// Starts the service once it is installed (unless it is disabled), and
+    /// stops it before it is upgraded or removed.
+    fn new(service: &WindowsService) -> Self {
+        ServiceControl {
+            id: sanitize_identifier(&format!("Service_{}", service.name), '_', false),
+            name: windows_registry::escape_formatted(&service.name),
+            start: (service.start_type != ServiceStartType::Disabled)
+                .then(|| "install".to_string()),
+            stop: "both".to_string(),
+            remove: "uninstall".to_string(),
+            wait: "yes".to_string(),
+        }
+    }
 }
 
 #[derive(Clone, Serialize)]
@@ -613,14 +1371,14 @@ struct RegistryValue {
     root: String,
     #[serde(rename = "@Key")]
     key: String,
-    #[serde(rename = "@Name")]
-    name: String,
+    #[serde(rename = "@Name", skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
     #[serde(rename = "@Type")]
     value_type: String,
     #[serde(rename = "@Value")]
     value: String,
-    #[serde(rename = "@KeyPath")]
-    key_path: String,
+    #[serde(rename = "@KeyPath", skip_serializing_if = "Option::is_none")]
+    key_path: Option<String>,
 }
 
 #[derive(Clone, Serialize)]
@@ -631,6 +1389,8 @@ struct Shortcut {
     name: String,
     #[serde(rename = "@Description", skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(rename = "@Arguments", skip_serializing_if = "Option::is_none")]
+    arguments: Option<String>,
     #[serde(rename = "@Target")]
     target: String,
     #[serde(rename = "@Icon")]
@@ -679,10 +1439,110 @@ struct File {
     id: Option<String>,
     #[serde(rename = "@Source")]
     source: String,
+    #[serde(rename = "@Name", skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
     #[serde(rename = "@KeyPath", skip_serializing_if = "Option::is_none")]
     key_path: Option<String>,
 }
 
+// WiX bundle XML structure definitions
+#[derive(Serialize)]
+#[serde(rename = "Wix")]
+struct SetupDocument {
+    #[serde(rename = "@xmlns")]
+    xmlns: String,
+    #[serde(rename = "@xmlns:bal")]
+    xmlns_bal: String,
+    #[serde(rename = "@xmlns:util")]
+    xmlns_util: String,
+    #[serde(rename = "Bundle")]
+    bundle: Bundle,
+}
+
+#[derive(Serialize)]
+struct Bundle {
+    #[serde(rename = "@Name")]
+    name: String,
+    #[serde(rename = "@Version")]
+    version: String,
+    #[serde(rename = "@Manufacturer")]
+    manufacturer: String,
+    #[serde(rename = "@UpgradeCode")]
+    upgrade_code: String,
+    #[serde(rename = "BootstrapperApplication")]
+    bootstrapper_application: BootstrapperApplication,
+    #[serde(rename = "util:RegistrySearch")]
+    registry_searches: Vec<RegistrySearch>,
+    #[serde(rename = "Chain")]
+    chain: Chain,
+}
+
+#[derive(Serialize)]
+struct BootstrapperApplication {
+    #[serde(rename = "bal:WixStandardBootstrapperApplication")]
+    standard: StandardBootstrapperApplication,
+}
+
+#[derive(Serialize)]
+struct StandardBootstrapperApplication {
+    #[serde(rename = "@Theme")]
+    theme: String,
+    #[serde(rename = "@LicenseFile", skip_serializing_if = "Option::is_none")]
+    license_file: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RegistrySearch {
+    #[serde(rename = "@Id")]
+    id: String,
+    #[serde(rename = "@Root")]
+    root: String,
+    #[serde(rename = "@Key")]
+    key: String,
+    #[serde(rename = "@Value")]
+    value: String,
+    #[serde(rename = "@Variable")]
+    variable: String,
+    #[serde(rename = "@Result")]
+    result: String,
+    #[serde(rename = "@Bitness", skip_serializing_if = "Option::is_none")]
+    bitness: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Chain {
+    #[serde(rename = "ExePackage")]
+    exe_packages: Vec<ExePackage>,
+    #[serde(rename = "MsiPackage")]
+    msi_package: MsiPackage,
+}
+
+#[derive(Serialize)]
+struct ExePackage {
+    #[serde(rename = "@Id")]
+    id: String,
+    #[serde(rename = "@DisplayName")]
+    display_name: String,
+    #[serde(rename = "@SourceFile")]
+    source_file: String,
+    #[serde(rename = "@InstallArguments", skip_serializing_if = "Option::is_none")]
+    install_arguments: Option<String>,
+    #[serde(rename = "@DetectCondition", skip_serializing_if = "Option::is_none")]
+    detect_condition: Option<String>,
+    #[serde(rename = "@Permanent")]
+    permanent: String,
+    #[serde(rename = "@Vital")]
+    vital: String,
+}
+
+#[derive(Serialize)]
+struct MsiPackage {
+    #[serde(rename = "@Id")]
+    id: String,
+    #[serde(rename = "@SourceFile")]
+    source_file: String,
+}
+
 // WiX Project XML structure definitions
 #[derive(Serialize)]
 #[serde(rename = "Project")]
@@ -699,12 +1559,26 @@ struct WixProject {
 struct PropertyGroup {
     #[serde(rename = "OutputName")]
     output_name: String,
+    #[serde(rename = "OutputType", skip_serializing_if = "Option::is_none")]
+    output_type: Option<String>,
+    #[serde(rename = "Cultures")]
+    cultures: String,
+    #[serde(rename = "InstallerPlatform", skip_serializing_if = "Option::is_none")]
+    installer_platform: Option<String>,
+    #[serde(rename = "EnableDefaultCompileItems")]
+    enable_default_compile_items: bool,
+    #[serde(rename = "DefineConstants")]
+    define_constants: String,
+    #[serde(rename = "SuppressIces", skip_serializing_if = "Option::is_none")]
+    suppress_ices: Option<String>,
 }
 
 #[derive(Serialize)]
 struct ItemGroup {
     #[serde(rename = "PackageReference")]
-    package_reference: PackageReference,
+    package_references: Vec<PackageReference>,
+    #[serde(rename = "Compile")]
+    compile: Vec<Compile>,
 }
 
 #[derive(Serialize)]
@@ -715,6 +1589,27 @@ struct PackageReference {
     version: String,
 }
 
+#[derive(Serialize)]
+struct Compile {
+    #[serde(rename = "@Include")]
+    include: String,
+}
+
+/// Escapes the characters that MSBuild gives a meaning to in the values of
+/// properties and items, such as the `;` separating the items of a list.
+fn msbuild_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '%' | '$' | '@' | '\'' | ';' | '?' | '*' => {
+                escaped.push_str(&format!("%{:02X}", c as u32))
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 fn rtf_safe_content(origin_content: &str) -> String {
     let rtf_safe_content = origin_content
         .replace('\\', "\\\\")
@@ -734,7 +1629,7 @@ fn rtf_safe_content(origin_content: &str) -> String {
 // Converts a version string to WiX-compatible format (a.b.c.d where a, b, c, d are integers).
 // WiX requires: major.minor.build.revision format with all numeric parts.
 // Pre-release tags like "-beta1" or "+build" are stripped.
-fn sanitize_version_for_wix(version: &str) -> String {
+pub(crate) fn sanitize_version_for_wix(version: &str) -> String {
     // Strip pre-release and build metadata (anything after - or +)
     let version = version.split(['-', '+']).next().unwrap_or(version);
 
@@ -785,6 +1680,52 @@ fn generate_component_id_from_path(path_str: &str) -> String {
         .join("_")
 }
 
+// Returns the path of the bundle's license agreement, or else of one generated
+// from the package's license.
+fn license_rtf(settings: &Settings) -> crate::Result<PathBuf> {
+    if let Some(path) = settings.windows_license() {
+        return Ok(path);
+    }
+    let path = settings.project_out_directory().join("License.rtf");
+    generate_license_rtf(&path, settings)?;
+    Ok(path)
+}
+
+// The `Publish` elements of the UI, which launch the app from the exit dialog,
+// and go around the license agreement dialog of `WixUI_InstallDir` if it is
+// turned off.
+fn ui_publishes(settings: &Settings) -> Vec<Publish> {
+    let mut publishes = vec![Publish {
+        dialog: "ExitDialog".to_string(),
+        control: "Finish".to_string(),
+        event: "DoAction".to_string(),
+        value: "LaunchApplication".to_string(),
+        condition: "WIXUI_EXITDIALOGOPTIONALCHECKBOX = 1 and NOT Installed".to_string(),
+        order: None,
+    }];
+    if !settings.windows_license_dialog() {
+        // These outrank the `Publish` elements of the dialog set, whose order
+        // is 1.
+        publishes.push(Publish {
+            dialog: "WelcomeDlg".to_string(),
+            control: "Next".to_string(),
+            event: "NewDialog".to_string(),
+            value: "InstallDirDlg".to_string(),
+            condition: "NOT Installed".to_string(),
+            order: Some(2),
+        });
+        publishes.push(Publish {
+            dialog: "InstallDirDlg".to_string(),
+            control: "Back".to_string(),
+            event: "NewDialog".to_string(),
+            value: "WelcomeDlg".to_string(),
+            condition: "1".to_string(),
+            order: Some(2),
+        });
+    }
+    publishes
+}
+
 fn generate_license_rtf(rtf_path: &Path, settings: &Settings) -> crate::Result<()> {
     let license_content = settings
         .license_content()
@@ -946,3 +1887,169 @@ fn add_component_to_directory(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_outputs() {
+        let settings = Settings::for_test("name = \"My App\"\nmsi_language = \"de-DE\"");
+        assert_eq!(settings.msi_languages(), ["de-DE"]);
+        assert_eq!(output_name(&settings), "my-app");
+        assert!(generate_wixproj_file(&settings, &[], &[]).contains("<Cultures>de-DE</Cultures>"));
+
+        let settings = Settings::for_test(
+            "name = \"My App\"\n\
+             msi_languages = [\"en-US\", \"fr-FR\"]\n\
+             [localizations.fr]\n\
+             name = \"Mon App\"\n",
+        );
+        let french = settings.for_msi_language("fr-FR");
+        assert_eq!(french.msi_language(), "fr-FR");
+        assert_eq!(
+            french.localized_bundle_name(french.msi_language()),
+            "Mon App"
+        );
+        assert_eq!(output_name(&french), "my-app_fr-FR");
+        assert_eq!(
+            language_suffix(&settings.for_msi_language("en-US")),
+            "_en-US"
+        );
+    }
+
+    #[test]
+    fn license_dialog_publishes() {
+        let settings = Settings::for_test("[windows]\nlicense = \"EULA.rtf\"");
+        assert_eq!(
+            settings.windows_license(),
+            Some(PathBuf::from("/nonexistent/example/EULA.rtf"))
+        );
+        assert_eq!(ui_publishes(&settings).len(), 1);
+
+        let settings = Settings::for_test("[windows]\nlicense_dialog = false");
+        let publishes = ui_publishes(&settings);
+        let targets: Vec<_> = publishes
+            .iter()
+            .map(|publish| {
+                (
+                    publish.dialog.as_str(),
+                    publish.value.as_str(),
+                    publish.order,
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                ("ExitDialog", "LaunchApplication", None),
+                ("WelcomeDlg", "InstallDirDlg", Some(2)),
+                ("InstallDirDlg", "WelcomeDlg", Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn installer_platforms() {
+        assert_eq!(installer_platform("x86_64"), "x64");
+        assert_eq!(installer_platform("aarch64"), "arm64");
+        assert_eq!(installer_platform("x86"), "x86");
+    }
+
+    #[test]
+    fn wixproj_file() {
+        let settings = Settings::for_test("name = \"My App\"");
+        let wixproj = generate_wixproj_file(
+            &settings,
+            &[
+                PathBuf::from("installer.wxs"),
+                PathBuf::from("C:\\wix\\extra;1.wxs"),
+            ],
+            &[
+                ("ProductName", "My App".to_string()),
+                ("Manufacturer", "Jane; 100%".to_string()),
+            ],
+        );
+        assert!(wixproj.contains(
+            "<EnableDefaultCompileItems>false</EnableDefaultCompileItems>\n    \
+             <DefineConstants>ProductName=My App;Manufacturer=Jane%3B 100%25</DefineConstants>"
+        ));
+        assert!(wixproj.contains(
+            "<Compile Include=\"installer.wxs\"/>\n    \
+             <Compile Include=\"C:\\wix\\extra%3B1.wxs\"/>"
+        ));
+        assert!(!wixproj.contains("SuppressIces"));
+
+        let settings = Settings::for_test("windows = { install_scope = \"perUser\" }");
+        let wixproj = generate_wixproj_file(&settings, &[], &[]);
+        assert!(wixproj.contains("<SuppressIces>ICE38;ICE64;ICE91</SuppressIces>"));
+    }
+
+    #[test]
+    fn setup_bundle() {
+        let settings = Settings::for_test(
+            r#"
+            name = "My App"
+            msi_upgrade_code = "{6F8F2C2B-3B0B-4D5C-9C55-3E2E1C5E0F31}"
+
+            [[windows.prerequisites]]
+            kind = "webview2"
+            source = "redist/MicrosoftEdgeWebview2Setup.exe"
+
+            [[windows.prerequisites]]
+            source = "redist/runtime.exe"
+            arguments = "/quiet"
+            "#,
+        );
+        let wxs = generate_setup_wxs(
+            &settings,
+            upgrade_code(&settings).unwrap(),
+            Path::new("my-app.msi"),
+            None,
+        )
+        .unwrap();
+        assert!(
+            wxs.contains("<bal:WixStandardBootstrapperApplication Theme=\"hyperlinkLicense\"/>")
+        );
+        assert!(wxs.contains(
+            "Variable=\"WebView2MachineVersion\" Result=\"value\" Bitness=\"always32\"/>"
+        ));
+        assert!(wxs.contains(
+            "<ExePackage Id=\"Prerequisite0\" DisplayName=\"Microsoft Edge WebView2 Runtime\" \
+             SourceFile=\"/nonexistent/example/redist/MicrosoftEdgeWebview2Setup.exe\" \
+             InstallArguments=\"/silent /install\" DetectCondition=\"(WebView2MachineVersion"
+        ));
+        assert!(wxs.contains(
+            "<ExePackage Id=\"Prerequisite1\" DisplayName=\"runtime.exe\" \
+             SourceFile=\"/nonexistent/example/redist/runtime.exe\" InstallArguments=\"/quiet\" \
+             Permanent=\"yes\" Vital=\"yes\"/>\n      \
+             <MsiPackage Id=\"MainPackage\" SourceFile=\"my-app.msi\"/>"
+        ));
+        // The upgrade code of the setup.exe differs from that of the MSI.
+        assert!(!wxs.contains("6F8F2C2B-3B0B-4D5C-9C55-3E2E1C5E0F31"));
+        // WiX writes the setup.exe next to the MSI, under `bin/<configuration>`.
+        assert_eq!(
+            output_path(
+                &settings,
+                Path::new("wxsmsi/setup"),
+                &format!("{}.exe", setup_output_name(&settings))
+            ),
+            Path::new("wxsmsi/setup/bin/Debug/my-app-setup.exe")
+        );
+
+        let wxs = generate_setup_wxs(
+            &settings,
+            upgrade_code(&settings).unwrap(),
+            Path::new("my-app.msi"),
+            Some(Path::new("License.rtf")),
+        )
+        .unwrap();
+        assert!(wxs.contains("Theme=\"rtfLicense\" LicenseFile=\"License.rtf\"/>"));
+        let wixproj = generate_setup_wixproj_file(&settings, Path::new("setup.wxs"));
+        assert!(wixproj.contains(
+            "<OutputName>my-app-setup</OutputName>\n    <OutputType>Bundle</OutputType>"
+        ));
+        assert!(!wixproj.contains("InstallerPlatform"));
+        assert!(wixproj.contains("<PackageReference Include=\"WixToolset.Bal.wixext\""));
+    }
+}
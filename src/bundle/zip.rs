@@ -0,0 +1,99 @@
+// Writes zip archives, as used by formats (such as NuGet packages) that are
+// zip files underneath.  This wraps the `zip` crate so that every bundle
+// format gets the same entries: deflated, with Unix permissions, and with a
+// single modification time so that builds are reproducible.
+
+use chrono::{DateTime, Utc};
+use std::io::{Seek, Write};
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+/// Writes a zip archive to the wrapped writer.
+pub struct ZipWriter<W: Write + Seek> {
+    writer: zip::ZipWriter<W>,
+    options: SimpleFileOptions,
+}
+
+impl<W: Write + Seek> ZipWriter<W> {
+    /// Creates a writer whose entries all have the given modification time.
+    pub fn new(writer: W, mtime: DateTime<Utc>) -> ZipWriter<W> {
+        // DOS dates start in 1980, so earlier times become 1980-01-01.
+        let mtime = zip::DateTime::try_from(mtime.naive_utc()).unwrap_or_default();
+        ZipWriter {
+            writer: zip::ZipWriter::new(writer),
+            options: SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .last_modified_time(mtime),
+        }
+    }
+
+    /// Adds a file with the given contents.  `name` uses `/` as its separator.
+    pub fn add_file(&mut self, name: &str, data: &[u8], executable: bool) -> crate::Result<()> {
+        let mode = if executable { 0o755 } else { 0o644 };
+        self.writer
+            .start_file(name, self.options.unix_permissions(mode))?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// Adds an empty directory.  `name` uses `/` as its separator.
+    pub fn add_directory(&mut self, name: &str) -> crate::Result<()> {
+        self.writer
+            .add_directory(name, self.options.unix_permissions(0o755))?;
+        Ok(())
+    }
+
+    /// Writes the central directory, and returns the wrapped writer.
+    pub fn finish(self) -> crate::Result<W> {
+        Ok(self.writer.finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZipWriter;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn zip_archive() {
+        let mtime = chrono::DateTime::from_timestamp(1700000000, 0).unwrap();
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()), mtime);
+        zip.add_directory("dir").unwrap();
+        zip.add_file("dir/hello.txt", b"hello hello hello", false)
+            .unwrap();
+        zip.add_file("dir/run", b"#!/bin/sh\n", true).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 3);
+        let dir = archive.by_name("dir/").unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(dir.unix_mode().unwrap() & 0o777, 0o755);
+        drop(dir);
+        let mut file = archive.by_name("dir/hello.txt").unwrap();
+        assert_eq!(file.compression(), zip::CompressionMethod::Deflated);
+        assert_eq!(file.unix_mode().unwrap() & 0o777, 0o644);
+        let modified = file.last_modified().unwrap();
+        assert_eq!(
+            (modified.year(), modified.month(), modified.day()),
+            (2023, 11, 14)
+        );
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello hello hello");
+        drop(file);
+        let file = archive.by_name("dir/run").unwrap();
+        assert_eq!(file.unix_mode().unwrap() & 0o777, 0o755);
+    }
+
+    #[test]
+    fn zip_archive_before_1980() {
+        let mtime = chrono::DateTime::from_timestamp(0, 0).unwrap();
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()), mtime);
+        zip.add_file("a", b"a", false).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let modified = archive.by_index(0).unwrap().last_modified().unwrap();
+        assert_eq!(modified.year(), 1980);
+    }
+}
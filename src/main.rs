@@ -1,10 +1,15 @@
 mod bundle;
 
-use crate::bundle::{BuildArtifact, PackageType, Settings, bundle_project};
+use crate::bundle::{
+    Artifact, BuildArtifact, ChecksumAlgorithm, DenyWarnings, MessageFormat, PackageType, Settings,
+    UNIVERSAL_MACOS_TARGETS, bundle_project, check_identifier_collisions, create_universal_binary,
+    list_formats, print_artifacts, run_in_simulator, write_checksums,
+};
 use anyhow::Result;
 use clap::builder::{PossibleValuesParser, TypedValueParser};
 use std::env;
 use std::ffi::OsString;
+use std::path::PathBuf;
 use std::process;
 
 #[macro_export]
@@ -33,13 +38,16 @@ fn about_info() -> String {
 #[derive(clap::Parser, Clone)]
 #[command(version = version_0!(), author = clap::crate_authors!(", "), bin_name = "cargo bundle", about = about_info())]
 pub struct Cli {
-    /// Bundle the specified binary
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// Bundle the specified binary; may be given several times, for one bundle per binary
     #[arg(short, long, value_name = "NAME")]
-    pub bin: Option<String>,
+    pub bin: Vec<String>,
 
-    /// Bundle the specified example
-    #[arg(short, long, value_name = "NAME", conflicts_with = "bin")]
-    pub example: Option<String>,
+    /// Bundle the specified example; may be given several times, for one bundle per example
+    #[arg(short, long, value_name = "NAME")]
+    pub example: Vec<String>,
 
     /// Which bundle format to produce
     #[arg(short, long, value_name = "FORMAT", value_parser = PossibleValuesParser::new(PackageType::all()).map(|s| PackageType::try_from(s).unwrap()))]
@@ -53,7 +61,7 @@ pub struct Cli {
     #[arg(long, value_name = "NAME", conflicts_with = "release")]
     pub profile: Option<String>,
 
-    /// Build a bundle for the target triple
+    /// Build a bundle for the target triple, or for `universal-apple-darwin` to bundle a universal macOS binary
     #[arg(short, long, value_name = "TRIPLE")]
     pub target: Option<String>,
 
@@ -72,18 +80,100 @@ pub struct Cli {
     /// The name of the package to bundle. If not specified, the root package will be used.
     #[arg(short, long, value_name = "SPEC")]
     pub package: Option<String>,
+
+    /// Bundle every workspace member that has a `[package.metadata.bundle]` section
+    #[arg(long, conflicts_with_all = ["package", "bin", "example"])]
+    pub workspace: bool,
+
+    /// Read the bundle settings from the given file instead of `Bundle.toml` or `[package.metadata.bundle]`
+    #[arg(long, value_name = "PATH", conflicts_with = "workspace")]
+    pub config: Option<PathBuf>,
+
+    /// Override a bundle setting, e.g. `--set identifier=com.example.beta` or `--set deb_depends[]=libfoo`
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+
+    /// Treat warnings as errors, failing at the end of the run (the default) or at the first warning
+    #[arg(long, value_name = "WHEN", num_args = 0..=1, default_missing_value = "end")]
+    pub deny_warnings: Option<DenyWarnings>,
+
+    /// Sign macOS app bundles with the given identity, overriding the `osx_signing_identity` setting
+    #[arg(long, value_name = "IDENTITY")]
+    pub sign: Option<String>,
+
+    /// Notarize and staple macOS app bundles and disk images, using credentials from the environment
+    #[arg(long)]
+    pub notarize: bool,
+
+    /// Sign Linux packages with the given GPG key, overriding the `gpg_key` setting
+    #[arg(long, value_name = "KEY")]
+    pub gpg_key: Option<String>,
+
+    /// Write checksum files (e.g. `SHA256SUMS`) next to the bundles, with the given algorithms
+    #[arg(long, value_name = "ALGORITHMS", value_delimiter = ',')]
+    pub checksums: Vec<ChecksumAlgorithm>,
+
+    /// Write a checksum file for each bundle (e.g. `foo.deb.sha256`) instead of one per directory
+    #[arg(long, requires = "checksums")]
+    pub checksums_per_artifact: bool,
+
+    /// Only build reproducible bundles, timestamped with SOURCE_DATE_EPOCH or the time of the last commit
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// Report the bundles produced as a list on stderr, or also as JSON objects on stdout, one per line
+    #[arg(long, value_name = "FMT", value_enum, default_value_t)]
+    pub message_format: MessageFormat,
+}
+
+#[derive(clap::Subcommand, Clone)]
+pub enum CliCommand {
+    /// List the bundle formats, and whether the tools each requires are installed
+    List,
+    /// Bundle the app for the iOS simulator, then install and launch it there
+    Run {
+        /// The name or UDID of the simulator device to run the app on, e.g. "iPhone 16"
+        #[arg(long, value_name = "DEVICE")]
+        ios_simulator: String,
+    },
 }
 
-/// Runs `cargo build` to make sure the binary file is up-to-date.
+/// Returns the target triple of the iOS simulator on the host.
+fn ios_simulator_target() -> &'static str {
+    if cfg!(target_arch = "aarch64") {
+        "aarch64-apple-ios-sim"
+    } else {
+        "x86_64-apple-ios"
+    }
+}
+
+/// Runs `cargo build` to make sure the binary file is up-to-date.  A universal
+/// macOS binary is built for each of its targets, and then merged with `lipo`.
 fn build_project_if_unbuilt(settings: &Settings) -> crate::Result<()> {
     if std::env::var("CARGO_BUNDLE_SKIP_BUILD").is_ok() {
         return Ok(());
     }
 
+    if settings.is_universal_macos() {
+        for triple in UNIVERSAL_MACOS_TARGETS {
+            cargo_build(settings, Some(triple))?;
+        }
+        for helper in settings.osx_helpers() {
+            create_universal_binary(&settings.osx_helper_settings(helper)?)?;
+        }
+        create_universal_binary(settings)
+    } else {
+        cargo_build(settings, settings.target_triple())
+    }
+}
+
+/// Runs `cargo build` for the target `triple`, or for the host if it is `None`.
+fn cargo_build(settings: &Settings, triple: Option<&str>) -> crate::Result<()> {
     let mut cargo =
         process::Command::new(env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")));
     cargo.arg("build");
-    if let Some(triple) = settings.target_triple() {
+    cargo.arg(format!("--package={}", settings.package_name()));
+    if let Some(triple) = triple {
         cargo.arg(format!("--target={triple}"));
     }
     if let Some(features) = settings.features() {
@@ -98,6 +188,12 @@ fn build_project_if_unbuilt(settings: &Settings) -> crate::Result<()> {
             cargo.arg(format!("--example={name}"));
         }
     }
+    // Building the main binary builds every `bin` target already.
+    if !matches!(settings.build_artifact(), BuildArtifact::Main) {
+        for helper in settings.osx_helpers() {
+            cargo.arg(format!("--bin={}", helper.bin));
+        }
+    }
     match settings.build_profile() {
         "dev" => {}
         "release" => {
@@ -129,18 +225,73 @@ fn run() -> crate::Result<()> {
     if args.len() > 1 && args[1] == "bundle" {
         args.remove(1);
     }
-    let cli = <Cli as clap::Parser>::parse_from(args); // <Cli as clap::Parser>::parse();
-
-    {
-        let output_paths = env::current_dir()
-            .map_err(From::from)
-            .and_then(|d| Settings::new(d, &cli))
-            .and_then(|s| {
-                build_project_if_unbuilt(&s)?;
-                Ok(s)
-            })
-            .and_then(bundle_project)?;
-        bundle::print_finished(&output_paths)?;
+    let mut cli = <Cli as clap::Parser>::parse_from(args); // <Cli as clap::Parser>::parse();
+    if let Some(CliCommand::List) = cli.command {
+        return list_formats();
+    }
+    if let Some(CliCommand::Run { .. }) = cli.command {
+        if cli.format.get_or_insert(PackageType::IosBundle) != &PackageType::IosBundle {
+            anyhow::bail!("`cargo bundle run` only runs iOS bundles");
+        }
+        cli.target
+            .get_or_insert_with(|| ios_simulator_target().to_string());
+    }
+    if let Some(when) = cli.deny_warnings {
+        bundle::deny_warnings(when);
+    }
+
+    let current_dir = env::current_dir()?;
+    if cli.reproducible {
+        bundle::pin_source_date_epoch(&current_dir)?;
+    }
+
+    let all_settings = Settings::all(&current_dir, &cli)?;
+    if all_settings.iter().any(Settings::deny_warnings) {
+        bundle::deny_warnings(DenyWarnings::End);
+    }
+    check_identifier_collisions(&all_settings)?;
+
+    if let Some(CliCommand::Run { ios_simulator }) = &cli.command {
+        let [settings] = &all_settings[..] else {
+            anyhow::bail!("`cargo bundle run` runs one app; use --bin or --example to pick it");
+        };
+        if !settings.is_ios_simulator() {
+            anyhow::bail!(
+                "Cannot run a bundle for {} in the simulator",
+                settings.target_triple().unwrap_or("the host")
+            );
+        }
+        let bundle_identifier = settings.bundle_identifier().to_string();
+        build_project_if_unbuilt(settings)?;
+        let artifacts = bundle_project(settings.clone())?;
+        report_artifacts(&artifacts, &cli)?;
+        let app_bundle = artifacts
+            .iter()
+            .find(|artifact| artifact.path.extension().is_some_and(|ext| ext == "app"))
+            .ok_or_else(|| anyhow::anyhow!("No app bundle to run"))?;
+        return run_in_simulator(&app_bundle.path, &bundle_identifier, ios_simulator);
+    }
+
+    let mut artifacts = Vec::new();
+    for settings in all_settings {
+        build_project_if_unbuilt(&settings)?;
+        artifacts.append(&mut bundle_project(settings)?);
+    }
+    let output_paths: Vec<PathBuf> = artifacts.iter().map(|a| a.path.clone()).collect();
+    let checksum_paths =
+        write_checksums(&output_paths, &cli.checksums, cli.checksums_per_artifact)?;
+    artifacts.extend(checksum_paths.into_iter().map(Artifact::from));
+    report_artifacts(&artifacts, &cli)
+}
+
+/// Lists the bundles produced on stderr, and for `--message-format json`,
+/// describes them on stdout too.
+fn report_artifacts(artifacts: &[Artifact], cli: &Cli) -> crate::Result<()> {
+    bundle::check_warnings()?;
+    let output_paths = artifacts.iter().map(|a| a.path.clone()).collect();
+    bundle::print_finished(&output_paths)?;
+    if cli.message_format == MessageFormat::Json {
+        print_artifacts(artifacts, &cli.checksums)?;
     }
     Ok(())
 }